@@ -12,6 +12,7 @@ use std::{
     env,
     path::{Path, PathBuf},
     sync::{Arc, atomic::Ordering},
+    time::Instant,
 };
 
 use actix_files;
@@ -19,7 +20,7 @@ use actix_multipart::Multipart;
 use actix_web::{
     HttpRequest, HttpResponse, Responder, delete, get,
     http::header::{ContentDisposition, DispositionType},
-    patch, post, put, web,
+    patch, post, put, routes, web,
 };
 use actix_web_grants::{authorities::AuthDetails, proc_macro::protect};
 
@@ -45,11 +46,12 @@ use crate::{
         handles,
         models::{Channel, Role, TextPreset, User, UserMeta},
     },
-    file::{MoveObject, PathObject, norm_abs_path},
+    file::{MoveObject, PathObject, norm_abs_path, transcoder::Transcoder},
     player::{
-        controller::ChannelController,
+        controller::{ChannelController, ChannelManager, stall_timeout},
         utils::{
-            JsonPlaylist, get_data_map, get_date_range, import::import_file, sec_to_time,
+            JsonPlaylist, get_data_map, get_date_range, import::import_file,
+            include_file_extension, output_stats, program::programs, resume, sec_to_time,
             time_to_sec,
         },
     },
@@ -57,14 +59,24 @@ use crate::{
     utils::{
         TextFilter,
         advanced_config::AdvancedConfig,
-        channels::{create_channel, delete_channel},
-        config::{PlayoutConfig, Template, get_config},
-        control::{ControlParams, Process, ProcessCtl, control_state, send_message},
+        alert::{self, Alert},
+        as_run,
+        channels::{check_channel_paths, create_channel, delete_channel},
+        config::{OutputMode, PlayoutConfig, Template, format_errors, get_config},
+        control::{
+            ControlParams, FilterParams, GraphicParams, InsertParams, ItemEdit, Process,
+            ProcessCtl, ResumeParams, control_state, edit_item, insert_next, program_list,
+            resume_from, send_message, show_graphic, update_filter,
+        },
         errors::ServiceError,
+        library::{self, LibraryQuery},
+        logging::Target,
         mail::MailQueue,
         naive_date_time_from_str,
         playlist::{delete_playlist, generate_playlist, read_playlist, write_playlist},
         public_path, read_log_file,
+        staging::Staging,
+        statistics::{self, StatsQuery},
     },
     vec_strings,
 };
@@ -106,12 +118,22 @@ pub struct ImportObj {
     date: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ChannelObj {
+    channel: Channel,
+    config: PlayoutConfig,
+    #[serde(default)]
+    start: bool,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct ProgramObj {
     #[serde(default = "time_after", deserialize_with = "naive_date_time_from_str")]
     start_after: NaiveDateTime,
     #[serde(default = "time_before", deserialize_with = "naive_date_time_from_str")]
     start_before: NaiveDateTime,
+    #[serde(default)]
+    group: bool,
 }
 
 fn time_after() -> NaiveDateTime {
@@ -141,6 +163,18 @@ struct ProgramItem {
     out: f64,
     duration: f64,
     category: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    program_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ProgramGroup {
+    program_id: Option<String>,
+    start: String,
+    title: Option<String>,
+    items: usize,
+    duration: f64,
+    breaks: f64,
 }
 
 /// #### User Handling
@@ -476,6 +510,7 @@ async fn patch_channel(
         data.public = "/var/www/html/live".to_string();
     }
 
+    check_channel_paths(&data)?;
     handles::update_channel(&pool, *id, data.clone()).await?;
     let new_config = get_config(&pool, *id).await?;
 
@@ -513,6 +548,87 @@ async fn add_channel(
     }
 }
 
+/// **Create Channel with Config**
+///
+/// The config is a complete playout config, like the one from `/api/playout/config/{id}`.
+/// With `"start": true` the new channel starts right away.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/channel/config/ -H "Content-Type: application/json" \
+/// -d '{ "channel": { <CHANNEL DATA> }, "config": { <CONFIG DATA> }, "start": true }' \
+/// -H "Authorization: Bearer <TOKEN>"
+/// ```
+#[post("/channel/config/")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+pub async fn add_channel_config(
+    pool: web::Data<Pool<Sqlite>>,
+    data: web::Json<ChannelObj>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    queue: web::Data<Mutex<Vec<Arc<Mutex<MailQueue>>>>>,
+) -> Result<impl Responder, ServiceError> {
+    let data = data.into_inner();
+    let mut channel = create_channel(
+        &pool,
+        controllers.clone().into_inner(),
+        queue.clone().into_inner(),
+        data.channel,
+    )
+    .await?;
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(channel.id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {} not found!", channel.id)))?;
+
+    if let Err(e) = apply_channel_config(&pool, &manager, data.config, &queue).await {
+        // don't keep a half configured channel, the config error is the one to report
+        if let Err(del_e) = delete_channel(
+            &pool,
+            channel.id,
+            controllers.into_inner(),
+            queue.into_inner(),
+        )
+        .await
+        {
+            error!(target: Target::file_mail(), channel = channel.id;
+                "Remove channel <span class=\"log-number\">{}</span> after failed config: {del_e}",
+                channel.id
+            );
+        }
+
+        return Err(e);
+    }
+
+    if data.start {
+        handles::update_player(&pool, channel.id, true).await?;
+        manager.channel.lock().await.active = true;
+        channel.active = true;
+        manager.start().await?;
+    }
+
+    Ok(web::Json(channel))
+}
+
+/// Save the config to a new channel, with the output of the same mode.
+async fn apply_channel_config(
+    pool: &Pool<Sqlite>,
+    manager: &ChannelManager,
+    config: PlayoutConfig,
+    mail_queues: &Mutex<Vec<Arc<Mutex<MailQueue>>>>,
+) -> Result<(), ServiceError> {
+    let mut config = norm_config_paths(manager, config).await?;
+    let mode = config.output.mode.to_string();
+
+    config.output.id = handles::select_outputs(pool, manager.id)
+        .await?
+        .into_iter()
+        .find(|o| o.name == mode)
+        .map(|o| o.id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Output {mode} not found!")))?;
+
+    save_playout_config(pool, manager, config, mail_queues).await
+}
+
 /// **Delete Channel**
 ///
 /// ```BASH
@@ -664,6 +780,7 @@ async fn update_advanced_config(
     }
     .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
 
+    check_advanced_config(&manager, &data).await?;
     handles::update_advanced_configuration(&pool, *id, data.into_inner()).await?;
     let new_config = get_config(&pool, *id).await?;
 
@@ -699,6 +816,7 @@ async fn add_advanced_config(
     }
     .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
 
+    check_advanced_config(&manager, &data).await?;
     handles::insert_advanced_configuration(&pool, *id, None, data.into_inner()).await?;
     let new_config = get_config(&pool, *id).await?;
 
@@ -707,6 +825,20 @@ async fn add_advanced_config(
     Ok(web::Json("Update success"))
 }
 
+/// Check the advanced config against the format of the channel, before it gets saved.
+async fn check_advanced_config(
+    manager: &ChannelManager,
+    data: &AdvancedConfig,
+) -> Result<(), ServiceError> {
+    let mut config = manager.config.read().await.clone();
+
+    config.advanced = data.clone();
+    config.advanced.decoder.output_cmd =
+        data.decoder.output_param.as_deref().and_then(shlex::split);
+
+    format_errors(&config)
+}
+
 /// **Get Config**
 ///
 /// ```BASH
@@ -753,7 +885,7 @@ async fn get_playout_config(
 async fn update_playout_config(
     pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
-    mut data: web::Json<PlayoutConfig>,
+    data: web::Json<PlayoutConfig>,
     controllers: web::Data<RwLock<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
@@ -764,9 +896,20 @@ async fn update_playout_config(
         guard.get(*id)
     }
     .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+    let data = norm_config_paths(&manager, data.into_inner()).await?;
+
+    save_playout_config(&pool, &manager, data, &mail_queues).await?;
+
+    Ok(web::Json("Update success"))
+}
+
+/// Storage paths in the submitted config relative to the channel storage.
+async fn norm_config_paths(
+    manager: &ChannelManager,
+    mut data: PlayoutConfig,
+) -> Result<PlayoutConfig, ServiceError> {
     let p = manager.channel.lock().await.storage.clone();
     let storage = Path::new(&p);
-    let config_id = manager.config.read().await.general.id;
 
     let (_, _, logo) = norm_abs_path(storage, &data.processing.logo)?;
     let (_, _, filler) = norm_abs_path(storage, &data.storage.filler)?;
@@ -776,15 +919,58 @@ async fn update_playout_config(
     data.storage.filler = filler;
     data.text.font = font;
 
-    handles::update_output(&pool, data.output.id, *id, &data.output.output_param).await?;
-    handles::update_configuration(&pool, config_id, data.into_inner()).await?;
-    let new_config = get_config(&pool, *id).await?;
+    if !data.storage.slate.is_empty() {
+        let (_, _, slate) = norm_abs_path(storage, &data.storage.slate)?;
+        data.storage.slate = slate;
+    }
+
+    if !data.storage.slate_audio.is_empty() {
+        let (_, _, audio) = norm_abs_path(storage, &data.storage.slate_audio)?;
+        data.storage.slate_audio = audio;
+    }
+
+    if !data.storage.bumper_in.is_empty() {
+        let (_, _, bumper) = norm_abs_path(storage, &data.storage.bumper_in)?;
+        data.storage.bumper_in = bumper;
+    }
+
+    if !data.storage.bumper_out.is_empty() {
+        let (_, _, bumper) = norm_abs_path(storage, &data.storage.bumper_out)?;
+        data.storage.bumper_out = bumper;
+    }
+
+    Ok(data)
+}
+
+/// Save the config and apply it to the channel.
+async fn save_playout_config(
+    pool: &Pool<Sqlite>,
+    manager: &ChannelManager,
+    data: PlayoutConfig,
+    mail_queues: &Mutex<Vec<Arc<Mutex<MailQueue>>>>,
+) -> Result<(), ServiceError> {
+    let id = manager.id;
+    let config_id = manager.config.read().await.general.id;
+
+    // an invalid format must not get into the database, the channel would not start anymore
+    format_errors(&PlayoutConfig::staged(pool, id, data.clone()).await?)?;
+
+    handles::update_output(
+        pool,
+        data.output.id,
+        id,
+        &data.output.output_param,
+        data.output.field_order,
+    )
+    .await?;
+    handles::update_configuration(pool, config_id, data).await?;
+    let new_config = get_config(pool, id).await?;
     let mut queues = mail_queues.lock().await;
 
     for queue in queues.iter_mut() {
         let mut queue_lock = queue.lock().await;
 
-        if queue_lock.id == *id {
+        if queue_lock.id == id {
             if queue_lock.config.recipient != new_config.mail.recipient {
                 queue_lock.clear_raw();
             }
@@ -796,7 +982,223 @@ async fn update_playout_config(
 
     manager.update_config(new_config).await;
 
-    Ok(web::Json("Update success"))
+    // saved values replace the live filter changes
+    resume::save(manager, |s| {
+        s.volume = None;
+        s.logo_opacity = None;
+    })
+    .await;
+
+    Ok(())
+}
+
+/// #### Staging
+///
+/// A changed config runs first on a preview output (`/<id>/preview/staging.m3u8`),
+/// the live output keeps the saved config until the staged one gets promoted.
+///
+/// **Get Staged Config**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/playout/config/1/staging -H 'Authorization: Bearer <TOKEN>'
+/// ```
+///
+/// Response is the staged config, or `null`.
+#[get("/playout/config/{id}/staging")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn get_staging_config(
+    id: web::Path<i32>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+
+    let config = manager
+        .staging
+        .lock()
+        .await
+        .as_ref()
+        .map(|s| s.config.clone());
+
+    Ok(web::Json(config))
+}
+
+/// **Stage Config**
+///
+/// Replaces a staged config, which is already there.
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/playout/config/1/staging -H "Content-Type: application/json" \
+/// -d { <CONFIG DATA> } -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[put("/playout/config/{id}/staging")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn stage_playout_config(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<PlayoutConfig>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+    let data = norm_config_paths(&manager, data.into_inner()).await?;
+    let config = PlayoutConfig::staged(&pool, *id, data).await?;
+
+    *manager.staging.lock().await = Some(Staging::new(&manager, config));
+
+    Ok(web::Json("Staging started"))
+}
+
+/// **Promote Staged Config**
+///
+/// Save the staged config and restart the live output with it, when the channel is running.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playout/config/1/staging/promote -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/playout/config/{id}/staging/promote")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn promote_staging_config(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+    mail_queues: web::Data<Mutex<Vec<Arc<Mutex<MailQueue>>>>>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+
+    // hold the lock, so the staged config can't change while it gets saved
+    let mut staging = manager.staging.lock().await;
+    let config = staging
+        .as_ref()
+        .map(|s| s.config.clone())
+        .ok_or_else(|| ServiceError::BadRequest("No staged config!".to_string()))?;
+
+    save_playout_config(&pool, &manager, config, &mail_queues).await?;
+    staging.take();
+    drop(staging);
+
+    if manager.is_alive.load(Ordering::SeqCst) {
+        manager.channel.lock().await.active = false;
+        manager.stop_all(false).await;
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+
+        manager.channel.lock().await.active = true;
+        manager.start().await?;
+    }
+
+    Ok(web::Json("Promote success"))
+}
+
+/// **Discard Staged Config**
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/playout/config/1/staging -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[delete("/playout/config/{id}/staging")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn discard_staging_config(
+    id: web::Path<i32>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+
+    if manager.staging.lock().await.take().is_none() {
+        return Err(ServiceError::BadRequest("No staged config!".to_string()));
+    }
+
+    Ok(web::Json("Staging discarded"))
+}
+
+/// **Switch Output**
+///
+/// Save the config and move the running output to the new output settings, without dead air:
+/// a second encoder starts with them and gets the same data as the current one, for two
+/// seconds, then it takes over. Other changes apply like with a normal update. HLS has no
+/// separate encoder and can't switch.
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/playout/config/1/output -H "Content-Type: application/json" \
+/// -d { <CONFIG DATA> } -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[allow(clippy::too_many_arguments)]
+#[put("/playout/config/{id}/output")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn switch_playout_output(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<PlayoutConfig>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+    mail_queues: web::Data<Mutex<Vec<Arc<Mutex<MailQueue>>>>>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+    let data = norm_config_paths(&manager, data.into_inner()).await?;
+
+    if data.output.mode == OutputMode::HLS
+        || manager.config.read().await.output.mode == OutputMode::HLS
+    {
+        return Err(ServiceError::BadRequest(
+            "HLS output can't switch, restart the channel instead!".to_string(),
+        ));
+    }
+
+    save_playout_config(&pool, &manager, data, &mail_queues).await?;
+
+    if !manager.is_alive.load(Ordering::SeqCst) {
+        return Ok(web::Json("Update success"));
+    }
+
+    manager.output_switch.notify_one();
+
+    Ok(web::Json("Output switch started"))
 }
 
 /// **Get Output**
@@ -983,26 +1385,25 @@ pub async fn send_text_message(
     }
 }
 
-/// **Control Playout**
+/// **Change Filter Values**
 ///
-/// - next
-/// - back
-/// - reset
+/// Change logo opacity and/or volume of the running playout, without restarting the encoder.
+/// Needs **Live Control** in the processing config.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/control/1/playout/ -H 'Content-Type: application/json'
-/// -d '{ "command": "reset" }' -H 'Authorization: Bearer <TOKEN>'
+/// curl -X POST http://127.0.0.1:8787/api/control/1/filter/ \
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>' \
+/// -d '{"logo_opacity": 0.5, "volume": 0.8}'
 /// ```
-#[post("/control/{id}/playout/")]
+#[post("/control/{id}/filter/")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
     expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
 )]
-pub async fn control_playout(
-    pool: web::Data<Pool<Sqlite>>,
+pub async fn send_filter_values(
     id: web::Path<i32>,
-    control: web::Json<ControlParams>,
+    data: web::Json<FilterParams>,
     controllers: web::Data<RwLock<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
@@ -1013,25 +1414,307 @@ pub async fn control_playout(
     }
     .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
 
-    if manager.is_processing.load(Ordering::SeqCst) {
-        return Err(ServiceError::Conflict(
-            "A command is already being processed, please wait".to_string(),
-        ));
+    match update_filter(manager, data.into_inner()).await {
+        Ok(res) => Ok(web::Json(res)),
+        Err(e) => Err(e),
     }
+}
 
-    manager.is_processing.store(true, Ordering::SeqCst);
+/// **Graphics**
+///
+/// Show or hide a graphics template, like a lower third, with the animation of the template.
+/// The fields fill the placeholders of the template text.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/graphics/ \
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>' \
+/// -d '{"template": "lower_third", "action": "show", "fields": {"name": "Jane Doe", "title": "Reporter"}}'
+/// ```
+#[post("/control/{id}/graphics/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn send_graphic(
+    id: web::Path<i32>,
+    data: web::Json<GraphicParams>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
 
-    let resp = match control_state(&pool, &manager, &control.control).await {
+    match show_graphic(manager, data.into_inner()).await {
         Ok(res) => Ok(web::Json(res)),
         Err(e) => Err(e),
-    };
-
-    manager.is_processing.store(false, Ordering::SeqCst);
-
-    resp
+    }
 }
 
-/// **Get current Clip**
+/// **Emergency Alert**
+///
+/// Crawl text over the picture and optionally the attention tone and an audio message
+/// instead of the program audio. The body is JSON or a CAP message (XML), CAP `msgType`
+/// Cancel or `"cancel": true` ends the alert.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/alert/ \
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>' \
+/// -d '{"headline": "Flood warning", "description": "Leave the river banks", "duration": 120, "tone": true, "audio": "alerts/flood.mp3"}'
+/// ```
+#[post("/control/{id}/alert/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn send_alert(
+    id: web::Path<i32>,
+    body: String,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+    let alert = Alert::parse(&body)?;
+
+    match alert::start(manager, alert).await {
+        Ok(res) => Ok(web::Json(res)),
+        Err(e) => Err(e),
+    }
+}
+
+/// **Control Playout**
+///
+/// - next
+/// - back
+/// - reset
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/playout/ -H 'Content-Type: application/json'
+/// -d '{ "command": "reset" }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/control/{id}/playout/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn control_playout(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    control: web::Json<ControlParams>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+
+    if manager.is_processing.load(Ordering::SeqCst) {
+        return Err(ServiceError::Conflict(
+            "A command is already being processed, please wait".to_string(),
+        ));
+    }
+
+    manager.is_processing.store(true, Ordering::SeqCst);
+
+    let resp = match control_state(&pool, &manager, &control.control).await {
+        Ok(res) => Ok(web::Json(res)),
+        Err(e) => Err(e),
+    };
+
+    manager.is_processing.store(false, Ordering::SeqCst);
+
+    resp
+}
+
+/// **Insert Clip**
+///
+/// Insert a clip, which plays directly after the current one.
+/// With policy `shift` the rest of the schedule moves back,
+/// with `drop_filler` the following filler clips are removed or shortened to recover the time.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/insert/ -H 'Content-Type: application/json'
+/// -d '{ "source": "/news/breaking.mp4", "in": 0, "out": 120, "policy": "drop_filler" }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/control/{id}/insert/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn insert_clip(
+    id: web::Path<i32>,
+    data: web::Json<InsertParams>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+
+    if !manager.is_alive.load(Ordering::SeqCst) {
+        return Err(ServiceError::Conflict("Playout is not running".to_string()));
+    }
+
+    if manager.is_processing.load(Ordering::SeqCst) {
+        return Err(ServiceError::Conflict(
+            "A command is already being processed, please wait".to_string(),
+        ));
+    }
+
+    manager.is_processing.store(true, Ordering::SeqCst);
+
+    let resp = insert_next(&manager, data.into_inner()).await;
+
+    manager.is_processing.store(false, Ordering::SeqCst);
+
+    Ok(web::Json(resp?))
+}
+
+/// **Get Program List**
+///
+/// The items of the running day, with their begin in seconds and their status:
+/// `played`, `playing` or `pending`.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/control/1/list/
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/control/{id}/list/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_program_list(
+    id: web::Path<i32>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+
+    Ok(web::Json(program_list(&manager).await))
+}
+
+/// **Edit Program Item**
+///
+/// Swap a pending item, or change its in and out point. The playlist file of the day
+/// gets the same change.
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/control/1/list/ -H 'Content-Type: application/json'
+/// -d '{ "index": 42, "source": "/docs/replacement.mp4", "in": 0, "out": 300 }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[put("/control/{id}/list/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn edit_program_item(
+    id: web::Path<i32>,
+    data: web::Json<ItemEdit>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+
+    if !manager.is_alive.load(Ordering::SeqCst) {
+        return Err(ServiceError::Conflict("Playout is not running".to_string()));
+    }
+
+    if manager.is_processing.load(Ordering::SeqCst) {
+        return Err(ServiceError::Conflict(
+            "A command is already being processed, please wait".to_string(),
+        ));
+    }
+
+    manager.is_processing.store(true, Ordering::SeqCst);
+
+    let resp = edit_item(&manager, data.into_inner()).await;
+
+    manager.is_processing.store(false, Ordering::SeqCst);
+
+    Ok(web::Json(resp?))
+}
+
+/// **Resume from Item**
+///
+/// Resume the schedule with a pending item, after a live overrun. The items before are
+/// dropped, the rest of the day gets new times. With `time` the item begins at this clock
+/// time: later a filler gap plays until then, earlier the item gets trimmed.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/resume/ -H 'Content-Type: application/json'
+/// -d '{ "index": 42, "time": "21:00:00" }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/control/{id}/resume/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn resume_schedule(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<ResumeParams>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+
+    if !manager.is_alive.load(Ordering::SeqCst) {
+        return Err(ServiceError::Conflict("Playout is not running".to_string()));
+    }
+
+    if manager.is_processing.load(Ordering::SeqCst) {
+        return Err(ServiceError::Conflict(
+            "A command is already being processed, please wait".to_string(),
+        ));
+    }
+
+    manager.is_processing.store(true, Ordering::SeqCst);
+
+    let resp = resume_from(&pool, &manager, data.into_inner()).await;
+
+    manager.is_processing.store(false, Ordering::SeqCst);
+
+    Ok(web::Json(resp?))
+}
+
+/// **Get current Clip**
 ///
 /// ```BASH
 /// curl -X GET http://127.0.0.1:8787/api/control/1/media/current
@@ -1052,9 +1735,12 @@ pub async fn control_playout(
 ///       "index": 39,
 ///       "ingest": false,
 ///       "mode": "playlist",
-///       "played": 67.808
+///       "played": 67.808,
+///       "warnings": { "date": "2024-02-01", "warnings": [] }
 ///     }
 /// ```
+///
+/// `warnings` are the problems, which the validation found in the loaded playlist.
 #[get("/control/{id}/media/current")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
@@ -1083,6 +1769,7 @@ pub async fn media_current(
 /// Control ffplayout process, like:
 /// - start
 /// - stop
+/// - drain (stop after the current clip or program block)
 /// - restart
 /// - status
 ///
@@ -1139,6 +1826,15 @@ pub async fn process_control(
             manager.channel.lock().await.active = false;
             manager.stop_all(true).await;
         }
+        ProcessCtl::Drain => {
+            let manager = manager.clone();
+
+            // the current clip can run for a while, so don't wait for it
+            tokio::spawn(async move {
+                manager.finish().await;
+                manager.stop_all(true).await;
+            });
+        }
         ProcessCtl::Restart => {
             manager.channel.lock().await.active = false;
             manager.stop_all(false).await;
@@ -1347,6 +2043,140 @@ pub async fn get_log(
     read_log_file(&id, &log.date, log.timezone, log.download).await
 }
 
+/// **As-run Report**
+///
+/// Compare the as-run log with the playlist of a date, items have the status
+/// `ok`, `late`, `short` or `missed`.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/log/1/as-run/2022-06-20
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/log/{id}/as-run/{date}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&params.0) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_as_run_report(
+    params: web::Path<(i32, String)>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, date) = params.into_inner();
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+
+    let config = manager.config.read().await.clone();
+
+    Ok(web::Json(as_run::report(&config, &date).await?))
+}
+
+/// **Channel Statistics**
+///
+/// Air time per category, errors, live ingest and filler of every day between `from` and
+/// `to` (YYYY-MM-DD), and the total over all days. Times are in minutes, without parameters
+/// it is the current month.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/statistics/1/?from=2026-10-01&to=2026-10-31'
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/statistics/{id}/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_statistics(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    query: web::Query<StatsQuery>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+
+    let config = manager.config.read().await.clone();
+
+    Ok(web::Json(statistics::report(&pool, &config, &query).await?))
+}
+
+/// **Media Library**
+///
+/// Search the library index of the channel storage. All parameters are optional:
+/// `search` (part of the path), `folder` (relative to storage), `order`
+/// (`path`, `duration`, `last_aired`, `loudness`), `limit` and `offset`.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/library/1/?search=news&limit=50'
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/library/{id}/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_library(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    query: web::Query<LibraryQuery>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let entries = handles::select_library(&pool, *id, &query).await?;
+
+    Ok(web::Json(entries))
+}
+
+/// **Scan Media Library**
+///
+/// Update the library index in the background, only new and changed files get probed.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/library/1/scan/ -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/library/{id}/scan/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn scan_library(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    controllers: web::Data<RwLock<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = {
+        let guard = controllers.read().await;
+        guard.get(*id)
+    }
+    .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found!")))?;
+
+    let config = manager.config.read().await.clone();
+    let pool = pool.into_inner();
+
+    tokio::spawn(async move {
+        if let Err(e) = library::scan(&pool, &config).await {
+            error!(target: Target::file_mail(), channel = config.general.channel_id; "Library scan: {e}");
+        }
+    });
+
+    Ok("Scan started")
+}
+
 /// ### File Operations
 ///
 /// **Get File/Folder List**
@@ -1513,7 +2343,17 @@ async fn save_file(
     //     .and_then(|cls| cls.parse().ok())
     //     .unwrap_or(0);
 
-    manager.storage.upload(payload, &obj.path, false).await?;
+    let paths = manager.storage.upload(payload, &obj.path, false).await?;
+    let config = manager.config.read().await.clone();
+
+    if config.storage.transcode {
+        for path in paths {
+            if include_file_extension(&config, &path) {
+                Transcoder::stage(&path).await?;
+                manager.transcoder.submit(config.clone(), path).await;
+            }
+        }
+    }
 
     Ok(HttpResponse::Ok().into())
 }
@@ -1593,10 +2433,145 @@ async fn get_public(
         }))
 }
 
+/// **Health**
+///
+/// Liveness check for container probes, without authentication.
+/// Answers with status 503, when an active channel has no running playout, or the playout
+/// loop of a running channel made no progress for three times the stall timeout.
+/// Then the process hangs and should be restarted. Served at `/healthz` and `/health`.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/healthz
+/// ```
+#[routes]
+#[get("/healthz")]
+#[get("/health")]
+async fn health(
+    controllers: web::Data<RwLock<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let managers = controllers.read().await.managers.clone();
+    let limit = stall_timeout() * 3;
+    let mut channels = vec![];
+    let mut healthy = true;
+
+    for manager in managers {
+        let active = manager.channel.lock().await.active;
+        let alive = manager.is_alive.load(Ordering::SeqCst);
+        let idle = manager.loop_idle().min(manager.output_idle());
+        let progressing = !alive || idle <= limit;
+
+        healthy &= (!active || alive) && progressing;
+        channels.push(serde_json::json!({
+            "id": manager.id,
+            "active": active,
+            "alive": alive,
+            "progressing": progressing,
+        }));
+    }
+
+    let body = serde_json::json!({
+        "status": if healthy { "ok" } else { "unhealthy" },
+        "channels": channels,
+    });
+
+    if healthy {
+        Ok(HttpResponse::Ok().json(body))
+    } else {
+        Ok(HttpResponse::ServiceUnavailable().json(body))
+    }
+}
+
+/// **Readiness**
+///
+/// Answers with status 503, when an active channel has no encoder running (in HLS mode
+/// the ffmpeg process, which writes the segments), or no data went to the encoder within
+/// the stall timeout (`--stall-timeout`).
+/// `buffer` is the fill level of the output buffer in milliseconds,
+/// `standby` is true, when the redundancy peer is on air.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/readyz
+/// ```
+#[get("/readyz")]
+async fn readyz(
+    controllers: web::Data<RwLock<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let managers = controllers.read().await.managers.clone();
+    let limit = stall_timeout();
+    let mut channels = vec![];
+    let mut ready = true;
+
+    for manager in managers {
+        if !manager.channel.lock().await.active {
+            continue;
+        }
+
+        let encoder = manager.encoder_running().await;
+        let idle = manager.output_idle();
+        let flowing = manager.is_alive.load(Ordering::SeqCst) && encoder && idle <= limit;
+
+        ready &= flowing;
+        channels.push(serde_json::json!({
+            "id": manager.id,
+            "encoder": encoder,
+            "flowing": flowing,
+            "idle": idle.as_secs(),
+            "buffer": manager.buffer_fill.load(Ordering::Relaxed),
+            "standby": manager.standby.load(Ordering::SeqCst),
+        }));
+    }
+
+    let body = serde_json::json!({ "ready": ready, "channels": channels });
+
+    if ready {
+        Ok(HttpResponse::Ok().json(body))
+    } else {
+        Ok(HttpResponse::ServiceUnavailable().json(body))
+    }
+}
+
+/// **Metrics**
+///
+/// Bytes, bitrate of the last minute and stalls of the encoder input per channel, for
+/// stream outputs also of the output and if it sends data, in the Prometheus text format.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/metrics
+/// ```
+#[get("/metrics")]
+async fn metrics(
+    controllers: web::Data<RwLock<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let managers = controllers.read().await.managers.clone();
+    let mut channels = vec![];
+
+    for manager in managers {
+        let running = manager.is_alive.load(Ordering::SeqCst);
+        let stats = manager
+            .output_stats
+            .lock()
+            .await
+            .snapshot(Instant::now(), running);
+
+        channels.push((
+            manager.id,
+            stats,
+            manager.buffer_fill.load(Ordering::Relaxed),
+        ));
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(output_stats::prometheus(&channels)))
+}
+
 /// **Import playlist**
 ///
-/// Import text/m3u file and convert it to a playlist
-/// lines with leading "#" will be ignore
+/// Import text/m3u/csv/xspf/cue file and convert it to a playlist
+/// lines with leading "#" will be ignore, except extended m3u tags
+/// (`#EXTINF` and `#EXT-X-PROGRAM-DATE-TIME`).
+/// CSV files can have a header with the columns: start, source, title, in, out, duration, category
+/// Tracks from CUE sheets are imported as in/out points of the referenced file
 ///
 /// ```BASH
 /// curl -X PUT http://127.0.0.1:8787/api/file/1/import/ -H 'Authorization: Bearer <TOKEN>'
@@ -1659,6 +2634,11 @@ async fn import_playlist(
 /// curl -X GET http://127.0.0.1:8787/api/program/1/?start_after=2022-11-13T10:00:00 \
 /// -H 'Authorization: Bearer <TOKEN>'
 /// ```
+///
+/// * get items with the same `program_id` as one program, with aggregate duration and breaks
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/program/1/?group=true -H 'Authorization: Bearer <TOKEN>'
+/// ```
 #[get("/program/{id}/")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
@@ -1683,6 +2663,7 @@ async fn get_program(
     let start_sec = config.playlist.start_sec.unwrap();
     let mut days = 0;
     let mut program = vec![];
+    let mut items = vec![];
     let after = obj.start_after;
     let mut before = obj.start_before;
 
@@ -1734,29 +2715,48 @@ async fn get_program(
                 .and_then(|r| r.captures(&item.source))
             {
                 Some(t) => t[1].to_string(),
-                None => item.source,
+                None => item.source.clone(),
             };
 
             let p_item = ProgramItem {
                 source,
                 start: start.format("%Y-%m-%d %H:%M:%S%.3f%:z").to_string(),
-                title: item.title,
+                title: item.title.clone(),
                 r#in: item.seek,
                 out: item.out,
                 duration: item.duration,
-                category: item.category,
+                category: item.category.clone(),
+                program_id: item.program_id.clone(),
             };
 
+            let length = item.out - item.seek;
+
             if naive >= after && naive <= before {
                 program.push(p_item);
+                items.push(item);
             }
 
-            naive += TimeDelta::try_milliseconds(((item.out - item.seek) * 1000.0) as i64)
-                .unwrap_or_default();
+            naive += TimeDelta::try_milliseconds((length * 1000.0) as i64).unwrap_or_default();
         }
     }
 
-    Ok(web::Json(program))
+    if obj.group {
+        let groups = programs(&items)
+            .into_iter()
+            .map(|p| ProgramGroup {
+                program_id: p.program_id,
+                start: program[p.index].start.clone(),
+                title: p.title,
+                items: p.items,
+                duration: p.duration,
+                breaks: p.breaks,
+            })
+            .collect::<Vec<_>>();
+
+        return Ok(HttpResponse::Ok().json(groups));
+    }
+
+    Ok(HttpResponse::Ok().json(program))
 }
 
 /// ### System Statistics
@@ -7,12 +7,15 @@ use rand::{Rng, distr::Alphanumeric};
 use sqlx::{Pool, Row, Sqlite, sqlite::SqliteQueryResult};
 
 use super::models::{AdvancedConfiguration, Configuration};
-use crate::db::models::{Channel, GlobalSettings, Output, Role, TextPreset, User};
+use crate::db::models::{
+    Channel, GlobalSettings, MediaEntry, Output, Role, StatEntry, TextPreset, User,
+};
 use crate::utils::{
     advanced_config::AdvancedConfig,
-    config::PlayoutConfig,
+    config::{FieldOrder, PlayoutConfig},
     errors::{ProcessError, ServiceError},
     is_running_in_container,
+    library::LibraryQuery,
 };
 
 pub async fn db_migrate(conn: &Pool<Sqlite>) -> Result<bool, ProcessError> {
@@ -48,7 +51,7 @@ pub async fn db_migrate(conn: &Pool<Sqlite>) -> Result<bool, ProcessError> {
 }
 
 pub async fn select_global(conn: &Pool<Sqlite>) -> Result<GlobalSettings, ProcessError> {
-    const QUERY: &str = "SELECT id, secret, logs, playlists, public, storage, shared, smtp_server, smtp_user, smtp_password, smtp_starttls, smtp_port FROM global WHERE id = 1";
+    const QUERY: &str = "SELECT id, secret, logs, playlists, public, storage, shared, smtp_server, smtp_user, smtp_password, smtp_starttls, smtp_port, ffmpeg, ffprobe FROM global WHERE id = 1";
 
     let result = sqlx::query_as(QUERY).fetch_one(conn).await?;
 
@@ -60,7 +63,7 @@ pub async fn update_global(
     global: GlobalSettings,
 ) -> Result<SqliteQueryResult, ProcessError> {
     const QUERY: &str = "UPDATE global SET logs = $2, playlists = $3, public = $4, storage = $5,
-            smtp_server = $6, smtp_user = $7, smtp_password = $8, smtp_starttls = $9, smtp_port = $10  WHERE id = 1";
+            smtp_server = $6, smtp_user = $7, smtp_password = $8, smtp_starttls = $9, smtp_port = $10, ffmpeg = $11, ffprobe = $12 WHERE id = 1";
 
     let result = sqlx::query(QUERY)
         .bind(global.id)
@@ -73,6 +76,8 @@ pub async fn update_global(
         .bind(global.smtp_password)
         .bind(global.smtp_starttls)
         .bind(global.smtp_port)
+        .bind(global.ffmpeg)
+        .bind(global.ffprobe)
         .execute(conn)
         .await?;
 
@@ -167,6 +172,48 @@ pub async fn update_stat(
     Ok(result)
 }
 
+pub async fn select_channel_state(
+    conn: &Pool<Sqlite>,
+    id: i32,
+) -> Result<Option<String>, ProcessError> {
+    const QUERY: &str = "SELECT state FROM channel_state WHERE channel_id = $1";
+
+    let result = sqlx::query_scalar(QUERY)
+        .bind(id)
+        .fetch_optional(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn update_channel_state(
+    conn: &Pool<Sqlite>,
+    id: i32,
+    state: &str,
+) -> Result<SqliteQueryResult, ProcessError> {
+    const QUERY: &str = "INSERT INTO channel_state (channel_id, state) VALUES($1, $2)
+        ON CONFLICT(channel_id) DO UPDATE SET state = $2;";
+
+    let result = sqlx::query(QUERY)
+        .bind(id)
+        .bind(state)
+        .execute(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn delete_channel_state(
+    conn: &Pool<Sqlite>,
+    id: i32,
+) -> Result<SqliteQueryResult, ProcessError> {
+    const QUERY: &str = "DELETE FROM channel_state WHERE channel_id = $1";
+
+    let result = sqlx::query(QUERY).bind(id).execute(conn).await?;
+
+    Ok(result)
+}
+
 pub async fn update_player(
     conn: &Pool<Sqlite>,
     id: i32,
@@ -257,7 +304,7 @@ pub async fn update_configuration(
     id: i32,
     config: PlayoutConfig,
 ) -> Result<SqliteQueryResult, ProcessError> {
-    const QUERY: &str = "UPDATE configurations SET general_stop_threshold = $2, mail_subject = $3, mail_recipient = $4, mail_level = $5, mail_interval = $6, logging_ffmpeg_level = $7, logging_ingest_level = $8, logging_detect_silence = $9, logging_ignore = $10, processing_mode = $11, processing_audio_only = $12, processing_copy_audio = $13, processing_copy_video = $14, processing_width = $15, processing_height = $16, processing_aspect = $17, processing_fps = $18, processing_add_logo = $19, processing_logo = $20, processing_logo_scale = $21, processing_logo_opacity = $22, processing_logo_position = $23, processing_audio_tracks = $24, processing_audio_track_index = $25, processing_audio_channels = $26, processing_volume = $27, processing_filter = $28, processing_override_filter = $29, processing_vtt_enable = $30, processing_vtt_dummy = $31, ingest_enable = $32, ingest_param = $33, ingest_filter = $34, playlist_day_start = $35, playlist_length = $36, playlist_infinit = $37, storage_filler = $38, storage_extensions = $39, storage_shuffle = $40, text_add = $41, text_from_filename = $42, text_font = $43, text_style = $44, text_regex = $45, task_enable = $46, task_path = $47, output_id = $48 WHERE id = $1";
+    const QUERY: &str = "UPDATE configurations SET general_stop_threshold = $2, mail_subject = $3, mail_recipient = $4, mail_level = $5, mail_interval = $6, logging_ffmpeg_level = $7, logging_ingest_level = $8, logging_detect_silence = $9, logging_ignore = $10, processing_mode = $11, processing_audio_only = $12, processing_copy_audio = $13, processing_copy_video = $14, processing_width = $15, processing_height = $16, processing_aspect = $17, processing_fps = $18, processing_add_logo = $19, processing_logo = $20, processing_logo_scale = $21, processing_logo_opacity = $22, processing_logo_position = $23, processing_audio_tracks = $24, processing_audio_track_index = $25, processing_audio_channels = $26, processing_volume = $27, processing_filter = $28, processing_override_filter = $29, processing_vtt_enable = $30, processing_vtt_dummy = $31, ingest_enable = $32, ingest_param = $33, ingest_filter = $34, playlist_day_start = $35, playlist_length = $36, playlist_infinit = $37, storage_filler = $38, storage_extensions = $39, storage_shuffle = $40, text_add = $41, text_from_filename = $42, text_font = $43, text_style = $44, text_regex = $45, task_enable = $46, task_path = $47, output_id = $48, playlist_recovery = $49, playlist_recovery_speed = $50, general_nice = $51, general_cgroup = $52, general_threads = $53, ingest_preview = $54, ingest_record = $55, ingest_record_path = $56, storage_vod_path = $57, storage_vod_template = $58, storage_vod_hook = $59, processing_audio_meter = $60, storage_fallback = $61, storage_fallback_clip = $62, processing_fps_policy = $63, processing_fps_tolerance = $64, processing_tonemap = $65, processing_tonemap_algorithm = $66, processing_tonemap_desat = $67, processing_tonemap_peak = $68, processing_timecode_burn_in = $69, processing_timecode_embed = $70, processing_ltc_channel = $71, processing_live_control = $72, task_icecast_url = $73, task_webhook_url = $74, processing_hls_id3 = $75, general_stall_timeout = $76, general_quarantine = $77, general_decoder_resume = $78, storage_incoming_url = $79, storage_incoming_path = $80, storage_incoming_interval = $81, storage_transcode = $82, storage_incoming_delete = $83, storage_transcode_workers = $84, storage_transcode_loudness = $85, storage_separation = $86, task_hook_clip_start = $87, task_hook_clip_end = $88, task_hook_ingest_start = $89, task_hook_ingest_stop = $90, task_hook_error = $91, task_hook_day_change = $92, task_script = $93, task_trigger_device = $94, task_trigger_debounce = $95, task_trigger_map = $96, task_vdcp_address = $97, task_companion_address = $98, task_osc_address = $99, task_osc_feedback = $100, general_buffer_latency = $101, processing_av_sync_tolerance = $102, task_redundancy_role = $103, task_redundancy_address = $104, task_redundancy_peer = $105, task_redundancy_timeout = $106, storage_slate = $107, storage_slate_audio = $108, general_slate_errors = $109, storage_bumper_in = $110, storage_bumper_out = $111, processing_audio_chains = $112, processing_audio_dayparts = $113, output_audio_url = $114, output_audio_param = $115, storage_upload_ignore = $116, storage_upload_min_age = $117, general_probe_workers = $118, general_probe_timeout = $119, playlist_slot_fit = $120, output_tags = $121, processing_captions = $122, text_ticker_url = $123, text_ticker_interval = $124, text_ticker_speed = $125, text_ticker_separator = $126, text_ticker_hours = $127, text_ticker_style = $128, playlist_next_card = $129, processing_watermark = $130, processing_watermark_latency = $131, output_compliance_path = $132, output_compliance_days = $133, output_compliance_param = $134, output_check_url = $135, output_check_interval = $136, processing_audio_languages = $137, output_languages = $138, processing_description_track = $139, playlist_read_ahead = $140, playlist_join = $141 WHERE id = $1";
 
     let result = sqlx::query(QUERY)
         .bind(id)
@@ -308,6 +355,99 @@ pub async fn update_configuration(
         .bind(config.task.enable)
         .bind(config.task.path.to_string_lossy().to_string())
         .bind(config.output.id)
+        .bind(config.playlist.recovery.to_string())
+        .bind(config.playlist.recovery_speed)
+        .bind(config.general.nice)
+        .bind(config.general.cgroup)
+        .bind(config.general.threads)
+        .bind(config.ingest.preview)
+        .bind(config.ingest.record)
+        .bind(config.ingest.record_path)
+        .bind(config.storage.vod_path)
+        .bind(config.storage.vod_template)
+        .bind(config.storage.vod_hook)
+        .bind(config.processing.audio_meter)
+        .bind(config.storage.fallback.join(";"))
+        .bind(config.storage.fallback_clip)
+        .bind(config.processing.fps_policy.to_string())
+        .bind(config.processing.fps_tolerance)
+        .bind(config.processing.tonemap)
+        .bind(config.processing.tonemap_algorithm)
+        .bind(config.processing.tonemap_desat)
+        .bind(config.processing.tonemap_peak)
+        .bind(config.processing.timecode_burn_in)
+        .bind(config.processing.timecode_embed)
+        .bind(config.processing.ltc_channel)
+        .bind(config.processing.live_control)
+        .bind(config.task.icecast_url)
+        .bind(config.task.webhook_url)
+        .bind(config.processing.hls_id3)
+        .bind(config.general.stall_timeout)
+        .bind(config.general.quarantine)
+        .bind(config.general.decoder_resume)
+        .bind(config.storage.incoming_url)
+        .bind(config.storage.incoming_path)
+        .bind(config.storage.incoming_interval)
+        .bind(config.storage.transcode)
+        .bind(config.storage.incoming_delete)
+        .bind(config.storage.transcode_workers)
+        .bind(config.storage.transcode_loudness)
+        .bind(config.storage.separation)
+        .bind(config.task.hook_clip_start)
+        .bind(config.task.hook_clip_end)
+        .bind(config.task.hook_ingest_start)
+        .bind(config.task.hook_ingest_stop)
+        .bind(config.task.hook_error)
+        .bind(config.task.hook_day_change)
+        .bind(config.task.script.to_string_lossy().to_string())
+        .bind(config.task.trigger_device)
+        .bind(config.task.trigger_debounce)
+        .bind(config.task.trigger_map)
+        .bind(config.task.vdcp_address)
+        .bind(config.task.companion_address)
+        .bind(config.task.osc_address)
+        .bind(config.task.osc_feedback)
+        .bind(config.general.buffer_latency)
+        .bind(config.processing.av_sync_tolerance)
+        .bind(config.task.redundancy_role)
+        .bind(config.task.redundancy_address)
+        .bind(config.task.redundancy_peer)
+        .bind(config.task.redundancy_timeout)
+        .bind(config.storage.slate)
+        .bind(config.storage.slate_audio)
+        .bind(config.general.slate_errors)
+        .bind(config.storage.bumper_in)
+        .bind(config.storage.bumper_out)
+        .bind(config.processing.audio_chains)
+        .bind(config.processing.audio_dayparts)
+        .bind(config.output.audio_url)
+        .bind(config.output.audio_param)
+        .bind(config.storage.upload_ignore)
+        .bind(config.storage.upload_min_age)
+        .bind(config.general.probe_workers)
+        .bind(config.general.probe_timeout)
+        .bind(config.playlist.slot_fit)
+        .bind(config.output.tags)
+        .bind(config.processing.captions)
+        .bind(config.text.ticker_url)
+        .bind(config.text.ticker_interval)
+        .bind(config.text.ticker_speed)
+        .bind(config.text.ticker_separator)
+        .bind(config.text.ticker_hours)
+        .bind(config.text.ticker_style)
+        .bind(config.playlist.next_card)
+        .bind(config.processing.watermark)
+        .bind(config.processing.watermark_latency)
+        .bind(config.output.compliance_path)
+        .bind(config.output.compliance_days)
+        .bind(config.output.compliance_param)
+        .bind(config.output.check_url)
+        .bind(config.output.check_interval)
+        .bind(config.processing.audio_languages)
+        .bind(config.output.languages)
+        .bind(config.processing.description_track)
+        .bind(config.playlist.read_ahead)
+        .bind(config.playlist.join.to_string())
         .execute(conn)
         .await?;
 
@@ -330,13 +470,13 @@ pub async fn insert_output(
     channel_id: i32,
     output: &Output,
 ) -> Result<i32, ProcessError> {
-    const QUERY: &str =
-        "INSERT INTO outputs (channel_id, name, parameters) VALUES($1, $2, $3) RETURNING id";
+    const QUERY: &str = "INSERT INTO outputs (channel_id, name, parameters, field_order) VALUES($1, $2, $3, $4) RETURNING id";
 
     let output_id = sqlx::query(QUERY)
         .bind(channel_id)
         .bind(&output.name)
         .bind(&output.parameters)
+        .bind(&output.field_order)
         .fetch_one(conn)
         .await?
         .get("id");
@@ -349,13 +489,16 @@ pub async fn update_output(
     id: i32,
     channel_id: i32,
     parameters: &str,
+    field_order: FieldOrder,
 ) -> Result<SqliteQueryResult, ProcessError> {
-    const QUERY: &str = "UPDATE outputs SET parameters = $3 WHERE id = $1 AND channel_id = $2";
+    const QUERY: &str =
+        "UPDATE outputs SET parameters = $3, field_order = $4 WHERE id = $1 AND channel_id = $2";
 
     let result = sqlx::query(QUERY)
         .bind(id)
         .bind(channel_id)
         .bind(parameters)
+        .bind(field_order.to_string())
         .execute(conn)
         .await?;
 
@@ -374,8 +517,8 @@ pub async fn insert_advanced_configuration(
             filter_fade_in, filter_fade_out, filter_logo, filter_overlay_logo_scale, filter_overlay_logo_fade_in,
             filter_overlay_logo_fade_out, filter_overlay_logo, filter_tpad, filter_drawtext_from_file,
             filter_drawtext_from_zmq, filter_aevalsrc, filter_afade_in, filter_afade_out, filter_apad,
-            filter_volume, filter_split, name)
-            VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27) RETURNING id";
+            filter_volume, filter_split, name, filter_hw_accel)
+            VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28) RETURNING id";
 
     const QUERY_UPDATE: &str = "UPDATE channels SET advanced_id = $2 WHERE id = $1";
 
@@ -407,6 +550,7 @@ pub async fn insert_advanced_configuration(
         .bind(config.filter.volume)
         .bind(config.filter.split)
         .bind(config.name)
+        .bind(config.filter.hw_accel)
         .fetch_one(conn)
         .await?
         .get("id");
@@ -432,8 +576,8 @@ pub async fn update_advanced_configuration(
         filter_scale = $9, filter_set_dar = $10, filter_fade_in = $11, filter_fade_out = $12, filter_logo = $13,
         filter_overlay_logo_scale = $14, filter_overlay_logo_fade_in = $15, filter_overlay_logo_fade_out = $16,
         filter_overlay_logo = $17, filter_tpad = $18, filter_drawtext_from_file = $19, filter_drawtext_from_zmq = $20,
-        filter_aevalsrc = $21, filter_afade_in = $22, filter_afade_out = $23, filter_apad = $24, filter_volume = $25, filter_split = $26, name = $27
-        WHERE id = $1";
+        filter_aevalsrc = $21, filter_afade_in = $22, filter_afade_out = $23, filter_apad = $24, filter_volume = $25, filter_split = $26, name = $27,
+        filter_hw_accel = $28 WHERE id = $1";
     const QUERY_CHL: &str = "UPDATE channels set advanced_id = $2 WHERE id = $1;";
 
     sqlx::query(QUERY_ADV)
@@ -464,6 +608,7 @@ pub async fn update_advanced_configuration(
         .bind(config.filter.volume)
         .bind(config.filter.split)
         .bind(config.name)
+        .bind(config.filter.hw_accel)
         .execute(conn)
         .await?;
 
@@ -484,7 +629,7 @@ pub async fn select_advanced_configuration(
         adv.ingest_input_param, adv.filter_deinterlace, adv.filter_pad_video, adv.filter_fps,adv.filter_scale, adv.filter_set_dar,
         adv.filter_fade_in, adv.filter_fade_out, adv.filter_overlay_logo_scale, adv.filter_overlay_logo_fade_in, adv.filter_overlay_logo_fade_out,
         adv.filter_overlay_logo, adv.filter_tpad, adv.filter_drawtext_from_file, adv.filter_drawtext_from_zmq, adv.filter_aevalsrc,
-        adv.filter_afade_in, adv.filter_afade_out, adv.filter_apad, adv.filter_volume, adv.filter_split, adv.filter_logo, adv.name
+        adv.filter_afade_in, adv.filter_afade_out, adv.filter_apad, adv.filter_volume, adv.filter_split, adv.filter_logo, adv.name, adv.filter_hw_accel
         FROM advanced_configurations adv left join channels ch on ch.advanced_id = adv.id WHERE ch.id = $1";
 
     let result = sqlx::query_as(QUERY)
@@ -753,3 +898,163 @@ pub async fn delete_preset(
 
     Ok(result)
 }
+
+pub async fn select_library(
+    conn: &Pool<Sqlite>,
+    channel_id: i32,
+    query: &LibraryQuery,
+) -> Result<Vec<MediaEntry>, ProcessError> {
+    // order column can't be a parameter, so it comes from a fixed list
+    let order = match query.order.as_str() {
+        "duration" => "duration DESC",
+        "last_aired" => "last_aired IS NULL, last_aired",
+        "loudness" => "loudness",
+        _ => "path",
+    };
+    let sql = format!(
+        "SELECT * FROM media_library WHERE channel_id = $1 AND path LIKE $2 AND path LIKE $3
+            ORDER BY {order} LIMIT $4 OFFSET $5"
+    );
+    let folder = query.folder.trim_matches('/');
+    let folder = if folder.is_empty() {
+        "%".to_string()
+    } else {
+        format!("{folder}/%")
+    };
+
+    let result = sqlx::query_as(&sql)
+        .bind(channel_id)
+        .bind(folder)
+        .bind(format!("%{}%", query.search))
+        .bind(if query.limit == 0 {
+            -1
+        } else {
+            query.limit as i64
+        })
+        .bind(query.offset as i64)
+        .fetch_all(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn insert_or_update_media(
+    conn: &Pool<Sqlite>,
+    entry: &MediaEntry,
+) -> Result<SqliteQueryResult, ProcessError> {
+    const QUERY: &str = "INSERT INTO media_library (channel_id, path, size, modified, duration, width, height, video_codec, audio_codec, loudness)
+        VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT(channel_id, path) DO UPDATE SET size = $3, modified = $4, duration = $5, width = $6, height = $7, video_codec = $8, audio_codec = $9, loudness = $10";
+
+    let result = sqlx::query(QUERY)
+        .bind(entry.channel_id)
+        .bind(&entry.path)
+        .bind(entry.size)
+        .bind(entry.modified)
+        .bind(entry.duration)
+        .bind(entry.width)
+        .bind(entry.height)
+        .bind(&entry.video_codec)
+        .bind(&entry.audio_codec)
+        .bind(entry.loudness)
+        .execute(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn delete_media(
+    conn: &Pool<Sqlite>,
+    channel_id: i32,
+    path: &str,
+) -> Result<SqliteQueryResult, ProcessError> {
+    const QUERY: &str = "DELETE FROM media_library WHERE channel_id = $1 AND path = $2;";
+
+    let result = sqlx::query(QUERY)
+        .bind(channel_id)
+        .bind(path)
+        .execute(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn update_statistics(
+    conn: &Pool<Sqlite>,
+    channel_id: i32,
+    date: &str,
+    category: &str,
+    seconds: f64,
+    errors: i64,
+) -> Result<SqliteQueryResult, ProcessError> {
+    const QUERY: &str =
+        "INSERT INTO statistics (channel_id, date, category, seconds, errors) VALUES($1, $2, $3, $4, $5)
+        ON CONFLICT(channel_id, date, category) DO UPDATE SET seconds = seconds + $4, errors = errors + $5;";
+
+    let result = sqlx::query(QUERY)
+        .bind(channel_id)
+        .bind(date)
+        .bind(category)
+        .bind(seconds)
+        .bind(errors)
+        .execute(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn select_statistics(
+    conn: &Pool<Sqlite>,
+    channel_id: i32,
+    from: &str,
+    to: &str,
+) -> Result<Vec<StatEntry>, ProcessError> {
+    const QUERY: &str = "SELECT date, category, seconds, errors FROM statistics
+        WHERE channel_id = $1 AND date >= $2 AND date <= $3 ORDER BY date, category";
+
+    let result = sqlx::query_as(QUERY)
+        .bind(channel_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn update_media_aired(
+    conn: &Pool<Sqlite>,
+    channel_id: i32,
+    path: &str,
+    aired: &str,
+) -> Result<SqliteQueryResult, ProcessError> {
+    const QUERY: &str =
+        "INSERT INTO media_library (channel_id, path, last_aired) VALUES($1, $2, $3)
+        ON CONFLICT(channel_id, path) DO UPDATE SET last_aired = $3;";
+
+    let result = sqlx::query(QUERY)
+        .bind(channel_id)
+        .bind(path)
+        .bind(aired)
+        .execute(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn select_aired_since(
+    conn: &Pool<Sqlite>,
+    channel_id: i32,
+    since: &str,
+) -> Result<Vec<String>, ProcessError> {
+    const QUERY: &str =
+        "SELECT path FROM media_library WHERE channel_id = $1 AND last_aired >= $2;";
+
+    let result = sqlx::query_scalar(QUERY)
+        .bind(channel_id)
+        .bind(since)
+        .fetch_all(conn)
+        .await?;
+
+    Ok(result)
+}
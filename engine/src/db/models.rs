@@ -9,7 +9,10 @@ use serde::{
 use sqlx::{FromRow, Pool, Row, Sqlite, sqlite::SqliteRow};
 
 use crate::utils::config::PlayoutConfig;
-use crate::{db::handles, utils::config::OutputMode};
+use crate::{
+    db::handles,
+    utils::config::{FieldOrder, OutputMode},
+};
 
 #[derive(Clone, Default, Debug, Deserialize, Serialize, sqlx::FromRow)]
 pub struct GlobalSettings {
@@ -25,6 +28,10 @@ pub struct GlobalSettings {
     pub smtp_password: String,
     pub smtp_starttls: bool,
     pub smtp_port: u16,
+    /// Path to the ffmpeg binary, empty for the one from PATH.
+    pub ffmpeg: String,
+    /// Path to the ffprobe binary, empty for the one from PATH.
+    pub ffprobe: String,
 }
 
 impl GlobalSettings {
@@ -46,6 +53,8 @@ impl GlobalSettings {
                 smtp_password: String::new(),
                 smtp_starttls: false,
                 smtp_port: 465,
+                ffmpeg: String::new(),
+                ffprobe: String::new(),
             },
         }
     }
@@ -283,6 +292,26 @@ pub struct Configuration {
     pub id: i32,
     pub channel_id: i32,
     pub general_stop_threshold: f64,
+    #[serde(default)]
+    pub general_nice: i32,
+    #[serde(default)]
+    pub general_cgroup: String,
+    #[serde(default)]
+    pub general_threads: i32,
+    #[serde(default)]
+    pub general_stall_timeout: f64,
+    #[serde(default)]
+    pub general_quarantine: u32,
+    #[serde(default)]
+    pub general_decoder_resume: bool,
+    #[serde(default)]
+    pub general_slate_errors: u32,
+    #[serde(default)]
+    pub general_buffer_latency: u32,
+    #[serde(default)]
+    pub general_probe_workers: u32,
+    #[serde(default)]
+    pub general_probe_timeout: f64,
 
     pub mail_subject: String,
     pub mail_recipient: String,
@@ -323,30 +352,196 @@ pub struct Configuration {
     pub processing_vtt_enable: bool,
     #[serde(default)]
     pub processing_vtt_dummy: Option<String>,
+    #[serde(default)]
+    pub processing_audio_meter: bool,
+    #[serde(default)]
+    pub processing_fps_policy: String,
+    #[serde(default)]
+    pub processing_fps_tolerance: f64,
+    #[serde(default)]
+    pub processing_tonemap: bool,
+    #[serde(default)]
+    pub processing_tonemap_algorithm: String,
+    #[serde(default)]
+    pub processing_tonemap_desat: f64,
+    #[serde(default)]
+    pub processing_tonemap_peak: f64,
+    #[serde(default)]
+    pub processing_timecode_burn_in: bool,
+    #[serde(default)]
+    pub processing_timecode_embed: bool,
+    #[serde(default)]
+    pub processing_ltc_channel: u8,
+    #[serde(default)]
+    pub processing_live_control: bool,
+    #[serde(default)]
+    pub processing_av_sync_tolerance: u32,
+    #[serde(default)]
+    pub processing_audio_chains: String,
+    #[serde(default)]
+    pub processing_audio_dayparts: String,
+    #[serde(default)]
+    pub processing_hls_id3: bool,
+    #[serde(default)]
+    pub processing_captions: bool,
+    #[serde(default)]
+    pub processing_watermark: String,
+    #[serde(default)]
+    pub processing_watermark_latency: u32,
+    #[serde(default)]
+    pub processing_audio_languages: String,
+    #[serde(default)]
+    pub processing_description_track: u8,
 
     pub ingest_enable: bool,
     pub ingest_param: String,
     #[serde(default)]
     pub ingest_filter: String,
+    #[serde(default)]
+    pub ingest_preview: bool,
+    #[serde(default)]
+    pub ingest_record: bool,
+    #[serde(default)]
+    pub ingest_record_path: String,
 
     pub playlist_day_start: String,
     pub playlist_length: String,
     pub playlist_infinit: bool,
+    #[serde(default)]
+    pub playlist_recovery: String,
+    #[serde(default)]
+    pub playlist_recovery_speed: f64,
+    #[serde(default)]
+    pub playlist_slot_fit: f64,
+    #[serde(default)]
+    pub playlist_next_card: bool,
+    #[serde(default)]
+    pub playlist_read_ahead: u32,
+    #[serde(default)]
+    pub playlist_join: String,
 
     pub storage_filler: String,
     pub storage_extensions: String,
     pub storage_shuffle: bool,
+    #[serde(default)]
+    pub storage_vod_path: String,
+    #[serde(default)]
+    pub storage_vod_template: String,
+    #[serde(default)]
+    pub storage_vod_hook: String,
+    #[serde(default)]
+    pub storage_fallback: String,
+    #[serde(default)]
+    pub storage_fallback_clip: String,
+    #[serde(default)]
+    pub storage_slate: String,
+    #[serde(default)]
+    pub storage_slate_audio: String,
+    #[serde(default)]
+    pub storage_bumper_in: String,
+    #[serde(default)]
+    pub storage_bumper_out: String,
+    #[serde(default)]
+    pub storage_upload_ignore: String,
+    #[serde(default)]
+    pub storage_upload_min_age: u32,
+    #[serde(default)]
+    pub storage_incoming_url: String,
+    #[serde(default)]
+    pub storage_incoming_path: String,
+    #[serde(default)]
+    pub storage_incoming_interval: u32,
+    #[serde(default)]
+    pub storage_transcode: bool,
+    #[serde(default)]
+    pub storage_transcode_workers: u32,
+    #[serde(default)]
+    pub storage_transcode_loudness: f64,
+    #[serde(default)]
+    pub storage_separation: f64,
+    #[serde(default)]
+    pub storage_incoming_delete: bool,
 
     pub text_add: bool,
     pub text_from_filename: bool,
     pub text_font: String,
     pub text_style: String,
     pub text_regex: String,
+    #[serde(default)]
+    pub text_ticker_url: String,
+    #[serde(default)]
+    pub text_ticker_interval: u32,
+    #[serde(default)]
+    pub text_ticker_speed: u32,
+    #[serde(default)]
+    pub text_ticker_separator: String,
+    #[serde(default)]
+    pub text_ticker_hours: String,
+    #[serde(default)]
+    pub text_ticker_style: String,
 
     pub task_enable: bool,
     pub task_path: String,
+    #[serde(default)]
+    pub task_icecast_url: String,
+    #[serde(default)]
+    pub task_webhook_url: String,
+    #[serde(default)]
+    pub task_hook_clip_start: String,
+    #[serde(default)]
+    pub task_hook_clip_end: String,
+    #[serde(default)]
+    pub task_hook_ingest_start: String,
+    #[serde(default)]
+    pub task_hook_ingest_stop: String,
+    #[serde(default)]
+    pub task_hook_error: String,
+    #[serde(default)]
+    pub task_hook_day_change: String,
+    #[serde(default)]
+    pub task_script: String,
+    #[serde(default)]
+    pub task_trigger_device: String,
+    #[serde(default)]
+    pub task_trigger_debounce: u32,
+    #[serde(default)]
+    pub task_trigger_map: String,
+    #[serde(default)]
+    pub task_vdcp_address: String,
+    #[serde(default)]
+    pub task_companion_address: String,
+    #[serde(default)]
+    pub task_osc_address: String,
+    #[serde(default)]
+    pub task_osc_feedback: String,
+    #[serde(default)]
+    pub task_redundancy_role: String,
+    #[serde(default)]
+    pub task_redundancy_address: String,
+    #[serde(default)]
+    pub task_redundancy_peer: String,
+    #[serde(default)]
+    pub task_redundancy_timeout: u32,
 
     pub output_id: i32,
+    #[serde(default)]
+    pub output_audio_url: String,
+    #[serde(default)]
+    pub output_audio_param: String,
+    #[serde(default)]
+    pub output_tags: String,
+    #[serde(default)]
+    pub output_compliance_path: String,
+    #[serde(default)]
+    pub output_compliance_days: u32,
+    #[serde(default)]
+    pub output_compliance_param: String,
+    #[serde(default)]
+    pub output_check_url: String,
+    #[serde(default)]
+    pub output_check_interval: u32,
+    #[serde(default)]
+    pub output_languages: String,
 }
 
 impl Configuration {
@@ -355,6 +550,16 @@ impl Configuration {
             id,
             channel_id,
             general_stop_threshold: config.general.stop_threshold,
+            general_nice: config.general.nice,
+            general_cgroup: config.general.cgroup,
+            general_threads: config.general.threads,
+            general_stall_timeout: config.general.stall_timeout,
+            general_quarantine: config.general.quarantine,
+            general_decoder_resume: config.general.decoder_resume,
+            general_slate_errors: config.general.slate_errors,
+            general_buffer_latency: config.general.buffer_latency,
+            general_probe_workers: config.general.probe_workers,
+            general_probe_timeout: config.general.probe_timeout,
             mail_subject: config.mail.subject,
             mail_recipient: config.mail.recipient,
             mail_level: config.mail.mail_level.to_string(),
@@ -383,24 +588,107 @@ impl Configuration {
             processing_filter: config.processing.custom_filter,
             processing_override_filter: config.processing.override_filter,
             processing_vtt_enable: config.processing.vtt_enable,
+            processing_audio_meter: config.processing.audio_meter,
+            processing_fps_policy: config.processing.fps_policy.to_string(),
+            processing_fps_tolerance: config.processing.fps_tolerance,
+            processing_tonemap: config.processing.tonemap,
+            processing_tonemap_algorithm: config.processing.tonemap_algorithm,
+            processing_tonemap_desat: config.processing.tonemap_desat,
+            processing_tonemap_peak: config.processing.tonemap_peak,
+            processing_timecode_burn_in: config.processing.timecode_burn_in,
+            processing_timecode_embed: config.processing.timecode_embed,
+            processing_ltc_channel: config.processing.ltc_channel,
+            processing_live_control: config.processing.live_control,
+            processing_av_sync_tolerance: config.processing.av_sync_tolerance,
+            processing_audio_chains: config.processing.audio_chains,
+            processing_audio_dayparts: config.processing.audio_dayparts,
+            processing_hls_id3: config.processing.hls_id3,
+            processing_captions: config.processing.captions,
+            processing_watermark: config.processing.watermark,
+            processing_watermark_latency: config.processing.watermark_latency,
+            processing_audio_languages: config.processing.audio_languages,
+            processing_description_track: config.processing.description_track,
             processing_vtt_dummy: config.processing.vtt_dummy,
             ingest_enable: config.ingest.enable,
             ingest_param: config.ingest.input_param,
             ingest_filter: config.ingest.custom_filter,
+            ingest_preview: config.ingest.preview,
+            ingest_record: config.ingest.record,
+            ingest_record_path: config.ingest.record_path,
             playlist_day_start: config.playlist.day_start,
             playlist_length: config.playlist.length,
             playlist_infinit: config.playlist.infinit,
+            playlist_recovery: config.playlist.recovery.to_string(),
+            playlist_recovery_speed: config.playlist.recovery_speed,
+            playlist_slot_fit: config.playlist.slot_fit,
+            playlist_next_card: config.playlist.next_card,
+            playlist_read_ahead: config.playlist.read_ahead,
+            playlist_join: config.playlist.join.to_string(),
             storage_filler: config.storage.filler,
             storage_extensions: config.storage.extensions.join(";"),
             storage_shuffle: config.storage.shuffle,
+            storage_vod_path: config.storage.vod_path,
+            storage_vod_template: config.storage.vod_template,
+            storage_vod_hook: config.storage.vod_hook,
+            storage_fallback: config.storage.fallback.join(";"),
+            storage_fallback_clip: config.storage.fallback_clip,
+            storage_slate: config.storage.slate,
+            storage_slate_audio: config.storage.slate_audio,
+            storage_bumper_in: config.storage.bumper_in,
+            storage_bumper_out: config.storage.bumper_out,
+            storage_upload_ignore: config.storage.upload_ignore,
+            storage_upload_min_age: config.storage.upload_min_age,
+            storage_incoming_url: config.storage.incoming_url,
+            storage_incoming_path: config.storage.incoming_path,
+            storage_incoming_interval: config.storage.incoming_interval,
+            storage_transcode: config.storage.transcode,
+            storage_transcode_workers: config.storage.transcode_workers,
+            storage_transcode_loudness: config.storage.transcode_loudness,
+            storage_separation: config.storage.separation,
+            storage_incoming_delete: config.storage.incoming_delete,
             text_add: config.text.add_text,
             text_font: config.text.font,
             text_from_filename: config.text.text_from_filename,
             text_style: config.text.style,
             text_regex: config.text.regex,
+            text_ticker_url: config.text.ticker_url,
+            text_ticker_interval: config.text.ticker_interval,
+            text_ticker_speed: config.text.ticker_speed,
+            text_ticker_separator: config.text.ticker_separator,
+            text_ticker_hours: config.text.ticker_hours,
+            text_ticker_style: config.text.ticker_style,
             task_enable: config.task.enable,
             task_path: config.task.path.to_string_lossy().to_string(),
+            task_icecast_url: config.task.icecast_url,
+            task_webhook_url: config.task.webhook_url,
+            task_hook_clip_start: config.task.hook_clip_start,
+            task_hook_clip_end: config.task.hook_clip_end,
+            task_hook_ingest_start: config.task.hook_ingest_start,
+            task_hook_ingest_stop: config.task.hook_ingest_stop,
+            task_hook_error: config.task.hook_error,
+            task_hook_day_change: config.task.hook_day_change,
+            task_script: config.task.script.to_string_lossy().to_string(),
+            task_trigger_device: config.task.trigger_device,
+            task_trigger_debounce: config.task.trigger_debounce,
+            task_trigger_map: config.task.trigger_map,
+            task_vdcp_address: config.task.vdcp_address,
+            task_companion_address: config.task.companion_address,
+            task_osc_address: config.task.osc_address,
+            task_osc_feedback: config.task.osc_feedback,
+            task_redundancy_role: config.task.redundancy_role,
+            task_redundancy_address: config.task.redundancy_address,
+            task_redundancy_peer: config.task.redundancy_peer,
+            task_redundancy_timeout: config.task.redundancy_timeout,
             output_id: config.output.id,
+            output_audio_url: config.output.audio_url,
+            output_audio_param: config.output.audio_param,
+            output_tags: config.output.tags,
+            output_compliance_path: config.output.compliance_path,
+            output_compliance_days: config.output.compliance_days,
+            output_compliance_param: config.output.compliance_param,
+            output_check_url: config.output.check_url,
+            output_check_interval: config.output.check_interval,
+            output_languages: config.output.languages,
         }
     }
 }
@@ -411,6 +699,8 @@ pub struct Output {
     pub channel_id: i32,
     pub name: String,
     pub parameters: String,
+    #[serde(default)]
+    pub field_order: String,
 }
 
 impl Output {
@@ -420,6 +710,7 @@ impl Output {
             channel_id,
             name: mode.to_string(),
             parameters,
+            field_order: FieldOrder::Progressive.to_string(),
         }
     }
 }
@@ -466,4 +757,36 @@ pub struct AdvancedConfiguration {
     pub filter_volume: Option<String>,
     pub filter_split: Option<String>,
     pub name: Option<String>,
+    #[serde(default)]
+    pub filter_hw_accel: Option<String>,
+}
+
+/// Played seconds and errors of a category on a day, the date is the playlist date.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, sqlx::FromRow)]
+pub struct StatEntry {
+    pub date: String,
+    pub category: String,
+    pub seconds: f64,
+    pub errors: i64,
+}
+
+/// Entry of the media library index, the path is relative to the channel storage.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, sqlx::FromRow)]
+pub struct MediaEntry {
+    #[sqlx(default)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub channel_id: i32,
+    pub path: String,
+    pub size: i64,
+    /// Modification time as unix timestamp.
+    pub modified: i64,
+    pub duration: f64,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    /// Integrated loudness in LUFS.
+    pub loudness: Option<f64>,
+    pub last_aired: Option<String>,
 }
@@ -0,0 +1,269 @@
+/// Incoming Media
+///
+/// Poll a FTP or SFTP drop folder and move new media into the storage. A file is transferred,
+/// when its size has not changed between two polls, so uploads in progress are left alone.
+/// The transfer goes to a hidden `.part` file first and gets renamed when it is complete,
+/// that way the folder watcher sees only finished files. With `storage.transcode` the file
+/// goes to the [transcoder](super::transcoder) instead.
+///
+/// Listing, transfer and delete run over `curl`, which must be installed with SFTP support
+/// for `sftp://` URLs.
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use log::*;
+use reqwest::Url;
+use tokio::{fs, process::Command};
+
+use crate::file::{
+    backend::uri_encode,
+    norm_abs_path,
+    transcoder::{Transcoder, part_path, target_path},
+};
+use crate::player::utils::include_file_extension;
+use crate::utils::{config::PlayoutConfig, errors::ServiceError, logging::Target};
+use crate::vec_strings;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RemoteFile {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Folder for incoming media, relative paths are inside the storage.
+pub fn incoming_folder(config: &PlayoutConfig) -> PathBuf {
+    norm_abs_path(&config.channel.storage, &config.storage.incoming_path)
+        .map(|(path, _, _)| path)
+        .unwrap_or_else(|_| config.channel.storage.join("incoming"))
+}
+
+/// URL without password, for logging.
+fn display_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut u) => {
+            let _ = u.set_password(None);
+            u.to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Path part of the URL, as it is written, for the remote delete command.
+fn remote_path(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, r)| r);
+
+    rest.find('/')
+        .map_or(String::new(), |i| rest[i..].to_string())
+}
+
+/// Parse a directory listing from `curl`, in Unix `ls -l` or in DOS style.
+///
+/// Only regular files are returned.
+pub fn parse_listing(listing: &str) -> Vec<RemoteFile> {
+    let mut files = vec![];
+
+    for line in listing.lines() {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+
+        // DOS style: 06-01-24  10:00AM  12345 clip.mp4
+        if fields.len() >= 4 && fields[0].len() == 8 && fields[0].chars().nth(2) == Some('-') {
+            if let Ok(size) = fields[2].parse() {
+                files.push(RemoteFile {
+                    name: fields[3..].join(" "),
+                    size,
+                });
+            }
+
+            continue;
+        }
+
+        // Unix style: -rw-r--r-- 1 user group 12345 Jun 01 10:00 clip.mp4
+        if fields.len() >= 9
+            && fields[0].starts_with('-')
+            && let Ok(size) = fields[4].parse()
+        {
+            files.push(RemoteFile {
+                name: fields[8..].join(" "),
+                size,
+            });
+        }
+    }
+
+    files
+}
+
+/// Path in the storage, transcoded files get the mp4 extension.
+pub fn target(config: &PlayoutConfig, folder: &Path, name: &str) -> PathBuf {
+    if config.storage.transcode {
+        target_path(&folder.join(name))
+    } else {
+        folder.join(name)
+    }
+}
+
+/// Files, which have the same size as in the poll before.
+pub fn stable_files(previous: &HashMap<String, u64>, current: &[RemoteFile]) -> Vec<RemoteFile> {
+    current
+        .iter()
+        .filter(|f| f.size > 0 && previous.get(&f.name) == Some(&f.size))
+        .cloned()
+        .collect()
+}
+
+async fn curl(args: &[String]) -> Result<Vec<u8>, ServiceError> {
+    let output = Command::new("curl")
+        .args(["-s", "-S", "--fail"])
+        .args(args)
+        .kill_on_drop(true)
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(ServiceError::ServiceUnavailable(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+async fn list(url: &str) -> Result<Vec<RemoteFile>, ServiceError> {
+    let listing = curl(&[url.to_string()]).await?;
+
+    Ok(parse_listing(&String::from_utf8_lossy(&listing)))
+}
+
+async fn delete(url: &str, name: &str) -> Result<(), ServiceError> {
+    let path = format!("{}{name}", remote_path(url));
+    let quote = if url.starts_with("sftp://") {
+        format!("rm \"{path}\"")
+    } else {
+        format!("DELE {}", path.trim_start_matches('/'))
+    };
+
+    curl(&vec_strings!["--list-only", "-Q", quote, url]).await?;
+
+    Ok(())
+}
+
+/// Transfer a file from the drop folder, with transcoding it goes to the queue afterwards.
+async fn transfer(
+    config: &PlayoutConfig,
+    transcoder: &Transcoder,
+    url: &str,
+    folder: &Path,
+    file: &RemoteFile,
+) -> Result<PathBuf, ServiceError> {
+    let source = folder.join(&file.name);
+    let part = part_path(&source);
+
+    curl(&vec_strings![
+        "-o",
+        part.to_string_lossy(),
+        format!("{url}{}", uri_encode(&file.name, true))
+    ])
+    .await?;
+
+    if config.storage.transcode {
+        transcoder.submit(config.clone(), source.clone()).await;
+    } else {
+        fs::rename(&part, &source).await?;
+    }
+
+    Ok(source)
+}
+
+/// Poll the drop folder until the channel stops.
+pub async fn watch(config: PlayoutConfig, is_alive: Arc<AtomicBool>, transcoder: Transcoder) {
+    let id = config.general.channel_id;
+    let mut url = config.storage.incoming_url.trim().to_string();
+    let folder = incoming_folder(&config);
+    let interval = Duration::from_secs(config.storage.incoming_interval.max(5) as u64);
+    let mut previous = HashMap::new();
+    let mut done = HashSet::new();
+
+    if !url.ends_with('/') {
+        url.push('/');
+    }
+
+    if let Err(e) = fs::create_dir_all(&folder).await {
+        error!(target: Target::file_mail(), channel = id; "Incoming folder: {e}");
+        return;
+    }
+
+    debug!(target: Target::file_mail(), channel = id;
+        "Watch drop folder: <span class=\"log-addr\">{}</span>",
+        display_url(&url)
+    );
+
+    while is_alive.load(Ordering::SeqCst) {
+        match list(&url).await {
+            Ok(files) => {
+                let files = files
+                    .into_iter()
+                    .filter(|f| include_file_extension(&config, Path::new(&f.name)))
+                    .collect::<Vec<_>>();
+
+                for file in stable_files(&previous, &files) {
+                    if !is_alive.load(Ordering::SeqCst) {
+                        break;
+                    }
+
+                    if done.contains(&file) {
+                        continue;
+                    }
+
+                    if target(&config, &folder, &file.name).exists() {
+                        done.insert(file);
+                        continue;
+                    }
+
+                    match transfer(&config, &transcoder, &url, &folder, &file).await {
+                        Ok(target) => {
+                            info!(target: Target::file_mail(), channel = id;
+                                "Incoming media: <span class=\"log-addr\">{}</span>",
+                                target.display()
+                            );
+
+                            if config.storage.incoming_delete
+                                && let Err(e) = delete(&url, &file.name).await
+                            {
+                                error!(target: Target::file_mail(), channel = id;
+                                    "Delete <span class=\"log-addr\">{}</span> from drop folder failed: {e}",
+                                    file.name
+                                );
+                            }
+
+                            done.insert(file);
+                        }
+                        Err(e) => {
+                            error!(target: Target::file_mail(), channel = id;
+                                "Transfer <span class=\"log-addr\">{}</span> failed: {e}",
+                                file.name
+                            );
+                        }
+                    }
+                }
+
+                previous = files.into_iter().map(|f| (f.name, f.size)).collect();
+            }
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = id;
+                    "Drop folder <span class=\"log-addr\">{}</span>: {e}",
+                    display_url(&url)
+                );
+            }
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
@@ -20,7 +20,10 @@ use tokio::{
 };
 use tokio_stream::StreamExt;
 
-use crate::file::{MoveObject, PathObject, VideoFile, norm_abs_path, watcher::watch};
+use crate::file::{
+    MoveObject, PathObject, VideoFile, incoming, norm_abs_path, transcoder::Transcoder,
+    watcher::watch,
+};
 use crate::player::utils::{Media, file_extension, include_file_extension, probe::MediaProbe};
 use crate::utils::{config::PlayoutConfig, errors::ServiceError, logging::Target};
 
@@ -29,6 +32,7 @@ pub struct LocalStorage {
     pub root: Arc<RwLock<PathBuf>>,
     pub extensions: Arc<RwLock<Vec<String>>>,
     pub watch_handler: Arc<Mutex<Option<JoinHandle<()>>>>,
+    pub incoming_handler: Arc<Mutex<Option<JoinHandle<()>>>>,
 }
 
 impl LocalStorage {
@@ -43,6 +47,7 @@ impl LocalStorage {
             root: Arc::new(RwLock::new(root)),
             extensions: Arc::new(RwLock::new(extensions)),
             watch_handler: Arc::new(Mutex::new(None)),
+            incoming_handler: Arc::new(Mutex::new(None)),
         }
     }
 }
@@ -50,12 +55,13 @@ impl LocalStorage {
 impl Drop for LocalStorage {
     fn drop(&mut self) {
         let watch_handler = self.watch_handler.clone();
+        let incoming_handler = self.incoming_handler.clone();
 
         tokio::spawn(async move {
-            let mut watch_handler = watch_handler.lock().await;
-
-            if let Some(handler) = watch_handler.as_mut() {
-                handler.abort();
+            for handler in [watch_handler, incoming_handler] {
+                if let Some(handler) = handler.lock().await.as_mut() {
+                    handler.abort();
+                }
             }
         });
     }
@@ -247,7 +253,9 @@ impl LocalStorage {
         mut data: Multipart,
         path: &Path,
         is_abs: bool,
-    ) -> Result<(), ServiceError> {
+    ) -> Result<Vec<PathBuf>, ServiceError> {
+        let mut paths = vec![];
+
         while let Some(mut field) = data.try_next().await? {
             let content_disposition = field.content_disposition().ok_or("No content")?;
             debug!("{content_disposition}");
@@ -297,8 +305,11 @@ impl LocalStorage {
                     }
                 }
             }
+
+            paths.push(filepath);
         }
-        Ok(())
+
+        Ok(paths)
     }
 
     pub async fn watchman(
@@ -322,6 +333,28 @@ impl LocalStorage {
         }
     }
 
+    /// Poll the drop folder for incoming media.
+    pub async fn incoming(
+        &self,
+        config: PlayoutConfig,
+        is_alive: Arc<AtomicBool>,
+        transcoder: Transcoder,
+    ) {
+        if let Some(old_handle) = self.incoming_handler.lock().await.take() {
+            old_handle.abort();
+        }
+
+        let handle = tokio::spawn(incoming::watch(config, is_alive, transcoder));
+
+        *self.incoming_handler.lock().await = Some(handle);
+    }
+
+    pub async fn stop_incoming(&self) {
+        if let Some(handle) = self.incoming_handler.lock().await.take() {
+            handle.abort();
+        }
+    }
+
     pub async fn fill_filler_list(
         &self,
         config: &PlayoutConfig,
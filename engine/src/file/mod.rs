@@ -3,8 +3,11 @@ use std::path::{Path, PathBuf};
 use relative_path::RelativePath;
 use serde::{Deserialize, Serialize};
 
+pub mod backend;
+pub mod incoming;
 pub mod local;
-mod watcher;
+pub mod transcoder;
+pub mod watcher;
 
 use crate::utils::errors::ServiceError;
 use local::LocalStorage;
@@ -0,0 +1,367 @@
+/// Transcoder
+///
+/// New media (uploads and files from the drop folder) can be normalized to the house format,
+/// before they are eligible for playout: scaled and padded to the processing resolution and
+/// frame rate, H.264 video, AAC audio and, when a target is set, loudness normalized.
+///
+/// While a file waits or runs, it has a hidden `.part` name, so the folder watcher and the
+/// media browser ignore it. The result appears under its final name, when it is complete.
+/// At most `storage.transcode_workers` jobs run at once, the job list with progress
+/// is sent over the `transcode` event endpoint.
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use log::*;
+use serde::Serialize;
+use tokio::{
+    fs,
+    io::{AsyncBufReadExt, AsyncReadExt, BufReader},
+    process::Command,
+    sync::{Mutex, Notify},
+};
+
+use crate::player::process::ResourceLimits;
+use crate::player::utils::{add_thread_limit, ffmpeg_bin, probe::MediaProbe};
+use crate::utils::{
+    config::PlayoutConfig,
+    errors::ServiceError,
+    logging::{Target, fmt_cmd},
+};
+use crate::vec_strings;
+
+pub const TRANSCODE_EXTENSION: &str = "mp4";
+const PART_EXTENSION: &str = "part";
+const OUTPUT_EXTENSION: &str = "transcode";
+/// Finished jobs, which are kept in the list.
+const KEEP_FINISHED: usize = 20;
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct TranscodeJob {
+    pub id: usize,
+    pub name: String,
+    pub status: JobStatus,
+    /// Progress in percent.
+    pub progress: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Hidden name of a file, which waits for the transcoder.
+pub fn part_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    path.with_file_name(format!(".{name}.{PART_EXTENSION}"))
+}
+
+/// Final path of a transcoded file.
+pub fn target_path(path: &Path) -> PathBuf {
+    path.with_extension(TRANSCODE_EXTENSION)
+}
+
+/// Transcode command to the house format.
+pub fn transcode_cmd(config: &PlayoutConfig, input: &Path, target: &Path) -> Vec<String> {
+    let mut cmd = vec_strings![
+        "-hide_banner",
+        "-nostats",
+        "-v",
+        "level+error",
+        "-progress",
+        "pipe:1",
+        "-i",
+        input.to_string_lossy()
+    ];
+
+    if config.processing.audio_only {
+        cmd.push("-vn".to_string());
+    } else {
+        let (w, h) = (config.processing.width, config.processing.height);
+
+        cmd.append(&mut vec_strings![
+            "-vf",
+            format!(
+                "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={}",
+                config.processing.fps
+            ),
+            "-c:v",
+            "libx264",
+            "-crf",
+            "20",
+            "-preset",
+            "medium",
+            "-pix_fmt",
+            "yuv420p"
+        ]);
+    }
+
+    if config.storage.transcode_loudness < 0.0 {
+        cmd.append(&mut vec_strings![
+            "-af",
+            format!(
+                "loudnorm=I={}:TP=-1.5:LRA=11",
+                config.storage.transcode_loudness
+            )
+        ]);
+    }
+
+    cmd.append(&mut vec_strings![
+        "-c:a",
+        "aac",
+        "-b:a",
+        "192k",
+        "-ar",
+        "48000",
+        "-ac",
+        config.processing.audio_channels,
+        "-movflags",
+        "+faststart",
+        "-f",
+        "mp4",
+        "-y",
+        target.to_string_lossy()
+    ]);
+
+    add_thread_limit(config, &mut cmd);
+
+    cmd
+}
+
+/// Progress in percent from a line of `-progress` output.
+pub fn parse_progress(line: &str, duration: f64) -> Option<f64> {
+    let time = line
+        .strip_prefix("out_time_us=")?
+        .trim()
+        .parse::<f64>()
+        .ok()?;
+
+    if duration <= 0.0 {
+        return None;
+    }
+
+    Some(((time / 10_000.0 / duration).clamp(0.0, 100.0) * 10.0).round() / 10.0)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Transcoder {
+    jobs: Arc<Mutex<Vec<TranscodeJob>>>,
+    next_id: Arc<AtomicUsize>,
+    notify: Arc<Notify>,
+}
+
+impl Transcoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn jobs(&self) -> Vec<TranscodeJob> {
+        self.jobs.lock().await.clone()
+    }
+
+    /// Hide a new file from playout, until `submit` has done its work.
+    pub async fn stage(path: &Path) -> Result<(), std::io::Error> {
+        fs::rename(path, part_path(path)).await
+    }
+
+    /// Queue a staged file, the source path is the name it had before.
+    pub async fn submit(&self, config: PlayoutConfig, source: PathBuf) -> usize {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let mut jobs = self.jobs.lock().await;
+        let finished = jobs
+            .iter()
+            .filter(|j| matches!(j.status, JobStatus::Done | JobStatus::Failed))
+            .count();
+
+        if finished > KEEP_FINISHED {
+            let mut skip = finished - KEEP_FINISHED;
+
+            jobs.retain(|j| {
+                if skip > 0 && matches!(j.status, JobStatus::Done | JobStatus::Failed) {
+                    skip -= 1;
+                    return false;
+                }
+
+                true
+            });
+        }
+
+        jobs.push(TranscodeJob {
+            id,
+            name: source
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            status: JobStatus::Queued,
+            progress: 0.0,
+            error: None,
+        });
+
+        tokio::spawn(self.clone().run(config, id, source));
+
+        id
+    }
+
+    async fn update(&self, id: usize, status: JobStatus, progress: f64, error: Option<String>) {
+        if let Some(job) = self.jobs.lock().await.iter_mut().find(|j| j.id == id) {
+            job.status = status;
+            job.progress = progress;
+            job.error = error;
+        }
+    }
+
+    /// Wait for a free worker and mark the job as running.
+    async fn acquire(&self, id: usize, workers: usize) {
+        loop {
+            let notified = self.notify.notified();
+
+            {
+                let mut jobs = self.jobs.lock().await;
+                let running = jobs
+                    .iter()
+                    .filter(|j| j.status == JobStatus::Running)
+                    .count();
+                let first_queued = jobs.iter().find(|j| j.status == JobStatus::Queued);
+
+                if running < workers
+                    && first_queued.is_some_and(|j| j.id == id)
+                    && let Some(job) = jobs.iter_mut().find(|j| j.id == id)
+                {
+                    job.status = JobStatus::Running;
+                    self.notify.notify_waiters();
+
+                    return;
+                }
+            }
+
+            notified.await;
+        }
+    }
+
+    async fn run(self, config: PlayoutConfig, id: usize, source: PathBuf) {
+        let channel = config.general.channel_id;
+
+        self.acquire(id, config.storage.transcode_workers.max(1) as usize)
+            .await;
+
+        match self.transcode(&config, id, &source).await {
+            Ok(target) => {
+                self.update(id, JobStatus::Done, 100.0, None).await;
+
+                info!(target: Target::file_mail(), channel = channel;
+                    "Transcode done: <span class=\"log-addr\">{}</span>",
+                    target.display()
+                );
+            }
+            Err(e) => {
+                let progress = self
+                    .jobs
+                    .lock()
+                    .await
+                    .iter()
+                    .find(|j| j.id == id)
+                    .map_or(0.0, |j| j.progress);
+
+                self.update(id, JobStatus::Failed, progress, Some(e.to_string()))
+                    .await;
+
+                // the original stays available under its old name
+                if let Err(e) = fs::rename(part_path(&source), &source).await {
+                    error!(target: Target::file_mail(), channel = channel; "Restore <span class=\"log-addr\">{}</span>: {e}", source.display());
+                }
+
+                error!(target: Target::file_mail(), channel = channel;
+                    "Transcode <span class=\"log-addr\">{}</span> failed: {e}",
+                    source.display()
+                );
+            }
+        }
+
+        self.notify.notify_waiters();
+    }
+
+    async fn transcode(
+        &self,
+        config: &PlayoutConfig,
+        id: usize,
+        source: &Path,
+    ) -> Result<PathBuf, ServiceError> {
+        let staged = part_path(source);
+        let target = target_path(source);
+        let output = part_path(&target).with_extension(OUTPUT_EXTENSION);
+
+        if target.exists() && target != source {
+            return Err(ServiceError::Conflict(format!(
+                "Target already exists: {}",
+                target.display()
+            )));
+        }
+
+        let duration = MediaProbe::new(&staged)
+            .await
+            .map(MediaProbe::format_duration)
+            .unwrap_or_default();
+        let cmd = transcode_cmd(config, &staged, &output);
+
+        debug!(target: Target::file_mail(), channel = config.general.channel_id;
+            "Transcode CMD: <span class=\"log-cmd\">ffmpeg {}</span>",
+            fmt_cmd(&cmd)
+        );
+
+        let mut proc = ResourceLimits::new(config).spawn(
+            Command::new(ffmpeg_bin())
+                .args(cmd)
+                .kill_on_drop(true)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped()),
+        )?;
+
+        let mut stderr = proc.stderr.take();
+        let stderr_task = tokio::spawn(async move {
+            let mut buffer = String::new();
+
+            if let Some(stderr) = stderr.as_mut() {
+                let _ = stderr.read_to_string(&mut buffer).await;
+            }
+
+            buffer
+        });
+
+        if let Some(stdout) = proc.stdout.take() {
+            let mut lines = BufReader::new(stdout).lines();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                if let Some(progress) = parse_progress(&line, duration) {
+                    self.update(id, JobStatus::Running, progress, None).await;
+                }
+            }
+        }
+
+        let status = proc.wait().await?;
+        let stderr = stderr_task.await.unwrap_or_default();
+
+        if !status.success() {
+            let _ = fs::remove_file(&output).await;
+
+            return Err(ServiceError::Conflict(stderr.trim().to_string()));
+        }
+
+        fs::rename(&output, &target).await?;
+        fs::remove_file(&staged).await?;
+
+        Ok(target)
+    }
+}
@@ -1,35 +1,307 @@
 use std::{
-    path::Path,
+    collections::HashMap,
+    path::{Path, PathBuf},
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
-        mpsc::channel,
     },
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
 
+use async_walkdir::WalkDir;
 use log::*;
 use notify::{
+    Event,
     EventKind::{Create, Modify, Remove},
     RecursiveMode,
-    event::{CreateKind, ModifyKind, RemoveKind, RenameMode},
+    event::{ModifyKind, RenameMode},
 };
-use notify_debouncer_full::new_debouncer;
-use tokio::sync::Mutex;
+use notify_debouncer_full::{DebounceEventResult, new_debouncer};
+use tokio::{
+    sync::{Mutex, mpsc},
+    time::timeout,
+};
+use tokio_stream::StreamExt;
 
-use crate::player::utils::{Media, include_file_extension};
+use crate::player::utils::{Media, include_file_extension, upload_ignored};
 use crate::utils::{config::PlayoutConfig, logging::Target};
 
+/// Time, in which file events are collected to one.
+const EVENT_DEBOUNCE: Duration = Duration::from_millis(500);
+/// How often pending files get checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Time without change of size and modification time, before a new file gets added.
+pub const STABLE_TIME: Duration = Duration::from_secs(2);
+
+type FileStat = (u64, SystemTime);
+
+/// New files, which get added when they are completely written.
+#[derive(Debug)]
+pub struct PendingFiles {
+    files: HashMap<PathBuf, (Option<FileStat>, Instant)>,
+    stable_time: Duration,
+}
+
+impl Default for PendingFiles {
+    fn default() -> Self {
+        Self {
+            files: HashMap::new(),
+            stable_time: STABLE_TIME,
+        }
+    }
+}
+
+impl PendingFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait longer than [`STABLE_TIME`] for files without change, shorter times are ignored.
+    pub fn with_stable_time(stable_time: Duration) -> Self {
+        Self {
+            files: HashMap::new(),
+            stable_time: stable_time.max(STABLE_TIME),
+        }
+    }
+
+    /// The file is new or changed, wait again for it.
+    pub fn touch(&mut self, path: &Path, now: Instant) {
+        self.files.insert(path.to_path_buf(), (None, now));
+    }
+
+    pub fn remove(&mut self, path: &Path) -> bool {
+        self.files.remove(path).is_some()
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Files, which have not changed for the stable time, they are taken from the list.
+    /// Files, which are gone, get dropped.
+    pub fn stable(
+        &mut self,
+        now: Instant,
+        stat: impl Fn(&Path) -> Option<FileStat>,
+    ) -> Vec<PathBuf> {
+        let mut ready = vec![];
+
+        let stable_time = self.stable_time;
+
+        self.files.retain(|path, (last, since)| {
+            let Some(current) = stat(path) else {
+                return false;
+            };
+
+            if *last != Some(current) {
+                *last = Some(current);
+                *since = now;
+            } else if current.0 > 0 && now.duration_since(*since) >= stable_time {
+                ready.push(path.clone());
+                return false;
+            }
+
+            true
+        });
+
+        ready.sort();
+
+        ready
+    }
+}
+
+fn file_stat(path: &Path) -> Option<FileStat> {
+    let meta = path.metadata().ok().filter(std::fs::Metadata::is_file)?;
+
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+/// Files in a folder, which was moved into the watched folder.
+async fn folder_files(folder: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let mut entries = WalkDir::new(folder);
+
+    while let Some(Ok(entry)) = entries.next().await {
+        if entry.path().is_file() {
+            files.push(entry.path());
+        }
+    }
+
+    files
+}
+
+struct Watcher {
+    id: i32,
+    config: PlayoutConfig,
+    sources: Arc<Mutex<Vec<Media>>>,
+    pending: PendingFiles,
+}
+
+impl Watcher {
+    async fn created(&mut self, path: &Path) {
+        if path.is_dir() {
+            for file in folder_files(path).await {
+                Box::pin(self.created(&file)).await;
+            }
+        } else if path.is_file()
+            && include_file_extension(&self.config, path)
+            && !upload_ignored(&self.config, path)
+            && !self
+                .sources
+                .lock()
+                .await
+                .iter()
+                .any(|m| Path::new(&m.source) == path)
+        {
+            self.pending.touch(path, Instant::now());
+        }
+    }
+
+    async fn removed(&mut self, path: &Path) {
+        if path.exists() {
+            return;
+        }
+
+        self.pending.remove(path);
+        self.pending
+            .files
+            .retain(|pending, _| !pending.starts_with(path));
+
+        let mut sources = self.sources.lock().await;
+        let count = sources.len();
+
+        sources.retain(|m| !Path::new(&m.source).starts_with(path));
+
+        if sources.len() != count {
+            for (i, media) in sources.iter_mut().enumerate() {
+                media.index = Some(i);
+            }
+
+            info!(target: Target::file_mail(), channel = self.id; "Remove file: <span class=\"log-addr\">{path:?}</span>");
+        }
+    }
+
+    async fn moved(&mut self, old_path: &Path, new_path: &Path) {
+        if self.pending.remove(old_path) {
+            self.created(new_path).await;
+            return;
+        }
+
+        let position = self
+            .sources
+            .lock()
+            .await
+            .iter()
+            .position(|m| Path::new(&m.source) == old_path);
+
+        match position {
+            Some(index)
+                if include_file_extension(&self.config, new_path)
+                    && !upload_ignored(&self.config, new_path) =>
+            {
+                // a move inside the folder, the file is complete
+                let media = Media::new(index, &new_path.to_string_lossy(), false).await;
+                self.sources.lock().await[index] = media;
+
+                info!(target: Target::file_mail(), channel = self.id;
+                    "Move file: <span class=\"log-addr\">{old_path:?}</span> to <span class=\"log-addr\">{new_path:?}</span>"
+                );
+            }
+            _ => {
+                self.removed(old_path).await;
+                self.created(new_path).await;
+            }
+        }
+    }
+
+    async fn event(&mut self, event: &Event) {
+        match (event.kind, event.paths.as_slice()) {
+            (Modify(ModifyKind::Name(RenameMode::Both)), [old_path, new_path]) => {
+                self.moved(old_path, new_path).await;
+            }
+            (Create(_) | Modify(ModifyKind::Name(RenameMode::To)), [path, ..]) => {
+                self.created(path).await;
+            }
+            (Modify(ModifyKind::Data(_) | ModifyKind::Any), [path, ..]) => {
+                // still written, wait again for the end
+                if self.pending.contains(path) {
+                    self.pending.touch(path, Instant::now());
+                }
+            }
+            (Remove(_) | Modify(ModifyKind::Name(RenameMode::From)), [path, ..]) => {
+                self.removed(path).await;
+            }
+            (Modify(ModifyKind::Name(_)), [path, ..]) => {
+                // rename without the other side, like from the polling backend
+                if path.exists() {
+                    self.created(path).await;
+                } else {
+                    self.removed(path).await;
+                }
+            }
+            _ => {
+                trace!("Not tracked file event: {event:?}");
+            }
+        }
+    }
+
+    /// Files, which were too young for the first scan of the folder, wait here.
+    async fn young_files(&mut self, folder: &Path) {
+        let min_age = Duration::from_secs(self.config.storage.upload_min_age as u64);
+
+        if min_age.is_zero() {
+            return;
+        }
+
+        for file in folder_files(folder).await {
+            let young = file_stat(&file)
+                .and_then(|(_, modified)| modified.elapsed().ok())
+                .is_none_or(|age| age < min_age + STABLE_TIME);
+
+            if young {
+                self.created(&file).await;
+            }
+        }
+    }
+
+    async fn add_stable(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        for path in self.pending.stable(Instant::now(), file_stat) {
+            let mut sources = self.sources.lock().await;
+
+            if sources.iter().any(|m| Path::new(&m.source) == path) {
+                continue;
+            }
+
+            let media = Media::new(sources.len(), &path.to_string_lossy(), false).await;
+
+            sources.push(media);
+            info!(target: Target::file_mail(), channel = self.id; "Create new file: <span class=\"log-addr\">{path:?}</span>");
+        }
+    }
+}
+
 /// Create a watcher, which monitor file changes.
 /// When a change is register, update the current file list.
 /// This makes it possible, to play infinitely and and always new files to it.
+///
+/// New files are added, when they are completely written: their size and modification
+/// time must stay the same for [`STABLE_TIME`], or `storage.upload_min_age` when it is longer.
+/// Files with a name from `storage.upload_ignore` wait for the rename to their final name.
 pub async fn watch(
     config: PlayoutConfig,
     is_alive: Arc<AtomicBool>,
     sources: Arc<Mutex<Vec<Media>>>,
 ) {
     let id = config.general.channel_id;
-    let path = Path::new(&config.channel.storage);
+    let path = config.channel.storage.clone();
 
     if !path.exists() {
         error!(target: Target::file_mail(), channel = id; "Folder path not exists: '{path:?}'");
@@ -37,81 +309,51 @@ pub async fn watch(
     }
 
     debug!(target: Target::file_mail(), channel = id;
-        "Monitor folder: <span class=\"log-addr\">{:?}</span>",
-        config.channel.storage
+        "Monitor folder: <span class=\"log-addr\">{path:?}</span>"
     );
 
-    let (tx, rx) = channel();
-    let mut debouncer = new_debouncer(Duration::from_secs(3), None, tx).unwrap();
-    debouncer.watch(path, RecursiveMode::Recursive).unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let debouncer = new_debouncer(EVENT_DEBOUNCE, None, move |result: DebounceEventResult| {
+        let _ = tx.send(result);
+    });
+
+    let mut debouncer = match debouncer {
+        Ok(debouncer) => debouncer,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Folder watcher: {e}");
+            return;
+        }
+    };
+
+    if let Err(e) = debouncer.watch(&path, RecursiveMode::Recursive) {
+        error!(target: Target::file_mail(), channel = id; "Monitor folder <span class=\"log-addr\">{path:?}</span>: {e}");
+        return;
+    }
+
+    let stable_time = Duration::from_secs(config.storage.upload_min_age as u64);
+    let mut watcher = Watcher {
+        id,
+        config,
+        sources,
+        pending: PendingFiles::with_stable_time(stable_time),
+    };
+
+    watcher.young_files(&path).await;
 
     while is_alive.load(Ordering::SeqCst) {
-        if let Ok(result) = rx.try_recv() {
-            match result {
-                Ok(events) => {
-                    let events: Vec<_> = events.to_vec();
-                    for event in events {
-                        match event.kind {
-                            Create(CreateKind::File) | Modify(ModifyKind::Name(RenameMode::To)) => {
-                                let new_path = &event.paths[0];
-
-                                if new_path.is_file() && include_file_extension(&config, new_path) {
-                                    let index = sources.lock().await.len();
-                                    let media =
-                                        Media::new(index, &new_path.to_string_lossy(), false).await;
-
-                                    sources.lock().await.push(media);
-                                    info!(target: Target::file_mail(), channel = id; "Create new file: <span class=\"log-addr\">{new_path:?}</span>");
-                                }
-                            }
-                            Remove(RemoveKind::File)
-                            | Modify(ModifyKind::Name(RenameMode::From)) => {
-                                let old_path = &event.paths[0];
-
-                                if !old_path.is_file() && include_file_extension(&config, old_path)
-                                {
-                                    sources
-                                        .lock()
-                                        .await
-                                        .retain(|x| x.source != old_path.to_string_lossy());
-                                    info!(target: Target::file_mail(), channel = id; "Remove file: <span class=\"log-addr\">{old_path:?}</span>");
-                                }
-                            }
-                            Modify(ModifyKind::Name(RenameMode::Both)) => {
-                                let old_path = &event.paths[0];
-                                let new_path = &event.paths[1];
-
-                                let mut media_list = sources.lock().await;
-
-                                if let Some(index) = media_list
-                                    .iter()
-                                    .position(|x| *x.source == old_path.display().to_string())
-                                {
-                                    let media =
-                                        Media::new(index, &new_path.to_string_lossy(), false).await;
-                                    media_list[index] = media;
-                                    info!(target: Target::file_mail(), channel = id; "Move file: <span class=\"log-addr\">{old_path:?}</span> to <span class=\"log-addr\">{new_path:?}</span>");
-                                } else if include_file_extension(&config, new_path) {
-                                    let index = media_list.len();
-                                    let media =
-                                        Media::new(index, &new_path.to_string_lossy(), false).await;
-
-                                    media_list.push(media);
-                                    info!(target: Target::file_mail(), channel = id; "Create new file: <span class=\"log-addr\">{new_path:?}</span>");
-                                }
-                            }
-                            _ => {
-                                trace!("Not tracked file event: {event:?}");
-                            }
-                        }
-                    }
+        match timeout(CHECK_INTERVAL, rx.recv()).await {
+            Ok(Some(Ok(events))) => {
+                for event in events {
+                    watcher.event(&event).await;
                 }
-                Err(errors) => errors.iter().for_each(
-                    |error| error!(target: Target::file_mail(), channel = id; "{error:?}"),
-                ),
             }
+            Ok(Some(Err(errors))) => errors
+                .iter()
+                .for_each(|error| error!(target: Target::file_mail(), channel = id; "{error:?}")),
+            Ok(None) => break,
+            Err(_) => {}
         }
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+        watcher.add_stable().await;
     }
 }
@@ -1,7 +1,10 @@
 use std::{
     collections::HashSet,
+    path::PathBuf,
+    process,
     sync::{Arc, atomic::AtomicBool},
     thread,
+    time::Duration,
 };
 
 use actix_web::{App, HttpServer, middleware::Logger, web};
@@ -17,6 +20,7 @@ use log::*;
 use tokio::{
     fs::File,
     io::AsyncReadExt,
+    signal::unix::{SignalKind, signal},
     sync::{Mutex, RwLock},
 };
 
@@ -25,18 +29,25 @@ use ffplayout::{
     api::routes::*,
     db::{db_drop, db_pool, handles, init_globales},
     player::{
-        controller::{ChannelController, ChannelManager},
-        utils::{JsonPlaylist, get_date, is_remote, json_validate::validate_playlist},
+        controller::{ChannelController, ChannelManager, set_stall_timeout},
+        utils::{
+            JsonPlaylist, get_date, is_remote, json_serializer::expand_loops,
+            json_validate::validate_playlist, set_binaries, warnings::PlaylistWarnings,
+        },
     },
     sse::{SseAuthState, broadcast::Broadcaster, routes::*},
     utils::{
         args_parse::init_args,
-        config::get_config,
+        as_run,
+        config::{PlayoutConfig, get_config, set_overrides},
+        error_report,
         errors::ProcessError,
-        logging::init_logging,
+        logging::{Target, init_logging},
         mail::{self, MailQueue},
         playlist::generate_playlist,
+        telemetry,
         time_machine::set_mock_time,
+        update,
     },
     validator,
 };
@@ -55,30 +66,92 @@ fn thread_counter() -> usize {
     (available_threads / 2).max(2)
 }
 
+/// In foreground mode nothing else stops the channels, so drain them here on SIGTERM and exit.
+fn shutdown_on_sigterm(controllers: Arc<RwLock<ChannelController>>) {
+    tokio::spawn(async move {
+        let Ok(mut sigterm) = signal(SignalKind::terminate()) else {
+            error!("Unable to listen for SIGTERM");
+            return;
+        };
+
+        sigterm.recv().await;
+        info!("Received SIGTERM, stop channels...");
+
+        let managers = controllers.read().await.managers.clone();
+
+        for manager in &managers {
+            if ARGS.drain {
+                manager.finish().await;
+            }
+
+            manager
+                .drain(Duration::from_secs(ARGS.shutdown_timeout))
+                .await;
+        }
+
+        process::exit(0);
+    });
+}
+
 #[tokio::main]
 async fn main() -> Result<(), ProcessError> {
     let mail_queues = Arc::new(Mutex::new(vec![]));
     let pool = db_pool().await?;
 
+    set_overrides(ARGS.overrides.as_deref().unwrap_or_default())?;
+    init_globales(&pool).await?;
+
+    // logger handle should be kept alive until the end,
+    // it starts before the arguments, to show warnings from imports and checks
+    let _logger = init_logging(mail_queues.clone());
+
     let mut init = init_args(&pool).await?;
 
-    if ARGS.init {
+    if ARGS.init || ARGS.check_config.is_some() {
         return Ok(());
     }
 
+    if ARGS.check_update {
+        return update::check_cli().await;
+    }
+
     set_mock_time(&ARGS.fake_time)?;
-    init_globales(&pool).await?;
+    set_stall_timeout(ARGS.stall_timeout);
 
-    // logger handle should be kept alive until the end
-    let _logger = init_logging(mail_queues.clone());
+    // the command line wins over the global settings
+    let global = handles::select_global(&pool).await?;
+    let binary = |path: &str| (!path.is_empty()).then(|| PathBuf::from(path));
+
+    set_binaries(
+        ARGS.ffmpeg.clone().or_else(|| binary(&global.ffmpeg)),
+        ARGS.ffprobe.clone().or_else(|| binary(&global.ffprobe)),
+    );
+
+    let _telemetry = telemetry::init(&ARGS.otlp_endpoint)?;
+    error_report::init(&ARGS.error_report, ARGS.error_sample_rate)?;
 
     let channel_controllers = Arc::new(RwLock::new(ChannelController::new()));
 
     if let Some(conn) = &ARGS.listen {
         let channels = handles::select_related_channels(&pool, None).await?;
 
-        for channel in channels.into_iter() {
-            let config = get_config(&pool, channel.id).await?;
+        for mut channel in channels.into_iter() {
+            let config = match get_config(&pool, channel.id).await {
+                Ok(config) => config,
+                Err(e) => {
+                    // keep the channel, so its config can be fixed in the frontend
+                    error!(target: Target::file_mail(), channel = channel.id; "Channel <span class=\"log-number\">{}</span> is not started: {e}", channel.id);
+                    channel.active = false;
+
+                    match PlayoutConfig::new(&pool, channel.id, None).await {
+                        Ok(config) => config,
+                        Err(e) => {
+                            error!("Skip channel {}: {e}", channel.id);
+                            continue;
+                        }
+                    }
+                }
+            };
             let m_queue = Arc::new(Mutex::new(MailQueue::new(channel.id, config.mail.clone())));
             let channel_active = channel.active;
             let manager = ChannelManager::new(pool.clone(), channel, config).await;
@@ -107,6 +180,14 @@ async fn main() -> Result<(), ProcessError> {
             .ok_or(ProcessError::IO(
                 "<ADRESSE>:<PORT> needed! For example: 127.0.0.1:8787".to_string(),
             ))?;
+        if ARGS.update_interval > 0 {
+            tokio::spawn(update::run(
+                channel_controllers.clone(),
+                ARGS.update_interval,
+                ARGS.update_command.clone(),
+            ));
+        }
+
         let controllers = web::Data::from(channel_controllers.clone());
         let queues = web::Data::from(mail_queues);
         let auth_state = web::Data::new(SseAuthState {
@@ -148,6 +229,11 @@ async fn main() -> Result<(), ProcessError> {
                         .service(update_advanced_config)
                         .service(get_playout_config)
                         .service(update_playout_config)
+                        .service(get_staging_config)
+                        .service(stage_playout_config)
+                        .service(promote_staging_config)
+                        .service(discard_staging_config)
+                        .service(switch_playout_output)
                         .service(get_playout_outputs)
                         .service(add_preset)
                         .service(get_presets)
@@ -157,17 +243,29 @@ async fn main() -> Result<(), ProcessError> {
                         .service(get_all_channels)
                         .service(patch_channel)
                         .service(add_channel)
+                        .service(add_channel_config)
                         .service(remove_channel)
                         .service(update_user)
                         .service(send_text_message)
+                        .service(send_filter_values)
+                        .service(send_alert)
+                        .service(send_graphic)
                         .service(control_playout)
+                        .service(insert_clip)
                         .service(media_current)
+                        .service(get_program_list)
+                        .service(edit_program_item)
+                        .service(resume_schedule)
                         .service(process_control)
                         .service(get_playlist)
                         .service(save_playlist)
                         .service(gen_playlist)
                         .service(del_playlist)
                         .service(get_log)
+                        .service(get_as_run_report)
+                        .service(get_statistics)
+                        .service(get_library)
+                        .service(scan_library)
                         .service(file_browser)
                         .service(add_dir)
                         .service(move_rename)
@@ -184,7 +282,10 @@ async fn main() -> Result<(), ProcessError> {
                         .service(event_stream),
                 )
                 .service(get_file)
-                .service(get_public);
+                .service(get_public)
+                .service(health)
+                .service(readyz)
+                .service(metrics);
 
             #[cfg(all(not(debug_assertions), feature = "embed_frontend"))]
             {
@@ -204,11 +305,16 @@ async fn main() -> Result<(), ProcessError> {
         })
         .bind((addr, port))?
         .workers(thread_counter())
+        .shutdown_timeout(ARGS.shutdown_timeout)
         .run()
         .await?;
     } else if ARGS.drop_db {
         db_drop().await;
     } else if let Some(channel_ids) = &ARGS.channel {
+        if ARGS.container && ARGS.foreground {
+            shutdown_on_sigterm(channel_controllers.clone());
+        }
+
         for (index, channel_id) in channel_ids.iter().enumerate() {
             let config = get_config(&pool, *channel_id).await?;
             let channel = handles::select_channel(&pool, channel_id).await?;
@@ -249,15 +355,22 @@ async fn main() -> Result<(), ProcessError> {
                 let mut contents = String::new();
                 f.read_to_string(&mut contents).await?;
 
-                let playlist: JsonPlaylist = serde_json::from_str(&contents)?;
+                let mut playlist: JsonPlaylist = serde_json::from_str(&contents)?;
+
+                expand_loops(&config, &mut playlist).await;
 
                 validate_playlist(
                     config,
                     Arc::new(Mutex::new(Vec::new())),
                     playlist,
                     Arc::new(AtomicBool::new(true)),
+                    Arc::new(Mutex::new(PlaylistWarnings::default())),
                 )
                 .await;
+            } else if let Some(date) = &ARGS.as_run_report {
+                let report = as_run::report(&config, date).await?;
+
+                print!("{}", as_run::report_table(&report));
             } else if ARGS.test_mail {
                 mail::send_mail(&config.mail, "This is just a test email...".to_string()).await?;
             }
@@ -275,8 +388,14 @@ async fn main() -> Result<(), ProcessError> {
     let managers = channel_controllers.read().await.managers.clone();
 
     for manager in &managers {
-        manager.channel.lock().await.active = false;
-        manager.stop_all(false).await;
+        if ARGS.container {
+            manager
+                .drain(Duration::from_secs(ARGS.shutdown_timeout))
+                .await;
+        } else {
+            manager.channel.lock().await.active = false;
+            manager.stop_all(false).await;
+        }
     }
 
     pool.close().await;
@@ -0,0 +1,255 @@
+/// Command Builders
+///
+/// The ffmpeg (and ffplay) arguments of decoder, encoder and ingest server, built from
+/// the config and the prepared media. They have no side effects besides logging, the
+/// outputs spawn them as they are, so the exact invocation for a config can be verified.
+///
+/// Filters are async to build, they come from `Media::add_filter` of the caller.
+use std::path::Path;
+
+use log::*;
+
+use crate::player::{
+    filter::Filters,
+    input::ingest::{ingest_preview, ingest_record},
+    utils::{
+        Media, add_thread_limit, audio_output, insert_readrate, output_stats::progress_param,
+        pipe_input, prepare_output_cmd,
+    },
+};
+use crate::utils::{compliance, config::PlayoutConfig, logging::Target};
+use crate::vec_strings;
+
+/// Parameters, which ffplay doesn't support in the desktop output.
+const FFPLAY_UNSUPPORTED: [&str; 11] = [
+    "-c",
+    "-c:v",
+    "-c:v:0",
+    "-b:v",
+    "-b:v:0",
+    "-vcodec",
+    "-c:a",
+    "-acodec",
+    "-crf",
+    "-map",
+    "-filter_complex",
+];
+
+fn prefix(log_format: &str) -> Vec<String> {
+    vec_strings!["-hide_banner", "-nostats", "-v", log_format]
+}
+
+/// Subtitle file of the ingest, when WebVTT is enabled.
+fn vtt_dummy(config: &PlayoutConfig) -> Option<String> {
+    let vtt_dummy = config
+        .channel
+        .storage
+        .join(config.processing.vtt_dummy.clone().unwrap_or_default());
+
+    (config.processing.vtt_enable && vtt_dummy.is_file())
+        .then(|| vtt_dummy.to_string_lossy().to_string())
+}
+
+/// Decoder for the stream, null and desktop output, which writes to the encoder pipe.
+pub fn decoder_cmd(config: &PlayoutConfig, node: &Media, log_format: &str) -> Vec<String> {
+    let mut cmd = prefix(log_format);
+
+    cmd.append(&mut node.input_cmd(config));
+    cmd.append(&mut node.cmd.clone().unwrap_or_default());
+
+    if let Some(mut filter) = node.filter.clone() {
+        cmd.append(&mut filter.cmd());
+        cmd.append(&mut filter.map());
+    }
+
+    if config.processing.vtt_enable && cmd.iter().any(|s| s.ends_with(".vtt")) {
+        let i = cmd.iter().filter(|&n| n == "-i").count().saturating_sub(1);
+
+        cmd.append(&mut vec_strings!("-map", format!("{i}:s"), "-c:s", "copy"));
+    }
+
+    if let Some(processing_cmd) = &config.processing.cmd {
+        cmd.extend_from_slice(processing_cmd);
+    }
+
+    add_thread_limit(config, &mut cmd);
+
+    cmd
+}
+
+/// Encoder of the stream output, which reads from the pipe in real time.
+pub fn stream_cmd(
+    config: &PlayoutConfig,
+    filters: &Option<Filters>,
+    log_format: &str,
+) -> Vec<String> {
+    let mut enc_prefix = prefix(log_format);
+    enc_prefix.append(&mut progress_param());
+
+    if let Some(input_cmd) = &config.advanced.encoder.input_cmd {
+        enc_prefix.append(&mut input_cmd.clone());
+    }
+
+    enc_prefix.append(&mut pipe_input(config));
+
+    insert_readrate(&config.general.ffmpeg_options, &mut enc_prefix, 1.0, 0.0);
+
+    let mut cmd = prepare_output_cmd(config, enc_prefix, filters);
+    cmd.append(&mut audio_output(config));
+    cmd.append(&mut compliance::output(config, filters));
+    add_thread_limit(config, &mut cmd);
+
+    cmd
+}
+
+/// Encoder of the null output, also used for the standby of the redundancy.
+pub fn null_cmd(
+    config: &PlayoutConfig,
+    filters: &Option<Filters>,
+    log_format: &str,
+) -> Vec<String> {
+    let mut enc_prefix = prefix(log_format);
+
+    if let Some(input_cmd) = &config.advanced.encoder.input_cmd {
+        enc_prefix.append(&mut input_cmd.clone());
+    }
+
+    enc_prefix.push("-re".to_string());
+    enc_prefix.append(&mut pipe_input(config));
+
+    let mut cmd = prepare_output_cmd(config, enc_prefix, filters);
+    add_thread_limit(config, &mut cmd);
+
+    cmd
+}
+
+/// ffplay command of the desktop output, `text_filter` is the drawtext filter for the zmq socket.
+pub fn desktop_cmd(
+    config: &PlayoutConfig,
+    text_filter: Option<String>,
+    log_format: &str,
+) -> Vec<String> {
+    let mut cmd = prefix(log_format);
+
+    if let Some(encoder_input_cmd) = &config.advanced.encoder.input_cmd {
+        cmd.append(&mut encoder_input_cmd.clone());
+    }
+
+    cmd.push("-autoexit".to_string());
+    cmd.append(&mut pipe_input(config));
+    cmd.append(&mut vec_strings!["-window_title", "ffplayout"]);
+
+    if let Some(mut output_cmd) = config.output.output_cmd.clone() {
+        if output_cmd
+            .iter()
+            .any(|i| FFPLAY_UNSUPPORTED.contains(&i.as_str()))
+        {
+            warn!(target: Target::file_mail(), channel = config.general.channel_id; "ffplay doesn't support given output parameters, they will be skipped!");
+        } else {
+            cmd.append(&mut output_cmd);
+        }
+    }
+
+    if let Some(filter) = text_filter {
+        cmd.append(&mut vec_strings!["-vf", format!("null,{filter}")]);
+    }
+
+    cmd
+}
+
+/// Ingest server, which writes to the encoder pipe.
+pub fn ingest_cmd(config: &PlayoutConfig, filters: &Option<Filters>) -> Vec<String> {
+    let mut cmd = prefix("level+info");
+    let vtt_dummy = vtt_dummy(config);
+
+    if let Some(ingest_input_cmd) = &config.advanced.ingest.input_cmd {
+        cmd.append(&mut ingest_input_cmd.clone());
+    }
+
+    cmd.append(&mut config.ingest.input_cmd.clone().unwrap_or_default());
+
+    if let Some(vtt) = &vtt_dummy {
+        cmd.append(&mut vec_strings!["-i", vtt]);
+    }
+
+    if let Some(mut filter) = filters.clone() {
+        cmd.append(&mut filter.cmd());
+        cmd.append(&mut filter.map());
+    }
+
+    if vtt_dummy.is_some() {
+        cmd.append(&mut vec_strings!("-map", "1:s", "-c:s", "copy"));
+    }
+
+    if let Some(processing_cmd) = &config.processing.cmd {
+        cmd.extend_from_slice(processing_cmd);
+    }
+
+    cmd.append(&mut ingest_preview(config));
+    cmd.append(&mut ingest_record(config));
+    add_thread_limit(config, &mut cmd);
+
+    cmd
+}
+
+/// Ingest server of the HLS output, which writes the segments itself.
+pub fn hls_ingest_cmd(config: &PlayoutConfig, filters: &Option<Filters>) -> Vec<String> {
+    let mut server_prefix = prefix("level+info");
+
+    if let Some(ingest_input_cmd) = &config.advanced.ingest.input_cmd {
+        server_prefix.append(&mut ingest_input_cmd.clone());
+    }
+
+    server_prefix.append(&mut config.ingest.input_cmd.clone().unwrap_or_default());
+
+    if let Some(vtt) = vtt_dummy(config) {
+        server_prefix.append(&mut vec_strings!["-i", vtt]);
+    }
+
+    let mut cmd = prepare_output_cmd(config, server_prefix, filters);
+    cmd.append(&mut ingest_preview(config));
+    cmd.append(&mut ingest_record(config));
+    add_thread_limit(config, &mut cmd);
+
+    cmd
+}
+
+/// Decoder of the HLS output, which writes the segments of the clip.
+///
+/// `read_rate` speeds up or slows down the clip, to catch up with the schedule,
+/// `id3` is the file with the timed metadata.
+pub fn hls_decoder_cmd(
+    config: &PlayoutConfig,
+    node: &Media,
+    read_rate: f64,
+    id3: Option<&Path>,
+    log_format: &str,
+) -> Vec<String> {
+    let mut dec_prefix = prefix(log_format);
+    let mut node_cmd = node.cmd.clone().unwrap_or_default();
+
+    dec_prefix.append(&mut node.input_cmd(config));
+
+    insert_readrate(
+        &config.general.ffmpeg_options,
+        &mut node_cmd,
+        read_rate,
+        node.pre_roll,
+    );
+
+    dec_prefix.append(&mut node_cmd);
+
+    if let Some(path) = id3 {
+        dec_prefix.append(&mut vec_strings![
+            "-f",
+            "mpegts",
+            "-i",
+            path.to_string_lossy()
+        ]);
+    }
+
+    let mut cmd = prepare_output_cmd(config, dec_prefix, &node.filter);
+    add_thread_limit(config, &mut cmd);
+
+    cmd
+}
@@ -1,10 +1,12 @@
 use std::{
     cmp, fmt,
     path::Path,
+    process::ExitStatus,
     sync::{
-        Arc,
-        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, OnceLock,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     },
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use async_walkdir::WalkDir;
@@ -16,7 +18,7 @@ use tokio::{
     fs,
     io::{self, AsyncReadExt},
     process::{Child, ChildStdout},
-    sync::{Mutex, RwLock},
+    sync::{Mutex, Notify, RwLock},
     time::{Duration, Instant, sleep},
 };
 use tokio_stream::StreamExt;
@@ -24,19 +26,53 @@ use tokio_stream::StreamExt;
 use crate::{
     ARGS,
     db::{handles, models::Channel},
-    file::{init_storage, local::LocalStorage},
-    player::{output::player, utils::Media},
+    file::{init_storage, local::LocalStorage, transcoder::Transcoder},
+    player::{
+        filter::graphics::Graphic,
+        output::player,
+        process::{FfmpegSpawner, ProcessSpawner},
+        utils::{
+            Media, meter::AudioMeter, output_stats::OutputStats, quarantine::Quarantine,
+            read_ahead, resume, warnings::PlaylistWarnings,
+        },
+    },
     utils::{
-        config::{OutputMode, PlayoutConfig},
+        alert::AlertState,
+        clock, companion, compliance,
+        config::{FallbackSource, OutputMode, PlayoutConfig, ProcessMode},
+        downstream,
         errors::ServiceError,
+        library,
         logging::Target,
+        osc, redundancy,
+        script::Script,
+        staging::Staging,
+        ticker, triggers, vdcp, widgets,
     },
 };
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+static STALL_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// Set after how many seconds without output a channel counts as stalled.
+pub fn set_stall_timeout(secs: u64) {
+    let _ = STALL_TIMEOUT.set(Duration::from_secs(secs));
+}
+
+pub fn stall_timeout() -> Duration {
+    *STALL_TIMEOUT.get().unwrap_or(&Duration::from_secs(10))
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
 /// Defined process units.
-#[derive(Clone, Debug, Default, Copy, Eq, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Copy, Eq, Hash, Serialize, Deserialize, PartialEq)]
 pub enum ProcessUnit {
     #[default]
     Decoder,
@@ -56,6 +92,9 @@ impl fmt::Display for ProcessUnit {
 
 use ProcessUnit::*;
 
+/// Path and modification time of a loaded playlist.
+pub type LoadedPlaylist = (String, Option<String>);
+
 #[derive(Clone, Debug)]
 pub struct ChannelManager {
     pub id: i32,
@@ -77,7 +116,46 @@ pub struct ChannelManager {
     pub filler_list: Arc<Mutex<Vec<Media>>>,
     pub current_index: Arc<AtomicUsize>,
     pub filler_index: Arc<AtomicUsize>,
+    pub last_loop: Arc<AtomicU64>,
+    pub last_output: Arc<AtomicU64>,
+    pub audio_meter: Arc<Mutex<AudioMeter>>,
+    pub fallback: Arc<Mutex<Option<FallbackSource>>>,
+    pub quarantine: Arc<Mutex<Quarantine>>,
     pub storage: LocalStorage,
+    pub transcoder: Transcoder,
+    pub script: Arc<Mutex<Option<Script>>>,
+    /// Live ingest waits for a take from the triggers.
+    pub ingest_hold: Arc<AtomicBool>,
+    /// Stop after the current clip or program block.
+    pub draining: Arc<AtomicBool>,
+    /// Hold the schedule at the next clip boundary, filler or the slate plays until the release.
+    pub hold: Arc<AtomicBool>,
+    /// Fill level of the output buffer in milliseconds.
+    pub buffer_fill: Arc<AtomicU64>,
+    /// Output is held back, the redundancy peer is on air.
+    pub standby: Arc<AtomicBool>,
+    /// Slate is taken by the operator, the schedule continues after the release.
+    pub slate_hold: Arc<AtomicBool>,
+    /// Config, which runs on the preview output until it gets promoted.
+    pub staging: Arc<Mutex<Option<Staging>>>,
+    /// Starts the ffmpeg processes, a mock in tests.
+    pub spawner: Arc<dyn ProcessSpawner>,
+    /// Problems, which the validation found in the current playlist.
+    pub playlist_warnings: Arc<Mutex<PlaylistWarnings>>,
+    /// Problems, which the read-ahead validation found in the playlist of the next day.
+    pub next_warnings: Arc<Mutex<PlaylistWarnings>>,
+    /// Emergency alert, with its audio items.
+    pub alert: Arc<Mutex<AlertState>>,
+    /// Unix time in milliseconds, when the current media started.
+    pub media_start: Arc<AtomicU64>,
+    /// Start a second encoder with the saved output config, which takes over seamless.
+    pub output_switch: Arc<Notify>,
+    /// Bytes, bitrates and stalls of the encoder input and the stream output.
+    pub output_stats: Arc<Mutex<OutputStats>>,
+    /// Date of the playlist, which doesn't exist, it gets loaded as soon as it is there.
+    pub missing_playlist: Arc<Mutex<Option<String>>>,
+    /// Path and modification time of the playlist in the current list, for the playlist watcher.
+    pub loaded_playlist: Arc<Mutex<Option<LoadedPlaylist>>>,
 }
 
 impl ChannelManager {
@@ -105,15 +183,38 @@ impl ChannelManager {
             filler_list: Arc::new(Mutex::new(vec![])),
             current_index: Arc::new(AtomicUsize::new(0)),
             filler_index: Arc::new(AtomicUsize::new(0)),
+            last_loop: Arc::new(AtomicU64::new(0)),
+            last_output: Arc::new(AtomicU64::new(0)),
+            audio_meter: Arc::new(Mutex::new(AudioMeter::new())),
+            fallback: Arc::new(Mutex::new(None)),
+            quarantine: Arc::new(Mutex::new(Quarantine::new())),
             decoder: Arc::new(Mutex::new(None)),
             encoder: Arc::new(Mutex::new(None)),
             ingest: Arc::new(Mutex::new(None)),
             ingest_reader: Arc::new(Mutex::new(None)),
             ingest_is_alive: Arc::new(AtomicBool::new(false)),
             is_processing: Arc::new(AtomicBool::new(false)),
-            filter_chain: None,
+            filter_chain: Some(Arc::new(Mutex::new(vec![]))),
             current_date: Arc::new(Mutex::new(String::new())),
             storage,
+            transcoder: Transcoder::new(),
+            script: Arc::new(Mutex::new(None)),
+            ingest_hold: Arc::new(AtomicBool::new(false)),
+            draining: Arc::new(AtomicBool::new(false)),
+            hold: Arc::new(AtomicBool::new(false)),
+            buffer_fill: Arc::new(AtomicU64::new(0)),
+            standby: Arc::new(AtomicBool::new(false)),
+            slate_hold: Arc::new(AtomicBool::new(false)),
+            staging: Arc::new(Mutex::new(None)),
+            spawner: Arc::new(FfmpegSpawner),
+            playlist_warnings: Arc::new(Mutex::new(PlaylistWarnings::default())),
+            next_warnings: Arc::new(Mutex::new(PlaylistWarnings::default())),
+            alert: Arc::new(Mutex::new(AlertState::default())),
+            media_start: Arc::new(AtomicU64::new(0)),
+            output_switch: Arc::new(Notify::new()),
+            output_stats: Arc::new(Mutex::new(OutputStats::default())),
+            missing_playlist: Arc::new(Mutex::new(None)),
+            loaded_playlist: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -277,18 +378,21 @@ impl ChannelManager {
 
     /// Wait for process to proper close.
     /// This prevents orphaned/zombi processes in system
-    pub async fn wait(&self, unit: ProcessUnit) {
+    pub async fn wait(&self, unit: ProcessUnit) -> Option<ExitStatus> {
         let child = match unit {
             Decoder => &self.decoder,
             Encoder => &self.encoder,
             Ingest => &self.ingest,
         };
 
+        let mut status = None;
+
         if let Some(proc) = child.lock().await.as_mut() {
             let mut counter = 0;
             loop {
                 match proc.try_wait() {
-                    Ok(Some(_)) => {
+                    Ok(Some(s)) => {
+                        status = Some(s);
                         break;
                     }
                     Ok(None) => {
@@ -311,6 +415,8 @@ impl ChannelManager {
         *child.lock().await = None;
 
         tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+
+        status
     }
 
     /// No matter what is running, terminate them all.
@@ -325,17 +431,124 @@ impl ChannelManager {
             if let Err(e) = handles::update_player(&self.db_pool, channel_id, false).await {
                 error!(target: Target::all(), channel = channel_id; "Player status cannot be written: {e}");
             };
+
+            resume::clear(self).await;
         } else {
             debug!(target: Target::all(), channel = channel_id; "Stop all child processes from channel: <span class=\"log-number\">{channel_id}</span>");
         }
 
         self.is_alive.store(false, Ordering::SeqCst);
         self.ingest_is_alive.store(false, Ordering::SeqCst);
+        self.storage.stop_incoming().await;
 
         for unit in [Decoder, Encoder, Ingest] {
             self.stop(unit).await;
         }
     }
+
+    /// Mark progress from the playout loop, with `output` when data went to the encoder.
+    pub fn tick(&self, output: bool) {
+        let now = unix_millis();
+
+        self.last_loop.store(now, Ordering::Relaxed);
+
+        if output {
+            self.last_output.store(now, Ordering::Relaxed);
+        }
+    }
+
+    /// Media, which starts now.
+    pub async fn set_current(&self, node: &Media) {
+        *self.current_media.lock().await = Some(node.clone());
+        self.media_start.store(unix_millis(), Ordering::Relaxed);
+    }
+
+    /// Play time of the current media.
+    pub fn media_elapsed(&self) -> Duration {
+        Duration::from_millis(
+            unix_millis().saturating_sub(self.media_start.load(Ordering::Relaxed)),
+        )
+    }
+
+    /// The process, which writes the output, is running. In HLS mode this is the decoder,
+    /// or the ingest server.
+    pub async fn encoder_running(&self) -> bool {
+        if self.config.read().await.output.mode == OutputMode::HLS {
+            self.decoder.lock().await.is_some() || self.ingest.lock().await.is_some()
+        } else {
+            self.encoder.lock().await.is_some()
+        }
+    }
+
+    /// Time since the encoder got data.
+    pub fn output_idle(&self) -> Duration {
+        Duration::from_millis(
+            unix_millis().saturating_sub(self.last_output.load(Ordering::Relaxed)),
+        )
+    }
+
+    /// Time since the playout loop made any progress.
+    pub fn loop_idle(&self) -> Duration {
+        Duration::from_millis(unix_millis().saturating_sub(self.last_loop.load(Ordering::Relaxed)))
+    }
+
+    /// Stop the channel graceful: end decoder and ingest first, so the encoder
+    /// can write out the remaining frames, and kill it after the timeout.
+    pub async fn drain(&self, timeout: Duration) {
+        let channel_id = self.id;
+
+        self.channel.lock().await.active = false;
+        self.is_alive.store(false, Ordering::SeqCst);
+        self.ingest_is_alive.store(false, Ordering::SeqCst);
+        self.storage.stop_incoming().await;
+
+        for unit in [Decoder, Ingest] {
+            self.stop(unit).await;
+        }
+
+        let drained = tokio::time::timeout(timeout, async {
+            loop {
+                match self.encoder.lock().await.as_mut().map(Child::try_wait) {
+                    Some(Ok(None)) => (),
+                    _ => break,
+                }
+
+                sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .is_ok();
+
+        if !drained {
+            warn!(target: Target::all(), channel = channel_id; "Encoder not finished after <span class=\"log-number\">{}s</span>, kill it", timeout.as_secs());
+        }
+
+        self.stop(Encoder).await;
+    }
+
+    /// Stop the channel after the current clip, or after the current program block.
+    ///
+    /// The player takes no new items, ends a running live ingest and drains the encoder
+    /// when the clip is over. This waits until the player has stopped.
+    pub async fn finish(&self) {
+        if !self.is_alive.load(Ordering::SeqCst) {
+            return;
+        }
+
+        info!(target: Target::all(), channel = self.id; "Stop channel after the current clip");
+
+        self.channel.lock().await.active = false;
+        self.draining.store(true, Ordering::SeqCst);
+        self.ingest_hold.store(true, Ordering::SeqCst);
+
+        if self.ingest_is_alive.load(Ordering::SeqCst) {
+            self.stop(Ingest).await;
+        }
+
+        while self.is_alive.load(Ordering::SeqCst) {
+            sleep(Duration::from_millis(100)).await;
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -409,6 +622,84 @@ async fn run_channel(manager: ChannelManager) -> Result<(), ServiceError> {
             .await;
     }
 
+    resume::restore(&manager).await;
+
+    if !config.storage.incoming_url.trim().is_empty() {
+        manager
+            .storage
+            .incoming(
+                config.clone(),
+                manager.is_alive.clone(),
+                manager.transcoder.clone(),
+            )
+            .await;
+    }
+
+    manager.ingest_hold.store(false, Ordering::SeqCst);
+    manager.draining.store(false, Ordering::SeqCst);
+    manager.hold.store(false, Ordering::SeqCst);
+    manager.slate_hold.store(false, Ordering::SeqCst);
+
+    if !config.task.trigger_device.trim().is_empty() {
+        tokio::spawn(triggers::watch(manager.clone(), config.clone()));
+    }
+
+    if !config.task.vdcp_address.trim().is_empty() {
+        tokio::spawn(vdcp::listen(manager.clone(), config.clone()));
+    }
+
+    if !config.task.companion_address.trim().is_empty() {
+        tokio::spawn(companion::listen(manager.clone(), config.clone()));
+    }
+
+    if !config.task.osc_address.trim().is_empty() || !config.task.osc_feedback.trim().is_empty() {
+        tokio::spawn(osc::run(manager.clone(), config.clone()));
+    }
+
+    if !config.text.ticker_url.trim().is_empty() {
+        tokio::spawn(ticker::run(manager.clone(), config.clone()));
+    }
+
+    tokio::spawn(clock::run(manager.clone(), config.clone()));
+
+    if config.processing.mode == ProcessMode::Playlist && config.playlist.read_ahead > 0 {
+        tokio::spawn(read_ahead::run(manager.clone(), config.clone()));
+    }
+
+    if config.storage.graphics.values().any(Graphic::is_widget) {
+        tokio::spawn(widgets::run(manager.clone(), config.clone()));
+    }
+
+    if compliance::enabled(&config) {
+        // the segment muxer doesn't create the folder
+        if let Err(e) = fs::create_dir_all(compliance::folder(&config)).await {
+            error!(target: Target::file_mail(), channel = config.general.channel_id; "Compliance recording: {e}");
+        }
+
+        tokio::spawn(compliance::run(manager.clone(), config.clone()));
+    }
+
+    if !config.output.check_url.trim().is_empty() {
+        tokio::spawn(downstream::run(manager.clone(), config.clone()));
+    }
+
+    // with redundancy the output starts in standby, until the heartbeats decide
+    let redundant = redundancy::enabled(&config);
+    manager.standby.store(redundant, Ordering::SeqCst);
+
+    if redundant {
+        tokio::spawn(redundancy::run(manager.clone(), config.clone()));
+    }
+
+    let pool = manager.db_pool.clone();
+    let scan_config = config.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = library::scan(&pool, &scan_config).await {
+            error!(target: Target::file_mail(), channel = channel_id; "Library scan: {e}");
+        }
+    });
+
     // 4. Player starten
     player(manager).await
 }
@@ -0,0 +1,159 @@
+/// Alternate Outputs
+///
+/// In stream mode with more than one output, the outputs can have tags (`output.tags`),
+/// like `web, local`. Playlist items list in `blackout` the tags, on which they are not
+/// allowed to run, for example a web stream without the rights for a sports event.
+///
+/// The encoder gets the slate as overlay and a muted copy of the audio for every tagged
+/// output. Both are off, until an item with the tag of the output starts, then the player
+/// switches them over zmq. Outputs without a tag always keep the program.
+use crate::player::{
+    filter::{FilterType::*, Filters},
+    utils::{Media, languages::output_tracks, slate_loop},
+};
+use crate::utils::config::PlayoutConfig;
+
+/// Tags of the outputs, in the order of the output parameters.
+pub fn output_tags(config: &PlayoutConfig) -> Vec<String> {
+    config
+        .output
+        .tags
+        .split(',')
+        .map(|tag| tag.trim().to_string())
+        .collect()
+}
+
+/// Socket of the encoder, when it can switch outputs to the slate.
+fn socket(config: &PlayoutConfig) -> Option<&String> {
+    let processing = &config.processing;
+
+    config
+        .output
+        .zmq_socket
+        .as_ref()
+        .filter(|_| !processing.audio_only && !processing.copy_video && !processing.copy_audio)
+}
+
+/// Outputs with a tag, by their index.
+fn tagged(config: &PlayoutConfig) -> impl Iterator<Item = (usize, String)> {
+    output_tags(config)
+        .into_iter()
+        .take(config.output.output_count)
+        .enumerate()
+        .filter(|(_, tag)| !tag.is_empty())
+}
+
+/// zmq filter of the encoder, in front of the split, which gets the commands for the whole
+/// graph: the output switches and the graphics.
+pub fn control(config: &PlayoutConfig, chain: &mut Filters) {
+    let processing = &config.processing;
+
+    if let Some(socket) = config
+        .output
+        .zmq_socket
+        .as_ref()
+        .filter(|_| !processing.audio_only && !processing.copy_video)
+    {
+        chain.add(
+            &format!("zmq=b=tcp\\\\://'{}'", socket.replace(':', "\\:")),
+            0,
+            Video,
+        );
+    }
+}
+
+/// Slate overlay and muted audio for the tagged outputs, after the split of the video.
+///
+/// The audio tracks get split too, for the outputs, which have the track in their
+/// languages, the output `i` maps `[aout_<track>_<i>]`.
+pub fn outputs(config: &PlayoutConfig, chain: &mut Filters) {
+    let count = chain.video_out_link.len();
+
+    if socket(config).is_none() || count < 2 {
+        return;
+    }
+
+    let (width, height) = (config.processing.width, config.processing.height);
+    let slate = slate_loop(config).unwrap_or_else(|| {
+        format!(
+            "color=c=black:s={width}x{height}:r={}",
+            config.processing.fps
+        )
+    });
+    let tagged = tagged(config).map(|(i, _)| i).collect::<Vec<_>>();
+
+    for (i, link) in chain.video_out_link.iter_mut().enumerate() {
+        if tagged.contains(&i) {
+            chain.video_chain.push_str(&format!(
+                ";{slate},format=yuv420p[slate{i}];{link}[slate{i}]overlay@alt{i}=enable=0:eof_action=repeat[valt{i}]"
+            ));
+
+            *link = format!("[valt{i}]");
+        }
+    }
+
+    let tracks = (0..count)
+        .map(|i| output_tracks(config, i))
+        .collect::<Vec<_>>();
+
+    for track in 0..config.processing.audio_tracks {
+        let users = (0..count)
+            .filter(|i| tracks[*i].contains(&track))
+            .collect::<Vec<_>>();
+
+        if users.is_empty() {
+            continue;
+        }
+
+        let links = users
+            .iter()
+            .map(|i| {
+                if tagged.contains(i) {
+                    format!("[asplit_{track}_{i}]")
+                } else {
+                    format!("[aout_{track}_{i}]")
+                }
+            })
+            .collect::<String>();
+
+        if !chain.audio_chain.is_empty() {
+            chain.audio_chain.push(';');
+        }
+
+        chain
+            .audio_chain
+            .push_str(&format!("[0:a:{track}]asplit={}{links}", users.len()));
+
+        for i in tagged.iter().filter(|i| users.contains(i)) {
+            chain.audio_chain.push_str(&format!(
+                ";[asplit_{track}_{i}]volume@alt{i}_{track}=volume=0:enable=0[aout_{track}_{i}]"
+            ));
+        }
+
+        for i in users {
+            chain.audio_out_link.push(format!("[aout_{track}_{i}]"));
+        }
+    }
+}
+
+/// zmq commands for the start of the item: on for the outputs with a tag from `blackout`,
+/// off for the others.
+pub fn commands(config: &PlayoutConfig, node: &Media) -> Vec<String> {
+    if socket(config).is_none() {
+        return vec![];
+    }
+
+    let mut commands = vec![];
+
+    for (i, tag) in tagged(config) {
+        let on = u8::from(node.blackout.contains(&tag));
+
+        commands.push(format!("overlay@alt{i} enable {on}"));
+
+        for track in output_tracks(config, i) {
+            commands.push(format!("volume@alt{i}_{track} enable {on}"));
+        }
+    }
+
+    commands
+}
@@ -0,0 +1,136 @@
+/// Day Part Audio Chains
+///
+/// Named audio filter chains (`processing.audio_chains`), one per line:
+///
+/// ```text
+/// drive = acompressor=threshold=-18dB:ratio=4:attack=5:release=100,alimiter=limit=0.9
+/// night = dynaudnorm=f=250:g=15
+/// ```
+///
+/// The day parts (`processing.audio_dayparts`) select a chain by the time of day:
+///
+/// ```text
+/// 06:00-10:00 = drive
+/// 16:00-19:00 = drive
+/// 22:00-06:00 = night
+/// ```
+///
+/// The start time of the clip selects the chain, so it changes only at clip boundaries.
+/// Outside of the day parts no chain is applied. Lines are separated by new line or `;`,
+/// lines with `#` at the start are comments.
+use std::collections::HashMap;
+
+use chrono::{NaiveTime, Timelike};
+
+use crate::utils::config::PlayoutConfig;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DayPart {
+    /// Seconds of the day.
+    pub start: f64,
+    pub end: f64,
+    pub chain: String,
+}
+
+impl DayPart {
+    /// A day part, where the end is before the start, goes over midnight.
+    pub fn contains(&self, sec: f64) -> bool {
+        let sec = sec.rem_euclid(86400.0);
+
+        if self.start <= self.end {
+            self.start <= sec && sec < self.end
+        } else {
+            sec >= self.start || sec < self.end
+        }
+    }
+}
+
+fn lines(table: &str) -> impl Iterator<Item = &str> {
+    table
+        .split(['\n', ';'])
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+}
+
+pub(crate) fn day_sec(time: &str) -> Option<f64> {
+    let time = NaiveTime::parse_from_str(time.trim(), "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time.trim(), "%H:%M"))
+        .ok()?;
+
+    Some(time.num_seconds_from_midnight() as f64)
+}
+
+/// Parse the chain table, invalid lines are returned as errors.
+pub fn parse_chains(table: &str) -> (HashMap<String, String>, Vec<String>) {
+    let mut chains = HashMap::new();
+    let mut errors = vec![];
+
+    for line in lines(table) {
+        match line.split_once('=') {
+            Some((name, filter)) if !name.trim().is_empty() && !filter.trim().is_empty() => {
+                chains.insert(name.trim().to_string(), filter.trim().to_string());
+            }
+            _ => errors.push(format!("Audio chain '{line}' is not name=filters!")),
+        }
+    }
+
+    (chains, errors)
+}
+
+/// Parse the day parts, the chains must exist. Invalid lines are returned as errors.
+pub fn parse_dayparts(
+    table: &str,
+    chains: &HashMap<String, String>,
+) -> (Vec<DayPart>, Vec<String>) {
+    let mut dayparts = vec![];
+    let mut errors = vec![];
+
+    for line in lines(table) {
+        let Some((range, chain)) = line.split_once('=') else {
+            errors.push(format!("Day part '{line}' is not HH:MM-HH:MM=chain!"));
+            continue;
+        };
+
+        let chain = chain.trim();
+
+        match range
+            .split_once('-')
+            .and_then(|(s, e)| Some((day_sec(s)?, day_sec(e)?)))
+        {
+            Some((start, end)) if chains.contains_key(chain) => dayparts.push(DayPart {
+                start,
+                end,
+                chain: chain.to_string(),
+            }),
+            Some(_) => errors.push(format!("Day part '{line}' has no audio chain '{chain}'!")),
+            None => errors.push(format!("Day part '{line}' is not HH:MM-HH:MM=chain!")),
+        }
+    }
+
+    (dayparts, errors)
+}
+
+/// Errors in the audio chains and day parts of the config.
+pub fn check(config: &PlayoutConfig) -> Vec<String> {
+    let (chains, mut errors) = parse_chains(&config.processing.audio_chains);
+    let (_, mut daypart_errors) = parse_dayparts(&config.processing.audio_dayparts, &chains);
+
+    errors.append(&mut daypart_errors);
+
+    errors
+}
+
+/// Filters of the day part at `sec` (seconds of the day), the first matching day part wins.
+pub fn chain_at(config: &PlayoutConfig, sec: f64) -> Option<String> {
+    if config.processing.audio_dayparts.trim().is_empty() {
+        return None;
+    }
+
+    let (mut chains, _) = parse_chains(&config.processing.audio_chains);
+    let (dayparts, _) = parse_dayparts(&config.processing.audio_dayparts, &chains);
+
+    dayparts
+        .iter()
+        .find(|d| d.contains(sec))
+        .and_then(|d| chains.remove(&d.chain))
+}
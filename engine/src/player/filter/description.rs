@@ -0,0 +1,95 @@
+/// Audio Description
+///
+/// With `processing.description_track`, one audio track is the audio description (AD) for
+/// the visually impaired: the program audio, mixed with a sidecar file of the clip, which
+/// has the same name with `.ad` in front of the extension:
+///
+/// ```text
+/// movie.mp4
+/// movie.ad.wav
+/// ```
+///
+/// The program audio gets ducked under the voice of the description. Clips without a
+/// sidecar have the program audio on this track.
+use std::path::{Path, PathBuf};
+
+use crate::player::{
+    filter::{FilterType::Audio, Filters},
+    utils::Media,
+};
+use crate::utils::config::{PlayoutConfig, Processing};
+use crate::vec_strings;
+
+/// Extensions of the sidecar files, in the order of the search.
+pub const SIDECAR_EXTENSIONS: [&str; 7] = ["wav", "flac", "m4a", "aac", "mp3", "ogg", "opus"];
+/// Name of the track in the outputs (`output.languages`) and HLS renditions.
+pub const DESCRIPTION_NAME: &str = "ad";
+
+/// Audio track of the description, `None` when it is off.
+pub fn track(processing: &Processing) -> Option<i32> {
+    let track = processing.description_track as i32;
+
+    (track > 0 && track < processing.audio_tracks && !processing.copy_audio).then_some(track)
+}
+
+/// Sidecar file of the clip, like `movie.ad.wav` for `movie.mp4`.
+pub fn sidecar(source: &str) -> Option<PathBuf> {
+    let path = Path::new(source);
+    let stem = path.file_stem()?.to_string_lossy();
+
+    SIDECAR_EXTENSIONS
+        .iter()
+        .map(|ext| path.with_file_name(format!("{stem}.{DESCRIPTION_NAME}.{ext}")))
+        .find(|p| p.is_file())
+}
+
+/// Decoder input of the sidecar, with the same seek and length as the clip.
+pub fn input(config: &PlayoutConfig, node: &mut Media, seek: Option<f64>) -> Vec<String> {
+    node.description = track(&config.processing)
+        .and_then(|_| sidecar(&node.source))
+        .map(|p| p.to_string_lossy().to_string());
+
+    let Some(path) = &node.description else {
+        return vec![];
+    };
+
+    let mut cmd = vec![];
+
+    if let Some(start) = seek {
+        cmd.append(&mut vec_strings!["-ss", start]);
+    }
+
+    cmd.append(&mut vec_strings![
+        "-i",
+        path,
+        "-t",
+        node.out - node.seek + node.pre_roll
+    ]);
+
+    cmd
+}
+
+/// Mix the sidecar into the description track, the program audio ducks under the voice.
+pub fn mix(config: &PlayoutConfig, chain: &mut Filters, node: &Media, nr: i32) {
+    if node.description.is_none() || track(&config.processing) != Some(nr) {
+        return;
+    }
+
+    // the sidecar follows the video and the separate audio
+    let input = if node.audio.is_empty() { 1 } else { 2 };
+    let trim = if node.pre_roll > 0.0 {
+        format!("atrim=start={},asetpts=PTS-STARTPTS,", node.pre_roll)
+    } else {
+        String::new()
+    };
+
+    chain.add(
+        &format!(
+            "anull[adm{nr}];[{input}:a:0]{trim}aresample=async=1,asplit=2[adk{nr}][adv{nr}];\
+            [adm{nr}][adk{nr}]sidechaincompress=threshold=0.03:ratio=6:attack=50:release=600[add{nr}];\
+            [add{nr}][adv{nr}]amix=inputs=2:duration=first:dropout_transition=0:normalize=0"
+        ),
+        nr,
+        Audio,
+    );
+}
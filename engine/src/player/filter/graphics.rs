@@ -0,0 +1,240 @@
+/// Graphics Templates
+///
+/// Lower thirds and other graphics, which get shown and hidden over the control API.
+/// The templates are in `graphics/templates.json` of the channel storage, by name:
+///
+/// ```json
+/// {
+///     "bug": {"image": "graphics/bug_%03d.png", "x": "W-w-40", "y": "40"},
+///     "lower_third": {
+///         "image": "graphics/lower_third.png", "x": "0", "y": "H-h-60",
+///         "text": {"text": "{name}\n{title}", "x": "80", "y": "h-150", "fontsize": "36"},
+///         "animation": "slide", "length": 0.6
+///     }
+/// }
+/// ```
+///
+/// `image` is a PNG, or an image sequence with a pattern, relative to the storage. `text`
+/// has the values of the text API, `{field}` in the text gets the field of the request.
+/// The animation is `fade`, `slide` (from the left) or `cut`, `length` in seconds.
+///
+/// Templates with a `url` are data widgets: their text gets the fields of the JSON from
+/// the url, every `interval` seconds, nested values with dots (`{current.temp}`).
+/// `{time}` and `{date}` are the clock of the channel. `"visible": true` shows a template
+/// from the start, without the API.
+///
+/// The encoder gets an overlay for every image and a drawtext for every text, which are
+/// invisible, until they get animated in over zmq.
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::player::filter::{FilterType::*, Filters};
+use crate::utils::{TextFilter, config::PlayoutConfig};
+
+/// Updates per second of the animation.
+const ANIMATION_RATE: f64 = 25.0;
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Animation {
+    #[default]
+    Fade,
+    Slide,
+    Cut,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct Graphic {
+    #[serde(default)]
+    pub image: Option<String>,
+    #[serde(default = "default_position")]
+    pub x: String,
+    #[serde(default = "default_position")]
+    pub y: String,
+    #[serde(default)]
+    pub text: Option<TextFilter>,
+    #[serde(default)]
+    pub animation: Animation,
+    #[serde(default = "default_length")]
+    pub length: f64,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+    #[serde(default)]
+    pub visible: bool,
+}
+
+impl Graphic {
+    /// Template, which gets its text from the data task and not from the request.
+    pub fn is_widget(&self) -> bool {
+        self.url.is_some()
+            || self
+                .text
+                .as_ref()
+                .and_then(|t| t.text.as_deref())
+                .is_some_and(|t| t.contains("{time}") || t.contains("{date}"))
+    }
+}
+
+fn default_position() -> String {
+    "0".to_string()
+}
+
+fn default_length() -> f64 {
+    0.5
+}
+
+fn default_interval() -> u32 {
+    300
+}
+
+/// Templates of the channel, image paths are absolute.
+pub async fn templates(storage: &Path) -> BTreeMap<String, Graphic> {
+    let file = storage.join("graphics").join("templates.json");
+
+    let Ok(content) = tokio::fs::read_to_string(&file).await else {
+        return BTreeMap::new();
+    };
+
+    match serde_json::from_str::<BTreeMap<String, Graphic>>(&content) {
+        Ok(mut templates) => {
+            for graphic in templates.values_mut() {
+                if let Some(image) = graphic.image.as_mut() {
+                    *image = storage.join(&*image).to_string_lossy().to_string();
+                }
+            }
+
+            templates
+        }
+        Err(e) => {
+            error!("Graphics templates <span class=\"log-addr\">{file:?}</span>: {e}");
+            BTreeMap::new()
+        }
+    }
+}
+
+/// Overlays and drawtext filters of the templates, all invisible.
+pub fn overlays(config: &PlayoutConfig, chain: &mut Filters) {
+    if config.output.zmq_socket.is_none() || config.processing.copy_video {
+        return;
+    }
+
+    let font = if Path::new(&config.text.font_path).is_file() {
+        format!(":fontfile='{}'", config.text.font_path)
+    } else {
+        String::new()
+    };
+
+    for (i, graphic) in config.storage.graphics.values().enumerate() {
+        let alpha = u8::from(graphic.visible);
+
+        if let Some(image) = &graphic.image {
+            let path = image.replace('\\', "/").replace(':', "\\\\:");
+            let sequence = if path.contains('%') { ":f=image2" } else { "" };
+
+            chain.add(
+                &format!(
+                    "movie={path}{sequence}:loop=0,setpts=N/(FRAME_RATE*TB),format=rgba,colorchannelmixer@gfx{i}=aa={alpha}"
+                ),
+                0,
+                Video,
+            );
+            chain.add(
+                &format!("overlay@gfx{i}=x='{}':y='{}'", graphic.x, graphic.y),
+                0,
+                Video,
+            );
+        }
+
+        if let Some(text) = &graphic.text {
+            let text = TextFilter {
+                text: None,
+                alpha: Some(alpha.to_string()),
+                ..text.clone()
+            };
+
+            chain.add(&format!("drawtext@gfxt{i}={text}{font}"), 0, Video);
+        }
+    }
+}
+
+/// Template with its index, which names its filters.
+pub fn template<'a>(config: &'a PlayoutConfig, name: &str) -> Option<(usize, &'a Graphic)> {
+    config
+        .storage
+        .graphics
+        .iter()
+        .enumerate()
+        .find(|(_, (n, _))| *n == name)
+        .map(|(i, (_, graphic))| (i, graphic))
+}
+
+/// Text of the template with the fields of the request.
+pub fn fill(text: &str, fields: &HashMap<String, String>) -> String {
+    fields.iter().fold(text.to_string(), |text, (key, value)| {
+        text.replace(&format!("{{{key}}}"), value)
+    })
+}
+
+/// Progress of the animation steps, from 0 (hidden) to 1 (shown).
+pub fn steps(graphic: &Graphic, show: bool) -> Vec<f64> {
+    let count = match graphic.animation {
+        Animation::Cut => 1,
+        _ => ((graphic.length * ANIMATION_RATE).round() as usize).max(1),
+    };
+
+    (1..=count)
+        .map(|n| {
+            let progress = n as f64 / count as f64;
+
+            if show { progress } else { 1.0 - progress }
+        })
+        .collect()
+}
+
+/// zmq commands for one step of the animation. `text` is the filled text, which gets
+/// set with the first step.
+pub fn commands(index: usize, graphic: &Graphic, text: Option<&str>, progress: f64) -> Vec<String> {
+    let (alpha, shift) = match graphic.animation {
+        Animation::Slide if progress > 0.0 => (1.0, 1.0 - progress),
+        Animation::Fade => (progress, 0.0),
+        _ => (progress.ceil(), 0.0),
+    };
+    let mut commands = vec![];
+
+    if graphic.image.is_some() {
+        commands.push(format!("colorchannelmixer@gfx{index} aa {alpha:.3}"));
+
+        if graphic.animation == Animation::Slide {
+            commands.push(format!("overlay@gfx{index} x ({})-W*{shift:.3}", graphic.x));
+        }
+    }
+
+    if let Some(values) = &graphic.text {
+        let mut reinit = format!("alpha={alpha:.3}");
+
+        if graphic.animation == Animation::Slide {
+            let x = values.x.as_deref().unwrap_or("0");
+            reinit.push_str(&format!(":x='({x})-w*{shift:.3}'"));
+        }
+
+        if let Some(text) = text {
+            let text = TextFilter {
+                text: Some(text.to_string()),
+                ..Default::default()
+            };
+
+            reinit.push_str(&format!(":{text}"));
+        }
+
+        commands.push(format!("drawtext@gfxt{index} reinit {reinit}"));
+    }
+
+    commands
+}
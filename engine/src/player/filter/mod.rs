@@ -5,16 +5,30 @@ use regex::Regex;
 use shlex::split;
 use tokio::sync::Mutex;
 
+pub mod alternate;
+pub mod audio_chains;
 mod custom;
+pub mod description;
+pub mod graphics;
 pub mod v_drawtext;
 
 use crate::player::{
     controller::ProcessUnit::{self, *},
-    utils::{Media, calc_aspect, custom_format, fps_calc, fraction, is_close},
+    utils::{
+        Media, calc_aspect, custom_format, fps_calc, fraction, is_close, is_generated,
+        languages::source_track,
+        meter::METER_FILTER,
+        probe::VideoStream,
+        time_in_seconds,
+        timecode::{burn_in_filter, ltc_filter},
+    },
 };
 use crate::utils::{
-    config::{OutputMode::*, PlayoutConfig},
+    compliance::{self, COMPLIANCE_LINK},
+    config::{FieldOrder, FpsPolicy, OutputMode::*, PlayoutConfig},
     logging::Target,
+    ticker,
+    time_machine::time_now,
 };
 use crate::vec_strings;
 
@@ -37,6 +51,9 @@ use FilterType::*;
 
 const HW_FILTER_POSTFIX: &[&str; 6] = &["_cuda", "_npp", "_opencl", "_vaapi", "_vulkan", "_qsv"];
 
+/// Filters which work on hardware frames too, they need no download.
+const HW_NEUTRAL_FILTER: &[&str; 3] = &["fps=", "setdar=", "setsar="];
+
 #[derive(Debug, Clone)]
 pub struct Filters {
     unit: ProcessUnit,
@@ -56,6 +73,8 @@ pub struct Filters {
     video_position: i32,
     audio_last: i32,
     video_last: i32,
+    /// Stream of the input for every audio track, when it differs from the track.
+    audio_source: Vec<i32>,
 }
 
 impl Filters {
@@ -85,10 +104,23 @@ impl Filters {
             video_position: 0,
             audio_last: -1,
             video_last: -1,
+            audio_source: vec![],
         }
     }
 
+    /// Stream of the input for the audio track.
+    fn source(&self, track: i32) -> i32 {
+        self.audio_source
+            .get(track as usize)
+            .copied()
+            .unwrap_or(track)
+    }
+
     pub fn add(&mut self, filter: &str, track_nr: i32, filter_type: FilterType) {
+        let input_nr = match filter_type {
+            Audio => self.source(track_nr),
+            Video => track_nr,
+        };
         let (map, chain, position, last) = match filter_type {
             Audio => (
                 &mut self.audio_map,
@@ -124,7 +156,7 @@ impl Filters {
                 chain_start.push_str(&sep);
             } else {
                 // build audio/video selector like [0:a:0]
-                chain_start.push_str(&format!("{sep}[{position}:{filter_type}:{track_nr}]"));
+                chain_start.push_str(&format!("{sep}[{position}:{filter_type}:{input_nr}]"));
             }
 
             if self.hw_context
@@ -134,6 +166,13 @@ impl Filters {
                 && filter_type == Video
             {
                 chain_start.push_str("hwdownload,format=nv12,");
+            } else if !self.hw_context
+                && is_hw(filter)
+                && !filter.contains("hwupload")
+                && filter_type == Video
+            {
+                chain_start.push_str(&hw_upload_str(&self.config));
+                chain_start.push(',');
             }
 
             chain_start.push_str(filter);
@@ -158,9 +197,13 @@ impl Filters {
 
         for (i, filter) in self.v_chain.iter().enumerate() {
             if filter.starts_with("movie=") {
-                if self.hw_context
+                let hw_overlay = self
+                    .v_chain
+                    .iter()
+                    .any(|f| f.starts_with("overlay") && is_hw(f));
+
+                if ((self.hw_context && !self.config.advanced.is_empty_filter()) || hw_overlay)
                     && !last_is_hw(&self.video_chain)
-                    && !self.config.advanced.is_empty_filter()
                 {
                     let hw_up = hw_upload_str(&self.config);
 
@@ -169,7 +212,12 @@ impl Filters {
                 }
                 self.video_chain.push_str("[v];");
             } else if filter.starts_with("overlay") {
-                let hw_up = hw_upload(&self.config, &self.video_chain, filter);
+                let mut hw_up = hw_upload(&self.config, &self.video_chain, filter);
+
+                if filter.starts_with("overlay_cuda") && hw_up.starts_with("format=nv12") {
+                    // keep alpha channel from logo
+                    hw_up = hw_up.replacen("format=nv12", "format=yuva420p", 1);
+                }
 
                 if !hw_up.is_empty() {
                     self.video_chain.push(',');
@@ -214,8 +262,16 @@ impl Filters {
             if self.hw_context && !last_is_hw(&self.video_chain) {
                 let hw_up = hw_upload_str(&self.config);
 
-                self.video_chain.push_str(",format=nv12,");
+                if !hw_up.starts_with("format=") {
+                    self.video_chain.push_str(",format=nv12");
+                }
+
+                self.video_chain.push(',');
                 self.video_chain.push_str(&hw_up);
+            } else if !self.hw_context && last_is_hw(&self.video_chain) {
+                // GPU filters without hardware decoding, bring frames back for the pipe
+                self.video_chain
+                    .push_str(",hwdownload,format=nv12,format=yuv420p");
             }
 
             self.video_chain
@@ -272,7 +328,7 @@ impl Filters {
 
         if self.audio_last == -1 {
             for i in 0..self.config.processing.audio_tracks {
-                let a_map = format!("{}:a:{i}", self.audio_position);
+                let a_map = format!("{}:a:{}", self.audio_position, self.source(i));
 
                 if !o_map.contains(&a_map) {
                     o_map.append(&mut vec_strings!["-map", a_map]);
@@ -294,8 +350,18 @@ fn is_hw(filter: &str) -> bool {
     HW_FILTER_POSTFIX.iter().any(|p| filter.contains(p))
 }
 
+fn is_hw_neutral(filter: &str) -> bool {
+    let filter = filter.rsplit(']').next().unwrap_or(filter);
+
+    HW_NEUTRAL_FILTER.iter().any(|n| filter.starts_with(n))
+}
+
 fn last_is_hw(chain: &str) -> bool {
-    let parts: Vec<&str> = chain.split_terminator([',', ';']).map(str::trim).collect();
+    let parts: Vec<&str> = chain
+        .split_terminator([',', ';'])
+        .map(str::trim)
+        .filter(|p| !is_hw_neutral(p))
+        .collect();
 
     match parts.len() {
         0 => false,
@@ -318,6 +384,7 @@ fn hw_download(chain: &str, f: &str) -> String {
 
     if last_is_hw(chain)
         && !is_hw(f)
+        && !is_hw_neutral(f)
         && !f.starts_with("null[")
         && !f.starts_with("[")
         && !f.contains("hwdownload")
@@ -334,6 +401,13 @@ fn hw_download(chain: &str, f: &str) -> String {
 }
 
 fn hw_upload_str(config: &PlayoutConfig) -> String {
+    match config.advanced.hw_accel() {
+        Some("cuda") => return "format=nv12,hwupload_cuda".to_string(),
+        Some("qsv") => return "format=nv12,hwupload=extra_hw_frames=64".to_string(),
+        Some(_) => return "format=nv12,hwupload".to_string(),
+        None => (),
+    }
+
     if config
         .advanced
         .decoder
@@ -370,6 +444,38 @@ fn deinterlace(config: &PlayoutConfig, chain: &mut Filters, field_order: &Option
     }
 }
 
+/// Convert HDR (PQ/HLG) and BT.2020 sources to BT.709 SDR.
+fn tonemap(config: &PlayoutConfig, chain: &mut Filters, stream: &VideoStream) {
+    if !config.processing.tonemap {
+        return;
+    }
+
+    if stream.is_hdr() {
+        let mut tonemap = format!(
+            "tonemap=tonemap={}:desat={}",
+            config.processing.tonemap_algorithm, config.processing.tonemap_desat
+        );
+
+        if config.processing.tonemap_peak > 0.0 {
+            tonemap.push_str(&format!(":peak={}", config.processing.tonemap_peak));
+        }
+
+        chain.add(
+            &format!(
+                "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,{tonemap},zscale=t=bt709:m=bt709:r=tv,format=yuv420p"
+            ),
+            0,
+            Video,
+        );
+    } else if stream.is_bt2020() {
+        chain.add(
+            "zscale=p=bt709:t=bt709:m=bt709:r=tv,format=yuv420p",
+            0,
+            Video,
+        );
+    }
+}
+
 fn pad(config: &PlayoutConfig, chain: &mut Filters, aspect: f64) {
     if !is_close(aspect, config.processing.aspect, 0.03) {
         let (numerator, denominator) = fraction(config.processing.aspect, 100);
@@ -379,21 +485,64 @@ fn pad(config: &PlayoutConfig, chain: &mut Filters, aspect: f64) {
                 &pad_video,
                 &[&numerator.to_string(), &denominator.to_string()],
             ),
-            None => format!("pad='ih*{numerator}/{denominator}:ih:(ow-iw)/2:(oh-ih)/2'"),
+            None => match config.advanced.hw_accel() {
+                Some("vaapi") => {
+                    format!("pad_vaapi=w=ih*{numerator}/{denominator}:h=ih:x=(ow-iw)/2:y=(oh-ih)/2")
+                }
+                _ => format!("pad='ih*{numerator}/{denominator}:ih:(ow-iw)/2:(oh-ih)/2'"),
+            },
         };
 
         chain.add(&pad, 0, Video);
     }
 }
 
+/// Convert frame rate by policy, unknown frame rates get always the fps filter.
+///
+/// With captions, the fps filter is also used for the other policies, because it keeps
+/// the caption data and minterpolate drops them.
 fn fps(config: &PlayoutConfig, chain: &mut Filters, fps: f64) {
-    if fps != config.processing.fps {
-        let fps_filter = match config.advanced.filter.fps.clone() {
-            Some(fps) => custom_format(&fps, &[&config.processing.fps]),
-            None => format!("fps={}", config.processing.fps),
-        };
+    let target = config.processing.fps;
 
-        chain.add(&fps_filter, 0, Video);
+    if fps == target || (fps > 0.0 && (fps - target).abs() <= config.processing.fps_tolerance) {
+        return;
+    }
+
+    let fps_filter = match config.processing.fps_policy {
+        FpsPolicy::Blend if fps > 0.0 && !config.processing.captions => {
+            format!("minterpolate=fps={target}:mi_mode=blend")
+        }
+        FpsPolicy::Interpolate if fps > 0.0 && !config.processing.captions => {
+            format!("minterpolate=fps={target}:mi_mode=mci:mc_mode=aobmc:vsbmc=1")
+        }
+        _ => match config.advanced.filter.fps.clone() {
+            Some(fps) => custom_format(&fps, &[&target]),
+            None => format!("fps={target}"),
+        },
+    };
+
+    chain.add(&fps_filter, 0, Video);
+}
+
+/// Interlace the progressive processed frames for the output.
+///
+/// With double frame rate (like 50p for 1080i50), two frames are woven to one,
+/// otherwise the frames are only flagged (PsF).
+fn interlace(config: &PlayoutConfig, chain: &mut Filters) {
+    let (mode, field) = match config.output.field_order {
+        FieldOrder::Progressive => return,
+        FieldOrder::Tff => ("interleave_top", "tff"),
+        FieldOrder::Bff => ("interleave_bottom", "bff"),
+    };
+
+    if config.processing.fps >= 48.0 {
+        chain.add(
+            &format!("tinterlace=mode={mode},fieldorder={field}"),
+            0,
+            Video,
+        );
+    } else {
+        chain.add(&format!("setfield={field}"), 0, Video);
     }
 }
 
@@ -412,9 +561,16 @@ fn scale(config: &PlayoutConfig, chain: &mut Filters, width: Option<i64>, height
     } else if width.is_some_and(|w| w != config.processing.width)
         || height.is_some_and(|w| w != config.processing.height)
     {
+        let scale = match config.advanced.hw_accel() {
+            Some("cuda") => "scale_npp",
+            Some("vaapi") => "scale_vaapi",
+            Some("qsv") => "scale_qsv",
+            _ => "scale",
+        };
+
         chain.add(
             &format!(
-                "scale={}:{}",
+                "{scale}={}:{}",
                 config.processing.width, config.processing.height
             ),
             0,
@@ -498,7 +654,12 @@ fn overlay(config: &PlayoutConfig, chain: &mut Filters, node: &mut Media) {
                 &[logo_path, config.processing.logo_opacity.to_string()],
             ),
             None => format!(
-                "movie={logo_path}:loop=0,setpts=N/(FRAME_RATE*TB),format=rgba,colorchannelmixer=aa={}",
+                "movie={logo_path}:loop=0,setpts=N/(FRAME_RATE*TB),format=rgba,colorchannelmixer{}=aa={}",
+                if config.processing.live_control {
+                    "@logo"
+                } else {
+                    ""
+                },
                 config.processing.logo_opacity,
             ),
         };
@@ -536,13 +697,31 @@ fn overlay(config: &PlayoutConfig, chain: &mut Filters, node: &mut Media) {
 
         let overlay = match &config.advanced.filter.overlay_logo {
             Some(ov) => custom_format(ov, &[&config.processing.logo_position]),
-            None => format!("overlay={}:shortest=1", config.processing.logo_position),
+            None => match config.advanced.hw_accel() {
+                Some(hw) => format!(
+                    "overlay_{hw}={}:shortest=1",
+                    config.processing.logo_position
+                ),
+                None => format!("overlay={}:shortest=1", config.processing.logo_position),
+            },
         };
 
         chain.add(&overlay, 0, Video);
     }
 }
 
+/// Cut the pre-roll of the two stage seek, so the clip starts exactly at the in point.
+fn trim(chain: &mut Filters, node: &Media, nr: i32, filter_type: FilterType) {
+    if node.pre_roll > 0.0 {
+        let filter = match filter_type {
+            Audio => format!("atrim=start={},asetpts=PTS-STARTPTS", node.pre_roll),
+            Video => format!("trim=start={},setpts=PTS-STARTPTS", node.pre_roll),
+        };
+
+        chain.add(&filter, nr, filter_type);
+    }
+}
+
 fn extend_video(config: &PlayoutConfig, chain: &mut Filters, node: &mut Media) {
     if let Some(video_duration) = node
         .probe
@@ -610,8 +789,50 @@ fn extend_audio(config: &PlayoutConfig, chain: &mut Filters, node: &mut Media, n
     }
 }
 
+/// Play clip faster, to catch up a delay in the playout, or a bit faster or slower,
+/// to fit into the slot of the item.
+///
+/// Frame rate gets restored after setpts, so the output stays constant.
+fn speed(
+    config: &PlayoutConfig,
+    chain: &mut Filters,
+    node: &Media,
+    nr: i32,
+    filter_type: FilterType,
+) {
+    if let Some(factor) = node.speed.filter(|s| *s > 0.0 && *s != 1.0) {
+        let filter = match filter_type {
+            Audio => format!("atempo={factor:.4}"),
+            Video => format!("setpts=PTS/{factor:.4},fps={}", config.processing.fps),
+        };
+
+        chain.add(&filter, nr, filter_type);
+    }
+}
+
+/// Keep audio in sync with the timestamps, small drift gets stretched,
+/// drift over the tolerance gets padded or trimmed.
+fn av_sync(config: &PlayoutConfig, chain: &mut Filters, nr: i32) {
+    if config.processing.av_sync_tolerance > 0 {
+        chain.add(
+            &format!(
+                "aresample=async=1000:min_hard_comp={}:first_pts=0",
+                config.processing.av_sync_tolerance as f64 / 1000.0
+            ),
+            nr,
+            Audio,
+        );
+    }
+}
+
 fn audio_volume(config: &PlayoutConfig, chain: &mut Filters, nr: i32) {
-    if config.processing.volume != 1.0 {
+    if config.processing.live_control {
+        chain.add(
+            &format!("volume@volume{nr}={}", config.processing.volume),
+            nr,
+            Audio,
+        );
+    } else if config.processing.volume != 1.0 {
         let volume = match config.advanced.filter.volume.clone() {
             Some(volume) => custom_format(&volume, &[config.processing.volume]),
             None => format!("volume={}", config.processing.volume),
@@ -621,6 +842,95 @@ fn audio_volume(config: &PlayoutConfig, chain: &mut Filters, nr: i32) {
     }
 }
 
+/// Audio chain of the day part, in which the clip starts.
+fn daypart_audio(config: &PlayoutConfig, chain: &mut Filters, node: &Media, nr: i32) {
+    if matches!(node.unit, Decoder | Ingest) {
+        let start = match node.unit {
+            Decoder => node
+                .begin
+                .unwrap_or_else(|| time_in_seconds(&config.channel.timezone)),
+            _ => time_in_seconds(&config.channel.timezone),
+        };
+
+        if let Some(filter) = audio_chains::chain_at(config, start) {
+            chain.add(&filter, nr, Audio);
+        }
+    }
+}
+
+/// Watermark encoder for audience measurement. Its latency gets padded at the end and
+/// trimmed at the start, so the audio stays in sync with the video.
+fn watermark(config: &PlayoutConfig, chain: &mut Filters, node: &Media, nr: i32) {
+    let filter = config.processing.watermark.trim();
+
+    if filter.is_empty() || !matches!(node.unit, Decoder | Ingest) {
+        return;
+    }
+
+    if config.processing.watermark_latency > 0 {
+        let latency = config.processing.watermark_latency as f64 / 1000.0;
+
+        chain.add(
+            &format!("apad=pad_dur={latency},{filter},atrim=start={latency},asetpts=PTS-STARTPTS"),
+            nr,
+            Audio,
+        );
+    } else {
+        chain.add(filter, nr, Audio);
+    }
+}
+
+/// Socket for runtime commands from the control API, the zmq filter sends them to the whole graph.
+fn live_control(
+    config: &PlayoutConfig,
+    chain: &mut Filters,
+    node: &Media,
+    nr: i32,
+    filter_type: FilterType,
+) {
+    let socket = match node.unit {
+        Ingest => config.processing.zmq_server_socket.as_ref(),
+        _ => config.processing.zmq_stream_socket.as_ref(),
+    };
+
+    if let Some(socket) = socket {
+        let zmq = if filter_type == Video { "zmq" } else { "azmq" };
+
+        chain.add(
+            &format!("{zmq}=b=tcp\\\\://'{}'", socket.replace(':', "\\:")),
+            nr,
+            filter_type,
+        );
+    }
+}
+
+/// Burn in the wall clock timecode.
+fn timecode(config: &PlayoutConfig, chain: &mut Filters) {
+    if config.processing.timecode_burn_in {
+        let time = time_now(&config.channel.timezone).time();
+
+        chain.add(&burn_in_filter(config, &time), 0, Video);
+    }
+}
+
+/// LTC on one channel of the first audio track.
+fn ltc(config: &PlayoutConfig, chain: &mut Filters, nr: i32) {
+    if config.processing.ltc_channel > 0
+        && config.processing.ltc_channel <= config.processing.audio_channels
+    {
+        let time = time_now(&config.channel.timezone).time();
+
+        chain.add(&ltc_filter(config, &time), nr, Audio);
+    }
+}
+
+/// Loudness meter on the first audio track, the values are read from stderr.
+fn audio_meter(config: &PlayoutConfig, chain: &mut Filters, node: &Media, nr: i32) {
+    if config.processing.audio_meter && matches!(node.unit, Decoder | Ingest) {
+        chain.add(METER_FILTER, nr, Audio);
+    }
+}
+
 pub fn split_filter(config: &PlayoutConfig, chain: &mut Filters, nr: i32, filter_type: FilterType) {
     let count = config.output.output_count;
 
@@ -646,6 +956,25 @@ pub fn split_filter(config: &PlayoutConfig, chain: &mut Filters, nr: i32, filter
     }
 }
 
+/// Split the video for the compliance recording, after the filters of the encoder.
+fn compliance_split(config: &PlayoutConfig, chain: &mut Filters) {
+    if !compliance::enabled(config) || config.processing.audio_only {
+        return;
+    }
+
+    if let Some(link) = chain.video_out_link.first_mut() {
+        chain
+            .video_chain
+            .push_str(&format!(";{link}split=2[vmain]{COMPLIANCE_LINK}"));
+        *link = "[vmain]".to_string();
+    } else if !chain.video_chain.is_empty() {
+        chain
+            .video_chain
+            .push_str(&format!(",split=2[vmain]{COMPLIANCE_LINK}"));
+        chain.video_out_link.push("[vmain]".to_string());
+    }
+}
+
 /// Process output filter chain and add new filters to existing ones.
 fn process_output_filters(config: &PlayoutConfig, chain: &mut Filters, output_filter: &str) {
     let re_v = Regex::new(r"\[[0:]+[v^\[]+([:0]+)?\]").unwrap(); // match video filter input link
@@ -725,27 +1054,45 @@ pub async fn filter_chains(
         return filters;
     }
 
-    if node.source.contains("color=c=") {
+    if is_generated(&node.source) {
         filters.audio_position = 1;
     }
 
     if node.unit == Encoder {
         if !config.processing.audio_only {
             add_text(config, &mut filters, node, filter_chain).await;
+            graphics::overlays(config, &mut filters);
+
+            if let Some(ticker) = ticker::filter(config) {
+                filters.add(&ticker, 0, Video);
+            }
+
+            interlace(config, &mut filters);
         }
 
         if let Some(f) = config.output.output_filter.clone() {
             process_output_filters(config, &mut filters, &f);
-        } else if config.output.output_count > 1 && !config.processing.audio_only {
-            split_filter(config, &mut filters, 0, Video);
+        } else if !config.processing.audio_only {
+            alternate::control(config, &mut filters);
+
+            if config.output.output_count > 1 {
+                split_filter(config, &mut filters, 0, Video);
+            }
         }
 
         filters.build();
+        alternate::outputs(config, &mut filters);
+
+        if config.output.output_filter.is_none() {
+            compliance_split(config, &mut filters);
+        }
 
         return filters;
     }
 
     if !config.processing.audio_only && !config.processing.copy_video {
+        trim(&mut filters, node, 0, Video);
+
         if let Some(probe) = node.probe.as_ref() {
             if Path::new(&node.audio).is_file() {
                 filters.audio_position = 1;
@@ -756,6 +1103,7 @@ pub async fn filter_chains(
                 let frame_per_sec = fps_calc(&v_stream.frame_rate, 1.0);
 
                 deinterlace(config, &mut filters, &v_stream.field_order);
+                tonemap(config, &mut filters, v_stream);
                 pad(config, &mut filters, aspect);
                 fps(config, &mut filters, frame_per_sec);
                 scale(config, &mut filters, v_stream.width, v_stream.height);
@@ -771,6 +1119,7 @@ pub async fn filter_chains(
         add_text(config, &mut filters, node, filter_chain).await;
         fade(config, &mut filters, node, 0, Video);
         overlay(config, &mut filters, node);
+        speed(config, &mut filters, node, 0, Video);
     }
 
     let (proc_vf, proc_af) = if node.unit == Ingest {
@@ -784,6 +1133,11 @@ pub async fn filter_chains(
     if !config.processing.copy_video {
         custom(&proc_vf, &mut filters, 0, Video);
         custom(&list_vf, &mut filters, 0, Video);
+
+        if !config.processing.audio_only {
+            timecode(config, &mut filters);
+            live_control(config, &mut filters, node, 0, Video);
+        }
     }
 
     let mut audio_indexes = vec![];
@@ -796,17 +1150,36 @@ pub async fn filter_chains(
         audio_indexes.push(config.processing.audio_track_index);
     }
 
+    // a separate audio file keeps its order
+    if !Path::new(&node.audio).is_file() {
+        filters.audio_source = (0..config.processing.audio_tracks)
+            .map(|i| source_track(config, node.probe.as_ref(), i))
+            .collect();
+    }
+
+    // the description track starts with the program audio
+    if let Some(track) = description::track(&config.processing) {
+        if filters.audio_source.is_empty() {
+            filters.audio_source = (0..config.processing.audio_tracks).collect();
+        }
+
+        filters.audio_source[track as usize] = filters.source(0);
+    }
+
     if !config.processing.copy_audio {
+        let meter_track = audio_indexes.first().copied();
+
         for i in audio_indexes {
             if node
                 .probe
                 .as_ref()
-                .and_then(|p| p.audio.get(i as usize))
+                .and_then(|p| p.audio.get(filters.source(i) as usize))
                 .is_some()
                 || Path::new(&node.audio).is_file()
             {
+                trim(&mut filters, node, i, Audio);
                 extend_audio(config, &mut filters, node, i);
-            } else if node.unit == Decoder && !node.source.contains("color=c=") {
+            } else if node.unit == Decoder && !is_generated(&node.source) {
                 error!(target: Target::file_mail(), channel = config.general.channel_id;
                     "Missing audio track (id {i}) from <span class=\"log-addr\">{}</span>",
                     node.source
@@ -818,21 +1191,39 @@ pub async fn filter_chains(
             // add at least anull filter, for correct filter construction,
             // is important for split filter in HLS mode
             filters.add("anull", i, Audio);
+            description::mix(config, &mut filters, node, i);
 
+            av_sync(config, &mut filters, i);
             fade(config, &mut filters, node, i, Audio);
             audio_volume(config, &mut filters, i);
+            daypart_audio(config, &mut filters, node, i);
+            speed(config, &mut filters, node, i, Audio);
 
             custom(&proc_af, &mut filters, i, Audio);
             custom(&list_af, &mut filters, i, Audio);
+            watermark(config, &mut filters, node, i);
+
+            if meter_track == Some(i) {
+                audio_meter(config, &mut filters, node, i);
+                ltc(config, &mut filters, i);
+
+                if config.processing.audio_only || config.processing.copy_video {
+                    live_control(config, &mut filters, node, i, Audio);
+                }
+            }
         }
     } else if config.processing.audio_track_index > -1 {
         error!(target: Target::file_mail(), channel = config.general.channel_id; "Setting 'audio_track_index' other than '-1' is not allowed in audio copy mode!");
     }
 
-    if config.output.mode == HLS
-        && let Some(f) = config.output.output_filter.clone()
-    {
-        process_output_filters(config, &mut filters, &f);
+    if config.output.mode == HLS {
+        if !config.processing.audio_only && !config.processing.copy_video {
+            interlace(config, &mut filters);
+        }
+
+        if let Some(f) = config.output.output_filter.clone() {
+            process_output_filters(config, &mut filters, &f);
+        }
     }
 
     filters.build();
@@ -8,9 +8,9 @@ use tokio_stream::StreamExt;
 
 use crate::player::{
     controller::ChannelManager,
-    utils::{Media, include_file_extension, time_in_seconds},
+    utils::{Media, include_file_extension, time_in_seconds, upload_pending},
 };
-use crate::utils::{config::PlayoutConfig, logging::Target};
+use crate::utils::{config::PlayoutConfig, library, logging::Target, time_machine::time_now};
 
 /// Folder Sources
 ///
@@ -49,6 +49,14 @@ impl FolderSource {
 
             while let Some(Ok(entry)) = entries.next().await {
                 if entry.path().is_file() && include_file_extension(config, &entry.path()) {
+                    if upload_pending(config, &entry.path()) {
+                        // the folder watcher adds it, when the upload is done
+                        debug!(target: Target::file_mail(), channel = id;
+                            "Skip file in upload: <span class=\"log-addr\">{:?}</span>", entry.path()
+                        );
+                        continue;
+                    }
+
                     let media = Media::new(0, &entry.path().to_string_lossy(), false).await;
                     media_list.push(media);
                 }
@@ -117,7 +125,40 @@ impl FolderSource {
 
 /// Create iterator for folder source
 impl FolderSource {
+    /// Next file, files in quarantine and files within the separation time are skipped.
     pub async fn next(&mut self) -> Option<Media> {
+        let config = self.manager.config.read().await.clone();
+        let count = self.manager.current_list.lock().await.len();
+        let date = time_now(&config.channel.timezone).date_naive();
+        let recent = library::recently_aired(&self.manager.db_pool, &config).await;
+
+        for _ in 0..count {
+            let node = self.next_node().await?;
+
+            if self
+                .manager
+                .quarantine
+                .lock()
+                .await
+                .contains(&node.source, date)
+            {
+                warn!(target: Target::file_mail(), channel = config.general.channel_id;
+                    "Skip clip in quarantine: <span class=\"log-addr\">{}</span>", node.source
+                );
+            } else if recent.contains(&node.source) {
+                debug!(target: Target::file_mail(), channel = config.general.channel_id;
+                    "Skip clip within separation time: <span class=\"log-addr\">{}</span>", node.source
+                );
+            } else {
+                return Some(node);
+            }
+        }
+
+        // all files are in quarantine or aired recently, play them anyway
+        self.next_node().await
+    }
+
+    async fn next_node(&mut self) -> Option<Media> {
         let config = self.manager.config.read().await.clone();
         let id = config.general.id;
 
@@ -1,20 +1,24 @@
-use std::{process::Stdio, sync::atomic::Ordering};
+use std::{path::PathBuf, sync::atomic::Ordering};
 
 use log::*;
 use tokio::{
+    fs,
     io::{AsyncBufReadExt, BufReader},
-    process::{ChildStderr, Command},
+    process::ChildStderr,
 };
 
 use crate::utils::{
-    config::{FFMPEG_IGNORE_ERRORS, FFMPEG_UNRECOVERABLE_ERRORS, PlayoutConfig},
+    config::{FFMPEG_IGNORE_ERRORS, FFMPEG_UNRECOVERABLE_ERRORS, PlayoutConfig, channel_path},
     logging::{Target, log_line},
 };
 use crate::vec_strings;
 use crate::{
+    file::norm_abs_path,
     player::{
+        commands::ingest_cmd,
         controller::{ChannelManager, ProcessUnit::*},
-        utils::{Media, is_free_tcp_port, valid_stream},
+        process::{Pipe, ResourceLimits},
+        utils::{Media, ffmpeg_bin, is_free_tcp_port, valid_stream},
     },
     utils::{errors::ServiceError, logging::fmt_cmd},
 };
@@ -30,6 +34,10 @@ async fn server_monitor(
 
     let mut lines = buffer.lines();
     while let Ok(Some(line)) = lines.next_line().await {
+        if manager.audio_meter.lock().await.update(&line) {
+            continue;
+        }
+
         if !FFMPEG_IGNORE_ERRORS.iter().any(|i| line.contains(*i))
             && !ignore.iter().any(|i| line.contains(i))
         {
@@ -46,6 +54,14 @@ async fn server_monitor(
 
             break;
         } else if !is_running && line.contains("Input #0") {
+            if manager.ingest_hold.load(Ordering::SeqCst) {
+                warn!(target: Target::file_mail(), channel = id; "Live ingest is on hold, drop stream");
+
+                manager.stop(Ingest).await;
+
+                break;
+            }
+
             manager.ingest_is_alive.store(true, Ordering::SeqCst);
 
             is_running = true;
@@ -64,6 +80,113 @@ async fn server_monitor(
     Ok(())
 }
 
+pub const INGEST_PREVIEW: &str = "ingest.m3u8";
+
+/// Extra output for the ingest server: a small HLS stream from the raw ingest feed,
+/// so the operator can check the contribution. It is served under `/<id>/preview/ingest.m3u8`.
+pub fn ingest_preview(config: &PlayoutConfig) -> Vec<String> {
+    if !config.ingest.preview {
+        return vec![];
+    }
+
+    let playlist = config.channel.public.join("preview").join(INGEST_PREVIEW);
+
+    vec_strings![
+        "-map",
+        "0:v:0?",
+        "-map",
+        "0:a:0?",
+        "-vf",
+        "scale=-2:360",
+        "-c:v",
+        "libx264",
+        "-preset",
+        "veryfast",
+        "-tune",
+        "zerolatency",
+        "-b:v",
+        "600k",
+        "-c:a",
+        "aac",
+        "-b:a",
+        "64k",
+        "-ac",
+        "2",
+        "-f",
+        "hls",
+        "-hls_time",
+        "2",
+        "-hls_list_size",
+        "5",
+        "-hls_flags",
+        "delete_segments+omit_endlist",
+        playlist.to_string_lossy()
+    ]
+}
+
+/// Folder for ingest recordings, relative paths are inside the storage.
+pub fn record_folder(config: &PlayoutConfig) -> PathBuf {
+    norm_abs_path(
+        &config.channel.storage,
+        &channel_path(config, &config.ingest.record_path),
+    )
+    .map(|(path, _, _)| path)
+    .unwrap_or_else(|_| config.channel.storage.join("recordings"))
+}
+
+/// Extra output for the ingest server: the raw contribution, without re-encoding.
+///
+/// The segment muxer opens the file when the stream comes in, so the name has the start time of the show.
+pub fn ingest_record(config: &PlayoutConfig) -> Vec<String> {
+    if !config.ingest.record {
+        return vec![];
+    }
+
+    let file = record_folder(config).join("live_%Y-%m-%d_%H-%M-%S.mkv");
+
+    vec_strings![
+        "-map",
+        "0:v?",
+        "-map",
+        "0:a?",
+        "-c",
+        "copy",
+        "-f",
+        "segment",
+        "-segment_time",
+        "86400",
+        "-segment_format",
+        "matroska",
+        "-strftime",
+        "1",
+        "-reset_timestamps",
+        "1",
+        file.to_string_lossy()
+    ]
+}
+
+/// Create the folders for preview and recordings,
+/// and remove an old preview playlist, so no stale feed is shown.
+pub async fn prepare_ingest_outputs(config: &PlayoutConfig) {
+    let id = config.general.channel_id;
+
+    if config.ingest.preview {
+        let folder = config.channel.public.join("preview");
+
+        if let Err(e) = fs::create_dir_all(&folder).await {
+            error!(target: Target::file_mail(), channel = id; "Ingest preview folder: {e}");
+        }
+
+        let _ = fs::remove_file(folder.join(INGEST_PREVIEW)).await;
+    }
+
+    if config.ingest.record
+        && let Err(e) = fs::create_dir_all(record_folder(config)).await
+    {
+        error!(target: Target::file_mail(), channel = id; "Ingest record folder: {e}");
+    }
+}
+
 /// ffmpeg Ingest Server
 ///
 /// Start ffmpeg in listen mode, and wait for input.
@@ -72,40 +195,13 @@ pub async fn ingest_server(
     manager: ChannelManager,
 ) -> Result<(), ServiceError> {
     let id = config.general.channel_id;
-    let mut server_cmd = vec_strings!["-hide_banner", "-nostats", "-v", "level+info"];
     let stream_input = config.ingest.input_cmd.clone().unwrap();
     let mut dummy_media = Media::new(0, "Live Stream", false).await;
     dummy_media.unit = Ingest;
     dummy_media.add_filter(&config, &None).await;
     let is_alive = manager.is_alive.clone();
     let ingest_is_alive = manager.ingest_is_alive.clone();
-    let vtt_dummy = config
-        .channel
-        .storage
-        .join(config.processing.vtt_dummy.clone().unwrap_or_default());
-
-    if let Some(ingest_input_cmd) = config.advanced.ingest.input_cmd {
-        server_cmd.append(&mut ingest_input_cmd.clone());
-    }
-
-    server_cmd.append(&mut stream_input.clone());
-
-    if config.processing.vtt_enable && vtt_dummy.is_file() {
-        server_cmd.append(&mut vec_strings!["-i", vtt_dummy.to_string_lossy()]);
-    }
-
-    if let Some(mut filter) = dummy_media.filter {
-        server_cmd.append(&mut filter.cmd());
-        server_cmd.append(&mut filter.map());
-    }
-
-    if config.processing.vtt_enable && vtt_dummy.is_file() {
-        server_cmd.append(&mut vec_strings!("-map", "1:s", "-c:s", "copy"));
-    }
-
-    if let Some(mut cmd) = config.processing.cmd {
-        server_cmd.append(&mut cmd);
-    }
+    let server_cmd = ingest_cmd(&config, &dummy_media.filter);
 
     debug!(target: Target::file_mail(), channel = id;
         "Server CMD: <span class=\"log-cmd\">ffmpeg {}</span>",
@@ -134,16 +230,18 @@ pub async fn ingest_server(
         info!(target: Target::file_mail(), channel = id; "Start ingest server, listening on: <span class=\"log-addr\">{url}</span>");
     };
 
+    let limits = ResourceLimits::new(&config);
+
     while is_alive.load(Ordering::SeqCst) {
+        prepare_ingest_outputs(&config).await;
+
         let proc_ctl = manager.clone();
         let level = config.logging.ingest_level.clone();
         let ignore = config.logging.ignore_lines.clone();
-        let mut server_proc = Command::new("ffmpeg")
-            .args(server_cmd.clone())
-            .kill_on_drop(true)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
+        let mut server_proc =
+            manager
+                .spawner
+                .spawn(Ingest, ffmpeg_bin(), &server_cmd, Pipe::Stdout, &limits)?;
         let ingest_stdout = server_proc.stdout.take().unwrap();
         let server_err = BufReader::new(server_proc.stderr.take().unwrap());
 
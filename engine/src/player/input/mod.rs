@@ -3,16 +3,22 @@ use log::*;
 pub mod folder;
 pub mod ingest;
 pub mod playlist;
+pub mod test_pattern;
 
 pub use ingest::ingest_server;
-pub use playlist::CurrentProgram;
+pub use playlist::{CurrentProgram, watch_playlist};
 
-use crate::player::{controller::ChannelManager, input::folder::FolderSource, utils::Media};
+use crate::player::{
+    controller::ChannelManager,
+    input::{folder::FolderSource, test_pattern::TestPatternSource},
+    utils::Media,
+};
 use crate::utils::{config::ProcessMode::*, logging::Target};
 
 pub enum SourceIterator {
     Folder(Box<FolderSource>),
     Playlist(Box<CurrentProgram>),
+    Test(Box<TestPatternSource>),
 }
 
 impl SourceIterator {
@@ -20,11 +26,12 @@ impl SourceIterator {
         match self {
             SourceIterator::Folder(folder_source) => folder_source.next().await,
             SourceIterator::Playlist(program) => program.next().await,
+            SourceIterator::Test(pattern) => pattern.next().await,
         }
     }
 }
 
-/// Create a source iterator from playlist, or from folder.
+/// Create a source iterator from playlist, from folder, or with the test pattern.
 pub async fn source_generator(manager: ChannelManager) -> SourceIterator {
     let config = manager.config.read().await.clone();
     let id = config.general.channel_id;
@@ -47,9 +54,16 @@ pub async fn source_generator(manager: ChannelManager) -> SourceIterator {
         }
         Playlist => {
             info!(target: Target::file_mail(), channel = id; "Playout in playlist mode");
+            tokio::spawn(watch_playlist(manager.clone()));
+
             let program = CurrentProgram::new(manager);
 
             SourceIterator::Playlist(Box::new(program.await))
         }
+        Test => {
+            info!(target: Target::file_mail(), channel = id; "Playout in test mode");
+
+            SourceIterator::Test(Box::new(TestPatternSource::new(manager).await))
+        }
     }
 }
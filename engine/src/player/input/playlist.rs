@@ -1,31 +1,418 @@
 use std::{
-    path::Path,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
     },
+    time::{Duration, Instant},
 };
 
 use log::*;
 
 use crate::db::handles;
+use crate::file::backend::{Backend, StorageBackend, is_storage_url};
 use crate::player::{
-    controller::ChannelManager,
+    controller::{ChannelManager, ProcessUnit::Decoder},
     utils::{
-        JsonPlaylist, Media, gen_dummy, get_delta, is_close, is_remote,
-        json_serializer::{read_json, set_defaults},
-        loop_filler, loop_image, modified_time,
+        JsonPlaylist, Media, Origin, gen_dummy, gen_next_card, gen_slate, gen_test_pattern,
+        get_date, get_delta, is_close, is_filler, is_generated, is_remote, json_reader,
+        json_serializer::{
+            expand_loops, fit_hard_starts, next_hard_start, read_json, set_defaults,
+        },
+        loop_filler, loop_image, modified_time, playlist_seconds,
         probe::MediaProbe,
-        seek_and_length, time_in_seconds,
+        resume, sec_to_time, seek_and_length, target_length, time_in_seconds,
     },
 };
 use crate::utils::{
-    config::{IMAGE_FORMAT, PlayoutConfig},
+    calendar,
+    config::{FallbackSource, IMAGE_FORMAT, JoinPolicy, PlayoutConfig, RecoveryStrategy},
+    control::{InsertPolicy, insert_media, resume_media},
+    hooks::{self, HookEvent},
     logging::Target,
+    time_machine::time_now,
 };
 
 const NEXT_START_THRESHOLD: f64 = 1.5;
 const IS_CLOSE_THRESHOLD: f64 = 2.0;
+const RECOVERY_MIN_DELAY: f64 = 0.5;
+/// Time between the checks for a missing playlist.
+const MISSING_RETRY: Duration = Duration::from_secs(10);
+/// Category of the filler or slate, while the schedule is on hold.
+pub const HOLD: &str = "hold";
+const HOLD_LENGTH: f64 = 60.0;
+/// Length of the "Up next" card.
+pub const NEXT_CARD_LENGTH: f64 = 10.0;
+pub const NEXT_CARD: &str = "next_card";
+
+/// Bumper around an ad break.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bumper {
+    /// Before the first ad.
+    In,
+    /// After the last ad.
+    Out,
+}
+
+fn is_ad(media: &Media) -> bool {
+    media.category == "advertisement"
+}
+
+/// Bumper, which belongs in front of the item at `index`.
+pub fn bumper_at(list: &[Media], index: usize) -> Option<Bumper> {
+    let (Some(last), Some(media)) = (
+        index.checked_sub(1).and_then(|i| list.get(i)),
+        list.get(index),
+    ) else {
+        return None;
+    };
+
+    if last.category == "bumper" || media.category == "bumper" {
+        None
+    } else if is_ad(media) && !is_ad(last) {
+        Some(Bumper::In)
+    } else if !is_ad(media) && is_ad(last) {
+        Some(Bumper::Out)
+    } else {
+        None
+    }
+}
+
+/// Put an "Up next" card at the end of the filler at `index`, when the next item is a program.
+///
+/// The filler gets shorter by the card, a filler which is not much longer than the card
+/// gets replaced. Returns the title on the card.
+pub fn insert_next_card(
+    list: &mut Vec<Media>,
+    index: usize,
+    is_filler: impl Fn(&Media) -> bool,
+) -> Option<String> {
+    let (Some(gap), Some(next)) = (list.get(index), list.get(index + 1)) else {
+        return None;
+    };
+
+    if !is_filler(gap) || is_filler(next) || next.category == NEXT_CARD {
+        return None;
+    }
+
+    let slack = gap.length();
+
+    if slack < NEXT_CARD_LENGTH {
+        return None;
+    }
+
+    let title = next
+        .title
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .or_else(|| {
+            Path::new(&next.source)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+        })?;
+    let length = if slack - NEXT_CARD_LENGTH < 1.0 {
+        slack
+    } else {
+        NEXT_CARD_LENGTH
+    };
+    let card = Media {
+        begin: gap.begin.map(|b| b + slack - length),
+        category: NEXT_CARD.to_string(),
+        title: Some(title.clone()),
+        duration: length,
+        out: length,
+        origin: Origin::Inserted,
+        ..Media::default()
+    };
+
+    if length < slack {
+        list[index].out -= length * list[index].speed.unwrap_or(1.0);
+        list.insert(index + 1, card);
+    } else {
+        list[index] = card;
+    }
+
+    for (i, item) in list.iter_mut().enumerate().skip(index) {
+        item.index = Some(i);
+    }
+
+    Some(title)
+}
+
+/// Merge a changed program into the running one.
+///
+/// All items before `index` are already played, or are playing at the moment, so they are taken from the current list.
+/// When the new program differs in that range, the last played item gets searched in the new program
+/// and only the items after it are taken. The second return value is true, when such a conflict happens.
+///
+/// Pending items, which are inserted or changed at runtime, are not in the file. They are kept
+/// behind the file item, which they follow in the current list.
+pub fn merge_program(current: &[Media], new: Vec<Media>, index: usize) -> (Vec<Media>, bool) {
+    let index = index.min(current.len());
+    let (played, pending) = current.split_at(index);
+    let from_file: Vec<&Media> = played
+        .iter()
+        .filter(|m| m.origin != Origin::Inserted)
+        .collect();
+    let conflict = new.len() < from_file.len()
+        || from_file.iter().zip(&new).any(|(c, n)| {
+            c.origin == Origin::File && (c.source != n.source || c.seek != n.seek || c.out != n.out)
+        });
+
+    // take the last played item, which also exists in the new program, as anchor
+    let split = from_file
+        .iter()
+        .enumerate()
+        .rev()
+        .find_map(|(p, playing)| {
+            new.iter()
+                .enumerate()
+                .filter(|(_, n)| n.source == playing.source)
+                .min_by_key(|(i, _)| i.abs_diff(p))
+                .map(|(i, _)| i + 1)
+        })
+        .unwrap_or(from_file.len());
+
+    let mut tail: Vec<Media> = new.into_iter().skip(split).collect();
+    let mut position = 0;
+
+    for item in pending {
+        let found = |source: &str, seek: Option<f64>| {
+            tail[position..]
+                .iter()
+                .position(|n| n.source == source && seek.is_none_or(|s| n.seek == s))
+                .map(|i| position + i)
+        };
+
+        match item.origin {
+            Origin::File => {
+                if let Some(i) = found(&item.source, Some(item.seek)) {
+                    position = i + 1;
+                }
+            }
+            Origin::Inserted => {
+                tail.insert(position, item.clone());
+                position += 1;
+            }
+            Origin::Changed => {
+                if let Some(i) = found(&item.source, None) {
+                    tail.drain(position..=i);
+                }
+
+                tail.insert(position, item.clone());
+                position += 1;
+            }
+        }
+    }
+
+    let mut program = played.to_vec();
+    program.append(&mut tail);
+
+    for (i, item) in program.iter_mut().enumerate() {
+        item.index = Some(i);
+    }
+
+    (program, conflict)
+}
+
+/// Shorten or remove the next filler clips after `from`, until `delay` is recovered.
+///
+/// Searching stops at the next hard start item, the item in front of it gets trimmed
+/// in any case, see [`hard_overrun`]. Returns the recovered seconds.
+pub fn drop_filler(
+    list: &mut Vec<Media>,
+    from: usize,
+    delay: f64,
+    is_filler: impl Fn(&Media) -> bool,
+) -> f64 {
+    let mut remaining = delay;
+    let mut i = from;
+
+    while i < list.len() && remaining > 0.0 && list[i].start.is_soft() {
+        if is_filler(&list[i]) {
+            let length = list[i].out - list[i].seek;
+
+            if length <= remaining {
+                remaining -= length;
+                list.remove(i);
+                continue;
+            }
+
+            // the shortened filler starts later, but ends at its planned time
+            list[i].out -= remaining;
+            list[i].begin = list[i].begin.map(|b| b + remaining);
+            remaining = 0.0;
+        }
+
+        i += 1;
+    }
+
+    for (index, item) in list.iter_mut().enumerate().skip(from) {
+        item.index = Some(index);
+    }
+
+    delay - remaining
+}
+
+/// Speed factor for a clip with `length`, to catch up `delay` seconds, limited by `max_speed`.
+pub fn catch_up_speed(length: f64, delay: f64, max_speed: f64) -> f64 {
+    if length <= delay || length <= 0.0 {
+        return max_speed.max(1.0);
+    }
+
+    (length / (length - delay)).clamp(1.0, max_speed.max(1.0))
+}
+
+/// The item should be skipped, when the playout is `delay` seconds behind.
+///
+/// Only soft items can be skipped, hard items keep their clock time.
+pub fn skip_late(node: &Media, delay: f64) -> bool {
+    node.start.is_soft() && node.skip_late.is_some_and(|limit| delay > limit)
+}
+
+/// Seconds, which the soft `node` at `index` runs into the hard start item directly after it,
+/// when `node` begins at `begin`.
+pub fn hard_overrun(node: &Media, list: &[Media], index: usize, begin: f64, start_sec: f64) -> f64 {
+    if !node.start.is_soft() {
+        return 0.0;
+    }
+
+    match next_hard_start(list, index + 1, start_sec) {
+        Some((i, target)) if i == index + 1 => (begin + node.length() - target).max(0.0),
+        _ => 0.0,
+    }
+}
+
+/// Playlist file of the date, `None` when it is no date.
+fn playlist_file(config: &PlayoutConfig, date: &str) -> Option<PathBuf> {
+    let d: Vec<&str> = date.split('-').collect();
+
+    (d.len() == 3).then(|| {
+        config
+            .channel
+            .playlists
+            .join(d[0])
+            .join(d[1])
+            .join(format!("{date}.json"))
+    })
+}
+
+/// Switch to a missing playlist, as soon as it is there, also in the middle of the day.
+async fn retry_missing(manager: &ChannelManager, config: &PlayoutConfig, date: &str) {
+    let Some(path) = playlist_file(config, date) else {
+        return;
+    };
+    let path = path.to_string_lossy();
+
+    if modified_time(&path).await.is_some() {
+        info!(target: Target::file_mail(), channel = config.general.channel_id;
+            "Playlist <span class=\"log-addr\">{path}</span> is there now, switch to it"
+        );
+
+        manager.list_init.store(true, Ordering::SeqCst);
+        manager.stop(Decoder).await;
+    }
+}
+
+/// Read a changed playlist, local or remote.
+async fn read_changed(path: &str) -> Result<JsonPlaylist, String> {
+    if is_remote(path) || is_storage_url(path) {
+        let object = Backend::new(path)
+            .read(path)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        serde_json::from_slice(&object.data).map_err(|e| e.to_string())
+    } else {
+        json_reader(&PathBuf::from(path))
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Watch the playlist from the current day and merge changes into the running program,
+/// so future items are updated immediately and not only at the next clip change.
+///
+/// This is the only place, where changes get merged; the playlist iterator reads a playlist
+/// only when it starts or the day changes.
+pub async fn watch_playlist(manager: ChannelManager) {
+    let mut retried = Instant::now();
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+
+        let config = manager.config.read().await.clone();
+        let missing = manager.missing_playlist.lock().await.clone();
+
+        if let Some(missing) = missing {
+            if retried.elapsed() >= MISSING_RETRY {
+                retried = Instant::now();
+                retry_missing(&manager, &config, &missing).await;
+            }
+
+            continue;
+        }
+
+        if manager.list_init.load(Ordering::SeqCst) {
+            continue;
+        }
+
+        let Some((path, loaded)) = manager.loaded_playlist.lock().await.clone() else {
+            continue;
+        };
+
+        let modified = modified_time(&path).await;
+
+        if modified.is_none() || modified == loaded {
+            continue;
+        }
+
+        let mut changed = read_changed(&path).await;
+
+        if let Ok(playlist) = changed.as_mut() {
+            playlist.start_sec = config.playlist.start_sec;
+            expand_loops(&config, playlist).await;
+            set_defaults(&config, playlist);
+        }
+
+        // the iterator could load another playlist in the meantime
+        let mut loaded_playlist = manager.loaded_playlist.lock().await;
+
+        if loaded_playlist.as_ref().map(|(p, _)| p) != Some(&path) {
+            continue;
+        }
+
+        // a broken file is reported once, not on every check
+        *loaded_playlist = Some((path.clone(), modified));
+
+        let mut playlist = match changed {
+            Ok(p) => p,
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = config.general.channel_id; "Changed playlist not readable: {e}");
+                continue;
+            }
+        };
+
+        let mut current_list = manager.current_list.lock().await;
+        let index = manager.current_index.load(Ordering::SeqCst);
+        let (program, conflict) = merge_program(&current_list, playlist.program, index);
+
+        if conflict {
+            warn!(target: Target::file_mail(), channel = config.general.channel_id; "Changes on already played items are ignored");
+        }
+
+        playlist.program = program;
+        playlist.start_sec = config.playlist.start_sec;
+        set_defaults(&config, &mut playlist);
+
+        info!(target: Target::file_mail(), channel = config.general.channel_id;
+            "Merge changes from playlist <span class=\"log-addr\">{path}</span>"
+        );
+
+        *current_list = playlist.program;
+    }
+}
 
 /// Struct for current playlist.
 ///
@@ -36,12 +423,12 @@ pub struct CurrentProgram {
     config: PlayoutConfig,
     manager: ChannelManager,
     start_sec: f64,
-    length_sec: f64,
     json_playlist: JsonPlaylist,
     current_node: Media,
     is_alive: Arc<AtomicBool>,
     last_json_path: Option<String>,
     last_node_ad: bool,
+    fallback: Option<(usize, Instant)>,
 }
 
 /// Prepare a playlist iterator.
@@ -55,7 +442,6 @@ impl CurrentProgram {
             config: config.clone(),
             manager,
             start_sec: config.playlist.start_sec.unwrap(),
-            length_sec: config.playlist.length_sec.unwrap(),
             json_playlist: JsonPlaylist::new(
                 "1970-01-01".to_string(),
                 config.playlist.start_sec.unwrap(),
@@ -64,58 +450,108 @@ impl CurrentProgram {
             is_alive,
             last_json_path: None,
             last_node_ad: false,
+            fallback: None,
         }
     }
 
-    // Check if there is no current playlist or file got updated,
-    // and when is so load/reload it.
-    async fn load_or_update_playlist(&mut self, seek: bool) {
-        let mut get_current = false;
-        let mut reload = false;
+    // Generate the missing playlist from calendar, when a calendar exists.
+    async fn playlist_from_calendar(&self, seek: bool, get_next: bool) {
+        let date = get_date(
+            seek,
+            self.start_sec,
+            get_next,
+            &self.config.channel.timezone,
+        );
 
-        if let Some(path) = self.json_playlist.path.clone() {
-            if (Path::new(&path).is_file() || is_remote(&path))
-                && self.json_playlist.modified != modified_time(&path).await
-            {
-                info!(target: Target::file_mail(), channel = self.channel_id; "Reload playlist <span class=\"log-addr\">{path}</span>");
-                self.manager.list_init.store(true, Ordering::SeqCst);
-                get_current = true;
-                reload = true;
+        if let Err(e) = calendar::materialize(&self.manager, &date).await {
+            error!(target: Target::file_mail(), channel = self.channel_id; "Generate playlist from calendar failed: {e}");
+        }
+    }
+
+    /// Alert once, when the playlist of the day is missing. The fallback plays until it is
+    /// there, the playlist watcher retries it.
+    async fn check_missing(&self) {
+        let date = &self.json_playlist.date;
+        let mut missing = self.manager.missing_playlist.lock().await;
+
+        match (&self.json_playlist.path, missing.as_ref()) {
+            (None, last) if last != Some(date) => {
+                error!(target: Target::file_mail(), channel = self.channel_id;
+                    "Playlist for <span class=\"log-number\">{date}</span> not exist, the fallback plays until it is there!"
+                );
+
+                *missing = Some(date.clone());
             }
-        } else {
-            get_current = true;
+            (Some(path), Some(_)) => {
+                info!(target: Target::file_mail(), channel = self.channel_id;
+                    "Missing playlist <span class=\"log-addr\">{path}</span> is loaded"
+                );
+
+                *missing = None;
+            }
+            _ => (),
         }
+    }
+
+    /// Tell the playlist watcher, which playlist is in the current list.
+    async fn set_loaded(&self) {
+        *self.manager.loaded_playlist.lock().await = self
+            .json_playlist
+            .path
+            .clone()
+            .map(|p| (p, self.json_playlist.modified.clone()));
+    }
+
+    // Load the playlist, when there is no current one. Changes of a loaded playlist
+    // get merged by the playlist watcher.
+    async fn load_or_update_playlist(&mut self, seek: bool) {
+        if self.json_playlist.path.is_none() {
+            self.playlist_from_calendar(seek, false).await;
 
-        if get_current {
             self.json_playlist = read_json(
                 &mut self.config,
                 self.manager.current_list.clone(),
-                self.json_playlist.path.clone(),
+                None,
                 self.is_alive.clone(),
+                self.manager.playlist_warnings.clone(),
                 seek,
                 false,
             )
             .await;
 
-            if !reload {
-                if let Some(file) = &self.json_playlist.path {
-                    info!(target: Target::file_mail(), channel = self.channel_id; "Read playlist: <span class=\"log-addr\">{file}</span>");
-                }
+            self.check_missing().await;
+            self.set_loaded().await;
 
-                let new_date = self.json_playlist.date.clone();
+            if let Some(file) = &self.json_playlist.path {
+                info!(target: Target::file_mail(), channel = self.channel_id; "Read playlist: <span class=\"log-addr\">{file}</span>");
+            }
 
-                let last_date_mismatch = {
-                    let channel = self.manager.channel.lock().await;
-                    channel.last_date.as_ref() != Some(&new_date)
-                };
+            let new_date = self.json_playlist.date.clone();
 
-                if last_date_mismatch {
-                    self.set_status(&Some(new_date.clone()), 0.0).await;
-                }
+            let last_date_mismatch = {
+                let channel = self.manager.channel.lock().await;
+                channel.last_date.as_ref() != Some(&new_date)
+            };
+
+            if last_date_mismatch {
+                self.set_status(&Some(new_date.clone()), 0.0).await;
+            }
+
+            let previous = self.manager.current_date.lock().await.clone();
 
-                self.manager.current_date.lock().await.clone_from(&new_date);
+            if !previous.is_empty() && previous != new_date {
+                hooks::trigger(
+                    &self.config,
+                    HookEvent::DayChange,
+                    BTreeMap::from([
+                        ("date".to_string(), new_date.clone()),
+                        ("previous".to_string(), previous),
+                    ]),
+                );
             }
 
+            self.manager.current_date.lock().await.clone_from(&new_date);
+
             self.manager
                 .current_list
                 .lock()
@@ -136,8 +572,9 @@ impl CurrentProgram {
     async fn check_for_playlist(&mut self, seek: bool) -> bool {
         let (delta, total_delta) = get_delta(
             &self.config,
-            &time_in_seconds(&self.config.channel.timezone),
+            &playlist_seconds(&self.config.channel.timezone, self.start_sec),
         );
+        let length_sec = target_length(&self.config, &self.json_playlist.date);
         let mut next = false;
 
         let mut duration = self.current_node.out;
@@ -160,29 +597,35 @@ impl CurrentProgram {
         trace!(
             "delta: {delta} | total_delta: {total_delta}, index: {node_index}, last index: {last_index}, init: {} \n        next_start: {next_start} | length_sec: {} | source {}",
             self.manager.list_init.load(Ordering::SeqCst),
-            self.length_sec,
+            length_sec,
             self.current_node.source
         );
 
         // Check if we over the target length or we are close to it, if so we load the next playlist.
         if !self.config.playlist.infinit
-            && (next_start >= self.length_sec
+            && (next_start >= length_sec
                 || is_close(total_delta, 0.0, IS_CLOSE_THRESHOLD)
-                || is_close(total_delta, self.length_sec, IS_CLOSE_THRESHOLD))
+                || is_close(total_delta, length_sec, IS_CLOSE_THRESHOLD))
         {
             trace!("get next day");
             next = true;
 
+            self.playlist_from_calendar(false, true).await;
+
             self.json_playlist = read_json(
                 &mut self.config,
                 self.manager.current_list.clone(),
                 None,
                 self.is_alive.clone(),
+                self.manager.playlist_warnings.clone(),
                 false,
                 true,
             )
             .await;
 
+            self.check_missing().await;
+            self.set_loaded().await;
+
             if let Some(file) = &self.json_playlist.path {
                 info!(target: Target::file_mail(), channel = self.channel_id; "Read next playlist: <span class=\"log-addr\">{file}</span>");
             }
@@ -243,16 +686,10 @@ impl CurrentProgram {
         }
     }
 
-    // Get current time and when we are before start time,
-    // we add full seconds of a day to it.
+    // Get current time, counted in real seconds from the start of the playlist day,
+    // so it runs on over midnight and over a change of the daylight saving time.
     fn get_current_time(&mut self) -> f64 {
-        let mut time_sec = time_in_seconds(&self.config.channel.timezone);
-
-        if time_sec < self.start_sec {
-            time_sec += 86400.0; // self.config.playlist.length_sec.unwrap();
-        }
-
-        time_sec
+        playlist_seconds(&self.config.channel.timezone, self.start_sec)
     }
 
     // On init or reload we need to seek for the current clip.
@@ -287,7 +724,7 @@ impl CurrentProgram {
                 .begin
                 .unwrap_or(self.config.playlist.start_sec.unwrap_or_default());
 
-            let end = start + item.out - item.seek;
+            let end = start + item.length();
 
             if end > time_sec {
                 self.manager.list_init.store(false, Ordering::SeqCst);
@@ -304,8 +741,8 @@ impl CurrentProgram {
         let mut is_filler = false;
 
         if !self.manager.list_init.load(Ordering::SeqCst) {
-            let time_sec = self.get_current_time();
             let index = self.manager.current_index.load(Ordering::SeqCst);
+            let joined = self.join_schedule(index).await;
             let nodes = self.manager.current_list.lock().await;
             let last_index = nodes.len() - 1;
 
@@ -317,8 +754,21 @@ impl CurrentProgram {
 
             trace!("Clip from init: {}", node_clone.source);
 
-            node_clone.seek += time_sec
-                - (node_clone.begin.unwrap() - self.manager.channel.lock().await.time_shift);
+            // probe first, so the seek point is taken as close as possible to the decoder start
+            if node_clone.probe.is_none()
+                && !node_clone.source.is_empty()
+                && let Err(e) = node_clone.add_probe(true).await
+            {
+                trace!("{e:?}");
+            }
+
+            if !joined {
+                let time_sec = self.get_current_time();
+
+                node_clone.seek += (time_sec
+                    - (node_clone.begin.unwrap() - self.manager.channel.lock().await.time_shift))
+                    * node_clone.speed.unwrap_or(1.0);
+            }
 
             self.last_next_ad(&mut node_clone).await;
 
@@ -330,7 +780,7 @@ impl CurrentProgram {
                 .current_node
                 .source
                 .contains(&self.config.channel.storage.to_string_lossy().to_string())
-                || self.current_node.source.contains("color=c=#121212")
+                || is_generated(&self.current_node.source)
             {
                 is_filler = true;
             }
@@ -339,11 +789,133 @@ impl CurrentProgram {
         is_filler
     }
 
+    /// Join the schedule with the item at `index`, which should have begun already,
+    /// after the join policy.
+    ///
+    /// Returns false, when the item gets joined by seeking into it.
+    async fn join_schedule(&mut self, index: usize) -> bool {
+        let policy = self.config.playlist.join;
+
+        if policy == JoinPolicy::Seek {
+            return false;
+        }
+
+        let time_shift = self.manager.channel.lock().await.time_shift;
+        let now = self.get_current_time() + time_shift;
+        let mut list = self.manager.current_list.lock().await;
+
+        let Some(item) = list.get(index) else {
+            return false;
+        };
+
+        let begin = item.begin.unwrap_or(self.start_sec);
+        let source = item.source.clone();
+
+        if now - begin < 1.0 {
+            return false;
+        }
+
+        let position = match policy {
+            JoinPolicy::Top => resume_media(&mut list, index, index, now, now),
+            _ => match next_hard_start(&list, index + 1, self.start_sec) {
+                Some((next, target)) => {
+                    resume_media(&mut list, index, next, target + time_shift, now)
+                }
+                None => return false,
+            },
+        };
+
+        match position {
+            Ok(position) => {
+                if !self.config.playlist.infinit {
+                    fit_hard_starts(&self.config, &mut list, position + 1, self.start_sec);
+                }
+
+                info!(target: Target::file_mail(), channel = self.channel_id;
+                    "Join the schedule <span class=\"log-number\">{}</span> late (<span class=\"log-number\">{policy}</span>) with <span class=\"log-addr\">{}</span>",
+                    sec_to_time(now - begin),
+                    list[position].source
+                );
+
+                true
+            }
+            Err(e) => {
+                warn!(target: Target::file_mail(), channel = self.channel_id;
+                    "Join <span class=\"log-addr\">{source}</span> by seek: {e}"
+                );
+
+                false
+            }
+        }
+    }
+
+    /// Insert the bumper clip in front of the item at `index`, when an ad break starts or ends there.
+    ///
+    /// The time is taken from the following filler clips, otherwise the schedule shifts.
+    async fn insert_bumper(&mut self, index: usize) {
+        let storage = &self.config.storage;
+        let bumper = bumper_at(&self.manager.current_list.lock().await, index);
+        let path = match bumper {
+            Some(Bumper::In) => &storage.bumper_in_path,
+            Some(Bumper::Out) => &storage.bumper_out_path,
+            None => return,
+        };
+
+        if !path.is_file() {
+            return;
+        }
+
+        let mut media = Media::new(index, &path.to_string_lossy(), true).await;
+
+        if media.duration <= 0.0 {
+            error!(target: Target::file_mail(), channel = self.channel_id;
+                "Bumper <span class=\"log-addr\">{}</span> has no valid duration!",
+                media.source
+            );
+            return;
+        }
+
+        media.category = "bumper".to_string();
+        media.out = media.duration;
+
+        let mut list = self.manager.current_list.lock().await;
+        let recovered = insert_media(&mut list, index, media, InsertPolicy::DropFiller, |m| {
+            is_filler(&self.config, m)
+        });
+
+        if !self.config.playlist.infinit {
+            fit_hard_starts(&self.config, &mut list, index + 1, self.start_sec);
+        }
+
+        debug!(target: Target::file_mail(), channel = self.channel_id;
+            "Insert {bumper:?} bumper, shift schedule for <span class=\"log-number\">{:.3}</span> seconds",
+            list[index].duration - recovered
+        );
+    }
+
+    /// "Up next" card in the slack time before the next program.
+    async fn next_card(&mut self, index: usize) {
+        if !self.config.playlist.next_card {
+            return;
+        }
+
+        let mut list = self.manager.current_list.lock().await;
+
+        if let Some(title) = insert_next_card(&mut list, index, |m| is_filler(&self.config, m)) {
+            debug!(target: Target::file_mail(), channel = self.channel_id;
+                "Insert \"Up next\" card for <span class=\"log-addr\">{title}</span>"
+            );
+        }
+    }
+
     async fn fill_end(&mut self, total_delta: f64) {
         // Fill end from playlist
         let index = self.manager.current_index.load(Ordering::SeqCst);
         let mut media = Media::new(index, "", false).await;
-        media.begin = Some(time_in_seconds(&self.config.channel.timezone));
+        media.begin = Some(playlist_seconds(
+            &self.config.channel.timezone,
+            self.start_sec,
+        ));
         media.duration = total_delta;
         media.out = total_delta;
 
@@ -384,6 +956,8 @@ impl CurrentProgram {
             time_sec -= time_in_current_loop;
         }
 
+        // the current list has the merged changes
+        self.json_playlist.program = self.manager.current_list.lock().await.clone();
         self.json_playlist.start_sec = Some(time_sec);
         set_defaults(&self.config, &mut self.json_playlist);
         self.manager
@@ -399,8 +973,8 @@ impl CurrentProgram {
         debug!(target: Target::file_mail(), channel = self.channel_id; "Playlist init");
         let (_, total_delta) = get_delta(&self.config, &node.begin.unwrap());
 
-        if !self.config.playlist.infinit && node.out - node.seek > total_delta {
-            node.out = total_delta + node.seek;
+        if !self.config.playlist.infinit && node.length() > total_delta {
+            node.out = total_delta * node.speed.unwrap_or(1.0) + node.seek;
         }
 
         self.gen_source(node, last_index).await;
@@ -493,11 +1067,34 @@ impl CurrentProgram {
             }
         }
 
-        if (total_delta > node.out - node.seek && !last)
+        if (total_delta > node.length() && !last)
             || node.index.unwrap() < 2
             || !self.config.playlist.length.contains(':')
             || self.config.playlist.infinit
         {
+            if shifted_delta < -RECOVERY_MIN_DELAY
+                && self.config.playlist.length.contains(':')
+                && !self.config.playlist.infinit
+            {
+                if skip_late(&node, -shifted_delta) {
+                    info!(target: Target::file_mail(), channel = self.channel_id;
+                        "Playout is <span class=\"log-number\">{:.3}</span> seconds behind, skip: <span class=\"log-addr\">{}</span>",
+                        -shifted_delta,
+                        node.source
+                    );
+
+                    node.skip = true;
+                    self.current_node = node;
+                    return;
+                }
+
+                self.recover(&mut node, -shifted_delta).await;
+            }
+
+            if self.config.playlist.length.contains(':') && !self.config.playlist.infinit {
+                self.fit_hard_start(&mut node, shifted_delta).await;
+            }
+
             // when we are in the 24 hour range, get the clip
             self.gen_source(node, last_index).await;
 
@@ -514,6 +1111,76 @@ impl CurrentProgram {
         self.current_node = node;
     }
 
+    /// Catch up a delay with the configured recovery strategy.
+    async fn recover(&mut self, node: &mut Media, delay: f64) {
+        let length = node.out - node.seek;
+
+        match self.config.playlist.recovery {
+            RecoveryStrategy::None => {}
+            RecoveryStrategy::TrimClip => {
+                if length - delay >= 1.0 && node.start.is_soft() {
+                    node.out -= delay;
+
+                    info!(target: Target::file_mail(), channel = self.channel_id;
+                        "Playout is <span class=\"log-number\">{delay:.3}</span> seconds behind, trim clip: <span class=\"log-addr\">{}</span>",
+                        node.source
+                    );
+                }
+            }
+            RecoveryStrategy::DropFiller => {
+                let from = node.index.unwrap_or_default() + 1;
+                let recovered = {
+                    let mut list = self.manager.current_list.lock().await;
+                    drop_filler(&mut list, from, delay, |m| is_filler(&self.config, m))
+                };
+
+                if recovered > 0.0 {
+                    info!(target: Target::file_mail(), channel = self.channel_id;
+                        "Playout is <span class=\"log-number\">{delay:.3}</span> seconds behind, drop <span class=\"log-number\">{recovered:.3}</span> seconds filler"
+                    );
+                }
+            }
+            RecoveryStrategy::SpeedUp => {
+                if self.config.processing.copy_audio || self.config.processing.copy_video {
+                    debug!(target: Target::file_mail(), channel = self.channel_id; "Speed up is not possible in copy mode");
+                } else if length > 1.0 {
+                    let factor = catch_up_speed(length, delay, self.config.playlist.recovery_speed);
+
+                    if factor > 1.0 {
+                        node.speed = Some(factor * node.speed.unwrap_or(1.0));
+
+                        info!(target: Target::file_mail(), channel = self.channel_id;
+                            "Playout is <span class=\"log-number\">{delay:.3}</span> seconds behind, play clip with speed <span class=\"log-number\">{factor:.3}</span>"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Trim the soft item in front of a hard start item, so that the hard item begins at its
+    /// clock time. `delta` is the difference between the planned begin and the current time.
+    ///
+    /// This is independent from the recovery strategy, a clip which ends up shorter then
+    /// one second is skipped.
+    async fn fit_hard_start(&self, node: &mut Media, delta: f64) {
+        let index = node.index.unwrap_or_default();
+        let begin = node.begin.unwrap_or_default() - delta;
+        let overrun = {
+            let list = self.manager.current_list.lock().await;
+            hard_overrun(node, &list, index, begin, self.start_sec)
+        };
+
+        if overrun > 0.0 {
+            node.out = (node.out - overrun * node.speed.unwrap_or(1.0)).max(node.seek);
+
+            info!(target: Target::file_mail(), channel = self.channel_id;
+                "Next item has a hard start, trim <span class=\"log-addr\">{}</span> by <span class=\"log-number\">{overrun:.3}</span> seconds",
+                node.source
+            );
+        }
+    }
+
     /// Generate the source CMD, or when clip not exist, get a dummy.
     pub async fn gen_source(&mut self, mut node: Media, last_index: usize) {
         let node_index = node.index.unwrap_or_default();
@@ -543,7 +1210,53 @@ impl CurrentProgram {
 
         trace!("Clip length: {duration}, duration: {}", node.duration);
 
-        if node.probe.is_none()
+        if node.category == NEXT_CARD {
+            let start = node.begin.unwrap_or_default() + duration;
+            let (source, cmd) = gen_next_card(
+                &self.config,
+                duration,
+                node.title.as_deref().unwrap_or_default(),
+                start,
+            );
+
+            node.source = source;
+            node.cmd = Some(cmd);
+            node.probe = None;
+            node.add_filter(&self.config, &self.manager.filter_chain.clone())
+                .await;
+            self.current_node = node;
+
+            return;
+        }
+
+        if node.category == "filler" && is_generated(&node.source) {
+            // gap before a hard start, the configured filler or the dummy with the current length
+            if !self
+                .fallback_source(FallbackSource::Filler, &mut node, duration)
+                .await
+            {
+                let (source, cmd) = gen_dummy(&self.config, duration);
+                node.source = source;
+                node.cmd = Some(cmd);
+            }
+
+            node.add_filter(&self.config, &self.manager.filter_chain.clone())
+                .await;
+            self.current_node = node;
+
+            return;
+        }
+
+        let quarantined = self.manager.quarantine.lock().await.contains(
+            &node.source,
+            time_now(&self.config.channel.timezone).date_naive(),
+        );
+
+        if quarantined {
+            warn!(target: Target::file_mail(), channel = self.channel_id;
+                "Skip clip in quarantine: <span class=\"log-addr\">{}</span>", node.source
+            );
+        } else if node.probe.is_none()
             && !node.source.is_empty()
             && let Err(e) = node.add_probe(true).await
         {
@@ -551,7 +1264,9 @@ impl CurrentProgram {
         };
 
         // separate if condition, because of node.add_probe() in last condition
-        if node.probe.is_some() {
+        if node.probe.is_some() && !quarantined {
+            self.end_fallback().await;
+
             if node
                 .source
                 .rsplit_once('.')
@@ -571,120 +1286,19 @@ impl CurrentProgram {
         } else {
             trace!("clip index: {node_index} | last index: {last_index}");
 
-            if node_index < last_index {
+            if node_index < last_index && !quarantined {
                 error!(
                     target: Target::file_mail(), channel = self.channel_id;
                     "Source not found: <span class=\"log-addr\">{}</span>", node.source
                 );
             }
 
-            let filler = {
-                let fillers = self.manager.filler_list.lock().await;
-
-                if self.manager.current_list.lock().await.len() - 1 < last_index {
-                    self.manager.list_init.store(true, Ordering::SeqCst);
-                }
-
-                if self.config.storage.filler_path.is_dir() && !fillers.is_empty() {
-                    let index = self
-                        .manager
-                        .filler_index
-                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |i| {
-                            Some(if i + 1 >= fillers.len() { 0 } else { i + 1 })
-                        })
-                        .unwrap_or(0);
-                    trace!("take filler: {}", fillers[index].source);
-                    Some(fillers[index].clone())
-                } else {
-                    None
-                }
-            };
-
-            if let Some(mut filler_media) = filler {
-                if filler_media.probe.is_none()
-                    && let Err(e) = filler_media.add_probe(false).await
-                {
-                    error!(target: Target::file_mail(), channel = self.channel_id; "{e:?}");
-                };
-
-                if node.duration > 0.0 && filler_media.duration > duration {
-                    filler_media.out = duration;
-                }
-
-                node.source = filler_media.source;
-                node.seek = 0.0;
-                node.out = filler_media.out;
-                node.duration = filler_media.duration;
-                node.cmd = Some(loop_filler(&self.config, &node));
-                node.probe = filler_media.probe;
-            } else {
-                match MediaProbe::new(&self.config.storage.filler_path).await {
-                    Ok(probe) => {
-                        if self
-                            .config
-                            .storage
-                            .filler_path
-                            .extension()
-                            .map(|e| e.to_string_lossy().to_lowercase())
-                            .filter(|c| IMAGE_FORMAT.contains(&c.as_str()))
-                            .is_some()
-                        {
-                            node.source = self
-                                .config
-                                .storage
-                                .filler_path
-                                .clone()
-                                .to_string_lossy()
-                                .to_string();
-                            node.cmd = Some(loop_image(&self.config, &node));
-                            node.probe = Some(probe);
-                        } else if let Some(filler_duration) = probe.clone().format.duration {
-                            // Create placeholder from config filler.
-                            let filler_out = if node.duration == 0.0 {
-                                filler_duration
-                            } else {
-                                filler_duration.min(duration)
-                            };
-
-                            node.source = self
-                                .config
-                                .storage
-                                .filler_path
-                                .clone()
-                                .to_string_lossy()
-                                .to_string();
-                            node.seek = 0.0;
-                            node.out = filler_out;
-                            node.duration = filler_duration;
-                            node.cmd = Some(loop_filler(&self.config, &node));
-                            node.probe = Some(probe);
-                        } else {
-                            // Create colored placeholder.
-                            let (source, cmd) = gen_dummy(&self.config, duration);
-                            node.source = source;
-                            node.cmd = Some(cmd);
-                        }
-                    }
-                    Err(e) => {
-                        // Create colored placeholder.
-                        error!(target: Target::file_mail(), channel = self.channel_id; "Filler error: {e}");
-
-                        let mut dummy_duration = 60.0;
-
-                        if node.duration > 0.0 && dummy_duration > duration {
-                            dummy_duration = duration;
-                        }
-
-                        let (source, cmd) = gen_dummy(&self.config, dummy_duration);
-                        node.seek = 0.0;
-                        node.out = dummy_duration;
-                        node.duration = dummy_duration;
-                        node.source = source;
-                        node.cmd = Some(cmd);
-                    }
-                }
+            if self.manager.current_list.lock().await.len() - 1 < last_index {
+                self.manager.list_init.store(true, Ordering::SeqCst);
             }
 
+            self.fallback(&mut node, duration).await;
+
             warn!(
                 target: Target::file_mail(), channel = self.channel_id;
                 "Generate filler with <span class=\"log-number\">{:.2}</span> seconds length!",
@@ -703,11 +1317,237 @@ impl CurrentProgram {
         self.current_node = node;
     }
 
+    /// Go through the fallback chain, until one step can be played.
+    ///
+    /// A step with timeout is left, when it was used longer in a row. When the chain
+    /// descends it gets logged and the current step is shown in the playout status.
+    async fn fallback(&mut self, node: &mut Media, duration: f64) {
+        let chain = self.config.storage.fallback_chain.clone();
+        let now = Instant::now();
+        let (mut step, since) = self.fallback.unwrap_or((0, now));
+
+        if let Some(s) = chain.get(step)
+            && s.timeout > 0
+            && now.duration_since(since).as_secs() >= s.timeout
+        {
+            warn!(target: Target::file_mail(), channel = self.channel_id;
+                "Fallback <span class=\"log-addr\">{}</span> timed out after <span class=\"log-number\">{}</span> seconds",
+                s.source,
+                s.timeout
+            );
+
+            step += 1;
+        }
+
+        while let Some(s) = chain.get(step) {
+            if self.fallback_source(s.source, node, duration).await {
+                break;
+            }
+
+            step += 1;
+        }
+
+        let source = match chain.get(step) {
+            Some(s) => s.source,
+            None => {
+                self.generated_source(node, duration, gen_test_pattern);
+                FallbackSource::Test
+            }
+        };
+
+        if self.fallback.is_none_or(|(s, _)| s != step) {
+            warn!(target: Target::file_mail(), channel = self.channel_id;
+                "Fallback to <span class=\"log-addr\">{source}</span>"
+            );
+
+            self.fallback = Some((step, now));
+            *self.manager.fallback.lock().await = Some(source);
+        }
+    }
+
+    /// Filler, or the slate without filler, while the schedule is on hold.
+    async fn hold_node(&mut self) -> Media {
+        if self.current_node.category != HOLD {
+            info!(target: Target::file_mail(), channel = self.channel_id; "Schedule is on hold, until it gets released");
+        }
+
+        let mut node = Media {
+            title: Some("Hold".to_string()),
+            category: HOLD.to_string(),
+            out: HOLD_LENGTH,
+            duration: HOLD_LENGTH,
+            ..Media::default()
+        };
+
+        if !self
+            .fallback_source(FallbackSource::Filler, &mut node, HOLD_LENGTH)
+            .await
+        {
+            let (source, cmd) = gen_slate(&self.config, HOLD_LENGTH);
+            node.source = source;
+            node.cmd = Some(cmd);
+        }
+
+        node.add_filter(&self.config, &self.manager.filter_chain)
+            .await;
+        self.current_node = node.clone();
+
+        node
+    }
+
+    async fn end_fallback(&mut self) {
+        if self.fallback.take().is_some() {
+            info!(target: Target::file_mail(), channel = self.channel_id; "Fallback ended, continue with {}", self.config.processing.mode);
+
+            *self.manager.fallback.lock().await = None;
+        }
+    }
+
+    /// Fill node with a fallback source, returns `false` when the source is not available.
+    async fn fallback_source(
+        &mut self,
+        source: FallbackSource,
+        node: &mut Media,
+        duration: f64,
+    ) -> bool {
+        match source {
+            FallbackSource::Filler => {
+                let filler = {
+                    let fillers = self.manager.filler_list.lock().await;
+
+                    if self.config.storage.filler_path.is_dir() && !fillers.is_empty() {
+                        let index = self
+                            .manager
+                            .filler_index
+                            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |i| {
+                                Some(if i + 1 >= fillers.len() { 0 } else { i + 1 })
+                            })
+                            .unwrap_or(0);
+                        trace!("take filler: {}", fillers[index].source);
+                        Some((fillers[index].clone(), (index + 1) % fillers.len()))
+                    } else {
+                        None
+                    }
+                };
+
+                if let Some((mut filler_media, next_index)) = filler {
+                    resume::save(&self.manager, |s| s.filler_index = next_index).await;
+
+                    if filler_media.probe.is_none()
+                        && let Err(e) = filler_media.add_probe(false).await
+                    {
+                        error!(target: Target::file_mail(), channel = self.channel_id; "{e:?}");
+                    };
+
+                    if node.duration > 0.0 && filler_media.duration > duration {
+                        filler_media.out = duration;
+                    }
+
+                    node.source = filler_media.source;
+                    node.seek = 0.0;
+                    node.out = filler_media.out;
+                    node.duration = filler_media.duration;
+                    node.cmd = Some(loop_filler(&self.config, node));
+                    node.probe = filler_media.probe;
+
+                    true
+                } else {
+                    let path = self.config.storage.filler_path.clone();
+
+                    self.single_source(&path, node, duration).await
+                }
+            }
+            FallbackSource::Clip => {
+                let path = self.config.storage.fallback_clip_path.clone();
+
+                self.single_source(&path, node, duration).await
+            }
+            FallbackSource::Placeholder => {
+                self.generated_source(node, duration, gen_dummy);
+                true
+            }
+            FallbackSource::Test => {
+                self.generated_source(node, duration, gen_test_pattern);
+                true
+            }
+        }
+    }
+
+    /// Loop a single image or clip, like the filler file or fallback clip.
+    async fn single_source(&self, path: &Path, node: &mut Media, duration: f64) -> bool {
+        if !path.is_file() {
+            return false;
+        }
+
+        match MediaProbe::new(path).await {
+            Ok(probe) => {
+                if path
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .filter(|c| IMAGE_FORMAT.contains(&c.as_str()))
+                    .is_some()
+                {
+                    node.source = path.to_string_lossy().to_string();
+                    node.cmd = Some(loop_image(&self.config, node));
+                    node.probe = Some(probe);
+                } else if let Some(clip_duration) = probe.clone().format.duration {
+                    let clip_out = if node.duration == 0.0 {
+                        clip_duration
+                    } else {
+                        clip_duration.min(duration)
+                    };
+
+                    node.source = path.to_string_lossy().to_string();
+                    node.seek = 0.0;
+                    node.out = clip_out;
+                    node.duration = clip_duration;
+                    node.cmd = Some(loop_filler(&self.config, node));
+                    node.probe = Some(probe);
+                } else {
+                    return false;
+                }
+
+                true
+            }
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = self.channel_id;
+                    "Fallback <span class=\"log-addr\">{}</span>: {e}",
+                    path.display()
+                );
+
+                false
+            }
+        }
+    }
+
+    /// Generated placeholder, in parts of maximal one minute.
+    fn generated_source(
+        &self,
+        node: &mut Media,
+        duration: f64,
+        generate: fn(&PlayoutConfig, f64) -> (String, Vec<String>),
+    ) {
+        let mut dummy_duration = 60.0;
+
+        if node.duration > 0.0 && dummy_duration > duration {
+            dummy_duration = duration;
+        }
+
+        let (source, cmd) = generate(&self.config, dummy_duration);
+        node.seek = 0.0;
+        node.out = dummy_duration;
+        node.duration = dummy_duration;
+        node.source = source;
+        node.cmd = Some(cmd);
+        node.probe = None;
+    }
+
     async fn duplicate_for_seek_and_loop(&mut self, node: &mut Media) {
         let index = node.index.unwrap_or_default();
 
         let mut node_duplicate = node.clone();
         node_duplicate.seek = 0.0;
+        node_duplicate.origin = Origin::Inserted;
         let orig_seek = node.seek;
         node.out = node.duration;
 
@@ -751,13 +1591,9 @@ impl CurrentProgram {
             // so we fill the gap with a dummy.
             trace!("Init clip is no filler");
 
-            let mut current_time = time_in_seconds(&self.config.channel.timezone);
+            let current_time = playlist_seconds(&self.config.channel.timezone, self.start_sec);
             let (_, total_delta) = get_delta(&self.config, &current_time);
 
-            if self.start_sec > current_time {
-                current_time += self.length_sec + 1.0;
-            }
-
             let length = self.manager.current_list.lock().await.len();
             let last_index = length.saturating_sub(1);
 
@@ -783,8 +1619,15 @@ impl CurrentProgram {
             return Some(self.current_node.clone());
         }
 
+        if self.manager.hold.load(Ordering::SeqCst) {
+            return Some(self.hold_node().await);
+        }
+
         let index = self.manager.current_index.load(Ordering::SeqCst);
 
+        self.insert_bumper(index).await;
+        self.next_card(index).await;
+
         let current_list = self.manager.current_list.lock().await;
         let length = current_list.len();
 
@@ -0,0 +1,52 @@
+use log::*;
+
+use crate::player::{
+    controller::ChannelManager,
+    utils::{Media, gen_test_pattern, time_in_seconds},
+};
+
+/// Length of one test pattern clip, after it a new one gets started.
+pub const TEST_PATTERN_LENGTH: f64 = 3600.0;
+
+/// Test Pattern Source
+///
+/// Plays endless SMPTE bars with 1kHz tone, for line-up and checking the output chain.
+#[derive(Debug, Clone)]
+pub struct TestPatternSource {
+    manager: ChannelManager,
+}
+
+impl TestPatternSource {
+    pub async fn new(manager: ChannelManager) -> Self {
+        *manager.current_list.lock().await = vec![test_node(&manager).await];
+
+        Self { manager }
+    }
+
+    pub async fn next(&mut self) -> Option<Media> {
+        let config = self.manager.config.read().await.clone();
+        let mut node = test_node(&self.manager).await;
+
+        trace!("Next test pattern clip");
+
+        node.add_filter(&config, &self.manager.filter_chain).await;
+        node.begin = Some(time_in_seconds(&config.channel.timezone));
+
+        Some(node)
+    }
+}
+
+async fn test_node(manager: &ChannelManager) -> Media {
+    let config = manager.config.read().await;
+    let (source, cmd) = gen_test_pattern(&config, TEST_PATTERN_LENGTH);
+
+    Media {
+        index: Some(0),
+        title: Some("Test Pattern".to_string()),
+        out: TEST_PATTERN_LENGTH,
+        duration: TEST_PATTERN_LENGTH,
+        source,
+        cmd: Some(cmd),
+        ..Media::default()
+    }
+}
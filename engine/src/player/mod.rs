@@ -1,5 +1,7 @@
+pub mod commands;
 pub mod controller;
 pub mod filter;
 pub mod input;
 pub mod output;
+pub mod process;
 pub mod utils;
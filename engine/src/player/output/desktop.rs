@@ -1,57 +1,28 @@
-use std::process::Stdio;
-
 use log::*;
-use tokio::process::{Child, Command};
-
-use crate::player::filter::v_drawtext;
+use tokio::process::Child;
+
+use crate::player::{
+    commands::desktop_cmd,
+    controller::ProcessUnit::*,
+    filter::v_drawtext,
+    process::{Pipe, ProcessSpawner, ResourceLimits},
+    utils::ffplay_bin,
+};
 use crate::utils::errors::ServiceError;
 use crate::utils::{
     config::PlayoutConfig,
     logging::{Target, fmt_cmd},
 };
-use crate::vec_strings;
 
 /// Desktop Output
 ///
 /// Instead of streaming, we run a ffplay instance and play on desktop.
-pub async fn output(config: &PlayoutConfig, log_format: &str) -> Result<Child, ServiceError> {
-    let mut enc_filter: Vec<String> = vec![];
-    let mut enc_cmd = vec_strings!["-hide_banner", "-nostats", "-v", log_format];
-
-    if let Some(encoder_input_cmd) = &config.advanced.encoder.input_cmd {
-        enc_cmd.append(&mut encoder_input_cmd.clone());
-    }
-
-    enc_cmd.append(&mut vec_strings![
-        "-autoexit",
-        "-i",
-        "pipe:0",
-        "-window_title",
-        "ffplayout"
-    ]);
-
-    if let Some(mut cmd) = config.output.output_cmd.clone() {
-        if cmd.iter().any(|i| {
-            [
-                "-c",
-                "-c:v",
-                "-c:v:0",
-                "-b:v",
-                "-b:v:0",
-                "-vcodec",
-                "-c:a",
-                "-acodec",
-                "-crf",
-                "-map",
-                "-filter_complex",
-            ]
-            .contains(&i.as_str())
-        }) {
-            warn!(target: Target::file_mail(), channel = config.general.channel_id; "ffplay doesn't support given output parameters, they will be skipped!");
-        } else {
-            enc_cmd.append(&mut cmd);
-        }
-    }
+pub async fn output(
+    config: &PlayoutConfig,
+    log_format: &str,
+    spawner: &dyn ProcessSpawner,
+) -> Result<Child, ServiceError> {
+    let mut text_filter = None;
 
     if config.text.add_text
         && !config.text.text_from_filename
@@ -63,24 +34,23 @@ pub async fn output(config: &PlayoutConfig, log_format: &str) -> Result<Child, S
             socket
         );
 
-        let mut filter: String = "null,".to_string();
-        filter.push_str(v_drawtext::filter_node(config, None, &None).await.as_str());
-        enc_filter = vec!["-vf".to_string(), filter];
+        text_filter = Some(v_drawtext::filter_node(config, None, &None).await);
     }
 
-    enc_cmd.append(&mut enc_filter);
+    let enc_cmd = desktop_cmd(config, text_filter, log_format);
 
     debug!(target: Target::file_mail(), channel = config.general.channel_id;
         "Encoder CMD: <span class=\"log-cmd\">ffplay {}</span>",
         fmt_cmd(&enc_cmd)
     );
 
-    let child = Command::new("ffplay")
-        .args(enc_cmd)
-        .kill_on_drop(true)
-        .stdin(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
+    let child = spawner.spawn(
+        Encoder,
+        &ffplay_bin(),
+        &enc_cmd,
+        Pipe::Stdin,
+        &ResourceLimits::new(config),
+    )?;
 
     Ok(child)
 }
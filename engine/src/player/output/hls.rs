@@ -17,32 +17,30 @@ out:
 
 */
 
-use std::{
-    path::PathBuf,
-    process::Stdio,
-    sync::{
-        Arc,
-        atomic::{AtomicBool, Ordering},
-    },
-    time::SystemTime,
-};
+use std::{collections::BTreeMap, path::PathBuf, sync::atomic::Ordering, time::SystemTime};
 
 use log::*;
 use tokio::{
     fs,
     io::{AsyncBufReadExt, BufReader},
-    process::Command,
-    time::{Duration, sleep},
+    time::{Duration, Instant, sleep},
 };
 
-use crate::utils::{logging::log_line, task_runner};
-use crate::vec_strings;
+use crate::utils::{
+    alert, as_run,
+    hooks::{self, HookEvent},
+    logging::log_line,
+    now_playing, script, statistics, task_runner, telemetry,
+    time_machine::time_now,
+};
 use crate::{
     player::{
+        commands::{hls_decoder_cmd, hls_ingest_cmd},
         controller::{ChannelManager, ProcessUnit::*},
-        input::source_generator,
+        input::{ingest::prepare_ingest_outputs, source_generator},
+        process::{Pipe, ResourceLimits},
         utils::{
-            Media, get_delta, insert_readrate, is_free_tcp_port, prepare_output_cmd, sec_to_time,
+            Media, ffmpeg_bin, get_delta, id3, is_free_tcp_port, is_generated, sec_to_time,
             stderr_reader, valid_stream,
         },
     },
@@ -52,25 +50,24 @@ use crate::{
     },
 };
 
-/// Periodically checks if HLS segments are still being updated.
+const WATCHDOG_POLL: Duration = Duration::from_secs(2);
+
+/// Periodically checks if HLS segments are still being updated, every update counts as
+/// output progress of the channel, for the health probes.
 /// If no new segment is written for longer than `hls_time * 3`, returns an error.
 pub async fn hls_watchdog(
-    channel_id: i32,
+    manager: ChannelManager,
     segment_dir: PathBuf,
     hls_time: Duration,
-    is_alive: Arc<AtomicBool>,
 ) -> Result<(), ServiceError> {
-    let mut init = true;
+    let channel_id = manager.id;
+    let start = Instant::now();
+    let mut last_update = None;
 
     loop {
-        if init {
-            sleep(hls_time * 3).await;
-            init = false;
-        } else {
-            sleep(hls_time).await;
-        }
+        sleep(hls_time.min(WATCHDOG_POLL)).await;
 
-        if !is_alive.load(Ordering::SeqCst) {
+        if !manager.is_alive.load(Ordering::SeqCst) {
             break;
         }
 
@@ -91,7 +88,12 @@ pub async fn hls_watchdog(
                 .duration_since(last_mod)
                 .unwrap_or_default();
 
-            if age > hls_time * 3 {
+            if last_update.is_none_or(|t| last_mod > t) {
+                manager.tick(true);
+                last_update = Some(last_mod);
+            }
+
+            if start.elapsed() >= hls_time * 3 && age > hls_time * 3 {
                 error!(target: Target::file_mail(), channel = channel_id;
                     "HLS segment write timeout! Last update: <span class=\"log-number\">{:.3}s</span>", age.as_secs_f32()
                 );
@@ -110,7 +112,6 @@ async fn ingest_writer(manager: ChannelManager) -> Result<(), ServiceError> {
     let playlist_init = manager.list_init.clone();
     let chain = manager.filter_chain.clone();
     let stream_input = config.ingest.input_cmd.clone().unwrap();
-    let mut server_prefix = vec_strings!["-hide_banner", "-nostats", "-v", "level+info"];
     let mut dummy_media = Media::new(0, "Live Stream", false).await;
 
     dummy_media.unit = Ingest;
@@ -119,25 +120,10 @@ async fn ingest_writer(manager: ChannelManager) -> Result<(), ServiceError> {
     let is_alive = manager.is_alive.clone();
     let ingest_is_alive = manager.ingest_is_alive.clone();
 
-    if let Some(ingest_input_cmd) = &config.advanced.ingest.input_cmd {
-        server_prefix.append(&mut ingest_input_cmd.clone());
-    }
-
-    server_prefix.append(&mut stream_input.clone());
-
-    if config.processing.vtt_enable {
-        let vtt_dummy = config
-            .channel
-            .storage
-            .join(config.processing.vtt_dummy.clone().unwrap_or_default());
-
-        if vtt_dummy.is_file() {
-            server_prefix.append(&mut vec_strings!["-i", vtt_dummy.to_string_lossy()]);
-        }
-    }
-
-    let server_cmd = prepare_output_cmd(&config, server_prefix.clone(), &dummy_media.filter);
+    let server_cmd = hls_ingest_cmd(&config, &dummy_media.filter);
     let mut is_running;
+    let mut live_start = Instant::now();
+    let mut ingest_span = None;
 
     if let Some(url) = stream_input.iter().find(|s| s.contains("://")) {
         for num in 0..5 {
@@ -166,13 +152,16 @@ async fn ingest_writer(manager: ChannelManager) -> Result<(), ServiceError> {
         fmt_cmd(&server_cmd)
     );
 
+    let limits = ResourceLimits::new(&config);
+
     loop {
+        prepare_ingest_outputs(&config).await;
+
         let mut level = &config.logging.ffmpeg_level;
-        let mut server_proc = Command::new("ffmpeg")
-            .args(server_cmd.clone())
-            .stderr(Stdio::piped())
-            .kill_on_drop(true)
-            .spawn()?;
+        let mut server_proc =
+            manager
+                .spawner
+                .spawn(Ingest, ffmpeg_bin(), &server_cmd, Pipe::None, &limits)?;
 
         let server_err = BufReader::new(server_proc.stderr.take().unwrap());
         let mut lines = server_err.lines();
@@ -181,6 +170,14 @@ async fn ingest_writer(manager: ChannelManager) -> Result<(), ServiceError> {
         is_running = false;
 
         while let Some(line) = lines.next_line().await? {
+            if is_running {
+                manager.tick(false);
+            }
+
+            if manager.audio_meter.lock().await.update(&line) {
+                continue;
+            }
+
             if line.contains("rtmp")
                 && (line.contains("Unexpected stream") || line.contains("App field don't match up"))
                 && !valid_stream(&line)
@@ -189,12 +186,22 @@ async fn ingest_writer(manager: ChannelManager) -> Result<(), ServiceError> {
 
                 manager.stop(Ingest).await;
             } else if !is_running && line.contains("Input #0") {
+                if manager.ingest_hold.load(Ordering::SeqCst) {
+                    warn!(target: Target::file_mail(), channel = id; "Live ingest is on hold, drop stream");
+
+                    manager.stop(Ingest).await;
+                    continue;
+                }
+
                 level = &config.logging.ingest_level;
                 ingest_is_alive.store(true, Ordering::SeqCst);
                 playlist_init.store(true, Ordering::SeqCst);
                 is_running = true;
+                live_start = Instant::now();
+                ingest_span = Some(telemetry::span("ingest", id));
 
                 info!(target: Target::file_mail(), channel = id; "Switch from {} to live ingest", config.processing.mode);
+                hooks::trigger(&config, HookEvent::IngestStart, BTreeMap::new());
 
                 manager.stop(Decoder).await;
             }
@@ -204,6 +211,19 @@ async fn ingest_writer(manager: ChannelManager) -> Result<(), ServiceError> {
 
         if ingest_is_alive.load(Ordering::SeqCst) {
             info!(target: Target::file_mail(), channel = id; "Switch from live ingest to {}", config.processing.mode);
+            hooks::trigger(&config, HookEvent::IngestStop, BTreeMap::new());
+            tokio::spawn(statistics::ingest(
+                manager.clone(),
+                live_start.elapsed().as_secs_f64(),
+            ));
+        }
+
+        if let Some(mut span) = ingest_span.take() {
+            telemetry::attribute(
+                &mut span,
+                "ingest.seconds",
+                live_start.elapsed().as_secs_f64(),
+            );
         }
 
         ingest_is_alive.store(false, Ordering::SeqCst);
@@ -223,23 +243,55 @@ async fn write(manager: &ChannelManager, ff_log_format: &str) -> Result<(), Serv
     let get_source = source_generator(manager.clone());
     let ingest_is_alive = manager.ingest_is_alive.clone();
     let id = config.general.channel_id;
-    let current_media = manager.current_media.clone();
     let is_alive = manager.is_alive.clone();
 
     let mut get_source = get_source.await;
+    let mut last_program = None;
+    let mut slate = None;
+    let mut slate_trigger = super::SlateTrigger::new(config.general.slate_errors);
+    let limits = ResourceLimits::new(&config);
+
+    manager.tick(false);
+
+    while let Some(node) = match slate.take() {
+        Some(slate) => Some(slate),
+        None => match alert::next_node(manager).await {
+            Some(alert) => Some(alert),
+            None if manager.slate_hold.load(Ordering::SeqCst) => Some(
+                super::slate_node(manager, &config, &Media::default(), super::SLATE_LENGTH).await,
+            ),
+            None => super::load_source(&mut get_source, id).await,
+        },
+    } {
+        manager.tick(false);
+
+        if super::drain_before(manager, &last_program, &node) {
+            break;
+        }
+
+        let Some(mut node) = script::process(manager, &config, node).await else {
+            continue;
+        };
+
+        if node.cmd.is_none() {
+            // keep the HLS playlist going and find the current clip again
+            warn!(target: Target::file_mail(), channel = id; "No source to play, show slate");
+
+            manager.list_init.store(true, Ordering::SeqCst);
+            slate_trigger.update(manager, true);
+            node = super::slate_node(manager, &config, &node, super::SLATE_LENGTH).await;
+        }
 
-    while let Some(node) = get_source.next().await {
-        *current_media.lock().await = Some(node.clone());
+        manager.set_current(&node).await;
         let ignore = config.logging.ignore_lines.clone();
 
         if !is_alive.load(Ordering::SeqCst) {
             break;
         }
 
-        let mut cmd = match &node.cmd {
-            Some(cmd) => cmd.clone(),
-            None => break,
-        };
+        if node.cmd.is_none() {
+            break;
+        }
 
         if node.skip {
             continue;
@@ -251,6 +303,8 @@ async fn write(manager: &ChannelManager, ff_log_format: &str) -> Result<(), Serv
             node.source
         );
 
+        super::log_program(manager, &node).await;
+
         if config.task.enable {
             if config.task.path.is_file() {
                 let manager3 = manager.clone();
@@ -264,12 +318,12 @@ async fn write(manager: &ChannelManager, ff_log_format: &str) -> Result<(), Serv
             }
         }
 
-        let mut dec_prefix = vec_strings!["-hide_banner", "-nostats", "-v", &ff_log_format];
-
-        if let Some(decoder_input_cmd) = &config.advanced.decoder.input_cmd {
-            dec_prefix.append(&mut decoder_input_cmd.clone());
+        if !config.task.icecast_url.is_empty() || !config.task.webhook_url.is_empty() {
+            tokio::spawn(now_playing::push(config.clone(), node.clone()));
         }
 
+        hooks::trigger(&config, HookEvent::ClipStart, hooks::media_vars(&node));
+
         let mut read_rate = 1.0;
 
         if let Some(begin) = &node.begin {
@@ -286,30 +340,123 @@ async fn write(manager: &ChannelManager, ff_log_format: &str) -> Result<(), Serv
             }
         }
 
-        insert_readrate(&config.general.ffmpeg_options, &mut cmd, read_rate);
+        let mut id3_path = None;
 
-        dec_prefix.append(&mut cmd);
-        let dec_cmd = prepare_output_cmd(&config, dec_prefix, &node.filter);
+        if config.processing.hls_id3 {
+            let mut list = manager.current_list.lock().await.clone();
+            let mut index = node.index.unwrap_or_default();
+
+            // filler and fallback clips are not in the playlist
+            if list.get(index).is_none_or(|m| m.source != node.source) {
+                list = vec![node.clone()];
+                index = 0;
+            }
+
+            match id3::write_id3(&config, &list, index).await {
+                Ok(path) => id3_path = Some(path),
+                Err(e) => {
+                    error!(target: Target::file_mail(), channel = id; "Couldn't write ID3 metadata: {e}");
+                }
+            }
+        }
+
+        let dec_cmd = hls_decoder_cmd(
+            &config,
+            &node,
+            read_rate,
+            id3_path.as_deref(),
+            ff_log_format,
+        );
 
         debug!(target: Target::file_mail(), channel = id;
             "HLS writer CMD: <span class=\"log-cmd\">ffmpeg {}</span>",
             fmt_cmd(&dec_cmd)
         );
 
-        let mut dec_proc = Command::new("ffmpeg")
-            .args(dec_cmd)
-            .kill_on_drop(true)
-            .stderr(Stdio::piped())
-            .spawn()?;
+        let aired = time_now(&config.channel.timezone);
+        let clip_start = Instant::now();
+        let mut clip_span = telemetry::span("clip", id);
+        telemetry::media(&mut clip_span, &node);
+
+        let mut dec_proc =
+            manager
+                .spawner
+                .spawn(Decoder, ffmpeg_bin(), &dec_cmd, Pipe::None, &limits)?;
+
+        let mut dec_span = telemetry::child("decoder", id, &clip_span);
+        telemetry::media(&mut dec_span, &node);
 
         let dec_err = BufReader::new(dec_proc.stderr.take().unwrap());
         *manager.decoder.lock().await = Some(dec_proc);
 
-        stderr_reader(dec_err, ignore, Decoder, id).await?;
+        stderr_reader(
+            dec_err,
+            ignore,
+            Decoder,
+            id,
+            Some(manager.audio_meter.clone()),
+            None,
+        )
+        .await?;
+
+        let status = manager.wait(Decoder).await;
+        let failed = status.and_then(|s| s.code()).is_some_and(|c| c != 0);
+
+        if let Some(code) = status.and_then(|s| s.code()) {
+            telemetry::attribute(&mut dec_span, "process.exit_code", code as i64);
+        }
+
+        telemetry::attribute(
+            &mut clip_span,
+            "media.played",
+            clip_start.elapsed().as_secs_f64(),
+        );
+
+        if failed {
+            telemetry::error(&mut dec_span, "failed");
+            telemetry::error(&mut clip_span, "clip failed");
+        }
+
+        drop(dec_span);
+        last_program.clone_from(&node.program_id);
+        super::clip_hooks(&config, &node, clip_start.elapsed().as_secs_f64(), failed);
 
-        manager.wait(Decoder).await;
+        tokio::spawn(as_run::log(
+            manager.clone(),
+            node.clone(),
+            aired,
+            clip_start.elapsed().as_secs_f64(),
+            failed,
+        ));
+
+        if !ingest_is_alive.load(Ordering::SeqCst) && is_alive.load(Ordering::SeqCst) {
+            super::check_quarantine(manager, &config, &node.source, failed).await;
+
+            let mut remaining = node.length() - clip_start.elapsed().as_secs_f64();
+
+            if !is_generated(&node.source) {
+                slate_trigger.update(manager, failed);
+            }
+
+            if slate_trigger.active() {
+                // try the schedule again at its current position, after a short slate
+                manager.list_init.store(true, Ordering::SeqCst);
+                remaining = remaining.min(super::SLATE_LENGTH);
+            }
+
+            if failed && remaining >= super::MIN_SLATE_LENGTH && !is_generated(&node.source) {
+                warn!(target: Target::file_mail(), channel = id;
+                    "<span class=\"log-addr\">{}</span> failed, show slate for <span class=\"log-number\">{}</span>",
+                    node.source,
+                    sec_to_time(remaining)
+                );
+
+                slate = Some(super::slate_node(manager, &config, &node, remaining).await);
+            }
+        }
 
         while ingest_is_alive.load(Ordering::SeqCst) {
+            manager.tick(false);
             tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         }
     }
@@ -323,7 +470,6 @@ async fn write(manager: &ChannelManager, ff_log_format: &str) -> Result<(), Serv
 pub async fn writer(manager: &ChannelManager, ff_log_format: &str) -> Result<(), ServiceError> {
     let config = manager.config.read().await.clone();
     let manager2 = manager.clone();
-    let is_alive = manager.is_alive.clone();
     let output_cmd = config.output.output_cmd.unwrap_or_default();
     let hls_duration = output_cmd
         .windows(2)
@@ -344,10 +490,9 @@ pub async fn writer(manager: &ChannelManager, ff_log_format: &str) -> Result<(),
     };
 
     let watchdog_hls = tokio::spawn(hls_watchdog(
-        config.general.channel_id,
+        manager.clone(),
         config.channel.public.clone(),
         Duration::from_secs(hls_duration),
-        is_alive,
     ));
 
     tokio::select! {
@@ -1,32 +1,70 @@
-use std::{process::Stdio, sync::atomic::Ordering};
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
 
 use log::*;
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt, BufReader},
-    process::{ChildStdin, Command},
+    io::{AsyncReadExt, BufReader},
+    time::timeout,
 };
 
 mod desktop;
 mod hls;
 mod null;
 mod stream;
+pub mod supervisor;
 
+use crate::ARGS;
 use crate::player::{
+    commands::decoder_cmd,
     controller::{ChannelManager, ProcessUnit::*},
-    input::{ingest_server, source_generator},
-    utils::{sec_to_time, stderr_reader},
+    input::{SourceIterator, source_generator},
+    process::{Pipe, ResourceLimits},
+    utils::{
+        Media, av_sync::SyncMonitor, ffmpeg_bin, gen_slate, is_generated, program, sec_to_time,
+        seek_and_length, stderr_reader,
+    },
 };
 use crate::utils::{
-    config::OutputMode::*,
+    alert, as_run,
+    config::{IMAGE_FORMAT, OutputMode::*, PlayoutConfig},
+    control,
     errors::ServiceError,
+    hooks::{self, HookEvent},
     logging::{Target, fmt_cmd},
-    task_runner,
+    now_playing, script, statistics, task_runner, telemetry,
+    time_machine::time_now,
+    vod,
 };
-use crate::vec_strings;
+use supervisor::EncoderSink;
+
+/// Next clip from the source, loading it can take a while: reading the playlist, probing.
+pub(crate) async fn load_source(sources: &mut SourceIterator, id: i32) -> Option<Media> {
+    let mut span = telemetry::span("source.load", id);
+    let node = sources.next().await;
+
+    if let Some(node) = &node {
+        telemetry::media(&mut span, node);
+    }
+
+    node
+}
+
+/// How often a crashed decoder gets restarted for the same clip.
+const MAX_RESUMES: usize = 3;
+/// Remaining length in seconds, which is worth a decoder restart.
+const MIN_RESUME_LENGTH: f64 = 5.0;
+/// Slate length, when there is no source, before the playlist is checked again.
+const SLATE_LENGTH: f64 = 5.0;
+/// Remaining length of a failed clip, which gets filled with the slate.
+const MIN_SLATE_LENGTH: f64 = 1.0;
 
 async fn play(
     manager: ChannelManager,
-    mut enc_writer: ChildStdin,
+    sink: EncoderSink,
     ff_log_format: &str,
 ) -> Result<(), ServiceError> {
     let config = manager.config.read().await.clone();
@@ -36,12 +74,49 @@ async fn play(
     let ingest_is_alive = manager.ingest_is_alive.clone();
     let mut buffer = vec![0u8; 64 * 1024]; // Linux pipe buffer size
     let mut live_on = false;
+    let mut live_start = Instant::now();
+    let mut ingest_span = None;
 
     // get source iterator
     let mut node_sources = source_generator(manager.clone()).await;
 
-    while let Some(node) = node_sources.next().await {
-        *manager.current_media.lock().await = Some(node.clone());
+    let mut last_program = None;
+    let mut slate = None;
+    let mut slate_trigger = SlateTrigger::new(config.general.slate_errors);
+    let limits = ResourceLimits::new(&config);
+
+    manager.tick(false);
+
+    while let Some(node) = match slate.take() {
+        Some(slate) => Some(slate),
+        None => match alert::next_node(&manager).await {
+            Some(alert) => Some(alert),
+            None if manager.slate_hold.load(Ordering::SeqCst) => {
+                Some(slate_node(&manager, &config, &Media::default(), SLATE_LENGTH).await)
+            }
+            None => load_source(&mut node_sources, id).await,
+        },
+    } {
+        manager.tick(false);
+
+        if drain_before(&manager, &last_program, &node) {
+            break;
+        }
+
+        let Some(mut node) = script::process(&manager, &config, node).await else {
+            continue;
+        };
+
+        if node.cmd.is_none() {
+            // keep the encoder running and find the current clip again
+            warn!(target: Target::file_mail(), channel = id; "No source to play, show slate");
+
+            playlist_init.store(true, Ordering::SeqCst);
+            slate_trigger.update(&manager, true);
+            node = slate_node(&manager, &config, &node, SLATE_LENGTH).await;
+        }
+
+        manager.set_current(&node).await;
         let ignore_dec = config.logging.ignore_lines.clone();
 
         if !is_alive.load(Ordering::SeqCst) {
@@ -51,10 +126,13 @@ async fn play(
 
         trace!("Decoder CMD: {:?}", node.cmd);
 
-        let mut cmd = match node.cmd {
-            Some(cmd) => cmd,
-            None => break,
-        };
+        let record_node = node.record.then(|| node.clone());
+        let aired = time_now(&config.channel.timezone);
+        let mut replaced = false;
+
+        if node.cmd.is_none() {
+            break;
+        }
 
         if node.skip {
             // skip is different from node.cmd = None.
@@ -63,6 +141,9 @@ async fn play(
             continue;
         }
 
+        let mut clip_span = telemetry::span("clip", id);
+        telemetry::media(&mut clip_span, &node);
+
         let c_index = if cfg!(debug_assertions) {
             format!(
                 " ({}/{})",
@@ -80,6 +161,8 @@ async fn play(
             node.audio
         );
 
+        log_program(&manager, &node).await;
+
         if config.task.enable {
             if config.task.path.is_file() {
                 let channel_mgr_3 = manager.clone();
@@ -93,174 +176,541 @@ async fn play(
             }
         }
 
-        let mut dec_cmd = vec_strings!["-hide_banner", "-nostats", "-v", &ff_log_format];
-
-        if let Some(decoder_input_cmd) = &config.advanced.decoder.input_cmd {
-            dec_cmd.append(&mut decoder_input_cmd.clone());
+        if !config.task.icecast_url.is_empty() || !config.task.webhook_url.is_empty() {
+            tokio::spawn(now_playing::push(config.clone(), node.clone()));
         }
 
-        dec_cmd.append(&mut cmd);
+        hooks::trigger(&config, HookEvent::ClipStart, hooks::media_vars(&node));
 
-        if let Some(mut filter) = node.filter {
-            dec_cmd.append(&mut filter.cmd());
-            dec_cmd.append(&mut filter.map());
+        if config.output.zmq_socket.is_some() {
+            tokio::spawn(control::switch_outputs(config.clone(), node.clone()));
         }
 
-        if config.processing.vtt_enable && dec_cmd.iter().any(|s| s.ends_with(".vtt")) {
-            let i = dec_cmd
-                .iter()
-                .filter(|&n| n == "-i")
-                .count()
-                .saturating_sub(1);
+        let mut resumes = 0;
+        let mut played = Duration::ZERO;
+        let mut failed;
 
-            dec_cmd.append(&mut vec_strings!("-map", format!("{i}:s"), "-c:s", "copy"));
-        }
+        loop {
+            let dec_cmd = decoder_cmd(&config, &node, ff_log_format);
+
+            debug!(target: Target::file_mail(), channel = id;
+                "Decoder CMD: <span class=\"log-cmd\">ffmpeg {}</span>",
+                fmt_cmd(&dec_cmd)
+            );
+
+            // create ffmpeg decoder instance, for reading the input files
+            let mut dec_proc =
+                manager
+                    .spawner
+                    .spawn(Decoder, ffmpeg_bin(), &dec_cmd, Pipe::Stdout, &limits)?;
+
+            let mut dec_span = telemetry::child("decoder", id, &clip_span);
+            telemetry::media(&mut dec_span, &node);
+
+            let stall_timeout = node.stall_timeout.unwrap_or(config.general.stall_timeout);
+            // only local files can be resumed after the restart
+            let sync_tolerance = if Path::new(&node.source).is_file() {
+                config.processing.av_sync_tolerance as f64 / 1000.0
+            } else {
+                0.0
+            };
+            let mut sync_monitor = SyncMonitor::new();
+            let mut stalled = false;
+            let mut resync = false;
+            let clip_start = Instant::now();
+            let mut first_data = None;
+            let mut last_data = None;
+            let mut decoder_stdout = dec_proc.stdout.take().unwrap();
+            let dec_err = BufReader::new(dec_proc.stderr.take().unwrap());
+
+            *manager.clone().decoder.lock().await = Some(dec_proc);
+
+            let error_decoder_task = tokio::spawn(stderr_reader(
+                dec_err,
+                ignore_dec.clone(),
+                Decoder,
+                id,
+                Some(manager.audio_meter.clone()),
+                None,
+            ));
+
+            loop {
+                if ingest_is_alive.load(Ordering::SeqCst) {
+                    // read from ingest server instance
+                    if !live_on {
+                        info!(target: Target::file_mail(), channel = id; "Switch from {} to live ingest", config.processing.mode);
+                        hooks::trigger(&config, HookEvent::IngestStart, BTreeMap::new());
+                        playlist_init.store(true, Ordering::SeqCst);
+
+                        manager.stop(Decoder).await;
+                        live_on = true;
+                        live_start = Instant::now();
+                        ingest_span = Some(telemetry::span("ingest", id));
+                        telemetry::event(&mut clip_span, "ingest start");
+                        replaced = true;
+                    }
 
-        if let Some(cmd) = &config.processing.cmd {
-            dec_cmd.extend_from_slice(cmd);
-        }
+                    let num = {
+                        let mut ingest_reader_guard = manager.ingest_reader.lock().await;
+                        if let Some(ref mut ingest_stdout) = *ingest_reader_guard {
+                            ingest_stdout.read(&mut buffer[..]).await?
+                        } else {
+                            0
+                        }
+                    };
+
+                    if num == 0 {
+                        continue;
+                    }
 
-        debug!(target: Target::file_mail(), channel = id;
-            "Decoder CMD: <span class=\"log-cmd\">ffmpeg {}</span>",
-            fmt_cmd(&dec_cmd)
-        );
+                    sink.write(&buffer[..num]).await?;
+                    manager.tick(true);
+                } else {
+                    // read from decoder instance
+                    if live_on {
+                        info!(target: Target::file_mail(), channel = id; "Switch from live ingest to {}", config.processing.mode);
+                        hooks::trigger(&config, HookEvent::IngestStop, BTreeMap::new());
+                        tokio::spawn(statistics::ingest(
+                            manager.clone(),
+                            live_start.elapsed().as_secs_f64(),
+                        ));
+
+                        live_on = false;
+
+                        if let Some(mut span) = ingest_span.take() {
+                            telemetry::attribute(
+                                &mut span,
+                                "ingest.seconds",
+                                live_start.elapsed().as_secs_f64(),
+                            );
+                        }
+                        break;
+                    }
 
-        // create ffmpeg decoder instance, for reading the input files
-        let mut dec_proc = Command::new("ffmpeg")
-            .args(dec_cmd)
-            .kill_on_drop(true)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
+                    let num = if stall_timeout > 0.0 {
+                        match timeout(
+                            Duration::from_secs_f64(stall_timeout),
+                            decoder_stdout.read(&mut buffer[..]),
+                        )
+                        .await
+                        {
+                            Ok(num) => num?,
+                            Err(_) => {
+                                error!(target: Target::file_mail(), channel = id;
+                                    "Decoder stalled, no data for <span class=\"log-number\">{stall_timeout}s</span> after <span class=\"log-number\">{}</span> of <span class=\"log-addr\">{}</span>, skip to next clip",
+                                    sec_to_time(clip_start.elapsed().as_secs_f64()),
+                                    node.source
+                                );
+
+                                stalled = true;
+                                telemetry::event(&mut dec_span, "stall");
+
+                                if let Some(proc) = manager.decoder.lock().await.as_mut()
+                                    && let Err(e) = proc.kill().await
+                                {
+                                    error!(target: Target::file_mail(), channel = id; "Failed to kill stalled decoder: {e}");
+                                }
+
+                                break;
+                            }
+                        }
+                    } else {
+                        decoder_stdout.read(&mut buffer[..]).await?
+                    };
 
-        let mut decoder_stdout = dec_proc.stdout.take().unwrap();
-        let dec_err = BufReader::new(dec_proc.stderr.take().unwrap());
+                    if num == 0 {
+                        break;
+                    }
 
-        *manager.clone().decoder.lock().await = Some(dec_proc);
+                    sink.write(&buffer[..num]).await?;
+                    manager.tick(true);
+
+                    if sync_tolerance > 0.0
+                        && let Some(drift) = sync_monitor.feed(&buffer[..num])
+                        && drift.abs() > sync_tolerance
+                    {
+                        warn!(target: Target::file_mail(), channel = id;
+                            "A/V drift of <span class=\"log-number\">{:.0}ms</span> in <span class=\"log-addr\">{}</span>, restart decoder",
+                            drift * 1000.0,
+                            node.source
+                        );
+
+                        resync = true;
+                        telemetry::event(&mut dec_span, "resync");
+                        manager.stop(Decoder).await;
+                    }
 
-        let error_decoder_task = tokio::spawn(stderr_reader(dec_err, ignore_dec, Decoder, id));
+                    let now = Instant::now();
 
-        loop {
-            if ingest_is_alive.load(Ordering::SeqCst) {
-                // read from ingest server instance
-                if !live_on {
-                    info!(target: Target::file_mail(), channel = id; "Switch from {} to live ingest", config.processing.mode);
-                    playlist_init.store(true, Ordering::SeqCst);
+                    if first_data.is_none() {
+                        telemetry::event(&mut dec_span, "first data");
+                    }
 
-                    manager.stop(Decoder).await;
-                    live_on = true;
+                    first_data.get_or_insert(now);
+                    last_data = Some(now);
                 }
+            }
 
-                let num = {
-                    let mut ingest_reader_guard = manager.ingest_reader.lock().await;
-                    if let Some(ref mut ingest_stdout) = *ingest_reader_guard {
-                        ingest_stdout.read(&mut buffer[..]).await?
-                    } else {
-                        0
-                    }
-                };
+            drop(decoder_stdout);
+
+            let status = manager.wait(Decoder).await;
+            error_decoder_task.await??;
+
+            // a restart for the sync is no failure of the clip
+            failed = !resync && (stalled || status.and_then(|s| s.code()).is_some_and(|c| c != 0));
+
+            if let Some(code) = status.and_then(|s| s.code()) {
+                telemetry::attribute(&mut dec_span, "process.exit_code", code as i64);
+            }
+
+            if failed {
+                telemetry::error(&mut dec_span, if stalled { "stalled" } else { "failed" });
+            }
+
+            drop(dec_span);
+
+            if let (Some(first), Some(last)) = (first_data, last_data) {
+                played += last - first;
+            }
+
+            if !replaced && is_alive.load(Ordering::SeqCst) {
+                // a stalled decoder would stall again at the same position
+                if (resync || failed && !stalled && config.general.decoder_resume)
+                    && resumes < MAX_RESUMES
+                    && let (Some(first), Some(last)) = (first_data, last_data)
+                    && let Some(resume) = resume_node(&manager, &config, &node, last - first).await
+                {
+                    warn!(target: Target::file_mail(), channel = id;
+                        "Decoder {}, resume <span class=\"log-addr\">{}</span> at <span class=\"log-number\">{}</span>",
+                        if resync { "out of sync" } else { "crashed" },
+                        node.source,
+                        sec_to_time(resume.seek)
+                    );
+
+                    resumes += 1;
+                    node = resume;
 
-                if num == 0 {
-                    enc_writer.flush().await?;
                     continue;
                 }
 
-                enc_writer.write_all(&buffer[..num]).await?;
-            } else {
-                // read from decoder instance
-                if live_on {
-                    info!(target: Target::file_mail(), channel = id; "Switch from live ingest to {}", config.processing.mode);
+                check_quarantine(&manager, &config, &node.source, failed).await;
 
-                    live_on = false;
-                    break;
-                }
+                let mut remaining = node.length() - played.as_secs_f64();
+                let no_data = failed && first_data.is_none();
 
-                let num = decoder_stdout.read(&mut buffer[..]).await?;
+                if !is_generated(&node.source) {
+                    slate_trigger.update(&manager, no_data);
+                }
 
-                if num == 0 {
-                    enc_writer.flush().await?;
-                    break;
+                if slate_trigger.active() {
+                    // try the schedule again at its current position, after a short slate
+                    playlist_init.store(true, Ordering::SeqCst);
+                    remaining = remaining.min(SLATE_LENGTH);
                 }
 
-                enc_writer.write_all(&buffer[..num]).await?;
+                if no_data && remaining >= MIN_SLATE_LENGTH && !is_generated(&node.source) {
+                    warn!(target: Target::file_mail(), channel = id;
+                        "No data from <span class=\"log-addr\">{}</span>, show slate for <span class=\"log-number\">{}</span>",
+                        node.source,
+                        sec_to_time(remaining)
+                    );
+
+                    slate = Some(slate_node(&manager, &config, &node, remaining).await);
+                }
             }
+
+            break;
         }
 
-        drop(decoder_stdout);
+        last_program.clone_from(&node.program_id);
+        clip_hooks(&config, &node, played.as_secs_f64(), failed);
+        telemetry::attribute(&mut clip_span, "media.played", played.as_secs_f64());
+
+        if failed {
+            telemetry::error(&mut clip_span, "clip failed");
+        }
 
-        manager.wait(Decoder).await;
-        error_decoder_task.await??;
+        tokio::spawn(as_run::log(
+            manager.clone(),
+            node.clone(),
+            aired,
+            played.as_secs_f64(),
+            failed,
+        ));
+
+        if let Some(record_node) = record_node
+            && !replaced
+            && is_alive.load(Ordering::SeqCst)
+        {
+            let channel = manager.channel.lock().await.name.clone();
+
+            tokio::spawn(vod::export(config.clone(), channel, record_node, aired));
+        }
     }
 
     Ok(())
 }
 
+/// Check if the player stops before the node, when the channel drains.
+///
+/// Clips of the same program block as the last clip still play.
+pub(crate) fn drain_before(
+    manager: &ChannelManager,
+    last_program: &Option<String>,
+    node: &Media,
+) -> bool {
+    if !manager.draining.load(Ordering::SeqCst) {
+        return false;
+    }
+
+    if last_program.is_some() && *last_program == node.program_id {
+        return false;
+    }
+
+    info!(target: Target::file_mail(), channel = manager.id; "Drain: stop before <span class=\"log-addr\">{}</span>", node.source);
+
+    true
+}
+
+/// Run the hooks for the end of a clip and for a failed clip.
+pub(crate) fn clip_hooks(config: &PlayoutConfig, node: &Media, played: f64, failed: bool) {
+    let mut vars = hooks::media_vars(node);
+    vars.insert("played".to_string(), format!("{played:.3}"));
+    vars.insert("error".to_string(), failed.to_string());
+
+    hooks::trigger(config, HookEvent::ClipEnd, vars.clone());
+
+    if failed {
+        vars.insert(
+            "error".to_string(),
+            format!("Decoder failed on {}", node.source),
+        );
+
+        hooks::trigger(config, HookEvent::Error, vars);
+    }
+}
+
+/// Clip from the last played position on, when it can be resumed.
+///
+/// Only local files with a seekable duration are resumed; images,
+/// looped clips and remote sources get skipped as before.
+pub async fn resume_node(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    node: &Media,
+    played: Duration,
+) -> Option<Media> {
+    let is_image = node
+        .source
+        .rsplit_once('.')
+        .is_some_and(|(_, e)| IMAGE_FORMAT.contains(&e.to_lowercase().as_str()));
+
+    if is_image || node.out > node.duration || !Path::new(&node.source).is_file() {
+        return None;
+    }
+
+    let played = played.as_secs_f64() * node.speed.unwrap_or(1.0);
+    let seek = ((node.seek + played) * 1000.0).floor() / 1000.0;
+
+    if node.out - seek < MIN_RESUME_LENGTH {
+        return None;
+    }
+
+    let mut resume = node.clone();
+    resume.seek = seek;
+    resume.cmd = Some(seek_and_length(config, &mut resume));
+    resume.add_filter(config, &manager.filter_chain).await;
+
+    Some(resume)
+}
+
+/// Failed clips in a row (`general.slate_errors`), which switch to the standby slate.
+///
+/// While it is active, the failed clips get only a short slate and the schedule is tried
+/// again at its current position, so it comes back as soon as a source plays again.
+#[derive(Clone, Debug, Default)]
+pub struct SlateTrigger {
+    limit: u32,
+    failures: u32,
+}
+
+impl SlateTrigger {
+    pub fn new(limit: u32) -> Self {
+        Self { limit, failures: 0 }
+    }
+
+    pub fn active(&self) -> bool {
+        self.limit > 0 && self.failures >= self.limit
+    }
+
+    /// Count the result of a clip, returns the new state when it changes.
+    pub fn count(&mut self, failed: bool) -> Option<bool> {
+        let active = self.active();
+
+        if failed {
+            self.failures = self.failures.saturating_add(1);
+        } else {
+            self.failures = 0;
+        }
+
+        (active != self.active()).then(|| self.active())
+    }
+
+    fn update(&mut self, manager: &ChannelManager, failed: bool) {
+        match self.count(failed) {
+            Some(true) => {
+                error!(target: Target::file_mail(), channel = manager.id;
+                    "<span class=\"log-number\">{}</span> clips failed in a row, switch to standby slate",
+                    self.failures
+                );
+            }
+            Some(false) => {
+                info!(target: Target::file_mail(), channel = manager.id; "Sources play again, back to schedule");
+            }
+            None => {}
+        }
+    }
+}
+
+/// Slate in place of a clip, which can't be played, so the output keeps running.
+pub(crate) async fn slate_node(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    node: &Media,
+    duration: f64,
+) -> Media {
+    let (source, cmd) = gen_slate(config, duration);
+    let mut slate = Media {
+        index: node.index,
+        program_id: node.program_id.clone(),
+        title: Some("Slate".to_string()),
+        category: "slate".to_string(),
+        source,
+        seek: 0.0,
+        out: duration,
+        duration,
+        cmd: Some(cmd),
+        unit: node.unit,
+        ..Default::default()
+    };
+
+    slate.add_filter(config, &manager.filter_chain).await;
+
+    slate
+}
+
+/// Log the start of a program with its aggregate timing, on the first item.
+pub(crate) async fn log_program(manager: &ChannelManager, node: &Media) {
+    let (Some(index), Some(_)) = (node.index, &node.program_id) else {
+        return;
+    };
+    let id = manager.config.read().await.general.channel_id;
+    let list = manager.current_list.lock().await;
+
+    // filler and fallback clips are not in the playlist
+    if list.get(index).is_none_or(|m| m.source != node.source) {
+        return;
+    }
+
+    if let Some(program) = program::program_at(&list, index)
+        && program.index == index
+    {
+        info!(target: Target::file_mail(), channel = id;
+            "Start program <span class=\"log-addr\">{}</span> with <span class=\"log-number\">{}</span> items, duration: <span class=\"log-number\">{}</span>, breaks: <span class=\"log-number\">{}</span>",
+            program.program_id.unwrap_or_default(),
+            program.items,
+            sec_to_time(program.duration),
+            sec_to_time(program.breaks)
+        );
+    }
+}
+
+/// Count decoder failures of a file, after too many in a row it goes in quarantine.
+pub(crate) async fn check_quarantine(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    source: &str,
+    failed: bool,
+) {
+    if !Path::new(source).is_file() {
+        return;
+    }
+
+    let mut quarantine = manager.quarantine.lock().await;
+
+    if !failed {
+        quarantine.succeeded(source);
+    } else if quarantine.failed(
+        source,
+        time_now(&config.channel.timezone).date_naive(),
+        config.general.quarantine,
+    ) {
+        error!(target: Target::file_mail(), channel = config.general.channel_id;
+            "Clip failed <span class=\"log-number\">{}</span> times in a row, it is replaced by filler for the rest of the day: <span class=\"log-addr\">{source}</span>",
+            config.general.quarantine
+        );
+    }
+}
+
 /// Player
 ///
 /// Here we create the input file loop, from playlist, or folder source.
 /// Then we read the stdout from the reader ffmpeg instance
-/// and write it to the stdin from the streamer ffmpeg instance.
+/// and write it to the encoder sink.
 /// If it is configured we also fire up a ffmpeg ingest server instance,
 /// for getting live feeds.
 /// When a live ingest arrive, it stops the current playing and switch to the live source.
 /// When ingest stops, it switch back to playlist/folder mode.
+///
+/// The player is the supervisor: encoder sink and ingest server run as
+/// [workers](supervisor), which get restarted on their own after a failure.
 pub async fn player(manager: ChannelManager) -> Result<(), ServiceError> {
     let config = manager.config.read().await.clone();
-    let config_clone = config.clone();
     let ff_log_format = format!("level+{}", config.logging.ffmpeg_level.to_lowercase());
-    let ignore_enc = config.logging.ignore_lines.clone();
-    let channel_id = config.general.channel_id;
-
-    // get ffmpeg output instance
-    let mut enc_proc = match config.output.mode {
-        Desktop => desktop::output(&config, &ff_log_format).await?,
-        Null => null::output(&config, &ff_log_format).await?,
-        Stream => stream::output(&config, &ff_log_format).await?,
-        HLS => {
-            hls::writer(&manager, &ff_log_format).await?;
-            manager.stop_all(false).await;
-
-            return Ok(());
-        }
-    };
 
-    let enc_err = BufReader::new(enc_proc.stderr.take().unwrap());
-    let enc_writer = enc_proc.stdin.take().unwrap();
+    if config.output.mode == HLS {
+        hls::writer(&manager, &ff_log_format).await?;
+        manager.stop_all(false).await;
 
-    *manager.encoder.lock().await = Some(enc_proc);
-    let mgr_clone2 = manager.clone();
+        return Ok(());
+    }
 
-    // spawn a task to log ffmpeg output error messages
-    let handle_enc_stderr = tokio::spawn(stderr_reader(enc_err, ignore_enc, Encoder, channel_id));
+    let latency = Duration::from_millis(config.general.buffer_latency as u64);
+    let (sink, mut encoder) = supervisor::encoder_sink(manager.clone(), latency, &ff_log_format);
 
-    // spawn a task for a ffmpeg ingest server
-    let handle_ingest = if config.ingest.enable {
-        Some(tokio::spawn(ingest_server(config_clone, mgr_clone2)))
-    } else {
-        None
-    };
+    // spawn a worker for a ffmpeg ingest server
+    let mut ingest = config
+        .ingest
+        .enable
+        .then(|| tokio::spawn(supervisor::ingest_worker(manager.clone())));
 
-    tokio::select! {
-        result = handle_enc_stderr => {
-            result??;
-        }
+    let result = tokio::select! {
+        result = &mut encoder => result?,
 
         result = async {
-            if let Some(f) = handle_ingest {
+            if let Some(f) = ingest.as_mut() {
                 f.await?
             } else {
                 Ok(())
             }
-        }, if handle_ingest.is_some() => {
-            result?;
-        }
+        }, if ingest.is_some() => result,
 
-        result = play(manager, enc_writer, &ff_log_format) => {
-            result?;
-        }
-    }
+        result = play(manager.clone(), sink, &ff_log_format) => result,
+    };
 
     trace!("Out of source loop");
 
-    Ok(())
+    if let Some(ingest) = ingest {
+        ingest.abort();
+    }
+
+    if result.is_ok() && manager.draining.load(Ordering::SeqCst) {
+        manager
+            .drain(Duration::from_secs(ARGS.shutdown_timeout))
+            .await;
+    }
+
+    encoder.abort();
+
+    result
 }
@@ -0,0 +1,400 @@
+/// Supervisor
+///
+/// The player is split in a supervisor and worker tasks. The supervisor ([`super::player`])
+/// runs the decoder feed with sources, switching and timing; the encoder sink and the ingest
+/// server are workers, which get restarted on their own, when they fail. So a crashed encoder
+/// does not end the current clip and a failed ingest server does not stop the playout.
+///
+/// Only when a worker fails too often within [`RESTART_WINDOW`], the error goes up and the
+/// whole channel gets restarted.
+///
+/// Decoder feed and encoder sink are connected by a [`RingBuffer`], which keeps its data,
+/// when the encoder gets restarted.
+///
+/// An output switch ([`ChannelManager::output_switch`]) starts a second encoder with the
+/// saved output config. Both get the same data for [`SWITCH_OVERLAP`], then the new one
+/// takes over between two chunks and the old one finishes its output.
+use std::{
+    collections::VecDeque,
+    mem,
+    sync::{Arc, atomic::Ordering},
+    time::{Duration, Instant},
+};
+
+use log::*;
+use tokio::{
+    io::{AsyncWriteExt, BufReader},
+    process::{Child, ChildStdin},
+    task::JoinHandle,
+    time::{sleep, timeout},
+};
+
+use crate::player::{
+    controller::{ChannelManager, ProcessUnit::*},
+    input::ingest_server,
+    utils::{ring_buffer::RingBuffer, stderr_reader},
+};
+use crate::utils::{
+    config::{OutputMode::*, PlayoutConfig},
+    errors::ServiceError,
+    logging::Target,
+    telemetry,
+};
+use crate::vec_strings;
+
+/// Restarts of a worker within the window, before it gives up.
+pub const MAX_RESTARTS: usize = 3;
+pub const RESTART_WINDOW: Duration = Duration::from_secs(60);
+
+/// Time, in which the old and the new encoder of an output switch get the same data.
+pub const SWITCH_OVERLAP: Duration = Duration::from_secs(2);
+
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+/// Time for the old encoder of an output switch, to finish its output.
+const SWITCH_FINISH: Duration = Duration::from_secs(10);
+/// Bytes per write to the encoder, the Linux pipe buffer size.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Count restarts in a time window.
+#[derive(Clone, Debug)]
+pub struct RestartPolicy {
+    max: usize,
+    window: Duration,
+    restarts: VecDeque<Instant>,
+}
+
+impl RestartPolicy {
+    pub fn new(max: usize, window: Duration) -> Self {
+        Self {
+            max,
+            window,
+            restarts: VecDeque::new(),
+        }
+    }
+
+    /// Register a restart, returns false when there are too many.
+    pub fn allow(&mut self, now: Instant) -> bool {
+        while self
+            .restarts
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > self.window)
+        {
+            self.restarts.pop_front();
+        }
+
+        if self.restarts.len() >= self.max {
+            return false;
+        }
+
+        self.restarts.push_back(now);
+
+        true
+    }
+}
+
+/// Input side of the encoder sink, used by the decoder feed.
+///
+/// The buffer gets closed, when the sink is dropped.
+#[derive(Debug)]
+pub struct EncoderSink {
+    buffer: Arc<RingBuffer>,
+}
+
+impl EncoderSink {
+    pub async fn write(&self, data: &[u8]) -> Result<(), ServiceError> {
+        self.buffer.write(data).await
+    }
+}
+
+impl Drop for EncoderSink {
+    fn drop(&mut self) {
+        self.buffer.close();
+    }
+}
+
+async fn encoder_process(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    log_format: &str,
+    standby: bool,
+) -> Result<Child, ServiceError> {
+    let spawner = manager.spawner.as_ref();
+
+    if standby {
+        // the redundancy peer is on air, keep the timing without output
+        let mut standby_config = config.clone();
+        standby_config.output.mode = Null;
+        standby_config.output.output_count = 1;
+        standby_config.output.output_filter = None;
+        standby_config.output.output_cmd = Some(vec_strings!["-f", "null", "-"]);
+
+        return super::null::output(&standby_config, log_format, spawner).await;
+    }
+
+    match config.output.mode {
+        Desktop => super::desktop::output(config, log_format, spawner).await,
+        Null => super::null::output(config, log_format, spawner).await,
+        Stream => super::stream::output(config, log_format, spawner).await,
+        HLS => Err(ServiceError::Conflict(
+            "HLS has no separate encoder!".to_string(),
+        )),
+    }
+}
+
+/// Encoder process with its input and the task, which reads its stderr.
+struct EncoderProcess {
+    child: Child,
+    writer: ChildStdin,
+    stderr_task: JoinHandle<Result<(), ServiceError>>,
+}
+
+impl EncoderProcess {
+    async fn start(
+        manager: &ChannelManager,
+        config: &PlayoutConfig,
+        log_format: &str,
+        standby: bool,
+    ) -> Result<Self, ServiceError> {
+        let mut child = encoder_process(manager, config, log_format, standby).await?;
+        let enc_err = BufReader::new(child.stderr.take().unwrap());
+        let writer = child.stdin.take().unwrap();
+
+        let stderr_task = tokio::spawn(stderr_reader(
+            enc_err,
+            config.logging.ignore_lines.clone(),
+            Encoder,
+            config.general.channel_id,
+            None,
+            Some(manager.output_stats.clone()),
+        ));
+
+        Ok(Self {
+            child,
+            writer,
+            stderr_task,
+        })
+    }
+
+    /// Close the input, so the encoder finishes its output, kill it when it takes too long.
+    fn finish(self) {
+        let Self {
+            mut child,
+            writer,
+            stderr_task,
+        } = self;
+
+        drop(writer);
+
+        tokio::spawn(async move {
+            if timeout(SWITCH_FINISH, child.wait()).await.is_err() {
+                let _ = child.kill().await;
+            }
+
+            let _ = stderr_task.await;
+        });
+    }
+}
+
+/// Write the data of the feed to the encoder, until the feed is closed.
+///
+/// When the encoder stops or hits an unrecoverable error, it gets restarted.
+async fn encoder_worker(
+    manager: ChannelManager,
+    buffer: Arc<RingBuffer>,
+    log_format: String,
+) -> Result<(), ServiceError> {
+    let mut config = manager.config.read().await.clone();
+    let id = config.general.channel_id;
+    let mut policy = RestartPolicy::new(MAX_RESTARTS, RESTART_WINDOW);
+
+    manager.output_stats.lock().await.restart(Instant::now());
+
+    loop {
+        let standby = manager.standby.load(Ordering::SeqCst);
+        let EncoderProcess {
+            child,
+            mut writer,
+            mut stderr_task,
+        } = EncoderProcess::start(&manager, &config, &log_format, standby).await?;
+
+        *manager.encoder.lock().await = Some(child);
+
+        let mut enc_span = telemetry::span("encoder", id);
+        telemetry::attribute(&mut enc_span, "encoder.standby", standby);
+
+        // new encoder of an output switch, with its config and start
+        let mut next: Option<(EncoderProcess, PlayoutConfig, Instant)> = None;
+
+        let failure = loop {
+            tokio::select! {
+                chunk = buffer.read(CHUNK_SIZE) => match chunk {
+                    Some(data) => {
+                        manager
+                            .buffer_fill
+                            .store(buffer.latency().as_millis() as u64, Ordering::Relaxed);
+
+                        if let Err(e) = writer.write_all(&data).await {
+                            break e.to_string();
+                        }
+
+                        manager.output_stats.lock().await.input(data.len() as u64, Instant::now());
+
+                        let Some((encoder, _, start)) = next.as_mut() else {
+                            continue;
+                        };
+
+                        if encoder.writer.write_all(&data).await.is_err()
+                            || encoder.stderr_task.is_finished()
+                        {
+                            error!(target: Target::file_mail(), channel = id;
+                                "Output switch failed, the new encoder stopped, keep the current output"
+                            );
+
+                            if let Some((encoder, _, _)) = next.take() {
+                                encoder.stderr_task.abort();
+                            }
+                        } else if start.elapsed() >= SWITCH_OVERLAP
+                            && let Some((encoder, new_config, _)) = next.take()
+                        {
+                            // the new encoder takes over between two chunks
+                            let old_child = manager.encoder.lock().await.replace(encoder.child);
+                            let old_writer = mem::replace(&mut writer, encoder.writer);
+                            let old_task = mem::replace(&mut stderr_task, encoder.stderr_task);
+
+                            if let Some(child) = old_child {
+                                EncoderProcess {
+                                    child,
+                                    writer: old_writer,
+                                    stderr_task: old_task,
+                                }
+                                .finish();
+                            }
+
+                            config = new_config;
+                            telemetry::event(&mut enc_span, "output switch");
+
+                            info!(target: Target::file_mail(), channel = id; "Output switched to the new encoder");
+                        }
+                    }
+                    None => {
+                        // feed is done, the encoder finishes with the end of its input
+                        drop(next);
+                        drop(writer);
+                        stderr_task.await??;
+
+                        return Ok(());
+                    }
+                },
+                result = &mut stderr_task => {
+                    break match result {
+                        Ok(Ok(())) => "encoder stopped".to_string(),
+                        Ok(Err(e)) => e.to_string(),
+                        Err(e) => e.to_string(),
+                    };
+                }
+                _ = manager.output_switch.notified(), if next.is_none() => {
+                    let new_config = manager.config.read().await.clone();
+
+                    if standby {
+                        // no output to keep, the config applies when the output starts
+                        config = new_config;
+                        continue;
+                    }
+
+                    match EncoderProcess::start(&manager, &new_config, &log_format, false).await {
+                        Ok(encoder) => {
+                            info!(target: Target::file_mail(), channel = id; "Output switch: start new encoder");
+                            next = Some((encoder, new_config, Instant::now()));
+                        }
+                        Err(e) => {
+                            error!(target: Target::file_mail(), channel = id; "Output switch failed, keep the current output: {e}");
+                        }
+                    }
+                }
+            }
+        };
+
+        if let Some((encoder, _, _)) = next.take() {
+            encoder.stderr_task.abort();
+        }
+
+        if !manager.is_alive.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        if standby != manager.standby.load(Ordering::SeqCst) {
+            // switch between standby and output, no failure
+            manager.stop(Encoder).await;
+            continue;
+        }
+
+        telemetry::error(&mut enc_span, failure.clone());
+        drop(enc_span);
+
+        if !policy.allow(Instant::now()) {
+            return Err(ServiceError::Conflict(format!(
+                "Encoder failed {MAX_RESTARTS} times within {}s: {failure}",
+                RESTART_WINDOW.as_secs()
+            )));
+        }
+
+        error!(target: Target::file_mail(), channel = id; "Encoder failed ({failure}), restart it");
+
+        manager.stop(Encoder).await;
+        sleep(RESTART_DELAY).await;
+    }
+}
+
+/// Start the encoder sink worker, with a buffer for the target latency.
+pub fn encoder_sink(
+    manager: ChannelManager,
+    latency: Duration,
+    log_format: &str,
+) -> (EncoderSink, JoinHandle<Result<(), ServiceError>>) {
+    let buffer = Arc::new(RingBuffer::new(latency));
+    let worker_buffer = buffer.clone();
+    let log_format = log_format.to_string();
+
+    let handle = tokio::spawn(async move {
+        let result = encoder_worker(manager.clone(), worker_buffer.clone(), log_format).await;
+
+        // the feed must not wait for a sink, which is gone
+        worker_buffer.close();
+        manager.buffer_fill.store(0, Ordering::Relaxed);
+
+        result
+    });
+
+    (EncoderSink { buffer }, handle)
+}
+
+/// Run the ingest server and restart it after errors, until the channel stops.
+pub async fn ingest_worker(manager: ChannelManager) -> Result<(), ServiceError> {
+    let id = manager.id;
+    let mut policy = RestartPolicy::new(MAX_RESTARTS, RESTART_WINDOW);
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        let config = manager.config.read().await.clone();
+
+        let Err(e) = ingest_server(config, manager.clone()).await else {
+            continue;
+        };
+
+        manager.ingest_is_alive.store(false, Ordering::SeqCst);
+
+        if !manager.is_alive.load(Ordering::SeqCst) {
+            break;
+        }
+
+        if !policy.allow(Instant::now()) {
+            return Err(e);
+        }
+
+        error!(target: Target::file_mail(), channel = id; "Ingest server failed ({e}), restart it");
+
+        manager.stop(Ingest).await;
+        sleep(RESTART_DELAY).await;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,311 @@
+/// Process Spawner
+///
+/// Decoder, encoder and ingest server are started over a [`ProcessSpawner`] of the
+/// channel manager. [`FfmpegSpawner`] runs the real program, [`MockSpawner`] runs a small
+/// shell process instead, which plays back canned data: lines on stderr, bytes on stdout
+/// and an exit code. So clip switching, ingest takeover and error paths can be tested
+/// without ffmpeg.
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::CString,
+    fmt, io,
+    os::unix::ffi::OsStrExt,
+    path::{Component, Path},
+    process::Stdio,
+    sync::Mutex,
+    time::Duration,
+};
+
+use tokio::{
+    io::AsyncWriteExt,
+    process::{Child, Command},
+};
+
+use log::*;
+
+use crate::player::controller::ProcessUnit;
+use crate::utils::{config::PlayoutConfig, logging::Target};
+
+/// Pipe of the process, which is used besides stderr.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pipe {
+    /// Encoder, which reads from the player.
+    Stdin,
+    /// Decoder and ingest server, which write to the player.
+    Stdout,
+    /// Process, which writes its output itself, like the HLS writer.
+    None,
+}
+
+/// A cgroup is a path below /sys/fs/cgroup, without `..` to leave it.
+pub fn valid_cgroup(cgroup: &str) -> bool {
+    Path::new(cgroup.trim()).components().all(|c| {
+        matches!(
+            c,
+            Component::RootDir | Component::CurDir | Component::Normal(_)
+        )
+    })
+}
+
+/// Nice level and control group of the channel, which the process gets before it runs.
+#[derive(Clone, Debug, Default)]
+pub struct ResourceLimits {
+    channel_id: i32,
+    nice: i32,
+    cgroup_procs: Option<CString>,
+}
+
+impl ResourceLimits {
+    pub fn new(config: &PlayoutConfig) -> Self {
+        let channel_id = config.general.channel_id;
+        let cgroup = config.general.cgroup.trim();
+        let mut cgroup_procs = None;
+
+        // an invalid cgroup is reported by `check_format`, when the config gets loaded
+        if valid_cgroup(cgroup) && !cgroup.is_empty() {
+            let procs = Path::new("/sys/fs/cgroup")
+                .join(cgroup.trim_start_matches('/'))
+                .join("cgroup.procs");
+
+            cgroup_procs = CString::new(procs.as_os_str().as_bytes()).ok();
+        }
+
+        Self {
+            channel_id,
+            nice: config.general.nice.clamp(-20, 19),
+            cgroup_procs,
+        }
+    }
+
+    /// Set the limits in the forked child, before it executes the program.
+    ///
+    /// A process, which can't join the cgroup, doesn't start. A nice level, which the
+    /// user is not allowed to set, is only reported by [`ResourceLimits::spawn`].
+    pub fn apply(&self, cmd: &mut Command) {
+        if self.nice == 0 && self.cgroup_procs.is_none() {
+            return;
+        }
+
+        let nice = self.nice;
+        let procs = self.cgroup_procs.clone();
+
+        // SAFETY: the closure runs between fork and exec, it only makes syscalls
+        // and doesn't allocate.
+        unsafe {
+            cmd.pre_exec(move || {
+                if nice != 0 {
+                    nix::libc::setpriority(nix::libc::PRIO_PROCESS, 0, nice);
+                }
+
+                if let Some(procs) = &procs {
+                    let fd =
+                        nix::libc::open(procs.as_ptr(), nix::libc::O_WRONLY | nix::libc::O_CLOEXEC);
+
+                    if fd < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+
+                    // pid 0 moves the writing process itself
+                    let written = nix::libc::write(fd, b"0".as_ptr().cast(), 1);
+                    let err = io::Error::last_os_error();
+                    nix::libc::close(fd);
+
+                    if written != 1 {
+                        return Err(err);
+                    }
+                }
+
+                Ok(())
+            });
+        }
+    }
+
+    /// Spawn the command with the limits applied.
+    pub fn spawn(&self, cmd: &mut Command) -> io::Result<Child> {
+        self.apply(cmd);
+
+        let child = cmd.spawn().inspect_err(|e| {
+            if self.cgroup_procs.is_some() {
+                error!(target: Target::file_mail(), channel = self.channel_id;
+                    "Start process in cgroup failed: {e}"
+                );
+            }
+        })?;
+
+        if self.nice != 0
+            && let Some(pid) = child.id()
+        {
+            // SAFETY: getpriority only reads the priority of the child process.
+            let nice = unsafe { nix::libc::getpriority(nix::libc::PRIO_PROCESS, pid) };
+
+            if nice != self.nice {
+                warn!(target: Target::file_mail(), channel = self.channel_id;
+                    "Set nice level <span class=\"log-number\">{}</span> failed, process runs with <span class=\"log-number\">{nice}</span>",
+                    self.nice
+                );
+            }
+        }
+
+        Ok(child)
+    }
+}
+
+pub trait ProcessSpawner: fmt::Debug + Send + Sync {
+    /// Start the process of the unit with the resource limits, stderr is always piped.
+    fn spawn(
+        &self,
+        unit: ProcessUnit,
+        program: &Path,
+        args: &[String],
+        pipe: Pipe,
+        limits: &ResourceLimits,
+    ) -> io::Result<Child>;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FfmpegSpawner;
+
+impl ProcessSpawner for FfmpegSpawner {
+    fn spawn(
+        &self,
+        _unit: ProcessUnit,
+        program: &Path,
+        args: &[String],
+        pipe: Pipe,
+        limits: &ResourceLimits,
+    ) -> io::Result<Child> {
+        let mut cmd = Command::new(program);
+
+        cmd.args(args).kill_on_drop(true).stderr(Stdio::piped());
+
+        match pipe {
+            Pipe::Stdin => cmd.stdin(Stdio::piped()),
+            Pipe::Stdout => cmd.stdout(Stdio::piped()),
+            Pipe::None => &mut cmd,
+        };
+
+        limits.spawn(&mut cmd)
+    }
+}
+
+/// Canned run of a process.
+#[derive(Clone, Debug, Default)]
+pub struct MockProcess {
+    /// Lines on stderr, written at start.
+    pub stderr: Vec<String>,
+    /// Data on stdout, after the stderr lines.
+    pub stdout: Vec<u8>,
+    /// Time, which the process keeps running after its output.
+    pub duration: Duration,
+    pub exit_code: i32,
+}
+
+impl MockProcess {
+    pub fn new(stdout: Vec<u8>) -> Self {
+        Self {
+            stdout,
+            ..Default::default()
+        }
+    }
+}
+
+const MOCK_SCRIPT: &str = r#"code=$1; wait=$2; shift 2
+for line in "$@"; do printf '%s\n' "$line" >&2; done
+cat
+sleep "$wait"
+exit "$code""#;
+
+/// Spawner with canned processes per unit, it needs only `sh`, `cat` and `sleep`.
+///
+/// The processes of a unit are used in order, the last one is repeated. A unit without
+/// processes ends at once with no output. All spawned commands are recorded.
+#[derive(Debug, Default)]
+pub struct MockSpawner {
+    processes: Mutex<HashMap<ProcessUnit, VecDeque<MockProcess>>>,
+    calls: Mutex<Vec<(ProcessUnit, Vec<String>)>>,
+}
+
+impl MockSpawner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&self, unit: ProcessUnit, process: MockProcess) {
+        self.processes
+            .lock()
+            .unwrap()
+            .entry(unit)
+            .or_default()
+            .push_back(process);
+    }
+
+    /// Arguments of the spawned processes of the unit.
+    pub fn calls(&self, unit: ProcessUnit) -> Vec<Vec<String>> {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(u, _)| *u == unit)
+            .map(|(_, args)| args.clone())
+            .collect()
+    }
+
+    fn next(&self, unit: ProcessUnit) -> MockProcess {
+        let mut processes = self.processes.lock().unwrap();
+        let Some(queue) = processes.get_mut(&unit) else {
+            return MockProcess::default();
+        };
+
+        if queue.len() > 1 {
+            queue.pop_front().unwrap_or_default()
+        } else {
+            queue.front().cloned().unwrap_or_default()
+        }
+    }
+}
+
+impl ProcessSpawner for MockSpawner {
+    fn spawn(
+        &self,
+        unit: ProcessUnit,
+        _program: &Path,
+        args: &[String],
+        pipe: Pipe,
+        _limits: &ResourceLimits,
+    ) -> io::Result<Child> {
+        let process = self.next(unit);
+
+        self.calls.lock().unwrap().push((unit, args.to_vec()));
+
+        let mut cmd = Command::new("sh");
+
+        cmd.arg("-c")
+            .arg(MOCK_SCRIPT)
+            .arg("sh")
+            .arg(process.exit_code.to_string())
+            .arg(process.duration.as_secs_f64().to_string())
+            .args(&process.stderr)
+            .kill_on_drop(true)
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        if pipe == Pipe::Stdout {
+            cmd.stdout(Stdio::piped());
+        } else {
+            // the input of an encoder gets consumed by cat
+            cmd.stdout(Stdio::null());
+        }
+
+        let mut child = cmd.spawn()?;
+
+        if pipe != Pipe::Stdin
+            && let Some(mut stdin) = child.stdin.take()
+        {
+            tokio::spawn(async move {
+                let _ = stdin.write_all(&process.stdout).await;
+            });
+        }
+
+        Ok(child)
+    }
+}
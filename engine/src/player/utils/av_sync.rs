@@ -0,0 +1,155 @@
+/// A/V Sync
+///
+/// The decoder sends MPEG-TS through the pipe. The monitor reads the PTS of the video and
+/// audio packets and compares them with the offset at the clip start, so drift, which
+/// accumulates on long clips, gets detected. When it is over the tolerance
+/// (`processing.av_sync_tolerance`), the player restarts the decoder at the current position.
+///
+/// Smaller drift is corrected in the decoder by the `aresample` filter.
+use std::collections::VecDeque;
+
+pub const TS_PACKET: usize = 188;
+pub const TS_SYNC: u8 = 0x47;
+/// PTS clock rate.
+pub const PTS_RATE: f64 = 90_000.0;
+
+/// Offset samples for the average.
+const WINDOW: usize = 50;
+/// PTS jump in seconds, which is a new time base and not drift.
+const DISCONTINUITY: f64 = 10.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PesKind {
+    Video,
+    Audio,
+}
+
+/// Stream kind and PTS from the start of a PES packet in a TS packet.
+pub fn pes_pts(packet: &[u8]) -> Option<(PesKind, i64)> {
+    if packet.len() < TS_PACKET || packet[0] != TS_SYNC || packet[1] & 0x40 == 0 {
+        return None;
+    }
+
+    let adaptation = (packet[3] >> 4) & 0x03;
+    let mut start = 4;
+
+    if adaptation & 0x01 == 0 {
+        return None;
+    }
+
+    if adaptation & 0x02 != 0 {
+        start += 1 + packet[4] as usize;
+    }
+
+    let pes = packet.get(start..TS_PACKET)?;
+
+    if pes.len() < 14 || pes[..3] != [0, 0, 1] || pes[7] & 0x80 == 0 {
+        return None;
+    }
+
+    let kind = match pes[3] {
+        0xE0..=0xEF => PesKind::Video,
+        0xC0..=0xDF | 0xBD => PesKind::Audio,
+        _ => return None,
+    };
+
+    let p = &pes[9..14];
+    let pts = (((p[0] as i64) >> 1) & 0x07) << 30
+        | (p[1] as i64) << 22
+        | ((p[2] as i64) >> 1) << 15
+        | (p[3] as i64) << 7
+        | (p[4] as i64) >> 1;
+
+    Some((kind, pts))
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SyncMonitor {
+    remainder: Vec<u8>,
+    video: Option<i64>,
+    audio: Option<i64>,
+    offsets: VecDeque<f64>,
+    baseline: Option<f64>,
+}
+
+impl SyncMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the data from the pipe and get the drift in seconds, positive when
+    /// video is ahead of audio.
+    pub fn feed(&mut self, data: &[u8]) -> Option<f64> {
+        self.remainder.extend_from_slice(data);
+
+        let mut used = 0;
+        let mut drift = None;
+
+        loop {
+            let Some(start) = self.remainder[used..].iter().position(|b| *b == TS_SYNC) else {
+                // no packet start, the rest is garbage
+                used = self.remainder.len();
+                break;
+            };
+            let start = used + start;
+
+            if self.remainder.len() < start + TS_PACKET {
+                used = start;
+                break;
+            }
+
+            if let Some((kind, pts)) = pes_pts(&self.remainder[start..start + TS_PACKET])
+                && let Some(d) = self.add(kind, pts)
+            {
+                drift = Some(d);
+            }
+
+            used = start + TS_PACKET;
+        }
+
+        self.remainder.drain(..used);
+
+        drift
+    }
+
+    fn add(&mut self, kind: PesKind, pts: i64) -> Option<f64> {
+        let last = match kind {
+            PesKind::Video => self.video.replace(pts),
+            PesKind::Audio => self.audio.replace(pts),
+        };
+
+        if last.is_some_and(|l| ((pts - l) as f64 / PTS_RATE).abs() > DISCONTINUITY) {
+            self.reset();
+            return None;
+        }
+
+        let (Some(video), Some(audio)) = (self.video, self.audio) else {
+            return None;
+        };
+
+        if kind != PesKind::Video {
+            return None;
+        }
+
+        self.offsets.push_back((video - audio) as f64 / PTS_RATE);
+
+        if self.offsets.len() < WINDOW {
+            return None;
+        }
+
+        let average = self.offsets.iter().sum::<f64>() / self.offsets.len() as f64;
+        self.offsets.pop_front();
+
+        // the muxer interleaves with a constant offset, only a change of it is drift
+        let baseline = *self.baseline.get_or_insert(average);
+
+        Some(average - baseline)
+    }
+
+    pub fn reset(&mut self) {
+        self.video = None;
+        self.audio = None;
+        self.offsets.clear();
+        self.baseline = None;
+    }
+}
@@ -0,0 +1,247 @@
+/// ID3 Timed Metadata
+///
+/// In HLS mode every clip gets a small MPEG-TS file as extra input, which carries one
+/// ID3 tag at the clip start. ffmpeg copies it as `timed_id3` data stream into the segments,
+/// so players (hls.js, Safari, video.js, etc.) can read it as cue.
+///
+/// The tag contains the frames:
+///
+/// | frame                | content                                        |
+/// |----------------------|------------------------------------------------|
+/// | TIT2                 | title                                          |
+/// | TPE1                 | artist, when known                             |
+/// | TXXX:CATEGORY        | category from playlist                         |
+/// | TXXX:AD_BREAK        | `start` on first ad, `end` on first clip after |
+/// | TXXX:AD_DURATION     | length of all following ads in seconds         |
+use std::path::PathBuf;
+
+use crate::player::utils::Media;
+use crate::utils::{config::PlayoutConfig, now_playing::NowPlaying};
+
+/// Ending of the input files, to find them in the ffmpeg command.
+pub const ID3_EXTENSION: &str = ".id3.ts";
+
+const TS_PACKET_SIZE: usize = 188;
+const PMT_PID: u16 = 0x1000;
+const ID3_PID: u16 = 0x0100;
+const ID3_STREAM_TYPE: u8 = 0x15;
+const PRIVATE_STREAM_1: u8 = 0xBD;
+
+fn is_ad(media: &Media) -> bool {
+    media.category == "advertisement"
+}
+
+/// Frames for the clip at `index`, the list is needed for the length of an ad break.
+pub fn id3_frames(list: &[Media], index: usize) -> Vec<(String, String)> {
+    let Some(media) = list.get(index) else {
+        return vec![];
+    };
+    let now_playing = NowPlaying::new(0, media);
+    let mut frames = vec![("TIT2".to_string(), now_playing.title)];
+
+    if let Some(artist) = now_playing.artist {
+        frames.push(("TPE1".to_string(), artist));
+    }
+
+    if !media.category.is_empty() {
+        frames.push(("TXXX:CATEGORY".to_string(), media.category.clone()));
+    }
+
+    if is_ad(media) && (index == 0 || !is_ad(&list[index - 1])) {
+        let duration: f64 = list[index..]
+            .iter()
+            .take_while(|m| is_ad(m))
+            .map(Media::length)
+            .sum();
+
+        frames.push(("TXXX:AD_BREAK".to_string(), "start".to_string()));
+        frames.push(("TXXX:AD_DURATION".to_string(), format!("{duration:.3}")));
+    } else if !is_ad(media) && index > 0 && is_ad(&list[index - 1]) {
+        frames.push(("TXXX:AD_BREAK".to_string(), "end".to_string()));
+    }
+
+    frames
+}
+
+fn syncsafe(size: usize) -> [u8; 4] {
+    [
+        ((size >> 21) & 0x7F) as u8,
+        ((size >> 14) & 0x7F) as u8,
+        ((size >> 7) & 0x7F) as u8,
+        (size & 0x7F) as u8,
+    ]
+}
+
+/// ID3v2.4 tag with UTF-8 text frames, `TXXX:NAME` becomes a user defined text frame.
+pub fn id3_tag(frames: &[(String, String)]) -> Vec<u8> {
+    let mut body = vec![];
+
+    for (id, value) in frames {
+        let (id, content) = match id.split_once(':') {
+            Some((id, description)) => (id, format!("{description}\0{value}")),
+            None => (id.as_str(), value.clone()),
+        };
+        let size = content.len() + 1;
+
+        body.extend_from_slice(id.as_bytes());
+        body.extend_from_slice(&syncsafe(size));
+        body.extend_from_slice(&[0, 0, 3]);
+        body.extend_from_slice(content.as_bytes());
+    }
+
+    let mut tag = vec![b'I', b'D', b'3', 4, 0, 0];
+    tag.extend_from_slice(&syncsafe(body.len()));
+    tag.append(&mut body);
+
+    tag
+}
+
+/// CRC-32/MPEG-2 for the PSI sections.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF_u32;
+
+    for byte in data {
+        crc ^= (*byte as u32) << 24;
+
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+/// Section with the given table id, the section length and CRC are added.
+fn section(table_id: u8, id: u16, data: &[u8]) -> Vec<u8> {
+    let length = data.len() + 9;
+    let mut section = vec![
+        table_id,
+        0xB0 | (length >> 8) as u8,
+        length as u8,
+        (id >> 8) as u8,
+        id as u8,
+        0xC1,
+        0,
+        0,
+    ];
+    section.extend_from_slice(data);
+    section.extend_from_slice(&crc32(&section).to_be_bytes());
+
+    section
+}
+
+/// Descriptor body for ID3 metadata, as written from ffmpeg and Apple's segmenter.
+fn metadata_descriptor(tag: u8, mut tail: Vec<u8>) -> Vec<u8> {
+    let mut descriptor = vec![tag, 0, 0xFF, 0xFF, b'I', b'D', b'3', b' ', 0xFF];
+    descriptor.extend_from_slice(b"ID3 ");
+    descriptor.push(0);
+    descriptor.append(&mut tail);
+    descriptor[1] = (descriptor.len() - 2) as u8;
+
+    descriptor
+}
+
+/// Split payload in TS packets, the last packet is filled with an adaptation field.
+fn packets(pid: u16, payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+
+    for (cc, chunk) in payload.chunks(TS_PACKET_SIZE - 4).enumerate() {
+        let start = if cc == 0 { 0x40 } else { 0 };
+        let stuffing = TS_PACKET_SIZE - 4 - chunk.len();
+
+        out.extend_from_slice(&[0x47, start | (pid >> 8) as u8, pid as u8]);
+
+        if stuffing == 0 {
+            out.push(0x10 | (cc & 0x0F) as u8);
+        } else {
+            out.push(0x30 | (cc & 0x0F) as u8);
+            out.push((stuffing - 1) as u8);
+
+            if stuffing > 1 {
+                out.push(0);
+                out.extend(std::iter::repeat_n(0xFF, stuffing - 2));
+            }
+        }
+
+        out.extend_from_slice(chunk);
+    }
+
+    out
+}
+
+/// MPEG-TS with PAT, PMT and one PES with the ID3 tag at PTS 0.
+pub fn id3_ts(tag: &[u8]) -> Vec<u8> {
+    let mut pat = vec![0];
+    pat.append(&mut section(
+        0x00,
+        1,
+        &[0, 1, 0xE0 | (PMT_PID >> 8) as u8, PMT_PID as u8],
+    ));
+
+    let program_info = metadata_descriptor(0x25, vec![0x1F, 0, 1]);
+    let es_info = metadata_descriptor(0x26, vec![0x0F]);
+    let mut pmt_data = vec![
+        0xE0 | (ID3_PID >> 8) as u8,
+        ID3_PID as u8,
+        0xF0,
+        program_info.len() as u8,
+    ];
+    pmt_data.extend_from_slice(&program_info);
+    pmt_data.extend_from_slice(&[
+        ID3_STREAM_TYPE,
+        0xE0 | (ID3_PID >> 8) as u8,
+        ID3_PID as u8,
+        0xF0,
+        es_info.len() as u8,
+    ]);
+    pmt_data.extend_from_slice(&es_info);
+
+    let mut pmt = vec![0];
+    pmt.append(&mut section(0x02, 1, &pmt_data));
+
+    let pes_length = (tag.len() + 8).min(u16::MAX as usize);
+    let mut pes = vec![
+        0,
+        0,
+        1,
+        PRIVATE_STREAM_1,
+        (pes_length >> 8) as u8,
+        pes_length as u8,
+        0x84,
+        0x80,
+        5,
+        0x21,
+        0,
+        1,
+        0,
+        1,
+    ];
+    pes.extend_from_slice(tag);
+
+    let mut ts = packets(0, &pat);
+    ts.append(&mut packets(PMT_PID, &pmt));
+    ts.append(&mut packets(ID3_PID, &pes));
+
+    ts
+}
+
+/// Write the ID3 input for the clip and return its path.
+pub async fn write_id3(
+    config: &PlayoutConfig,
+    list: &[Media],
+    index: usize,
+) -> Result<PathBuf, std::io::Error> {
+    let path = std::env::temp_dir().join(format!(
+        "ffplayout_{}{ID3_EXTENSION}",
+        config.general.channel_id
+    ));
+    let tag = id3_tag(&id3_frames(list, index));
+
+    tokio::fs::write(&path, id3_ts(&tag)).await?;
+
+    Ok(path)
+}
@@ -1,12 +1,479 @@
-/// Import text/m3u file and create a playlist out of it
-use std::{io::Error, path::Path};
+/// Import text/m3u/csv/xspf/cue file and create a playlist out of it
+use std::{
+    collections::HashMap,
+    io::Error,
+    path::{Path, PathBuf},
+};
 
+use chrono::{DateTime, NaiveDateTime, NaiveTime, Timelike};
 use tokio::{
     fs::{File, create_dir_all},
     io::{AsyncBufReadExt, BufReader},
 };
 
-use crate::player::utils::{Media, json_reader, json_serializer::JsonPlaylist, json_writer};
+use crate::player::utils::{
+    Media, MediaProbe, StartMode, file_extension, json_reader, json_serializer::JsonPlaylist,
+    json_writer, sec_to_time, time_to_sec,
+};
+
+/// Single entry from a imported schedule, before it gets converted to a media clip.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportItem {
+    pub start: Option<f64>,
+    pub source: String,
+    pub title: Option<String>,
+    pub seek: Option<f64>,
+    pub out: Option<f64>,
+    pub duration: Option<f64>,
+    pub category: Option<String>,
+}
+
+impl ImportItem {
+    fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Parse time value, which can be in seconds, or in format hh:mm:ss[.ms].
+///
+/// From date-times, like 2024-01-01T06:00:00+01:00, the local time of day is taken.
+pub fn parse_time(value: &str) -> Option<f64> {
+    let value = value.trim();
+
+    if value.is_empty() {
+        return None;
+    } else if !value.contains(':') {
+        return value.parse().ok().filter(|v: &f64| *v >= 0.0);
+    }
+
+    let time = DateTime::parse_from_rfc3339(value)
+        .map(|d| d.time())
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z").map(|d| d.time()))
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").map(|d| d.time()))
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").map(|d| d.time()))
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S%.f%#z"))
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S%.f"));
+
+    match time {
+        Ok(time) => Some(time.num_seconds_from_midnight() as f64 + time.nanosecond() as f64 / 1e9),
+        // durations can be longer then a day
+        Err(_)
+            if value
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == ':' || c == '.') =>
+        {
+            Some(time_to_sec(value, &None))
+        }
+        Err(_) => None,
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// Split one csv line, quoted fields and escaped quotes ("") are supported.
+fn split_csv_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => {
+                fields.push(field.trim().to_string());
+                field.clear();
+            }
+            c => field.push(c),
+        }
+    }
+
+    fields.push(field.trim().to_string());
+
+    fields
+}
+
+/// Parse csv schedule.
+///
+/// The delimiter (`,`, `;` or tab) is detected from the first line.
+/// When the first line is a header, the columns are mapped by name:
+/// `start`/`begin`/`time`, `source`/`file`/`path`, `title`/`name`,
+/// `in`/`seek`, `out`, `duration`/`length` and `category`.
+/// Without header the order is: `start, source, title, duration`.
+pub fn parse_csv(content: &str) -> Vec<ImportItem> {
+    let mut lines = content
+        .lines()
+        .map(|l| l.trim_start_matches('\u{feff}'))
+        .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        .peekable();
+    let Some(first) = lines.peek() else {
+        return vec![];
+    };
+
+    let delimiter = ['\t', ';', ',']
+        .into_iter()
+        .max_by_key(|d| first.matches(*d).count())
+        .unwrap_or(',');
+
+    let mut columns = ["start", "source", "title", "duration"]
+        .map(String::from)
+        .to_vec();
+    let header = split_csv_line(first, delimiter)
+        .iter()
+        .map(|c| c.to_lowercase())
+        .collect::<Vec<_>>();
+
+    if header
+        .iter()
+        .any(|c| matches!(c.as_str(), "source" | "file" | "path"))
+    {
+        columns = header;
+        lines.next();
+    }
+
+    let mut items = vec![];
+
+    for line in lines {
+        let mut item = ImportItem::default();
+
+        for (column, value) in columns.iter().zip(split_csv_line(line, delimiter)) {
+            match column.as_str() {
+                "start" | "begin" | "time" => item.start = parse_time(&value),
+                "source" | "file" | "path" => item.source = value,
+                "title" | "name" => item.title = non_empty(&value),
+                "in" | "seek" => item.seek = parse_time(&value),
+                "out" => item.out = parse_time(&value),
+                "duration" | "length" => item.duration = parse_time(&value),
+                "category" => item.category = non_empty(&value),
+                _ => (),
+            }
+        }
+
+        if !item.source.is_empty() {
+            items.push(item);
+        }
+    }
+
+    items
+}
+
+/// Parse (extended) m3u playlist.
+///
+/// Supported tags are `#EXTINF:<duration> [attributes],<title>`,
+/// where the attribute `start="hh:mm:ss"` can define the start time,
+/// and `#EXT-X-PROGRAM-DATE-TIME:<date time>` for the following entry.
+/// All other lines with a leading `#` are ignored.
+pub fn parse_m3u(content: &str) -> Vec<ImportItem> {
+    let mut items = vec![];
+    let mut next = ImportItem::default();
+
+    for line in content.lines().map(str::trim) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            let (attributes, title) = info.split_once(',').unwrap_or((info, ""));
+            let mut attributes = attributes.split_whitespace();
+
+            next.duration = attributes.next().and_then(parse_time).filter(|d| *d > 0.0);
+            next.title = non_empty(title);
+
+            for attr in attributes {
+                if let Some(("start", value)) = attr.split_once('=') {
+                    next.start = parse_time(value.trim_matches('"'));
+                }
+            }
+        } else if let Some(time) = line.strip_prefix("#EXT-X-PROGRAM-DATE-TIME:") {
+            next.start = parse_time(time);
+        } else if !line.is_empty() && !line.starts_with('#') {
+            next.source = line.to_string();
+            items.push(next);
+            next = ImportItem::default();
+        }
+    }
+
+    items
+}
+
+/// Decode the predefined xml entities and numeric character references.
+fn xml_unescape(value: &str) -> String {
+    let mut result = String::new();
+    let mut rest = value.trim();
+
+    while let Some(pos) = rest.find('&') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+
+        let decoded = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            e if e.starts_with("#x") => u32::from_str_radix(&e[2..], 16)
+                .ok()
+                .and_then(char::from_u32),
+            e if e.starts_with('#') => e[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+
+        if let Some(c) = decoded {
+            result.push(c);
+        } else {
+            result.push_str(&rest[..=end]);
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    result.push_str(rest);
+
+    result
+}
+
+/// Decode percent encoded characters from a URI.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = value.get(i + 1..i + 3)
+            && let Ok(b) = u8::from_str_radix(hex, 16)
+        {
+            decoded.push(b);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Get the content from the first matching xml element.
+fn xml_element<'a>(content: &'a str, name: &str) -> Option<&'a str> {
+    let start = content.find(&format!("<{name}>"))? + name.len() + 2;
+    let end = content[start..].find(&format!("</{name}>"))? + start;
+
+    Some(&content[start..end])
+}
+
+/// Parse XSPF playlist.
+///
+/// From every `<track>` the `location`, `title`, `creator` and `duration` (in milliseconds) are taken,
+/// local `file://` locations are converted to paths.
+pub fn parse_xspf(content: &str) -> Vec<ImportItem> {
+    let mut items = vec![];
+
+    for track in content.split("<track>").skip(1) {
+        let track = track.split("</track>").next().unwrap_or(track);
+        let Some(location) = xml_element(track, "location").map(xml_unescape) else {
+            continue;
+        };
+
+        let source = match location.strip_prefix("file://") {
+            Some(path) => percent_decode(path),
+            None => location,
+        };
+
+        let title = xml_element(track, "title").map(xml_unescape);
+        let creator = xml_element(track, "creator").map(xml_unescape);
+
+        let mut item = ImportItem::new(&source);
+        item.title = match (creator, title) {
+            (Some(c), Some(t)) => non_empty(&format!("{c} - {t}")),
+            (c, t) => t.or(c).and_then(|v| non_empty(&v)),
+        };
+        item.duration = xml_element(track, "duration")
+            .and_then(|d| d.trim().parse::<f64>().ok())
+            .map(|d| d / 1000.0)
+            .filter(|d| *d > 0.0);
+
+        items.push(item);
+    }
+
+    items
+}
+
+/// Split cue sheet line into command and arguments, quoted arguments are supported.
+fn cue_line(line: &str) -> (String, Vec<String>) {
+    let mut args = split_csv_line(line.trim(), ' ');
+    args.retain(|a| !a.is_empty());
+
+    if args.is_empty() {
+        return (String::new(), args);
+    }
+
+    (args.remove(0).to_uppercase(), args)
+}
+
+/// Convert cue sheet time (mm:ss:ff, 75 frames per second) to seconds.
+fn cue_time(value: &str) -> Option<f64> {
+    let mut t = value.split(':').map(|v| v.parse::<f64>().ok());
+
+    Some(t.next()?? * 60.0 + t.next()?? + t.next()?? / 75.0)
+}
+
+/// Parse cue sheet.
+///
+/// Every track becomes a clip, with `INDEX 01` as in point and the start of the next track
+/// in the same file as out point. The out point from the last track is the end of the file.
+/// Relative file paths get resolved against `base`, when the file exists there.
+pub fn parse_cue(content: &str, base: Option<&Path>) -> Vec<ImportItem> {
+    let mut items: Vec<ImportItem> = vec![];
+    let mut album_performer = None;
+    let mut performer = None;
+    let mut file = String::new();
+    let mut track: Option<ImportItem> = None;
+
+    let push_track =
+        |items: &mut Vec<ImportItem>, track: Option<ImportItem>, performer: &Option<String>| {
+            if let Some(mut t) = track.filter(|t| t.seek.is_some()) {
+                if let Some(p) = performer {
+                    t.title = Some(match t.title {
+                        Some(title) => format!("{p} - {title}"),
+                        None => p.clone(),
+                    });
+                }
+
+                if let Some(last) = items.last_mut()
+                    && last.source == t.source
+                {
+                    last.out = t.seek;
+                }
+
+                items.push(t);
+            }
+        };
+
+    for line in content.lines() {
+        let (command, args) = cue_line(line.trim_start_matches('\u{feff}'));
+
+        match (command.as_str(), args.first()) {
+            ("FILE", Some(f)) => {
+                push_track(&mut items, track.take(), &performer);
+
+                let path = PathBuf::from(f);
+                file = match base.map(|b| b.join(&path)) {
+                    Some(p) if path.is_relative() && p.is_file() => p.to_string_lossy().to_string(),
+                    _ => f.clone(),
+                };
+            }
+            ("TRACK", _) => {
+                push_track(&mut items, track.take(), &performer);
+
+                performer = album_performer.clone();
+                track = Some(ImportItem::new(&file));
+            }
+            ("TITLE", Some(title)) => {
+                if let Some(t) = track.as_mut() {
+                    t.title = non_empty(title);
+                }
+            }
+            ("PERFORMER", Some(p)) => {
+                if track.is_some() {
+                    performer = non_empty(p);
+                } else {
+                    album_performer = non_empty(p);
+                }
+            }
+            ("INDEX", Some(num)) if num.parse::<u32>() == Ok(1) => {
+                if let Some(t) = track.as_mut() {
+                    t.seek = args.get(1).and_then(|v| cue_time(v));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    push_track(&mut items, track.take(), &performer);
+
+    items
+}
+
+/// Convert the imported items to media clips.
+///
+/// Items are probed, when the probe fails the duration from the schedule is taken.
+/// Items with a start time become hard start items: a clip gets cut at the start of the
+/// next clip, and a gap before the next clip is filled when the playlist is loaded.
+pub async fn import_to_media(items: Vec<ImportItem>, do_probe: bool) -> Vec<Media> {
+    let mut program: Vec<Media> = vec![];
+    let mut probes: HashMap<String, Option<MediaProbe>> = HashMap::new();
+
+    for (i, item) in items.iter().enumerate() {
+        // cue sheets and xspf lists can point multiple times to the same file, so probe it only once
+        let mut media = match probes.get(&item.source) {
+            Some(probe) => {
+                let mut media = Media::new(0, &item.source, false).await;
+                media.probe.clone_from(probe);
+                media.duration = probe
+                    .as_ref()
+                    .and_then(|p| p.format.duration)
+                    .unwrap_or_default();
+                media
+            }
+            None => {
+                let media = Media::new(0, &item.source, do_probe).await;
+                probes.insert(item.source.clone(), media.probe.clone());
+                media
+            }
+        };
+
+        if media.duration == 0.0 {
+            media.duration = item.duration.unwrap_or_default();
+        }
+
+        media.title = item.title.clone();
+        media.seek = item.seek.unwrap_or_default().min(media.duration);
+        media.out = item.out.unwrap_or(media.duration).min(media.duration);
+        media.category = item.category.clone().unwrap_or_default();
+
+        if let Some(start) = item.start {
+            media.start = StartMode::Hard;
+            media.time = Some(sec_to_time(start));
+        }
+
+        if let Some(start) = item.start
+            && let Some(next_start) = items.get(i + 1).and_then(|n| n.start)
+        {
+            let mut length = next_start - start;
+
+            if length < 0.0 {
+                // next item starts after midnight
+                length += 86400.0;
+            }
+
+            if length > 0.0 && media.out - media.seek > length {
+                media.out = media.seek + length;
+            }
+        }
+
+        if media.duration > 0.0 && media.out > media.seek {
+            program.push(media);
+        }
+    }
+
+    program
+}
 
 pub async fn import_file(
     playlist_root: &Path,
@@ -41,16 +508,29 @@ pub async fn import_file(
     create_dir_all(playlist_path).await?;
 
     let mut lines = reader.lines();
-    while let Some(line) = lines.next_line().await? {
-        if !line.starts_with('#') {
-            let item = Media::new(0, &line, true).await;
+    let mut content = vec![];
 
-            if item.duration > 0.0 {
-                playlist.program.push(item);
-            }
-        }
+    while let Some(line) = lines.next_line().await? {
+        content.push(line);
     }
 
+    let content = content.join("\n");
+    let extension = file_extension(path).map(str::to_lowercase);
+
+    let items = match extension.as_deref() {
+        Some("csv" | "tsv") => parse_csv(&content),
+        Some("xspf") => parse_xspf(&content),
+        Some("cue") => parse_cue(&content, path.parent()),
+        _ if content.trim_start().starts_with("#EXTM3U") => parse_m3u(&content),
+        _ => content
+            .lines()
+            .filter(|l| !l.starts_with('#') && !l.trim().is_empty())
+            .map(ImportItem::new)
+            .collect(),
+    };
+
+    playlist.program = import_to_media(items, true).await;
+
     let mut file_exists = false;
 
     if playlist_file.is_file() {
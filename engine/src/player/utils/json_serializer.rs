@@ -1,5 +1,5 @@
 use std::{
-    path::Path,
+    path::{Path, PathBuf},
     sync::{Arc, atomic::AtomicBool},
 };
 
@@ -7,11 +7,15 @@ use log::*;
 use serde::{Deserialize, Serialize};
 use tokio::{fs::File, io::AsyncReadExt, sync::Mutex};
 
+use crate::file::backend::{Backend, StorageBackend, is_storage_url};
 use crate::player::utils::{
-    Media, PlayoutConfig, get_date, is_remote, json_validate::validate_playlist, modified_time,
-    time_from_header,
+    ItemType, Media, Origin, PlayoutConfig, StartMode, gen_dummy, get_date, include_file_extension,
+    is_close, is_remote,
+    json_validate::{probe_items, validate_playlist},
+    modified_time, time_to_sec,
+    warnings::PlaylistWarnings,
 };
-use crate::utils::{config::DUMMY_LEN, logging::Target};
+use crate::utils::{config::DUMMY_LEN, logging::Target, telemetry};
 
 /// This is our main playlist object, it holds all necessary information for the current day.
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -68,6 +72,36 @@ fn default_channel() -> String {
     "Channel 1".to_string()
 }
 
+/// Speed factor to play `length` seconds in `slot` seconds, when the change is at most
+/// `max_percent` of the slot.
+pub fn slot_speed(length: f64, slot: f64, max_percent: f64) -> Option<f64> {
+    if length <= 0.0 || slot <= 0.0 {
+        return None;
+    }
+
+    let change = (length - slot).abs() / slot * 100.0;
+
+    (change <= max_percent).then_some(length / slot)
+}
+
+/// Set the speed of an item with slot, so that it plays exactly the slot time.
+///
+/// Returns `false`, when the clip doesn't fit into the slot within `playlist.slot_fit`,
+/// or the speed can't change because of stream copy; then it plays with its own length.
+pub fn fit_slot(config: &PlayoutConfig, item: &mut Media) -> bool {
+    let Some(slot) = item.slot else {
+        return true;
+    };
+
+    let copy = config.processing.copy_audio || config.processing.copy_video;
+
+    item.speed = slot_speed(item.out - item.seek, slot, config.playlist.slot_fit)
+        .filter(|_| !copy)
+        .filter(|factor| *factor != 1.0);
+
+    item.speed.is_some() || is_close(item.out - item.seek, slot, 0.001)
+}
+
 pub fn set_defaults(config: &PlayoutConfig, playlist: &mut JsonPlaylist) {
     let mut start_sec = playlist.start_sec.unwrap();
     let mut length = 0.0;
@@ -82,19 +116,328 @@ pub fn set_defaults(config: &PlayoutConfig, playlist: &mut JsonPlaylist) {
         item.filter = None;
 
         let source_path = Path::new(&item.source);
-        if source_path.is_relative() {
+        if source_path.is_relative() && !is_remote(&item.source) && !is_storage_url(&item.source) {
             let new_path = config.storage.path.join(source_path);
             item.source = new_path.to_string_lossy().to_string();
         }
 
-        let dur = item.out - item.seek;
+        fit_slot(config, item);
+
+        let dur = item.length();
         start_sec += dur;
         length += dur;
     }
 
+    if !config.playlist.infinit && playlist.program.iter().any(|m| m.start == StartMode::Hard) {
+        fit_hard_starts(
+            config,
+            &mut playlist.program,
+            0,
+            playlist.start_sec.unwrap(),
+        );
+        length = playlist.program.iter().map(Media::length).sum();
+    }
+
     playlist.length = Some(length);
 }
 
+/// Clock time relative to the playlist start, times before the start are on the next day.
+fn clock_target(time: &str, start_sec: f64) -> f64 {
+    let mut target = time_to_sec(time, &None);
+
+    if target < start_sec {
+        target += 86400.0;
+    }
+
+    target
+}
+
+/// Clock time from a hard item, relative to the playlist start.
+fn hard_target(item: &Media, start_sec: f64) -> Option<f64> {
+    let time = item.time.as_ref().filter(|t| t.contains(':'))?;
+
+    Some(clock_target(time, start_sec))
+}
+
+/// Clips of a loop item: the item itself, or the media files of its folder.
+async fn loop_clips(config: &PlayoutConfig, item: &Media) -> Vec<Media> {
+    let mut path = PathBuf::from(&item.source);
+
+    if path.is_relative() && !is_remote(&item.source) && !is_storage_url(&item.source) {
+        path = config.storage.path.join(path);
+    }
+
+    let mut clips = vec![];
+
+    if path.is_dir() {
+        let mut files = vec![];
+
+        if let Ok(mut entries) = tokio::fs::read_dir(&path).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let file = entry.path();
+
+                if file.is_file() && include_file_extension(config, &file) {
+                    files.push(file);
+                }
+            }
+        }
+
+        files.sort();
+
+        for (i, file) in files.iter().enumerate() {
+            clips.push(Media::new(i, &file.to_string_lossy(), false).await);
+        }
+    } else {
+        let mut clip = item.clone();
+        clip.source = path.to_string_lossy().to_string();
+
+        // without out point the whole file is looped
+        if clip.out <= clip.seek {
+            clip.out = clip.duration;
+        }
+
+        if clip.out > clip.seek {
+            return vec![clip];
+        }
+
+        clips.push(clip);
+    }
+
+    probe_items(config, clips, Arc::new(AtomicBool::new(true)))
+        .await
+        .into_iter()
+        .filter_map(|(mut clip, result)| {
+            if clip.out <= clip.seek {
+                clip.out = clip.duration;
+            }
+
+            result.is_ok().then_some(clip)
+        })
+        .collect()
+}
+
+/// Repetitions of the clips for `length` seconds, the last one gets cut.
+///
+/// The player skips clips under one second, so a shorter rest is taken from the
+/// repetition before.
+pub fn loop_pieces(item: &Media, clips: &[Media], length: f64) -> Vec<Media> {
+    let mut pieces: Vec<Media> = vec![];
+    let mut remaining = length;
+    let mut last_length = 0.0;
+
+    if clips.iter().all(|c| c.out <= c.seek) {
+        return pieces;
+    }
+
+    for clip in clips.iter().filter(|c| c.out > c.seek).cycle() {
+        if remaining <= 0.0 {
+            break;
+        }
+
+        last_length = clip.out - clip.seek;
+
+        let mut piece = item.clone();
+        piece.source.clone_from(&clip.source);
+        piece.seek = clip.seek;
+        piece.out = clip.seek + last_length.min(remaining);
+        piece.duration = clip.duration;
+        piece.probe.clone_from(&clip.probe);
+        piece.item_type = ItemType::Clip;
+        piece.until = None;
+
+        if !pieces.is_empty() {
+            // only the first repetition keeps a hard start
+            piece.start = StartMode::Soft;
+            piece.time = None;
+        }
+
+        remaining -= piece.out - piece.seek;
+        pieces.push(piece);
+    }
+
+    if let [.., prev, last] = pieces.as_mut_slice() {
+        let rest = last.out - last.seek;
+
+        if rest < 1.0 && last_length >= 1.0 && prev.out - prev.seek > 2.0 {
+            prev.out -= 1.0 - rest;
+            last.out += 1.0 - rest;
+        }
+    }
+
+    pieces
+}
+
+/// Repeat the clips of the loop items until their clock time in `until`, so that the
+/// following item starts on time.
+///
+/// A loop plays its file (between in and out point) or the media files of its folder.
+/// The first repetition keeps the start mode of the item, so a loop can also start hard.
+pub async fn expand_loops(config: &PlayoutConfig, playlist: &mut JsonPlaylist) {
+    if !playlist
+        .program
+        .iter()
+        .any(|m| m.item_type == ItemType::Loop)
+    {
+        return;
+    }
+
+    let id = config.general.channel_id;
+    let start_sec = playlist
+        .start_sec
+        .or(config.playlist.start_sec)
+        .unwrap_or_default();
+    let mut begin = start_sec;
+    let mut program = vec![];
+
+    for mut item in std::mem::take(&mut playlist.program) {
+        if item.start == StartMode::Hard
+            && let Some(target) = hard_target(&item, start_sec)
+        {
+            begin = target;
+        }
+
+        if item.item_type != ItemType::Loop {
+            fit_slot(config, &mut item);
+
+            begin += item.length();
+            program.push(item);
+            continue;
+        }
+
+        let Some(until) = item.until.as_deref().filter(|t| t.contains(':')) else {
+            error!(target: Target::file_mail(), channel = id; "Loop <span class=\"log-addr\">{}</span> has no until time, it is skipped!", item.source);
+            continue;
+        };
+
+        let length = clock_target(until, start_sec) - begin;
+
+        if length < 1.0 {
+            warn!(target: Target::file_mail(), channel = id; "Loop <span class=\"log-addr\">{}</span> starts after <span class=\"log-number\">{until}</span>, it is skipped", item.source);
+            continue;
+        }
+
+        let clips = loop_clips(config, &item).await;
+        let mut pieces = loop_pieces(&item, &clips, length);
+
+        if pieces.is_empty() {
+            error!(target: Target::file_mail(), channel = id; "Loop <span class=\"log-addr\">{}</span> has no playable clip, filler runs until <span class=\"log-number\">{until}</span>!", item.source);
+
+            pieces.push(Media {
+                category: "filler".to_string(),
+                duration: length,
+                out: length,
+                start: item.start,
+                time: item.time.clone(),
+                ..Media::default()
+            });
+        }
+
+        begin += length;
+        program.append(&mut pieces);
+    }
+
+    playlist.program = program;
+}
+
+/// Index and clock time of the next hard start item, beginning from index `from`.
+pub fn next_hard_start(program: &[Media], from: usize, start_sec: f64) -> Option<(usize, f64)> {
+    program
+        .iter()
+        .enumerate()
+        .skip(from)
+        .filter(|(_, item)| item.start == StartMode::Hard)
+        .find_map(|(i, item)| hard_target(item, start_sec).map(|t| (i, t)))
+}
+
+/// Fit the program to the hard start items, beginning from index `from`.
+///
+/// When the content before a hard item is too long, the preceding soft items get trimmed
+/// (clips which ends up shorter then one second are skipped from the player).
+/// When it is too short, a filler item is inserted to close the gap, see [`gap`].
+/// Drift at runtime is corrected by the player, see `playlist::hard_overrun`.
+pub fn fit_hard_starts(
+    config: &PlayoutConfig,
+    program: &mut Vec<Media>,
+    from: usize,
+    start_sec: f64,
+) {
+    let mut begin = program.get(from).and_then(|m| m.begin).unwrap_or(start_sec);
+    let mut i = from;
+
+    while i < program.len() {
+        if program[i].start == StartMode::Hard
+            && let Some(target) = hard_target(&program[i], start_sec)
+        {
+            let diff = target - begin;
+
+            if diff >= 1.0 {
+                program.insert(i, gap(config, begin, i, diff));
+                begin = target;
+                i += 1;
+            } else if diff <= -1.0 {
+                let mut overrun = -diff;
+                let mut j = i;
+
+                while j > from && overrun > 0.0 && program[j - 1].start == StartMode::Soft {
+                    j -= 1;
+                    let cut = overrun.min(program[j].length());
+                    program[j].out -= cut * program[j].speed.unwrap_or(1.0);
+                    overrun -= cut;
+                }
+
+                begin = program[j].begin.unwrap_or(begin);
+
+                for item in &mut program[j..i] {
+                    item.begin = Some(begin);
+                    begin += item.length();
+                }
+            }
+        }
+
+        program[i].begin = Some(begin);
+        program[i].index = Some(i);
+        begin += program[i].length();
+        i += 1;
+    }
+}
+
+/// Filler item for a gap before a hard start item. It has the dummy as source, the player
+/// takes the configured filler instead, when there is one.
+pub fn gap(config: &PlayoutConfig, begin: f64, index: usize, length: f64) -> Media {
+    let (source, cmd) = gen_dummy(config, length);
+
+    Media {
+        begin: Some(begin),
+        index: Some(index),
+        category: "filler".to_string(),
+        source,
+        cmd: Some(cmd),
+        duration: length,
+        out: length,
+        origin: Origin::Inserted,
+        ..Media::default()
+    }
+}
+
+/// Path of the playlist from `date` in the playlist folder, or the playlist file itself.
+pub fn playlist_path(config: &PlayoutConfig, date: &str) -> PathBuf {
+    let playlists = config.channel.playlists.to_string_lossy();
+
+    if config.channel.playlists.is_dir() || is_remote(&playlists) || is_storage_url(&playlists) {
+        let d: Vec<&str> = date.split('-').collect();
+
+        return config
+            .channel
+            .playlists
+            .join(d[0])
+            .join(d[1])
+            .join(date)
+            .with_extension("json");
+    }
+
+    config.channel.playlists.clone()
+}
+
 /// Read json playlist file, fills JsonPlaylist struct and set some extra values,
 /// which we need to process.
 pub async fn read_json(
@@ -102,23 +445,15 @@ pub async fn read_json(
     current_list: Arc<Mutex<Vec<Media>>>,
     path: Option<String>,
     is_alive: Arc<AtomicBool>,
+    warnings: Arc<Mutex<PlaylistWarnings>>,
     seek: bool,
     get_next: bool,
 ) -> JsonPlaylist {
     let id = config.general.channel_id;
     let config_clone = config.clone();
-    let mut playlist_path = config.channel.playlists.clone();
     let start_sec = config.playlist.start_sec.unwrap();
     let date = get_date(seek, start_sec, get_next, &config.channel.timezone);
-
-    if playlist_path.is_dir() || is_remote(&config.channel.playlists.to_string_lossy()) {
-        let d: Vec<&str> = date.split('-').collect();
-        playlist_path = playlist_path
-            .join(d[0])
-            .join(d[1])
-            .join(date.clone())
-            .with_extension("json");
-    }
+    let mut playlist_path = playlist_path(config, &date);
 
     let mut current_file = playlist_path.as_path().display().to_string();
 
@@ -127,43 +462,41 @@ pub async fn read_json(
         current_file = p;
     }
 
-    if is_remote(&current_file) {
-        if let Ok(resp) = reqwest::Client::new().get(&current_file).send().await
-            && resp.status().is_success()
-        {
-            let headers = resp.headers().clone();
-
-            if let Ok(body) = resp.text().await {
-                let mut playlist: JsonPlaylist = match serde_json::from_str(&body) {
-                    Ok(p) => p,
-                    Err(e) => {
-                        error!(target: Target::file_mail(), channel = id; "Could't read remote json playlist. {e:?}");
-                        JsonPlaylist::new(date, start_sec)
-                    }
-                };
-
-                playlist.path = Some(current_file);
-                playlist.start_sec = Some(start_sec);
-
-                if let Some(time) = time_from_header(&headers) {
-                    playlist.modified = Some(time.to_string());
+    let mut span = telemetry::span("playlist.read", id);
+    telemetry::attribute(&mut span, "playlist.path", current_file.clone());
+
+    if is_remote(&current_file) || is_storage_url(&current_file) {
+        if let Ok(object) = Backend::new(&current_file).read(&current_file).await {
+            let mut playlist: JsonPlaylist = match serde_json::from_slice(&object.data) {
+                Ok(p) => p,
+                Err(e) => {
+                    error!(target: Target::file_mail(), channel = id; "Could't read remote json playlist. {e:?}");
+                    JsonPlaylist::new(date, start_sec)
                 }
+            };
 
-                let list_clone = playlist.clone();
+            playlist.path = Some(current_file);
+            playlist.start_sec = Some(start_sec);
+            playlist.modified = object.modified;
 
-                if !config.general.skip_validation {
-                    tokio::spawn(validate_playlist(
-                        config_clone,
-                        current_list,
-                        list_clone,
-                        is_alive,
-                    ));
-                }
+            expand_loops(config, &mut playlist).await;
 
-                set_defaults(config, &mut playlist);
+            let list_clone = playlist.clone();
 
-                return playlist;
+            if !config.general.skip_validation {
+                tokio::spawn(validate_playlist(
+                    config_clone,
+                    current_list,
+                    list_clone,
+                    is_alive,
+                    warnings,
+                ));
             }
+
+            set_defaults(config, &mut playlist);
+            telemetry::attribute(&mut span, "playlist.items", playlist.program.len() as i64);
+
+            return playlist;
         }
     } else if playlist_path.is_file() {
         let modified = modified_time(&current_file).await;
@@ -195,6 +528,8 @@ pub async fn read_json(
         playlist.start_sec = Some(start_sec);
         playlist.modified = modified;
 
+        expand_loops(config, &mut playlist).await;
+
         let list_clone = playlist.clone();
 
         if !config.general.skip_validation {
@@ -203,15 +538,18 @@ pub async fn read_json(
                 current_list,
                 list_clone,
                 is_alive,
+                warnings,
             ));
         }
 
         set_defaults(config, &mut playlist);
+        telemetry::attribute(&mut span, "playlist.items", playlist.program.len() as i64);
 
         return playlist;
     }
 
-    error!(target: Target::file_mail(), channel = id; "Playlist <span class=\"log-addr\">{current_file}</span> not exist!");
+    // the playlist iterator alerts once per day
+    debug!(target: Target::file(), channel = id; "Playlist <span class=\"log-addr\">{current_file}</span> not exist!");
 
     JsonPlaylist::new(date, start_sec)
 }
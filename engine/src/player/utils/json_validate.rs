@@ -5,9 +5,10 @@ use std::{
         Arc,
         atomic::{AtomicBool, Ordering},
     },
-    time::Instant,
+    time::{Duration, Instant},
 };
 
+use futures_util::{StreamExt, stream};
 use log::*;
 use regex::Regex;
 use tokio::{
@@ -15,12 +16,17 @@ use tokio::{
     io::{AsyncBufReadExt, BufReader},
     process::Command,
     sync::Mutex,
+    time::timeout,
 };
 
 use crate::player::filter::FilterType::Audio;
+use crate::player::process::ResourceLimits;
 use crate::player::utils::{
-    JsonPlaylist, Media, is_close, is_remote, loop_image, sec_to_time, seek_and_length,
-    time_in_seconds, time_to_sec,
+    JsonPlaylist, Media, add_thread_limit, ffmpeg_bin, is_close, is_remote,
+    json_serializer::fit_slot,
+    live_input, loop_image, playlist_date, playlist_seconds, sec_to_time, seek_and_length,
+    target_length, time_to_sec,
+    warnings::{PlaylistWarnings, WarningKind, short_item, stream_mismatch},
 };
 use crate::utils::{
     config::{FFMPEG_IGNORE_ERRORS, IMAGE_FORMAT, OutputMode::Null, PlayoutConfig},
@@ -40,6 +46,7 @@ async fn check_media(
     mut node: Media,
     pos: usize,
     begin: f64,
+    warnings: &mut PlaylistWarnings,
 ) -> Result<(), ProcessError> {
     let id = config.general.channel_id;
     let mut dec_cmd = vec_strings!["-hide_banner", "-nostats", "-v", "level+info"];
@@ -96,11 +103,14 @@ async fn check_media(
     dec_cmd.append(&mut filter.map());
     dec_cmd.append(&mut vec_strings!["-t", process_length, "-f", "null", "-"]);
 
-    let mut enc_proc = Command::new("ffmpeg")
-        .args(dec_cmd)
-        .kill_on_drop(true)
-        .stderr(Stdio::piped())
-        .spawn()?;
+    add_thread_limit(&config, &mut dec_cmd);
+
+    let mut enc_proc = ResourceLimits::new(&config).spawn(
+        Command::new(ffmpeg_bin())
+            .args(dec_cmd)
+            .kill_on_drop(true)
+            .stderr(Stdio::piped()),
+    )?;
 
     let enc_err = BufReader::new(enc_proc.stderr.take().unwrap());
     let mut lines = enc_err.lines();
@@ -132,12 +142,23 @@ async fn check_media(
         }
     }
 
+    for e in &error_list {
+        warnings.push(Some(pos), begin, &node.source, WarningKind::Decode, e);
+    }
+
     if silence_end - silence_start > process_length {
+        warnings.push(
+            Some(pos),
+            begin,
+            &node.source,
+            WarningKind::Silent,
+            "Audio is totally silent!",
+        );
         error_list.push("Audio is totally silent!".to_string());
     }
 
     if !error_list.is_empty() {
-        error!(target: Target::file_mail(), channel = id;
+        error!(target: Target::file(), channel = id;
             "<span class=\"log-gray\">[Validator]</span> ffmpeg error on position <span class=\"log-number\">{pos}</span> - {}: <span class=\"log-addr\">{}</span>: {}",
             sec_to_time(begin),
             node.source,
@@ -145,8 +166,6 @@ async fn check_media(
         );
     }
 
-    error_list.clear();
-
     if let Err(e) = enc_proc.wait().await {
         error!(target: Target::file_mail(), channel = id; "Validation process: {e:?}");
     }
@@ -157,7 +176,14 @@ async fn check_media(
 /// Validate Webvtt.
 ///
 /// - Check if duration matches with video duration
-async fn check_vtt(source: &str, duration: f64, channel_id: i32) -> Result<(), ProcessError> {
+async fn check_vtt(
+    source: &str,
+    duration: f64,
+    channel_id: i32,
+    pos: usize,
+    begin: f64,
+    warnings: &mut PlaylistWarnings,
+) -> Result<(), ProcessError> {
     let vtt_path = Path::new(source).with_extension("vtt");
 
     if vtt_path.is_file() {
@@ -183,7 +209,17 @@ async fn check_vtt(source: &str, duration: f64, channel_id: i32) -> Result<(), P
             let last_sec = time_to_sec(&timestamp, &None);
 
             if last_sec > duration {
-                error!(target: Target::file_mail(), channel = channel_id;
+                warnings.push(
+                    Some(pos),
+                    begin,
+                    source,
+                    WarningKind::Subtitle,
+                    format!(
+                        "WebVTT is longer, {timestamp} versus {} video duration",
+                        sec_to_time(duration)
+                    ),
+                );
+                error!(target: Target::file(), channel = channel_id;
                     "<span class=\"log-gray\">[Validation]</span> Webvtt <span class=\"log-addr\">{vtt_path:?}</span> is longer, <span class=\"log-number\">{timestamp}</span> versus <span class=\"log-number\">{}</span> video duration.",
                     sec_to_time(duration)
                 );
@@ -194,39 +230,128 @@ async fn check_vtt(source: &str, duration: f64, channel_id: i32) -> Result<(), P
     Ok(())
 }
 
+/// Probe the items concurrently, with at most `general.probe_workers` at once.
+///
+/// A probe, which takes longer than `general.probe_timeout`, fails. The probed items
+/// are returned in their order, with the result of the probe.
+pub async fn probe_items(
+    config: &PlayoutConfig,
+    items: Vec<Media>,
+    is_alive: Arc<AtomicBool>,
+) -> Vec<(Media, Result<(), String>)> {
+    let workers = config.general.probe_workers.max(1) as usize;
+    let probe_timeout = config.general.probe_timeout;
+
+    stream::iter(items)
+        .map(move |mut item| {
+            let is_alive = is_alive.clone();
+
+            async move {
+                if !is_alive.load(Ordering::SeqCst) {
+                    return (item, Ok(()));
+                }
+
+                let check_audio = !item.audio.is_empty();
+                let result = if probe_timeout > 0.0 {
+                    timeout(
+                        Duration::from_secs_f64(probe_timeout),
+                        item.add_probe(check_audio),
+                    )
+                    .await
+                    .unwrap_or_else(|_| Err(format!("Probe timed out after {probe_timeout}s")))
+                } else {
+                    item.add_probe(check_audio).await
+                };
+
+                (item, result)
+            }
+        })
+        .buffered(workers)
+        .collect()
+        .await
+}
+
 /// Validate a given playlist, to check if:
 ///
 /// - the source files are existing
 /// - file can be read by ffprobe and metadata exists
 /// - total playtime fits target length from config
 ///
+/// The problems are collected in `warnings` and sent as one alert at the end.
+///
 /// This function we run in a thread, to don't block the main function.
 pub async fn validate_playlist(
     mut config: PlayoutConfig,
     current_list: Arc<Mutex<Vec<Media>>>,
     mut playlist: JsonPlaylist,
     is_alive: Arc<AtomicBool>,
+    warnings: Arc<Mutex<PlaylistWarnings>>,
 ) {
     let id = config.general.channel_id;
     let date = playlist.date;
+    let mut found = PlaylistWarnings::new(&date);
+    let mut reference = None;
 
     if config.text.add_text && !config.text.text_from_filename {
         // Turn of drawtext filter with zmq, because its port is needed by the decoder instance.
         config.text.add_text = false;
     }
 
-    let mut length = config.playlist.length_sec.unwrap();
+    // The same for the live control sockets.
+    config.processing.live_control = false;
+
     let mut begin = config.playlist.start_sec.unwrap();
-    let mut time_sec = time_in_seconds(&config.channel.timezone);
+    let target = target_length(&config, &date);
+    let length = target + begin;
+    // Clips are only passed in the playlist of the current day.
+    let time_sec = if date == playlist_date(&config.channel.timezone, begin) {
+        playlist_seconds(&config.channel.timezone, begin)
+    } else {
+        begin
+    };
 
-    length += begin;
+    debug!(target: Target::file_mail(), channel = id; "Validate playlist from: <span class=\"log-number\">{date}</span>");
+    let timer = Instant::now();
+
+    // Probe all local sources, which are not passed, at once, to not wait for each file.
+    let mut start = begin;
+    let mut local = vec![];
+
+    for item in &mut playlist.program {
+        fit_slot(&config, item);
 
-    if time_sec < config.playlist.start_sec.unwrap_or_default() {
-        time_sec += 86400.0;
+        let passed = start < time_sec;
+        start += item.length();
+
+        if passed || is_remote(&item.source) {
+            local.push(false);
+            continue;
+        }
+
+        let source_path = Path::new(&item.source);
+
+        if source_path.is_relative() {
+            item.source = config
+                .storage
+                .path
+                .join(source_path)
+                .to_string_lossy()
+                .to_string();
+        }
+
+        local.push(true);
     }
 
-    debug!(target: Target::file_mail(), channel = id; "Validate playlist from: <span class=\"log-number\">{date}</span>");
-    let timer = Instant::now();
+    let items = playlist
+        .program
+        .iter()
+        .zip(&local)
+        .filter(|(_, local)| **local)
+        .map(|(item, _)| item.clone())
+        .collect();
+    let mut probes = probe_items(&config, items, is_alive.clone())
+        .await
+        .into_iter();
 
     for (index, item) in playlist.program.iter_mut().enumerate() {
         if !is_alive.load(Ordering::SeqCst) {
@@ -237,34 +362,68 @@ pub async fn validate_playlist(
 
         if begin < time_sec {
             // Do not validate clips that are being passed.
-            begin += item.out - item.seek;
+            begin += item.length();
             continue;
         }
 
-        if !is_remote(&item.source) {
-            let source_path = Path::new(&item.source);
-            if source_path.is_relative() {
-                let new_path = config.storage.path.join(source_path);
-                item.source = new_path.to_string_lossy().to_string();
-            }
+        if let Err(e) = live_input::validate(
+            &item.source,
+            &item.live.clone().unwrap_or_default(),
+            &config.general,
+        ) {
+            found.push(Some(pos), begin, &item.source, WarningKind::Live, &e);
+            error!(target: Target::file(), channel = id;
+                "<span class=\"log-gray\">[Validation]</span> Error on position <span class=\"log-number\">{pos:0>3}</span> - <span class=\"log-number\">{}</span>: {e}",
+                sec_to_time(begin)
+            );
+        }
 
-            if item.audio.is_empty() {
-                if let Err(e) = item.add_probe(false).await {
-                    error!(target: Target::file_mail(), channel = id;
-                        "<span class=\"log-gray\">[Validation]</span> Error on position <span class=\"log-number\">{pos:0>3}</span> - <span class=\"log-number\">{}</span>: {e}",
-                        sec_to_time(begin)
-                    );
-                }
-            } else if let Err(e) = item.add_probe(true).await {
-                error!(target: Target::file_mail(), channel = id;
+        if local[index]
+            && let Some((probed, result)) = probes.next()
+        {
+            *item = probed;
+
+            if let Err(e) = result {
+                let kind = if Path::new(&item.source).is_file() {
+                    WarningKind::Unreadable
+                } else {
+                    WarningKind::Missing
+                };
+
+                found.push(Some(pos), begin, &item.source, kind, &e);
+                error!(target: Target::file(), channel = id;
                     "<span class=\"log-gray\">[Validation]</span> Error on position <span class=\"log-number\">{pos:0>3}</span> - <span class=\"log-number\">{}</span>: {e}",
                     sec_to_time(begin)
                 );
             }
         }
 
-        if item.probe.is_some() {
-            match check_media(&config, item.clone(), pos, begin).await {
+        if !fit_slot(&config, item)
+            && let Some(slot) = item.slot
+        {
+            found.push(
+                Some(pos),
+                begin,
+                &item.source,
+                WarningKind::Slot,
+                format!(
+                    "Clip with {} doesn't fit into slot of {}",
+                    sec_to_time(item.out - item.seek),
+                    sec_to_time(slot)
+                ),
+            );
+        }
+
+        if let Some(probe) = &item.probe {
+            if let Some(message) = short_item(item.out, probe) {
+                found.push(Some(pos), begin, &item.source, WarningKind::Short, message);
+            }
+
+            if let Some(message) = stream_mismatch(&config, probe, &mut reference) {
+                found.push(Some(pos), begin, &item.source, WarningKind::Codec, message);
+            }
+
+            match check_media(&config, item.clone(), pos, begin, &mut found).await {
                 Err(e) => {
                     error!(target: Target::file_mail(), channel = id; "{e}");
                 }
@@ -308,22 +467,60 @@ pub async fn validate_playlist(
             }
 
             if config.processing.vtt_enable
-                && let Err(e) = check_vtt(&item.source, item.duration, id).await
+                && let Err(e) =
+                    check_vtt(&item.source, item.duration, id, pos, begin, &mut found).await
             {
                 error!(target: Target::file_mail(), channel = id; "{e}");
             }
         }
 
-        begin += item.out - item.seek;
+        begin += item.length();
     }
 
     if !config.playlist.infinit && length > begin + 1.2 {
-        error!(target: Target::file_mail(), channel = id;
+        found.push(
+            None,
+            begin,
+            "",
+            WarningKind::Length,
+            format!(
+                "Playlist not long enough, {} needed",
+                sec_to_time(length - begin)
+            ),
+        );
+        error!(target: Target::file(), channel = id;
             "<span class=\"log-gray\">[Validation]</span> Playlist from <span class=\"log-number\">{date}</span> not long enough, <span class=\"log-number\">{}</span> needed!",
             sec_to_time(length - begin),
         );
+    } else if !config.playlist.infinit
+        && Some(target) != config.playlist.length_sec
+        && begin > length + 1.2
+    {
+        // A full day playlist on the short day of a daylight saving time change.
+        found.push(
+            None,
+            length,
+            "",
+            WarningKind::Length,
+            format!("Playlist too long, {} get cut", sec_to_time(begin - length)),
+        );
+        warn!(target: Target::file(), channel = id;
+            "<span class=\"log-gray\">[Validation]</span> Playlist from <span class=\"log-number\">{date}</span> is longer than the day of <span class=\"log-number\">{}</span>, <span class=\"log-number\">{}</span> get cut!",
+            sec_to_time(target),
+            sec_to_time(begin - length),
+        );
     }
 
+    if !found.is_empty() {
+        error!(target: Target::file_mail(), channel = id;
+            "<span class=\"log-gray\">[Validation]</span> Playlist from <span class=\"log-number\">{date}</span> has problems: {}\n    {}",
+            found.summary(),
+            found.lines().join("\n    ")
+        );
+    }
+
+    *warnings.lock().await = found;
+
     if config.general.validate {
         info!(target: Target::file_mail(), channel = id;
             "<span class=\"log-gray\">[Validation]</span> Playlist length: <span class=\"log-number\">{}</span>",
@@ -0,0 +1,231 @@
+/// Audio Languages
+///
+/// With `processing.audio_languages`, like `deu, eng`, the audio tracks of the playout
+/// are languages: track `0` is the first (primary) language, track `1` the second, and so
+/// on. The decoder takes the streams of a file by their language tag, files without the
+/// tags keep the order of their streams.
+///
+/// The outputs select their languages with `output.languages`, one entry per output, like
+/// `deu+eng, deu`. An output without an entry gets all tracks, only FLV (RTMP), which can
+/// carry one audio stream, gets the primary language. HLS without a `-var_stream_map`
+/// gets the languages as renditions of one audio group, with a master playlist.
+///
+/// The track of the audio description (`processing.description_track`) has the name `ad`
+/// and the primary language, its entry in `processing.audio_languages` is ignored.
+use crate::player::{
+    filter::description::{self, DESCRIPTION_NAME},
+    utils::probe::MediaProbe,
+};
+use crate::utils::config::{PlayoutConfig, Processing};
+use crate::vec_strings;
+
+const AUDIO_GROUP: &str = "audio";
+
+/// Languages of the audio tracks, in the order of the tracks.
+pub fn audio_languages(processing: &Processing) -> Vec<String> {
+    let languages = processing.audio_languages.trim();
+
+    if languages.is_empty() {
+        return vec![];
+    }
+
+    languages
+        .split(',')
+        .map(|l| l.trim().to_lowercase())
+        .take(processing.audio_tracks.max(1) as usize)
+        .collect()
+}
+
+/// Names of the audio tracks, for the outputs: the language or `ad`, empty when it has none.
+pub fn track_names(processing: &Processing) -> Vec<String> {
+    let languages = audio_languages(processing);
+    let description = description::track(processing);
+
+    (0..processing.audio_tracks)
+        .map(|i| {
+            if description == Some(i) {
+                DESCRIPTION_NAME.to_string()
+            } else {
+                languages.get(i as usize).cloned().unwrap_or_default()
+            }
+        })
+        .collect()
+}
+
+/// Language of the audio track, the description has the primary language.
+fn track_language(processing: &Processing, track: i32) -> Option<String> {
+    let languages = audio_languages(processing);
+    let track = if description::track(processing) == Some(track) {
+        0
+    } else {
+        track
+    };
+
+    languages
+        .get(track as usize)
+        .filter(|l| !l.is_empty())
+        .cloned()
+}
+
+/// Stream of the file for the audio track, by its language.
+pub fn source_track(config: &PlayoutConfig, probe: Option<&MediaProbe>, track: i32) -> i32 {
+    audio_languages(&config.processing)
+        .get(track as usize)
+        .filter(|l| !l.is_empty())
+        .and_then(|language| {
+            probe?
+                .audio
+                .iter()
+                .position(|a| a.language.as_ref() == Some(language))
+        })
+        .map_or(track, |i| i as i32)
+}
+
+/// Format (`-f`) of every output in the output parameters.
+pub fn output_formats(params: &[String]) -> Vec<String> {
+    let mut formats = vec![];
+    let mut format = String::new();
+
+    for (i, param) in params.iter().enumerate().skip(1) {
+        if params[i - 1] == "-f" {
+            format.clone_from(param);
+        } else if !param.starts_with('-') && !params[i - 1].starts_with('-') {
+            formats.push(std::mem::take(&mut format));
+        }
+    }
+
+    formats
+}
+
+/// Audio tracks of the output with index `output`.
+pub fn output_tracks(config: &PlayoutConfig, output: usize) -> Vec<i32> {
+    let all = (0..config.processing.audio_tracks).collect::<Vec<_>>();
+    let names = track_names(&config.processing);
+    let entry = config
+        .output
+        .languages
+        .split(',')
+        .nth(output)
+        .map(|e| e.trim().to_lowercase())
+        .unwrap_or_default();
+
+    if entry.is_empty() {
+        let flv = config
+            .output
+            .output_cmd
+            .as_ref()
+            .and_then(|cmd| output_formats(cmd).get(output).cloned())
+            .is_some_and(|f| f == "flv");
+
+        return if flv { vec![0] } else { all };
+    }
+
+    let tracks = entry
+        .split('+')
+        .filter_map(|l| names.iter().position(|name| name == l.trim()))
+        .map(|i| i as i32)
+        .collect::<Vec<_>>();
+
+    if tracks.is_empty() { all } else { tracks }
+}
+
+/// Languages, which don't fit to the audio tracks.
+pub fn check(config: &PlayoutConfig) -> Vec<String> {
+    let mut warnings = vec![];
+    let names = track_names(&config.processing);
+    let languages = config.processing.audio_languages.trim();
+    let count = if languages.is_empty() {
+        0
+    } else {
+        languages.split(',').count()
+    };
+
+    // the description needs no language
+    let tracks = config.processing.audio_tracks as usize;
+    let needed = tracks - usize::from(description::track(&config.processing).is_some());
+
+    if count > 0 && (count < needed || count > tracks) {
+        warnings.push(format!(
+            "{count} audio language(s) for {} audio track(s)",
+            config.processing.audio_tracks
+        ));
+    }
+
+    for entry in config.output.languages.split(',') {
+        for language in entry.split('+').map(|l| l.trim().to_lowercase()) {
+            if !language.is_empty() && !names.contains(&language) {
+                warnings.push(format!(
+                    "Output language '{language}' is not in the audio languages"
+                ));
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Language metadata of the audio tracks, for the output of the decoder.
+pub fn metadata(processing: &Processing) -> Vec<String> {
+    let mut cmd = vec![];
+
+    for i in 0..processing.audio_tracks {
+        if let Some(language) = track_language(processing, i) {
+            cmd.append(&mut vec_strings![
+                format!("-metadata:s:a:{i}"),
+                format!("language={language}")
+            ]);
+        }
+    }
+
+    if let Some(track) = description::track(processing) {
+        cmd.append(&mut vec_strings![
+            format!("-disposition:a:{track}"),
+            "visual_impaired"
+        ]);
+    }
+
+    cmd
+}
+
+/// Audio renditions for HLS output parameters without `-var_stream_map`, when the tracks
+/// have names. The playlist name needs `%v`, for the name of the variants.
+pub fn hls_renditions(cmd: &mut Vec<String>, processing: &Processing) {
+    let names = track_names(processing);
+
+    if names.len() < 2
+        || names.iter().all(String::is_empty)
+        || cmd.iter().any(|p| p == "tee" || p == "-var_stream_map")
+        || !cmd.last().is_some_and(|p| p.contains("%v"))
+    {
+        return;
+    }
+
+    let mut map = format!("v:0,agroup:{AUDIO_GROUP},name:video");
+
+    for (i, name) in names.iter().enumerate() {
+        let name = if name.is_empty() {
+            format!("audio_{i}")
+        } else {
+            name.clone()
+        };
+
+        map.push_str(&format!(" a:{i},agroup:{AUDIO_GROUP},name:{name}"));
+
+        if let Some(language) = track_language(processing, i as i32) {
+            map.push_str(&format!(",language:{language}"));
+        }
+
+        if i == 0 {
+            map.push_str(",default:yes");
+        }
+    }
+
+    let mut params = vec_strings!["-var_stream_map", map];
+
+    if !cmd.iter().any(|p| p == "-master_pl_name") {
+        params.append(&mut vec_strings!["-master_pl_name", "master.m3u8"]);
+    }
+
+    let last = cmd.len() - 1;
+    cmd.splice(last..last, params);
+}
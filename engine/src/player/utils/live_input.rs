@@ -0,0 +1,135 @@
+/// Live Inputs
+///
+/// Live sources in the playlist can have protocol options under `"live"`:
+///
+/// - RTSP (`rtsp://`, `rtsps://`): `rtsp_transport`, `tcp` when it is not set
+/// - SRT (`srt://`): `latency` in milliseconds and `passphrase` (10 to 79 characters)
+/// - NDI (`ndi://<source name>`): no options, needs ffmpeg with `libndi_newtek`
+///
+/// The options are checked, when the playlist gets validated.
+use serde::{Deserialize, Serialize};
+
+use crate::utils::config::General;
+use crate::vec_strings;
+
+pub const RTSP_TRANSPORTS: [&str; 4] = ["tcp", "udp", "udp_multicast", "http"];
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LiveOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rtsp_transport: Option<String>,
+    /// SRT latency in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LiveProtocol {
+    Rtsp,
+    Srt,
+    Ndi,
+}
+
+impl LiveProtocol {
+    pub fn from_source(source: &str) -> Option<Self> {
+        let scheme = source.split_once("://")?.0.to_lowercase();
+
+        match scheme.as_str() {
+            "rtsp" | "rtsps" => Some(Self::Rtsp),
+            "srt" => Some(Self::Srt),
+            "ndi" => Some(Self::Ndi),
+            _ => None,
+        }
+    }
+}
+
+/// Check the options against the protocol of the source and the ffmpeg build.
+pub fn validate(source: &str, options: &LiveOptions, general: &General) -> Result<(), String> {
+    let protocol = LiveProtocol::from_source(source);
+
+    if options.rtsp_transport.is_some() && protocol != Some(LiveProtocol::Rtsp) {
+        return Err("Option rtsp_transport is only for RTSP sources".to_string());
+    }
+
+    if (options.latency.is_some() || options.passphrase.is_some())
+        && protocol != Some(LiveProtocol::Srt)
+    {
+        return Err("Options latency and passphrase are only for SRT sources".to_string());
+    }
+
+    if let Some(transport) = &options.rtsp_transport
+        && !RTSP_TRANSPORTS.contains(&transport.as_str())
+    {
+        return Err(format!(
+            "RTSP transport '{transport}' is not one of: {}",
+            RTSP_TRANSPORTS.join(", ")
+        ));
+    }
+
+    if let Some(passphrase) = &options.passphrase
+        && !(10..=79).contains(&passphrase.chars().count())
+    {
+        return Err("SRT passphrase needs 10 to 79 characters".to_string());
+    }
+
+    match protocol {
+        Some(LiveProtocol::Srt)
+            if !general.ffmpeg_protocols.is_empty()
+                && !general.ffmpeg_protocols.iter().any(|p| p == "srt") =>
+        {
+            Err("ffmpeg has no SRT support (libsrt)".to_string())
+        }
+        Some(LiveProtocol::Ndi)
+            if !general.ffmpeg_libs.is_empty()
+                && !general.ffmpeg_libs.iter().any(|l| l == "libndi_newtek") =>
+        {
+            Err("ffmpeg has no NDI support (libndi_newtek)".to_string())
+        }
+        Some(LiveProtocol::Ndi) if source["ndi://".len()..].trim().is_empty() => {
+            Err("NDI source name is missing".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Input arguments for a live source, `None` for other sources.
+pub fn input_args(source: &str, options: &LiveOptions) -> Option<Vec<String>> {
+    let mut args = vec![];
+
+    match LiveProtocol::from_source(source)? {
+        LiveProtocol::Rtsp => {
+            let transport = options.rtsp_transport.as_deref().unwrap_or("tcp");
+
+            args.append(&mut vec_strings![
+                "-rtsp_transport",
+                transport,
+                "-i",
+                source
+            ]);
+        }
+        LiveProtocol::Srt => {
+            if let Some(latency) = options.latency {
+                // libsrt takes microseconds
+                args.append(&mut vec_strings!["-latency", latency as u64 * 1000]);
+            }
+
+            if let Some(passphrase) = &options.passphrase {
+                args.append(&mut vec_strings!["-passphrase", passphrase]);
+            }
+
+            args.append(&mut vec_strings!["-i", source]);
+        }
+        LiveProtocol::Ndi => {
+            args.append(&mut vec_strings![
+                "-f",
+                "libndi_newtek",
+                "-i",
+                &source["ndi://".len()..]
+            ]);
+        }
+    }
+
+    Some(args)
+}
@@ -0,0 +1,93 @@
+/// Audio Meter
+///
+/// When `processing.audio_meter` is enabled, the `ebur128` filter adds the loudness
+/// values to the audio frames (every 100ms) and `ametadata` prints them to stderr:
+///
+/// ```text
+/// frame:21   pts:20480   pts_time:0.426667
+/// lavfi.r128.M=-23.474
+/// lavfi.r128.true_peaks_ch0=-6.021
+/// lavfi.r128.true_peaks_ch1=-5.880
+/// ```
+///
+/// The stderr readers pass the lines to the meter of the channel, the values are
+/// shown in the status API and in the playout SSE stream.
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+
+pub const METER_FILTER: &str =
+    "ebur128=metadata=1:peak=true,ametadata=mode=print:file='pipe\\:2':direct=1";
+
+const METER_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, Default)]
+pub struct AudioMeter {
+    momentary: Option<f64>,
+    true_peak: Option<f64>,
+    peaks: Vec<f64>,
+    updated: Option<Instant>,
+}
+
+impl AudioMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a line from ffmpeg, returns `false` when it is no meter line.
+    pub fn update(&mut self, line: &str) -> bool {
+        if line.starts_with("frame:") && line.contains("pts_time:") {
+            self.flush();
+
+            return true;
+        }
+
+        let Some((key, value)) = line
+            .strip_prefix("lavfi.r128.")
+            .and_then(|l| l.split_once('='))
+        else {
+            return false;
+        };
+
+        let value = value.trim().parse::<f64>().ok().filter(|v| v.is_finite());
+
+        if key == "M" {
+            self.momentary = value;
+            self.updated = Some(Instant::now());
+        } else if key.starts_with("true_peaks_ch") {
+            self.peaks.push(value.unwrap_or(f64::NEG_INFINITY));
+        }
+
+        true
+    }
+
+    /// Take the highest peak from all channels of the last frame.
+    fn flush(&mut self) {
+        if !self.peaks.is_empty() {
+            let peak = self.peaks.drain(..).fold(f64::NEG_INFINITY, f64::max);
+            self.true_peak = Some(peak).filter(|p| p.is_finite());
+        }
+    }
+
+    pub fn momentary(&self) -> Option<f64> {
+        self.momentary
+    }
+
+    pub fn true_peak(&self) -> Option<f64> {
+        self.true_peak
+    }
+
+    /// Values for the status, `null` when the meter got no update for some time.
+    pub fn to_value(&mut self) -> Value {
+        self.flush();
+
+        if self.updated.is_none_or(|t| t.elapsed() > METER_TIMEOUT) {
+            return Value::Null;
+        }
+
+        json!({
+            "momentary": self.momentary.map(|m| (m * 10.0).round() / 10.0),
+            "true_peak": self.true_peak.map(|p| (p * 10.0).round() / 10.0),
+        })
+    }
+}
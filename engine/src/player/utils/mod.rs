@@ -6,12 +6,16 @@ use std::{
     path::{Path, PathBuf},
     process::{Stdio, exit},
     str::FromStr,
-    sync::{Arc, atomic::Ordering},
+    sync::{Arc, OnceLock, atomic::Ordering},
+    time::{Duration, Instant},
 };
 
 use chrono::{TimeDelta, prelude::*};
 use chrono_tz::Tz;
+use live_input::LiveOptions;
 use log::*;
+use meter::AudioMeter;
+use output_stats::{OutputStats, Progress};
 use probe::MediaProbe;
 use rand::prelude::*;
 use regex::Regex;
@@ -25,20 +29,34 @@ use tokio::{
     sync::Mutex,
 };
 
+pub mod av_sync;
+pub mod id3;
 pub mod import;
 pub mod json_serializer;
 pub mod json_validate;
+pub mod languages;
+pub mod live_input;
+pub mod meter;
+pub mod output_stats;
 pub mod probe;
-
+pub mod program;
+pub mod quarantine;
+pub mod read_ahead;
+pub mod resume;
+pub mod ring_buffer;
+pub mod timecode;
+pub mod warnings;
+
+use crate::file::backend::{Backend, StorageBackend, is_storage_url, media_url};
 use crate::player::{
     controller::{
         ChannelManager,
         ProcessUnit::{self, *},
     },
-    filter::{Filters, filter_chains},
+    filter::{Filters, description, filter_chains},
 };
 use crate::utils::{
-    config::{FFMPEG_IGNORE_ERRORS, OutputMode::*, PlayoutConfig},
+    config::{FFMPEG_IGNORE_ERRORS, OutputMode::*, PlayoutConfig, ProcessMode, RecoveryStrategy},
     errors::ServiceError,
     logging::{LogDedup, Target},
     time_machine::time_now,
@@ -63,6 +81,57 @@ pub fn valid_stream(msg: &str) -> bool {
     false
 }
 
+pub const AUDIO_OUTPUT_PARAM: &str = "-c:a libmp3lame -b:a 128k -ar 44100 -f mp3";
+
+/// Audio only output (`output.audio_url`), from the first audio track of the encoder input.
+///
+/// It runs over the tee muxer, so a missing server doesn't stop the video outputs.
+/// The format comes from `-f` in `output.audio_param`, mp3 when it is not set.
+pub fn audio_output(config: &PlayoutConfig) -> Vec<String> {
+    let url = config.output.audio_url.trim();
+
+    if url.is_empty() {
+        return vec![];
+    }
+
+    let param = match config.output.audio_param.trim() {
+        "" => AUDIO_OUTPUT_PARAM,
+        param => param,
+    };
+    let mut params = shlex::split(param).unwrap_or_default();
+    let mut format = "mp3".to_string();
+
+    if let Some(i) = params.iter().position(|p| p == "-f")
+        && i + 1 < params.len()
+    {
+        format = params.remove(i + 1);
+        params.remove(i);
+    }
+
+    let mut cmd = vec_strings!["-map", "0:a:0"];
+
+    cmd.append(&mut params);
+    cmd.append(&mut vec_strings![
+        "-f",
+        "tee",
+        format!("[f={format}:onfail=ignore]{url}")
+    ]);
+
+    cmd
+}
+
+/// Audio track of the output, from the filter, when the audio is split for the outputs.
+fn output_audio(filter: &Filters, output: usize, track: i32) -> String {
+    if filter.audio_out_link.is_empty() {
+        format!("0:a:{track}")
+    } else {
+        format!("[aout_{track}_{output}]")
+    }
+}
+
+/// Seconds before the in point, from where the clip gets decoded.
+const SEEK_PRE_ROLL: f64 = 5.0;
+
 /// Prepare output parameters
 ///
 /// Seek for multiple outputs and add mapping for it.
@@ -75,12 +144,19 @@ pub fn prepare_output_cmd(
     let mut new_params = vec![];
     let mut count = 0;
     let re_v = Regex::new(r"\[?0:v(:0)?\]?").unwrap();
+    let re_a = Regex::new(r"^\[?0:a(?::(\d+))?\]?$").unwrap();
 
     if let Some(mut filter) = filters.clone() {
         for (i, param) in output_params.iter().enumerate() {
             if filter.video_out_link.len() > count && re_v.is_match(param) {
                 // replace mapping with link from filter struct
                 new_params.push(filter.video_out_link[count].clone());
+            } else if !filter.audio_out_link.is_empty()
+                && let Some(track) = re_a.captures(param)
+            {
+                let track = track.get(1).map_or("0", |t| t.as_str());
+
+                new_params.push(format!("[aout_{track}_{count}]"));
             } else {
                 new_params.push(param.clone());
             }
@@ -98,8 +174,9 @@ pub fn prepare_output_cmd(
                 {
                     new_params.append(&mut vec_strings!["-map", filter.video_out_link[count]]);
 
-                    for i in 0..config.processing.audio_tracks {
-                        new_params.append(&mut vec_strings!["-map", format!("0:a:{i}")]);
+                    for i in languages::output_tracks(config, count) {
+                        new_params
+                            .append(&mut vec_strings!["-map", output_audio(&filter, count, i)]);
                     }
                 }
             }
@@ -118,18 +195,40 @@ pub fn prepare_output_cmd(
         } else if &output_params[0] != "-map" && !filter.video_out_link.is_empty() {
             cmd.append(&mut vec_strings!["-map", filter.video_out_link[0].clone()]);
 
-            for i in 0..config.processing.audio_tracks {
-                cmd.append(&mut vec_strings!["-map", format!("0:a:{i}")]);
+            for i in languages::output_tracks(config, 0) {
+                cmd.append(&mut vec_strings!["-map", output_audio(&filter, 0, i)]);
             }
         }
     }
 
+    if config.processing.timecode_embed && !config.processing.audio_only {
+        let time = time_now(&config.channel.timezone).time();
+
+        cmd.append(&mut vec_strings![
+            "-timecode",
+            timecode::timecode(&time, timecode::timecode_rate(config.processing.fps))
+        ]);
+    }
+
+    let inputs = cmd
+        .windows(2)
+        .filter(|w| w[0] == "-i")
+        .map(|w| w[1].ends_with(id3::ID3_EXTENSION))
+        .collect::<Vec<_>>();
+
     if config.processing.vtt_enable {
-        let i = cmd.iter().filter(|&n| n == "-i").count().saturating_sub(1);
+        let i = inputs
+            .iter()
+            .rposition(|is_id3| !is_id3)
+            .unwrap_or_default();
 
         cmd.append(&mut vec_strings!("-map", format!("{i}:s?")));
     }
 
+    if let Some(i) = inputs.iter().position(|is_id3| *is_id3) {
+        cmd.append(&mut vec_strings!("-map", format!("{i}:d"), "-c:d", "copy"));
+    }
+
     cmd.append(&mut output_params);
 
     cmd
@@ -151,6 +250,18 @@ pub fn get_media_map(media: Media) -> Value {
             .insert("title".to_string(), Value::String(title));
     }
 
+    if let Some(artist) = media.artist {
+        obj.as_object_mut()
+            .unwrap()
+            .insert("artist".to_string(), Value::String(artist));
+    }
+
+    if let Some(program_id) = media.program_id {
+        obj.as_object_mut()
+            .unwrap()
+            .insert("program_id".to_string(), Value::String(program_id));
+    }
+
     obj
 }
 
@@ -163,14 +274,35 @@ pub async fn get_data_map(manager: &ChannelManager) -> Map<String, Value> {
         .clone()
         .unwrap_or_else(Media::default);
     let channel = manager.channel.lock().await.clone();
-    let config = manager.config.read().await.processing.clone();
+    let playout_config = manager.config.read().await.clone();
+    let config = playout_config.processing.clone();
     let ingest_is_alive = manager.ingest_is_alive.load(Ordering::SeqCst);
+    let audio_meter = manager.audio_meter.lock().await.to_value();
+    let fallback = *manager.fallback.lock().await;
+    let quarantine = manager.quarantine.lock().await.sources();
+    let warnings = manager.playlist_warnings.lock().await.clone();
+    let missing_playlist = manager.missing_playlist.lock().await.clone();
+    let next_warnings = manager.next_warnings.lock().await.clone();
 
     let mut data_map = Map::new();
-    let current_time = time_in_seconds(&channel.timezone);
+    let current_time = clip_seconds(&playout_config);
     let shift = channel.time_shift;
     let begin = media.begin.unwrap_or(0.0) - shift;
     let played_time = current_time - begin;
+    let program = match media.index {
+        Some(index) if media.program_id.is_some() => {
+            let list = manager.current_list.lock().await;
+
+            program::program_at(&list, index).map(|p| {
+                let begin = list[p.index].begin.unwrap_or(0.0) - shift;
+                let mut value = json!(p);
+                value["elapsed"] = json!(((current_time - begin) * 1000.0).round() / 1000.0);
+
+                value
+            })
+        }
+        _ => None,
+    };
 
     data_map.insert("index".to_string(), json!(media.index));
     data_map.insert("ingest".to_string(), json!(ingest_is_alive));
@@ -184,6 +316,17 @@ pub async fn get_data_map(manager: &ChannelManager) -> Map<String, Value> {
         json!((played_time * 1000.0).round() / 1000.0),
     );
     data_map.insert("media".to_string(), get_media_map(media));
+    data_map.insert("audio_meter".to_string(), audio_meter);
+    data_map.insert("fallback".to_string(), json!(fallback));
+    data_map.insert("quarantine".to_string(), json!(quarantine));
+    data_map.insert("program".to_string(), json!(program));
+    data_map.insert("warnings".to_string(), json!(warnings));
+    data_map.insert("missing_playlist".to_string(), json!(missing_playlist));
+    data_map.insert(
+        "hold".to_string(),
+        json!(manager.hold.load(Ordering::SeqCst)),
+    );
+    data_map.insert("next_warnings".to_string(), json!(next_warnings));
 
     data_map
 }
@@ -198,8 +341,14 @@ pub struct Media {
     pub index: Option<usize>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
     #[serde(rename = "in")]
     pub seek: f64,
+
+    /// Decoded part before the in point, which gets trimmed in the filter.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub pre_roll: f64,
     pub out: f64,
     pub duration: f64,
 
@@ -248,6 +397,113 @@ pub struct Media {
 
     #[serde(default, skip_serializing)]
     pub unit: ProcessUnit,
+
+    /// Playback speed factor, set by the underrun recovery.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub speed: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "StartMode::is_soft")]
+    pub start: StartMode,
+
+    /// Export the item to the VOD folder, after it is aired.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub record: bool,
+
+    /// Seconds without data from the decoder, overrides the global stall timeout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stall_timeout: Option<f64>,
+
+    /// ffmpeg input options for the decoder, like `-analyzeduration 10M -probesize 10M`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_options: Option<String>,
+
+    /// Protocol options for RTSP, SRT and NDI sources.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live: Option<LiveOptions>,
+
+    /// Items in a row with the same id are reported as one program.
+    #[serde(default, alias = "block", skip_serializing_if = "Option::is_none")]
+    pub program_id: Option<String>,
+
+    /// Clock time (hh:mm:ss) for hard start items.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+
+    /// A loop repeats its file or folder until the clock time in `until`.
+    #[serde(default, rename = "type", skip_serializing_if = "ItemType::is_clip")]
+    pub item_type: ItemType,
+
+    /// Clock time (hh:mm:ss), until which a loop item repeats.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+
+    /// Exact play time in seconds, the speed of the clip changes a bit to fit into it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<f64>,
+
+    /// Seconds, which the playout may be behind at the begin of the item, otherwise it
+    /// gets skipped. For low priority items, like promos and fillers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_late: Option<f64>,
+
+    /// Tags of the outputs, which show the slate instead of this item (`output.tags`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blackout: Vec<String>,
+
+    /// Sidecar file with the audio description, which is an input of the decoder.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub description: Option<String>,
+
+    /// Where the item in the running program comes from, see [`Origin`].
+    #[serde(skip_serializing, skip_deserializing)]
+    pub origin: Origin,
+}
+
+/// Scheduling mode from a playlist item.
+///
+/// Soft items float with the preceding content, hard items must start at their clock time.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StartMode {
+    #[default]
+    Soft,
+    Hard,
+}
+
+impl StartMode {
+    pub fn is_soft(&self) -> bool {
+        *self == Self::Soft
+    }
+}
+
+/// Type of a playlist item.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemType {
+    #[default]
+    Clip,
+    Loop,
+}
+
+impl ItemType {
+    pub fn is_clip(&self) -> bool {
+        *self == Self::Clip
+    }
+}
+
+/// Origin of an item in the running program.
+///
+/// Changes of the playlist file are merged into the running program, items which are
+/// not from the file must survive this merge.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Origin {
+    #[default]
+    File,
+    /// Inserted or generated at runtime, like a bumper, gap or "Up next" card.
+    Inserted,
+    /// Item from the file, which is changed in the running program. It replaces the
+    /// same item of the file and the file items before it.
+    Changed,
 }
 
 impl Media {
@@ -256,7 +512,7 @@ impl Media {
         let mut probe = None;
 
         if do_probe
-            && (is_remote(src) || Path::new(src).is_file())
+            && (is_remote(src) || is_storage_url(src) || Path::new(src).is_file())
             && let Ok(p) = MediaProbe::new(src).await
         {
             probe = Some(p.clone());
@@ -268,7 +524,9 @@ impl Media {
             begin: None,
             index: Some(index),
             title: None,
+            artist: None,
             seek: 0.0,
+            pre_roll: 0.0,
             out: duration,
             duration,
             duration_audio: 0.0,
@@ -284,9 +542,29 @@ impl Media {
             next_ad: false,
             skip: false,
             unit: Decoder,
+            speed: None,
+            start: StartMode::Soft,
+            record: false,
+            stall_timeout: None,
+            input_options: None,
+            live: None,
+            program_id: None,
+            time: None,
+            item_type: ItemType::Clip,
+            until: None,
+            slot: None,
+            skip_late: None,
+            blackout: vec![],
+            description: None,
+            origin: Origin::File,
         }
     }
 
+    /// Play time of the clip, which changes with its speed.
+    pub fn length(&self) -> f64 {
+        (self.out - self.seek) / self.speed.unwrap_or(1.0)
+    }
+
     pub async fn add_probe(&mut self, check_audio: bool) -> Result<(), String> {
         let mut errors = vec![];
 
@@ -339,6 +617,30 @@ impl Media {
         let mut node = self.clone();
         self.filter = Some(filter_chains(config, &mut node, filter_chain).await);
     }
+
+    /// Decoder input options of the item, after the ones from the config.
+    pub fn input_cmd(&self, config: &PlayoutConfig) -> Vec<String> {
+        let mut cmd = config
+            .advanced
+            .decoder
+            .input_cmd
+            .clone()
+            .unwrap_or_default();
+
+        if let Some(options) = &self.input_options {
+            match shlex::split(options) {
+                Some(mut options) => cmd.append(&mut options),
+                None => {
+                    error!(target: Target::file_mail(), channel = config.general.channel_id;
+                        "Input options of <span class=\"log-addr\">{}</span> are not valid: {options}",
+                        self.source
+                    );
+                }
+            }
+        }
+
+        cmd
+    }
 }
 
 impl Default for Media {
@@ -347,7 +649,9 @@ impl Default for Media {
             begin: None,
             index: Some(0),
             title: None,
+            artist: None,
             seek: 0.0,
+            pre_roll: 0.0,
             out: 0.0,
             duration: 0.0,
             duration_audio: 0.0,
@@ -363,6 +667,21 @@ impl Default for Media {
             next_ad: false,
             skip: false,
             unit: Decoder,
+            speed: None,
+            start: StartMode::Soft,
+            record: false,
+            stall_timeout: None,
+            input_options: None,
+            live: None,
+            program_id: None,
+            time: None,
+            item_type: ItemType::Clip,
+            until: None,
+            slot: None,
+            skip_late: None,
+            blackout: vec![],
+            description: None,
+            origin: Origin::File,
         }
     }
 }
@@ -370,6 +689,7 @@ impl Default for Media {
 impl PartialEq for Media {
     fn eq(&self, other: &Self) -> bool {
         self.title == other.title
+            && self.artist == other.artist
             && self.seek == other.seek
             && self.out == other.out
             && self.duration == other.duration
@@ -377,6 +697,17 @@ impl PartialEq for Media {
             && self.category == other.category
             && self.audio == other.audio
             && self.custom_filter == other.custom_filter
+            && self.start == other.start
+            && self.time == other.time
+            && self.stall_timeout == other.stall_timeout
+            && self.input_options == other.input_options
+            && self.live == other.live
+            && self.program_id == other.program_id
+            && self.item_type == other.item_type
+            && self.until == other.until
+            && self.slot == other.slot
+            && self.skip_late == other.skip_late
+            && self.blackout == other.blackout
     }
 }
 
@@ -394,6 +725,11 @@ fn is_empty_string(st: &String) -> bool {
     *st == String::new()
 }
 
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
 /// Calculate fps from rate/factor string
 pub fn fps_calc(r_frame_rate: &str, default: f64) -> f64 {
     if let Some((r, f)) = r_frame_rate.split_once('/')
@@ -457,6 +793,89 @@ pub fn get_date(seek: bool, start: f64, get_next: bool, timezone: &Option<Tz>) -
     local.format("%Y-%m-%d").to_string()
 }
 
+/// Local start of the playlist day `date`, the earlier one when the time is doubled by a
+/// change of the daylight saving time.
+fn day_start(date: NaiveDate, start: f64, tz: Tz) -> Option<DateTime<Tz>> {
+    let time = date.and_hms_opt(0, 0, 0)? + TimeDelta::milliseconds((start * 1000.0) as i64);
+
+    tz.from_local_datetime(&time).earliest()
+}
+
+/// Real length of the playlist day `date` in seconds, from its start to the start of the
+/// next day. On days with a change of the daylight saving time it is 23 or 25 hours.
+pub fn day_length(date: NaiveDate, start: f64, tz: Tz) -> f64 {
+    match (
+        day_start(date, start, tz),
+        date.succ_opt().and_then(|next| day_start(next, start, tz)),
+    ) {
+        (Some(begin), Some(end)) => (end - begin).as_seconds_f64(),
+        _ => 86400.0,
+    }
+}
+
+/// Real length of the playlist day `date` in the time zone of the channel.
+fn channel_day_length(config: &PlayoutConfig, date: &str) -> f64 {
+    let tz = time_now(&config.channel.timezone).timezone();
+
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|d| day_length(d, config.playlist.start_sec.unwrap_or_default(), tz))
+        .unwrap_or(86400.0)
+}
+
+/// Target length of the playlist from `date`. A configured length of a full day follows
+/// the real length of the day, other lengths (special events) are taken as they are.
+pub fn target_length(config: &PlayoutConfig, date: &str) -> f64 {
+    let length = config.playlist.length_sec.unwrap_or(86400.0);
+
+    if length > 0.0 && length != 86400.0 {
+        return length;
+    }
+
+    channel_day_length(config, date)
+}
+
+/// Like [`time_in_seconds`], but the seconds run on from the playlist start in real time,
+/// also over midnight and over a change of the daylight saving time. So they stay
+/// comparable with the begin of the playlist items.
+pub fn playlist_seconds_at(now: DateTime<Tz>, start: f64) -> f64 {
+    let wall = (now.hour() * 3600 + now.minute() * 60 + now.second()) as f64
+        + (now.nanosecond() as f64 / 1000000000.0);
+    let mut date = now.date_naive();
+
+    if wall < start {
+        date = date.pred_opt().unwrap_or(date);
+    }
+
+    match day_start(date, start, now.timezone()) {
+        Some(begin) => start + (now - begin).as_seconds_f64(),
+        None if wall < start => wall + 86400.0,
+        None => wall,
+    }
+}
+
+/// Playlist seconds of the current time, see [`playlist_seconds_at`].
+pub fn playlist_seconds(timezone: &Option<Tz>, start: f64) -> f64 {
+    playlist_seconds_at(time_now(timezone), start)
+}
+
+/// Current seconds to compare with the begin of the current clip: playlist seconds in
+/// playlist mode, wall clock seconds otherwise.
+pub fn clip_seconds(config: &PlayoutConfig) -> f64 {
+    if config.processing.mode == ProcessMode::Playlist {
+        playlist_seconds(
+            &config.channel.timezone,
+            config.playlist.start_sec.unwrap_or_default(),
+        )
+    } else {
+        time_in_seconds(&config.channel.timezone)
+    }
+}
+
+/// Date of the current playlist day, the day before, when the time is before the start.
+pub fn playlist_date(timezone: &Option<Tz>, start: f64) -> String {
+    get_date(true, start, false, timezone)
+}
+
 pub fn time_from_header(headers: &header::HeaderMap) -> Option<DateTime<Local>> {
     if let Some(time) = headers.get(header::LAST_MODIFIED)
         && let Ok(t) = time.to_str()
@@ -471,6 +890,10 @@ pub fn time_from_header(headers: &header::HeaderMap) -> Option<DateTime<Local>>
 
 /// Get file modification time.
 pub async fn modified_time(path: &str) -> Option<String> {
+    if is_storage_url(path) {
+        return Backend::new(path).modified(path).await;
+    }
+
     if is_remote(path) {
         let response = reqwest::Client::new().head(path).send().await;
 
@@ -534,34 +957,39 @@ pub fn sum_durations(clip_list: &[Media]) -> f64 {
     clip_list.iter().map(|item| item.out).sum()
 }
 
+/// Filler clips are marked by category, or they come from the filler path.
+pub fn is_filler(config: &PlayoutConfig, media: &Media) -> bool {
+    let filler_path = config.storage.filler_path.to_string_lossy();
+
+    media.category == "filler"
+        || (!filler_path.is_empty() && media.source.starts_with(&*filler_path))
+}
+
 /// Get delta between clip start and current time. This value we need to check,
 /// if we still in sync.
 ///
 /// We also get here the global delta between clip start and time when a new playlist should start.
 pub fn get_delta(config: &PlayoutConfig, begin: &f64) -> (f64, f64) {
-    let mut current_time = time_in_seconds(&config.channel.timezone);
     let start = config.playlist.start_sec.unwrap();
-    let length = config.playlist.length_sec.unwrap_or(86400.0);
-    let mut target_length = 86400.0;
+    let mut current_time = playlist_seconds(&config.channel.timezone, start);
+    let date = playlist_date(&config.channel.timezone, start);
+    let day = channel_day_length(config, &date);
+    let target_length = target_length(config, &date);
 
-    if length > 0.0 && length != target_length {
-        target_length = length;
-    }
-
-    if begin == &start && start == 0.0 && 86400.0 - current_time < 4.0 {
-        current_time -= 86400.0;
+    if begin == &start && start == 0.0 && day - current_time < 4.0 {
+        current_time -= day;
     } else if start >= current_time && begin != &start {
-        current_time += 86400.0;
+        current_time += day;
     }
 
     let mut current_delta = begin - current_time;
 
     if is_close(
         current_delta.abs(),
-        86400.0,
+        day,
         config.general.stop_threshold + 2.0,
     ) {
-        current_delta = current_delta.abs() - 86400.0;
+        current_delta = current_delta.abs() - day;
     }
 
     let total_delta = if current_time < start {
@@ -573,13 +1001,41 @@ pub fn get_delta(config: &PlayoutConfig, begin: &f64) -> (f64, f64) {
     (current_delta, total_delta)
 }
 
-pub fn insert_readrate(options: &[String], args: &mut Vec<String>, rate: f64) {
+/// Container of the pipe between decoder and encoder.
+///
+/// MPEG-TS has timestamps and the decoder marks the start of every clip as discontinuity,
+/// so the encoder follows clip changes and live takeovers by the timestamps.
+pub const PIPE_FORMAT: &str = "mpegts";
+
+/// Encoder input from the pipe, with the pipe format, when the input command sets none.
+pub fn pipe_input(config: &PlayoutConfig) -> Vec<String> {
+    let has_format = config
+        .advanced
+        .encoder
+        .input_cmd
+        .as_ref()
+        .is_some_and(|cmd| cmd.iter().any(|arg| arg == "-f"));
+
+    if has_format {
+        vec_strings!["-i", "pipe:0"]
+    } else {
+        vec_strings!["-f", PIPE_FORMAT, "-i", "pipe:0"]
+    }
+}
+
+pub fn insert_readrate(options: &[String], args: &mut Vec<String>, rate: f64, burst: f64) {
     let mut i = 0;
     while i < args.len() {
         if args[i] == "-i" {
             args.insert(i, rate.to_string());
             args.insert(i, "-readrate".to_string());
 
+            // read the pre-roll of a two stage seek without delay
+            if burst > 0.0 && options.contains(&"-readrate_initial_burst".to_string()) {
+                args.insert(i, burst.to_string());
+                args.insert(i, "-readrate_initial_burst".to_string());
+            }
+
             if options.contains(&"-readrate_catchup".to_string()) {
                 args.insert(i, 1.5.to_string());
                 args.insert(i, "-readrate_catchup".to_string());
@@ -605,7 +1061,7 @@ pub fn insert_readrate(options: &[String], args: &mut Vec<String>, rate: f64) {
 /// Loop image until target duration is reached.
 pub fn loop_image(config: &PlayoutConfig, node: &Media) -> Vec<String> {
     let duration = node.out - node.seek;
-    let mut source_cmd: Vec<String> = vec_strings!["-loop", "1", "-i", node.source.clone()];
+    let mut source_cmd: Vec<String> = vec_strings!["-loop", "1", "-i", media_url(&node.source)];
 
     info!(
         "Loop image <span class=\"log-addr\">{}</span>, total duration: <span class=\"log-number\">{duration:.2}</span>",
@@ -617,7 +1073,7 @@ pub fn loop_image(config: &PlayoutConfig, node: &Media) -> Vec<String> {
             source_cmd.append(&mut vec_strings!["-ss", node.seek]);
         }
 
-        source_cmd.append(&mut vec_strings!["-i", node.audio.clone()]);
+        source_cmd.append(&mut vec_strings!["-i", media_url(&node.audio)]);
     }
 
     source_cmd.append(&mut vec_strings!["-t", duration]);
@@ -706,12 +1162,28 @@ pub fn seek_and_length(config: &PlayoutConfig, node: &mut Media) -> Vec<String>
     let mut cut_audio = false;
     let mut loop_audio = false;
     let remote_source = is_remote(&node.source);
+    let mut input_seek = None;
+
+    node.pre_roll = 0.0;
 
     if remote_source && node.probe.clone().and_then(|f| f.format.duration).is_none() {
         node.out -= node.seek;
         node.seek = 0.0;
+    } else if node.seek > 0.0 && !config.processing.copy_video && !config.processing.copy_audio {
+        // Two stage seek: jump fast to a point before the in point,
+        // the rest gets decoded and trimmed, for a frame exact start.
+        let start = ((node.seek - SEEK_PRE_ROLL).max(0.0) * 1000.0).round() / 1000.0;
+        node.pre_roll = ((node.seek - start) * 1_000_000.0).round() / 1_000_000.0;
+
+        if start > 0.0 {
+            input_seek = Some(start);
+        }
     } else if node.seek > 0.5 {
-        source_cmd.append(&mut vec_strings!["-ss", node.seek]);
+        input_seek = Some(node.seek);
+    }
+
+    if let Some(start) = input_seek {
+        source_cmd.append(&mut vec_strings!["-ss", start]);
     }
 
     if loop_count > 1 {
@@ -723,15 +1195,21 @@ pub fn seek_and_length(config: &PlayoutConfig, node: &mut Media) -> Vec<String>
         source_cmd.append(&mut vec_strings!["-stream_loop", loop_count]);
     }
 
-    source_cmd.append(&mut vec_strings!["-i", node.source.clone()]);
+    match live_input::input_args(&node.source, &node.live.clone().unwrap_or_default()) {
+        Some(mut args) => source_cmd.append(&mut args),
+        None => source_cmd.append(&mut vec_strings!["-i", media_url(&node.source)]),
+    }
 
     if node.duration > node.out || remote_source || loop_count > 1 {
-        source_cmd.append(&mut vec_strings!["-t", node.out - node.seek]);
+        source_cmd.append(&mut vec_strings![
+            "-t",
+            node.out - node.seek + node.pre_roll
+        ]);
     }
 
     if !node.audio.is_empty() {
-        if node.seek > 0.5 {
-            source_cmd.append(&mut vec_strings!["-ss", node.seek]);
+        if let Some(start) = input_seek {
+            source_cmd.append(&mut vec_strings!["-ss", start]);
         }
 
         if node.duration_audio > node.out {
@@ -741,13 +1219,18 @@ pub fn seek_and_length(config: &PlayoutConfig, node: &mut Media) -> Vec<String>
             loop_audio = true;
         }
 
-        source_cmd.append(&mut vec_strings!["-i", node.audio.clone()]);
+        source_cmd.append(&mut vec_strings!["-i", media_url(&node.audio)]);
 
         if cut_audio || loop_audio || remote_source {
-            source_cmd.append(&mut vec_strings!["-t", node.out - node.seek]);
+            source_cmd.append(&mut vec_strings![
+                "-t",
+                node.out - node.seek + node.pre_roll
+            ]);
         }
     }
 
+    source_cmd.append(&mut description::input(config, node, input_seek));
+
     if config.processing.vtt_enable {
         let vtt_file = Path::new(&node.source).with_extension("vtt");
         let vtt_dummy = config
@@ -767,6 +1250,11 @@ pub fn seek_and_length(config: &PlayoutConfig, node: &mut Media) -> Vec<String>
             error!("<span class=\"log-addr\">{:?}</span> not found!", vtt_dummy);
             None
         } {
+            // keep the cues in time with clips, which play faster or slower
+            if let Some(speed) = node.speed.filter(|s| *s != 1.0) {
+                source_cmd.append(&mut vec_strings!["-itsscale", 1.0 / speed]);
+            }
+
             source_cmd.append(&mut vec_strings![
                 "-i",
                 vtt_path,
@@ -780,6 +1268,7 @@ pub fn seek_and_length(config: &PlayoutConfig, node: &mut Media) -> Vec<String>
 }
 
 /// Create a dummy clip as a placeholder for missing video files.
+/// Dark placeholder with pink noise.
 pub fn gen_dummy(config: &PlayoutConfig, duration: f64) -> (String, Vec<String>) {
     let color = "#121212";
     let source = format!(
@@ -800,6 +1289,158 @@ pub fn gen_dummy(config: &PlayoutConfig, duration: f64) -> (String, Vec<String>)
         format!("anoisesrc=d={duration}:c=pink:r=48000:a=0.3")
     ];
 
+    add_vtt_dummy(config, &mut source_cmd);
+
+    (source, source_cmd)
+}
+
+/// Endless video of the slate file, scaled and padded to the output size.
+pub fn slate_loop(config: &PlayoutConfig) -> Option<String> {
+    let (width, height) = (config.processing.width, config.processing.height);
+
+    config.storage.slate_path.is_file().then(|| {
+        format!(
+            "movie={}:loop=0,setpts=N/(FRAME_RATE*TB),scale={width}:{height}:force_original_aspect_ratio=decrease,pad={width}:{height}:(ow-iw)/2:(oh-ih)/2,fps={}",
+            config
+                .storage
+                .slate_path
+                .to_string_lossy()
+                .replace(':', "\\:"),
+            config.processing.fps
+        )
+    })
+}
+
+/// Slate for error states, when no source can be played: the slate image or video loop
+/// (`storage.slate`), or black, with the looped slate audio (`storage.slate_audio`) or silence.
+pub fn gen_slate(config: &PlayoutConfig, duration: f64) -> (String, Vec<String>) {
+    let (width, height) = (config.processing.width, config.processing.height);
+    let source = match slate_loop(config) {
+        Some(slate) => format!("{slate},trim=duration={duration}"),
+        None => format!(
+            "color=c=black:s={width}x{height}:d={duration}:r={}",
+            config.processing.fps
+        ),
+    };
+    let mut source_cmd: Vec<String> = vec_strings![
+        "-f",
+        "lavfi",
+        "-i",
+        format!("{source},format=pix_fmts=yuv420p")
+    ];
+
+    if config.storage.slate_audio_path.is_file() {
+        source_cmd.append(&mut vec_strings![
+            "-stream_loop",
+            "-1",
+            "-t",
+            duration,
+            "-i",
+            config.storage.slate_audio_path.to_string_lossy()
+        ]);
+    } else {
+        source_cmd.append(&mut vec_strings![
+            "-f",
+            "lavfi",
+            "-t",
+            duration,
+            "-i",
+            "anullsrc=r=48000:cl=stereo"
+        ]);
+    }
+
+    add_vtt_dummy(config, &mut source_cmd);
+
+    (source, source_cmd)
+}
+
+pub const TEST_PATTERN: &str = "smptehdbars";
+
+/// Test pattern with SMPTE bars, 1kHz tone (-18 dBFS), the channel name and the local time.
+///
+/// Is used for line-up in test mode and as last step in the fallback chain.
+pub fn gen_test_pattern(config: &PlayoutConfig, duration: f64) -> (String, Vec<String>) {
+    let source = format!(
+        "{TEST_PATTERN}=s={}x{}:d={duration}",
+        config.processing.width, config.processing.height
+    );
+    let font = if Path::new(&config.text.font_path).is_file() {
+        format!(":fontfile='{}'", config.text.font_path)
+    } else {
+        String::new()
+    };
+    let name = config
+        .channel
+        .name
+        .chars()
+        .filter(|c| !matches!(c, '\'' | '\\' | '%'))
+        .collect::<String>()
+        .replace(':', "\\:");
+    let text_style = "fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=10:x=(w-tw)/2";
+
+    let mut source_cmd: Vec<String> = vec_strings![
+        "-f",
+        "lavfi",
+        "-i",
+        format!(
+            "{source}:r={},drawtext=text='{name}'{font}:fontsize=h/14:{text_style}:y=h/12,drawtext=text='%{{localtime\\:%X}}'{font}:fontsize=h/10:{text_style}:y=h*0.7,format=pix_fmts=yuv420p",
+            config.processing.fps
+        ),
+        "-f",
+        "lavfi",
+        "-i",
+        format!("sine=f=1000:r=48000:d={duration}")
+    ];
+
+    add_vtt_dummy(config, &mut source_cmd);
+
+    (source, source_cmd)
+}
+
+/// "Up next" card with the title and the start time (seconds of the day) of the next program.
+pub fn gen_next_card(
+    config: &PlayoutConfig,
+    duration: f64,
+    title: &str,
+    start: f64,
+) -> (String, Vec<String>) {
+    let source = format!(
+        "color=c=#121212:s={}x{}:d={duration}",
+        config.processing.width, config.processing.height
+    );
+    let font = if Path::new(&config.text.font_path).is_file() {
+        format!(":fontfile='{}'", config.text.font_path)
+    } else {
+        String::new()
+    };
+    let title = title
+        .chars()
+        .filter(|c| !matches!(c, '\'' | '\\' | '%'))
+        .collect::<String>()
+        .replace(':', "\\:");
+    let time = sec_to_time(start % 86400.0)[..5].replace(':', "\\:");
+    let text_style = "fontcolor=white:x=(w-tw)/2";
+
+    let mut source_cmd: Vec<String> = vec_strings![
+        "-f",
+        "lavfi",
+        "-i",
+        format!(
+            "{source}:r={},drawtext=text='Up next'{font}:fontsize=h/20:fontcolor=gray:x=(w-tw)/2:y=h*0.32,drawtext=text='{title}'{font}:fontsize=h/12:{text_style}:y=h*0.42,drawtext=text='at {time}'{font}:fontsize=h/16:{text_style}:y=h*0.58,format=pix_fmts=yuv420p",
+            config.processing.fps
+        ),
+        "-f",
+        "lavfi",
+        "-i",
+        format!("aevalsrc=0:c=stereo:s=48000:d={duration}")
+    ];
+
+    add_vtt_dummy(config, &mut source_cmd);
+
+    (source, source_cmd)
+}
+
+fn add_vtt_dummy(config: &PlayoutConfig, source_cmd: &mut Vec<String>) {
     if config.processing.vtt_enable {
         let vtt_dummy = config
             .channel
@@ -812,8 +1453,6 @@ pub fn gen_dummy(config: &PlayoutConfig, duration: f64) -> (String, Vec<String>)
             error!("WebVTT enabled, but no vtt or dummy file found!");
         }
     }
-
-    (source, source_cmd)
 }
 
 // fn get_output_count(cmd: &[String]) -> i32 {
@@ -834,8 +1473,13 @@ pub fn gen_dummy(config: &PlayoutConfig, duration: f64) -> (String, Vec<String>)
 //     count
 // }
 
+/// Generated sources, which have no file and the audio comes from a second input.
+pub fn is_generated(source: &str) -> bool {
+    source.contains("color=c=") || source.starts_with(TEST_PATTERN) || source.starts_with("movie=")
+}
+
 pub fn is_remote(path: &str) -> bool {
-    Regex::new(r"^(https?|rtmps?|rts?p|udp|tcp|srt)://.*")
+    Regex::new(r"^(https?|rtmps?|rtsps?|rtp|udp|tcp|srt|ndi)://.*")
         .unwrap()
         .is_match(&path.to_lowercase())
 }
@@ -883,6 +1527,67 @@ pub fn include_file_extension(config: &PlayoutConfig, file_path: &Path) -> bool
     include
 }
 
+/// Match a file name against a pattern, `*` matches any text and `?` one character.
+pub fn name_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            // let the last star take one more character
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, n));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// File name matches one of the upload patterns (`storage.upload_ignore`),
+/// like `*.part` or `.*` for the temporary files of rsync.
+pub fn upload_ignored(config: &PlayoutConfig, file_path: &Path) -> bool {
+    let Some(name) = file_path.file_name().map(|n| n.to_string_lossy()) else {
+        return false;
+    };
+
+    config
+        .storage
+        .upload_ignore
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .any(|p| name_match(p, &name))
+}
+
+/// File looks like it is still uploaded: its name matches an upload pattern,
+/// or it was changed within the last `storage.upload_min_age` seconds.
+pub fn upload_pending(config: &PlayoutConfig, file_path: &Path) -> bool {
+    if upload_ignored(config, file_path) {
+        return true;
+    }
+
+    let min_age = Duration::from_secs(config.storage.upload_min_age as u64);
+
+    !min_age.is_zero()
+        && file_path
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|m| m.elapsed().ok())
+            .is_none_or(|age| age < min_age)
+}
+
 /// Read ffmpeg stderr decoder and encoder instance
 /// and log the output.
 pub async fn stderr_reader(
@@ -890,11 +1595,26 @@ pub async fn stderr_reader(
     ignore: Vec<String>,
     suffix: ProcessUnit,
     channel_id: i32,
+    meter: Option<Arc<Mutex<AudioMeter>>>,
+    stats: Option<Arc<Mutex<OutputStats>>>,
 ) -> Result<(), ServiceError> {
     let mut lines = buffer.lines();
     let mut debup = LogDedup::new(suffix, channel_id);
+    let mut progress = Progress::default();
 
     while let Some(line) = lines.next_line().await? {
+        if let Some(meter) = &meter
+            && meter.lock().await.update(&line)
+        {
+            continue;
+        }
+
+        if let Some(stats) = &stats
+            && progress.update(&line, &mut *stats.lock().await, Instant::now())
+        {
+            continue;
+        }
+
         if FFMPEG_IGNORE_ERRORS.iter().any(|i| line.contains(*i))
             || ignore.iter().any(|i| line.contains(i))
         {
@@ -909,8 +1629,71 @@ pub async fn stderr_reader(
     Ok(())
 }
 
+/// Add the thread limit from the channel config to an ffmpeg command.
+///
+/// `-filter_threads` is global, `-threads` is placed after the last input, so it counts for the encoder.
+pub fn add_thread_limit(config: &PlayoutConfig, cmd: &mut Vec<String>) {
+    let threads = config.general.threads;
+
+    if threads <= 0
+        || cmd
+            .iter()
+            .any(|a| a == "-threads" || a == "-filter_threads")
+    {
+        return;
+    }
+
+    let pos = cmd
+        .iter()
+        .rposition(|a| a == "-i")
+        .map_or(0, |i| (i + 2).min(cmd.len()));
+
+    cmd.splice(pos..pos, vec_strings!["-threads", threads]);
+    cmd.splice(0..0, vec_strings!["-filter_threads", threads]);
+}
+
+static FFMPEG_BIN: OnceLock<PathBuf> = OnceLock::new();
+static FFPROBE_BIN: OnceLock<PathBuf> = OnceLock::new();
+
+/// Set custom paths for the ffmpeg binaries, without them the binaries from PATH are used.
+pub fn set_binaries(ffmpeg: Option<PathBuf>, ffprobe: Option<PathBuf>) {
+    if let Some(path) = ffmpeg {
+        let _ = FFMPEG_BIN.set(path);
+    }
+
+    if let Some(path) = ffprobe {
+        let _ = FFPROBE_BIN.set(path);
+    }
+}
+
+pub fn ffmpeg_bin() -> &'static Path {
+    FFMPEG_BIN
+        .get()
+        .map_or(Path::new("ffmpeg"), PathBuf::as_path)
+}
+
+pub fn ffprobe_bin() -> &'static Path {
+    FFPROBE_BIN
+        .get()
+        .map_or(Path::new("ffprobe"), PathBuf::as_path)
+}
+
+/// ffplay is taken from the folder of a custom ffmpeg, when it exists there.
+pub fn ffplay_bin() -> PathBuf {
+    if let Some(ffplay) = FFMPEG_BIN
+        .get()
+        .and_then(|f| f.parent())
+        .map(|p| p.join("ffplay"))
+        .filter(|p| p.is_file())
+    {
+        return ffplay;
+    }
+
+    PathBuf::from("ffplay")
+}
+
 /// Run program to test if it is in system.
-async fn is_in_system(name: &str) -> Result<(), String> {
+async fn is_in_system(name: &Path) -> Result<(), String> {
     match Command::new(name)
         .stderr(Stdio::null())
         .stdout(Stdio::null())
@@ -921,12 +1704,59 @@ async fn is_in_system(name: &str) -> Result<(), String> {
                 return Err(format!("{e}"));
             };
         }
-        Err(e) => return Err(format!("{name} not found on system! {e}")),
+        Err(e) => return Err(format!("{} not found on system! {e}", name.display())),
     }
 
     Ok(())
 }
 
+async fn ffmpeg_stdout(args: &[&str]) -> Result<String, String> {
+    let output = Command::new(ffmpeg_bin())
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("couldn't spawn ffmpeg process: {e}"))?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Get version number from `ffmpeg -version` output.
+pub fn parse_ffmpeg_version(output: &str) -> Option<String> {
+    output
+        .lines()
+        .next()?
+        .strip_prefix("ffmpeg version ")?
+        .split_whitespace()
+        .next()
+        .map(str::to_string)
+}
+
+/// Get names from `ffmpeg -encoders` output, the list starts after the separator line.
+pub fn parse_ffmpeg_encoders(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|l| !l.trim_start().starts_with("------"))
+        .skip(1)
+        .filter_map(|l| l.split_whitespace().nth(1).map(str::to_string))
+        .collect()
+}
+
+/// Get input and output protocols from `ffmpeg -protocols` output.
+pub fn parse_ffmpeg_protocols(output: &str) -> Vec<String> {
+    let mut protocols = vec![];
+
+    for line in output.lines().skip_while(|l| l.trim() != "Input:") {
+        let name = line.trim();
+
+        if !name.is_empty() && !name.ends_with(':') && !protocols.iter().any(|p| p == name) {
+            protocols.push(name.to_string());
+        }
+    }
+
+    protocols
+}
+
 async fn ffmpeg_info(config: &mut PlayoutConfig) -> Result<(), String> {
     let id = config.general.channel_id;
     let ignore_flags = [
@@ -939,7 +1769,7 @@ async fn ffmpeg_info(config: &mut PlayoutConfig) -> Result<(), String> {
         "--enable-nonfree",
     ];
 
-    let mut ff_proc = match Command::new("ffmpeg")
+    let mut ff_proc = match Command::new(ffmpeg_bin())
         .args(["-filters"])
         .kill_on_drop(true)
         .stdout(Stdio::piped())
@@ -994,7 +1824,7 @@ async fn ffmpeg_info(config: &mut PlayoutConfig) -> Result<(), String> {
         error!(target: Target::file_mail(), channel = id; "{e}");
     };
 
-    let mut ff_proc = match Command::new("ffmpeg")
+    let mut ff_proc = match Command::new(ffmpeg_bin())
         .args(["-h", "long", "-hide_banner"])
         .kill_on_drop(true)
         .stdout(Stdio::piped())
@@ -1024,22 +1854,133 @@ async fn ffmpeg_info(config: &mut PlayoutConfig) -> Result<(), String> {
         error!(target: Target::file_mail(), channel = id; "{e}");
     };
 
+    config.general.ffmpeg_version =
+        parse_ffmpeg_version(&ffmpeg_stdout(&["-version"]).await?).unwrap_or_default();
+    config.general.ffmpeg_encoders =
+        parse_ffmpeg_encoders(&ffmpeg_stdout(&["-hide_banner", "-encoders"]).await?);
+    config.general.ffmpeg_protocols =
+        parse_ffmpeg_protocols(&ffmpeg_stdout(&["-hide_banner", "-protocols"]).await?);
+
     Ok(())
 }
 
+/// Collect encoders, filters and protocols, which the channel config needs and ffmpeg not has.
+///
+/// Lists which could not be detected are skipped.
+pub fn missing_features(config: &PlayoutConfig) -> Vec<String> {
+    let general = &config.general;
+    let mut missing = vec![];
+    let empty = vec![];
+    let output_cmd = config.output.output_cmd.as_ref().unwrap_or(&empty);
+    let decoder_cmd = config
+        .advanced
+        .decoder
+        .output_cmd
+        .as_ref()
+        .unwrap_or(&empty);
+    let ingest_cmd = config.ingest.input_cmd.as_ref().unwrap_or(&empty);
+
+    if !general.ffmpeg_encoders.is_empty() {
+        for cmd in [output_cmd, decoder_cmd] {
+            for pair in cmd.windows(2) {
+                let (key, codec) = (&pair[0], &pair[1]);
+
+                if (key == "-c"
+                    || key.starts_with("-c:")
+                    || key.starts_with("-codec")
+                    || ["-vcodec", "-acodec", "-scodec"].contains(&key.as_str()))
+                    && codec != "copy"
+                    && !general.ffmpeg_encoders.contains(codec)
+                    && !missing.contains(&format!("encoder: {codec}"))
+                {
+                    missing.push(format!("encoder: {codec}"));
+                }
+            }
+        }
+    }
+
+    if !general.ffmpeg_filters.is_empty() {
+        let mut filters = vec![];
+
+        if config.text.add_text {
+            filters.push("drawtext".to_string());
+        }
+
+        if config.processing.add_logo {
+            filters.extend(["movie".to_string(), "overlay".to_string()]);
+        }
+
+        if config.logging.detect_silence {
+            filters.push("silencedetect".to_string());
+        }
+
+        if config.playlist.recovery == RecoveryStrategy::SpeedUp {
+            filters.push("atempo".to_string());
+        }
+
+        if let Some(hw) = config.advanced.hw_accel() {
+            filters.push(match hw {
+                "cuda" => "scale_npp".to_string(),
+                _ => format!("scale_{hw}"),
+            });
+
+            if config.processing.add_logo {
+                filters.push(format!("overlay_{hw}"));
+            }
+        }
+
+        let re = Regex::new(r"(?:^|[,;\]])\s*([a-z0-9_]+)(?:@[\w]+)?=").unwrap();
+
+        for custom in [
+            &config.processing.custom_filter,
+            &config.ingest.custom_filter,
+        ] {
+            filters.extend(re.captures_iter(custom).map(|c| c[1].to_string()));
+        }
+
+        for filter in filters {
+            if !general.ffmpeg_filters.contains(&filter)
+                && !missing.contains(&format!("filter: {filter}"))
+            {
+                missing.push(format!("filter: {filter}"));
+            }
+        }
+    }
+
+    if !general.ffmpeg_protocols.is_empty() {
+        let re = Regex::new(r"^([a-z][a-z0-9+.-]*)://").unwrap();
+
+        for arg in output_cmd.iter().chain(ingest_cmd) {
+            if let Some(protocol) = re.captures(arg).map(|c| c[1].to_string())
+                && !general.ffmpeg_protocols.contains(&protocol)
+                && !missing.contains(&format!("protocol: {protocol}"))
+            {
+                missing.push(format!("protocol: {protocol}"));
+            }
+        }
+    }
+
+    missing
+}
+
 /// Validate ffmpeg/ffprobe/ffplay.
 ///
 /// Check if they are in system and has all libs and codecs we need.
 pub async fn validate_ffmpeg(config: &mut PlayoutConfig) -> Result<(), String> {
-    is_in_system("ffmpeg").await?;
-    is_in_system("ffprobe").await?;
+    is_in_system(ffmpeg_bin()).await?;
+    is_in_system(ffprobe_bin()).await?;
 
     if config.output.mode == Desktop {
-        is_in_system("ffplay").await?;
+        is_in_system(&ffplay_bin()).await?;
     }
 
     ffmpeg_info(config).await?;
 
+    debug!(target: Target::file_mail(), channel = config.general.channel_id;
+        "ffmpeg version: <span class=\"log-number\">{}</span>",
+        config.general.ffmpeg_version
+    );
+
     if config
         .output
         .output_cmd
@@ -1061,6 +2002,14 @@ pub async fn validate_ffmpeg(config: &mut PlayoutConfig) -> Result<(), String> {
         );
     }
 
+    if config.processing.live_control && !config.general.ffmpeg_libs.contains(&"libzmq".to_string())
+    {
+        return Err(
+            "ffmpeg contains no libzmq! Disable live_control in config or compile ffmpeg with libzmq."
+                .to_string(),
+        );
+    }
+
     if config
         .output
         .output_cmd
@@ -1075,6 +2024,16 @@ pub async fn validate_ffmpeg(config: &mut PlayoutConfig) -> Result<(), String> {
         return Err("ffmpeg contains no libfdk-aac!".to_string());
     }
 
+    let missing = missing_features(config);
+
+    if !missing.is_empty() {
+        return Err(format!(
+            "ffmpeg {} is missing required features: {}",
+            config.general.ffmpeg_version,
+            missing.join(", ")
+        ));
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,310 @@
+/// Output Statistics
+///
+/// Bytes, which go through the pipe into the encoder, and for stream outputs the bytes,
+/// which the encoder muxed into its output. The stream encoder reports them with
+/// `-progress pipe:2` in its stderr:
+///
+/// ```text
+/// total_size=1048576
+/// out_time_us=5000000
+/// progress=continue
+/// ```
+///
+/// The bitrates are taken per minute. A stall is a time of [`STALL_TIME`] without new
+/// bytes: the input stalls, when the playout delivers no data, the output stalls, when the
+/// encoder can't send it (a slow or lost connection). The values are in `/metrics`.
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::vec_strings;
+
+/// Time without new bytes, which counts as a stall.
+pub const STALL_TIME: Duration = Duration::from_secs(3);
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// Progress report of the encoder, every second.
+pub fn progress_param() -> Vec<String> {
+    vec_strings!["-progress", "pipe:2", "-stats_period", "1"]
+}
+
+/// Key and value of a progress line, `None` when it is no progress line.
+pub fn progress_line(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.trim().split_once('=')?;
+
+    (key.starts_with("stream_")
+        || [
+            "frame",
+            "fps",
+            "bitrate",
+            "total_size",
+            "out_time_us",
+            "out_time_ms",
+            "out_time",
+            "dup_frames",
+            "drop_frames",
+            "speed",
+            "progress",
+        ]
+        .contains(&key))
+    .then_some((key, value.trim()))
+}
+
+/// Bytes, bitrate and stalls of one side.
+#[derive(Clone, Debug)]
+struct Counter {
+    bytes: u64,
+    stalls: u64,
+    /// Bits per second of the last minute.
+    bitrate: f64,
+    changed: Instant,
+    stalled: bool,
+    minute: Instant,
+    minute_bytes: u64,
+}
+
+impl Counter {
+    fn new(now: Instant) -> Self {
+        Self {
+            bytes: 0,
+            stalls: 0,
+            bitrate: 0.0,
+            changed: now,
+            stalled: false,
+            minute: now,
+            minute_bytes: 0,
+        }
+    }
+
+    /// Start the timers again, the totals stay.
+    fn restart(&mut self, now: Instant) {
+        self.changed = now;
+        self.stalled = false;
+        self.minute = now;
+        self.minute_bytes = self.bytes;
+    }
+
+    fn add(&mut self, bytes: u64, now: Instant) {
+        self.tick(now);
+
+        if bytes > 0 {
+            self.bytes += bytes;
+            self.changed = now;
+            self.stalled = false;
+        }
+    }
+
+    fn tick(&mut self, now: Instant) {
+        if !self.stalled && now.saturating_duration_since(self.changed) >= STALL_TIME {
+            self.stalls += 1;
+            self.stalled = true;
+        }
+
+        let elapsed = now.saturating_duration_since(self.minute);
+
+        if elapsed >= MINUTE {
+            self.bitrate = (self.bytes - self.minute_bytes) as f64 * 8.0 / elapsed.as_secs_f64();
+            self.minute = now;
+            self.minute_bytes = self.bytes;
+        }
+    }
+
+    fn snapshot(&self) -> CounterSnapshot {
+        CounterSnapshot {
+            bytes: self.bytes,
+            bitrate: self.bitrate.round() as u64,
+            stalls: self.stalls,
+            stalled: self.stalled,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CounterSnapshot {
+    pub bytes: u64,
+    /// Bits per second of the last minute.
+    pub bitrate: u64,
+    pub stalls: u64,
+    pub stalled: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct StatsSnapshot {
+    pub input: CounterSnapshot,
+    /// Only for outputs with progress report.
+    pub output: Option<CounterSnapshot>,
+    pub connected: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct OutputStats {
+    input: Counter,
+    output: Option<Counter>,
+    ended: bool,
+}
+
+impl Default for OutputStats {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl OutputStats {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            input: Counter::new(now),
+            output: None,
+            ended: false,
+        }
+    }
+
+    /// Start of the encoder sink, the totals stay.
+    pub fn restart(&mut self, now: Instant) {
+        self.input.restart(now);
+
+        if let Some(output) = self.output.as_mut() {
+            output.restart(now);
+        }
+    }
+
+    /// Bytes, which got written to the encoder.
+    pub fn input(&mut self, bytes: u64, now: Instant) {
+        self.input.add(bytes, now);
+    }
+
+    /// Bytes, which the encoder muxed into its output.
+    pub fn output(&mut self, bytes: u64, now: Instant) {
+        self.ended = false;
+        self.output
+            .get_or_insert_with(|| Counter::new(now))
+            .add(bytes, now);
+    }
+
+    /// The encoder closed its output.
+    pub fn end(&mut self) {
+        self.ended = true;
+    }
+
+    /// Current values, stalls only count while the channel runs.
+    pub fn snapshot(&mut self, now: Instant, running: bool) -> StatsSnapshot {
+        if running {
+            self.input.tick(now);
+
+            if let Some(output) = self.output.as_mut() {
+                output.tick(now);
+            }
+        }
+
+        let output = self.output.as_ref().map(Counter::snapshot);
+
+        StatsSnapshot {
+            input: self.input.snapshot(),
+            connected: running && !self.ended && output.as_ref().is_some_and(|o| !o.stalled),
+            output,
+        }
+    }
+}
+
+/// Progress of one encoder process, its `total_size` counts from zero.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Progress {
+    total_size: u64,
+}
+
+impl Progress {
+    /// Read a line from ffmpeg, returns `false` when it is no progress line.
+    pub fn update(&mut self, line: &str, stats: &mut OutputStats, now: Instant) -> bool {
+        let Some((key, value)) = progress_line(line) else {
+            return false;
+        };
+
+        match key {
+            "total_size" => {
+                if let Ok(size) = value.parse::<u64>() {
+                    stats.output(size.saturating_sub(self.total_size), now);
+                    self.total_size = size;
+                }
+            }
+            "progress" if value == "end" => stats.end(),
+            _ => (),
+        }
+
+        true
+    }
+}
+
+/// Metrics of the channels in the Prometheus text format.
+pub fn prometheus(channels: &[(i32, StatsSnapshot, u64)]) -> String {
+    let mut metrics = vec![
+        (
+            "ffplayout_input_bytes_total",
+            "counter",
+            "Bytes written to the encoder.",
+        ),
+        (
+            "ffplayout_input_bitrate",
+            "gauge",
+            "Bits per second into the encoder, in the last minute.",
+        ),
+        (
+            "ffplayout_input_stalls_total",
+            "counter",
+            "Times without data for the encoder.",
+        ),
+        (
+            "ffplayout_output_bytes_total",
+            "counter",
+            "Bytes muxed into the stream output.",
+        ),
+        (
+            "ffplayout_output_bitrate",
+            "gauge",
+            "Bits per second of the stream output, in the last minute.",
+        ),
+        (
+            "ffplayout_output_stalls_total",
+            "counter",
+            "Times in which the stream output sent no data.",
+        ),
+        (
+            "ffplayout_output_connected",
+            "gauge",
+            "Stream output sends data.",
+        ),
+        (
+            "ffplayout_buffer_milliseconds",
+            "gauge",
+            "Fill level of the output buffer.",
+        ),
+    ]
+    .into_iter()
+    .map(|(name, kind, help)| {
+        (
+            name,
+            format!("# HELP {name} {help}\n# TYPE {name} {kind}\n"),
+        )
+    })
+    .collect::<Vec<_>>();
+
+    for (id, stats, buffer) in channels {
+        let mut values = vec![stats.input.bytes, stats.input.bitrate, stats.input.stalls];
+
+        if let Some(output) = &stats.output {
+            values.extend([
+                output.bytes,
+                output.bitrate,
+                output.stalls,
+                stats.connected as u64,
+            ]);
+        }
+
+        for ((name, text), value) in metrics.iter_mut().zip(values) {
+            text.push_str(&format!("{name}{{channel=\"{id}\"}} {value}\n"));
+        }
+
+        let (name, text) = metrics.last_mut().unwrap();
+        text.push_str(&format!("{name}{{channel=\"{id}\"}} {buffer}\n"));
+    }
+
+    metrics.into_iter().map(|(_, text)| text).collect()
+}
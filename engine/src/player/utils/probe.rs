@@ -1,8 +1,11 @@
+use std::collections::HashMap;
+
 use log::*;
 use serde::{Deserialize, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
 use tokio::process;
 
+use crate::file::backend::media_url;
 use crate::utils::errors::ProcessError;
 
 pub async fn ffprobe(path: impl AsRef<std::path::Path>) -> Result<FfProbe, FfProbeError> {
@@ -12,7 +15,7 @@ pub async fn ffprobe(path: impl AsRef<std::path::Path>) -> Result<FfProbe, FfPro
 pub async fn ffprobe_config(path: impl AsRef<std::path::Path>) -> Result<FfProbe, FfProbeError> {
     let path = path.as_ref();
 
-    let mut cmd = process::Command::new("ffprobe");
+    let mut cmd = process::Command::new(super::ffprobe_bin());
 
     cmd.args([
         "-v",
@@ -23,12 +26,15 @@ pub async fn ffprobe_config(path: impl AsRef<std::path::Path>) -> Result<FfProbe
         "json",
     ]);
 
-    cmd.arg(path);
+    cmd.arg(media_url(&path.to_string_lossy()));
 
     // Prevent CMD popup on Windows.
     #[cfg(target_os = "windows")]
     cmd.creation_flags(0x08000000);
 
+    // A probe, which runs into a timeout, gets dropped.
+    cmd.kill_on_drop(true);
+
     let out = cmd.output().await.map_err(FfProbeError::Io)?;
 
     if !out.status.success() {
@@ -91,6 +97,12 @@ pub struct Stream {
     pub height: Option<i64>,
     pub r_frame_rate: String,
     pub field_order: Option<String>,
+    pub pix_fmt: Option<String>,
+    pub color_space: Option<String>,
+    pub color_transfer: Option<String>,
+    pub color_primaries: Option<String>,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
 }
 
 #[serde_as]
@@ -103,6 +115,19 @@ pub struct Format {
     pub size: Option<i64>,
     #[serde_as(as = "Option<DisplayFromStr>")]
     pub bit_rate: Option<i64>,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+impl Format {
+    /// Value of a container tag, the tag names are not case sensitive.
+    pub fn tag(&self, name: &str) -> Option<String> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
@@ -110,6 +135,10 @@ pub struct MediaFormat {
     pub duration: Option<f64>,
     pub nb_streams: i64,
     pub size: i64,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub artist: Option<String>,
 }
 
 impl MediaFormat {
@@ -118,6 +147,8 @@ impl MediaFormat {
             duration: format.duration,
             nb_streams: format.nb_streams,
             size: format.size.unwrap_or_default(),
+            title: format.tag("title"),
+            artist: format.tag("artist"),
         }
     }
 }
@@ -128,11 +159,22 @@ pub struct AudioStream {
     pub codec_name: Option<String>,
     pub duration: Option<f64>,
     pub sample_rate: Option<i64>,
+    /// Language tag of the stream, like `deu`.
+    #[serde(default)]
+    pub language: Option<String>,
 }
 
 impl AudioStream {
     pub fn new(stream: Stream) -> Self {
+        let language = stream
+            .tags
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("language"))
+            .map(|(_, value)| value.trim().to_lowercase())
+            .filter(|value| !value.is_empty() && value != "und");
+
         Self {
+            language,
             channels: stream.channels.unwrap_or(2),
             codec_name: stream.codec_name,
             duration: stream.duration,
@@ -152,6 +194,14 @@ pub struct VideoStream {
     pub height: Option<i64>,
     pub frame_rate: String,
     pub field_order: Option<String>,
+    #[serde(default)]
+    pub pix_fmt: Option<String>,
+    #[serde(default)]
+    pub color_space: Option<String>,
+    #[serde(default)]
+    pub color_transfer: Option<String>,
+    #[serde(default)]
+    pub color_primaries: Option<String>,
 }
 
 impl VideoStream {
@@ -166,8 +216,26 @@ impl VideoStream {
             height: stream.height,
             frame_rate: stream.r_frame_rate,
             field_order: stream.field_order,
+            pix_fmt: stream.pix_fmt,
+            color_space: stream.color_space,
+            color_transfer: stream.color_transfer,
+            color_primaries: stream.color_primaries,
         }
     }
+
+    /// PQ (HDR10) or HLG transfer.
+    pub fn is_hdr(&self) -> bool {
+        matches!(
+            self.color_transfer.as_deref(),
+            Some("smpte2084" | "arib-std-b67")
+        )
+    }
+
+    /// Wide color gamut, with or without HDR transfer.
+    pub fn is_bt2020(&self) -> bool {
+        self.color_primaries.as_deref() == Some("bt2020")
+            || matches!(self.color_space.as_deref(), Some("bt2020nc" | "bt2020c"))
+    }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
@@ -0,0 +1,89 @@
+/// Programs
+///
+/// Playlist items in a row with the same `program_id` (or `block`) form one logical program,
+/// for example the segments of a show and the breaks between them. Log, status and the
+/// program API report them as one unit with aggregate timing:
+///
+/// ```json
+/// { "in": 0, "out": 1500.0, "duration": 1500.0, "source": "show_part1.mp4", "program_id": "news-2024-06-01" },
+/// { "in": 0, "out": 120.0, "duration": 120.0, "source": "ad.mp4", "category": "advertisement", "program_id": "news-2024-06-01" },
+/// { "in": 0, "out": 1380.0, "duration": 1380.0, "source": "show_part2.mp4", "program_id": "news-2024-06-01" }
+/// ```
+use serde::Serialize;
+
+use crate::player::utils::Media;
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct Program {
+    pub program_id: Option<String>,
+    pub title: Option<String>,
+    /// Index of the first item.
+    pub index: usize,
+    pub items: usize,
+    pub duration: f64,
+    /// Length of the advertisement items.
+    pub breaks: f64,
+}
+
+impl Program {
+    fn new(program_id: Option<String>, index: usize) -> Self {
+        Self {
+            program_id,
+            index,
+            ..Default::default()
+        }
+    }
+
+    fn add(&mut self, media: &Media) {
+        let length = media.length();
+
+        self.items += 1;
+        self.duration += length;
+
+        if media.category == "advertisement" {
+            self.breaks += length;
+        }
+
+        if self.title.is_none() {
+            self.title.clone_from(&media.title);
+        }
+    }
+}
+
+/// Group the list, items without `program_id` are a program on their own.
+pub fn programs(list: &[Media]) -> Vec<Program> {
+    let mut programs: Vec<Program> = vec![];
+
+    for (index, media) in list.iter().enumerate() {
+        match programs.last_mut() {
+            Some(last) if last.program_id.is_some() && last.program_id == media.program_id => {
+                last.add(media);
+            }
+            _ => {
+                let mut program = Program::new(media.program_id.clone(), index);
+                program.add(media);
+                programs.push(program);
+            }
+        }
+    }
+
+    programs
+}
+
+/// Program, which contains the item at `index`, when the item has a `program_id`.
+pub fn program_at(list: &[Media], index: usize) -> Option<Program> {
+    let program_id = list.get(index)?.program_id.as_ref()?;
+    let start = list[..index]
+        .iter()
+        .rposition(|m| m.program_id.as_ref() != Some(program_id))
+        .map_or(0, |i| i + 1);
+    let end = list[index..]
+        .iter()
+        .position(|m| m.program_id.as_ref() != Some(program_id))
+        .map_or(list.len(), |i| index + i);
+
+    let mut program = programs(&list[start..end]).pop()?;
+    program.index = start;
+
+    Some(program)
+}
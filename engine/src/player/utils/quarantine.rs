@@ -0,0 +1,65 @@
+/// Quarantine
+///
+/// A clip fails, when the decoder stops with an error or stalls. After `general.quarantine`
+/// failures in a row, the clip gets on the quarantine list and is replaced by filler
+/// for the rest of the day. The list is cleared when the date changes.
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::NaiveDate;
+
+#[derive(Clone, Debug, Default)]
+pub struct Quarantine {
+    date: Option<NaiveDate>,
+    failures: HashMap<String, u32>,
+    sources: BTreeSet<String>,
+}
+
+impl Quarantine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn check_date(&mut self, date: NaiveDate) {
+        if self.date != Some(date) {
+            self.date = Some(date);
+            self.failures.clear();
+            self.sources.clear();
+        }
+    }
+
+    /// Count a failure, returns `true` when the source gets quarantined with it.
+    pub fn failed(&mut self, source: &str, date: NaiveDate, limit: u32) -> bool {
+        self.check_date(date);
+
+        if limit == 0 || self.sources.contains(source) {
+            return false;
+        }
+
+        let count = self.failures.entry(source.to_string()).or_default();
+        *count += 1;
+
+        if *count >= limit {
+            self.failures.remove(source);
+            self.sources.insert(source.to_string());
+
+            return true;
+        }
+
+        false
+    }
+
+    /// Reset the failures from a source, after it was played without error.
+    pub fn succeeded(&mut self, source: &str) {
+        self.failures.remove(source);
+    }
+
+    pub fn contains(&mut self, source: &str, date: NaiveDate) -> bool {
+        self.check_date(date);
+
+        self.sources.contains(source)
+    }
+
+    pub fn sources(&self) -> Vec<String> {
+        self.sources.iter().cloned().collect()
+    }
+}
@@ -0,0 +1,133 @@
+/// Read-Ahead Validation
+///
+/// In the last `playlist.read_ahead` minutes of the day, the playlist of the next day gets
+/// validated, so missing files and other problems can be fixed before they go on air. The
+/// problems are sent as one alert, with one line per item, and they are in the status of
+/// the channel as `next_warnings`. A missing playlist is an alert too. When the playlist
+/// changes, it gets validated again.
+use std::{
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
+
+use chrono::{DateTime, Timelike};
+use chrono_tz::Tz;
+use log::*;
+use tokio::{sync::Mutex, time::sleep};
+
+use crate::player::{
+    controller::ChannelManager,
+    utils::{
+        json_serializer::{playlist_path, read_json},
+        json_validate::validate_playlist,
+        playlist_seconds_at, target_length,
+        warnings::{PlaylistWarnings, WarningKind},
+    },
+};
+use crate::utils::{config::PlayoutConfig, logging::Target, time_machine::time_now};
+
+const POLL: Duration = Duration::from_secs(60);
+
+/// Date of the next playlist, when `now` is in the read-ahead time of the current day.
+pub fn next_date(config: &PlayoutConfig, now: DateTime<Tz>) -> Option<String> {
+    let lead = config.playlist.read_ahead as f64 * 60.0;
+    let start = config.playlist.start_sec?;
+    let mut date = now.date_naive();
+
+    if (now.num_seconds_from_midnight() as f64) < start {
+        date = date.pred_opt()?;
+    }
+
+    let today = date.format("%Y-%m-%d").to_string();
+    let remaining = start + target_length(config, &today) - playlist_seconds_at(now, start);
+
+    if lead <= 0.0 || remaining <= 0.0 || remaining > lead {
+        return None;
+    }
+
+    date.succ_opt().map(|d| d.format("%Y-%m-%d").to_string())
+}
+
+/// Validate the playlist from `date`, `last` is the date and modification time of the last
+/// validation, the playlist is only validated again, when it changed.
+async fn validate(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    date: &str,
+    last: &mut Option<(String, Option<String>)>,
+) {
+    let id = config.general.channel_id;
+    let path = playlist_path(config, date).to_string_lossy().to_string();
+    let mut read_config = config.clone();
+    // validated here, not in the background
+    read_config.general.skip_validation = true;
+
+    let playlist = read_json(
+        &mut read_config,
+        Arc::new(Mutex::new(vec![])),
+        Some(path.clone()),
+        manager.is_alive.clone(),
+        manager.next_warnings.clone(),
+        false,
+        false,
+    )
+    .await;
+
+    let state = (date.to_string(), playlist.modified.clone());
+
+    if last.as_ref() == Some(&state) {
+        return;
+    }
+
+    *last = Some(state);
+
+    if playlist.path.is_none() {
+        let mut found = PlaylistWarnings::new(date);
+        found.push(
+            None,
+            config.playlist.start_sec.unwrap_or_default(),
+            &path,
+            WarningKind::Missing,
+            "Playlist doesn't exist",
+        );
+
+        error!(target: Target::file_mail(), channel = id;
+            "<span class=\"log-gray\">[Validation]</span> Playlist for the next day <span class=\"log-number\">{date}</span> doesn't exist yet: <span class=\"log-addr\">{path}</span>"
+        );
+
+        *manager.next_warnings.lock().await = found;
+
+        return;
+    }
+
+    info!(target: Target::file(), channel = id;
+        "<span class=\"log-gray\">[Validation]</span> Read ahead playlist for the next day <span class=\"log-number\">{date}</span>"
+    );
+
+    validate_playlist(
+        config.clone(),
+        Arc::new(Mutex::new(vec![])),
+        playlist,
+        manager.is_alive.clone(),
+        manager.next_warnings.clone(),
+    )
+    .await;
+}
+
+pub async fn run(manager: ChannelManager, config: PlayoutConfig) {
+    let mut last = None;
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        sleep(POLL).await;
+
+        match next_date(&config, time_now(&config.channel.timezone)) {
+            Some(date) => validate(&manager, &config, &date, &mut last).await,
+            None if last.is_some() => {
+                // the next day is the current day now
+                last = None;
+                *manager.next_warnings.lock().await = PlaylistWarnings::default();
+            }
+            None => (),
+        }
+    }
+}
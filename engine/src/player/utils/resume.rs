@@ -0,0 +1,108 @@
+/// Resume State
+///
+/// Values, which are not in the playlist, but are needed to continue the playout after
+/// a restart like before: the text overlay, live filter values and the position in the
+/// filler list. The state is saved on every change and removed, when the channel gets
+/// stopped on purpose, or the config is saved.
+use std::sync::atomic::Ordering;
+
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::db::handles;
+use crate::player::controller::ChannelManager;
+use crate::utils::{config::PlayoutConfig, logging::Target};
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ResumeState {
+    /// Parameters of the last text message (drawtext).
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub volume: Option<f64>,
+    #[serde(default)]
+    pub logo_opacity: Option<f64>,
+    /// Next filler in the list.
+    #[serde(default)]
+    pub filler_index: usize,
+}
+
+impl ResumeState {
+    /// State from JSON, an invalid state is empty.
+    pub fn from_json(json: &str) -> Self {
+        serde_json::from_str(json).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Take over the live filter values in the config.
+    pub fn apply(&self, config: &mut PlayoutConfig) {
+        if let Some(volume) = self.volume {
+            config.processing.volume = volume;
+        }
+
+        if let Some(opacity) = self.logo_opacity {
+            config.processing.logo_opacity = opacity;
+        }
+    }
+}
+
+pub async fn load(manager: &ChannelManager) -> ResumeState {
+    handles::select_channel_state(&manager.db_pool, manager.id)
+        .await
+        .ok()
+        .flatten()
+        .map(|s| ResumeState::from_json(&s))
+        .unwrap_or_default()
+}
+
+/// Change the saved state.
+pub async fn save(manager: &ChannelManager, change: impl FnOnce(&mut ResumeState)) {
+    let mut state = load(manager).await;
+    change(&mut state);
+
+    let result = match serde_json::to_string(&state) {
+        Ok(json) => handles::update_channel_state(&manager.db_pool, manager.id, &json)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    if let Err(e) = result {
+        error!(target: Target::file_mail(), channel = manager.id; "Resume state cannot be saved: {e}");
+    }
+}
+
+pub async fn clear(manager: &ChannelManager) {
+    if let Err(e) = handles::delete_channel_state(&manager.db_pool, manager.id).await {
+        error!(target: Target::file_mail(), channel = manager.id; "Resume state cannot be removed: {e}");
+    }
+}
+
+/// Restore the saved state on the start of the channel.
+pub async fn restore(manager: &ChannelManager) {
+    let state = load(manager).await;
+
+    if state.is_empty() {
+        return;
+    }
+
+    debug!(target: Target::file_mail(), channel = manager.id; "Restore state: {state:?}");
+
+    state.apply(&mut *manager.config.write().await);
+
+    if let (Some(text), Some(chain)) = (&state.text, &manager.filter_chain) {
+        *chain.lock().await = vec![text.clone()];
+    }
+
+    let fillers = manager.filler_list.lock().await.len();
+
+    if state.filler_index < fillers {
+        manager
+            .filler_index
+            .store(state.filler_index, Ordering::SeqCst);
+    }
+}
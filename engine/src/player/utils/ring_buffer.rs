@@ -0,0 +1,179 @@
+/// Ring Buffer
+///
+/// Bounded buffer between decoder and encoder. The encoder reads in real time, the decoder
+/// runs ahead until the buffer holds the target latency (`general.buffer_latency`), so
+/// short storage stalls are covered by the buffered data and don't starve the output.
+///
+/// The byte count for the target latency comes from the measured read rate of the encoder,
+/// until there is a rate, [`MIN_CAPACITY`] is used.
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Notify;
+
+use crate::utils::errors::ServiceError;
+
+pub const MIN_CAPACITY: usize = 1024 * 1024;
+pub const MAX_CAPACITY: usize = 256 * 1024 * 1024;
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Read rate in bytes per second, measured over windows of one second.
+#[derive(Clone, Debug)]
+pub struct ByteRate {
+    start: Instant,
+    bytes: usize,
+    rate: f64,
+}
+
+impl ByteRate {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            start: now,
+            bytes: 0,
+            rate: 0.0,
+        }
+    }
+
+    pub fn add(&mut self, bytes: usize, now: Instant) {
+        self.bytes += bytes;
+
+        let elapsed = now.duration_since(self.start);
+
+        if elapsed >= RATE_WINDOW {
+            let rate = self.bytes as f64 / elapsed.as_secs_f64();
+
+            // smooth the rate, bitrates of clips differ
+            self.rate = if self.rate > 0.0 {
+                (self.rate + rate) / 2.0
+            } else {
+                rate
+            };
+            self.start = now;
+            self.bytes = 0;
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    data: VecDeque<u8>,
+    rate: ByteRate,
+    closed: bool,
+}
+
+#[derive(Debug)]
+pub struct RingBuffer {
+    state: Mutex<State>,
+    readable: Notify,
+    writable: Notify,
+    target: Duration,
+}
+
+impl RingBuffer {
+    pub fn new(target: Duration) -> Self {
+        Self {
+            state: Mutex::new(State {
+                data: VecDeque::with_capacity(MIN_CAPACITY),
+                rate: ByteRate::new(Instant::now()),
+                closed: false,
+            }),
+            readable: Notify::new(),
+            writable: Notify::new(),
+            target,
+        }
+    }
+
+    fn capacity(&self, state: &State) -> usize {
+        ((state.rate.rate() * self.target.as_secs_f64()) as usize).clamp(MIN_CAPACITY, MAX_CAPACITY)
+    }
+
+    /// Append data, waits while the buffer is full.
+    pub async fn write(&self, data: &[u8]) -> Result<(), ServiceError> {
+        loop {
+            let writable = self.writable.notified();
+
+            {
+                let mut state = self.state.lock().unwrap();
+
+                if state.closed {
+                    return Err(ServiceError::Conflict(
+                        "Output buffer is closed!".to_string(),
+                    ));
+                }
+
+                if state.data.len() < self.capacity(&state) {
+                    state.data.extend(data);
+                    self.readable.notify_waiters();
+
+                    return Ok(());
+                }
+            }
+
+            writable.await;
+        }
+    }
+
+    /// Take up to `max` bytes, waits while the buffer is empty.
+    ///
+    /// Returns `None`, when the buffer is closed and empty.
+    pub async fn read(&self, max: usize) -> Option<Vec<u8>> {
+        loop {
+            let readable = self.readable.notified();
+
+            {
+                let mut state = self.state.lock().unwrap();
+
+                if !state.data.is_empty() {
+                    let num = max.min(state.data.len());
+                    let chunk = state.data.drain(..num).collect::<Vec<u8>>();
+                    state.rate.add(num, Instant::now());
+                    self.writable.notify_waiters();
+
+                    return Some(chunk);
+                }
+
+                if state.closed {
+                    return None;
+                }
+            }
+
+            readable.await;
+        }
+    }
+
+    /// No more data gets written, the rest can still be read.
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.readable.notify_waiters();
+        self.writable.notify_waiters();
+    }
+
+    /// Buffered bytes.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Fill level as play time, zero until the read rate is known.
+    pub fn latency(&self) -> Duration {
+        let state = self.state.lock().unwrap();
+        let rate = state.rate.rate();
+
+        if rate > 0.0 {
+            Duration::from_secs_f64(state.data.len() as f64 / rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
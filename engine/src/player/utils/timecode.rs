@@ -0,0 +1,150 @@
+/// SMPTE Timecode
+///
+/// The timecode follows the wall clock of the channel timezone, with the channel fps
+/// as nominal rate (non drop frame). It can be:
+///
+/// - burned into the picture, with the timecode option from `drawtext`
+/// - embedded in the output, with the `-timecode` option (GOP header for mpeg2video, tmcd track for mov/mxf)
+/// - generated as LTC on one channel of the first audio track
+///
+/// LTC is generated by `aevalsrc`: every sample calculates the position in the 80 bit frame
+/// and the biphase mark level. The frame words and the level at the start of a bit are cached
+/// in the expression variables, so the loops run only once per frame and per bit:
+///
+/// | var | content                                        |
+/// |-----|------------------------------------------------|
+/// | 0   | position in frames since midnight              |
+/// | 1   | frame number                                   |
+/// | 2   | bit index in LTC frame                         |
+/// | 3   | level at start of bit                          |
+/// | 4   | last bit (frame * 80 + bit)                    |
+/// | 5   | bits 0-31: frames and seconds                  |
+/// | 6   | bits 32-63: minutes and hours                  |
+/// | 7/8 | loop counter and sum                           |
+/// | 9   | last frame                                     |
+use chrono::{NaiveTime, Timelike};
+
+use crate::utils::config::PlayoutConfig;
+
+/// Sync word of bit 64 to 79, with bit 64 as LSB.
+const LTC_SYNC: u32 = 0b1011_1111_1111_1100;
+const LTC_AMPLITUDE: f64 = 0.5;
+const LTC_SAMPLE_RATE: u32 = 48000;
+
+/// Nominal timecode rate, 29.97 becomes 30, etc.
+pub fn timecode_rate(fps: f64) -> u32 {
+    (fps.round() as u32).max(1)
+}
+
+/// Timecode of the time, formatted as HH:MM:SS:FF.
+pub fn timecode(time: &NaiveTime, rate: u32) -> String {
+    let frames = time.nanosecond().min(999_999_999) as u64 * rate as u64 / 1_000_000_000;
+
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        time.hour(),
+        time.minute(),
+        time.second(),
+        frames
+    )
+}
+
+/// Bit `k` of the LTC frame, as 0 or 1.
+fn ltc_bit(k: &str) -> String {
+    format!(
+        "gt(if(lt({k},32),bitand(ld(5),pow(2,{k})),if(lt({k},64),bitand(ld(6),pow(2,{k}-32)),bitand({LTC_SYNC},pow(2,{k}-64)))),0)"
+    )
+}
+
+/// Two BCD digits, with the tens at bit 8 and the next two digits at bit 16 and 24.
+fn bcd_word(low: &str, high: &str) -> String {
+    format!("mod({low},10)+floor({low}/10)*256+mod({high},10)*65536+floor({high}/10)*16777216")
+}
+
+/// `aevalsrc` expression for LTC, which starts at `time`.
+pub fn ltc_expr(time: &NaiveTime, rate: u32) -> String {
+    let offset = time.num_seconds_from_midnight() as f64 + time.nanosecond() as f64 / 1e9;
+    // biphase mark correction bit: 59 for 25 fps, 27 for the other rates
+    let polarity = if rate == 25 { 6 } else { 5 };
+    let sum_bits = |end: &str| {
+        format!(
+            "st(7,0);st(8,0);while(lt(ld(7),{end}),st(8,ld(8)+{});st(7,ld(7)+1))",
+            ltc_bit("ld(7)")
+        )
+    };
+
+    [
+        format!("st(0,(t+{offset})*{rate})"),
+        "st(1,floor(ld(0)))".to_string(),
+        format!(
+            "if(not(eq(ld(1),ld(9))),st(9,ld(1));st(7,mod(ld(1),{rate}));st(8,mod(floor(ld(1)/{rate}),60));st(5,{});st(7,mod(floor(ld(1)/{}),60));st(8,mod(floor(ld(1)/{}),24));st(6,{});{};st({polarity},ld({polarity})+mod(ld(8),2)*134217728))",
+            bcd_word("ld(7)", "ld(8)"),
+            rate * 60,
+            rate * 3600,
+            bcd_word("ld(7)", "ld(8)"),
+            sum_bits("80"),
+        ),
+        "st(2,floor((ld(0)-ld(1))*80))".to_string(),
+        format!(
+            "if(not(eq(ld(1)*80+ld(2),ld(4))),st(4,ld(1)*80+ld(2));{};st(3,mod(ld(2)+ld(8),2)))",
+            sum_bits("ld(2)")
+        ),
+        format!(
+            "(2*mod(ld(3)+{}*gte(ld(0)*80-floor(ld(0)*80),0.5),2)-1)*{LTC_AMPLITUDE}",
+            ltc_bit("ld(2)")
+        ),
+    ]
+    .join(";")
+}
+
+/// Burn in the timecode, at the bottom of the picture.
+pub fn burn_in_filter(config: &PlayoutConfig, time: &NaiveTime) -> String {
+    let rate = timecode_rate(config.processing.fps);
+    let font = if std::path::Path::new(&config.text.font_path).is_file() {
+        format!(":fontfile='{}'", config.text.font_path)
+    } else {
+        String::new()
+    };
+
+    format!(
+        "drawtext=timecode='{}':timecode_rate={rate}{font}:fontsize=h/18:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=6:x=(w-tw)/2:y=h-th-h/18",
+        timecode(time, rate).replace(':', "\\:")
+    )
+}
+
+/// Channel layout for the program audio, before the LTC channel gets merged.
+fn channel_layout(channels: u8) -> String {
+    match channels {
+        1 => "mono".to_string(),
+        2 => "stereo".to_string(),
+        3 => "2.1".to_string(),
+        4 => "4.0".to_string(),
+        5 => "5.0".to_string(),
+        6 => "5.1".to_string(),
+        7 => "6.1".to_string(),
+        8 => "7.1".to_string(),
+        c => format!("{c}c"),
+    }
+}
+
+/// Replace channel `ltc_channel` (starting from 1) of the audio track with LTC.
+pub fn ltc_filter(config: &PlayoutConfig, time: &NaiveTime) -> String {
+    let channels = config.processing.audio_channels;
+    let ltc_channel = config.processing.ltc_channel.saturating_sub(1);
+    let pan = (0..channels)
+        .map(|c| {
+            if c == ltc_channel {
+                format!("c{c}=c{channels}")
+            } else {
+                format!("c{c}=c{c}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("|");
+
+    format!(
+        "aformat=sample_rates={LTC_SAMPLE_RATE}:channel_layouts={}[ltc_prg];aevalsrc='{}':s={LTC_SAMPLE_RATE}:c=mono[ltc];[ltc_prg][ltc]amerge=inputs=2,pan={channels}c|{pan}",
+        channel_layout(channels),
+        ltc_expr(time, timecode_rate(config.processing.fps))
+    )
+}
@@ -0,0 +1,182 @@
+/// Playlist Warnings
+///
+/// Problems, which the validation finds in a loaded playlist: missing and unreadable files,
+/// items which end after their file or don't fit into their slot, streams which don't fit
+/// to the output, decoder errors and a too short playlist. They are in the status of the
+/// channel until the next playlist is validated, and they are sent as one alert, instead
+/// of one mail per item.
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::player::utils::{probe::MediaProbe, sec_to_time};
+use crate::utils::config::PlayoutConfig;
+
+/// Difference in seconds, before an out point counts as after the end of the file.
+const SHORT_TOLERANCE: f64 = 1.2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    Missing,
+    Unreadable,
+    Short,
+    Slot,
+    Codec,
+    Decode,
+    Silent,
+    Live,
+    Subtitle,
+    Length,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PlaylistWarning {
+    /// Position in the playlist, starting with 1, none for the whole playlist.
+    pub position: Option<usize>,
+    /// Start time of the item.
+    pub begin: String,
+    pub source: String,
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PlaylistWarnings {
+    pub date: String,
+    pub warnings: Vec<PlaylistWarning>,
+}
+
+impl PlaylistWarnings {
+    pub fn new(date: &str) -> Self {
+        Self {
+            date: date.to_string(),
+            warnings: vec![],
+        }
+    }
+
+    pub fn push(
+        &mut self,
+        position: Option<usize>,
+        begin: f64,
+        source: &str,
+        kind: WarningKind,
+        message: impl Into<String>,
+    ) {
+        self.warnings.push(PlaylistWarning {
+            position,
+            begin: sec_to_time(begin),
+            source: source.to_string(),
+            kind,
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    /// Count of the warnings per kind, like `2 missing, 1 short`.
+    pub fn summary(&self) -> String {
+        let mut counts = BTreeMap::new();
+
+        for warning in &self.warnings {
+            *counts.entry(warning.kind).or_insert(0) += 1;
+        }
+
+        counts
+            .iter()
+            .map(|(kind, count)| {
+                let kind = serde_json::to_value(kind).unwrap_or_default();
+
+                format!("{count} {}", kind.as_str().unwrap_or_default())
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// One line per warning, like `003 10:15:00 movie.mp4: File not found`.
+    pub fn lines(&self) -> Vec<String> {
+        self.warnings
+            .iter()
+            .map(|w| {
+                let position = w.position.map_or("---".to_string(), |p| format!("{p:0>3}"));
+                let source = if w.source.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {}", w.source)
+                };
+
+                format!("{position} {}{source}: {}", w.begin, w.message)
+            })
+            .collect()
+    }
+}
+
+/// The out point of the item is after the end of its file.
+pub fn short_item(out: f64, probe: &MediaProbe) -> Option<String> {
+    let duration = probe.format.duration?;
+
+    (out > duration + SHORT_TOLERANCE).then(|| {
+        format!(
+            "Out point {} is after the end of the file {}",
+            sec_to_time(out),
+            sec_to_time(duration)
+        )
+    })
+}
+
+/// Streams of the file, which don't fit to the output.
+///
+/// The output needs a video stream, except for audio only. When video or audio is copied,
+/// the codec must be the same in all files, `reference` is the probe of the first file.
+pub fn stream_mismatch(
+    config: &PlayoutConfig,
+    probe: &MediaProbe,
+    reference: &mut Option<MediaProbe>,
+) -> Option<String> {
+    let processing = &config.processing;
+
+    if !processing.audio_only && probe.video.is_empty() {
+        return Some("File has no video stream".to_string());
+    }
+
+    let Some(first) = reference else {
+        *reference = Some(probe.clone());
+        return None;
+    };
+
+    let video = probe.video.first();
+    let first_video = first.video.first();
+
+    if processing.copy_video
+        && !processing.audio_only
+        && let (Some(v), Some(f)) = (video, first_video)
+        && (v.codec_name != f.codec_name || v.width != f.width || v.height != f.height)
+    {
+        return Some(format!(
+            "Video {} {}x{} differs from {} {}x{}, but video gets copied",
+            v.codec_name.as_deref().unwrap_or("unknown"),
+            v.width.unwrap_or_default(),
+            v.height.unwrap_or_default(),
+            f.codec_name.as_deref().unwrap_or("unknown"),
+            f.width.unwrap_or_default(),
+            f.height.unwrap_or_default(),
+        ));
+    }
+
+    if processing.copy_audio
+        && let (Some(a), Some(f)) = (probe.audio.first(), first.audio.first())
+        && (a.codec_name != f.codec_name || a.sample_rate != f.sample_rate)
+    {
+        return Some(format!(
+            "Audio {} {}Hz differs from {} {}Hz, but audio gets copied",
+            a.codec_name.as_deref().unwrap_or("unknown"),
+            a.sample_rate.unwrap_or_default(),
+            f.codec_name.as_deref().unwrap_or("unknown"),
+            f.sample_rate.unwrap_or_default(),
+        ));
+    }
+
+    None
+}
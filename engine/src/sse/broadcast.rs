@@ -125,6 +125,20 @@ impl Broadcaster {
                         failed_clients.push(index);
                     };
                 }
+                Endpoint::Transcode => {
+                    let jobs = client.manager.transcoder.jobs().await;
+
+                    if client
+                        .sender
+                        .send(
+                            sse::Data::new(serde_json::to_string(&jobs).unwrap_or_default()).into(),
+                        )
+                        .await
+                        .is_err()
+                    {
+                        failed_clients.push(index);
+                    };
+                }
             }
         }
 
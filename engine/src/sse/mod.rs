@@ -20,6 +20,7 @@ pub enum Endpoint {
     Playout,
     #[default]
     System,
+    Transcode,
 }
 
 impl FromStr for Endpoint {
@@ -29,6 +30,7 @@ impl FromStr for Endpoint {
         match input {
             "playout" => Ok(Self::Playout),
             "system" => Ok(Self::System),
+            "transcode" => Ok(Self::Transcode),
             _ => Err("Missing endpoint".to_string()),
         }
     }
@@ -39,6 +41,7 @@ impl fmt::Display for Endpoint {
         match *self {
             Self::Playout => write!(f, "playout"),
             Self::System => write!(f, "system"),
+            Self::Transcode => write!(f, "transcode"),
         }
     }
 }
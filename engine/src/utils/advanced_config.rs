@@ -1,14 +1,18 @@
 use std::path::Path;
 
+use log::*;
 use serde::{Deserialize, Serialize};
 use serde_with::{NoneAsEmptyString, serde_as};
 use shlex::split;
 use sqlx::{Pool, Sqlite};
-use tokio::io::AsyncReadExt;
 use ts_rs::TS;
 
 use crate::db::{handles, models::AdvancedConfiguration};
-use crate::utils::ServiceError;
+use crate::utils::{
+    ServiceError,
+    config::{from_toml, read_config_file},
+};
+use crate::vec_strings;
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, TS)]
 #[ts(export, export_to = "advanced_config.d.ts")]
@@ -130,6 +134,31 @@ pub struct FilterConfig {
     #[ts(type = "string")]
     #[serde_as(as = "NoneAsEmptyString")]
     pub split: Option<String>,
+    /// GPU backend for scale, pad and overlay: cuda, vaapi or qsv.
+    #[ts(type = "string")]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub hw_accel: Option<String>,
+}
+
+pub const HW_ACCEL: [&str; 3] = ["cuda", "vaapi", "qsv"];
+
+/// Initialize the GPU for filtering, when the decoder itself has no hardware context.
+fn hw_device(hw_accel: &Option<String>, input_param: &str) -> Vec<String> {
+    match hw_accel.as_deref().map(str::trim) {
+        Some(hw)
+            if HW_ACCEL.contains(&hw)
+                && !input_param.contains("-hw")
+                && !input_param.contains("_hw_device") =>
+        {
+            vec_strings![
+                "-init_hw_device",
+                format!("{hw}=gpu"),
+                "-filter_hw_device",
+                "gpu"
+            ]
+        }
+        _ => vec![],
+    }
 }
 
 impl AdvancedConfig {
@@ -141,9 +170,13 @@ impl AdvancedConfig {
                 input_param: config.decoder_input_param.clone(),
                 output_param: config.decoder_output_param.clone(),
                 input_cmd: match config.decoder_input_param {
-                    Some(input_param) => split(&input_param),
-                    None => None,
-                },
+                    Some(input_param) => split(&input_param).map(|mut cmd| {
+                        cmd.splice(0..0, hw_device(&config.filter_hw_accel, &input_param));
+                        cmd
+                    }),
+                    None => Some(hw_device(&config.filter_hw_accel, "")),
+                }
+                .filter(|cmd| !cmd.is_empty()),
                 output_cmd: match config.decoder_output_param {
                     Some(output_param) => split(&output_param),
                     None => None,
@@ -178,6 +211,7 @@ impl AdvancedConfig {
                 apad: config.filter_apad,
                 volume: config.filter_volume,
                 split: config.filter_split,
+                hw_accel: config.filter_hw_accel,
             },
             ingest: IngestConfig {
                 input_param: config.ingest_input_param.clone(),
@@ -215,6 +249,7 @@ impl AdvancedConfig {
                 "apad",
                 "volume",
                 "split",
+                "hw_accel",
             ];
 
             let toml_string = toml_edit::ser::to_string_pretty(&config)?;
@@ -276,6 +311,7 @@ impl AdvancedConfig {
                             "apad" => item.decor_mut().set_suffix(" # apad=whole_dur={}"),
                             "volume" => item.decor_mut().set_suffix(" # volume={}"),
                             "split" => item.decor_mut().set_suffix(" # split={}{}"),
+                            "hw_accel" => item.decor_mut().set_suffix(" # cuda, vaapi or qsv"),
                             _ => (),
                         }
                     }
@@ -296,11 +332,12 @@ impl AdvancedConfig {
 
     pub async fn import(pool: &Pool<Sqlite>, id: i32, path: &Path) -> Result<(), ServiceError> {
         if path.is_file() {
-            let mut file = tokio::fs::File::open(path).await?;
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).await?;
+            let contents = read_config_file(path).await?;
+            let (config, unknown) = from_toml::<Self>(&contents)?;
 
-            let config: Self = toml_edit::de::from_str(&contents).unwrap();
+            for key in unknown {
+                warn!("Unknown key '{key}' in {}, ignored", path.display());
+            }
 
             handles::update_advanced_configuration(pool, id, config).await?;
         } else {
@@ -310,6 +347,15 @@ impl AdvancedConfig {
         Ok(())
     }
 
+    /// GPU backend for the filter chain, only known backends are taken.
+    pub fn hw_accel(&self) -> Option<&str> {
+        self.filter
+            .hw_accel
+            .as_deref()
+            .map(str::trim)
+            .filter(|h| HW_ACCEL.contains(h))
+    }
+
     pub fn is_empty_filter(&self) -> bool {
         self.filter.aevalsrc.is_none()
             && self.filter.afade_in.is_none()
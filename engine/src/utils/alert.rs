@@ -0,0 +1,483 @@
+/// Emergency Alerts
+///
+/// Alerts come over the API, as JSON or as CAP message (Common Alerting Protocol, XML).
+/// The headline and description run as crawl over the picture, which needs the text
+/// overlay over zmq (text on, without text from filename). Optionally the audio of the
+/// program gets replaced by the attention tone and/or the alert message: the current clip
+/// continues from its position with the alert audio, afterwards the schedule continues
+/// at the current time.
+///
+/// A CAP message with `msgType` Cancel, or JSON with `"cancel": true`, ends the alert.
+use std::{
+    collections::VecDeque,
+    f64::consts::TAU,
+    path::Path,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use log::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use tokio::time::sleep;
+
+use crate::player::{
+    controller::{ChannelManager, ProcessUnit::*},
+    utils::{Media, is_remote, probe::MediaProbe},
+};
+use crate::utils::{
+    TextFilter, config::PlayoutConfig, control::reinit_text, errors::ServiceError, logging::Target,
+};
+use crate::vec_strings;
+
+/// Seconds of the crawl, when the alert has no duration.
+pub const ALERT_DURATION: f64 = 60.0;
+/// Length of the attention tone in seconds.
+pub const TONE_LENGTH: f64 = 8.0;
+/// Category of the alert audio items.
+const ALERT_CATEGORY: &str = "alert";
+/// Speed of the crawl in pixels per second.
+const CRAWL_SPEED: u32 = 120;
+const TONE_RATE: u32 = 48000;
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Alert {
+    #[serde(default)]
+    pub headline: String,
+    #[serde(default)]
+    pub description: String,
+    /// Seconds of the crawl.
+    #[serde(default)]
+    pub duration: Option<f64>,
+    /// Replace the audio with the attention tone first.
+    #[serde(default)]
+    pub tone: bool,
+    /// Audio file or URL with the alert message, which replaces the audio.
+    #[serde(default)]
+    pub audio: Option<String>,
+    /// End the running alert.
+    #[serde(default)]
+    pub cancel: bool,
+}
+
+/// Audio items of the alert, which the player takes before the next clip.
+#[derive(Debug, Default)]
+pub struct AlertState {
+    pub nodes: VecDeque<Media>,
+    /// Counts the alerts, only the end of the last one brings the text back.
+    serial: u64,
+    /// Text filter from before the alert.
+    text: Option<Vec<String>>,
+    /// Clip under the alert audio, with its position at the start of the audio.
+    clip: Option<(Media, f64, Instant)>,
+}
+
+/// Value of the first element with the tag, without namespace prefix.
+fn cap_value(xml: &str, tag: &str) -> Option<String> {
+    let re = Regex::new(&format!(
+        r"(?s)<(?:\w+:)?{tag}(?:\s[^>]*)?>(.*?)</(?:\w+:)?{tag}>"
+    ))
+    .ok()?;
+
+    re.captures(xml)
+        .map(|c| unescape(c[1].trim()))
+        .filter(|v| !v.is_empty())
+}
+
+fn unescape(text: &str) -> String {
+    if let Some(data) = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|t| t.strip_suffix("]]>"))
+    {
+        return data.trim().to_string();
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+impl Alert {
+    /// Alert from a CAP 1.2 message, only the first `info` block is used.
+    ///
+    /// The attention tone is on for the severity Extreme and Severe, the message is
+    /// the first `resource` with an audio type.
+    pub fn from_cap(xml: &str) -> Result<Self, ServiceError> {
+        if !xml.contains("alert") {
+            return Err(ServiceError::BadRequest("No CAP alert".to_string()));
+        }
+
+        let cancel = cap_value(xml, "msgType").is_some_and(|t| t == "Cancel");
+        let info = cap_value(xml, "info").unwrap_or_default();
+        let headline = cap_value(&info, "headline")
+            .or_else(|| cap_value(&info, "event"))
+            .unwrap_or_default();
+        let description = [
+            cap_value(&info, "description"),
+            cap_value(&info, "instruction"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+        let tone = cap_value(&info, "severity").is_some_and(|s| s == "Extreme" || s == "Severe");
+        let resources = Regex::new(r"(?s)<(?:\w+:)?resource>(.*?)</(?:\w+:)?resource>").unwrap();
+        let audio = resources
+            .captures_iter(&info)
+            .map(|c| c[1].to_string())
+            .find(|r| cap_value(r, "mimeType").is_some_and(|m| m.starts_with("audio/")))
+            .and_then(|r| cap_value(&r, "uri"));
+
+        if headline.is_empty() && !cancel {
+            return Err(ServiceError::BadRequest(
+                "CAP alert without headline".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            headline,
+            description,
+            duration: None,
+            tone,
+            audio,
+            cancel,
+        })
+    }
+
+    /// Alert from the request body, CAP when it is XML, else JSON.
+    pub fn parse(body: &str) -> Result<Self, ServiceError> {
+        if body.trim_start().starts_with('<') {
+            Self::from_cap(body)
+        } else {
+            Ok(serde_json::from_str(body)?)
+        }
+    }
+
+    /// Text of the crawl in one line.
+    pub fn crawl_text(&self) -> String {
+        let text = [self.headline.as_str(), self.description.as_str()]
+            .into_iter()
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join(" - ");
+
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// drawtext values of the crawl, which runs from right to left at the bottom.
+    pub fn crawl_filter(&self) -> TextFilter {
+        TextFilter {
+            text: Some(self.crawl_text()),
+            x: Some(format!("w-mod(t*{CRAWL_SPEED},w+tw)")),
+            y: Some("h-th-40".to_string()),
+            fontsize: Some("36".to_string()),
+            line_spacing: None,
+            fontcolor: Some("#ffffff".to_string()),
+            alpha: None,
+            r#box: Some("1".to_string()),
+            boxcolor: Some("#cc0000".to_string()),
+            boxborderw: Some("12".to_string()),
+        }
+    }
+}
+
+/// WAV with the attention tone: the sum of 853 Hz and 960 Hz, in 16 bit stereo.
+pub fn tone_wav(seconds: f64) -> Vec<u8> {
+    let samples = (seconds * TONE_RATE as f64) as u32;
+    let data_size = samples * 4;
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&TONE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(TONE_RATE * 4).to_le_bytes());
+    wav.extend_from_slice(&4u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+
+    for n in 0..samples {
+        let t = n as f64 / TONE_RATE as f64;
+        let value = 0.35 * ((TAU * 853.0 * t).sin() + (TAU * 960.0 * t).sin());
+        let sample = (value * i16::MAX as f64) as i16;
+
+        wav.extend_from_slice(&sample.to_le_bytes());
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    wav
+}
+
+/// Current clip from `position` on, with the audio of the alert.
+///
+/// The picture gets padded, when the clip ends before the alert audio.
+pub fn alert_node(current: &Media, position: f64, audio: &str, length: f64) -> Media {
+    let mut node = current.clone();
+    let remaining = (current.duration - position).max(0.0);
+
+    node.title = Some("Emergency Alert".to_string());
+    node.category = ALERT_CATEGORY.to_string();
+    node.seek = 0.0;
+    node.out = length;
+    node.duration = length;
+    node.audio = audio.to_string();
+    node.duration_audio = length;
+    node.speed = None;
+    node.slot = None;
+    node.blackout = vec![];
+    node.cmd = Some(vec_strings![
+        "-ss",
+        position,
+        "-i",
+        current.source,
+        "-i",
+        audio,
+        "-t",
+        length
+    ]);
+
+    if let Some(video) = node.probe.as_mut().and_then(|p| p.video.first_mut()) {
+        video.duration = Some(remaining);
+    }
+
+    node
+}
+
+/// Path of the alert audio, files are relative to the storage, URLs get downloaded.
+async fn audio_path(
+    config: &PlayoutConfig,
+    audio: &str,
+    index: usize,
+) -> Result<String, ServiceError> {
+    if is_remote(audio) {
+        let response = reqwest::get(audio)
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| ServiceError::BadRequest(format!("Alert audio {audio}: {e}")))?;
+        let data = response
+            .bytes()
+            .await
+            .map_err(|e| ServiceError::BadRequest(format!("Alert audio {audio}: {e}")))?;
+        let path = std::env::temp_dir().join(format!(
+            "ffplayout_alert_{}_{index}",
+            config.general.channel_id
+        ));
+
+        tokio::fs::write(&path, data).await?;
+
+        return Ok(path.to_string_lossy().to_string());
+    }
+
+    let path = config.channel.storage.join(audio);
+
+    if !path.is_file() {
+        return Err(ServiceError::BadRequest(format!(
+            "Alert audio {audio} not found"
+        )));
+    }
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Local clip, which is on air, and its position. Under the audio of an alert it is
+/// the clip, which the alert interrupted.
+async fn clip_on_air(manager: &ChannelManager) -> Option<(Media, f64)> {
+    let current = manager.current_media.lock().await.clone()?;
+
+    if current.category == ALERT_CATEGORY {
+        let (clip, position, since) = manager.alert.lock().await.clip.clone()?;
+
+        return Some((clip, position + since.elapsed().as_secs_f64()));
+    }
+
+    let position =
+        current.seek + manager.media_elapsed().as_secs_f64() * current.speed.unwrap_or(1.0);
+
+    (Path::new(&current.source).is_file() && current.probe.is_some()).then_some((current, position))
+}
+
+async fn audio_nodes(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    alert: &Alert,
+) -> Result<Vec<Media>, ServiceError> {
+    let mut sources = vec![];
+
+    if alert.tone {
+        let tone = std::env::temp_dir().join(format!("ffplayout_alert_{}_0.wav", manager.id));
+        tokio::fs::write(&tone, tone_wav(TONE_LENGTH)).await?;
+
+        sources.push((tone.to_string_lossy().to_string(), TONE_LENGTH));
+    }
+
+    if let Some(audio) = &alert.audio {
+        let path = audio_path(config, audio, 1).await?;
+        let length = MediaProbe::new(path.as_str())
+            .await
+            .ok()
+            .and_then(|p| p.format.duration)
+            .ok_or_else(|| ServiceError::BadRequest(format!("Alert audio {audio} not readable")))?;
+
+        sources.push((path, length));
+    }
+
+    if sources.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let Some((clip, mut position)) = clip_on_air(manager).await else {
+        warn!(target: Target::file_mail(), channel = manager.id; "Alert audio needs a local clip on air, only the crawl runs");
+        return Ok(vec![]);
+    };
+    let mut nodes = vec![];
+
+    manager.alert.lock().await.clip = Some((clip.clone(), position, Instant::now()));
+
+    for (audio, length) in sources {
+        let mut node = alert_node(&clip, position, &audio, length);
+        node.add_filter(config, &manager.filter_chain).await;
+
+        nodes.push(node);
+        position += length;
+    }
+
+    Ok(nodes)
+}
+
+/// End the alert: text from before back, queued audio away.
+async fn end(manager: &ChannelManager, config: &PlayoutConfig, serial: Option<u64>) {
+    let (text, audio) = {
+        let mut state = manager.alert.lock().await;
+
+        if serial.is_some_and(|s| s != state.serial) {
+            return;
+        }
+
+        let audio = !state.nodes.is_empty()
+            || manager
+                .current_media
+                .lock()
+                .await
+                .as_ref()
+                .is_some_and(|m| m.category == ALERT_CATEGORY);
+
+        state.nodes.clear();
+        state.clip = None;
+
+        (state.text.take(), audio)
+    };
+
+    if let Some(text) = text {
+        let filter = text
+            .first()
+            .cloned()
+            .unwrap_or_else(|| TextFilter::default().to_string());
+
+        if let Some(chain) = &manager.filter_chain {
+            *chain.lock().await = text;
+        }
+
+        reinit_text(manager, config, &filter).await;
+    }
+
+    if audio && serial.is_none() {
+        manager.list_init.store(true, Ordering::SeqCst);
+        manager.stop(Decoder).await;
+    }
+
+    info!(target: Target::file_mail(), channel = manager.id; "Emergency alert ended");
+}
+
+/// Put the alert on air, or end it.
+pub async fn start(
+    manager: ChannelManager,
+    alert: Alert,
+) -> Result<Map<String, Value>, ServiceError> {
+    let config = manager.config.read().await.clone();
+    let mut data_map = Map::new();
+
+    if alert.cancel {
+        end(&manager, &config, None).await;
+        data_map.insert("operation".to_string(), json!("alert_cancel"));
+
+        return Ok(data_map);
+    }
+
+    let nodes = audio_nodes(&manager, &config, &alert).await?;
+    let crawl = config.text.zmq_stream_socket.is_some();
+
+    if !crawl && nodes.is_empty() {
+        return Err(ServiceError::ServiceUnavailable(
+            "alert needs text overlay over zmq or alert audio!".to_string(),
+        ));
+    }
+
+    warn!(target: Target::file_mail(), channel = manager.id;
+        "Emergency alert: <span class=\"log-addr\">{}</span>", alert.headline
+    );
+
+    let serial = {
+        let mut state = manager.alert.lock().await;
+        state.serial += 1;
+
+        if crawl && state.text.is_none() {
+            state.text = Some(match &manager.filter_chain {
+                Some(chain) => chain.lock().await.clone(),
+                None => vec![],
+            });
+        }
+
+        if !nodes.is_empty() {
+            state.nodes = nodes.clone().into();
+        }
+
+        state.serial
+    };
+
+    if crawl {
+        let filter = alert.crawl_filter().to_string();
+
+        if let Some(chain) = &manager.filter_chain {
+            *chain.lock().await = vec![filter.clone()];
+        }
+
+        reinit_text(&manager, &config, &filter).await;
+    }
+
+    if !nodes.is_empty() {
+        // the schedule continues at the current time, after the alert audio
+        manager.list_init.store(true, Ordering::SeqCst);
+        manager.stop(Decoder).await;
+    }
+
+    let audio_length = nodes.iter().map(|n| n.out).sum::<f64>();
+    let duration = alert.duration.unwrap_or(ALERT_DURATION).max(audio_length);
+    let manager_end = manager.clone();
+
+    tokio::spawn(async move {
+        sleep(Duration::from_secs_f64(duration)).await;
+        end(&manager_end, &config, Some(serial)).await;
+    });
+
+    data_map.insert("operation".to_string(), json!("alert"));
+    data_map.insert("duration".to_string(), json!(duration));
+    data_map.insert("crawl".to_string(), json!(crawl));
+    data_map.insert(
+        "audio".to_string(),
+        json!(nodes.iter().map(|n| n.audio.clone()).collect::<Vec<_>>()),
+    );
+
+    Ok(data_map)
+}
+
+/// Next audio item of the alert, before the schedule.
+pub async fn next_node(manager: &ChannelManager) -> Option<Media> {
+    manager.alert.lock().await.nodes.pop_front()
+}
@@ -4,6 +4,7 @@ use std::path::{Path, PathBuf};
 use std::os::unix::fs::MetadataExt;
 
 use clap::Parser;
+use flexi_logger::Level;
 use inquire::{Confirm, CustomType, Password, PasswordDisplayMode, Text};
 use sqlx::{Pool, Sqlite};
 
@@ -15,9 +16,12 @@ use crate::db::{
     handles,
     models::{Channel, User},
 };
+use crate::file::norm_abs_path;
 use crate::utils::{
     advanced_config::AdvancedConfig,
-    config::{OutputMode, PlayoutConfig},
+    config::{
+        Output, OutputMode, PlayoutConfig, check_config, fixed_path, from_toml, read_config_file,
+    },
 };
 
 use super::errors::ProcessError;
@@ -84,6 +88,21 @@ pub struct Args {
     #[clap(long, env, help_heading = Some("General"), help = "Path to database file")]
     pub db: Option<PathBuf>,
 
+    #[clap(long, env, help_heading = Some("General"), help = "Path to ffmpeg binary")]
+    pub ffmpeg: Option<PathBuf>,
+
+    #[clap(long, env, help_heading = Some("General"), help = "Path to ffprobe binary")]
+    pub ffprobe: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help_heading = Some("General"),
+        num_args = 0..=1,
+        value_name = "FILE",
+        help = "Check channel configuration, or the given config file, without starting playout"
+    )]
+    pub check_config: Option<Option<PathBuf>>,
+
     #[clap(
         long,
         help_heading = Some("General"),
@@ -114,6 +133,13 @@ pub struct Args {
     #[clap(long, help_heading = Some("General"), help = "List available channel ids")]
     pub list_channels: bool,
 
+    #[clap(
+        long,
+        help_heading = Some("General"),
+        help = "Check GitHub for a newer release and exit"
+    )]
+    pub check_update: bool,
+
     #[clap(short, env, long, help_heading = Some("General"), help = "Listen on IP:PORT, like: 127.0.0.1:8787")]
     pub listen: Option<String>,
 
@@ -128,12 +154,97 @@ pub struct Args {
     #[clap(long, env, help_heading = Some("General"), help = "Log to console")]
     pub log_to_console: bool,
 
+    #[clap(
+        long,
+        env = "FFPLAYOUT_CONTAINER",
+        help_heading = Some("General"),
+        help = "Container mode: log only to stdout and stop channels graceful on SIGTERM"
+    )]
+    pub container: bool,
+
+    #[clap(
+        long,
+        env,
+        default_value_t = 10,
+        help_heading = Some("General"),
+        help = "Seconds to wait for the encoder on shutdown, before it gets killed"
+    )]
+    pub shutdown_timeout: u64,
+
+    #[clap(
+        long,
+        env = "FFPLAYOUT_DRAIN",
+        help_heading = Some("General"),
+        help = "On SIGTERM, let the current clip or program block finish before stopping"
+    )]
+    pub drain: bool,
+
+    #[clap(
+        long,
+        env,
+        default_value_t = 10,
+        help_heading = Some("General"),
+        help = "Seconds without output, after which a channel is not ready (/readyz)"
+    )]
+    pub stall_timeout: u64,
+
     #[clap(long, env, help_heading = Some("General"), help = "Keep log file for given days")]
     pub log_backup_count: Option<usize>,
 
     #[clap(long, env, help_heading = Some("General"), help = "Add timestamp to log line")]
     pub log_timestamp: bool,
 
+    #[clap(
+        long,
+        env,
+        help_heading = Some("General"),
+        help = "Send tracing spans to OTLP/HTTP endpoint, like: http://127.0.0.1:4318/v1/traces"
+    )]
+    pub otlp_endpoint: Option<String>,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("General"),
+        help = "Send errors and panics to a Sentry DSN, or as JSON to a HTTP endpoint"
+    )]
+    pub error_report: Option<String>,
+
+    #[clap(
+        long,
+        env,
+        default_value_t = 1.0,
+        help_heading = Some("General"),
+        help = "Part of the errors, which are reported, from 0.0 to 1.0"
+    )]
+    pub error_sample_rate: f64,
+
+    #[clap(
+        long,
+        env,
+        default_value_t = 0,
+        help_heading = Some("General"),
+        help = "Check every given hours for a new release, 0 disables the check"
+    )]
+    pub update_interval: u64,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("General"),
+        help = "Run command for a new release, {version} is replaced with the version"
+    )]
+    pub update_command: Option<String>,
+
+    #[clap(
+        long = "override",
+        help_heading = Some("General"),
+        value_name = "KEY=VALUE",
+        num_args = 1..,
+        help = "Override config keys, like: output.mode=hls. Also possible with env variables, like: FFPLAYOUT__OUTPUT__MODE=hls"
+    )]
+    pub overrides: Option<Vec<String>>,
+
     #[clap(
         short,
         long,
@@ -171,6 +282,14 @@ pub struct Args {
     #[clap(long, help_heading = Some("Playlist"), help = "Only validate given playlist")]
     pub validate: bool,
 
+    #[clap(
+        long,
+        help_heading = Some("Playlist"),
+        help = "Compare as-run log with the playlist of date, like: 2022-01-01",
+        value_name = "YYYY-MM-DD"
+    )]
+    pub as_run_report: Option<String>,
+
     #[clap(long, env, help_heading = Some("Playout"), help = "Run playout without webserver and frontend")]
     pub foreground: bool,
 
@@ -264,6 +383,8 @@ pub async fn init_args(pool: &Pool<Sqlite>) -> Result<bool, ProcessError> {
                 .prompt()?;
         }
 
+        fixed_path("Logging", &global.logs)?;
+
         if let Some(p) = args.public {
             global.public = p;
         } else {
@@ -333,6 +454,14 @@ pub async fn init_args(pool: &Pool<Sqlite>) -> Result<bool, ProcessError> {
                 .prompt()?;
         }
 
+        if let Some(path) = &args.ffmpeg {
+            global.ffmpeg = path.to_string_lossy().to_string();
+        }
+
+        if let Some(path) = &args.ffprobe {
+            global.ffprobe = path.to_string_lossy().to_string();
+        }
+
         handles::update_global(pool, global.clone()).await?;
 
         let mut channel = handles::select_channel(pool, &1).await?;
@@ -445,6 +574,26 @@ pub async fn init_args(pool: &Pool<Sqlite>) -> Result<bool, ProcessError> {
         }
     }
 
+    if let Some(file) = &ARGS.check_config {
+        if let Some(channel) = &ARGS.channel {
+            let mut failed = false;
+
+            for id in channel {
+                failed |= check_channel_config(pool, *id, file.as_deref()).await?;
+            }
+
+            if failed {
+                return Err(ProcessError::Custom("Config check failed".to_string()));
+            }
+
+            println!("Config check done...");
+        } else {
+            return Err(ProcessError::Custom(
+                "Channel ID(s) needed! Use `--channel 1 ...`".to_string(),
+            ));
+        }
+    }
+
     if let Some(path) = &ARGS.import_config {
         if let Some(channel) = &ARGS.channel {
             for id in channel {
@@ -463,6 +612,64 @@ pub async fn init_args(pool: &Pool<Sqlite>) -> Result<bool, ProcessError> {
     Ok(init)
 }
 
+/// Print the config findings from a channel, returns true when errors are found.
+///
+/// With a file, its settings are checked against the paths and outputs of the channel.
+async fn check_channel_config(
+    pool: &Pool<Sqlite>,
+    id: i32,
+    file: Option<&Path>,
+) -> Result<bool, ProcessError> {
+    let mut config = PlayoutConfig::new(pool, id, None).await?;
+
+    if let Some(path) = file {
+        let contents = read_config_file(path).await?;
+
+        match from_toml::<PlayoutConfig>(&contents) {
+            Ok((file_config, unknown)) => {
+                for key in unknown {
+                    println!(
+                        "[WARN] Channel {id}: unknown key '{key}' in {}",
+                        path.display()
+                    );
+                }
+
+                let storage = config.channel.storage.clone();
+
+                config = PlayoutConfig {
+                    output: Output {
+                        output_cmd: shlex::split(&file_config.output.output_param),
+                        ..file_config.output
+                    },
+                    channel: config.channel,
+                    advanced: config.advanced,
+                    ..file_config
+                };
+
+                config.storage.filler_path = norm_abs_path(&storage, &config.storage.filler)?.0;
+                config.storage.fallback_clip_path =
+                    norm_abs_path(&storage, &config.storage.fallback_clip)?.0;
+                config.text.font_path = norm_abs_path(&storage, &config.text.font)?
+                    .0
+                    .to_string_lossy()
+                    .to_string();
+            }
+            Err(e) => {
+                println!("[ERROR] Channel {id}: {e}");
+                return Ok(true);
+            }
+        }
+    }
+
+    let findings = check_config(&config);
+
+    for (level, msg) in &findings {
+        println!("[{}] Channel {id}: {msg}", level.as_str().to_uppercase());
+    }
+
+    Ok(findings.iter().any(|(level, _)| *level == Level::Error))
+}
+
 #[cfg(target_family = "unix")]
 async fn update_permissions() {
     use crate::db::DB_PATH;
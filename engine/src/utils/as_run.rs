@@ -0,0 +1,265 @@
+/// As-run Log and Report
+///
+/// Every aired clip is appended as JSON line to `as_run_{channel}_{date}.jsonl` in the log folder,
+/// the date is the playlist date. The report compares the log with the scheduled playlist
+/// of a day and marks the items, which aired late, short or not at all.
+use std::{io::ErrorKind, path::PathBuf};
+
+use chrono::{DateTime, NaiveDate, Timelike};
+use chrono_tz::Tz;
+use log::*;
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::player::{
+    controller::ChannelManager,
+    utils::{Media, sec_to_time},
+};
+use crate::utils::{
+    config::PlayoutConfig, errors::ServiceError, library, logging::Target, playlist::read_playlist,
+    statistics,
+};
+
+/// Seconds, which an item can be late or short, before it gets marked.
+pub const REPORT_TOLERANCE: f64 = 2.0;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AsRunItem {
+    pub index: Option<usize>,
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program_id: Option<String>,
+    pub aired: String,
+    /// Air time in seconds from midnight of the playlist date.
+    pub begin: f64,
+    /// Played length in seconds.
+    pub duration: f64,
+    #[serde(default)]
+    pub error: bool,
+}
+
+impl AsRunItem {
+    pub fn new(date: &str, node: &Media, aired: &DateTime<Tz>, duration: f64, error: bool) -> Self {
+        let mut begin =
+            aired.num_seconds_from_midnight() as f64 + aired.nanosecond() as f64 / 1_000_000_000.0;
+
+        // clips after midnight belong to the playlist of the day before
+        if aired.format("%Y-%m-%d").to_string().as_str() > date {
+            begin += 86400.0;
+        }
+
+        Self {
+            index: node.index,
+            source: node.source.clone(),
+            title: node.title.clone(),
+            program_id: node.program_id.clone(),
+            aired: aired.format("%Y-%m-%d %H:%M:%S%.3f%:z").to_string(),
+            begin: (begin * 1000.0).round() / 1000.0,
+            duration: (duration * 1000.0).round() / 1000.0,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AsRunStatus {
+    Ok,
+    Late,
+    Short,
+    Missed,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ReportItem {
+    pub index: usize,
+    pub source: String,
+    pub title: Option<String>,
+    pub program_id: Option<String>,
+    pub scheduled: f64,
+    pub length: f64,
+    pub aired: Option<f64>,
+    pub duration: f64,
+    pub delay: Option<f64>,
+    pub status: AsRunStatus,
+}
+
+pub fn as_run_path(config: &PlayoutConfig, date: &str) -> PathBuf {
+    config
+        .channel
+        .logs
+        .join(format!("as_run_{}_{date}.jsonl", config.general.channel_id))
+}
+
+/// Append an aired item to the log of the given playlist date.
+pub async fn write(
+    config: &PlayoutConfig,
+    date: &str,
+    item: &AsRunItem,
+) -> Result<(), std::io::Error> {
+    let mut line = serde_json::to_string(item)?;
+    line.push('\n');
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(as_run_path(config, date))
+        .await?;
+
+    file.write_all(line.as_bytes()).await
+}
+
+/// Log an aired clip, outside of playlist mode the date is the air date.
+pub async fn log(
+    manager: ChannelManager,
+    node: Media,
+    aired: DateTime<Tz>,
+    duration: f64,
+    error: bool,
+) {
+    let config = manager.config.read().await.clone();
+    let mut date = manager.current_date.lock().await.clone();
+
+    if date.is_empty() {
+        date = aired.format("%Y-%m-%d").to_string();
+    }
+
+    let item = AsRunItem::new(&date, &node, &aired, duration, error);
+
+    if let Err(e) = write(&config, &date, &item).await {
+        error!(target: Target::file_mail(), channel = config.general.channel_id; "Couldn't write as-run log: {e}");
+    }
+
+    let category = statistics::category(&config, &node);
+
+    statistics::add(&manager.db_pool, &config, &date, &category, duration, error).await;
+
+    if !error {
+        let aired = aired.format(library::AIRED_FORMAT).to_string();
+
+        library::aired(&manager.db_pool, &config, &node.source, &aired).await;
+    }
+}
+
+/// Read the log of a playlist date, a missing log is an empty one.
+pub async fn read(config: &PlayoutConfig, date: &str) -> Result<Vec<AsRunItem>, std::io::Error> {
+    let content = match fs::read_to_string(as_run_path(config, date)).await {
+        Ok(c) => c,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    Ok(content
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect())
+}
+
+/// Compare the scheduled items with the as-run log.
+///
+/// Log entries are matched in order by source, entries for the same item in a row
+/// (for example after a restart of the playout) count as one airing.
+pub fn compare(
+    start_sec: f64,
+    scheduled: &[Media],
+    as_run: &[AsRunItem],
+    tolerance: f64,
+) -> Vec<ReportItem> {
+    let mut report = vec![];
+    let mut begin = start_sec;
+    let mut cursor = 0;
+
+    for (index, media) in scheduled.iter().enumerate() {
+        let length = media.length();
+        let mut aired = None;
+        let mut duration = 0.0;
+
+        if let Some(pos) = as_run[cursor..]
+            .iter()
+            .position(|a| a.source == media.source)
+        {
+            let first = &as_run[cursor + pos];
+            let entries = as_run[cursor + pos..]
+                .iter()
+                .take_while(|a| a.source == first.source && a.index == first.index)
+                .collect::<Vec<_>>();
+
+            aired = Some(first.begin);
+            duration = entries.iter().map(|a| a.duration).sum();
+            cursor += pos + entries.len();
+        }
+
+        let delay = aired.map(|a| ((a - begin) * 1000.0).round() / 1000.0);
+        let status = if aired.is_none() {
+            AsRunStatus::Missed
+        } else if duration < length - tolerance {
+            AsRunStatus::Short
+        } else if delay.is_some_and(|d| d > tolerance) {
+            AsRunStatus::Late
+        } else {
+            AsRunStatus::Ok
+        };
+
+        report.push(ReportItem {
+            index,
+            source: media.source.clone(),
+            title: media.title.clone(),
+            program_id: media.program_id.clone(),
+            scheduled: begin,
+            length,
+            aired,
+            duration,
+            delay,
+            status,
+        });
+
+        begin += length;
+    }
+
+    report
+}
+
+/// Report for the playlist date.
+pub async fn report(config: &PlayoutConfig, date: &str) -> Result<Vec<ReportItem>, ServiceError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+
+    let playlist = read_playlist(config, date.to_string()).await?;
+    let as_run = read(config, date).await?;
+
+    Ok(compare(
+        config.playlist.start_sec.unwrap_or_default(),
+        &playlist.program,
+        &as_run,
+        REPORT_TOLERANCE,
+    ))
+}
+
+/// Plain text table of the report, for the command line.
+pub fn report_table(report: &[ReportItem]) -> String {
+    let mut table = format!(
+        "{:<12} {:<12} {:>8} {:<12} {:<12} {:<6} Source\n",
+        "Scheduled", "Aired", "Delay", "Length", "Played", "Status"
+    );
+
+    for item in report {
+        let status = serde_json::to_value(item.status)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+
+        table.push_str(&format!(
+            "{:<12} {:<12} {:>8} {:<12} {:<12} {:<6} {}\n",
+            sec_to_time(item.scheduled),
+            item.aired.map(sec_to_time).unwrap_or_default(),
+            item.delay.map(|d| format!("{d:.1}")).unwrap_or_default(),
+            sec_to_time(item.length),
+            sec_to_time(item.duration),
+            status,
+            item.source
+        ));
+    }
+
+    table
+}
@@ -0,0 +1,216 @@
+/// Playout Calendar
+///
+/// A calendar describes with recurring rules, which template is used on which day.
+/// From it the daily playlists get generated, when they not exist.
+///
+/// The calendar is read from the file `calendar.json` in the playlist folder:
+///
+/// ```JSON
+/// {
+///     "rules": [
+///         {
+///             "weekdays": ["Mon", "Tue", "Wed", "Thu", "Fri"],
+///             "template": { "sources": [...] }
+///         },
+///         {
+///             "weekdays": ["Sat"],
+///             "every_weeks": 2,
+///             "from": "2025-01-04",
+///             "template": { "sources": [...] }
+///         }
+///     ],
+///     "dates": [
+///         {
+///             "date": "2025-12-25",
+///             "template": { "sources": [...] }
+///         }
+///     ]
+/// }
+/// ```
+///
+/// Special dates override the rules, from the rules the first matching one is taken.
+use std::{io::Error, path::PathBuf};
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use log::*;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::file::norm_abs_path;
+use crate::player::controller::ChannelManager;
+use crate::utils::{
+    config::{PlayoutConfig, Template},
+    generator::playlist_from_template,
+    logging::Target,
+};
+
+pub const CALENDAR_FILE: &str = "calendar.json";
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Calendar {
+    #[serde(default)]
+    pub rules: Vec<CalendarRule>,
+    #[serde(default)]
+    pub dates: Vec<CalendarDate>,
+}
+
+/// Recurring rule, without weekdays and monthdays it matches every day.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct CalendarRule {
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+    #[serde(default)]
+    pub monthdays: Vec<u32>,
+    #[serde(default)]
+    pub every_weeks: Option<u32>,
+    #[serde(default)]
+    pub from: Option<NaiveDate>,
+    #[serde(default)]
+    pub until: Option<NaiveDate>,
+    pub template: Template,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct CalendarDate {
+    pub date: NaiveDate,
+    pub template: Template,
+}
+
+impl CalendarRule {
+    pub fn matches(&self, date: NaiveDate) -> bool {
+        if self.from.is_some_and(|f| date < f) || self.until.is_some_and(|u| date > u) {
+            return false;
+        }
+
+        if !self.weekdays.is_empty() && !self.weekdays.contains(&date.weekday()) {
+            return false;
+        }
+
+        if !self.monthdays.is_empty() && !self.monthdays.contains(&date.day()) {
+            return false;
+        }
+
+        if let Some(every) = self.every_weeks.filter(|e| *e > 1) {
+            // count the weeks from the start date, or from the first monday of the unix epoch
+            let start = self
+                .from
+                .unwrap_or(NaiveDate::from_ymd_opt(1970, 1, 5).unwrap());
+            let weeks = start.week(Weekday::Mon).first_day();
+            let passed = (date - weeks).num_days().div_euclid(7);
+
+            return passed.rem_euclid(i64::from(every)) == 0;
+        }
+
+        true
+    }
+}
+
+impl Calendar {
+    pub async fn load(config: &PlayoutConfig) -> Option<Self> {
+        let path = calendar_path(config);
+
+        if !path.is_file() {
+            return None;
+        }
+
+        match fs::read(&path).await {
+            Ok(content) => match serde_json::from_slice(&content) {
+                Ok(calendar) => Some(calendar),
+                Err(e) => {
+                    error!(target: Target::file_mail(), channel = config.general.channel_id;
+                        "Calendar <span class=\"log-addr\">{}</span> not readable: {e}",
+                        path.display()
+                    );
+                    None
+                }
+            },
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = config.general.channel_id; "{e}");
+                None
+            }
+        }
+    }
+
+    /// Get template for the given date, special dates have priority over rules.
+    pub fn template_for(&self, date: &str) -> Option<Template> {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+
+        if let Some(special) = self.dates.iter().find(|d| d.date == date) {
+            return Some(special.template.clone());
+        }
+
+        self.rules
+            .iter()
+            .find(|r| r.matches(date))
+            .map(|r| r.template.clone())
+    }
+}
+
+pub fn calendar_path(config: &PlayoutConfig) -> PathBuf {
+    config.channel.playlists.join(CALENDAR_FILE)
+}
+
+/// Make template paths absolute and restrict them to the storage folder.
+pub fn resolve_template(config: &PlayoutConfig, mut template: Template) -> Template {
+    for source in &mut template.sources {
+        source.paths = source
+            .paths
+            .iter()
+            .filter_map(|p| {
+                norm_abs_path(&config.channel.storage, &p.to_string_lossy())
+                    .ok()
+                    .map(|(path, _, _)| path)
+            })
+            .collect();
+    }
+
+    if let Some(ident) = template.ident.as_mut()
+        && let Ok((path, _, _)) =
+            norm_abs_path(&config.channel.storage, &ident.path.to_string_lossy())
+    {
+        ident.path = path;
+    }
+
+    template.sources.sort_by_key(|s| s.start);
+
+    template
+}
+
+/// Generate the playlist for the given date from calendar, when it not exists.
+///
+/// Returns the path from the new playlist, or `None` when nothing was to do.
+pub async fn materialize(manager: &ChannelManager, date: &str) -> Result<Option<PathBuf>, Error> {
+    let config = manager.config.read().await.clone();
+    let id = config.general.channel_id;
+    let d: Vec<&str> = date.split('-').collect();
+
+    if d.len() != 3 || !config.channel.playlists.is_dir() {
+        return Ok(None);
+    }
+
+    let playlist_path = config.channel.playlists.join(d[0]).join(d[1]);
+    let playlist_file = playlist_path.join(format!("{date}.json"));
+
+    if playlist_file.is_file() {
+        return Ok(None);
+    }
+
+    let Some(template) = Calendar::load(&config)
+        .await
+        .and_then(|c| c.template_for(date))
+    else {
+        return Ok(None);
+    };
+
+    info!(target: Target::file_mail(), channel = id;
+        "Generate playlist from calendar: <span class=\"log-addr\">{}</span>",
+        playlist_file.display()
+    );
+
+    let playlist = playlist_from_template(manager, resolve_template(&config, template), date).await;
+
+    fs::create_dir_all(playlist_path).await?;
+    fs::write(&playlist_file, serde_json::to_string_pretty(&playlist)?).await?;
+
+    Ok(Some(playlist_file))
+}
@@ -11,7 +11,7 @@ use crate::db::{
 use crate::player::controller::{ChannelController, ChannelManager};
 use crate::utils::{
     advanced_config::{AdvancedConfig, DecoderConfig, FilterConfig, IngestConfig},
-    config::get_config,
+    config::{fixed_path, get_config},
     errors::ServiceError,
     mail::MailQueue,
 };
@@ -82,6 +82,13 @@ fn validate_and_set_defaults(mut channel: Channel) -> Channel {
     channel
 }
 
+/// The folders of a channel are kept while it runs, so they can't change by date.
+pub fn check_channel_paths(channel: &Channel) -> Result<(), ServiceError> {
+    fixed_path("Public", &channel.public)?;
+    fixed_path("Playlist", &channel.playlists)?;
+    fixed_path("Storage", &channel.storage)
+}
+
 pub async fn create_channel(
     conn: &Pool<Sqlite>,
     controllers: Arc<RwLock<ChannelController>>,
@@ -89,6 +96,7 @@ pub async fn create_channel(
     target_channel: Channel,
 ) -> Result<Channel, ServiceError> {
     let validated_channel = validate_and_set_defaults(target_channel);
+    check_channel_paths(&validated_channel)?;
     let channel = handles::insert_channel(conn, validated_channel).await?;
     let outputs = [
         models::Output::new(channel.id, OutputMode::HLS, OUTPUT_HLS.to_string()),
@@ -200,6 +208,12 @@ pub async fn delete_channel(
 ) -> Result<(), ServiceError> {
     let channel = handles::select_channel(conn, &id).await?;
     handles::delete_channel(conn, &channel.id).await?;
+
+    if let Some(manager) = controllers.read().await.get(id) {
+        // stop the staging preview
+        manager.staging.lock().await.take();
+    }
+
     controllers.write().await.remove(id);
     let mut queue_guard = queue.lock().await;
     let mut new_queue = Vec::with_capacity(queue_guard.len());
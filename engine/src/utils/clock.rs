@@ -0,0 +1,117 @@
+/// Clock Discipline
+///
+/// The playlist runs by the wall clock of the host, when the clock is not synchronized
+/// or jumps, the timing breaks without an error. At start and every ten minutes the
+/// NTP status from `timedatectl` gets checked, a change gets logged. Between the checks
+/// the wall clock is compared with the monotonic clock, a step over the threshold is
+/// an error.
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant, SystemTime},
+};
+
+use log::*;
+use tokio::{process::Command, time::sleep};
+
+use crate::player::controller::ChannelManager;
+use crate::utils::{config::PlayoutConfig, logging::Target};
+
+/// Seconds, which the wall clock may differ from the monotonic clock between two polls.
+pub const STEP_THRESHOLD: f64 = 0.5;
+const POLL: Duration = Duration::from_secs(5);
+const NTP_INTERVAL: Duration = Duration::from_secs(600);
+
+/// `NTPSynchronized` from `timedatectl show`, `None` when it is unknown.
+pub fn parse_ntp(output: &str) -> Option<bool> {
+    output.lines().find_map(|line| {
+        let value = line
+            .trim()
+            .strip_prefix("NTPSynchronized=")
+            .unwrap_or(line.trim());
+
+        match value {
+            "yes" => Some(true),
+            "no" => Some(false),
+            _ => None,
+        }
+    })
+}
+
+async fn ntp_synchronized() -> Option<bool> {
+    let output = Command::new("timedatectl")
+        .args(["show", "--property=NTPSynchronized"])
+        .output()
+        .await
+        .ok()
+        .filter(|o| o.status.success())?;
+
+    parse_ntp(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Wall clock and monotonic clock of the last poll.
+#[derive(Debug, Clone, Copy)]
+pub struct ClockWatch {
+    wall: SystemTime,
+    mono: Instant,
+}
+
+impl ClockWatch {
+    pub fn new(wall: SystemTime, mono: Instant) -> Self {
+        Self { wall, mono }
+    }
+
+    /// Seconds, which the wall clock jumped since the last poll, negative is backwards.
+    pub fn step(&mut self, wall: SystemTime, mono: Instant) -> f64 {
+        let wall_elapsed = match wall.duration_since(self.wall) {
+            Ok(d) => d.as_secs_f64(),
+            Err(e) => -e.duration().as_secs_f64(),
+        };
+        let mono_elapsed = mono.duration_since(self.mono).as_secs_f64();
+
+        self.wall = wall;
+        self.mono = mono;
+
+        wall_elapsed - mono_elapsed
+    }
+}
+
+pub async fn run(manager: ChannelManager, config: PlayoutConfig) {
+    let id = config.general.channel_id;
+    let mut watch = ClockWatch::new(SystemTime::now(), Instant::now());
+    let mut synced = None;
+    let mut checked: Option<Instant> = None;
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        if checked.is_none_or(|c| c.elapsed() >= NTP_INTERVAL) {
+            let status = ntp_synchronized().await;
+
+            match status {
+                Some(false) if synced != status => {
+                    error!(target: Target::file_mail(), channel = id;
+                        "System clock is not synchronized (NTP), the playlist timing may be off!"
+                    );
+                }
+                Some(true) if synced == Some(false) => {
+                    info!(target: Target::file_mail(), channel = id; "System clock is synchronized again (NTP)");
+                }
+                None if checked.is_none() => {
+                    debug!(target: Target::file(), channel = id; "NTP status of the system clock is unknown");
+                }
+                _ => (),
+            }
+
+            synced = status;
+            checked = Some(Instant::now());
+        }
+
+        sleep(POLL).await;
+
+        let step = watch.step(SystemTime::now(), Instant::now());
+
+        if step.abs() > STEP_THRESHOLD {
+            error!(target: Target::file_mail(), channel = id;
+                "System clock jumped by <span class=\"log-number\">{step:.3}</span> seconds, the playlist timing may be off!"
+            );
+        }
+    }
+}
@@ -0,0 +1,143 @@
+/// Companion Integration
+///
+/// A plain text command surface (`task.companion_address`) for Bitfocus Companion and button
+/// panels like the Stream Deck. With the "Generic TCP/UDP" module, every button sends one
+/// command; over TCP a command is one line, over UDP one datagram on the same port.
+///
+/// The commands are the [trigger actions](crate::utils::triggers::TriggerAction), with
+/// `take_live` and `release_live` as names for the ingest actions, and `status` for the
+/// current clip. Over TCP, every command gets an answer: `OK`, `OK <title>` for the status,
+/// or `ERR <message>`.
+use std::{sync::atomic::Ordering, time::Duration};
+
+use log::*;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream, UdpSocket},
+    time::timeout,
+};
+
+use crate::player::controller::ChannelManager;
+use crate::utils::{
+    config::PlayoutConfig,
+    logging::Target,
+    triggers::{TriggerAction, run_action},
+};
+
+const POLL: Duration = Duration::from_secs(1);
+
+/// Run a command and get the answer.
+async fn answer(manager: &ChannelManager, id: i32, command: &str) -> String {
+    if command.eq_ignore_ascii_case("status") {
+        let current = manager.current_media.lock().await.clone();
+
+        return match current {
+            Some(media) => format!("OK {}", media.title.unwrap_or(media.source)),
+            None => "OK".to_string(),
+        };
+    }
+
+    let action = match command.parse::<TriggerAction>() {
+        Ok(a) => a,
+        Err(e) => return format!("ERR {e}"),
+    };
+
+    info!(target: Target::file_mail(), channel = id; "Companion: {action}");
+
+    match run_action(manager, &action).await {
+        Ok(_) => "OK".to_string(),
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Companion {action} failed: {e}");
+            format!("ERR {e}")
+        }
+    }
+}
+
+async fn connection(manager: &ChannelManager, id: i32, stream: TcpStream) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        let line = match timeout(POLL, lines.next_line()).await {
+            Ok(Ok(Some(line))) => line,
+            Ok(Ok(None)) | Ok(Err(_)) => break,
+            Err(_) => continue,
+        };
+        let command = line.trim();
+
+        if command.is_empty() {
+            continue;
+        }
+
+        let reply = answer(manager, id, command).await;
+
+        if writer
+            .write_all(format!("{reply}\n").as_bytes())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+async fn listen_tcp(manager: &ChannelManager, id: i32, listener: TcpListener) {
+    while manager.is_alive.load(Ordering::SeqCst) {
+        let Ok(accepted) = timeout(POLL, listener.accept()).await else {
+            continue;
+        };
+
+        match accepted {
+            Ok((stream, peer)) => {
+                debug!(target: Target::file_mail(), channel = id; "Companion client: <span class=\"log-addr\">{peer}</span>");
+
+                let manager = manager.clone();
+                tokio::spawn(async move { connection(&manager, id, stream).await });
+            }
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = id; "Companion: {e}");
+            }
+        }
+    }
+}
+
+async fn listen_udp(manager: &ChannelManager, id: i32, socket: UdpSocket) {
+    let mut buffer = [0u8; 1024];
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        let num = match timeout(POLL, socket.recv_from(&mut buffer)).await {
+            Ok(Ok((n, _))) => n,
+            Ok(Err(e)) => {
+                error!(target: Target::file_mail(), channel = id; "Companion: {e}");
+                continue;
+            }
+            Err(_) => continue,
+        };
+        let datagram = String::from_utf8_lossy(&buffer[..num]).to_string();
+
+        for command in datagram.lines().map(str::trim).filter(|c| !c.is_empty()) {
+            answer(manager, id, command).await;
+        }
+    }
+}
+
+/// Listen for commands over TCP and UDP until the channel stops.
+pub async fn listen(manager: ChannelManager, config: PlayoutConfig) {
+    let id = config.general.channel_id;
+    let address = config.task.companion_address.trim().to_string();
+
+    let (tcp, udp) = match (
+        TcpListener::bind(&address).await,
+        UdpSocket::bind(&address).await,
+    ) {
+        (Ok(t), Ok(u)) => (t, u),
+        (Err(e), _) | (_, Err(e)) => {
+            error!(target: Target::file_mail(), channel = id; "Companion address <span class=\"log-addr\">{address}</span>: {e}");
+            return;
+        }
+    };
+
+    info!(target: Target::file_mail(), channel = id; "Companion listening on: <span class=\"log-addr\">{address}</span>");
+
+    tokio::join!(listen_tcp(&manager, id, tcp), listen_udp(&manager, id, udp));
+}
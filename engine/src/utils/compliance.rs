@@ -0,0 +1,147 @@
+/// Compliance Recording
+///
+/// A rolling recording of the program output in low bitrate, for complaints to the
+/// regulator and other questions about what was on air. It is a second output of the
+/// encoder in stream mode, behind the graphics, tickers and alerts of the encoder, and
+/// writes segments of 15 minutes into `output.compliance_path`.
+///
+/// The output runs over the tee muxer, so a full disk doesn't stop the program. Segments
+/// older than `output.compliance_days` get deleted, independent from the VOD export.
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    time::{Duration, SystemTime},
+};
+
+use log::*;
+use tokio::{fs, time::sleep};
+
+use crate::file::norm_abs_path;
+use crate::player::{controller::ChannelManager, filter::Filters};
+use crate::utils::{
+    config::{OutputMode, PlayoutConfig, channel_path},
+    logging::Target,
+};
+use crate::vec_strings;
+
+pub const COMPLIANCE_PARAM: &str = "-s 640x360 -c:v libx264 -preset veryfast -b:v 500k -maxrate 500k -bufsize 1M -force_key_frames expr:gte(t,n_forced*2) -c:a aac -b:a 64k -ac 2";
+pub const COMPLIANCE_EXTENSION: &str = "ts";
+/// Filter link of the split video, after the filters of the encoder.
+pub const COMPLIANCE_LINK: &str = "[vrec]";
+/// Seconds of a segment.
+const SEGMENT_TIME: u32 = 900;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+const POLL: Duration = Duration::from_secs(10);
+
+pub fn enabled(config: &PlayoutConfig) -> bool {
+    !config.output.compliance_path.trim().is_empty() && config.output.mode == OutputMode::Stream
+}
+
+/// Folder of the recording, relative paths are inside the storage.
+pub fn folder(config: &PlayoutConfig) -> PathBuf {
+    norm_abs_path(
+        &config.channel.storage,
+        &channel_path(config, &config.output.compliance_path),
+    )
+    .map(|(path, _, _)| path)
+    .unwrap_or_else(|_| config.channel.storage.join("compliance"))
+}
+
+/// Output arguments of the encoder, the video comes from the split after the encoder
+/// filters, when there are any.
+pub fn output(config: &PlayoutConfig, filters: &Option<Filters>) -> Vec<String> {
+    if !enabled(config) {
+        return vec![];
+    }
+
+    let param = match config.output.compliance_param.trim() {
+        "" => COMPLIANCE_PARAM,
+        param => param,
+    };
+    let mut cmd = vec![];
+
+    if !config.processing.audio_only {
+        let video = if filters
+            .as_ref()
+            .is_some_and(|f| f.video_chain.contains(COMPLIANCE_LINK))
+        {
+            COMPLIANCE_LINK
+        } else {
+            "0:v"
+        };
+
+        cmd.append(&mut vec_strings!["-map", video]);
+    }
+
+    cmd.append(&mut vec_strings!["-map", "0:a:0"]);
+    cmd.append(&mut shlex::split(param).unwrap_or_default());
+    cmd.append(&mut vec_strings![
+        "-f",
+        "tee",
+        format!(
+            "[f=segment:segment_time={SEGMENT_TIME}:segment_format=mpegts:strftime=1:reset_timestamps=1:onfail=ignore]{}",
+            folder(config)
+                .join(format!("%Y-%m-%d_%H-%M-%S.{COMPLIANCE_EXTENSION}"))
+                .to_string_lossy()
+        )
+    ]);
+
+    cmd
+}
+
+/// Delete the segments, which are older then `days`.
+pub async fn prune(folder: &Path, days: u32, now: SystemTime) -> Vec<PathBuf> {
+    let mut removed = vec![];
+    let Some(limit) = now.checked_sub(Duration::from_secs(days as u64 * 86400)) else {
+        return removed;
+    };
+    let Ok(mut entries) = fs::read_dir(folder).await else {
+        return removed;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+
+        if path.extension().and_then(|e| e.to_str()) != Some(COMPLIANCE_EXTENSION) {
+            continue;
+        }
+
+        let old = entry
+            .metadata()
+            .await
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified < limit);
+
+        if old && fs::remove_file(&path).await.is_ok() {
+            removed.push(path);
+        }
+    }
+
+    removed
+}
+
+/// Delete old segments, every hour.
+pub async fn run(manager: ChannelManager, config: PlayoutConfig) {
+    let id = config.general.channel_id;
+    let folder = folder(&config);
+    let mut pruned = None;
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        if config.output.compliance_days > 0
+            && pruned.is_none_or(|p: SystemTime| p.elapsed().unwrap_or_default() >= PRUNE_INTERVAL)
+        {
+            let removed = prune(&folder, config.output.compliance_days, SystemTime::now()).await;
+
+            if !removed.is_empty() {
+                debug!(target: Target::file(), channel = id;
+                    "Compliance recording: delete <span class=\"log-number\">{}</span> old segments",
+                    removed.len()
+                );
+            }
+
+            pruned = Some(SystemTime::now());
+        }
+
+        sleep(POLL).await;
+    }
+}
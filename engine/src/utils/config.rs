@@ -1,30 +1,43 @@
 use std::{
-    fmt,
+    collections::BTreeMap,
+    env, fmt,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::OnceLock,
 };
 
 use chrono::NaiveTime;
 use chrono_tz::Tz;
 use flexi_logger::Level;
+use log::*;
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use shlex::split;
 use sqlx::{Pool, Sqlite};
+use sysinfo::System;
 use tokio::{fs, io::AsyncReadExt};
+use toml_edit::{DocumentMut, TableLike};
 use ts_rs::TS;
 
 use crate::ARGS;
 use crate::AdvancedConfig;
 use crate::db::{handles, models};
-use crate::file::norm_abs_path;
-use crate::player::utils::validate_ffmpeg;
-use crate::utils::{gen_tcp_socket, time_to_sec};
+use crate::file::{backend::Backend, norm_abs_path};
+use crate::player::{
+    filter::{
+        audio_chains,
+        graphics::{self, Graphic},
+    },
+    process::valid_cgroup,
+    utils::{PIPE_FORMAT, languages, validate_ffmpeg},
+};
+use crate::utils::{gen_tcp_socket, logging::Target, time_machine::time_now, time_to_sec};
 use crate::vec_strings;
 
 use super::errors::ServiceError;
 
 pub const DUMMY_LEN: f64 = 60.0;
+pub const OVERRIDE_ENV_PREFIX: &str = "FFPLAYOUT__";
 pub const IMAGE_FORMAT: [&str; 21] = [
     "bmp", "dds", "dpx", "exr", "gif", "hdr", "j2k", "jpg", "jpeg", "pcx", "pfm", "pgm", "phm",
     "png", "psd", "ppm", "sgi", "svg", "tga", "tif", "webp",
@@ -61,6 +74,21 @@ pub const FFMPEG_UNRECOVERABLE_ERRORS: [&str; 9] = [
     "Option not found",
 ];
 
+/// Algorithms of the tonemap filter, for HDR sources.
+pub const TONEMAP_ALGORITHMS: [&str; 7] = [
+    "none", "clip", "linear", "gamma", "reinhard", "hable", "mobius",
+];
+
+/// Video encoders, which can write CEA-608/708 captions as A/53 side data.
+pub const CAPTION_ENCODERS: [&str; 6] = [
+    "libx264",
+    "libx265",
+    "mpeg2video",
+    "h264_nvenc",
+    "hevc_nvenc",
+    "h264_qsv",
+];
+
 #[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize, Serialize, TS)]
 #[ts(export, export_to = "playout_config.d.ts")]
 #[serde(rename_all = "lowercase")]
@@ -108,6 +136,49 @@ impl fmt::Display for OutputMode {
     }
 }
 
+/// Field order of the output, interlaced outputs are processed progressive
+/// and get interlaced at the end of the filter chain.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "lowercase")]
+pub enum FieldOrder {
+    #[default]
+    Progressive,
+    /// Top field first
+    Tff,
+    /// Bottom field first
+    Bff,
+}
+
+impl FieldOrder {
+    pub fn new(s: &str) -> Self {
+        s.parse().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for FieldOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FieldOrder::Progressive => write!(f, "progressive"),
+            FieldOrder::Tff => write!(f, "tff"),
+            FieldOrder::Bff => write!(f, "bff"),
+        }
+    }
+}
+
+impl FromStr for FieldOrder {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "progressive" => Ok(Self::Progressive),
+            "tff" => Ok(Self::Tff),
+            "bff" => Ok(Self::Bff),
+            _ => Err("Use 'progressive', 'tff' or 'bff'".to_string()),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize, Eq, PartialEq, TS)]
 #[ts(export, export_to = "playout_config.d.ts")]
 #[serde(rename_all = "lowercase")]
@@ -115,12 +186,14 @@ pub enum ProcessMode {
     Folder,
     #[default]
     Playlist,
+    Test,
 }
 
 impl ProcessMode {
     fn new(s: &str) -> Self {
         match s {
             "folder" => Self::Folder,
+            "test" => Self::Test,
             _ => Self::Playlist,
         }
     }
@@ -131,6 +204,7 @@ impl fmt::Display for ProcessMode {
         match *self {
             ProcessMode::Folder => write!(f, "folder"),
             ProcessMode::Playlist => write!(f, "playlist"),
+            ProcessMode::Test => write!(f, "test"),
         }
     }
 }
@@ -142,7 +216,8 @@ impl FromStr for ProcessMode {
         match input {
             "folder" => Ok(Self::Folder),
             "playlist" => Ok(Self::Playlist),
-            _ => Err("Use 'folder' or 'playlist'".to_string()),
+            "test" => Ok(Self::Test),
+            _ => Err("Use 'folder', 'playlist' or 'test'".to_string()),
         }
     }
 }
@@ -150,6 +225,21 @@ impl FromStr for ProcessMode {
 #[derive(Clone, Debug, Default, Deserialize, Serialize, TS)]
 pub struct Template {
     pub sources: Vec<Source>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ident: Option<Ident>,
+}
+
+/// Station ident, which gets inserted at the first clip change after every interval (top of the hour by default).
+#[derive(Clone, Debug, Deserialize, Serialize, TS)]
+pub struct Ident {
+    pub path: PathBuf,
+    #[ts(type = "string")]
+    #[serde(default = "default_ident_interval")]
+    pub interval: NaiveTime,
+}
+
+fn default_ident_interval() -> NaiveTime {
+    NaiveTime::from_hms_opt(1, 0, 0).unwrap()
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, TS)]
@@ -189,6 +279,9 @@ pub struct PlayoutConfig {
 
 #[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
 pub struct Channel {
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub name: String,
     pub logs: PathBuf,
     pub public: PathBuf,
     pub playlists: PathBuf,
@@ -200,17 +293,52 @@ pub struct Channel {
 
 impl Channel {
     pub fn new(config: &models::GlobalSettings, channel: models::Channel) -> Self {
+        let fill = |path: &str| PathBuf::from(fill_path(path, channel.id));
+
         Self {
-            logs: PathBuf::from(config.logs.clone()),
-            public: PathBuf::from(channel.public.clone()),
-            playlists: PathBuf::from(channel.playlists.clone()),
-            storage: PathBuf::from(channel.storage.clone()),
+            name: channel.name.clone(),
+            logs: fill(&config.logs),
+            public: fill(&channel.public),
+            playlists: fill(&channel.playlists),
+            storage: fill(&channel.storage),
             shared: config.shared,
             timezone: channel.timezone,
         }
     }
 }
 
+/// Fill the placeholders of a path: `{channel}` with the channel id and `{hostname}` with
+/// the name of the server.
+pub fn fill_path(path: &str, channel: i32) -> String {
+    if !path.contains('{') {
+        return path.to_string();
+    }
+
+    path.replace("{channel}", &channel.to_string())
+        .replace("{hostname}", &System::host_name().unwrap_or_default())
+}
+
+/// Path of a folder, which is used only once, like for a recording. `{date}` is the
+/// current date in the time zone of the channel.
+pub fn channel_path(config: &PlayoutConfig, path: &str) -> String {
+    let date = time_now(&config.channel.timezone)
+        .format("%Y-%m-%d")
+        .to_string();
+
+    fill_path(path, config.general.channel_id).replace("{date}", &date)
+}
+
+/// Paths, which are kept while the channel runs, can't have a `{date}`.
+pub fn fixed_path(name: &str, path: &str) -> Result<(), ServiceError> {
+    if path.contains("{date}") {
+        return Err(ServiceError::BadRequest(format!(
+            "{name} path can't have a {{date}} placeholder: {path}"
+        )));
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
 #[ts(export, export_to = "playout_config.d.ts")]
 pub struct General {
@@ -221,6 +349,36 @@ pub struct General {
     #[serde(skip_serializing, skip_deserializing)]
     pub channel_id: i32,
     pub stop_threshold: f64,
+    /// Nice level for the ffmpeg processes of this channel, 0 keeps the default priority.
+    #[serde(default)]
+    pub nice: i32,
+    /// Control group (cgroup v2) for the ffmpeg processes, relative to /sys/fs/cgroup.
+    #[serde(default)]
+    pub cgroup: String,
+    /// Thread count passed to ffmpeg, 0 lets ffmpeg decide.
+    #[serde(default)]
+    pub threads: i32,
+    /// Seconds without data from the decoder, before the clip gets skipped, 0 is off.
+    #[serde(default)]
+    pub stall_timeout: f64,
+    /// Failures in a row, before a clip is skipped for the rest of the day, 0 is off.
+    #[serde(default)]
+    pub quarantine: u32,
+    /// Restart a crashed decoder at the last played position, instead of skipping the clip.
+    #[serde(default)]
+    pub decoder_resume: bool,
+    /// Failed clips in a row, before the standby slate runs until a source plays again, 0 is off.
+    #[serde(default)]
+    pub slate_errors: u32,
+    /// Milliseconds of output, which are buffered between decoder and encoder.
+    #[serde(default)]
+    pub buffer_latency: u32,
+    /// Files, which are probed at once, when a playlist is loaded.
+    #[serde(default)]
+    pub probe_workers: u32,
+    /// Seconds for the probe of a file, before it counts as failed, 0 is off.
+    #[serde(default)]
+    pub probe_timeout: f64,
     #[ts(skip)]
     #[serde(skip_serializing, skip_deserializing)]
     pub generate: Option<Vec<String>>,
@@ -235,6 +393,15 @@ pub struct General {
     pub ffmpeg_options: Vec<String>,
     #[ts(skip)]
     #[serde(skip_serializing, skip_deserializing)]
+    pub ffmpeg_version: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub ffmpeg_encoders: Vec<String>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub ffmpeg_protocols: Vec<String>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
     pub template: Option<Template>,
     #[ts(skip)]
     #[serde(skip_serializing, skip_deserializing)]
@@ -252,10 +419,23 @@ impl General {
             id: config.id,
             channel_id: config.channel_id,
             stop_threshold: config.general_stop_threshold,
+            nice: config.general_nice,
+            cgroup: config.general_cgroup.clone(),
+            threads: config.general_threads,
+            stall_timeout: config.general_stall_timeout,
+            quarantine: config.general_quarantine,
+            decoder_resume: config.general_decoder_resume,
+            slate_errors: config.general_slate_errors,
+            buffer_latency: config.general_buffer_latency,
+            probe_workers: config.general_probe_workers,
+            probe_timeout: config.general_probe_timeout,
             generate: None,
             ffmpeg_filters: vec![],
             ffmpeg_libs: vec![],
             ffmpeg_options: vec![],
+            ffmpeg_version: String::new(),
+            ffmpeg_encoders: vec![],
+            ffmpeg_protocols: vec![],
             template: None,
             skip_validation: false,
             validate: false,
@@ -375,6 +555,11 @@ pub struct Processing {
     pub height: i64,
     pub aspect: f64,
     pub fps: f64,
+    #[serde(default)]
+    pub fps_policy: FpsPolicy,
+    /// Clips within this frame rate difference are passed through unchanged.
+    #[serde(default)]
+    pub fps_tolerance: f64,
     pub add_logo: bool,
     pub logo: String,
     #[ts(skip)]
@@ -394,6 +579,63 @@ pub struct Processing {
     pub vtt_enable: bool,
     #[serde(default)]
     pub vtt_dummy: Option<String>,
+    #[serde(default)]
+    pub audio_meter: bool,
+    #[serde(default)]
+    pub tonemap: bool,
+    #[serde(default = "default_tonemap_algorithm")]
+    pub tonemap_algorithm: String,
+    #[serde(default)]
+    pub tonemap_desat: f64,
+    /// Signal peak for the tonemap filter, `0` takes the value from the metadata.
+    #[serde(default)]
+    pub tonemap_peak: f64,
+    #[serde(default)]
+    pub timecode_burn_in: bool,
+    #[serde(default)]
+    pub timecode_embed: bool,
+    /// Channel of the first audio track for LTC, starting from 1, `0` is off.
+    #[serde(default)]
+    pub ltc_channel: u8,
+    /// Change logo opacity and volume at runtime, over zmq.
+    #[serde(default)]
+    pub live_control: bool,
+    /// Milliseconds of A/V drift, before the decoder gets restarted, `0` is off.
+    #[serde(default)]
+    pub av_sync_tolerance: u32,
+    /// Named audio filter chains, one `name = filters` per line.
+    #[serde(default)]
+    pub audio_chains: String,
+    /// Day parts, which select an audio chain, one `HH:MM-HH:MM = name` per line.
+    #[serde(default)]
+    pub audio_dayparts: String,
+    /// Inject ID3 timed metadata (title, ad breaks) into the HLS segments.
+    #[serde(default)]
+    pub hls_id3: bool,
+    /// Pass embedded CEA-608/708 captions through to the output.
+    #[serde(default)]
+    pub captions: bool,
+    /// Audio filter of the watermark encoder for audience measurement, at the end of the
+    /// audio chain.
+    #[serde(default)]
+    pub watermark: String,
+    /// Milliseconds, by which the watermark encoder delays the audio.
+    #[serde(default)]
+    pub watermark_latency: u32,
+    /// Languages of the audio tracks (ISO 639-2, like `deu, eng`), one per track, comma
+    /// separated. The first is the primary language.
+    #[serde(default)]
+    pub audio_languages: String,
+    /// Audio track with the audio description, mixed from the sidecar files of the clips
+    /// (`<clip>.ad.wav`), `0` is off.
+    #[serde(default)]
+    pub description_track: u8,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub zmq_stream_socket: Option<String>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub zmq_server_socket: Option<String>,
     #[ts(skip)]
     #[serde(skip_serializing, skip_deserializing)]
     pub cmd: Option<Vec<String>>,
@@ -411,6 +653,27 @@ impl Processing {
             height: config.processing_height,
             aspect: config.processing_aspect,
             fps: config.processing_fps,
+            fps_policy: FpsPolicy::new(&config.processing_fps_policy),
+            fps_tolerance: config.processing_fps_tolerance,
+            tonemap: config.processing_tonemap,
+            tonemap_algorithm: config.processing_tonemap_algorithm.clone(),
+            tonemap_desat: config.processing_tonemap_desat,
+            tonemap_peak: config.processing_tonemap_peak,
+            timecode_burn_in: config.processing_timecode_burn_in,
+            timecode_embed: config.processing_timecode_embed,
+            ltc_channel: config.processing_ltc_channel,
+            live_control: config.processing_live_control,
+            av_sync_tolerance: config.processing_av_sync_tolerance,
+            audio_chains: config.processing_audio_chains.clone(),
+            audio_dayparts: config.processing_audio_dayparts.clone(),
+            hls_id3: config.processing_hls_id3,
+            captions: config.processing_captions,
+            watermark: config.processing_watermark.clone(),
+            watermark_latency: config.processing_watermark_latency,
+            audio_languages: config.processing_audio_languages.clone(),
+            description_track: config.processing_description_track,
+            zmq_stream_socket: None,
+            zmq_server_socket: None,
             add_logo: config.processing_add_logo,
             logo: config.processing_logo.clone(),
             logo_path: config.processing_logo.clone(),
@@ -424,6 +687,7 @@ impl Processing {
             override_filter: config.processing_override_filter,
             vtt_enable: config.processing_vtt_enable,
             vtt_dummy: config.processing_vtt_dummy.clone(),
+            audio_meter: config.processing_audio_meter,
             cmd: None,
         }
     }
@@ -435,6 +699,12 @@ pub struct Ingest {
     pub enable: bool,
     pub input_param: String,
     pub custom_filter: String,
+    #[serde(default)]
+    pub preview: bool,
+    #[serde(default)]
+    pub record: bool,
+    #[serde(default)]
+    pub record_path: String,
     #[ts(skip)]
     #[serde(skip_serializing, skip_deserializing)]
     pub input_cmd: Option<Vec<String>>,
@@ -446,6 +716,9 @@ impl Ingest {
             enable: config.ingest_enable,
             input_param: config.ingest_param.clone(),
             custom_filter: config.ingest_filter.clone(),
+            preview: config.ingest_preview,
+            record: config.ingest_record,
+            record_path: config.ingest_record_path.clone(),
             input_cmd: None,
         }
     }
@@ -463,6 +736,27 @@ pub struct Playlist {
     #[serde(skip_serializing, skip_deserializing)]
     pub length_sec: Option<f64>,
     pub infinit: bool,
+    #[serde(default)]
+    pub recovery: RecoveryStrategy,
+    #[serde(default = "default_recovery_speed")]
+    pub recovery_speed: f64,
+    /// Percent, by which the speed of a clip may change, to fit into its slot.
+    #[serde(default)]
+    pub slot_fit: f64,
+    /// "Up next" card at the end of gaps before a program, instead of filler.
+    #[serde(default)]
+    pub next_card: bool,
+    /// Minutes before the end of the day, in which the playlist of the next day gets
+    /// validated, 0 is off.
+    #[serde(default)]
+    pub read_ahead: u32,
+    /// How to join the item, which runs already, when the playout starts late.
+    #[serde(default)]
+    pub join: JoinPolicy,
+}
+
+fn default_recovery_speed() -> f64 {
+    1.05
 }
 
 impl Playlist {
@@ -473,10 +767,243 @@ impl Playlist {
             length: config.playlist_length.clone(),
             length_sec: None,
             infinit: config.playlist_infinit,
+            recovery: RecoveryStrategy::new(&config.playlist_recovery),
+            recovery_speed: config.playlist_recovery_speed,
+            slot_fit: config.playlist_slot_fit,
+            next_card: config.playlist_next_card,
+            read_ahead: config.playlist_read_ahead,
+            join: JoinPolicy::new(&config.playlist_join),
+        }
+    }
+}
+
+/// Conversion of clips with a different frame rate than the channel.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "snake_case")]
+pub enum FpsPolicy {
+    /// Drop or duplicate frames, with the fps filter.
+    #[default]
+    DropDup,
+    /// Blend neighbor frames, with minterpolate.
+    Blend,
+    /// Motion compensated interpolation, with minterpolate. Needs a lot of CPU power!
+    Interpolate,
+}
+
+impl FpsPolicy {
+    fn new(s: &str) -> Self {
+        s.parse().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for FpsPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FpsPolicy::DropDup => write!(f, "drop_dup"),
+            FpsPolicy::Blend => write!(f, "blend"),
+            FpsPolicy::Interpolate => write!(f, "interpolate"),
+        }
+    }
+}
+
+impl FromStr for FpsPolicy {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "drop_dup" => Ok(Self::DropDup),
+            "blend" => Ok(Self::Blend),
+            "interpolate" => Ok(Self::Interpolate),
+            _ => Err("Use 'drop_dup', 'blend' or 'interpolate'".to_string()),
+        }
+    }
+}
+
+/// Strategy to get back on time, when the playout falls behind schedule.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryStrategy {
+    #[default]
+    None,
+    TrimClip,
+    DropFiller,
+    SpeedUp,
+}
+
+impl RecoveryStrategy {
+    fn new(s: &str) -> Self {
+        s.parse().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for RecoveryStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecoveryStrategy::None => write!(f, "none"),
+            RecoveryStrategy::TrimClip => write!(f, "trim_clip"),
+            RecoveryStrategy::DropFiller => write!(f, "drop_filler"),
+            RecoveryStrategy::SpeedUp => write!(f, "speed_up"),
+        }
+    }
+}
+
+impl FromStr for RecoveryStrategy {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "none" => Ok(Self::None),
+            "trim_clip" => Ok(Self::TrimClip),
+            "drop_filler" => Ok(Self::DropFiller),
+            "speed_up" => Ok(Self::SpeedUp),
+            _ => Err("Use 'none', 'trim_clip', 'drop_filler' or 'speed_up'".to_string()),
+        }
+    }
+}
+
+/// Join of the scheduled item, when the playout starts or resumes late.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "snake_case")]
+pub enum JoinPolicy {
+    /// Seek into the item, to the point where it would be now.
+    #[default]
+    Seek,
+    /// Start the item from the top, the following items move later.
+    Top,
+    /// Skip to the next hard start item, with a filler gap until then.
+    NextHard,
+}
+
+impl JoinPolicy {
+    fn new(s: &str) -> Self {
+        s.parse().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for JoinPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JoinPolicy::Seek => write!(f, "seek"),
+            JoinPolicy::Top => write!(f, "top"),
+            JoinPolicy::NextHard => write!(f, "next_hard"),
+        }
+    }
+}
+
+impl FromStr for JoinPolicy {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "seek" => Ok(Self::Seek),
+            "top" => Ok(Self::Top),
+            "next_hard" => Ok(Self::NextHard),
+            _ => Err("Use 'seek', 'top' or 'next_hard'".to_string()),
+        }
+    }
+}
+
+/// Source for a step in the fallback chain, which is used when a clip or playlist is missing.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FallbackSource {
+    Filler,
+    Clip,
+    Placeholder,
+    Test,
+}
+
+impl fmt::Display for FallbackSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FallbackSource::Filler => write!(f, "filler"),
+            FallbackSource::Clip => write!(f, "clip"),
+            FallbackSource::Placeholder => write!(f, "placeholder"),
+            FallbackSource::Test => write!(f, "test"),
+        }
+    }
+}
+
+impl FromStr for FallbackSource {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "filler" => Ok(Self::Filler),
+            "clip" => Ok(Self::Clip),
+            "placeholder" => Ok(Self::Placeholder),
+            "test" => Ok(Self::Test),
+            _ => Err(format!(
+                "Fallback '{input}': use 'filler', 'clip', 'placeholder' or 'test'"
+            )),
         }
     }
 }
 
+/// Step in the fallback chain, written as `source` or `source=timeout`.
+///
+/// The timeout are the seconds the step is used in a row, before the next step takes over.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct FallbackStep {
+    pub source: FallbackSource,
+    pub timeout: u64,
+}
+
+impl FromStr for FallbackStep {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (source, timeout) = match input.trim().split_once('=') {
+            Some((source, timeout)) => (
+                source.trim(),
+                timeout
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| format!("Fallback '{input}': timeout is not a number"))?,
+            ),
+            None => (input.trim(), 0),
+        };
+
+        Ok(Self {
+            source: source.parse()?,
+            timeout,
+        })
+    }
+}
+
+pub const DEFAULT_FALLBACK: &str = "filler;clip;placeholder=300;test";
+
+/// Parse the fallback chain, the test pattern is always the last step.
+pub fn fallback_chain(steps: &[String]) -> Result<Vec<FallbackStep>, String> {
+    let mut chain = steps
+        .iter()
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.parse::<FallbackStep>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if chain.is_empty() {
+        chain = DEFAULT_FALLBACK
+            .split(';')
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+    }
+
+    if chain
+        .last()
+        .is_none_or(|s| s.source != FallbackSource::Test || s.timeout > 0)
+    {
+        chain.push(FallbackStep {
+            source: FallbackSource::Test,
+            timeout: 0,
+        });
+    }
+
+    Ok(chain)
+}
+
 #[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
 #[ts(export, export_to = "playout_config.d.ts")]
 pub struct Storage {
@@ -492,8 +1019,82 @@ pub struct Storage {
     pub filler_path: PathBuf,
     pub extensions: Vec<String>,
     pub shuffle: bool,
+    #[serde(default)]
+    pub vod_path: String,
+    #[serde(default)]
+    pub vod_template: String,
+    #[serde(default)]
+    pub vod_hook: String,
+    #[serde(default)]
+    pub fallback: Vec<String>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub fallback_chain: Vec<FallbackStep>,
+    #[serde(default)]
+    pub fallback_clip: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub fallback_clip_path: PathBuf,
+    /// Image or video loop for the slate, which runs when no source can be played.
+    #[serde(default)]
+    pub slate: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub slate_path: PathBuf,
+    /// Audio, which loops under the slate, silence when it is empty.
+    #[serde(default)]
+    pub slate_audio: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub slate_audio_path: PathBuf,
+    /// Clip, which runs in front of an ad break.
+    #[serde(default)]
+    pub bumper_in: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub bumper_in_path: PathBuf,
+    /// Clip, which runs after an ad break.
+    #[serde(default)]
+    pub bumper_out: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub bumper_out_path: PathBuf,
+    /// File name patterns of uploads in progress, which folder mode ignores, like `*.part`.
+    #[serde(default)]
+    pub upload_ignore: String,
+    /// Seconds after the last change, before folder mode plays a new file.
+    #[serde(default)]
+    pub upload_min_age: u32,
+    /// FTP or SFTP drop folder, which gets polled for new media.
+    #[serde(default)]
+    pub incoming_url: String,
+    /// Target folder for incoming media, relative paths are inside the storage.
+    #[serde(default)]
+    pub incoming_path: String,
+    /// Poll interval in seconds.
+    #[serde(default)]
+    pub incoming_interval: u32,
+    /// Delete files from the drop folder after the transfer.
+    #[serde(default)]
+    pub incoming_delete: bool,
+    /// Transcode new media (uploads and incoming) to the house format before playout.
+    #[serde(default)]
+    pub transcode: bool,
+    /// Transcode jobs, which run at the same time.
+    #[serde(default)]
+    pub transcode_workers: u32,
+    /// Loudness target in LUFS, 0 disables the normalization.
+    #[serde(default)]
+    pub transcode_loudness: f64,
+    /// Hours before a clip can air again, in folder mode and in the playlist generator.
+    /// 0 disables the rule.
+    #[serde(default)]
+    pub separation: f64,
     #[serde(skip_deserializing)]
     pub shared_storage: bool,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub graphics: BTreeMap<String, Graphic>,
 }
 
 impl Storage {
@@ -509,7 +1110,38 @@ impl Storage {
                 .map(String::from)
                 .collect(),
             shuffle: config.storage_shuffle,
+            vod_path: config.storage_vod_path.clone(),
+            vod_template: config.storage_vod_template.clone(),
+            vod_hook: config.storage_vod_hook.clone(),
+            fallback: config
+                .storage_fallback
+                .split(';')
+                .filter(|s| !s.trim().is_empty())
+                .map(String::from)
+                .collect(),
+            fallback_chain: vec![],
+            fallback_clip: config.storage_fallback_clip.clone(),
+            fallback_clip_path: PathBuf::from(config.storage_fallback_clip.clone()),
+            slate: config.storage_slate.clone(),
+            slate_path: PathBuf::from(config.storage_slate.clone()),
+            slate_audio: config.storage_slate_audio.clone(),
+            slate_audio_path: PathBuf::from(config.storage_slate_audio.clone()),
+            bumper_in: config.storage_bumper_in.clone(),
+            bumper_in_path: PathBuf::from(config.storage_bumper_in.clone()),
+            bumper_out: config.storage_bumper_out.clone(),
+            bumper_out_path: PathBuf::from(config.storage_bumper_out.clone()),
+            upload_ignore: config.storage_upload_ignore.clone(),
+            upload_min_age: config.storage_upload_min_age,
+            incoming_url: config.storage_incoming_url.clone(),
+            incoming_path: config.storage_incoming_path.clone(),
+            incoming_interval: config.storage_incoming_interval,
+            incoming_delete: config.storage_incoming_delete,
+            transcode: config.storage_transcode,
+            transcode_workers: config.storage_transcode_workers,
+            transcode_loudness: config.storage_transcode_loudness,
+            separation: config.storage_separation,
             shared_storage,
+            graphics: BTreeMap::new(),
         }
     }
 }
@@ -535,6 +1167,24 @@ pub struct Text {
     pub text_from_filename: bool,
     pub style: String,
     pub regex: String,
+    /// RSS or JSON feed of the news ticker.
+    #[serde(default)]
+    pub ticker_url: String,
+    /// Seconds between the updates of the feed.
+    #[serde(default)]
+    pub ticker_interval: u32,
+    /// Speed of the crawl in pixels per second.
+    #[serde(default)]
+    pub ticker_speed: u32,
+    /// Text between the items of the feed.
+    #[serde(default)]
+    pub ticker_separator: String,
+    /// Hours, in which the ticker runs, like `06:00-09:00; 17:00-23:00`, empty is always.
+    #[serde(default)]
+    pub ticker_hours: String,
+    /// drawtext values of the ticker, without text and x.
+    #[serde(default)]
+    pub ticker_style: String,
 }
 
 impl Text {
@@ -549,6 +1199,12 @@ impl Text {
             text_from_filename: config.text_from_filename,
             style: config.text_style.clone(),
             regex: config.text_regex.clone(),
+            ticker_url: config.text_ticker_url.clone(),
+            ticker_interval: config.text_ticker_interval,
+            ticker_speed: config.text_ticker_speed,
+            ticker_separator: config.text_ticker_separator.clone(),
+            ticker_hours: config.text_ticker_hours.clone(),
+            ticker_style: config.text_ticker_style.clone(),
         }
     }
 }
@@ -558,23 +1214,129 @@ impl Text {
 pub struct Task {
     pub enable: bool,
     pub path: PathBuf,
-}
-
-impl Task {
-    fn new(config: &models::Configuration) -> Self {
-        Self {
-            enable: config.task_enable,
-            path: PathBuf::from(config.task_path.clone()),
-        }
-    }
-}
-
-#[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
-#[ts(export, export_to = "playout_config.d.ts")]
-pub struct Output {
+    /// Icecast admin metadata URL, with credentials and mount point.
+    #[serde(default)]
+    pub icecast_url: String,
+    /// Webhook, which gets the now playing metadata as JSON.
+    #[serde(default)]
+    pub webhook_url: String,
+    /// Hook commands for lifecycle events, see [hooks](crate::utils::hooks).
+    #[serde(default)]
+    pub hook_clip_start: String,
+    #[serde(default)]
+    pub hook_clip_end: String,
+    #[serde(default)]
+    pub hook_ingest_start: String,
+    #[serde(default)]
+    pub hook_ingest_stop: String,
+    #[serde(default)]
+    pub hook_error: String,
+    #[serde(default)]
+    pub hook_day_change: String,
+    /// Lua script, which decides about the next clip, see [script](crate::utils::script).
+    #[serde(default)]
+    pub script: PathBuf,
+    /// GPIO folder or serial port for triggers, see [triggers](crate::utils::triggers).
+    #[serde(default)]
+    pub trigger_device: String,
+    /// Debounce time of trigger inputs in milliseconds.
+    #[serde(default)]
+    pub trigger_debounce: u32,
+    /// Mapping table, `input=action` per line.
+    #[serde(default)]
+    pub trigger_map: String,
+    /// Listen address for the VDCP adapter, see [vdcp](crate::utils::vdcp).
+    #[serde(default)]
+    pub vdcp_address: String,
+    /// Listen address for Companion commands over TCP and UDP, see [companion](crate::utils::companion).
+    #[serde(default)]
+    pub companion_address: String,
+    /// Listen address for OSC commands, see [osc](crate::utils::osc).
+    #[serde(default)]
+    pub osc_address: String,
+    /// OSC feedback targets, `host:port` separated by comma.
+    #[serde(default)]
+    pub osc_feedback: String,
+    /// Role in a redundant pair, `active` or `passive`, see [redundancy](crate::utils::redundancy).
+    #[serde(default)]
+    pub redundancy_role: String,
+    /// UDP listen address for the heartbeat of the peer.
+    #[serde(default)]
+    pub redundancy_address: String,
+    /// Heartbeat address of the peer, `host:port`.
+    #[serde(default)]
+    pub redundancy_peer: String,
+    /// Seconds without heartbeat, before the passive instance takes over.
+    #[serde(default)]
+    pub redundancy_timeout: u32,
+}
+
+impl Task {
+    fn new(config: &models::Configuration) -> Self {
+        Self {
+            enable: config.task_enable,
+            path: PathBuf::from(config.task_path.clone()),
+            icecast_url: config.task_icecast_url.clone(),
+            webhook_url: config.task_webhook_url.clone(),
+            hook_clip_start: config.task_hook_clip_start.clone(),
+            hook_clip_end: config.task_hook_clip_end.clone(),
+            hook_ingest_start: config.task_hook_ingest_start.clone(),
+            hook_ingest_stop: config.task_hook_ingest_stop.clone(),
+            hook_error: config.task_hook_error.clone(),
+            hook_day_change: config.task_hook_day_change.clone(),
+            script: PathBuf::from(config.task_script.clone()),
+            trigger_device: config.task_trigger_device.clone(),
+            trigger_debounce: config.task_trigger_debounce,
+            trigger_map: config.task_trigger_map.clone(),
+            vdcp_address: config.task_vdcp_address.clone(),
+            companion_address: config.task_companion_address.clone(),
+            osc_address: config.task_osc_address.clone(),
+            osc_feedback: config.task_osc_feedback.clone(),
+            redundancy_role: config.task_redundancy_role.clone(),
+            redundancy_address: config.task_redundancy_address.clone(),
+            redundancy_peer: config.task_redundancy_peer.clone(),
+            redundancy_timeout: config.task_redundancy_timeout,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct Output {
     pub id: i32,
     pub mode: OutputMode,
     pub output_param: String,
+    #[serde(default)]
+    pub field_order: FieldOrder,
+    /// Audio only output in stream mode, like an Icecast mount for a web player.
+    #[serde(default)]
+    pub audio_url: String,
+    /// Encoder options for the audio output, can have `-f <format>`.
+    #[serde(default)]
+    pub audio_param: String,
+    /// Tags of the outputs in stream mode, one per output, comma separated.
+    /// Items with a tag in `blackout` show the slate on this output.
+    #[serde(default)]
+    pub tags: String,
+    /// Folder of the compliance recording in stream mode, empty is off.
+    #[serde(default)]
+    pub compliance_path: String,
+    /// Days, which the compliance recording is kept, `0` keeps it forever.
+    #[serde(default)]
+    pub compliance_days: u32,
+    /// Encoder options for the compliance recording.
+    #[serde(default)]
+    pub compliance_param: String,
+    /// Public url of the output (HLS playlist, RTMP, SRT), which gets checked, empty is off.
+    #[serde(default)]
+    pub check_url: String,
+    /// Seconds between the checks of the public output.
+    #[serde(default)]
+    pub check_interval: u32,
+    /// Audio languages of the outputs, one per output, comma separated, more languages of
+    /// one output with `+`, like `deu+eng, deu`.
+    #[serde(default)]
+    pub languages: String,
     #[ts(skip)]
     #[serde(skip_serializing, skip_deserializing)]
     pub output_count: usize,
@@ -584,6 +1346,9 @@ pub struct Output {
     #[ts(skip)]
     #[serde(skip_serializing, skip_deserializing)]
     pub output_cmd: Option<Vec<String>>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub zmq_socket: Option<String>,
 }
 
 impl Output {
@@ -598,9 +1363,20 @@ impl Output {
             id: output.id,
             mode: OutputMode::new(&output.name),
             output_param: output.parameters.clone(),
+            field_order: FieldOrder::new(&output.field_order),
+            audio_url: config.output_audio_url.clone(),
+            audio_param: config.output_audio_param.clone(),
+            tags: config.output_tags.clone(),
+            compliance_path: config.output_compliance_path.clone(),
+            compliance_days: config.output_compliance_days,
+            compliance_param: config.output_compliance_param.clone(),
+            check_url: config.output_check_url.clone(),
+            check_interval: config.output_check_interval,
+            languages: config.output_languages.clone(),
             output_count: 0,
             output_filter: None,
             output_cmd: None,
+            zmq_socket: None,
         }
     }
 }
@@ -619,6 +1395,7 @@ pub fn string_to_processing_mode(l: String) -> ProcessMode {
     match l.to_lowercase().as_str() {
         "playlist" => ProcessMode::Playlist,
         "folder" => ProcessMode::Folder,
+        "test" => ProcessMode::Test,
         _ => ProcessMode::Playlist,
     }
 }
@@ -637,6 +1414,90 @@ fn default_track_index() -> i32 {
     -1
 }
 
+/// Add encoder flags for interlaced coding in front of every output.
+pub fn interlace_flags(field_order: FieldOrder, cmd: &mut Vec<String>) {
+    let top = match field_order {
+        FieldOrder::Progressive => return,
+        FieldOrder::Tff => "1",
+        FieldOrder::Bff => "0",
+    };
+    let flags = vec_strings!["-flags", "+ildct+ilme", "-top", top];
+    let mut params = flags.clone();
+
+    for (i, param) in cmd.iter().enumerate() {
+        params.push(param.clone());
+
+        if i > 0 && i < cmd.len() - 1 && !param.starts_with('-') && !cmd[i - 1].starts_with('-') {
+            params.append(&mut flags.clone());
+        }
+    }
+
+    *cmd = params;
+}
+
+/// Add `-a53cc 1` to the video encoders, which can write A/53 captions.
+pub fn caption_flags(cmd: &mut Vec<String>) {
+    if cmd.iter().any(|p| p == "-a53cc") {
+        return;
+    }
+
+    let mut params = vec![];
+
+    for (i, param) in cmd.iter().enumerate() {
+        params.push(param.clone());
+
+        if i > 0
+            && CAPTION_ENCODERS.contains(&param.as_str())
+            && ["-c:v", "-codec:v", "-vcodec"].contains(&cmd[i - 1].as_str())
+        {
+            params.append(&mut vec_strings!["-a53cc", "1"]);
+        }
+    }
+
+    *cmd = params;
+}
+
+/// Subtitle rendition of the WebVTT stream in the HLS master playlist.
+///
+/// A `-var_stream_map` with one variant and without subtitles gets the subtitles,
+/// without a map the one variant gets created, together with the master playlist.
+/// Maps with more variants, and more audio tracks without a map, are left as they are.
+pub fn subtitle_rendition(cmd: &mut Vec<String>, audio_tracks: i32) {
+    const RENDITION: &str = "s:0,sgroup:subs,sname:Subtitles,default:YES";
+
+    if cmd.iter().any(|p| p == "tee") || cmd.len() < 2 {
+        return;
+    }
+
+    if let Some(i) = cmd.iter().position(|p| p == "-var_stream_map") {
+        if let Some(map) = cmd.get_mut(i + 1)
+            && map.split_whitespace().count() == 1
+            && !map.contains("s:")
+        {
+            map.push_str(&format!(",{RENDITION}"));
+        }
+
+        return;
+    }
+
+    if audio_tracks > 1 {
+        return;
+    }
+
+    let mut params = vec_strings!["-var_stream_map", format!("v:0,a:0,{RENDITION}")];
+
+    if !cmd.iter().any(|p| p == "-master_pl_name") {
+        params.append(&mut vec_strings!["-master_pl_name", "master.m3u8"]);
+    }
+
+    let last = cmd.len() - 1;
+    cmd.splice(last..last, params);
+}
+
+fn default_tonemap_algorithm() -> String {
+    "hable".to_string()
+}
+
 impl PlayoutConfig {
     pub async fn new(
         pool: &Pool<Sqlite>,
@@ -647,12 +1508,53 @@ impl PlayoutConfig {
         let channel = handles::select_channel(pool, &channel_id).await?;
         let mut config = handles::select_configuration(pool, channel_id).await?;
         let adv_config = handles::select_advanced_configuration(pool, channel_id).await?;
-        let outputs = handles::select_outputs(pool, channel_id).await?;
+        let mut outputs = handles::select_outputs(pool, channel_id).await?;
+
+        if let Some(overrides) = CONFIG_OVERRIDES.get() {
+            apply_overrides(&mut config, &mut outputs, overrides)?;
+        }
 
         if let Some(id) = output_id {
             config.output_id = id;
         }
 
+        Self::build(global, channel, config, adv_config, outputs).await
+    }
+
+    /// Build a config from submitted settings, without saving them, for the staging preview.
+    pub async fn staged(
+        pool: &Pool<Sqlite>,
+        channel_id: i32,
+        data: PlayoutConfig,
+    ) -> Result<Self, ServiceError> {
+        let global = handles::select_global(pool).await?;
+        let channel = handles::select_channel(pool, &channel_id).await?;
+        let adv_config = handles::select_advanced_configuration(pool, channel_id).await?;
+        let mut outputs = handles::select_outputs(pool, channel_id).await?;
+
+        for output in &mut outputs {
+            if output.id == data.output.id {
+                output.parameters.clone_from(&data.output.output_param);
+                output.field_order = data.output.field_order.to_string();
+            }
+        }
+
+        let mut config = models::Configuration::from(data.general.id, channel_id, data);
+
+        if let Some(overrides) = CONFIG_OVERRIDES.get() {
+            apply_overrides(&mut config, &mut outputs, overrides)?;
+        }
+
+        Self::build(global, channel, config, adv_config, outputs).await
+    }
+
+    async fn build(
+        global: models::GlobalSettings,
+        channel: models::Channel,
+        config: models::Configuration,
+        adv_config: models::AdvancedConfiguration,
+        outputs: Vec<models::Output>,
+    ) -> Result<Self, ServiceError> {
         let channel_data = channel.clone();
         let channel = Channel::new(&global, channel);
         let advanced = AdvancedConfig::new(adv_config);
@@ -667,7 +1569,9 @@ impl PlayoutConfig {
         let mut output = Output::new(&config, outputs);
         let mut storage = Storage::new(&config, channel.storage.clone(), channel.shared);
 
-        if !channel.playlists.is_dir() {
+        if !channel.playlists.is_dir()
+            && Backend::new(&channel.playlists.to_string_lossy()).is_local()
+        {
             fs::create_dir_all(&channel.playlists).await?;
         }
 
@@ -679,6 +1583,51 @@ impl PlayoutConfig {
 
         storage.filler = filler;
         storage.filler_path = filler_path;
+        storage.fallback_chain = fallback_chain(&storage.fallback).unwrap_or_else(|e| {
+            error!("{e}");
+            fallback_chain(&[]).unwrap_or_default()
+        });
+
+        if !config.storage_fallback_clip.is_empty() {
+            let (clip_path, _, clip) =
+                norm_abs_path(&channel.storage, &config.storage_fallback_clip)?;
+
+            storage.fallback_clip = clip;
+            storage.fallback_clip_path = clip_path;
+        }
+
+        if !config.storage_slate.is_empty() {
+            let (slate_path, _, slate) = norm_abs_path(&channel.storage, &config.storage_slate)?;
+
+            storage.slate = slate;
+            storage.slate_path = slate_path;
+        }
+
+        storage.graphics = graphics::templates(&channel.storage).await;
+
+        if !config.storage_slate_audio.is_empty() {
+            let (audio_path, _, audio) =
+                norm_abs_path(&channel.storage, &config.storage_slate_audio)?;
+
+            storage.slate_audio = audio;
+            storage.slate_audio_path = audio_path;
+        }
+
+        if !config.storage_bumper_in.is_empty() {
+            let (bumper_path, _, bumper) =
+                norm_abs_path(&channel.storage, &config.storage_bumper_in)?;
+
+            storage.bumper_in = bumper;
+            storage.bumper_in_path = bumper_path;
+        }
+
+        if !config.storage_bumper_out.is_empty() {
+            let (bumper_path, _, bumper) =
+                norm_abs_path(&channel.storage, &config.storage_bumper_out)?;
+
+            storage.bumper_out = bumper;
+            storage.bumper_out_path = bumper_path;
+        }
 
         playlist.start_sec = Some(time_to_sec(&playlist.day_start, &channel.timezone));
 
@@ -740,6 +1689,10 @@ impl PlayoutConfig {
                 "-mpegts_flags",
                 "initial_discontinuity"
             ]);
+
+            if processing.captions {
+                process_cmd.append(&mut vec_strings!["-a53cc", "1"]);
+            }
         }
 
         if processing.copy_audio {
@@ -752,7 +1705,11 @@ impl PlayoutConfig {
             ));
         }
 
-        process_cmd.append(&mut vec_strings!["-f", "mpegts", "-"]);
+        if !processing.copy_audio {
+            process_cmd.append(&mut languages::metadata(&processing));
+        }
+
+        process_cmd.append(&mut vec_strings!["-f", PIPE_FORMAT, "-"]);
 
         processing.cmd = Some(process_cmd);
 
@@ -763,7 +1720,11 @@ impl PlayoutConfig {
 
         if output.mode == OutputMode::Null {
             output.output_cmd = Some(vec_strings!["-f", "null", "-"]);
-        } else if let Some(mut cmd) = split(output.output_param.as_str()) {
+        } else if let Some(mut cmd) = split(&fill_path(&output.output_param, channel_data.id)) {
+            if output.mode == OutputMode::HLS && processing.vtt_enable && !processing.audio_only {
+                subtitle_rendition(&mut cmd, processing.audio_tracks);
+            }
+
             // get output count according to the var_stream_map value, or by counting output parameters
             if let Some(i) = cmd.clone().iter().position(|m| m == "-var_stream_map") {
                 output.output_count = cmd[i + 1].split_whitespace().count();
@@ -775,6 +1736,10 @@ impl PlayoutConfig {
                     .count();
             }
 
+            if output.mode == OutputMode::HLS && !processing.audio_only {
+                languages::hls_renditions(&mut cmd, &processing);
+            }
+
             if let Some(i) = cmd.clone().iter().position(|r| r == "-filter_complex") {
                 output.output_filter = Some(cmd[i + 1].clone());
                 cmd.remove(i);
@@ -836,6 +1801,12 @@ impl PlayoutConfig {
                 }
             }
 
+            interlace_flags(output.field_order, &mut cmd);
+
+            if processing.captions {
+                caption_flags(&mut cmd);
+            }
+
             output.output_cmd = Some(cmd);
         }
 
@@ -852,6 +1823,43 @@ impl PlayoutConfig {
             text.node_pos = None;
         }
 
+        let mut taken = vec![
+            text.zmq_stream_socket.clone(),
+            text.zmq_server_socket.clone(),
+        ];
+        let mut sockets = vec![];
+
+        // sockets for the zmq filter of decoder and ingest, which get the live control commands
+        if processing.live_control {
+            sockets.push(&mut processing.zmq_stream_socket);
+            sockets.push(&mut processing.zmq_server_socket);
+        }
+
+        // socket of the encoder, which switches tagged outputs to the slate and updates
+        // the graphics and the ticker
+        let tagged = output.mode == OutputMode::Stream
+            && output.output_count > 1
+            && !output.tags.trim().is_empty();
+        let overlays = !storage.graphics.is_empty() || !text.ticker_url.trim().is_empty();
+
+        if output.output_filter.is_none()
+            && (tagged || (output.mode != OutputMode::HLS && overlays))
+        {
+            sockets.push(&mut output.zmq_socket);
+        }
+
+        for socket in sockets {
+            for _ in 0..10 {
+                let free = gen_tcp_socket("").await;
+
+                if !taken.contains(&free) {
+                    taken.push(free.clone());
+                    *socket = free;
+                    break;
+                }
+            }
+        }
+
         let (font_path, _, font) = norm_abs_path(&channel.storage, &text.font)?;
         text.font = font;
         text.font_path = font_path.to_string_lossy().to_string();
@@ -883,11 +1891,12 @@ impl PlayoutConfig {
 
     pub async fn import(pool: &Pool<Sqlite>, id: i32, path: &Path) -> Result<(), ServiceError> {
         if path.is_file() {
-            let mut file = tokio::fs::File::open(path).await?;
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).await?;
+            let contents = read_config_file(path).await?;
+            let (config, unknown) = from_toml::<PlayoutConfig>(&contents)?;
 
-            let config: PlayoutConfig = toml_edit::de::from_str(&contents).unwrap();
+            for key in unknown {
+                warn!("Unknown key '{key}' in {}, ignored", path.display());
+            }
 
             // Update channel settings if they're provided in the TOML
             let mut channel_updated = false;
@@ -998,7 +2007,7 @@ pub async fn get_config(
 
         let mut template: Template = serde_json::from_slice(&buffer)?;
 
-        template.sources.sort_by(|d1, d2| d1.start.cmp(&d2.start));
+        template.sources.sort_by_key(|d1| d1.start);
 
         config.general.template = Some(template);
     }
@@ -1073,5 +2082,690 @@ pub async fn get_config(
         config.general.public_path = public;
     }
 
+    for (level, msg) in check_format(&config) {
+        if level != Level::Error {
+            warn!(target: Target::file_mail(), channel = channel_id; "{msg}");
+        }
+    }
+
+    format_errors(&config)?;
+
     Ok(config)
 }
+
+/// Reject the config, when `check_format` finds errors.
+pub fn format_errors(config: &PlayoutConfig) -> Result<(), ServiceError> {
+    let errors = check_format(config)
+        .into_iter()
+        .filter(|(level, _)| *level == Level::Error)
+        .map(|(_, msg)| msg)
+        .collect::<Vec<_>>();
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    Err(ServiceError::Conflict(format!(
+        "Channel {} has invalid format settings:\n- {}",
+        config.general.channel_id,
+        errors.join("\n- ")
+    )))
+}
+
+/// Count the arguments a filter template takes: `{}` in order, or `{0}`, `{1}`, ... by index.
+fn template_args(template: &str) -> usize {
+    let mut count = 0;
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '{' {
+            match chars.next() {
+                Some('}') => count += 1,
+                Some(n) if n.is_ascii_digit() => {
+                    count = count.max(n.to_digit(10).unwrap_or_default() as usize + 1);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    count
+}
+
+/// Cross-check processing, output and filter settings, before ffmpeg fails on them.
+pub fn check_format(config: &PlayoutConfig) -> Vec<(Level, String)> {
+    let mut findings = vec![];
+    let proc = &config.processing;
+
+    for (name, path) in [
+        ("Public", config.channel.public.to_string_lossy()),
+        ("Playlist", config.channel.playlists.to_string_lossy()),
+        ("Storage", config.channel.storage.to_string_lossy()),
+        ("Output", config.output.output_param.as_str().into()),
+    ] {
+        if path.contains("{date}") {
+            findings.push((
+                Level::Error,
+                format!("{name} path can't have a {{date}} placeholder: {path}"),
+            ));
+        }
+    }
+
+    if !valid_cgroup(&config.general.cgroup) {
+        findings.push((
+            Level::Error,
+            format!(
+                "Cgroup {} must be a path below /sys/fs/cgroup",
+                config.general.cgroup
+            ),
+        ));
+    }
+
+    if !proc.audio_only {
+        if proc.width <= 0 || proc.height <= 0 {
+            findings.push((
+                Level::Error,
+                format!(
+                    "Resolution {}x{} is invalid, set processing width and height",
+                    proc.width, proc.height
+                ),
+            ));
+        } else if proc.width % 2 != 0 || proc.height % 2 != 0 {
+            findings.push((
+                Level::Error,
+                format!(
+                    "Resolution {}x{} must have even width and height for yuv420p",
+                    proc.width, proc.height
+                ),
+            ));
+        } else if (proc.aspect - proc.width as f64 / proc.height as f64).abs() > 0.1 {
+            findings.push((
+                Level::Warn,
+                format!(
+                    "Aspect {} differs from resolution {}x{}, pixels will not be square",
+                    proc.aspect, proc.width, proc.height
+                ),
+            ));
+        }
+
+        if proc.fps <= 0.0 || proc.fps > 240.0 {
+            findings.push((
+                Level::Error,
+                format!(
+                    "Frame rate {} is out of range, set processing fps",
+                    proc.fps
+                ),
+            ));
+        }
+    }
+
+    if !proc.copy_audio {
+        if proc.audio_tracks < 1 {
+            findings.push((
+                Level::Error,
+                "At least one audio track is needed".to_string(),
+            ));
+        } else if proc.audio_track_index >= proc.audio_tracks {
+            findings.push((
+                Level::Error,
+                format!(
+                    "Audio track index {} does not exist, only {} track(s) are processed",
+                    proc.audio_track_index, proc.audio_tracks
+                ),
+            ));
+        }
+
+        if proc.description_track > 0 && proc.description_track as i32 >= proc.audio_tracks {
+            findings.push((
+                Level::Error,
+                format!(
+                    "Description track {} does not exist, only {} track(s) are processed",
+                    proc.description_track, proc.audio_tracks
+                ),
+            ));
+        }
+
+        let s302m = proc
+            .cmd
+            .as_ref()
+            .is_some_and(|c| c.iter().any(|a| a == "s302m"));
+
+        if s302m && ![2, 4, 6, 8].contains(&proc.audio_channels) {
+            findings.push((
+                Level::Error,
+                format!(
+                    "{} audio channels are not supported by the s302m pipe, use 2, 4, 6 or 8",
+                    proc.audio_channels
+                ),
+            ));
+        }
+    }
+
+    let value_of = |cmd: &Option<Vec<String>>, key: &str| {
+        cmd.as_ref().and_then(|c| {
+            c.iter()
+                .position(|a| a == key)
+                .and_then(|i| c.get(i + 1).cloned())
+        })
+    };
+
+    // decoder output must fit to the channel format, the encoder expects it
+    let decoder = &config.advanced.decoder.output_cmd;
+
+    if let Some(rate) = value_of(decoder, "-r").and_then(|r| r.parse::<f64>().ok())
+        && rate != proc.fps
+    {
+        findings.push((
+            Level::Error,
+            format!(
+                "Decoder output rate -r {rate} differs from processing fps {}",
+                proc.fps
+            ),
+        ));
+    }
+
+    if let Some(size) = value_of(decoder, "-s")
+        && size != format!("{}x{}", proc.width, proc.height)
+    {
+        findings.push((
+            Level::Error,
+            format!(
+                "Decoder output size -s {size} differs from processing resolution {}x{}",
+                proc.width, proc.height
+            ),
+        ));
+    }
+
+    if let Some(rate) =
+        value_of(&config.output.output_cmd, "-r").and_then(|r| r.parse::<f64>().ok())
+        && rate != proc.fps
+    {
+        findings.push((
+            Level::Warn,
+            format!(
+                "Output rate -r {rate} differs from processing fps {}, frames will be converted",
+                proc.fps
+            ),
+        ));
+    }
+
+    let filter = &config.advanced.filter;
+    let templates = [
+        ("deinterlace", &filter.deinterlace, 0),
+        ("pad_video", &filter.pad_video, 2),
+        ("fps", &filter.fps, 1),
+        ("scale", &filter.scale, 2),
+        ("set_dar", &filter.set_dar, 1),
+        ("fade_in", &filter.fade_in, 1),
+        ("fade_out", &filter.fade_out, 1),
+        ("logo", &filter.logo, 2),
+        ("overlay_logo_scale", &filter.overlay_logo_scale, 1),
+        ("overlay_logo_fade_in", &filter.overlay_logo_fade_in, 0),
+        ("overlay_logo_fade_out", &filter.overlay_logo_fade_out, 1),
+        ("overlay_logo", &filter.overlay_logo, 1),
+        ("tpad", &filter.tpad, 1),
+        ("drawtext_from_file", &filter.drawtext_from_file, 3),
+        ("drawtext_from_zmq", &filter.drawtext_from_zmq, 2),
+        ("aevalsrc", &filter.aevalsrc, 1),
+        ("afade_in", &filter.afade_in, 1),
+        ("afade_out", &filter.afade_out, 1),
+        ("apad", &filter.apad, 1),
+        ("volume", &filter.volume, 1),
+        ("split", &filter.split, 2),
+    ];
+
+    for (name, template, max) in templates {
+        if let Some(t) = template
+            && template_args(t) > max
+        {
+            findings.push((
+                Level::Error,
+                format!(
+                    "Advanced filter '{name}' takes at most {max} placeholder(s), but has {}: {t}",
+                    template_args(t)
+                ),
+            ));
+        }
+    }
+
+    findings
+}
+
+/// Read a TOML or JSON config file and merge its includes, the result is a TOML string.
+///
+/// With `include = ["shared.toml"]` the listed files are loaded first, relative to the file,
+/// and the keys of the including file win. So common settings can live in one place.
+pub async fn read_config_file(path: &Path) -> Result<String, ServiceError> {
+    let mut doc = DocumentMut::new();
+
+    merge_config_file(path, &mut doc, &mut vec![]).await?;
+
+    Ok(doc.to_string())
+}
+
+async fn merge_config_file(
+    path: &Path,
+    doc: &mut DocumentMut,
+    stack: &mut Vec<PathBuf>,
+) -> Result<(), ServiceError> {
+    let path = fs::canonicalize(path)
+        .await
+        .map_err(|e| ServiceError::BadRequest(format!("{}: {e}", path.display())))?;
+
+    if stack.contains(&path) {
+        return Err(ServiceError::BadRequest(format!(
+            "{}: include loop",
+            path.display()
+        )));
+    }
+
+    let contents = fs::read_to_string(&path).await?;
+    let mut file_doc = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => {
+            let value: serde_json::Value = serde_json::from_str(&contents)
+                .map_err(|e| ServiceError::BadRequest(format!("{}: {e}", path.display())))?;
+            toml_edit::ser::to_document(&value)?
+        }
+        _ => contents
+            .parse::<DocumentMut>()
+            .map_err(|e| ServiceError::BadRequest(format!("{}: {e}", path.display())))?,
+    };
+
+    if let Some(include) = file_doc.remove("include") {
+        let files = match include.as_array() {
+            Some(list) => list.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>(),
+            None => include.as_str().into_iter().collect(),
+        };
+        let parent = path.parent().unwrap_or(Path::new("."));
+
+        stack.push(path.clone());
+
+        for file in files {
+            Box::pin(merge_config_file(&parent.join(file), doc, stack)).await?;
+        }
+
+        stack.pop();
+    }
+
+    merge_tables(doc.as_table_mut(), file_doc.as_table());
+
+    Ok(())
+}
+
+fn merge_tables(base: &mut dyn TableLike, overlay: &dyn TableLike) {
+    for (key, item) in overlay.iter() {
+        match (base.get_mut(key), item.as_table_like()) {
+            (Some(base_item), Some(table)) if base_item.is_table_like() => {
+                if let Some(base_table) = base_item.as_table_like_mut() {
+                    merge_tables(base_table, table);
+                }
+            }
+            _ => {
+                base.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+/// Keys which are also accepted under an older name.
+const KEY_ALIASES: [(&str, &str); 2] = [("out", "output"), ("fontfile", "font")];
+
+/// Deserialize a TOML config strict, type errors name the key path, like `processing.width`.
+///
+/// Keys which the config doesn't know are returned, so they can be reported instead of silently dropped.
+pub fn from_toml<T>(contents: &str) -> Result<(T, Vec<String>), ServiceError>
+where
+    T: DeserializeOwned + Serialize,
+{
+    let deserializer = toml_edit::de::Deserializer::parse(contents)
+        .map_err(|e| ServiceError::BadRequest(e.to_string()))?;
+    let config: T = serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        ServiceError::BadRequest(format!("Key '{}': {}", e.path(), e.inner().message()))
+    })?;
+
+    let doc = contents.parse::<DocumentMut>()?;
+    let known = toml_edit::ser::to_string(&config)?.parse::<DocumentMut>()?;
+    let mut unknown = vec![];
+
+    unknown_keys(doc.as_table(), known.as_table(), "", &mut unknown);
+
+    Ok((config, unknown))
+}
+
+fn unknown_keys(
+    table: &dyn TableLike,
+    known: &dyn TableLike,
+    path: &str,
+    unknown: &mut Vec<String>,
+) {
+    for (key, item) in table.iter() {
+        let full_key = if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        };
+        let name = KEY_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == key)
+            .map_or(key, |(_, name)| name);
+
+        match known.get(name) {
+            Some(known_item) => {
+                if let (Some(t), Some(k)) = (item.as_table_like(), known_item.as_table_like()) {
+                    unknown_keys(t, k, &full_key, unknown);
+                }
+            }
+            None => unknown.push(full_key),
+        }
+    }
+}
+
+/// Check an output address, like `srt://127.0.0.1:40051?mode=caller`.
+fn check_url(url: &str) -> Option<String> {
+    let (protocol, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let (host, port) = if let Some(v6) = host.strip_prefix('[') {
+        v6.split_once(']')
+            .map_or(("", None), |(h, p)| (h, p.strip_prefix(':')))
+    } else {
+        host.split_once(':')
+            .map_or((host, None), |(h, p)| (h, Some(p)))
+    };
+
+    if host.is_empty() {
+        return Some(format!("Output address '{url}' has no host"));
+    }
+
+    match port {
+        Some(p) if p.parse::<u16>().is_err() => {
+            Some(format!("Output address '{url}' has invalid port '{p}'"))
+        }
+        None if ["rist", "rtp", "srt", "tcp", "udp"].contains(&protocol) => {
+            Some(format!("Output address '{url}' needs a port"))
+        }
+        _ => None,
+    }
+}
+
+/// Full config check, for `--check-config`: format, paths and output addresses.
+pub fn check_config(config: &PlayoutConfig) -> Vec<(Level, String)> {
+    let mut findings = check_format(config);
+    let mut paths = vec![
+        ("Storage", &config.channel.storage),
+        ("Playlist", &config.channel.playlists),
+        ("Log", &config.channel.logs),
+    ];
+
+    if config.output.mode == OutputMode::HLS {
+        paths.push(("Public", &config.channel.public));
+    }
+
+    for (name, path) in paths {
+        if !path.is_dir() {
+            findings.push((
+                Level::Error,
+                format!("{name} path '{}' not exists", path.display()),
+            ));
+        }
+    }
+
+    for e in audio_chains::check(config) {
+        findings.push((Level::Error, e));
+    }
+
+    for w in languages::check(config) {
+        findings.push((Level::Warn, w));
+    }
+
+    if !config.storage.filler_path.exists() {
+        findings.push((
+            Level::Warn,
+            format!(
+                "Filler '{}' not exists, the next fallback is used",
+                config.storage.filler_path.display()
+            ),
+        ));
+    }
+
+    if config.processing.tonemap
+        && !TONEMAP_ALGORITHMS.contains(&config.processing.tonemap_algorithm.as_str())
+    {
+        findings.push((
+            Level::Error,
+            format!(
+                "Tonemap algorithm '{}' is unknown, use one of: {}",
+                config.processing.tonemap_algorithm,
+                TONEMAP_ALGORITHMS.join(", ")
+            ),
+        ));
+    }
+
+    if config.processing.ltc_channel > config.processing.audio_channels {
+        findings.push((
+            Level::Error,
+            format!(
+                "LTC channel {} is higher than the audio channels ({})",
+                config.processing.ltc_channel, config.processing.audio_channels
+            ),
+        ));
+    } else if config.processing.ltc_channel > 0 && config.processing.copy_audio {
+        findings.push((
+            Level::Warn,
+            "LTC can't be generated in audio copy mode".to_string(),
+        ));
+    }
+
+    match fallback_chain(&config.storage.fallback) {
+        Ok(chain) => {
+            if chain.iter().any(|s| s.source == FallbackSource::Clip)
+                && !config.storage.fallback_clip.is_empty()
+                && !config.storage.fallback_clip_path.is_file()
+            {
+                findings.push((
+                    Level::Warn,
+                    format!(
+                        "Fallback clip '{}' not exists",
+                        config.storage.fallback_clip_path.display()
+                    ),
+                ));
+            }
+        }
+        Err(e) => findings.push((Level::Error, e)),
+    }
+
+    if !config.storage.slate.is_empty() && !config.storage.slate_path.is_file() {
+        findings.push((
+            Level::Warn,
+            format!(
+                "Slate image '{}' not exists, black is used",
+                config.storage.slate_path.display()
+            ),
+        ));
+    }
+
+    if !config.storage.slate_audio.is_empty() && !config.storage.slate_audio_path.is_file() {
+        findings.push((
+            Level::Warn,
+            format!(
+                "Slate audio '{}' not exists, silence is used",
+                config.storage.slate_audio_path.display()
+            ),
+        ));
+    }
+
+    for (bumper, path) in [
+        (&config.storage.bumper_in, &config.storage.bumper_in_path),
+        (&config.storage.bumper_out, &config.storage.bumper_out_path),
+    ] {
+        if !bumper.is_empty() && !path.is_file() {
+            findings.push((
+                Level::Warn,
+                format!(
+                    "Bumper '{}' not exists, breaks run without it",
+                    path.display()
+                ),
+            ));
+        }
+    }
+
+    if config.text.add_text && !Path::new(&config.text.font_path).is_file() {
+        findings.push((
+            Level::Warn,
+            format!("Font '{}' not exists", config.text.font_path),
+        ));
+    }
+
+    if config.output.mode != OutputMode::Null {
+        if !config.output.compliance_path.trim().is_empty()
+            && config.output.mode != OutputMode::Stream
+        {
+            findings.push((
+                Level::Warn,
+                "Compliance recording runs only in stream mode".to_string(),
+            ));
+        }
+
+        if !config.output.audio_url.is_empty() {
+            if config.output.mode != OutputMode::Stream {
+                findings.push((
+                    Level::Warn,
+                    "Audio output runs only in stream mode".to_string(),
+                ));
+            }
+
+            if let Some(msg) = check_url(&config.output.audio_url) {
+                findings.push((Level::Error, msg));
+            }
+        }
+
+        for item in config.output.output_cmd.iter().flatten() {
+            // tee muxer targets are separated by '|' and have options in brackets
+            for target in item.split('|') {
+                let target = target.rsplit(']').next().unwrap_or_default();
+
+                if target.contains("://")
+                    && let Some(msg) = check_url(target)
+                {
+                    findings.push((Level::Error, msg));
+                }
+            }
+        }
+    }
+
+    findings
+}
+
+static CONFIG_OVERRIDES: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+/// Config keys which have a different column name in the database.
+const OVERRIDE_COLUMNS: [(&str, &str); 6] = [
+    ("ingest.custom_filter", "ingest_filter"),
+    ("ingest.input_param", "ingest_param"),
+    ("logging.ignore_lines", "logging_ignore"),
+    ("processing.custom_filter", "processing_filter"),
+    ("text.add_text", "text_add"),
+    ("text.text_from_filename", "text_from_filename"),
+];
+
+/// Collect config overrides from `FFPLAYOUT__<SECTION>__<KEY>` variables and
+/// `--override section.key=value` arguments, arguments win over variables.
+/// Variables with a `_FILE` suffix read the value from a file, like a mounted secret.
+pub fn set_overrides(args: &[String]) -> Result<(), ServiceError> {
+    let mut overrides = vec![];
+
+    for (key, value) in env::vars() {
+        let Some(key) = key.strip_prefix(OVERRIDE_ENV_PREFIX) else {
+            continue;
+        };
+        let key = key.to_lowercase().replace("__", ".");
+
+        // secret mounts, like: FFPLAYOUT__OUTPUT__OUTPUT_PARAM_FILE=/run/secrets/stream
+        if let Some(key) = key.strip_suffix("_file") {
+            let secret = std::fs::read_to_string(&value)
+                .map_err(|e| ServiceError::BadRequest(format!("Override '{key}': {value}: {e}")))?;
+
+            overrides.push((key.to_string(), secret.trim_end().to_string()));
+        } else {
+            overrides.push((key, value));
+        }
+    }
+
+    for arg in args {
+        let (key, value) = arg.split_once('=').ok_or(ServiceError::BadRequest(format!(
+            "Override '{arg}' needs the form: section.key=value"
+        )))?;
+
+        overrides.push((key.trim().to_lowercase(), value.to_string()));
+    }
+
+    let _ = CONFIG_OVERRIDES.set(overrides);
+
+    Ok(())
+}
+
+/// Apply overrides to the stored channel config, values are parsed to the type of the key.
+///
+/// `output.mode` selects the output by name and `output.output_param` changes its parameters.
+pub fn apply_overrides(
+    config: &mut models::Configuration,
+    outputs: &mut [models::Output],
+    overrides: &[(String, String)],
+) -> Result<(), ServiceError> {
+    let mut values = serde_json::to_value(&*config)?;
+    let mut output_param = None;
+
+    for (key, value) in overrides {
+        let (section, field) = key.split_once('.').unwrap_or(("", key));
+        let section = if section == "out" { "output" } else { section };
+        let key = format!("{section}.{field}");
+        let invalid =
+            || ServiceError::BadRequest(format!("Override '{key}': invalid value '{value}'"));
+
+        match key.as_str() {
+            "output.mode" => {
+                let output = outputs
+                    .iter()
+                    .find(|o| o.name == value.to_lowercase())
+                    .ok_or_else(invalid)?;
+
+                values["output_id"] = output.id.into();
+            }
+            "output.output_param" => output_param = Some(value.clone()),
+            _ => {
+                let column = OVERRIDE_COLUMNS
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map_or(key.replace('.', "_"), |(_, c)| c.to_string());
+
+                let current = values
+                    .get_mut(&column)
+                    .filter(|_| !section.is_empty())
+                    .ok_or(ServiceError::BadRequest(format!(
+                        "Override '{key}': unknown config key"
+                    )))?;
+
+                *current = match current {
+                    serde_json::Value::Bool(_) => value.parse::<bool>().ok().map(Into::into),
+                    serde_json::Value::Number(n) if n.is_f64() => {
+                        value.parse::<f64>().ok().map(Into::into)
+                    }
+                    serde_json::Value::Number(_) => value.parse::<i64>().ok().map(Into::into),
+                    _ => Some(value.clone().into()),
+                }
+                .ok_or_else(invalid)?;
+            }
+        }
+    }
+
+    *config = serde_json::from_value(values)
+        .map_err(|e| ServiceError::BadRequest(format!("Override: {e}")))?;
+
+    if let Some(param) = output_param
+        && let Some(output) = outputs.iter_mut().find(|o| o.id == config.output_id)
+    {
+        output.parameters = param;
+    }
+
+    Ok(())
+}
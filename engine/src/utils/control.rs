@@ -1,17 +1,34 @@
-use std::{error::Error, fmt, str::FromStr, sync::atomic::Ordering};
+use std::{
+    collections::HashMap, error::Error, fmt, path::Path, str::FromStr, sync::atomic::Ordering,
+};
 
 use log::*;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value, json};
 use sqlx::{Pool, Sqlite};
+use tokio::time::{Duration, sleep, timeout};
 use zeromq::{Socket, SocketRecv, SocketSend, ZmqMessage};
 
 use crate::db::handles;
+use crate::file::norm_abs_path;
 use crate::player::{
     controller::{ChannelManager, ProcessUnit::*},
-    utils::{get_delta, get_media_map},
+    filter::{alternate, graphics},
+    input::playlist::HOLD,
+    utils::{
+        Media, Origin, StartMode, get_delta, get_media_map, is_filler, is_remote,
+        json_serializer::fit_hard_starts, playlist_seconds, resume, sec_to_time, time_to_sec,
+    },
+};
+use crate::utils::{
+    TextFilter,
+    config::{OutputMode::*, PlayoutConfig, ProcessMode::Playlist},
+    errors::ServiceError,
+    logging::Target,
+    playlist::{read_playlist, write_playlist},
 };
-use crate::utils::{TextFilter, config::OutputMode::*, errors::ServiceError, logging::Target};
+
+const ZMQ_TIMEOUT: Duration = Duration::from_secs(3);
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ControlParams {
@@ -25,6 +42,8 @@ pub enum ProcessCtl {
     Status,
     Start,
     Stop,
+    /// Stop after the current clip.
+    Drain,
     Restart,
 }
 
@@ -36,6 +55,7 @@ impl FromStr for ProcessCtl {
             "status" => Ok(Self::Status),
             "start" => Ok(Self::Start),
             "stop" => Ok(Self::Stop),
+            "drain" => Ok(Self::Drain),
             "restart" => Ok(Self::Restart),
             _ => Err(format!("Command '{input}' not found!")),
         }
@@ -48,6 +68,7 @@ impl fmt::Display for ProcessCtl {
             Self::Status => write!(f, "status"),
             Self::Start => write!(f, "start"),
             Self::Stop => write!(f, "stop"),
+            Self::Drain => write!(f, "drain"),
             Self::Restart => write!(f, "restart"),
         }
     }
@@ -60,6 +81,14 @@ pub enum PlayerCtl {
     Next,
     #[default]
     Reset,
+    /// Switch to the standby slate, until it gets released.
+    Slate,
+    /// Release the slate, back to the schedule at its current position.
+    Resume,
+    /// Hold the schedule at the next clip boundary.
+    Hold,
+    /// Release the hold, the schedule continues with the next item and is delayed.
+    Release,
 }
 
 impl FromStr for PlayerCtl {
@@ -70,6 +99,10 @@ impl FromStr for PlayerCtl {
             "back" => Ok(Self::Back),
             "next" => Ok(Self::Next),
             "reset" => Ok(Self::Reset),
+            "slate" => Ok(Self::Slate),
+            "resume" => Ok(Self::Resume),
+            "hold" => Ok(Self::Hold),
+            "release" => Ok(Self::Release),
             _ => Err(format!("Command '{input}' not found!")),
         }
     }
@@ -81,6 +114,82 @@ impl fmt::Display for PlayerCtl {
             Self::Back => write!(f, "back"),
             Self::Next => write!(f, "next"),
             Self::Reset => write!(f, "reset"),
+            Self::Slate => write!(f, "slate"),
+            Self::Resume => write!(f, "resume"),
+            Self::Hold => write!(f, "hold"),
+            Self::Release => write!(f, "release"),
+        }
+    }
+}
+
+/// How the schedule reacts on a inserted clip.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InsertPolicy {
+    /// Push all following clips back.
+    #[default]
+    Shift,
+    /// Remove or shorten the following filler clips, to recover the time.
+    DropFiller,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct InsertParams {
+    pub source: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default, rename = "in")]
+    pub seek: f64,
+    #[serde(default)]
+    pub out: Option<f64>,
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub policy: InsertPolicy,
+}
+
+/// Change of a pending item in the running program.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ItemEdit {
+    pub index: usize,
+    /// Swap the item, without `in` and `out` the new clip plays in full length.
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default, rename = "in")]
+    pub seek: Option<f64>,
+    #[serde(default)]
+    pub out: Option<f64>,
+}
+
+/// Resume the schedule with a pending item.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ResumeParams {
+    pub index: usize,
+    /// Clock time (`HH:MM:SS`), at which the item begins, the current time when it is empty.
+    #[serde(default)]
+    pub time: Option<String>,
+}
+
+/// Status of an item in the running program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    Played,
+    Playing,
+    Pending,
+}
+
+impl ItemStatus {
+    /// Status of the item at `index`, `current` is the index of the next item.
+    pub fn new(index: usize, current: usize) -> Self {
+        if index + 1 < current {
+            Self::Played
+        } else if index + 1 == current {
+            Self::Playing
+        } else {
+            Self::Pending
         }
     }
 }
@@ -90,7 +199,34 @@ pub struct Process {
     pub command: ProcessCtl,
 }
 
-async fn zmq_send(msg: &str, socket_addr: &str) -> Result<String, Box<dyn Error>> {
+/// Filter values, which can be changed while playing.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct FilterParams {
+    #[serde(default)]
+    pub logo_opacity: Option<f64>,
+    #[serde(default)]
+    pub volume: Option<f64>,
+}
+
+/// Show or hide a graphics template, the fields fill the placeholders of its text.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct GraphicParams {
+    pub template: String,
+    #[serde(default)]
+    pub action: GraphicAction,
+    #[serde(default)]
+    pub fields: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphicAction {
+    #[default]
+    Show,
+    Hide,
+}
+
+pub(crate) async fn zmq_send(msg: &str, socket_addr: &str) -> Result<String, Box<dyn Error>> {
     let mut socket = zeromq::ReqSocket::new();
     socket.connect(&format!("tcp://{socket_addr}")).await?;
     socket.send(msg.into()).await?;
@@ -113,42 +249,255 @@ pub async fn send_message(
             *clips_filter.lock().await = vec![filter.clone()];
         }
 
-        if config.output.mode == HLS {
-            if manager.ingest_is_alive.load(Ordering::SeqCst) {
-                let filter_server = format!("drawtext@dyntext reinit {filter}");
+        let text = message.text.as_ref().filter(|t| !t.is_empty());
+        let saved = text.map(|_| filter.clone());
+        resume::save(&manager, |s| s.text = saved).await;
 
-                if let Ok(reply) = zmq_send(
-                    &filter_server,
-                    &config.text.zmq_server_socket.clone().unwrap(),
-                )
-                .await
-                {
-                    data_map.insert("message".to_string(), json!(reply));
-                    return Ok(data_map);
-                };
-            } else {
-                manager.stop(Ingest).await;
-            }
+        if let Some(reply) = reinit_text(&manager, &config, &filter).await {
+            data_map.insert("message".to_string(), json!(reply));
+            return Ok(data_map);
         }
+    }
+
+    Err(ServiceError::ServiceUnavailable(
+        "text message missing!".to_string(),
+    ))
+}
 
-        if config.output.mode != HLS || !manager.ingest_is_alive.load(Ordering::SeqCst) {
-            let filter_stream = format!("drawtext@dyntext reinit {filter}");
+/// Send the drawtext filter to the ingest server, when it is live in HLS mode,
+/// or else to the stream.
+pub(crate) async fn reinit_text(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    filter: &str,
+) -> Option<String> {
+    let command = format!("drawtext@dyntext reinit {filter}");
 
-            if let Ok(reply) = zmq_send(
-                &filter_stream,
-                &config.text.zmq_stream_socket.clone().unwrap(),
-            )
-            .await
+    if config.output.mode == HLS {
+        if manager.ingest_is_alive.load(Ordering::SeqCst) {
+            if let Some(socket) = &config.text.zmq_server_socket
+                && let Ok(reply) = zmq_send(&command, socket).await
             {
-                data_map.insert("message".to_string(), json!(reply));
-                return Ok(data_map);
+                return Some(reply);
+            }
+        } else {
+            manager.stop(Ingest).await;
+        }
+    }
+
+    if (config.output.mode != HLS || !manager.ingest_is_alive.load(Ordering::SeqCst))
+        && let Some(socket) = &config.text.zmq_stream_socket
+        && let Ok(reply) = zmq_send(&command, socket).await
+    {
+        return Some(reply);
+    }
+
+    None
+}
+
+/// Update the config with the new filter values and get the zmq commands for them.
+pub fn live_commands(config: &mut PlayoutConfig, params: &FilterParams) -> Vec<String> {
+    let mut commands = vec![];
+
+    if let Some(opacity) = params.logo_opacity {
+        config.processing.logo_opacity = opacity.clamp(0.0, 1.0);
+
+        if config.processing.add_logo && !config.processing.audio_only {
+            commands.push(format!(
+                "colorchannelmixer@logo aa {}",
+                config.processing.logo_opacity
+            ));
+        }
+    }
+
+    if let Some(volume) = params.volume {
+        config.processing.volume = volume.max(0.0);
+
+        if !config.processing.copy_audio {
+            let tracks = if config.processing.audio_track_index == -1 {
+                (0..config.processing.audio_tracks).collect()
+            } else {
+                vec![config.processing.audio_track_index]
             };
+
+            for nr in tracks {
+                commands.push(format!(
+                    "volume@volume{nr} volume {}",
+                    config.processing.volume
+                ));
+            }
         }
     }
 
-    Err(ServiceError::ServiceUnavailable(
-        "text message missing!".to_string(),
-    ))
+    commands
+}
+
+/// Change filter values of the running decoder or ingest, without restarting the encoder.
+///
+/// The new values are also used for the next clips, until the config gets reloaded.
+pub async fn update_filter(
+    manager: ChannelManager,
+    params: FilterParams,
+) -> Result<Map<String, Value>, ServiceError> {
+    let mut data_map = Map::new();
+    let (commands, socket) = {
+        let mut config = manager.config.write().await;
+
+        if !config.processing.live_control {
+            return Err(ServiceError::ServiceUnavailable(
+                "live control is not enabled!".to_string(),
+            ));
+        }
+
+        let socket = if manager.ingest_is_alive.load(Ordering::SeqCst) {
+            config.processing.zmq_server_socket.clone()
+        } else {
+            config.processing.zmq_stream_socket.clone()
+        };
+
+        (live_commands(&mut config, &params), socket)
+    };
+
+    let (volume, opacity) = {
+        let config = manager.config.read().await;
+        (config.processing.volume, config.processing.logo_opacity)
+    };
+
+    resume::save(&manager, |s| {
+        if params.volume.is_some() {
+            s.volume = Some(volume);
+        }
+
+        if params.logo_opacity.is_some() {
+            s.logo_opacity = Some(opacity);
+        }
+    })
+    .await;
+
+    let Some(socket) = socket else {
+        return Err(ServiceError::ServiceUnavailable(
+            "live control socket missing!".to_string(),
+        ));
+    };
+
+    let mut replies = vec![];
+
+    for command in commands {
+        match timeout(ZMQ_TIMEOUT, zmq_send(&command, &socket)).await {
+            Ok(Ok(reply)) => replies.push(format!("{command}: {reply}")),
+            Ok(Err(e)) => {
+                error!(target: Target::file_mail(), channel = manager.id; "Live control: {e}");
+                return Err(ServiceError::ServiceUnavailable(e.to_string()));
+            }
+            Err(_) => {
+                return Err(ServiceError::ServiceUnavailable(
+                    "live control timeout!".to_string(),
+                ));
+            }
+        }
+    }
+
+    data_map.insert("message".to_string(), json!(replies));
+
+    Ok(data_map)
+}
+
+/// Animate a graphics template in or out, over the zmq socket of the encoder.
+pub async fn show_graphic(
+    manager: ChannelManager,
+    params: GraphicParams,
+) -> Result<Map<String, Value>, ServiceError> {
+    let config = manager.config.read().await.clone();
+    let mut data_map = Map::new();
+
+    let Some(socket) = config
+        .output
+        .zmq_socket
+        .clone()
+        .filter(|_| !config.processing.copy_video)
+    else {
+        return Err(ServiceError::ServiceUnavailable(
+            "graphics need templates and an output with encoder (not HLS)!".to_string(),
+        ));
+    };
+
+    let Some((index, graphic)) = graphics::template(&config, &params.template) else {
+        return Err(ServiceError::BadRequest(format!(
+            "Graphics template {} not found!",
+            params.template
+        )));
+    };
+
+    let show = params.action == GraphicAction::Show;
+    let text = graphic
+        .text
+        .as_ref()
+        .and_then(|t| t.text.as_deref())
+        .filter(|_| show && !graphic.is_widget())
+        .map(|t| graphics::fill(t, &params.fields));
+    let steps = graphics::steps(graphic, show);
+    let pause = Duration::from_secs_f64(graphic.length / steps.len() as f64);
+
+    for (n, progress) in steps.into_iter().enumerate() {
+        if n > 0 {
+            sleep(pause).await;
+        }
+
+        let text = text.as_deref().filter(|_| n == 0);
+
+        for command in graphics::commands(index, graphic, text, progress) {
+            match timeout(ZMQ_TIMEOUT, zmq_send(&command, &socket)).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => {
+                    error!(target: Target::file_mail(), channel = manager.id; "Graphics: {e}");
+                    return Err(ServiceError::ServiceUnavailable(e.to_string()));
+                }
+                Err(_) => {
+                    return Err(ServiceError::ServiceUnavailable(
+                        "graphics timeout!".to_string(),
+                    ));
+                }
+            }
+        }
+    }
+
+    data_map.insert("operation".to_string(), json!("graphic"));
+    data_map.insert("template".to_string(), json!(params.template));
+    data_map.insert("action".to_string(), json!(params.action));
+
+    Ok(data_map)
+}
+
+/// Switch the tagged outputs of the encoder to the slate, or back to the program, for the
+/// item which starts now.
+///
+/// The decoder runs ahead of the encoder by the buffer latency, so the switch waits for it.
+pub async fn switch_outputs(config: PlayoutConfig, node: Media) {
+    let commands = alternate::commands(&config, &node);
+    let Some(socket) = config.output.zmq_socket.as_ref() else {
+        return;
+    };
+
+    if commands.is_empty() {
+        return;
+    }
+
+    sleep(Duration::from_millis(config.general.buffer_latency as u64)).await;
+
+    for command in commands {
+        let error = match timeout(ZMQ_TIMEOUT, zmq_send(&command, socket)).await {
+            Ok(Ok(_)) => continue,
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => "timeout".to_string(),
+        };
+
+        error!(target: Target::file_mail(), channel = config.general.channel_id;
+            "Switch outputs for <span class=\"log-addr\">{}</span>: {error}",
+            node.source
+        );
+
+        break;
+    }
 }
 
 pub async fn control_state(
@@ -208,6 +557,71 @@ pub async fn control_state(
 
             data_map.insert("operation".to_string(), json!("reset_playout_state"));
         }
+
+        PlayerCtl::Slate => {
+            info!(target: Target::file_mail(), channel = id; "Switch to standby slate");
+
+            manager.slate_hold.store(true, Ordering::SeqCst);
+
+            data_map.insert("operation".to_string(), json!("standby_slate"));
+        }
+
+        PlayerCtl::Resume => {
+            info!(target: Target::file_mail(), channel = id; "Release slate, back to schedule");
+
+            manager.slate_hold.store(false, Ordering::SeqCst);
+            manager.list_init.store(true, Ordering::SeqCst);
+
+            data_map.insert("operation".to_string(), json!("resume_schedule"));
+        }
+
+        PlayerCtl::Hold => {
+            if config.processing.mode != Playlist {
+                return Err(ServiceError::BadRequest(
+                    "Hold works only in playlist mode".to_string(),
+                ));
+            }
+
+            info!(target: Target::file_mail(), channel = id; "Hold the schedule at the next clip boundary");
+
+            manager.hold.store(true, Ordering::SeqCst);
+
+            data_map.insert("operation".to_string(), json!("hold_schedule"));
+
+            // the current clip plays to its end
+            return Ok(data_map);
+        }
+
+        PlayerCtl::Release => {
+            manager.hold.store(false, Ordering::SeqCst);
+            data_map.insert("operation".to_string(), json!("release_hold"));
+
+            let on_hold = manager
+                .current_media
+                .lock()
+                .await
+                .as_ref()
+                .is_some_and(|m| m.category == HOLD);
+
+            if !on_hold {
+                info!(target: Target::file_mail(), channel = id; "Release the hold, before it started");
+
+                return Ok(data_map);
+            }
+
+            if let Some(media) = current_list.get(index) {
+                (shift, _) = get_delta(&config, &media.begin.unwrap_or(0.0));
+            }
+
+            info!(target: Target::file_mail(), channel = id;
+                "Release the hold, the schedule is delayed for <span class=\"log-number\">{:.3}</span> seconds",
+                -shift
+            );
+
+            data_map.insert("shifted_seconds".to_string(), json!(shift));
+
+            // the hold clip ends now, like with `next`, and not after its full length
+        }
     }
 
     manager.channel.lock().await.time_shift = shift;
@@ -216,3 +630,480 @@ pub async fn control_state(
 
     Ok(data_map)
 }
+
+/// Insert clip at `index` and recalculate the begin times from there.
+///
+/// With [`InsertPolicy::DropFiller`] the following filler clips get removed or shortened,
+/// until the length of the new clip is recovered. Returns the recovered seconds.
+pub fn insert_media(
+    list: &mut Vec<Media>,
+    index: usize,
+    mut media: Media,
+    policy: InsertPolicy,
+    is_filler: impl Fn(&Media) -> bool,
+) -> f64 {
+    let index = index.min(list.len());
+    let length = media.out - media.seek;
+    let mut remaining = length;
+    let begin = match index {
+        0 => list.first().and_then(|m| m.begin),
+        i => list[i - 1]
+            .begin
+            .map(|b| b + list[i - 1].out - list[i - 1].seek),
+    };
+
+    media.origin = Origin::Inserted;
+    list.insert(index, media);
+
+    if policy == InsertPolicy::DropFiller {
+        let mut i = index + 1;
+
+        while i < list.len() && remaining > 0.0 {
+            if is_filler(&list[i]) {
+                let filler_length = list[i].out - list[i].seek;
+
+                if filler_length <= remaining {
+                    remaining -= filler_length;
+                    list.remove(i);
+                    continue;
+                }
+
+                list[i].out -= remaining;
+                remaining = 0.0;
+            }
+
+            i += 1;
+        }
+    }
+
+    let mut next_begin = begin;
+
+    for (i, item) in list.iter_mut().enumerate() {
+        item.index = Some(i);
+
+        if i >= index
+            && let Some(b) = next_begin
+        {
+            item.begin = Some(b);
+            next_begin = Some(b + item.length());
+        }
+    }
+
+    length - remaining
+}
+
+/// Insert clip after the current playing one.
+pub async fn insert_next(
+    manager: &ChannelManager,
+    params: InsertParams,
+) -> Result<Map<String, Value>, ServiceError> {
+    let config = manager.config.read().await.clone();
+    let id = config.general.channel_id;
+    let source = if is_remote(&params.source) {
+        params.source.clone()
+    } else {
+        let (path, _, _) = norm_abs_path(&config.channel.storage, &params.source)?;
+
+        if !path.is_file() {
+            return Err(ServiceError::NotFound(format!(
+                "File {} not found!",
+                params.source
+            )));
+        }
+
+        path.to_string_lossy().to_string()
+    };
+
+    let mut media = Media::new(0, &source, true).await;
+
+    if media.duration <= 0.0 {
+        return Err(ServiceError::BadRequest(format!(
+            "{} has no valid duration!",
+            params.source
+        )));
+    }
+
+    media.title = params.title;
+    media.category = params.category;
+    media.seek = params.seek.clamp(0.0, media.duration);
+    media.out = params
+        .out
+        .filter(|o| *o > media.seek)
+        .map_or(media.duration, |o| o.min(media.duration));
+
+    let index = manager.current_index.load(Ordering::SeqCst);
+    let length = media.out - media.seek;
+    let recovered = {
+        let mut list = manager.current_list.lock().await;
+        let recovered = insert_media(&mut list, index, media.clone(), params.policy, |m| {
+            is_filler(&config, m)
+        });
+
+        if !config.playlist.infinit {
+            fit_hard_starts(
+                &config,
+                &mut list,
+                index + 1,
+                config.playlist.start_sec.unwrap_or_default(),
+            );
+        }
+
+        recovered
+    };
+
+    info!(target: Target::file_mail(), channel = id;
+        "Insert next clip: <span class=\"log-addr\">{}</span>, shift schedule for <span class=\"log-number\">{:.3}</span> seconds",
+        media.source,
+        length - recovered
+    );
+
+    let mut data_map = Map::new();
+    data_map.insert("operation".to_string(), json!("insert_next"));
+    data_map.insert("shifted_seconds".to_string(), json!(length - recovered));
+    data_map.insert("media".to_string(), get_media_map(media));
+
+    Ok(data_map)
+}
+
+/// Items of the running program, with their begin and status.
+pub async fn program_list(manager: &ChannelManager) -> Map<String, Value> {
+    let date = manager.current_date.lock().await.clone();
+    let list = manager.current_list.lock().await;
+    let current = manager.current_index.load(Ordering::SeqCst);
+
+    let program = list
+        .iter()
+        .enumerate()
+        .map(|(i, media)| {
+            let mut item = get_media_map(media.clone());
+            item["index"] = json!(i);
+            item["begin"] = json!(media.begin.map(|b| (b * 1000.0).round() / 1000.0));
+            item["status"] = json!(ItemStatus::new(i, current));
+
+            item
+        })
+        .collect::<Vec<_>>();
+
+    let mut data_map = Map::new();
+    data_map.insert("date".to_string(), json!(date));
+    data_map.insert("index".to_string(), json!(current));
+    data_map.insert("program".to_string(), json!(program));
+
+    data_map
+}
+
+/// Replace the item at `index` and recalculate the begin times from there.
+pub fn replace_media(list: &mut [Media], index: usize, mut media: Media) {
+    let mut next_begin = list[index].begin;
+
+    media.index = Some(index);
+    list[index] = media;
+
+    for item in &mut list[index..] {
+        if let Some(b) = next_begin {
+            item.begin = Some(b);
+            next_begin = Some(b + item.length());
+        }
+    }
+}
+
+/// Position of the item in the playlist file, items which are inserted at runtime, or
+/// come from a loop, are not in the file. With the same clip more than once, the nearest
+/// to `index` is taken.
+pub fn file_index(
+    config: &PlayoutConfig,
+    program: &[Media],
+    media: &Media,
+    index: usize,
+) -> Option<usize> {
+    program
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| {
+            let source = if Path::new(&item.source).is_relative() && !is_remote(&item.source) {
+                config
+                    .storage
+                    .path
+                    .join(&item.source)
+                    .to_string_lossy()
+                    .to_string()
+            } else {
+                item.source.clone()
+            };
+
+            source == media.source && item.seek == media.seek
+        })
+        .min_by_key(|(i, _)| i.abs_diff(index))
+        .map(|(i, _)| i)
+}
+
+/// Change a pending item of the running program and the same item in the playlist file.
+pub async fn edit_item(
+    manager: &ChannelManager,
+    edit: ItemEdit,
+) -> Result<Map<String, Value>, ServiceError> {
+    let config = manager.config.read().await.clone();
+    let id = config.general.channel_id;
+    let index = edit.index;
+
+    let old = {
+        let list = manager.current_list.lock().await;
+
+        list.get(index)
+            .cloned()
+            .ok_or_else(|| ServiceError::NotFound(format!("Item {index} not found!")))?
+    };
+
+    if index < manager.current_index.load(Ordering::SeqCst) {
+        return Err(ServiceError::Conflict(format!(
+            "Item {index} is already played"
+        )));
+    }
+
+    let mut media = old.clone();
+
+    if let Some(source) = &edit.source {
+        let source = if is_remote(source) {
+            source.clone()
+        } else {
+            let (path, _, _) = norm_abs_path(&config.channel.storage, source)?;
+
+            if !path.is_file() {
+                return Err(ServiceError::NotFound(format!("File {source} not found!")));
+            }
+
+            path.to_string_lossy().to_string()
+        };
+
+        let new = Media::new(0, &source, true).await;
+
+        if new.duration <= 0.0 {
+            return Err(ServiceError::BadRequest(format!(
+                "{source} has no valid duration!"
+            )));
+        }
+
+        media.source = new.source;
+        media.title = new.title;
+        media.duration = new.duration;
+        media.probe = new.probe;
+        media.seek = 0.0;
+        media.out = new.duration;
+    }
+
+    if edit.title.is_some() {
+        media.title = edit.title;
+    }
+
+    media.seek = edit.seek.unwrap_or(media.seek);
+    media.out = edit.out.unwrap_or(media.out);
+
+    if media.seek < 0.0 || media.out <= media.seek || media.out > media.duration {
+        return Err(ServiceError::BadRequest(format!(
+            "In {} and out {} don't fit to the duration {}",
+            media.seek, media.out, media.duration
+        )));
+    }
+
+    // the playlist file gets the same change, the playlist watcher merges it again
+    let date = manager.current_date.lock().await.clone();
+    let mut saved = false;
+
+    if let Ok(mut playlist) = read_playlist(&config, date).await
+        && let Some(i) = file_index(&config, &playlist.program, &old, index)
+    {
+        let item = &mut playlist.program[i];
+        item.source.clone_from(&media.source);
+        item.title.clone_from(&media.title);
+        item.seek = media.seek;
+        item.out = media.out;
+        item.duration = media.duration;
+
+        match write_playlist(&config, playlist).await {
+            // the file has the change already
+            Ok(_) | Err(ServiceError::Conflict(_)) => saved = true,
+            Err(e) => return Err(e),
+        }
+    }
+
+    if !saved {
+        if media.origin == Origin::File {
+            media.origin = Origin::Changed;
+        }
+
+        warn!(target: Target::file_mail(), channel = id;
+            "Item <span class=\"log-number\">{index}</span> is not in the playlist file, it is only changed in the running program"
+        );
+    }
+
+    {
+        let mut list = manager.current_list.lock().await;
+
+        // the item could be played in the meantime
+        if index < manager.current_index.load(Ordering::SeqCst) || index >= list.len() {
+            return Err(ServiceError::Conflict(format!(
+                "Item {index} is already played"
+            )));
+        }
+
+        replace_media(&mut list, index, media.clone());
+
+        if !config.playlist.infinit {
+            fit_hard_starts(
+                &config,
+                &mut list,
+                index,
+                config.playlist.start_sec.unwrap_or_default(),
+            );
+        }
+    }
+
+    info!(target: Target::file_mail(), channel = id;
+        "Change item <span class=\"log-number\">{index}</span>: <span class=\"log-addr\">{}</span>, in <span class=\"log-number\">{:.3}</span>, out <span class=\"log-number\">{:.3}</span>",
+        media.source,
+        media.seek,
+        media.out
+    );
+
+    let mut data_map = Map::new();
+    data_map.insert("operation".to_string(), json!("edit_item"));
+    data_map.insert("index".to_string(), json!(index));
+    data_map.insert("saved".to_string(), json!(saved));
+    data_map.insert("media".to_string(), get_media_map(media));
+
+    Ok(data_map)
+}
+
+/// Resume the running program with the item at `to`, which begins at `begin`, `now` is the
+/// current playlist time.
+///
+/// The pending items from `from` until `to` are dropped. When the item should have begun
+/// before, it gets trimmed, when it begins later, a filler gap plays until then.
+/// The begin times are recalculated from `from`. Returns the new position of the item.
+pub fn resume_media(
+    list: &mut Vec<Media>,
+    from: usize,
+    to: usize,
+    begin: f64,
+    now: f64,
+) -> Result<usize, String> {
+    if from > to || to >= list.len() {
+        return Err(format!("Item {to} is not pending"));
+    }
+
+    let item = &list[to];
+
+    if begin + item.length() <= now + 1.0 {
+        return Err(format!("Item {to} would be over already"));
+    }
+
+    list.drain(from..to);
+
+    let mut position = from;
+    let item = &mut list[from];
+    // the item begins at the chosen time, not at the time of its playlist
+    item.start = StartMode::Soft;
+    item.origin = Origin::Changed;
+
+    if begin < now {
+        item.seek += (now - begin) * item.speed.unwrap_or(1.0);
+    } else if begin - now >= 1.0 {
+        let gap = Media {
+            category: "filler".to_string(),
+            duration: begin - now,
+            out: begin - now,
+            origin: Origin::Inserted,
+            ..Media::default()
+        };
+
+        list.insert(from, gap);
+        position += 1;
+    }
+
+    let mut next_begin = now;
+
+    for (i, item) in list.iter_mut().enumerate() {
+        item.index = Some(i);
+
+        if i >= from {
+            item.begin = Some(next_begin);
+            next_begin += item.length();
+        }
+    }
+
+    Ok(position)
+}
+
+/// Resume the schedule with a pending item, after a live overrun.
+pub async fn resume_from(
+    conn: &Pool<Sqlite>,
+    manager: &ChannelManager,
+    params: ResumeParams,
+) -> Result<Map<String, Value>, ServiceError> {
+    let config = manager.config.read().await.clone();
+    let id = config.general.channel_id;
+    let start = config.playlist.start_sec.unwrap_or_default();
+    let now = playlist_seconds(&config.channel.timezone, start);
+    if config.processing.mode != Playlist {
+        return Err(ServiceError::BadRequest(
+            "Resume works only in playlist mode".to_string(),
+        ));
+    }
+
+    let begin = match params.time.as_deref().filter(|t| t.contains(':')) {
+        Some(time) => {
+            let mut begin = time_to_sec(time, &None);
+
+            // times before the playlist start are on the next day
+            if begin < start {
+                begin += 86400.0;
+            }
+
+            begin
+        }
+        None => now,
+    };
+
+    let (dropped, media) = {
+        let mut list = manager.current_list.lock().await;
+        let from = manager.current_index.load(Ordering::SeqCst);
+
+        if params.index < from {
+            return Err(ServiceError::Conflict(format!(
+                "Item {} is already played",
+                params.index
+            )));
+        }
+
+        let position = resume_media(&mut list, from, params.index, begin, now)
+            .map_err(ServiceError::BadRequest)?;
+
+        if !config.playlist.infinit {
+            fit_hard_starts(&config, &mut list, position + 1, start);
+        }
+
+        (params.index - from, list[position].clone())
+    };
+
+    info!(target: Target::file_mail(), channel = id;
+        "Resume the schedule with item <span class=\"log-number\">{}</span> at <span class=\"log-number\">{}</span>: <span class=\"log-addr\">{}</span>, drop <span class=\"log-number\">{dropped}</span> items",
+        params.index,
+        sec_to_time(begin),
+        media.source
+    );
+
+    // the program has the real times now
+    manager.hold.store(false, Ordering::SeqCst);
+    manager.channel.lock().await.time_shift = 0.0;
+    let current_date = manager.current_date.lock().await.clone();
+    handles::update_stat(conn, id, &Some(current_date), 0.0).await?;
+    manager.stop(Decoder).await;
+
+    let mut data_map = Map::new();
+    data_map.insert("operation".to_string(), json!("resume_from"));
+    data_map.insert("dropped".to_string(), json!(dropped));
+    data_map.insert("begin".to_string(), json!(sec_to_time(begin)));
+    data_map.insert("media".to_string(), get_media_map(media));
+
+    Ok(data_map)
+}
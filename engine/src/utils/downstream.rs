@@ -0,0 +1,205 @@
+/// Downstream Check
+///
+/// The player only knows, that the encoder takes its data. When `output.check_url` is set,
+/// the published stream gets checked every `output.check_interval` seconds, like a viewer
+/// would see it:
+///
+/// - An HLS playlist (`http(s)://…`) gets pulled and has to move on between two checks,
+///   a master playlist is followed to its first variant.
+/// - Other urls (RTMP, SRT, …) get opened with ffprobe, which has to find the streams.
+///
+/// While the player is sending, a missing or frozen stream is an error (log and mail),
+/// one more message follows, when it is back.
+use std::{sync::atomic::Ordering, time::Duration};
+
+use log::*;
+use reqwest::Url;
+use tokio::time::{Instant, sleep, timeout};
+
+use crate::player::{
+    controller::{ChannelManager, stall_timeout},
+    utils::probe::MediaProbe,
+};
+use crate::utils::{
+    config::{OutputMode::HLS, PlayoutConfig},
+    logging::Target,
+};
+
+const DEFAULT_INTERVAL: u64 = 60;
+const MIN_INTERVAL: u64 = 10;
+const CHECK_TIMEOUT: Duration = Duration::from_secs(20);
+const POLL: Duration = Duration::from_secs(1);
+
+/// Position of an HLS media playlist.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HlsState {
+    pub sequence: Option<u64>,
+    pub last_segment: Option<String>,
+}
+
+/// Parsed HLS playlist.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Hls {
+    /// Master playlist with the uri of its first variant.
+    Master(String),
+    Media(HlsState),
+}
+
+/// Parse an HLS playlist, `None` when it is no playlist.
+pub fn parse_hls(text: &str) -> Option<Hls> {
+    let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+
+    if lines.next()? != "#EXTM3U" {
+        return None;
+    }
+
+    let mut state = HlsState::default();
+    let mut variant = false;
+
+    for line in lines {
+        if let Some(sequence) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+            state.sequence = sequence.trim().parse().ok();
+        } else if line.starts_with("#EXT-X-STREAM-INF") {
+            variant = true;
+        } else if !line.starts_with('#') {
+            if variant {
+                return Some(Hls::Master(line.to_string()));
+            }
+
+            state.last_segment = Some(line.to_string());
+        }
+    }
+
+    Some(Hls::Media(state))
+}
+
+/// Problem of a media playlist, compared with the last check.
+pub fn hls_problem(last: Option<&HlsState>, state: &HlsState) -> Option<String> {
+    if state.last_segment.is_none() {
+        return Some("HLS playlist has no segments".to_string());
+    }
+
+    (last == Some(state)).then(|| "HLS playlist stands still".to_string())
+}
+
+/// Problem of the probed streams.
+pub fn probe_problem(config: &PlayoutConfig, probe: &MediaProbe) -> Option<String> {
+    if !config.processing.audio_only && probe.video.is_empty() {
+        return Some("stream has no video".to_string());
+    }
+
+    probe
+        .audio
+        .is_empty()
+        .then(|| "stream has no audio".to_string())
+}
+
+fn is_http(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+async fn fetch(url: &Url) -> Result<String, String> {
+    reqwest::Client::new()
+        .get(url.clone())
+        .timeout(CHECK_TIMEOUT)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| e.to_string())?
+        .text()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+async fn check_hls(url: &str, last: &mut Option<HlsState>) -> Option<String> {
+    let mut url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(e) => return Some(e.to_string()),
+    };
+
+    // a master playlist and its variant
+    for _ in 0..2 {
+        let text = match fetch(&url).await {
+            Ok(text) => text,
+            Err(e) => return Some(e),
+        };
+
+        match parse_hls(&text) {
+            Some(Hls::Master(variant)) => match url.join(&variant) {
+                Ok(u) => url = u,
+                Err(e) => return Some(e.to_string()),
+            },
+            Some(Hls::Media(state)) => {
+                let problem = hls_problem(last.as_ref(), &state);
+                *last = Some(state);
+
+                return problem;
+            }
+            None => return Some("no HLS playlist".to_string()),
+        }
+    }
+
+    Some("HLS variant is no media playlist".to_string())
+}
+
+async fn check_probe(config: &PlayoutConfig, url: &str) -> Option<String> {
+    match timeout(CHECK_TIMEOUT, MediaProbe::new(url)).await {
+        Ok(Ok(probe)) => probe_problem(config, &probe),
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some("no answer".to_string()),
+    }
+}
+
+pub async fn run(manager: ChannelManager, config: PlayoutConfig) {
+    let id = config.general.channel_id;
+    let url = config.output.check_url.trim().to_string();
+    let interval = Duration::from_secs(match config.output.check_interval as u64 {
+        0 => DEFAULT_INTERVAL,
+        i => i.max(MIN_INTERVAL),
+    });
+    let mut checked = Instant::now();
+    let mut last = None;
+    let mut failing = false;
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        sleep(POLL).await;
+
+        if checked.elapsed() < interval {
+            continue;
+        }
+
+        checked = Instant::now();
+
+        // only a stream, which the player is sending, can be checked, HLS has no separate encoder
+        let sending = !manager.standby.load(Ordering::SeqCst)
+            && (config.output.mode == HLS || manager.encoder.lock().await.is_some())
+            && manager.output_idle() <= stall_timeout();
+
+        if !sending {
+            last = None;
+            continue;
+        }
+
+        let problem = if is_http(&url) {
+            check_hls(&url, &mut last).await
+        } else {
+            check_probe(&config, &url).await
+        };
+
+        match problem {
+            Some(problem) if !failing => {
+                error!(target: Target::file_mail(), channel = id;
+                    "Downstream check of <span class=\"log-addr\">{url}</span> failed, the player is sending, but: {problem}"
+                );
+                failing = true;
+            }
+            None if failing => {
+                info!(target: Target::file_mail(), channel = id;
+                    "Downstream check of <span class=\"log-addr\">{url}</span> is fine again"
+                );
+                failing = false;
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+/// Error Reporting
+///
+/// With `--error-report` the errors of the channels and panics are sent to an error
+/// collector, so deployments in the field report their failures. The endpoint is a Sentry
+/// DSN (`https://<key>@<host>/<project>`), or any other URL, which gets the event as JSON.
+///
+/// Errors are the ones, which also go to the mail, with the channel id as context.
+/// `--error-sample-rate` sends only a part of them and a limit per minute keeps a failing
+/// channel from flooding the collector. Panics are not sampled, but count to the limit.
+use std::{
+    panic,
+    sync::{Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{SecondsFormat, Utc};
+use log::Level;
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::json;
+use sysinfo::System;
+use uuid::Uuid;
+
+use crate::utils::{errors::ProcessError, logging::remove_html};
+
+/// Events per minute, more are dropped.
+pub const MAX_EVENTS: usize = 30;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// A panic waits for its report, so it gets less time.
+const PANIC_TIMEOUT: Duration = Duration::from_secs(2);
+const RELEASE: &str = concat!("ffplayout@", env!("CARGO_PKG_VERSION"));
+
+static REPORTER: OnceLock<Reporter> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    /// Envelope URL of the project and the public key from the DSN.
+    Sentry {
+        url: String,
+        key: String,
+    },
+    Http(String),
+}
+
+impl Endpoint {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let url = Url::parse(input).map_err(|e| format!("Invalid error report URL: {e}"))?;
+
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("Error report URL needs http or https: {input}"));
+        }
+
+        if url.username().is_empty() {
+            return Ok(Self::Http(input.to_string()));
+        }
+
+        let host = url.host_str().unwrap_or_default();
+        let port = url.port().map(|p| format!(":{p}")).unwrap_or_default();
+        let path = url.path().trim_matches('/');
+        let (prefix, project) = match path.rsplit_once('/') {
+            Some((prefix, project)) => (format!("/{prefix}"), project),
+            None => (String::new(), path),
+        };
+
+        if project.is_empty() || !project.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("Sentry DSN without project id: {input}"));
+        }
+
+        Ok(Self::Sentry {
+            url: format!(
+                "{}://{host}{port}{prefix}/api/{project}/envelope/",
+                url.scheme()
+            ),
+            key: url.username().to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct ErrorEvent {
+    pub event_id: String,
+    pub timestamp: String,
+    /// `error`, or `fatal` for a panic.
+    pub level: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<i32>,
+    pub release: String,
+    pub server_name: String,
+}
+
+impl ErrorEvent {
+    pub fn new(level: &str, message: &str, channel: Option<i32>) -> Self {
+        Self {
+            event_id: Uuid::new_v4().simple().to_string(),
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            level: level.to_string(),
+            message: remove_html(message),
+            channel,
+            release: RELEASE.to_string(),
+            server_name: System::host_name().unwrap_or_default(),
+        }
+    }
+
+    /// Sentry envelope with the event as only item.
+    pub fn envelope(&self) -> String {
+        let mut tags = json!({});
+
+        if let Some(channel) = self.channel {
+            tags["channel"] = json!(channel.to_string());
+        }
+
+        let event = json!({
+            "event_id": self.event_id,
+            "timestamp": self.timestamp,
+            "level": self.level,
+            "platform": "other",
+            "logger": "ffplayout",
+            "release": self.release,
+            "server_name": self.server_name,
+            "message": { "formatted": self.message },
+            "tags": tags,
+        });
+
+        format!(
+            "{}\n{}\n{event}\n",
+            json!({ "event_id": self.event_id }),
+            json!({ "type": "event" })
+        )
+    }
+}
+
+/// The event is sent, when the random `roll` (0 to 1) is below the sample rate.
+pub fn sampled(rate: f64, roll: f64) -> bool {
+    roll < rate
+}
+
+/// Count of the events in the current minute.
+#[derive(Debug)]
+pub struct RateLimit {
+    start: Instant,
+    count: usize,
+}
+
+impl RateLimit {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            start: now,
+            count: 0,
+        }
+    }
+
+    /// Register an event, returns false when there are too many.
+    pub fn allow(&mut self, now: Instant) -> bool {
+        if now.duration_since(self.start) >= Duration::from_secs(60) {
+            self.start = now;
+            self.count = 0;
+        }
+
+        self.count += 1;
+        self.count <= MAX_EVENTS
+    }
+}
+
+struct Reporter {
+    endpoint: Endpoint,
+    sample_rate: f64,
+    limit: Mutex<RateLimit>,
+}
+
+impl Reporter {
+    fn request(&self, event: &ErrorEvent) -> (String, Vec<(&'static str, String)>, String) {
+        match &self.endpoint {
+            Endpoint::Sentry { url, key } => (
+                url.clone(),
+                vec![
+                    ("Content-Type", "application/x-sentry-envelope".to_string()),
+                    (
+                        "X-Sentry-Auth",
+                        format!(
+                            "Sentry sentry_version=7, sentry_key={key}, sentry_client={RELEASE}"
+                        ),
+                    ),
+                ],
+                event.envelope(),
+            ),
+            Endpoint::Http(url) => (
+                url.clone(),
+                vec![("Content-Type", "application/json".to_string())],
+                serde_json::to_string(event).unwrap_or_default(),
+            ),
+        }
+    }
+
+    /// Send in the background, from the runtime or from an own thread.
+    fn send(&'static self, event: ErrorEvent) {
+        let (url, headers, body) = self.request(&event);
+
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+                let mut request = reqwest::Client::new().post(url).body(body).timeout(TIMEOUT);
+
+                for (key, value) in headers {
+                    request = request.header(key, value);
+                }
+
+                if let Err(e) = request.send().await {
+                    eprintln!("Couldn't send error report: {e}");
+                }
+            });
+        } else {
+            send_blocking(url, headers, body, TIMEOUT);
+        }
+    }
+
+    /// Register an event at the limit.
+    fn allow(&self) -> bool {
+        // try_lock, a panic while the lock is held must not wait for itself
+        self.limit
+            .try_lock()
+            .map(|mut l| l.allow(Instant::now()))
+            .unwrap_or(false)
+    }
+}
+
+fn send_blocking(
+    url: String,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+    timeout: Duration,
+) {
+    // the blocking client needs a thread outside of the runtime
+    let sender = thread::spawn(move || {
+        let mut request = reqwest::blocking::Client::new()
+            .post(url)
+            .body(body)
+            .timeout(timeout);
+
+        for (key, value) in headers {
+            request = request.header(key, value);
+        }
+
+        if let Err(e) = request.send() {
+            eprintln!("Couldn't send error report: {e}");
+        }
+    });
+
+    let _ = sender.join();
+}
+
+/// Start the reporting, without endpoint nothing is sent.
+pub fn init(endpoint: &Option<String>, sample_rate: f64) -> Result<(), ProcessError> {
+    let Some(endpoint) = endpoint.as_ref().filter(|e| !e.is_empty()) else {
+        return Ok(());
+    };
+
+    let endpoint = Endpoint::parse(endpoint).map_err(ProcessError::Custom)?;
+    let reporter = REPORTER.get_or_init(|| Reporter {
+        endpoint,
+        sample_rate: sample_rate.clamp(0.0, 1.0),
+        limit: Mutex::new(RateLimit::new(Instant::now())),
+    });
+
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        if reporter.allow() {
+            let event = ErrorEvent::new("fatal", &info.to_string(), None);
+            let (url, headers, body) = reporter.request(&event);
+
+            // wait for it, the process may end after the panic
+            send_blocking(url, headers, body, PANIC_TIMEOUT);
+        }
+
+        default_hook(info);
+    }));
+
+    Ok(())
+}
+
+/// Report an error of a channel, other levels are ignored.
+pub fn capture(channel: i32, level: Level, message: &str) {
+    let Some(reporter) = REPORTER.get() else {
+        return;
+    };
+
+    if level != Level::Error || !sampled(reporter.sample_rate, rand::random::<f64>()) {
+        return;
+    }
+
+    if !reporter.allow() {
+        return;
+    }
+
+    let channel = (channel > 0).then_some(channel);
+
+    reporter.send(ErrorEvent::new("error", message, channel));
+}
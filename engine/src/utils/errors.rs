@@ -127,6 +127,12 @@ impl From<uuid::Error> for ServiceError {
     }
 }
 
+impl From<mlua::Error> for ServiceError {
+    fn from(err: mlua::Error) -> Self {
+        Self::BadRequest(err.to_string())
+    }
+}
+
 impl From<serde_json::Error> for ServiceError {
     fn from(err: serde_json::Error) -> Self {
         Self::BadRequest(err.to_string())
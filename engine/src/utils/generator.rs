@@ -7,7 +7,7 @@
 use std::io::Error;
 
 use async_walkdir::WalkDir;
-use chrono::Timelike;
+use chrono::{NaiveTime, Timelike};
 use lexical_sort::{StringSort, natural_lexical_cmp};
 use log::*;
 use rand::{Rng, rng, seq::SliceRandom};
@@ -18,13 +18,15 @@ use crate::player::{
     controller::ChannelManager,
     input::folder::FolderSource,
     utils::{
-        Media, get_date_range, include_file_extension, json_serializer::JsonPlaylist, sum_durations,
+        Media, get_date_range, include_file_extension, json_serializer::JsonPlaylist,
+        sum_durations, target_length,
     },
 };
 use crate::utils::{
+    calendar::{Calendar, resolve_template},
     config::{PlayoutConfig, Template},
+    library,
     logging::Target,
-    time_to_sec,
 };
 
 pub fn random_list(clip_list: Vec<Media>, total_length: f64) -> Vec<Media> {
@@ -124,25 +126,98 @@ pub async fn filler_list(
     filler_clip_list
 }
 
-pub async fn generate_from_template(
+fn naive_time_sec(time: &NaiveTime) -> f64 {
+    f64::from(time.num_seconds_from_midnight())
+}
+
+/// Insert ident at the first clip change after every interval boundary.
+///
+/// When the block ends before all idents are placed, the rest gets appended.
+pub fn insert_idents(
+    list: Vec<Media>,
+    ident: &Media,
+    interval: f64,
+    start: f64,
+    count: f64,
+) -> Vec<Media> {
+    let mut new_list = vec![];
+    let mut position = start;
+    let mut inserted = 0.0;
+    let mut next_boundary = ((start / interval).floor() + 1.0) * interval;
+
+    for item in list {
+        if inserted < count && position >= next_boundary {
+            position += ident.duration;
+            next_boundary += interval;
+            inserted += 1.0;
+            new_list.push(ident.clone());
+        }
+
+        position += item.out - item.seek;
+        new_list.push(item);
+    }
+
+    while inserted < count {
+        inserted += 1.0;
+        new_list.push(ident.clone());
+    }
+
+    new_list
+}
+
+/// Fill media list from template sources, without touching the current list from the channel.
+pub async fn template_list(
     config: &PlayoutConfig,
     manager: &ChannelManager,
     template: Template,
-) -> FolderSource {
+) -> Vec<Media> {
     let mut media_list = vec![];
-    let mut rng = rng();
     let mut index: usize = 0;
     let id = config.general.channel_id;
+    let mut position = template
+        .sources
+        .first()
+        .map(|s| naive_time_sec(&s.start))
+        .unwrap_or_default();
+    let mut ident = None;
+    let recent = library::recently_aired(&manager.db_pool, config).await;
+
+    if let Some(i) = &template.ident {
+        let media = Media::new(0, &i.path.to_string_lossy(), true).await;
+        let interval = naive_time_sec(&i.interval);
+
+        if media.duration > 0.0 && interval > 0.0 {
+            ident = Some((media, interval));
+        } else {
+            error!(target: Target::all(), channel = id; "Ident <span class=\"log-addr\">{:?}</span> is not usable!", i.path);
+        }
+    }
 
     for source in template.sources {
         let mut source_list = vec![];
-        let duration = (source.duration.hour() as f64 * 3600.0)
-            + (source.duration.minute() as f64 * 60.0)
-            + source.duration.second() as f64;
+        let mut duration = naive_time_sec(&source.duration);
+        let mut ident_count = 0.0;
+
+        if let Some((media, interval)) = &ident {
+            // reserve the time for the idents in this block
+            ident_count =
+                ((position + duration) / interval).floor() - (position / interval).floor();
+            duration = (duration - ident_count * media.duration).max(0.0);
+        }
 
         debug!(target: Target::all(), channel = id; "Generating playlist block with <span class=\"log-number\">{duration:.2}</span> seconds length");
 
         for path in source.paths {
+            if let Some(mut indexed) = library::folder_media(&manager.db_pool, config, &path).await
+            {
+                if !source.shuffle {
+                    indexed.sort_by(|a, b| natural_lexical_cmp(&a.source, &b.source));
+                }
+
+                source_list.append(&mut indexed);
+                continue;
+            }
+
             debug!("Search files in <span class=\"log-addr\">{path:?}</span>");
             let mut file_list = vec![];
             let mut entries = WalkDir::new(path);
@@ -165,8 +240,10 @@ pub async fn generate_from_template(
             }
         }
 
+        source_list = library::separate(source_list, &recent);
+
         let mut timed_list = if source.shuffle {
-            source_list.shuffle(&mut rng);
+            source_list.shuffle(&mut rng());
 
             random_list(source_list, duration)
         } else {
@@ -181,6 +258,11 @@ pub async fn generate_from_template(
             timed_list.append(&mut filler);
         }
 
+        if let Some((media, interval)) = &ident {
+            timed_list = insert_idents(timed_list, media, *interval, position, ident_count);
+        }
+
+        position += sum_durations(&timed_list);
         media_list.append(&mut timed_list);
     }
 
@@ -190,6 +272,38 @@ pub async fn generate_from_template(
         index += 1;
     }
 
+    media_list
+}
+
+/// Create the concrete playlist for the given date out of a template.
+///
+/// The template paths must be already resolved, see [`resolve_template`].
+pub async fn playlist_from_template(
+    manager: &ChannelManager,
+    template: Template,
+    date: &str,
+) -> JsonPlaylist {
+    let config = manager.config.read().await.clone();
+    let program = template_list(&config, manager, template).await;
+
+    JsonPlaylist {
+        channel: manager.channel.lock().await.name.clone(),
+        date: date.to_string(),
+        path: None,
+        start_sec: None,
+        length: None,
+        modified: None,
+        program,
+    }
+}
+
+pub async fn generate_from_template(
+    config: &PlayoutConfig,
+    manager: &ChannelManager,
+    template: Template,
+) -> FolderSource {
+    let media_list = template_list(config, manager, template).await;
+
     FolderSource::from_list(manager, media_list).await
 }
 
@@ -199,16 +313,6 @@ pub async fn playlist_generator(manager: &ChannelManager) -> Result<Vec<JsonPlay
     let id = config.general.channel_id;
     let channel_name = manager.channel.lock().await.name.clone();
 
-    let total_length = match config.playlist.length_sec {
-        Some(length) => length,
-        None => {
-            if config.playlist.length.contains(':') {
-                time_to_sec(&config.playlist.length, &config.channel.timezone)
-            } else {
-                86400.0
-            }
-        }
-    };
     let playlist_root = &config.channel.playlists;
     let mut playlists = vec![];
     let mut date_range = vec![];
@@ -233,13 +337,20 @@ pub async fn playlist_generator(manager: &ChannelManager) -> Result<Vec<JsonPlay
     // gives an iterator with infinit length
     let mut folder_iter = if let Some(template) = &config.general.template {
         from_template = true;
+        // templates from the command line are not resolved yet
+        let template = resolve_template(&config, template.clone());
 
-        generate_from_template(&config, manager, template.clone()).await
+        generate_from_template(&config, manager, template).await
     } else {
         FolderSource::new(&config, manager.clone()).await
     };
 
     let list_length = manager.current_list.lock().await.len();
+    let calendar = if from_template {
+        None
+    } else {
+        Calendar::load(&config).await
+    };
 
     for date in date_range {
         let d: Vec<&str> = date.split('-').collect();
@@ -247,6 +358,7 @@ pub async fn playlist_generator(manager: &ChannelManager) -> Result<Vec<JsonPlay
         let month = d[1];
         let playlist_path = playlist_root.join(year).join(month);
         let playlist_file = &playlist_path.join(format!("{date}.json"));
+        let total_length = target_length(&config, &date);
         let mut length = 0.0;
         let mut round = 0;
 
@@ -281,6 +393,12 @@ pub async fn playlist_generator(manager: &ChannelManager) -> Result<Vec<JsonPlay
         if from_template {
             let media_list = manager.current_list.lock().await;
             playlist.program = media_list.to_vec();
+        } else if let Some(template) = calendar
+            .as_ref()
+            .and_then(|c| c.template_for(&playlist.date))
+        {
+            let template = resolve_template(&config, template);
+            playlist.program = template_list(&config, manager, template).await;
         } else {
             while let Some(item) = folder_iter.next().await {
                 let duration = item.duration;
@@ -298,8 +416,8 @@ pub async fn playlist_generator(manager: &ChannelManager) -> Result<Vec<JsonPlay
 
             let list_duration = sum_durations(&playlist.program);
 
-            if config.playlist.length_sec.unwrap() > list_duration {
-                let time_left = config.playlist.length_sec.unwrap() - list_duration;
+            if total_length > list_duration {
+                let time_left = total_length - list_duration;
                 let mut fillers = filler_list(&config, manager, time_left).await;
 
                 playlist.program.append(&mut fillers);
@@ -0,0 +1,172 @@
+/// Lifecycle Hooks
+///
+/// External commands, which run on playout events: clip start and end, ingest start and stop,
+/// clip errors and the change to a new playlist day. A hook is a command line with
+/// placeholders like `{source}` or `{title}`, it gets split like in a shell and every
+/// placeholder is replaced inside its argument, so values with spaces stay one argument.
+///
+/// The same values are also in the environment, as `FFPLAYOUT_<NAME>` (`FFPLAYOUT_SOURCE`, ...).
+/// Hooks run in the background, a hook which takes longer than a minute gets killed.
+use std::{collections::BTreeMap, fmt, process::Stdio, time::Duration};
+
+use log::*;
+use tokio::{process::Command, time::timeout};
+
+use crate::player::utils::Media;
+use crate::utils::{config::PlayoutConfig, logging::Target};
+
+const HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HookEvent {
+    ClipStart,
+    ClipEnd,
+    IngestStart,
+    IngestStop,
+    Error,
+    DayChange,
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::ClipStart => write!(f, "clip_start"),
+            Self::ClipEnd => write!(f, "clip_end"),
+            Self::IngestStart => write!(f, "ingest_start"),
+            Self::IngestStop => write!(f, "ingest_stop"),
+            Self::Error => write!(f, "error"),
+            Self::DayChange => write!(f, "day_change"),
+        }
+    }
+}
+
+/// Command line of the event, empty when no hook is set.
+pub fn hook_command(config: &PlayoutConfig, event: HookEvent) -> &str {
+    let task = &config.task;
+
+    match event {
+        HookEvent::ClipStart => &task.hook_clip_start,
+        HookEvent::ClipEnd => &task.hook_clip_end,
+        HookEvent::IngestStart => &task.hook_ingest_start,
+        HookEvent::IngestStop => &task.hook_ingest_stop,
+        HookEvent::Error => &task.hook_error,
+        HookEvent::DayChange => &task.hook_day_change,
+    }
+    .trim()
+}
+
+/// Values of a clip for the placeholders.
+pub fn media_vars(node: &Media) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("source".to_string(), node.source.clone()),
+        ("title".to_string(), node.title.clone().unwrap_or_default()),
+        (
+            "program_id".to_string(),
+            node.program_id.clone().unwrap_or_default(),
+        ),
+        (
+            "index".to_string(),
+            node.index.map(|i| i.to_string()).unwrap_or_default(),
+        ),
+        ("duration".to_string(), format!("{:.3}", node.length())),
+    ])
+}
+
+/// Split the command line and fill in the placeholders, `None` when the line is not valid.
+pub fn hook_args(command: &str, vars: &BTreeMap<String, String>) -> Option<Vec<String>> {
+    let args = shlex::split(command)?;
+
+    if args.is_empty() {
+        return None;
+    }
+
+    Some(args.iter().map(|arg| fill_vars(arg, vars)).collect())
+}
+
+/// Replace the placeholders in one pass, placeholders inside the values stay as they are.
+fn fill_vars(arg: &str, vars: &BTreeMap<String, String>) -> String {
+    let mut filled = String::new();
+    let mut rest = arg;
+
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        match rest[1..]
+            .find(['{', '}'])
+            .filter(|end| rest.as_bytes()[end + 1] == b'}')
+            .and_then(|end| vars.get(&rest[1..end + 1]).map(|value| (end, value)))
+        {
+            Some((end, value)) => {
+                filled.push_str(value);
+                rest = &rest[end + 2..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    filled.push_str(rest);
+
+    filled
+}
+
+/// Run the hook of the event, when one is set.
+pub fn trigger(config: &PlayoutConfig, event: HookEvent, mut vars: BTreeMap<String, String>) {
+    let command = hook_command(config, event);
+
+    if command.is_empty() {
+        return;
+    }
+
+    let id = config.general.channel_id;
+    vars.insert("event".to_string(), event.to_string());
+    vars.insert("channel".to_string(), id.to_string());
+
+    let Some(args) = hook_args(command, &vars) else {
+        error!(target: Target::file_mail(), channel = id; "Hook {event} is not a valid command: <span class=\"log-cmd\">{command}</span>");
+        return;
+    };
+
+    tokio::spawn(run(id, event, args, vars));
+}
+
+async fn run(id: i32, event: HookEvent, args: Vec<String>, vars: BTreeMap<String, String>) {
+    let mut cmd = Command::new(&args[0]);
+
+    cmd.args(&args[1..])
+        .envs(
+            vars.iter()
+                .map(|(k, v)| (format!("FFPLAYOUT_{}", k.to_uppercase()), v)),
+        )
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .kill_on_drop(true);
+
+    trace!("Run {event} hook: {args:?}");
+
+    let mut child = match cmd.spawn() {
+        Ok(c) => c,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Couldn't run {event} hook <span class=\"log-cmd\">{}</span>: {e}", args[0]);
+            return;
+        }
+    };
+
+    match timeout(HOOK_TIMEOUT, child.wait()).await {
+        Ok(Ok(status)) if !status.success() => {
+            error!(target: Target::file_mail(), channel = id; "Hook {event} stops with: {status}");
+        }
+        Ok(Err(e)) => {
+            error!(target: Target::file_mail(), channel = id; "Hook {event}: {e}");
+        }
+        Err(_) => {
+            let _ = child.kill().await;
+
+            error!(target: Target::file_mail(), channel = id; "Hook {event} killed after <span class=\"log-number\">{}s</span>", HOOK_TIMEOUT.as_secs());
+        }
+        _ => (),
+    }
+}
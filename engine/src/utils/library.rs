@@ -0,0 +1,283 @@
+/// Media Library
+///
+/// Index of the channel storage in the database: duration, resolution, codecs, loudness
+/// and the date of the last airing for every media file. The frontend and the playlist
+/// generator can search and list it, without walking through the file system.
+///
+/// A scan probes only new or changed files and removes entries from deleted files.
+/// The loudness is measured with the `ebur128` filter, which needs to decode the whole audio.
+///
+/// The last airing is also the base for the separation rule (`storage.separation`):
+/// clips, which aired within the last hours, are skipped in folder mode and in the generator.
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    process::Stdio,
+    sync::{LazyLock, Mutex},
+    time::UNIX_EPOCH,
+};
+
+use async_walkdir::WalkDir;
+use chrono::{DateTime, Duration};
+use chrono_tz::Tz;
+use log::*;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use tokio::process::Command;
+use tokio_stream::StreamExt;
+
+use crate::db::{handles, models::MediaEntry};
+use crate::player::utils::{Media, ffmpeg_bin, include_file_extension, probe::MediaProbe};
+use crate::utils::{
+    config::PlayoutConfig, errors::ServiceError, logging::Target, time_machine::time_now,
+};
+
+/// Format of the `last_aired` column, which sorts like the time.
+pub const AIRED_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Channels with a running scan.
+static SCANNING: LazyLock<Mutex<HashSet<i32>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LibraryQuery {
+    /// Part of the path.
+    pub search: String,
+    /// Folder relative to the storage, including subfolders.
+    pub folder: String,
+    /// `path`, `duration`, `last_aired` or `loudness`.
+    pub order: String,
+    /// Maximum entries, 0 is unlimited.
+    pub limit: u32,
+    pub offset: u32,
+}
+
+/// Path relative to the storage, `None` when it is outside.
+pub fn relative_path(storage: &Path, source: &str) -> Option<String> {
+    Path::new(source)
+        .strip_prefix(storage)
+        .ok()
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+/// Integrated loudness from the `ebur128` summary.
+pub fn parse_loudness(output: &str) -> Option<f64> {
+    output
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|l| l.starts_with("I:") && l.ends_with("LUFS"))
+        .and_then(|l| {
+            l.trim_start_matches("I:")
+                .trim_end_matches("LUFS")
+                .trim()
+                .parse()
+                .ok()
+        })
+}
+
+async fn measure_loudness(path: &Path) -> Option<f64> {
+    let output = Command::new(ffmpeg_bin())
+        .args(["-hide_banner", "-nostats", "-i"])
+        .arg(path)
+        .args(["-vn", "-af", "ebur128", "-f", "null", "-"])
+        .kill_on_drop(true)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .ok()?;
+
+    parse_loudness(&String::from_utf8_lossy(&output.stderr))
+}
+
+async fn index_file(
+    channel_id: i32,
+    path: &Path,
+    relative: String,
+    size: i64,
+    modified: i64,
+) -> MediaEntry {
+    let mut entry = MediaEntry {
+        channel_id,
+        path: relative,
+        size,
+        modified,
+        ..Default::default()
+    };
+
+    if let Ok(probe) = MediaProbe::new(path).await {
+        entry.duration = probe.format.duration.unwrap_or_default();
+
+        if let Some(video) = probe.video.first() {
+            entry.width = video.width;
+            entry.height = video.height;
+            entry.video_codec.clone_from(&video.codec_name);
+        }
+
+        if let Some(audio) = probe.audio.first() {
+            entry.audio_codec.clone_from(&audio.codec_name);
+            entry.loudness = measure_loudness(path).await;
+        }
+    }
+
+    entry
+}
+
+/// Update the index from the storage, returns the number of probed files.
+pub async fn scan(pool: &Pool<Sqlite>, config: &PlayoutConfig) -> Result<usize, ServiceError> {
+    let id = config.general.channel_id;
+
+    if !SCANNING.lock().unwrap().insert(id) {
+        return Err(ServiceError::Conflict(
+            "Library scan is already running".to_string(),
+        ));
+    }
+
+    let result = scan_storage(pool, config).await;
+
+    SCANNING.lock().unwrap().remove(&id);
+
+    result
+}
+
+async fn scan_storage(pool: &Pool<Sqlite>, config: &PlayoutConfig) -> Result<usize, ServiceError> {
+    let id = config.general.channel_id;
+    let storage = &config.channel.storage;
+    let mut known: HashMap<String, (i64, i64)> =
+        handles::select_library(pool, id, &LibraryQuery::default())
+            .await?
+            .into_iter()
+            .map(|e| (e.path, (e.size, e.modified)))
+            .collect();
+    let mut entries = WalkDir::new(storage);
+    let mut probed = 0;
+
+    while let Some(Ok(entry)) = entries.next().await {
+        let path = entry.path();
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+
+        if hidden || !path.is_file() || !include_file_extension(config, &path) {
+            continue;
+        }
+
+        let Some(relative) = relative_path(storage, &path.to_string_lossy()) else {
+            continue;
+        };
+        let Ok(meta) = entry.metadata().await else {
+            continue;
+        };
+        let size = meta.len() as i64;
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs() as i64);
+
+        if known.remove(&relative) == Some((size, modified)) {
+            continue;
+        }
+
+        let media = index_file(id, &path, relative, size, modified).await;
+        handles::insert_or_update_media(pool, &media).await?;
+        probed += 1;
+    }
+
+    for path in known.keys() {
+        handles::delete_media(pool, id, path).await?;
+    }
+
+    info!(target: Target::file_mail(), channel = id;
+        "Library scan done, <span class=\"log-number\">{probed}</span> files indexed, <span class=\"log-number\">{}</span> removed",
+        known.len()
+    );
+
+    Ok(probed)
+}
+
+/// Media from the index in the given folder, without probing.
+///
+/// Returns `None` when the folder is not indexed, then the caller has to look into the file system.
+pub async fn folder_media(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    folder: &Path,
+) -> Option<Vec<Media>> {
+    let storage = &config.channel.storage;
+    let relative = relative_path(storage, &folder.to_string_lossy())?;
+    let query = LibraryQuery {
+        folder: relative,
+        ..Default::default()
+    };
+    let entries = handles::select_library(pool, config.general.channel_id, &query)
+        .await
+        .ok()
+        .map(|e| e.into_iter().filter(|e| e.modified > 0).collect::<Vec<_>>())
+        .filter(|e| !e.is_empty())?;
+
+    let mut list = vec![];
+
+    for entry in entries {
+        let mut media = Media::new(0, &storage.join(&entry.path).to_string_lossy(), false).await;
+        media.duration = entry.duration;
+        media.out = entry.duration;
+
+        list.push(media);
+    }
+
+    Some(list)
+}
+
+/// Remember the airing of a clip from the storage.
+pub async fn aired(pool: &Pool<Sqlite>, config: &PlayoutConfig, source: &str, date: &str) {
+    if let Some(path) = relative_path(&config.channel.storage, source)
+        && let Err(e) =
+            handles::update_media_aired(pool, config.general.channel_id, &path, date).await
+    {
+        error!(target: Target::file_mail(), channel = config.general.channel_id; "Library: {e}");
+    }
+}
+
+/// Start of the separation window, in the format of the `last_aired` column.
+pub fn separation_start(now: &DateTime<Tz>, hours: f64) -> String {
+    (*now - Duration::seconds((hours * 3600.0) as i64))
+        .format(AIRED_FORMAT)
+        .to_string()
+}
+
+/// Sources, which aired within the separation window.
+pub async fn recently_aired(pool: &Pool<Sqlite>, config: &PlayoutConfig) -> HashSet<String> {
+    if config.storage.separation <= 0.0 {
+        return HashSet::new();
+    }
+
+    let since = separation_start(
+        &time_now(&config.channel.timezone),
+        config.storage.separation,
+    );
+
+    match handles::select_aired_since(pool, config.general.channel_id, &since).await {
+        Ok(paths) => paths
+            .into_iter()
+            .map(|p| config.channel.storage.join(p).to_string_lossy().to_string())
+            .collect(),
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = config.general.channel_id; "Library: {e}");
+
+            HashSet::new()
+        }
+    }
+}
+
+/// Remove the recently aired clips from the list.
+///
+/// When all clips aired recently, the list stays as it is, an empty schedule is worse than a repeat.
+pub fn separate(list: Vec<Media>, recent: &HashSet<String>) -> Vec<Media> {
+    if list.iter().all(|m| recent.contains(&m.source)) {
+        return list;
+    }
+
+    list.into_iter()
+        .filter(|m| !recent.contains(&m.source))
+        .collect()
+}
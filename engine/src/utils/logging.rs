@@ -23,7 +23,8 @@ use super::ARGS;
 use crate::db::GLOBAL_SETTINGS;
 use crate::utils::{
     ServiceError,
-    config::FFMPEG_UNRECOVERABLE_ERRORS,
+    config::{FFMPEG_UNRECOVERABLE_ERRORS, fill_path},
+    error_report,
     mail::{MailQueue, mail_queue},
     time_machine::time_now,
 };
@@ -37,7 +38,7 @@ pub struct Target;
 
 impl Target {
     pub fn all() -> &'static str {
-        if ARGS.log_to_console {
+        if console_only() {
             "{_Default}"
         } else {
             "{file,mail,_Default}"
@@ -65,7 +66,11 @@ pub struct LogConsole;
 
 impl LogWriter for LogConsole {
     fn write(&self, now: &mut DeferredNow, record: &Record<'_>) -> std::io::Result<()> {
-        console_formatter(&mut std::io::stderr(), now, record)?;
+        if ARGS.container {
+            console_formatter(&mut std::io::stdout(), now, record)?;
+        } else {
+            console_formatter(&mut std::io::stderr(), now, record)?;
+        }
 
         println!();
         Ok(())
@@ -75,29 +80,28 @@ impl LogWriter for LogConsole {
     }
 }
 
+#[derive(Default)]
 pub struct MultiFileLogger {
-    log_path: PathBuf,
     writers: RwLock<HashMap<i32, Arc<FileLogWriter>>>,
 }
 
 impl MultiFileLogger {
-    pub fn new(log_path: PathBuf) -> Self {
-        Self {
-            log_path,
-            writers: RwLock::new(HashMap::new()),
-        }
-    }
-
     fn get_writer(&self, channel: i32) -> io::Result<Arc<FileLogWriter>> {
         // Lock the writers HashMap
         let mut writers = self.writers.write().unwrap();
 
         // Check if the writer already exists
         if let hash_map::Entry::Vacant(entry) = writers.entry(channel) {
+            let log_path = log_file_path(channel);
+
+            if !log_path.is_dir() {
+                std::fs::create_dir_all(&log_path)?;
+            }
+
             let writer = FileLogWriter::builder(
                 FileSpec::default()
                     .suppress_timestamp()
-                    .directory(&self.log_path)
+                    .directory(log_path)
                     .basename("ffplayout")
                     .discriminant(channel.to_string()),
             )
@@ -174,6 +178,8 @@ impl LogWriter for LogMailer {
         let now = now.now().format("%Y-%m-%d %H:%M:%S");
         let msg = strip_tags(&message);
 
+        error_report::capture(id, level, &msg);
+
         tokio::spawn({
             async move {
                 let mut queues_guard = mail_queues.lock().await;
@@ -349,26 +355,34 @@ fn file_formatter(
     write!(w, "{time} {log_line}")
 }
 
-pub fn log_file_path() -> PathBuf {
+/// Log folder of the channel, the path can have `{channel}` and `{hostname}`. The files
+/// rotate by day, so there is no `{date}`.
+pub fn log_file_path(channel: i32) -> PathBuf {
     let config = GLOBAL_SETTINGS.get().unwrap();
-    let mut log_path = PathBuf::from(&ARGS.logs.as_ref().unwrap_or(&config.logs));
+    let logs = ARGS.logs.as_ref().unwrap_or(&config.logs);
+    let mut log_path = PathBuf::from(fill_path(logs, channel));
 
     if !log_path.is_absolute() {
         log_path = env::current_dir().unwrap().join(log_path);
     }
 
-    if !log_path.is_dir() {
+    if !logs.contains('{') && !log_path.is_dir() {
         log_path = env::current_dir().unwrap();
     }
 
     log_path
 }
 
+/// In container mode logs go only to the console, like with `--log-to-console`.
+fn console_only() -> bool {
+    ARGS.log_to_console || ARGS.container
+}
+
 fn file_logger() -> Box<dyn LogWriter> {
-    if ARGS.log_to_console {
+    if console_only() {
         Box::new(LogConsole)
     } else {
-        Box::new(MultiFileLogger::new(log_file_path()))
+        Box::new(MultiFileLogger::default())
     }
 }
 
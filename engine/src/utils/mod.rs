@@ -17,21 +17,44 @@ use serde::{
 };
 
 pub mod advanced_config;
+pub mod alert;
 pub mod args_parse;
+pub mod as_run;
+pub mod calendar;
 pub mod channels;
+pub mod clock;
+pub mod companion;
+pub mod compliance;
 pub mod config;
 pub mod control;
+pub mod downstream;
+pub mod error_report;
 pub mod errors;
 pub mod generator;
+pub mod hooks;
+pub mod library;
 pub mod logging;
 pub mod mail;
+pub mod now_playing;
+pub mod osc;
 pub mod playlist;
+pub mod redundancy;
+pub mod script;
+pub mod staging;
+pub mod statistics;
 pub mod system;
 pub mod task_runner;
+pub mod telemetry;
+pub mod ticker;
 pub mod time_machine;
+pub mod triggers;
+pub mod update;
+pub mod vdcp;
+pub mod vod;
+pub mod widgets;
 
 use crate::ARGS;
-use crate::db::GLOBAL_SETTINGS;
+use crate::db::{GLOBAL_SETTINGS, models::TextPreset};
 use crate::player::utils::time_to_sec;
 use crate::utils::{
     errors::ServiceError,
@@ -95,6 +118,23 @@ where
     deserializer.deserialize_any(StringOrNumberVisitor)
 }
 
+impl From<TextPreset> for TextFilter {
+    fn from(preset: TextPreset) -> Self {
+        Self {
+            text: Some(preset.text),
+            x: Some(preset.x),
+            y: Some(preset.y),
+            fontsize: Some(preset.fontsize),
+            line_spacing: Some(preset.line_spacing),
+            fontcolor: Some(preset.fontcolor),
+            alpha: Some(preset.alpha),
+            r#box: Some(preset.r#box),
+            boxcolor: Some(preset.boxcolor),
+            boxborderw: Some(preset.boxborderw),
+        }
+    }
+}
+
 impl fmt::Display for TextFilter {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let escaped_text = self
@@ -203,7 +243,7 @@ pub async fn read_log_file(
         format!("_{date}")
     };
 
-    let log_path = log_file_path()
+    let log_path = log_file_path(*channel_id)
         .join(format!("ffplayout_{channel_id}{date_str}.log"))
         .clean();
     let file_size = fs::metadata(&log_path).await?.len() as f64;
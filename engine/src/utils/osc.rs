@@ -0,0 +1,324 @@
+/// OSC (Open Sound Control)
+///
+/// Audio consoles and show control software can trigger playout actions with OSC messages
+/// over UDP (`task.osc_address`), and get now playing and timer values back
+/// (`task.osc_feedback`, a list of `host:port` targets).
+///
+/// Incoming addresses are `/ffplayout/<action>`, the actions are the
+/// [trigger actions](crate::utils::triggers::TriggerAction), a string argument is the
+/// parameter (`/ffplayout/insert "promos/evening.mp4"`). Buttons send often `1` on press
+/// and `0` on release, so a message with `0` as first argument is ignored.
+/// `/ffplayout/volume` and `/ffplayout/logo_opacity` take a number and change the running
+/// filters, like the live control.
+///
+/// Feedback goes out every [`FEEDBACK_INTERVAL`] under `/ffplayout/now/...`,
+/// `/ffplayout/next/title` and `/ffplayout/ingest`.
+use std::{
+    net::{SocketAddr, ToSocketAddrs},
+    sync::atomic::Ordering,
+    time::Duration,
+};
+
+use log::*;
+use rosc::{OscMessage, OscPacket, OscType, decoder, encoder};
+use tokio::{
+    net::UdpSocket,
+    time::{sleep, timeout},
+};
+
+use crate::player::{controller::ChannelManager, utils::clip_seconds};
+use crate::utils::{
+    config::PlayoutConfig,
+    control::{FilterParams, update_filter},
+    logging::Target,
+    now_playing::NowPlaying,
+    triggers::{TriggerAction, run_action},
+};
+
+pub const PREFIX: &str = "/ffplayout/";
+pub const FEEDBACK_INTERVAL: Duration = Duration::from_millis(500);
+
+const POLL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OscCommand {
+    Action(TriggerAction),
+    Volume(f64),
+    LogoOpacity(f64),
+}
+
+fn number(arg: &OscType) -> Option<f64> {
+    match arg {
+        OscType::Int(v) => Some(*v as f64),
+        OscType::Long(v) => Some(*v as f64),
+        OscType::Float(v) => Some(*v as f64),
+        OscType::Double(v) => Some(*v),
+        OscType::Bool(v) => Some(*v as u8 as f64),
+        _ => None,
+    }
+}
+
+/// Command of a message, `None` for a button release.
+pub fn osc_command(msg: &OscMessage) -> Result<Option<OscCommand>, String> {
+    let Some(name) = msg.addr.strip_prefix(PREFIX) else {
+        return Err(format!("OSC address '{}' not valid!", msg.addr));
+    };
+    let value = msg.args.first().and_then(number);
+
+    match name {
+        "volume" | "logo_opacity" => {
+            let Some(value) = value else {
+                return Err(format!("OSC {} needs a number!", msg.addr));
+            };
+
+            if name == "volume" {
+                Ok(Some(OscCommand::Volume(value)))
+            } else {
+                Ok(Some(OscCommand::LogoOpacity(value)))
+            }
+        }
+        _ if value == Some(0.0) => Ok(None),
+        _ => {
+            let arg = msg.args.iter().find_map(|a| match a {
+                OscType::String(s) => Some(s.as_str()),
+                _ => None,
+            });
+            let action = match arg {
+                Some(arg) => format!("{name} {arg}"),
+                None => name.to_string(),
+            };
+
+            Ok(Some(OscCommand::Action(action.parse()?)))
+        }
+    }
+}
+
+/// Feedback targets from the setting, separated by comma or space.
+pub fn feedback_targets(targets: &str) -> (Vec<SocketAddr>, Vec<String>) {
+    let mut addresses = vec![];
+    let mut errors = vec![];
+
+    for target in targets.split([',', ' ', '\n']).filter(|t| !t.is_empty()) {
+        match target.to_socket_addrs().ok().and_then(|mut a| a.next()) {
+            Some(addr) => addresses.push(addr),
+            None => errors.push(format!("OSC feedback target '{target}' is not host:port!")),
+        }
+    }
+
+    (addresses, errors)
+}
+
+/// Playout state for the feedback.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Feedback {
+    pub title: String,
+    pub source: String,
+    pub index: i32,
+    pub elapsed: f64,
+    pub duration: f64,
+    pub next_title: String,
+    pub ingest: bool,
+}
+
+impl Feedback {
+    pub fn messages(&self) -> Vec<OscMessage> {
+        let msg = |addr: &str, arg: OscType| OscMessage {
+            addr: format!("{PREFIX}{addr}"),
+            args: vec![arg],
+        };
+
+        vec![
+            msg("now/title", OscType::String(self.title.clone())),
+            msg("now/source", OscType::String(self.source.clone())),
+            msg("now/index", OscType::Int(self.index)),
+            msg("now/elapsed", OscType::Float(self.elapsed as f32)),
+            msg("now/duration", OscType::Float(self.duration as f32)),
+            msg(
+                "now/remaining",
+                OscType::Float((self.duration - self.elapsed).max(0.0) as f32),
+            ),
+            msg("next/title", OscType::String(self.next_title.clone())),
+            msg("ingest", OscType::Int(self.ingest as i32)),
+        ]
+    }
+}
+
+async fn feedback(manager: &ChannelManager, id: i32) -> Feedback {
+    let channel = manager.channel.lock().await.clone();
+    let ingest = manager.ingest_is_alive.load(Ordering::SeqCst);
+    let next_index = manager.current_index.load(Ordering::SeqCst);
+    let next_title = manager
+        .current_list
+        .lock()
+        .await
+        .get(next_index)
+        .map(|m| NowPlaying::new(id, m).title)
+        .unwrap_or_default();
+
+    let Some(media) = manager.current_media.lock().await.clone() else {
+        return Feedback {
+            next_title,
+            ingest,
+            ..Default::default()
+        };
+    };
+
+    let now_playing = NowPlaying::new(id, &media);
+    let begin = media.begin.unwrap_or(0.0) - channel.time_shift;
+    let elapsed =
+        (clip_seconds(&*manager.config.read().await) - begin).clamp(0.0, now_playing.duration);
+
+    Feedback {
+        title: now_playing.title,
+        source: now_playing.source,
+        index: media.index.unwrap_or_default() as i32,
+        elapsed,
+        duration: now_playing.duration,
+        next_title,
+        ingest,
+    }
+}
+
+async fn send_feedback(
+    manager: &ChannelManager,
+    id: i32,
+    socket: &UdpSocket,
+    targets: &[SocketAddr],
+) {
+    while manager.is_alive.load(Ordering::SeqCst) {
+        for msg in feedback(manager, id).await.messages() {
+            let Ok(packet) = encoder::encode(&OscPacket::Message(msg)) else {
+                continue;
+            };
+
+            for target in targets {
+                if let Err(e) = socket.send_to(&packet, target).await {
+                    trace!("OSC feedback to {target}: {e}");
+                }
+            }
+        }
+
+        sleep(FEEDBACK_INTERVAL).await;
+    }
+}
+
+async fn run_command(manager: &ChannelManager, id: i32, msg: OscMessage) {
+    let command = match osc_command(&msg) {
+        Ok(Some(c)) => c,
+        Ok(None) => return,
+        Err(e) => {
+            warn!(target: Target::file_mail(), channel = id; "{e}");
+            return;
+        }
+    };
+
+    debug!(target: Target::file_mail(), channel = id; "OSC command: {command:?}");
+
+    let result = match command {
+        OscCommand::Action(action) => run_action(manager, &action).await,
+        OscCommand::Volume(volume) => {
+            let params = FilterParams {
+                volume: Some(volume),
+                ..Default::default()
+            };
+
+            update_filter(manager.clone(), params).await.map(|_| ())
+        }
+        OscCommand::LogoOpacity(opacity) => {
+            let params = FilterParams {
+                logo_opacity: Some(opacity),
+                ..Default::default()
+            };
+
+            update_filter(manager.clone(), params).await.map(|_| ())
+        }
+    };
+
+    if let Err(e) = result {
+        error!(target: Target::file_mail(), channel = id; "OSC <span class=\"log-addr\">{}</span> failed: {e}", msg.addr);
+    }
+}
+
+/// Flatten bundles to their messages.
+fn packet_messages(packet: OscPacket, messages: &mut Vec<OscMessage>) {
+    match packet {
+        OscPacket::Message(msg) => messages.push(msg),
+        OscPacket::Bundle(bundle) => {
+            for packet in bundle.content {
+                packet_messages(packet, messages);
+            }
+        }
+    }
+}
+
+async fn receive(manager: &ChannelManager, id: i32, socket: &UdpSocket) {
+    let mut buffer = [0u8; decoder::MTU];
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        let num = match timeout(POLL, socket.recv_from(&mut buffer)).await {
+            Ok(Ok((n, _))) => n,
+            Ok(Err(e)) => {
+                error!(target: Target::file_mail(), channel = id; "OSC: {e}");
+                continue;
+            }
+            Err(_) => continue,
+        };
+
+        let packet = match decoder::decode_udp(&buffer[..num]) {
+            Ok((_, packet)) => packet,
+            Err(e) => {
+                warn!(target: Target::file_mail(), channel = id; "OSC packet not valid: {e}");
+                continue;
+            }
+        };
+
+        let mut messages = vec![];
+        packet_messages(packet, &mut messages);
+
+        for msg in messages {
+            run_command(manager, id, msg).await;
+        }
+    }
+}
+
+/// Receive commands and send feedback until the channel stops.
+pub async fn run(manager: ChannelManager, config: PlayoutConfig) {
+    let id = config.general.channel_id;
+    let address = config.task.osc_address.trim();
+    let (targets, errors) = feedback_targets(&config.task.osc_feedback);
+
+    for e in errors {
+        error!(target: Target::file_mail(), channel = id; "{e}");
+    }
+
+    // feedback only needs a socket for sending
+    let bind = if address.is_empty() {
+        "0.0.0.0:0"
+    } else {
+        address
+    };
+
+    let socket = match UdpSocket::bind(bind).await {
+        Ok(s) => s,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "OSC address <span class=\"log-addr\">{bind}</span>: {e}");
+            return;
+        }
+    };
+
+    if !address.is_empty() {
+        info!(target: Target::file_mail(), channel = id; "OSC listening on: <span class=\"log-addr\">{address}</span>");
+    }
+
+    match (address.is_empty(), targets.is_empty()) {
+        (false, false) => {
+            tokio::join!(
+                receive(&manager, id, &socket),
+                send_feedback(&manager, id, &socket, &targets)
+            );
+        }
+        (false, true) => receive(&manager, id, &socket).await,
+        (true, false) => send_feedback(&manager, id, &socket, &targets).await,
+        (true, true) => (),
+    }
+}
@@ -3,7 +3,10 @@ use std::path::PathBuf;
 use log::*;
 use tokio::fs;
 
-use crate::file::norm_abs_path;
+use crate::file::{
+    backend::{Backend, StorageBackend},
+    norm_abs_path,
+};
 use crate::player::controller::ChannelManager;
 use crate::player::utils::{JsonPlaylist, json_reader, json_writer};
 use crate::utils::{config::PlayoutConfig, errors::ServiceError, generator::playlist_generator};
@@ -21,6 +24,15 @@ pub async fn read_playlist(
         .join(date.clone())
         .with_extension("json");
 
+    let backend = Backend::new(&playlist_path.to_string_lossy());
+
+    if !backend.is_local() {
+        let object = backend.read(&playlist_path.to_string_lossy()).await?;
+
+        return serde_json::from_slice(&object.data)
+            .map_err(|e| ServiceError::Conflict(e.to_string()));
+    }
+
     if !playlist_path.is_file() {
         return Err(ServiceError::NoContent());
     }
@@ -53,6 +65,30 @@ pub async fn write_playlist(
     }
 
     let mut file_exists = false;
+    let location = playlist_path.to_string_lossy().to_string();
+    let backend = Backend::new(&location);
+
+    if !backend.is_local() {
+        if let Ok(object) = backend.read(&location).await {
+            file_exists = true;
+
+            if serde_json::from_slice::<JsonPlaylist>(&object.data).is_ok_and(|p| p == json_data) {
+                return Err(ServiceError::Conflict(format!(
+                    "Playlist from {date}, already exists!"
+                )));
+            }
+        }
+
+        backend
+            .write(&location, serde_json::to_vec_pretty(&json_data)?)
+            .await?;
+
+        return if file_exists {
+            Ok(format!("Update playlist from {date} success!"))
+        } else {
+            Ok(format!("Write playlist from {date} success!"))
+        };
+    }
 
     if let Some(p) = playlist_path.parent() {
         fs::create_dir_all(p).await?;
@@ -106,6 +142,11 @@ pub async fn generate_playlist(manager: ChannelManager) -> Result<JsonPlaylist,
             source.paths = resolved_paths;
         }
 
+        if let Some(ident) = template.ident.as_mut() {
+            let (safe_path, _, _) = norm_abs_path(&storage_root, &ident.path.to_string_lossy())?;
+            ident.path = safe_path;
+        }
+
         {
             let mut config = manager.config.write().await;
             config.general.template = Some(template);
@@ -139,6 +180,17 @@ pub async fn delete_playlist(config: &PlayoutConfig, date: &str) -> Result<Strin
         .join(date)
         .with_extension("json");
 
+    let location = playlist_path.to_string_lossy().to_string();
+    let backend = Backend::new(&location);
+
+    if !backend.is_local() {
+        return match backend.delete(&location).await {
+            Ok(..) => Ok(format!("Delete playlist from {date} success!")),
+            Err(ServiceError::NoContent()) => Ok(format!("No playlist to delete on: {date}")),
+            Err(e) => Err(e),
+        };
+    }
+
     if playlist_path.is_file() {
         match fs::remove_file(playlist_path).await {
             Ok(..) => Ok(format!("Delete playlist from {date} success!")),
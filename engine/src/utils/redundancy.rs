@@ -0,0 +1,261 @@
+/// Redundancy
+///
+/// Two instances run the same channel with the same playlists, one with the role `active`,
+/// the other one `passive` (`task.redundancy_role`). Both play the schedule, but only one
+/// is on air: the other one runs its encoder with null output, so it stays in sync and
+/// can take over within seconds.
+///
+/// The instances send each other a heartbeat over UDP (`task.redundancy_address` and
+/// `task.redundancy_peer`). When the heartbeat of the on air instance is missing for
+/// `task.redundancy_timeout` seconds, the standby instance takes over the output.
+///
+/// The stream key is used only by one instance at a time:
+///
+/// - An instance which stops on purpose sends a release, so the peer takes over at once.
+/// - An instance which starts waits for the timeout, before it goes on air, so it doesn't
+///   push while the peer is on air after a failover. It stays in standby then.
+/// - When both are on air (after a network split), the passive instance goes back to standby.
+use std::{
+    net::{SocketAddr, ToSocketAddrs},
+    str::FromStr,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use log::*;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    net::UdpSocket,
+    time::{sleep, timeout},
+};
+
+use crate::player::controller::{ChannelManager, ProcessUnit::*};
+use crate::utils::{
+    config::{OutputMode, PlayoutConfig},
+    logging::Target,
+};
+
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Role {
+    #[default]
+    Off,
+    Active,
+    Passive,
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "" | "off" => Ok(Self::Off),
+            "active" => Ok(Self::Active),
+            "passive" => Ok(Self::Passive),
+            _ => Err(format!("Redundancy role '{input}' not valid!")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Heartbeat {
+    pub on_air: bool,
+    /// Sent on stop, the peer takes over without waiting for the timeout.
+    #[serde(default)]
+    pub release: bool,
+}
+
+/// Output state of one instance, from the peer heartbeats.
+#[derive(Clone, Debug)]
+pub struct Failover {
+    role: Role,
+    timeout: Duration,
+    started: Instant,
+    last_peer: Option<Instant>,
+    peer_on_air: bool,
+    released: bool,
+    on_air: bool,
+}
+
+impl Failover {
+    pub fn new(role: Role, timeout: Duration, now: Instant) -> Self {
+        Self {
+            role,
+            timeout,
+            started: now,
+            last_peer: None,
+            peer_on_air: false,
+            released: false,
+            on_air: false,
+        }
+    }
+
+    pub fn heartbeat(&mut self, heartbeat: &Heartbeat, now: Instant) {
+        if heartbeat.release {
+            self.last_peer = None;
+            self.peer_on_air = false;
+            self.released = true;
+        } else {
+            self.last_peer = Some(now);
+            self.peer_on_air = heartbeat.on_air;
+            self.released = false;
+        }
+    }
+
+    pub fn peer_alive(&self, now: Instant) -> bool {
+        self.last_peer
+            .is_some_and(|t| now.duration_since(t) < self.timeout)
+    }
+
+    pub fn on_air(&self) -> bool {
+        self.on_air
+    }
+
+    /// Update the output state, returns the new state when it changes.
+    pub fn check(&mut self, now: Instant) -> Option<bool> {
+        let on_air = if self.peer_alive(now) {
+            if self.peer_on_air {
+                // only one instance uses the stream key
+                self.on_air && self.role == Role::Active
+            } else {
+                self.on_air || self.role == Role::Active
+            }
+        } else {
+            self.on_air || self.released || now.duration_since(self.started) >= self.timeout
+        };
+
+        if on_air == self.on_air {
+            return None;
+        }
+
+        self.on_air = on_air;
+
+        Some(on_air)
+    }
+}
+
+/// Redundancy needs a role and an encoder, which can switch to standby.
+pub fn enabled(config: &PlayoutConfig) -> bool {
+    let id = config.general.channel_id;
+
+    match config.task.redundancy_role.parse::<Role>() {
+        Ok(Role::Off) => false,
+        Ok(_) if config.output.mode == OutputMode::HLS => {
+            error!(target: Target::file_mail(), channel = id; "Redundancy doesn't work with HLS output!");
+            false
+        }
+        Ok(_) => true,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "{e}");
+            false
+        }
+    }
+}
+
+async fn send(socket: &UdpSocket, peer: SocketAddr, heartbeat: &Heartbeat) {
+    if let Ok(data) = serde_json::to_vec(heartbeat)
+        && let Err(e) = socket.send_to(&data, peer).await
+    {
+        trace!("Redundancy heartbeat to {peer}: {e}");
+    }
+}
+
+/// Exchange heartbeats with the peer and switch the output, until the channel stops.
+pub async fn run(manager: ChannelManager, config: PlayoutConfig) {
+    let id = config.general.channel_id;
+    let task = &config.task;
+    let role = task.redundancy_role.parse::<Role>().unwrap_or_default();
+
+    let Some(peer) = task
+        .redundancy_peer
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut a| a.next())
+    else {
+        error!(target: Target::file_mail(), channel = id;
+            "Redundancy peer <span class=\"log-addr\">{}</span> is not host:port!",
+            task.redundancy_peer
+        );
+        return;
+    };
+
+    let socket = match UdpSocket::bind(&task.redundancy_address).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id;
+                "Redundancy can't listen on <span class=\"log-addr\">{}</span>: {e}",
+                task.redundancy_address
+            );
+            return;
+        }
+    };
+
+    info!(target: Target::file_mail(), channel = id;
+        "Redundancy as {role:?}, listening on: <span class=\"log-addr\">{}</span>, peer: <span class=\"log-addr\">{peer}</span>",
+        task.redundancy_address
+    );
+
+    let mut failover = Failover::new(
+        role,
+        Duration::from_secs(task.redundancy_timeout.max(1) as u64),
+        Instant::now(),
+    );
+    let mut buffer = [0u8; 1024];
+    let mut last_send: Option<Instant> = None;
+    let mut peer_alive = false;
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        if last_send.is_none_or(|t| t.elapsed() >= HEARTBEAT_INTERVAL) {
+            let heartbeat = Heartbeat {
+                on_air: failover.on_air(),
+                release: false,
+            };
+
+            send(&socket, peer, &heartbeat).await;
+            last_send = Some(Instant::now());
+        }
+
+        if let Ok(Ok((num, _))) = timeout(HEARTBEAT_INTERVAL, socket.recv_from(&mut buffer)).await {
+            match serde_json::from_slice::<Heartbeat>(&buffer[..num]) {
+                Ok(heartbeat) => failover.heartbeat(&heartbeat, Instant::now()),
+                Err(e) => trace!("Redundancy heartbeat not valid: {e}"),
+            }
+        }
+
+        let now = Instant::now();
+
+        if peer_alive != failover.peer_alive(now) {
+            peer_alive = !peer_alive;
+
+            if peer_alive {
+                info!(target: Target::file_mail(), channel = id; "Redundancy peer is up");
+            } else {
+                warn!(target: Target::file_mail(), channel = id; "Redundancy peer is down");
+            }
+        }
+
+        if let Some(on_air) = failover.check(now) {
+            if on_air {
+                warn!(target: Target::file_mail(), channel = id; "Redundancy: take over the output");
+            } else {
+                warn!(target: Target::file_mail(), channel = id; "Redundancy: peer is on air, go to standby");
+            }
+
+            manager.standby.store(!on_air, Ordering::SeqCst);
+            // the encoder worker starts again with the output of the new state
+            manager.stop(Encoder).await;
+        }
+    }
+
+    if failover.on_air() {
+        let heartbeat = Heartbeat {
+            on_air: false,
+            release: true,
+        };
+
+        send(&socket, peer, &heartbeat).await;
+        sleep(Duration::from_millis(50)).await;
+        send(&socket, peer, &heartbeat).await;
+    }
+}
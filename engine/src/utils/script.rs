@@ -0,0 +1,316 @@
+/// Scripting
+///
+/// A Lua script (`task.script`) decides about the next clip, before it plays. The script
+/// defines a global function `next_clip(clip, channel)`, which gets the clip as table
+/// (`source`, `audio`, `title`, `category`, `in`, `out`, `duration`, `custom_filter`,
+/// `program_id`, `index`, `begin`, ...) and the channel ID. The return value can be:
+///
+/// - nothing, `nil` or `true`: the clip plays as it is
+/// - `false`: veto, the clip gets skipped
+/// - a table: the fields replace the values of the clip, a new `source` gets probed again
+///
+/// The Lua state lives as long as the channel runs, so globals keep their values between
+/// clips. When the file changes, the script gets reloaded. A call, which takes longer than
+/// [`SCRIPT_TIMEOUT`], is aborted and the clip plays unchanged, like on any other script error.
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use log::*;
+use mlua::{
+    Error as LuaError, Function, HookTriggers, Lua, LuaOptions, LuaSerdeExt, StdLib, Value, VmState,
+};
+use serde::Deserialize;
+
+use crate::player::{
+    controller::ChannelManager,
+    utils::{Media, loop_image, seek_and_length},
+};
+use crate::utils::{
+    config::{IMAGE_FORMAT, PlayoutConfig},
+    errors::ServiceError,
+    logging::Target,
+};
+
+pub const SCRIPT_TIMEOUT: Duration = Duration::from_millis(500);
+const MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+const ENTRY_FUNCTION: &str = "next_clip";
+/// Functions, which could touch the system and are removed.
+const UNSAFE_FUNCTIONS: [(&str, &str); 8] = [
+    ("_G", "dofile"),
+    ("_G", "loadfile"),
+    ("os", "execute"),
+    ("os", "exit"),
+    ("os", "getenv"),
+    ("os", "remove"),
+    ("os", "rename"),
+    ("os", "tmpname"),
+];
+
+/// Values, which a script can change.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct ClipChange {
+    pub source: Option<String>,
+    pub audio: Option<String>,
+    #[serde(rename = "in")]
+    pub seek: Option<f64>,
+    pub out: Option<f64>,
+    pub title: Option<String>,
+    pub category: Option<String>,
+    pub custom_filter: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decision {
+    Keep,
+    Veto,
+    Change(ClipChange),
+}
+
+pub struct Script {
+    lua: Lua,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl fmt::Debug for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Script")
+            .field("path", &self.path)
+            .field("modified", &self.modified)
+            .finish()
+    }
+}
+
+impl Script {
+    /// Load a script from code, the name is used in error messages.
+    pub fn from_code(code: &str, name: &str) -> Result<Self, ServiceError> {
+        let libs = StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8 | StdLib::OS;
+        let lua = Lua::new_with(libs, LuaOptions::default())?;
+        lua.set_memory_limit(MEMORY_LIMIT)?;
+
+        for (lib, name) in UNSAFE_FUNCTIONS {
+            let table = match lib {
+                "_G" => lua.globals(),
+                _ => lua.globals().get::<mlua::Table>(lib)?,
+            };
+
+            table.set(name, Value::Nil)?;
+        }
+
+        let script = Self {
+            lua,
+            path: PathBuf::from(name),
+            modified: None,
+        };
+
+        script.with_timeout(|lua| lua.load(code).set_name(name).exec())?;
+
+        if script.entry().is_none() {
+            return Err(ServiceError::BadRequest(format!(
+                "Script {name} has no {ENTRY_FUNCTION} function"
+            )));
+        }
+
+        Ok(script)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ServiceError> {
+        let code = std::fs::read_to_string(path)?;
+        let mut script = Self::from_code(&code, &path.to_string_lossy())?;
+        script.path = path.to_path_buf();
+        script.modified = modified(path);
+
+        Ok(script)
+    }
+
+    fn entry(&self) -> Option<Function> {
+        self.lua.globals().get::<Function>(ENTRY_FUNCTION).ok()
+    }
+
+    /// Run with the instruction hook, which aborts after the timeout.
+    fn with_timeout<T>(
+        &self,
+        f: impl FnOnce(&Lua) -> Result<T, LuaError>,
+    ) -> Result<T, ServiceError> {
+        let deadline = Instant::now() + SCRIPT_TIMEOUT;
+
+        self.lua.set_hook(
+            HookTriggers::new().every_nth_instruction(1000),
+            move |_, _| {
+                if Instant::now() > deadline {
+                    return Err(LuaError::runtime("script timeout"));
+                }
+
+                Ok(VmState::Continue)
+            },
+        )?;
+
+        let result = f(&self.lua);
+        self.lua.remove_hook();
+
+        Ok(result?)
+    }
+
+    /// Ask the script about the next clip.
+    pub fn decide(&self, node: &Media, channel: i32) -> Result<Decision, ServiceError> {
+        let Some(entry) = self.entry() else {
+            return Ok(Decision::Keep);
+        };
+
+        self.with_timeout(|lua| {
+            let clip = lua.to_value(node)?;
+
+            if let Value::Table(table) = &clip {
+                table.set("index", node.index)?;
+                table.set("begin", node.begin)?;
+            }
+
+            match entry.call::<Value>((clip, channel))? {
+                Value::Nil | Value::Boolean(true) => Ok(Decision::Keep),
+                Value::Boolean(false) => Ok(Decision::Veto),
+                value @ Value::Table(_) => Ok(Decision::Change(lua.from_value(value)?)),
+                other => Err(LuaError::runtime(format!(
+                    "{ENTRY_FUNCTION} returns {}, expected nil, boolean or table",
+                    other.type_name()
+                ))),
+            }
+        })
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|m| m.modified()).ok()
+}
+
+/// Take over the changed values, returns true when the decoder command has to be rebuilt.
+pub fn apply_change(node: &mut Media, change: ClipChange) -> bool {
+    let mut rebuild = false;
+
+    if let Some(source) = change.source.filter(|s| *s != node.source) {
+        node.source = source;
+        node.probe = None;
+        node.duration = 0.0;
+
+        if change.out.is_none() {
+            node.out = 0.0;
+        }
+
+        rebuild = true;
+    }
+
+    if let Some(audio) = change.audio.filter(|a| *a != node.audio) {
+        node.audio = audio;
+        node.probe_audio = None;
+        rebuild = true;
+    }
+
+    if let Some(seek) = change.seek.filter(|s| *s != node.seek) {
+        node.seek = seek;
+        rebuild = true;
+    }
+
+    if let Some(out) = change.out.filter(|o| *o != node.out) {
+        node.out = out;
+        rebuild = true;
+    }
+
+    if let Some(filter) = change.custom_filter.filter(|f| *f != node.custom_filter) {
+        node.custom_filter = filter;
+        rebuild = true;
+    }
+
+    if change.title.is_some() {
+        node.title = change.title;
+    }
+
+    if let Some(category) = change.category {
+        node.category = category;
+    }
+
+    rebuild
+}
+
+/// Ask the script of the channel, it gets (re)loaded when the file changed.
+async fn decide(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    node: &Media,
+) -> Result<Decision, ServiceError> {
+    let path = &config.task.script;
+    let mut script = manager.script.lock().await;
+
+    if script
+        .as_ref()
+        .is_none_or(|s| s.path != *path || s.modified != modified(path))
+    {
+        *script = None;
+        *script = Some(Script::load(path)?);
+
+        info!(target: Target::file_mail(), channel = config.general.channel_id;
+            "Load script: <span class=\"log-addr\">{}</span>",
+            path.display()
+        );
+    }
+
+    match script.as_ref() {
+        Some(s) => s.decide(node, config.general.channel_id),
+        None => Ok(Decision::Keep),
+    }
+}
+
+/// Let the script decide about the next clip, `None` is a veto.
+pub async fn process(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    mut node: Media,
+) -> Option<Media> {
+    let id = config.general.channel_id;
+
+    if config.task.script.as_os_str().is_empty() || node.cmd.is_none() || node.skip {
+        return Some(node);
+    }
+
+    match decide(manager, config, &node).await {
+        Ok(Decision::Keep) => Some(node),
+        Ok(Decision::Veto) => {
+            info!(target: Target::file_mail(), channel = id;
+                "Script skips <span class=\"log-addr\">{}</span>",
+                node.source
+            );
+
+            None
+        }
+        Ok(Decision::Change(change)) => {
+            if apply_change(&mut node, change) {
+                if let Err(e) = node.add_probe(true).await {
+                    error!(target: Target::file_mail(), channel = id;
+                        "Script source <span class=\"log-addr\">{}</span>: {e}",
+                        node.source
+                    );
+                }
+
+                let is_image = node
+                    .source
+                    .rsplit_once('.')
+                    .is_some_and(|(_, e)| IMAGE_FORMAT.contains(&e.to_lowercase().as_str()));
+
+                node.cmd = Some(if is_image {
+                    loop_image(config, &node)
+                } else {
+                    seek_and_length(config, &mut node)
+                });
+                node.add_filter(config, &manager.filter_chain).await;
+            }
+
+            Some(node)
+        }
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Script: {e}");
+
+            Some(node)
+        }
+    }
+}
@@ -0,0 +1,211 @@
+/// Staging
+///
+/// A changed config (filters, overlays, output settings) can be staged, before it goes on air.
+/// The staged config runs on a preview output, with the clip which is currently playing,
+/// from its position on air. The preview is a small HLS stream, served under
+/// `/<id>/preview/staging.m3u8`; the live output keeps running with the saved config.
+///
+/// Promoting saves the staged config and restarts the live output with it, discarding it
+/// stops the preview.
+use std::{process::Stdio, sync::atomic::Ordering, time::Duration};
+
+use log::*;
+use tokio::{
+    fs,
+    process::{Child, Command},
+    task::JoinHandle,
+};
+
+use crate::player::{
+    controller::ChannelManager,
+    process::ResourceLimits,
+    utils::{Media, add_thread_limit, ffmpeg_bin, get_delta, seek_and_length},
+};
+use crate::utils::{
+    config::{OutputMode, PlayoutConfig},
+    logging::{Target, fmt_cmd},
+};
+use crate::vec_strings;
+
+pub const STAGING_PREVIEW: &str = "staging.m3u8";
+
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+/// Don't start the preview for the last seconds of a clip.
+const MIN_PREVIEW_LENGTH: f64 = 2.0;
+
+/// Staged config with the task, which runs its preview.
+#[derive(Debug)]
+pub struct Staging {
+    pub config: PlayoutConfig,
+    preview: JoinHandle<()>,
+}
+
+impl Staging {
+    pub fn new(manager: &ChannelManager, config: PlayoutConfig) -> Self {
+        let preview = tokio::spawn(run(manager.clone(), config.clone()));
+
+        Self { config, preview }
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        // the ffmpeg process gets killed with the task
+        self.preview.abort();
+    }
+}
+
+/// Config for the preview: no zmq sockets, no audio meter and a single output.
+pub fn preview_config(config: &PlayoutConfig) -> PlayoutConfig {
+    let mut preview_config = config.clone();
+
+    preview_config.text.zmq_stream_socket = None;
+    preview_config.text.zmq_server_socket = None;
+    preview_config.output.mode = OutputMode::Null;
+    preview_config.output.output_count = 1;
+    preview_config.output.output_filter = None;
+    preview_config.processing.audio_meter = false;
+    preview_config.processing.live_control = false;
+
+    preview_config
+}
+
+/// Preview command for a clip, the input part is the same as for the decoder,
+/// but read in real time.
+pub fn preview_cmd(config: &PlayoutConfig, media: &Media) -> Vec<String> {
+    let mut media = media.clone();
+    let playlist = config.channel.public.join("preview").join(STAGING_PREVIEW);
+    let mut cmd = vec_strings!["-hide_banner", "-nostats", "-v", "level+error", "-re"];
+
+    cmd.append(&mut media.input_cmd(config));
+    cmd.append(&mut media.cmd.clone().unwrap_or_default());
+
+    if let Some(filter) = media.filter.as_mut() {
+        cmd.append(&mut filter.cmd());
+        cmd.append(&mut filter.map());
+    }
+
+    cmd.append(&mut vec_strings![
+        "-c:v",
+        "libx264",
+        "-preset",
+        "veryfast",
+        "-tune",
+        "zerolatency",
+        "-pix_fmt",
+        "yuv420p",
+        "-c:a",
+        "aac",
+        "-b:a",
+        "128k",
+        "-f",
+        "hls",
+        "-hls_time",
+        "2",
+        "-hls_list_size",
+        "5",
+        "-hls_flags",
+        "delete_segments+omit_endlist",
+        playlist.to_string_lossy()
+    ]);
+
+    add_thread_limit(config, &mut cmd);
+
+    cmd
+}
+
+/// Clip on air, with the seek of its current position.
+async fn on_air(manager: &ChannelManager, config: &PlayoutConfig) -> Option<Media> {
+    if !manager.is_alive.load(Ordering::SeqCst) {
+        return None;
+    }
+
+    let mut media = manager.current_media.lock().await.clone()?;
+    let (delta, _) = get_delta(config, &media.begin?);
+    let seek = media.seek + (-delta).max(0.0) * media.speed.unwrap_or(1.0);
+
+    if media.out - seek < MIN_PREVIEW_LENGTH {
+        return None;
+    }
+
+    media.seek = (seek * 1000.0).floor() / 1000.0;
+    media.cmd = Some(seek_and_length(config, &mut media));
+    media.add_filter(config, &None).await;
+
+    Some(media)
+}
+
+async fn spawn_preview(config: &PlayoutConfig, media: &Media) -> Option<Child> {
+    let id = config.general.channel_id;
+    let cmd = preview_cmd(config, media);
+
+    debug!(target: Target::file_mail(), channel = id;
+        "Staging CMD: <span class=\"log-cmd\">ffmpeg {}</span>",
+        fmt_cmd(&cmd)
+    );
+
+    match ResourceLimits::new(config).spawn(
+        Command::new(ffmpeg_bin())
+            .args(cmd)
+            .kill_on_drop(true)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null()),
+    ) {
+        Ok(proc) => Some(proc),
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Couldn't start staging preview: {e}");
+            None
+        }
+    }
+}
+
+/// Follow the clips on air and render them with the staged config, until the task gets aborted.
+async fn run(manager: ChannelManager, config: PlayoutConfig) {
+    let id = config.general.channel_id;
+    let config = preview_config(&config);
+    let folder = config.channel.public.join("preview");
+    let mut playing = None;
+    let mut proc: Option<Child> = None;
+
+    if let Err(e) = fs::create_dir_all(&folder).await {
+        error!(target: Target::file_mail(), channel = id; "Staging preview folder: {e}");
+        return;
+    }
+
+    let _ = fs::remove_file(folder.join(STAGING_PREVIEW)).await;
+
+    loop {
+        let current = if manager.is_alive.load(Ordering::SeqCst) {
+            manager
+                .current_media
+                .lock()
+                .await
+                .as_ref()
+                .map(|m| (m.source.clone(), m.begin))
+        } else {
+            None
+        };
+
+        if current != playing {
+            playing = current;
+            proc = None;
+
+            if playing.is_some()
+                && let Some(media) = on_air(&manager, &config).await
+            {
+                proc = spawn_preview(&config, &media).await;
+            }
+        } else if let Some(p) = proc.as_mut()
+            && let Ok(Some(status)) = p.try_wait()
+        {
+            if !status.success() {
+                error!(target: Target::file_mail(), channel = id; "Staging preview stops with: {status}");
+            }
+
+            // wait for the next clip
+            proc = None;
+        }
+
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
@@ -0,0 +1,180 @@
+/// Channel Statistics
+///
+/// Daily numbers of a channel in the database, for monthly reporting: the air time per
+/// category, the errors, the minutes of live ingest and of filler. Every aired clip adds its
+/// played time to its category, filler clips count as `filler`, live ingest as `ingest`.
+/// Clips without category count as `clip`. The date is the playlist date, like in the
+/// as-run log.
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, NaiveDate};
+use log::*;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+
+use crate::db::{handles, models::StatEntry};
+use crate::player::{
+    controller::ChannelManager,
+    utils::{Media, is_filler},
+};
+use crate::utils::{
+    config::PlayoutConfig, errors::ServiceError, logging::Target, time_machine::time_now,
+};
+
+pub const CLIP: &str = "clip";
+pub const FILLER: &str = "filler";
+pub const INGEST: &str = "ingest";
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StatsQuery {
+    /// First date (YYYY-MM-DD), default is the first day of the current month.
+    pub from: String,
+    /// Last date (YYYY-MM-DD), default is today.
+    pub to: String,
+}
+
+/// Numbers of a day, or of all days together. Times are in minutes.
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+pub struct Statistics {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    /// Air time per category, without filler and ingest.
+    pub airtime: BTreeMap<String, f64>,
+    pub errors: i64,
+    pub ingest: f64,
+    pub filler: f64,
+}
+
+impl Statistics {
+    fn add(&mut self, entry: &StatEntry) {
+        self.errors += entry.errors;
+
+        match entry.category.as_str() {
+            FILLER => self.filler += entry.seconds / 60.0,
+            INGEST => self.ingest += entry.seconds / 60.0,
+            category => {
+                *self.airtime.entry(category.to_string()).or_default() += entry.seconds / 60.0;
+            }
+        }
+    }
+
+    fn round(mut self) -> Self {
+        let round = |m: f64| (m * 100.0).round() / 100.0;
+
+        self.airtime.values_mut().for_each(|m| *m = round(*m));
+        self.ingest = round(self.ingest);
+        self.filler = round(self.filler);
+
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+pub struct Report {
+    pub from: String,
+    pub to: String,
+    pub days: Vec<Statistics>,
+    pub total: Statistics,
+}
+
+/// Category of an aired clip in the statistics.
+pub fn category(config: &PlayoutConfig, node: &Media) -> String {
+    if is_filler(config, node) {
+        FILLER.to_string()
+    } else if node.category.is_empty() {
+        CLIP.to_string()
+    } else {
+        node.category.clone()
+    }
+}
+
+/// Add played seconds and an error to the category of a day.
+pub async fn add(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    date: &str,
+    category: &str,
+    seconds: f64,
+    error: bool,
+) {
+    if let Err(e) = handles::update_statistics(
+        pool,
+        config.general.channel_id,
+        date,
+        category,
+        seconds,
+        error as i64,
+    )
+    .await
+    {
+        error!(target: Target::file_mail(), channel = config.general.channel_id; "Couldn't write statistics: {e}");
+    }
+}
+
+/// Add the time of a live ingest, outside of playlist mode the date is the current date.
+pub async fn ingest(manager: ChannelManager, seconds: f64) {
+    let config = manager.config.read().await.clone();
+    let mut date = manager.current_date.lock().await.clone();
+
+    if date.is_empty() {
+        date = time_now(&config.channel.timezone)
+            .format("%Y-%m-%d")
+            .to_string();
+    }
+
+    add(&manager.db_pool, &config, &date, INGEST, seconds, false).await;
+}
+
+/// Sum up the entries per day and over all days.
+pub fn summary(from: &str, to: &str, entries: &[StatEntry]) -> Report {
+    let mut days: Vec<Statistics> = vec![];
+    let mut total = Statistics::default();
+
+    for entry in entries {
+        if days.last().and_then(|d| d.date.as_ref()) != Some(&entry.date) {
+            days.push(Statistics {
+                date: Some(entry.date.clone()),
+                ..Statistics::default()
+            });
+        }
+
+        if let Some(day) = days.last_mut() {
+            day.add(entry);
+        }
+
+        total.add(entry);
+    }
+
+    Report {
+        from: from.to_string(),
+        to: to.to_string(),
+        days: days.into_iter().map(Statistics::round).collect(),
+        total: total.round(),
+    }
+}
+
+/// Statistics of the channel between two dates, the current month by default.
+pub async fn report(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    query: &StatsQuery,
+) -> Result<Report, ServiceError> {
+    let today = time_now(&config.channel.timezone).date_naive();
+    let from = match query.from.as_str() {
+        "" => today.with_day(1).unwrap_or(today),
+        date => NaiveDate::parse_from_str(date, "%Y-%m-%d")?,
+    }
+    .format("%Y-%m-%d")
+    .to_string();
+    let to = match query.to.as_str() {
+        "" => today,
+        date => NaiveDate::parse_from_str(date, "%Y-%m-%d")?,
+    }
+    .format("%Y-%m-%d")
+    .to_string();
+
+    let entries = handles::select_statistics(pool, config.general.channel_id, &from, &to).await?;
+
+    Ok(summary(&from, &to, &entries))
+}
@@ -0,0 +1,95 @@
+/// Pipeline Tracing
+///
+/// With `--otlp-endpoint` every part of the pipeline sends OpenTelemetry spans over
+/// OTLP/HTTP, to a collector, Jaeger or Grafana Tempo: loading of the next source and the
+/// playlist, the air time of every clip, the decoder and encoder processes and live ingest.
+/// The spans have the channel id and the clip, so a stutter can be matched with the system
+/// metrics of the same time. Without endpoint the spans are no-ops.
+use opentelemetry::{
+    Context, KeyValue, Value, global,
+    trace::{Span, Status, TraceContextExt, Tracer},
+};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+
+pub use opentelemetry::global::BoxedSpan;
+
+use crate::player::utils::Media;
+use crate::utils::errors::ProcessError;
+
+pub const SERVICE_NAME: &str = "ffplayout";
+
+/// Keeps the exporter alive, the last spans are sent, when it gets dropped.
+pub struct Telemetry(Option<SdkTracerProvider>);
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(provider) = self.0.take() {
+            let _ = provider.shutdown();
+        }
+    }
+}
+
+/// Export the spans to the OTLP/HTTP endpoint, like `http://127.0.0.1:4318/v1/traces`.
+pub fn init(endpoint: &Option<String>) -> Result<Telemetry, ProcessError> {
+    let Some(endpoint) = endpoint.as_ref().filter(|e| !e.is_empty()) else {
+        return Ok(Telemetry(None));
+    };
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| ProcessError::Custom(format!("OTLP exporter: {e}")))?;
+    let provider = SdkTracerProvider::builder()
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .with_batch_exporter(exporter)
+        .build();
+
+    global::set_tracer_provider(provider.clone());
+
+    Ok(Telemetry(Some(provider)))
+}
+
+/// Start a span of the channel, it ends when it gets dropped.
+pub fn span(name: &'static str, channel: i32) -> BoxedSpan {
+    let mut span = global::tracer(SERVICE_NAME).start(name);
+    span.set_attribute(KeyValue::new("channel.id", channel as i64));
+
+    span
+}
+
+/// Start a span inside of the `parent` span.
+pub fn child(name: &'static str, channel: i32, parent: &BoxedSpan) -> BoxedSpan {
+    let cx = Context::new().with_remote_span_context(parent.span_context().clone());
+    let mut span = global::tracer(SERVICE_NAME).start_with_context(name, &cx);
+    span.set_attribute(KeyValue::new("channel.id", channel as i64));
+
+    span
+}
+
+/// Attributes of the clip, which the span belongs to.
+pub fn media(span: &mut BoxedSpan, node: &Media) {
+    span.set_attributes([
+        KeyValue::new("media.source", node.source.clone()),
+        KeyValue::new("media.category", node.category.clone()),
+        KeyValue::new("media.index", node.index.unwrap_or_default() as i64),
+        KeyValue::new("media.in", node.seek),
+        KeyValue::new("media.out", node.out),
+        KeyValue::new("media.length", node.length()),
+    ]);
+}
+
+pub fn attribute(span: &mut BoxedSpan, key: &'static str, value: impl Into<Value>) {
+    span.set_attribute(KeyValue::new(key, value));
+}
+
+/// Event at the current time of the span, like the first data of a decoder.
+pub fn event(span: &mut BoxedSpan, name: &'static str) {
+    span.add_event(name, vec![]);
+}
+
+/// Mark the span as failed.
+pub fn error(span: &mut BoxedSpan, message: impl Into<String>) {
+    span.set_status(Status::error(message.into()));
+}
@@ -0,0 +1,210 @@
+/// News Ticker
+///
+/// A crawl at the bottom of the picture, with the items of an RSS, Atom or JSON feed
+/// (`text.ticker_url`). The feed gets fetched every `text.ticker_interval` seconds, the
+/// items are joined with `text.ticker_separator`. JSON can be a list of strings, a list of
+/// objects with `title`, `headline` or `text`, or a JSON Feed with such `items`.
+///
+/// The ticker is a drawtext in the encoder, which gets its text over zmq, so it runs
+/// not in HLS mode. Outside of `text.ticker_hours` the text is empty.
+use std::{path::Path, sync::atomic::Ordering, time::Duration};
+
+use log::*;
+use regex::Regex;
+use serde_json::Value;
+use tokio::time::{Instant, sleep, timeout};
+
+use crate::player::{
+    controller::ChannelManager,
+    filter::audio_chains::{DayPart, day_sec},
+    utils::time_in_seconds,
+};
+use crate::utils::{TextFilter, config::PlayoutConfig, control::zmq_send, logging::Target};
+
+const POLL: Duration = Duration::from_secs(5);
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Titles of the feed items.
+pub fn parse_feed(body: &str) -> Vec<String> {
+    let body = body.trim();
+
+    if body.starts_with('<') {
+        let item = Regex::new(r"(?s)<(item|entry)[\s>].*?<title[^>]*>(.*?)</title>").unwrap();
+
+        return item
+            .captures_iter(body)
+            .map(|c| unescape(&c[2]))
+            .filter(|t| !t.is_empty())
+            .collect();
+    }
+
+    let items = match serde_json::from_str::<Value>(body) {
+        Ok(Value::Array(items)) => items,
+        Ok(Value::Object(mut feed)) => match feed.remove("items") {
+            Some(Value::Array(items)) => items,
+            _ => vec![],
+        },
+        _ => vec![],
+    };
+
+    items
+        .iter()
+        .filter_map(|item| match item {
+            Value::String(text) => Some(text.as_str()),
+            Value::Object(o) => ["title", "headline", "text"]
+                .iter()
+                .find_map(|k| o.get(*k).and_then(Value::as_str)),
+            _ => None,
+        })
+        .map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+fn unescape(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|t| t.strip_suffix("]]>"))
+        .unwrap_or(text);
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Text of the crawl, the separator comes also after the last item, before the crawl
+/// starts again.
+pub fn ticker_text(items: &[String], separator: &str) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+
+    format!("{}{separator}", items.join(separator))
+}
+
+/// Ticker runs at `sec` (seconds of the day), day parts like `06:00-09:00; 17:00-23:00`.
+pub fn active(hours: &str, sec: f64) -> bool {
+    let parts = hours
+        .split([';', ','])
+        .filter(|p| !p.trim().is_empty())
+        .filter_map(|p| {
+            let (start, end) = p.split_once('-')?;
+
+            Some(DayPart {
+                start: day_sec(start)?,
+                end: day_sec(end)?,
+                chain: String::new(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    parts.is_empty() || parts.iter().any(|p| p.contains(sec))
+}
+
+/// drawtext of the ticker, the text comes over zmq.
+pub fn filter(config: &PlayoutConfig) -> Option<String> {
+    if config.text.ticker_url.trim().is_empty()
+        || config.output.zmq_socket.is_none()
+        || config.processing.copy_video
+    {
+        return None;
+    }
+
+    let font = if Path::new(&config.text.font_path).is_file() {
+        format!(":fontfile='{}'", config.text.font_path)
+    } else {
+        String::new()
+    };
+    let style = config.text.ticker_style.trim().trim_start_matches(':');
+    let style = if style.is_empty() {
+        String::new()
+    } else {
+        format!(":{style}")
+    };
+
+    Some(format!(
+        "drawtext@ticker=text='':x='w-mod(t*{},w+tw)'{style}{font}",
+        config.text.ticker_speed.max(1)
+    ))
+}
+
+async fn fetch(url: &str) -> Result<Vec<String>, String> {
+    let body = reqwest::Client::new()
+        .get(url)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| e.to_string())?
+        .text()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(parse_feed(&body))
+}
+
+/// Fetch the feed and keep the text of the ticker up to date.
+pub async fn run(manager: ChannelManager, config: PlayoutConfig) {
+    let id = config.general.channel_id;
+    let Some(socket) = config.output.zmq_socket.clone() else {
+        warn!(target: Target::file_mail(), channel = id; "News ticker needs an output with encoder, not HLS");
+        return;
+    };
+    let interval = Duration::from_secs(config.text.ticker_interval.max(10) as u64);
+    let mut items = vec![];
+    let mut fetched: Option<Instant> = None;
+    let mut on_air = None;
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        if fetched.is_none_or(|f| f.elapsed() >= interval) {
+            match fetch(&config.text.ticker_url).await {
+                Ok(new_items) => items = new_items,
+                Err(e) => {
+                    error!(target: Target::file(), channel = id; "News ticker <span class=\"log-addr\">{}</span>: {e}", config.text.ticker_url);
+                }
+            }
+
+            fetched = Some(Instant::now());
+            // send the text again, in case the encoder restarted in between
+            on_air = None;
+        }
+
+        let text = if active(
+            &config.text.ticker_hours,
+            time_in_seconds(&config.channel.timezone),
+        ) {
+            ticker_text(&items, &config.text.ticker_separator)
+        } else {
+            String::new()
+        };
+
+        if on_air.as_ref() != Some(&text) {
+            let filter = TextFilter {
+                text: Some(text.clone()),
+                ..Default::default()
+            };
+
+            match timeout(
+                POLL,
+                zmq_send(&format!("drawtext@ticker reinit {filter}"), &socket),
+            )
+            .await
+            {
+                Ok(Ok(_)) => on_air = Some(text),
+                Ok(Err(e)) => {
+                    debug!(target: Target::file(), channel = id; "News ticker: {e}");
+                }
+                Err(_) => debug!(target: Target::file(), channel = id; "News ticker: zmq timeout"),
+            }
+        }
+
+        sleep(POLL).await;
+    }
+}
@@ -0,0 +1,389 @@
+/// GPI and Serial Triggers
+///
+/// Contact closures from a GPI interface or commands on a serial line trigger playout actions.
+/// The mapping table (`task.trigger_map`) has one `input=action` pair per line (or separated by `;`):
+///
+/// ```text
+/// gpio17=break /tv-media/breaks/station_break.mp4
+/// gpio27=take_ingest
+/// NEXT=next
+/// ```
+///
+/// When `task.trigger_device` is a directory (like `/sys/class/gpio`), the inputs are GPIO pins
+/// and their `value` files get polled; a trigger is a change from 0 to 1, which is stable for the
+/// debounce time. Otherwise the device is a serial port and every received line is an input,
+/// the same line within the debounce time counts once. The baud rate can follow the device
+/// with `@`, for example `/dev/ttyUSB0@9600`.
+///
+/// With a `take_ingest` action in the table, a live ingest goes only on air after the take.
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use log::*;
+use tokio::{
+    fs,
+    io::{AsyncBufReadExt, BufReader},
+    process::Command,
+    time::sleep,
+};
+
+use crate::db::handles;
+use crate::player::controller::{ChannelManager, ProcessUnit::Ingest};
+use crate::utils::{
+    TextFilter,
+    config::PlayoutConfig,
+    control::{InsertParams, InsertPolicy, PlayerCtl, control_state, insert_next, send_message},
+    errors::ServiceError,
+    logging::Target,
+};
+
+const GPIO_POLL: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TriggerAction {
+    Next,
+    Back,
+    Reset,
+    /// Insert a clip after the current one.
+    Insert(String),
+    /// Insert a clip and play it now.
+    Break(String),
+    TakeIngest,
+    ReleaseIngest,
+    /// Show a text preset, by its name.
+    LowerThird(String),
+    ClearLowerThird,
+    Slate,
+    Resume,
+    /// Hold the schedule at the next clip boundary.
+    Hold,
+    Release,
+}
+
+impl FromStr for TriggerAction {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (action, arg) = input
+            .trim()
+            .split_once(char::is_whitespace)
+            .map_or((input.trim(), ""), |(a, p)| (a, p.trim()));
+
+        match (action.to_lowercase().as_str(), arg.is_empty()) {
+            ("next", true) => Ok(Self::Next),
+            ("back", true) => Ok(Self::Back),
+            ("reset", true) => Ok(Self::Reset),
+            ("insert", false) => Ok(Self::Insert(arg.to_string())),
+            ("break", false) => Ok(Self::Break(arg.to_string())),
+            ("take_ingest" | "take_live", true) => Ok(Self::TakeIngest),
+            ("release_ingest" | "release_live", true) => Ok(Self::ReleaseIngest),
+            ("lower_third", false) => Ok(Self::LowerThird(arg.to_string())),
+            ("clear_lower_third", true) => Ok(Self::ClearLowerThird),
+            ("slate", true) => Ok(Self::Slate),
+            ("resume", true) => Ok(Self::Resume),
+            ("hold", true) => Ok(Self::Hold),
+            ("release", true) => Ok(Self::Release),
+            _ => Err(format!("Trigger action '{input}' not valid!")),
+        }
+    }
+}
+
+impl fmt::Display for TriggerAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Next => write!(f, "next"),
+            Self::Back => write!(f, "back"),
+            Self::Reset => write!(f, "reset"),
+            Self::Insert(source) => write!(f, "insert {source}"),
+            Self::Break(source) => write!(f, "break {source}"),
+            Self::TakeIngest => write!(f, "take_ingest"),
+            Self::ReleaseIngest => write!(f, "release_ingest"),
+            Self::LowerThird(name) => write!(f, "lower_third {name}"),
+            Self::ClearLowerThird => write!(f, "clear_lower_third"),
+            Self::Slate => write!(f, "slate"),
+            Self::Resume => write!(f, "resume"),
+            Self::Hold => write!(f, "hold"),
+            Self::Release => write!(f, "release"),
+        }
+    }
+}
+
+/// Parse the mapping table, invalid lines are returned as errors.
+pub fn parse_mapping(table: &str) -> (HashMap<String, TriggerAction>, Vec<String>) {
+    let mut mapping = HashMap::new();
+    let mut errors = vec![];
+
+    for line in table.split(['\n', ';']).map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line.split_once('=') {
+            Some((input, action)) if !input.trim().is_empty() => match action.parse() {
+                Ok(action) => {
+                    mapping.insert(input.trim().to_string(), action);
+                }
+                Err(e) => errors.push(e),
+            },
+            _ => errors.push(format!("Trigger line '{line}' is not input=action!")),
+        }
+    }
+
+    (mapping, errors)
+}
+
+/// Serial port and optional baud rate from the device setting.
+pub fn serial_device(device: &str) -> (PathBuf, Option<u32>) {
+    match device.rsplit_once('@') {
+        Some((path, baud)) if baud.parse::<u32>().is_ok() => {
+            (PathBuf::from(path), baud.parse().ok())
+        }
+        _ => (PathBuf::from(device), None),
+    }
+}
+
+/// Debounce for inputs, with the time of the event.
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    debounce: Duration,
+    /// Stable level of GPIO pins and the pending change.
+    levels: HashMap<String, (bool, Option<(bool, Instant)>)>,
+    /// Last time of serial inputs.
+    last_seen: HashMap<String, Instant>,
+}
+
+impl Debouncer {
+    pub fn new(debounce: Duration) -> Self {
+        Self {
+            debounce,
+            ..Default::default()
+        }
+    }
+
+    /// Level of a pin, returns true on a stable rising edge.
+    pub fn level(&mut self, key: &str, high: bool, now: Instant) -> bool {
+        let (stable, pending) = self.levels.entry(key.to_string()).or_insert((high, None));
+
+        if high == *stable {
+            *pending = None;
+            return false;
+        }
+
+        match *pending {
+            Some((level, since)) if level == high => {
+                if now.duration_since(since) >= self.debounce {
+                    *stable = high;
+                    *pending = None;
+
+                    return high;
+                }
+            }
+            _ => *pending = Some((high, now)),
+        }
+
+        false
+    }
+
+    /// Serial input, returns false for a repeat within the debounce time.
+    pub fn input(&mut self, key: &str, now: Instant) -> bool {
+        let repeat = self
+            .last_seen
+            .get(key)
+            .is_some_and(|t| now.duration_since(*t) < self.debounce);
+
+        self.last_seen.insert(key.to_string(), now);
+
+        !repeat
+    }
+}
+
+pub async fn run_action(
+    manager: &ChannelManager,
+    action: &TriggerAction,
+) -> Result<(), ServiceError> {
+    let insert = |source: &str| InsertParams {
+        source: source.to_string(),
+        title: None,
+        seek: 0.0,
+        out: None,
+        category: String::new(),
+        policy: InsertPolicy::Shift,
+    };
+
+    match action {
+        TriggerAction::Next => {
+            control_state(&manager.db_pool, manager, &PlayerCtl::Next).await?;
+        }
+        TriggerAction::Back => {
+            control_state(&manager.db_pool, manager, &PlayerCtl::Back).await?;
+        }
+        TriggerAction::Reset => {
+            control_state(&manager.db_pool, manager, &PlayerCtl::Reset).await?;
+        }
+        TriggerAction::Slate => {
+            control_state(&manager.db_pool, manager, &PlayerCtl::Slate).await?;
+        }
+        TriggerAction::Resume => {
+            control_state(&manager.db_pool, manager, &PlayerCtl::Resume).await?;
+        }
+        TriggerAction::Hold => {
+            control_state(&manager.db_pool, manager, &PlayerCtl::Hold).await?;
+        }
+        TriggerAction::Release => {
+            control_state(&manager.db_pool, manager, &PlayerCtl::Release).await?;
+        }
+        TriggerAction::Insert(source) => {
+            insert_next(manager, insert(source)).await?;
+        }
+        TriggerAction::Break(source) => {
+            insert_next(manager, insert(source)).await?;
+            control_state(&manager.db_pool, manager, &PlayerCtl::Next).await?;
+        }
+        TriggerAction::TakeIngest => {
+            manager.ingest_hold.store(false, Ordering::SeqCst);
+        }
+        TriggerAction::ReleaseIngest => {
+            manager.ingest_hold.store(true, Ordering::SeqCst);
+
+            if manager.ingest_is_alive.load(Ordering::SeqCst) {
+                manager.stop(Ingest).await;
+            }
+        }
+        TriggerAction::LowerThird(name) => {
+            let id = manager.config.read().await.general.channel_id;
+            let preset = handles::select_presets(&manager.db_pool, id)
+                .await?
+                .into_iter()
+                .find(|p| p.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| ServiceError::NotFound(format!("Text preset '{name}' not found")))?;
+
+            send_message(manager.clone(), TextFilter::from(preset)).await?;
+        }
+        TriggerAction::ClearLowerThird => {
+            let clear = TextFilter {
+                text: Some(String::new()),
+                ..Default::default()
+            };
+
+            send_message(manager.clone(), clear).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn trigger(manager: &ChannelManager, id: i32, input: &str, action: &TriggerAction) {
+    info!(target: Target::file_mail(), channel = id;
+        "Trigger <span class=\"log-addr\">{input}</span>: {action}"
+    );
+
+    if let Err(e) = run_action(manager, action).await {
+        error!(target: Target::file_mail(), channel = id; "Trigger {action} failed: {e}");
+    }
+}
+
+async fn watch_gpio(
+    manager: &ChannelManager,
+    id: i32,
+    folder: &Path,
+    mapping: &HashMap<String, TriggerAction>,
+    mut debouncer: Debouncer,
+) {
+    while manager.is_alive.load(Ordering::SeqCst) {
+        let now = Instant::now();
+
+        for (pin, action) in mapping {
+            let Ok(value) = fs::read_to_string(folder.join(pin).join("value")).await else {
+                continue;
+            };
+
+            if debouncer.level(pin, value.trim() == "1", now) {
+                trigger(manager, id, pin, action).await;
+            }
+        }
+
+        sleep(GPIO_POLL).await;
+    }
+}
+
+async fn watch_serial(
+    manager: &ChannelManager,
+    id: i32,
+    device: &str,
+    mapping: &HashMap<String, TriggerAction>,
+    mut debouncer: Debouncer,
+) -> Result<(), ServiceError> {
+    let (path, baud) = serial_device(device);
+    let mut stty = vec!["-F".to_string(), path.to_string_lossy().to_string()];
+
+    // raw mode, a read returns after one second without data
+    stty.extend(["raw", "-echo", "min", "0", "time", "10"].map(str::to_string));
+    stty.extend(baud.map(|b| b.to_string()));
+
+    if let Err(e) = Command::new("stty").args(&stty).status().await {
+        warn!(target: Target::file_mail(), channel = id; "Couldn't set up serial port: {e}");
+    }
+
+    let file = fs::File::open(&path).await?;
+    let mut lines = BufReader::new(file).lines();
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        let Some(line) = lines.next_line().await? else {
+            sleep(Duration::from_millis(100)).await;
+            continue;
+        };
+        let input = line.trim();
+
+        if input.is_empty() || !debouncer.input(input, Instant::now()) {
+            continue;
+        }
+
+        match mapping.get(input) {
+            Some(action) => trigger(manager, id, input, action).await,
+            None => debug!(target: Target::file_mail(), channel = id;
+                "Trigger input <span class=\"log-addr\">{input}</span> is not mapped"
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Watch the trigger device until the channel stops.
+pub async fn watch(manager: ChannelManager, config: PlayoutConfig) {
+    let id = config.general.channel_id;
+    let device = config.task.trigger_device.trim();
+    let (mapping, errors) = parse_mapping(&config.task.trigger_map);
+    let debouncer = Debouncer::new(Duration::from_millis(config.task.trigger_debounce as u64));
+
+    for e in errors {
+        error!(target: Target::file_mail(), channel = id; "{e}");
+    }
+
+    if mapping.is_empty() {
+        warn!(target: Target::file_mail(), channel = id; "Trigger device without mapping, skip it");
+        return;
+    }
+
+    if mapping.values().any(|a| *a == TriggerAction::TakeIngest) {
+        manager.ingest_hold.store(true, Ordering::SeqCst);
+    }
+
+    info!(target: Target::file_mail(), channel = id;
+        "Watch triggers on <span class=\"log-addr\">{device}</span>"
+    );
+
+    if Path::new(device).is_dir() {
+        watch_gpio(&manager, id, Path::new(device), &mapping, debouncer).await;
+    } else if let Err(e) = watch_serial(&manager, id, device, &mapping, debouncer).await {
+        error!(target: Target::file_mail(), channel = id;
+            "Trigger device <span class=\"log-addr\">{device}</span>: {e}"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+/// Version Check
+///
+/// `--check-update` compares the running version with the latest release on GitHub and
+/// exits. With `--update-interval` the server checks in the background and writes a warning
+/// to every channel, which also goes to the mail, once per new release. Nothing gets
+/// installed, only when `--update-command` is set, it runs with the new version, for example
+/// a script which pulls the package and restarts the service.
+use std::{collections::BTreeMap, process::Stdio, sync::Arc, time::Duration};
+
+use log::*;
+use serde::Deserialize;
+use tokio::{
+    process::Command,
+    sync::RwLock,
+    time::{interval, timeout},
+};
+
+use crate::player::controller::ChannelController;
+use crate::utils::{errors::ProcessError, hooks::hook_args, logging::Target};
+
+pub const CURRENT: &str = env!("CARGO_PKG_VERSION");
+
+const REPOSITORY: &str = env!("CARGO_PKG_REPOSITORY");
+const TIMEOUT: Duration = Duration::from_secs(10);
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub html_url: String,
+}
+
+impl Release {
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+}
+
+/// API address of the latest release, from the repository of the package.
+pub fn api_url() -> String {
+    let repo = REPOSITORY
+        .trim_end_matches('/')
+        .trim_start_matches("https://github.com/");
+
+    format!("https://api.github.com/repos/{repo}/releases/latest")
+}
+
+/// Major, minor and patch of a version like `v0.25.7` or `0.26.0-beta1`.
+pub fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim().trim_start_matches('v');
+    let version = version.split(['-', '+']).next()?;
+    let mut parts = version.split('.').map(|p| p.parse::<u64>().ok());
+
+    Some((
+        parts.next()??,
+        parts.next()??,
+        parts.next().unwrap_or(Some(0))?,
+    ))
+}
+
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+pub async fn latest_release() -> Result<Release, ProcessError> {
+    reqwest::Client::new()
+        .get(api_url())
+        .header("User-Agent", format!("ffplayout/{CURRENT}"))
+        .header("Accept", "application/vnd.github+json")
+        .timeout(TIMEOUT)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| ProcessError::Custom(format!("Version check failed: {e}")))?
+        .json::<Release>()
+        .await
+        .map_err(|e| ProcessError::Custom(format!("Version check failed: {e}")))
+}
+
+/// Print the result of the check, for `--check-update`.
+pub async fn check_cli() -> Result<(), ProcessError> {
+    let release = latest_release().await?;
+
+    if is_newer(release.version(), CURRENT) {
+        println!(
+            "New version {} is available (running {CURRENT}): {}",
+            release.version(),
+            release.html_url
+        );
+    } else {
+        println!("ffplayout {CURRENT} is up to date");
+    }
+
+    Ok(())
+}
+
+/// Check every `hours` for a new release, each release is reported only once.
+pub async fn run(controllers: Arc<RwLock<ChannelController>>, hours: u64, command: Option<String>) {
+    let mut ticker = interval(Duration::from_secs(hours * 3600));
+    let mut reported = String::new();
+
+    loop {
+        ticker.tick().await;
+
+        let release = match latest_release().await {
+            Ok(r) => r,
+            Err(e) => {
+                debug!("{e}");
+                continue;
+            }
+        };
+        let version = release.version().to_string();
+
+        if !is_newer(&version, CURRENT) || version == reported {
+            continue;
+        }
+
+        reported.clone_from(&version);
+
+        let managers = controllers.read().await.managers.clone();
+
+        if managers.is_empty() {
+            warn!(
+                "New ffplayout version <span class=\"log-number\">{version}</span> is available (running {CURRENT}): {}",
+                release.html_url
+            );
+        }
+
+        for manager in &managers {
+            warn!(target: Target::file_mail(), channel = manager.id; "New ffplayout version <span class=\"log-number\">{version}</span> is available (running {CURRENT}): {}", release.html_url);
+        }
+
+        if let Some(command) = command.as_ref().filter(|c| !c.is_empty()) {
+            update(command, &version).await;
+        }
+    }
+}
+
+/// Run the update command, the version is also in `FFPLAYOUT_VERSION`.
+async fn update(command: &str, version: &str) {
+    let vars = BTreeMap::from([
+        ("version".to_string(), version.to_string()),
+        ("current".to_string(), CURRENT.to_string()),
+    ]);
+
+    let Some(args) = hook_args(command, &vars) else {
+        error!("Update command is not valid: <span class=\"log-cmd\">{command}</span>");
+        return;
+    };
+
+    let mut cmd = Command::new(&args[0]);
+
+    cmd.args(&args[1..])
+        .envs(
+            vars.iter()
+                .map(|(k, v)| (format!("FFPLAYOUT_{}", k.to_uppercase()), v)),
+        )
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .kill_on_drop(true);
+
+    info!("Run update command for version <span class=\"log-number\">{version}</span>: {args:?}");
+
+    let mut child = match cmd.spawn() {
+        Ok(c) => c,
+        Err(e) => {
+            error!(
+                "Couldn't run update command <span class=\"log-cmd\">{}</span>: {e}",
+                args[0]
+            );
+            return;
+        }
+    };
+
+    match timeout(COMMAND_TIMEOUT, child.wait()).await {
+        Ok(Ok(status)) if !status.success() => {
+            error!("Update command stops with: {status}");
+        }
+        Ok(Err(e)) => error!("Update command: {e}"),
+        Err(_) => {
+            let _ = child.kill().await;
+
+            error!(
+                "Update command killed after <span class=\"log-number\">{}s</span>",
+                COMMAND_TIMEOUT.as_secs()
+            );
+        }
+        _ => (),
+    }
+}
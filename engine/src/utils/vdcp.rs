@@ -0,0 +1,355 @@
+/// VDCP Adapter
+///
+/// A subset of the Video Disk Control Protocol over TCP (`task.vdcp_address`), so automation
+/// and master control systems can cue and roll clips like on a video server. Serial ports
+/// can be connected with a serial to TCP bridge like `ser2net`.
+///
+/// A frame is `STX (0x02)`, byte count, `CMD-1`, `CMD-2`, data and checksum. The byte count
+/// covers the command bytes and the data, the checksum is the two's complement of their sum.
+/// Commands get `ACK (0x04)` or `NAK (0x05)` with an error byte, requests get a frame,
+/// with `0x80` added to `CMD-1` and the data length in its lower bits.
+///
+/// IDs are the file names without extension, 8 characters padded with spaces.
+/// They are looked up in the [media library](crate::utils::library).
+use std::{sync::atomic::Ordering, time::Duration};
+
+use log::*;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+
+use crate::db::handles;
+use crate::player::controller::ChannelManager;
+use crate::utils::{
+    config::PlayoutConfig,
+    control::{InsertParams, InsertPolicy, PlayerCtl, control_state, insert_next},
+    library::LibraryQuery,
+    logging::Target,
+};
+
+pub const STX: u8 = 0x02;
+pub const ACK: u8 = 0x04;
+pub const NAK: u8 = 0x05;
+/// NAK error: undefined command.
+pub const NAK_UNDEFINED: u8 = 0x01;
+/// NAK error: checksum error.
+pub const NAK_CHECKSUM: u8 = 0x04;
+/// NAK error: ID not found, or the command failed.
+pub const NAK_FAILED: u8 = 0x80;
+pub const ID_LENGTH: usize = 8;
+
+const POLL: Duration = Duration::from_secs(1);
+
+/// Port status bits.
+const STATUS_PLAYING: u8 = 0x02;
+const STATUS_CUED: u8 = 0x04;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub cmd1: u8,
+    pub cmd2: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Stop,
+    Play,
+    PlayCue(String),
+    PortStatus,
+    ActiveId,
+    ListFirstId,
+    ListNextId,
+}
+
+impl Command {
+    /// Command of a frame, the upper bits of CMD-1 are the type.
+    pub fn from_frame(frame: &Frame) -> Option<Self> {
+        match (frame.cmd1 & 0xF0, frame.cmd2) {
+            (0x10, 0x00) => Some(Self::Stop),
+            (0x10, 0x01) => Some(Self::Play),
+            (0x20, 0x24) => Some(Self::PlayCue(id_string(&frame.data))),
+            (0x30, 0x05) => Some(Self::PortStatus),
+            (0x30, 0x07) => Some(Self::ActiveId),
+            (0x30, 0x14) => Some(Self::ListFirstId),
+            (0x30, 0x15) => Some(Self::ListNextId),
+            _ => None,
+        }
+    }
+}
+
+pub fn checksum(bytes: &[u8]) -> u8 {
+    bytes
+        .iter()
+        .fold(0u8, |sum, b| sum.wrapping_add(*b))
+        .wrapping_neg()
+}
+
+pub fn encode_frame(cmd1: u8, cmd2: u8, data: &[u8]) -> Vec<u8> {
+    let mut body = vec![cmd1, cmd2];
+    body.extend_from_slice(data);
+
+    let mut frame = vec![STX, body.len() as u8];
+    frame.extend_from_slice(&body);
+    frame.push(checksum(&body));
+
+    frame
+}
+
+/// Answer to a request, the lower bits of CMD-1 are the data length.
+fn reply(cmd2: u8, data: &[u8]) -> Vec<u8> {
+    encode_frame(0xB0 | (data.len().min(0x0F) as u8), cmd2, data)
+}
+
+/// Parse the first frame from the buffer.
+///
+/// Returns the frame (an error on a wrong checksum) and the used bytes,
+/// `None` when the frame is not complete. Bytes before `STX` are skipped.
+pub fn parse_frame(buffer: &[u8]) -> Option<(Result<Frame, u8>, usize)> {
+    let start = buffer.iter().position(|b| *b == STX)?;
+    let count = *buffer.get(start + 1)? as usize;
+    let end = start + 2 + count;
+    let sum = *buffer.get(end)?;
+    let body = &buffer[start + 2..end];
+
+    if count < 2 || checksum(body) != sum {
+        return Some((Err(NAK_CHECKSUM), end + 1));
+    }
+
+    let frame = Frame {
+        cmd1: body[0],
+        cmd2: body[1],
+        data: body[2..].to_vec(),
+    };
+
+    Some((Ok(frame), end + 1))
+}
+
+/// ID from the data bytes, without padding.
+pub fn id_string(data: &[u8]) -> String {
+    String::from_utf8_lossy(&data[..data.len().min(ID_LENGTH)])
+        .trim_matches(|c: char| c == ' ' || c == '\0')
+        .to_string()
+}
+
+/// ID of a file, the name without extension, padded to 8 bytes.
+pub fn file_id(path: &str) -> [u8; ID_LENGTH] {
+    let stem = std::path::Path::new(path)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let mut id = [b' '; ID_LENGTH];
+
+    for (i, b) in stem.bytes().take(ID_LENGTH).enumerate() {
+        id[i] = b;
+    }
+
+    id
+}
+
+/// Connection state.
+#[derive(Debug, Default)]
+struct Session {
+    cued: Option<String>,
+    list_position: usize,
+}
+
+/// Library paths with their IDs.
+async fn library_ids(manager: &ChannelManager, id: i32) -> Vec<(String, [u8; ID_LENGTH])> {
+    handles::select_library(&manager.db_pool, id, &LibraryQuery::default())
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|e| e.modified > 0)
+        .map(|e| {
+            let clip_id = file_id(&e.path);
+            (e.path, clip_id)
+        })
+        .collect()
+}
+
+async fn handle(manager: &ChannelManager, session: &mut Session, command: Command) -> Vec<u8> {
+    let config = manager.config.read().await.clone();
+    let id = config.general.channel_id;
+
+    match command {
+        // the playout goes on with the schedule
+        Command::Stop => vec![ACK],
+        Command::Play => {
+            let Some(cued) = session.cued.take() else {
+                return vec![ACK];
+            };
+            let index = manager.current_index.load(Ordering::SeqCst);
+            let waiting = manager
+                .current_list
+                .lock()
+                .await
+                .get(index)
+                .is_some_and(|m| m.source == cued);
+
+            // when the cued clip is already playing, there is nothing to roll
+            if waiting
+                && let Err(e) = control_state(&manager.db_pool, manager, &PlayerCtl::Next).await
+            {
+                error!(target: Target::file_mail(), channel = id; "VDCP play: {e}");
+                return vec![NAK, NAK_FAILED];
+            }
+
+            vec![ACK]
+        }
+        Command::PlayCue(clip_id) => {
+            let Some((path, _)) = library_ids(manager, id)
+                .await
+                .into_iter()
+                .find(|(_, i)| id_string(i).eq_ignore_ascii_case(&clip_id))
+            else {
+                warn!(target: Target::file_mail(), channel = id; "VDCP ID <span class=\"log-addr\">{clip_id}</span> not found");
+                return vec![NAK, NAK_FAILED];
+            };
+            let params = InsertParams {
+                source: path,
+                title: None,
+                seek: 0.0,
+                out: None,
+                category: String::new(),
+                policy: InsertPolicy::Shift,
+            };
+
+            match insert_next(manager, params).await {
+                Ok(map) => {
+                    session.cued = map
+                        .get("media")
+                        .and_then(|m| m.get("source"))
+                        .and_then(|s| s.as_str())
+                        .map(str::to_string);
+
+                    vec![ACK]
+                }
+                Err(e) => {
+                    error!(target: Target::file_mail(), channel = id; "VDCP cue: {e}");
+                    vec![NAK, NAK_FAILED]
+                }
+            }
+        }
+        Command::PortStatus => {
+            let mut status = 0;
+
+            if manager.current_media.lock().await.is_some() {
+                status |= STATUS_PLAYING;
+            }
+
+            if session.cued.is_some() {
+                status |= STATUS_CUED;
+            }
+
+            reply(0x05, &[status])
+        }
+        Command::ActiveId => {
+            let clip_id = manager
+                .current_media
+                .lock()
+                .await
+                .as_ref()
+                .map_or([b' '; ID_LENGTH], |m| file_id(&m.source));
+
+            reply(0x07, &clip_id)
+        }
+        Command::ListFirstId | Command::ListNextId => {
+            if matches!(command, Command::ListFirstId) {
+                session.list_position = 0;
+            }
+
+            let ids = library_ids(manager, id).await;
+            let cmd2 = if matches!(command, Command::ListFirstId) {
+                0x14
+            } else {
+                0x15
+            };
+
+            match ids.get(session.list_position) {
+                Some((_, clip_id)) => {
+                    session.list_position += 1;
+                    reply(cmd2, clip_id)
+                }
+                None => reply(cmd2, &[]),
+            }
+        }
+    }
+}
+
+async fn connection(manager: &ChannelManager, id: i32, mut stream: TcpStream) {
+    let mut session = Session::default();
+    let mut buffer = vec![];
+    let mut chunk = [0u8; 256];
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        let num = match timeout(POLL, stream.read(&mut chunk)).await {
+            Ok(Ok(0)) | Ok(Err(_)) => break,
+            Ok(Ok(n)) => n,
+            Err(_) => continue,
+        };
+
+        buffer.extend_from_slice(&chunk[..num]);
+
+        while let Some((frame, used)) = parse_frame(&buffer) {
+            buffer.drain(..used);
+
+            let reply = match frame {
+                Ok(frame) => match Command::from_frame(&frame) {
+                    Some(command) => {
+                        debug!(target: Target::file_mail(), channel = id; "VDCP command: {command:?}");
+                        handle(manager, &mut session, command).await
+                    }
+                    None => vec![NAK, NAK_UNDEFINED],
+                },
+                Err(e) => vec![NAK, e],
+            };
+
+            if stream.write_all(&reply).await.is_err() {
+                return;
+            }
+        }
+
+        // drop garbage, which has no frame start
+        if !buffer.contains(&STX) {
+            buffer.clear();
+        }
+    }
+}
+
+/// Listen for VDCP connections until the channel stops.
+pub async fn listen(manager: ChannelManager, config: PlayoutConfig) {
+    let id = config.general.channel_id;
+    let address = config.task.vdcp_address.trim().to_string();
+
+    let listener = match TcpListener::bind(&address).await {
+        Ok(l) => l,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "VDCP address <span class=\"log-addr\">{address}</span>: {e}");
+            return;
+        }
+    };
+
+    info!(target: Target::file_mail(), channel = id; "VDCP listening on: <span class=\"log-addr\">{address}</span>");
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        let Ok(accepted) = timeout(POLL, listener.accept()).await else {
+            continue;
+        };
+
+        match accepted {
+            Ok((stream, peer)) => {
+                info!(target: Target::file_mail(), channel = id; "VDCP client: <span class=\"log-addr\">{peer}</span>");
+
+                let manager = manager.clone();
+                tokio::spawn(async move { connection(&manager, id, stream).await });
+            }
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = id; "VDCP: {e}");
+            }
+        }
+    }
+}
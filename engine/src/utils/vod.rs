@@ -0,0 +1,194 @@
+/// VOD Export
+///
+/// Playlist items with `"record": true` are rendered again after they are aired,
+/// with the same seek, length and filters (scale, logo, fades, etc.), into the VOD folder.
+///
+/// The file name comes from the template in the storage config, with the placeholders:
+/// `{date}`, `{time}`, `{title}` and `{channel}`. When a hook is set, it gets called
+/// with the path of the new file, for example to upload it.
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use chrono::DateTime;
+use chrono_tz::Tz;
+use log::*;
+use sanitize_filename::sanitize;
+use tokio::{fs, process::Command};
+
+use crate::file::norm_abs_path;
+use crate::player::process::ResourceLimits;
+use crate::player::utils::{Media, add_thread_limit, ffmpeg_bin};
+use crate::utils::{
+    config::{OutputMode, PlayoutConfig, channel_path},
+    logging::{Target, fmt_cmd},
+};
+use crate::vec_strings;
+
+pub const VOD_EXTENSION: &str = "mp4";
+
+/// Folder for VOD files, relative paths are inside the storage.
+pub fn vod_folder(config: &PlayoutConfig) -> PathBuf {
+    norm_abs_path(
+        &config.channel.storage,
+        &channel_path(config, &config.storage.vod_path),
+    )
+    .map(|(path, _, _)| path)
+    .unwrap_or_else(|_| config.channel.storage.join("vod"))
+}
+
+/// File name from template, the title falls back to the file stem of the source.
+pub fn vod_name(template: &str, channel: &str, media: &Media, aired: &DateTime<Tz>) -> String {
+    let template = if template.trim().is_empty() {
+        "{date}_{time}_{title}"
+    } else {
+        template
+    };
+    let title = media.title.clone().unwrap_or_else(|| {
+        Path::new(&media.source)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+
+    let name = template
+        .replace("{date}", &aired.format("%Y-%m-%d").to_string())
+        .replace("{time}", &aired.format("%H-%M-%S").to_string())
+        .replace("{title}", &title)
+        .replace("{channel}", channel);
+
+    format!("{}.{VOD_EXTENSION}", sanitize(name))
+}
+
+/// Render command for the aired item, the input part is the same as for the decoder.
+pub fn vod_cmd(config: &PlayoutConfig, media: &Media, target: &Path) -> Vec<String> {
+    let mut media = media.clone();
+    let mut cmd = vec_strings!["-hide_banner", "-nostats", "-v", "level+error"];
+
+    cmd.append(&mut media.cmd.clone().unwrap_or_default());
+
+    if let Some(filter) = media.filter.as_mut() {
+        cmd.append(&mut filter.cmd());
+        cmd.append(&mut filter.map());
+    }
+
+    cmd.append(&mut vec_strings![
+        "-c:v",
+        "libx264",
+        "-crf",
+        "20",
+        "-preset",
+        "medium",
+        "-pix_fmt",
+        "yuv420p",
+        "-c:a",
+        "aac",
+        "-b:a",
+        "192k",
+        "-movflags",
+        "+faststart",
+        "-y",
+        target.to_string_lossy()
+    ]);
+
+    add_thread_limit(config, &mut cmd);
+
+    cmd
+}
+
+/// Config for rendering: no zmq sockets, no audio meter, no timecode and a single output.
+fn vod_config(config: &PlayoutConfig) -> PlayoutConfig {
+    let mut vod_config = config.clone();
+
+    vod_config.text.zmq_stream_socket = None;
+    vod_config.text.zmq_server_socket = None;
+    vod_config.output.mode = OutputMode::Null;
+    vod_config.output.output_count = 1;
+    vod_config.output.output_filter = None;
+    vod_config.processing.audio_meter = false;
+    vod_config.processing.timecode_burn_in = false;
+    vod_config.processing.timecode_embed = false;
+    vod_config.processing.ltc_channel = 0;
+    vod_config.processing.live_control = false;
+
+    vod_config
+}
+
+/// Export an aired item to the VOD folder and run the hook afterwards.
+pub async fn export(config: PlayoutConfig, channel: String, mut media: Media, aired: DateTime<Tz>) {
+    let id = config.general.channel_id;
+    let folder = vod_folder(&config);
+    let target = folder.join(vod_name(
+        &config.storage.vod_template,
+        &channel,
+        &media,
+        &aired,
+    ));
+    let vod_config = vod_config(&config);
+
+    if let Err(e) = fs::create_dir_all(&folder).await {
+        error!(target: Target::file_mail(), channel = id; "VOD folder: {e}");
+        return;
+    }
+
+    media.add_filter(&vod_config, &None).await;
+
+    let cmd = vod_cmd(&vod_config, &media, &target);
+
+    debug!(target: Target::file_mail(), channel = id;
+        "VOD CMD: <span class=\"log-cmd\">ffmpeg {}</span>",
+        fmt_cmd(&cmd)
+    );
+
+    let proc = ResourceLimits::new(&config).spawn(
+        Command::new(ffmpeg_bin())
+            .args(cmd)
+            .kill_on_drop(true)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped()),
+    );
+
+    let output = match proc {
+        Ok(proc) => proc.wait_with_output().await,
+        Err(e) => Err(e),
+    };
+
+    match output {
+        Ok(out) if out.status.success() => {
+            info!(target: Target::file_mail(), channel = id;
+                "VOD export done: <span class=\"log-addr\">{}</span>",
+                target.display()
+            );
+        }
+        Ok(out) => {
+            error!(target: Target::file_mail(), channel = id;
+                "VOD export <span class=\"log-addr\">{}</span> failed: {}",
+                target.display(),
+                String::from_utf8_lossy(&out.stderr).trim()
+            );
+            return;
+        }
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "VOD export failed: {e}");
+            return;
+        }
+    }
+
+    if !config.storage.vod_hook.trim().is_empty() {
+        match Command::new(&config.storage.vod_hook)
+            .arg(&target)
+            .kill_on_drop(true)
+            .status()
+            .await
+        {
+            Ok(status) if !status.success() => {
+                error!(target: Target::file_mail(), channel = id; "VOD hook stops with: {status}");
+            }
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = id; "Couldn't run VOD hook: {e}");
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+/// Data Widgets
+///
+/// Graphics templates with a `url` or with the clock in the text, like the weather or
+/// the time. The JSON from the url gets fetched every `interval` seconds of the template,
+/// its values fill the placeholders of the text, the clock gets updated every minute.
+/// See [graphics](crate::player::filter::graphics) for the templates.
+use std::{collections::HashMap, sync::atomic::Ordering, time::Duration};
+
+use log::*;
+use regex::Regex;
+use serde_json::Value;
+use tokio::time::{Instant, sleep, timeout};
+
+use crate::player::{
+    controller::ChannelManager,
+    filter::graphics::{self, Graphic},
+};
+use crate::utils::{
+    TextFilter, config::PlayoutConfig, control::zmq_send, logging::Target, time_machine::time_now,
+};
+
+const POLL: Duration = Duration::from_secs(1);
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Values of the JSON as fields, nested keys and list indexes joined with dots.
+pub fn flatten(value: &Value) -> HashMap<String, String> {
+    fn walk(prefix: &str, value: &Value, fields: &mut HashMap<String, String>) {
+        let key = |k: &str| {
+            if prefix.is_empty() {
+                k.to_string()
+            } else {
+                format!("{prefix}.{k}")
+            }
+        };
+
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map {
+                    walk(&key(k), v, fields);
+                }
+            }
+            Value::Array(list) => {
+                for (i, v) in list.iter().enumerate() {
+                    walk(&key(&i.to_string()), v, fields);
+                }
+            }
+            Value::String(s) => {
+                fields.insert(prefix.to_string(), s.clone());
+            }
+            Value::Null => {
+                fields.insert(prefix.to_string(), String::new());
+            }
+            v => {
+                fields.insert(prefix.to_string(), v.to_string());
+            }
+        }
+    }
+
+    let mut fields = HashMap::new();
+    walk("", value, &mut fields);
+
+    fields
+}
+
+/// `time` and `date` of the channel.
+pub fn clock_fields(config: &PlayoutConfig) -> HashMap<String, String> {
+    let now = time_now(&config.channel.timezone);
+
+    HashMap::from([
+        ("time".to_string(), now.format("%H:%M").to_string()),
+        ("date".to_string(), now.format("%Y-%m-%d").to_string()),
+    ])
+}
+
+async fn fetch(url: &str) -> Result<HashMap<String, String>, String> {
+    let value = reqwest::Client::new()
+        .get(url)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| e.to_string())?
+        .json::<Value>()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(flatten(&value))
+}
+
+struct Widget<'a> {
+    index: usize,
+    graphic: &'a Graphic,
+    text: &'a str,
+    fields: HashMap<String, String>,
+    fetched: Option<Instant>,
+    on_air: Option<String>,
+}
+
+/// Keep the text of the widgets up to date.
+pub async fn run(manager: ChannelManager, config: PlayoutConfig) {
+    let id = config.general.channel_id;
+    let Some(socket) = config.output.zmq_socket.clone() else {
+        warn!(target: Target::file_mail(), channel = id; "Data widgets need an output with encoder, not HLS");
+        return;
+    };
+    let mut widgets = config
+        .storage
+        .graphics
+        .values()
+        .enumerate()
+        .filter(|(_, g)| g.is_widget())
+        .filter_map(|(index, graphic)| {
+            Some(Widget {
+                index,
+                graphic,
+                text: graphic.text.as_ref()?.text.as_deref()?,
+                fields: HashMap::new(),
+                fetched: None,
+                on_air: None,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let placeholder = Regex::new(r"\{[\w.]+\}").unwrap();
+
+    while manager.is_alive.load(Ordering::SeqCst) {
+        let clock = clock_fields(&config);
+
+        for widget in &mut widgets {
+            let interval = Duration::from_secs(widget.graphic.interval.max(5) as u64);
+
+            if let Some(url) = &widget.graphic.url
+                && widget.fetched.is_none_or(|f| f.elapsed() >= interval)
+            {
+                match fetch(url).await {
+                    Ok(fields) => widget.fields = fields,
+                    Err(e) => {
+                        error!(target: Target::file(), channel = id; "Data widget <span class=\"log-addr\">{url}</span>: {e}");
+                    }
+                }
+
+                widget.fetched = Some(Instant::now());
+                // send the text again, in case the encoder restarted in between
+                widget.on_air = None;
+            }
+
+            let text = graphics::fill(&graphics::fill(widget.text, &clock), &widget.fields);
+            // fields, which the data has not (yet), stay empty
+            let text = placeholder.replace_all(&text, "").to_string();
+
+            if widget.on_air.as_ref() == Some(&text) {
+                continue;
+            }
+
+            let filter = TextFilter {
+                text: Some(text.clone()),
+                ..Default::default()
+            };
+            let command = format!("drawtext@gfxt{} reinit {filter}", widget.index);
+
+            match timeout(POLL, zmq_send(&command, &socket)).await {
+                Ok(Ok(_)) => widget.on_air = Some(text),
+                Ok(Err(e)) => debug!(target: Target::file(), channel = id; "Data widget: {e}"),
+                Err(_) => debug!(target: Target::file(), channel = id; "Data widget: zmq timeout"),
+            }
+        }
+
+        sleep(POLL).await;
+    }
+}
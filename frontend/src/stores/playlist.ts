@@ -27,6 +27,7 @@ export const usePlaylist = defineStore('playlist', {
         ingestRuns: false,
         elapsedSec: 0,
         shift: 0,
+        audioMeter: null as AudioMeter | null,
         playoutIsRunning: false,
         last_channel: 0,
         firstLoad: true,
@@ -95,6 +96,7 @@ export const usePlaylist = defineStore('playlist', {
             this.elapsedSec = item.elapsed
             this.ingestRuns = item.ingest
             this.shift = item.shift
+            this.audioMeter = item.audio_meter ?? null
 
             this.progressValue = (this.elapsedSec * 100) / this.current.out
         },
@@ -1,7 +1,7 @@
 import { timezone } from 'dayjs/plugin/timezone.js';
 import type { JwtPayload } from 'jwt-decode'
 import type { AdvancedConfig } from './advanced_config'
-import type { PlayoutConfig, Playlist as Ply } from './playout_config'
+import type { FieldOrder, PlayoutConfig, Playlist as Ply } from './playout_config'
 
 export {}
 
@@ -25,6 +25,7 @@ declare global {
         id: number
         name: string
         parameters: string
+        field_order: FieldOrder
         channel_id: number
     }
 
@@ -81,6 +82,8 @@ declare global {
         uid: string
         begin: number
         title?: string | null
+        artist?: string | null
+        program_id?: string | null
         source: string
         duration: number
         in: number
@@ -88,6 +91,19 @@ declare global {
         audio?: string
         category?: string
         custom_filter?: string
+        input_options?: string
+        live?: {
+            rtsp_transport?: string
+            latency?: number
+            passphrase?: string
+        }
+        start?: 'soft' | 'hard'
+        time?: string
+        type?: 'clip' | 'loop'
+        until?: string
+        slot?: number
+        skip_late?: number
+        blackout?: string[]
         overtime?: boolean
     }
 
@@ -127,8 +143,14 @@ declare global {
         paths: string[]
     }
 
+    interface TemplateIdent {
+        path: string
+        interval?: string
+    }
+
     interface Template {
         sources: TemplateItem[]
+        ident?: TemplateIdent
     }
 
     interface BodyObject {
@@ -154,6 +176,36 @@ declare global {
         elapsed: number
         shift: number
         title?: string
+        audio_meter?: AudioMeter | null
+        quarantine?: string[]
+        program?: PlayoutProgram | null
+        warnings?: PlaylistWarnings
+    }
+
+    interface PlaylistWarnings {
+        date: string
+        warnings: {
+            position: number | null
+            begin: string
+            source: string
+            kind: 'missing' | 'unreadable' | 'short' | 'slot' | 'codec' | 'decode' | 'silent' | 'live' | 'subtitle' | 'length'
+            message: string
+        }[]
+    }
+
+    interface PlayoutProgram {
+        program_id: string
+        title?: string | null
+        index: number
+        items: number
+        duration: number
+        breaks: number
+        elapsed: number
+    }
+
+    interface AudioMeter {
+        momentary: number | null
+        true_peak: number | null
     }
 
     interface SplitTime {
@@ -1,18 +1,129 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type General = { stop_threshold: number, preview_url: string, public_path: string, };
+/**
+ * Field order of the output, interlaced outputs are processed progressive
+ * and get interlaced at the end of the filter chain.
+ */
+export type FieldOrder = "progressive" | "tff" | "bff";
 
-export type Ingest = { enable: boolean, input_param: string, custom_filter: string, };
+/**
+ * Conversion of clips with a different frame rate than the channel.
+ */
+export type FpsPolicy = "drop_dup" | "blend" | "interpolate";
+
+export type General = { stop_threshold: number, 
+/**
+ * Nice level for the ffmpeg processes of this channel, 0 keeps the default priority.
+ */
+nice: number, 
+/**
+ * Control group (cgroup v2) for the ffmpeg processes, relative to /sys/fs/cgroup.
+ */
+cgroup: string, 
+/**
+ * Thread count passed to ffmpeg, 0 lets ffmpeg decide.
+ */
+threads: number, 
+/**
+ * Seconds without data from the decoder, before the clip gets skipped, 0 is off.
+ */
+stall_timeout: number, 
+/**
+ * Failures in a row, before a clip is skipped for the rest of the day, 0 is off.
+ */
+quarantine: number, 
+/**
+ * Restart a crashed decoder at the last played position, instead of skipping the clip.
+ */
+decoder_resume: boolean, 
+/**
+ * Failed clips in a row, before the standby slate runs until a source plays again, 0 is off.
+ */
+slate_errors: number, 
+/**
+ * Milliseconds of output, which are buffered between decoder and encoder.
+ */
+buffer_latency: number, 
+/**
+ * Files, which are probed at once, when a playlist is loaded.
+ */
+probe_workers: number, 
+/**
+ * Seconds for the probe of a file, before it counts as failed, 0 is off.
+ */
+probe_timeout: number, preview_url: string, public_path: string, };
+
+export type Ingest = { enable: boolean, input_param: string, custom_filter: string, preview: boolean, record: boolean, record_path: string, };
+
+/**
+ * Join of the scheduled item, when the playout starts or resumes late.
+ */
+export type JoinPolicy = "seek" | "top" | "next_hard";
 
 export type Logging = { ffmpeg_level: string, ingest_level: string, detect_silence: boolean, ignore_lines: Array<string>, };
 
 export type Mail = { show: boolean, subject: string, recipient: string, mail_level: string, interval: bigint, };
 
-export type Output = { id: number, mode: OutputMode, output_param: string, };
+export type Output = { id: number, mode: OutputMode, output_param: string, field_order: FieldOrder, 
+/**
+ * Audio only output in stream mode, like an Icecast mount for a web player.
+ */
+audio_url: string, 
+/**
+ * Encoder options for the audio output, can have `-f <format>`.
+ */
+audio_param: string, 
+/**
+ * Tags of the outputs in stream mode, one per output, comma separated.
+ * Items with a tag in `blackout` show the slate on this output.
+ */
+tags: string, 
+/**
+ * Folder of the compliance recording in stream mode, empty is off.
+ */
+compliance_path: string, 
+/**
+ * Days, which the compliance recording is kept, `0` keeps it forever.
+ */
+compliance_days: number, 
+/**
+ * Encoder options for the compliance recording.
+ */
+compliance_param: string, 
+/**
+ * Public url of the output (HLS playlist, RTMP, SRT), which gets checked, empty is off.
+ */
+check_url: string, 
+/**
+ * Seconds between the checks of the public output.
+ */
+check_interval: number, 
+/**
+ * Audio languages of the outputs, one per output, comma separated, more languages of
+ * one output with `+`, like `deu+eng, deu`.
+ */
+languages: string, };
 
 export type OutputMode = "desktop" | "hls" | "null" | "stream";
 
-export type Playlist = { day_start: string, length: string, infinit: boolean, };
+export type Playlist = { day_start: string, length: string, infinit: boolean, recovery: RecoveryStrategy, recovery_speed: number, 
+/**
+ * Percent, by which the speed of a clip may change, to fit into its slot.
+ */
+slot_fit: number, 
+/**
+ * "Up next" card at the end of gaps before a program, instead of filler.
+ */
+next_card: boolean, 
+/**
+ * Minutes before the end of the day, in which the playlist of the next day gets
+ * validated, 0 is off.
+ */
+read_ahead: number, 
+/**
+ * How to join the item, which runs already, when the playout starts late.
+ */
+join: JoinPolicy, };
 
 /**
  * Channel Config
@@ -21,12 +132,213 @@ export type Playlist = { day_start: string, length: string, infinit: boolean, };
  */
 export type PlayoutConfig = { general: General, mail: Mail, logging: Logging, processing: Processing, ingest: Ingest, playlist: Playlist, storage: Storage, text: Text, task: Task, output: Output, };
 
-export type ProcessMode = "folder" | "playlist";
+export type ProcessMode = "folder" | "playlist" | "test";
+
+export type Processing = { mode: ProcessMode, audio_only: boolean, copy_audio: boolean, copy_video: boolean, width: bigint, height: bigint, aspect: number, fps: number, fps_policy: FpsPolicy, 
+/**
+ * Clips within this frame rate difference are passed through unchanged.
+ */
+fps_tolerance: number, add_logo: boolean, logo: string, logo_scale: string, logo_opacity: number, logo_position: string, audio_tracks: number, audio_track_index: number, audio_channels: number, volume: number, custom_filter: string, override_filter: boolean, vtt_enable: boolean, vtt_dummy: string | null, audio_meter: boolean, tonemap: boolean, tonemap_algorithm: string, tonemap_desat: number, 
+/**
+ * Signal peak for the tonemap filter, `0` takes the value from the metadata.
+ */
+tonemap_peak: number, timecode_burn_in: boolean, timecode_embed: boolean, 
+/**
+ * Channel of the first audio track for LTC, starting from 1, `0` is off.
+ */
+ltc_channel: number, 
+/**
+ * Change logo opacity and volume at runtime, over zmq.
+ */
+live_control: boolean, 
+/**
+ * Milliseconds of A/V drift, before the decoder gets restarted, `0` is off.
+ */
+av_sync_tolerance: number, 
+/**
+ * Named audio filter chains, one `name = filters` per line.
+ */
+audio_chains: string, 
+/**
+ * Day parts, which select an audio chain, one `HH:MM-HH:MM = name` per line.
+ */
+audio_dayparts: string, 
+/**
+ * Inject ID3 timed metadata (title, ad breaks) into the HLS segments.
+ */
+hls_id3: boolean, 
+/**
+ * Pass embedded CEA-608/708 captions through to the output.
+ */
+captions: boolean, 
+/**
+ * Audio filter of the watermark encoder for audience measurement, at the end of the
+ * audio chain.
+ */
+watermark: string, 
+/**
+ * Milliseconds, by which the watermark encoder delays the audio.
+ */
+watermark_latency: number, 
+/**
+ * Languages of the audio tracks (ISO 639-2, like `deu, eng`), one per track, comma
+ * separated. The first is the primary language.
+ */
+audio_languages: string, 
+/**
+ * Audio track with the audio description, mixed from the sidecar files of the clips
+ * (`<clip>.ad.wav`), `0` is off.
+ */
+description_track: number, };
 
-export type Processing = { mode: ProcessMode, audio_only: boolean, copy_audio: boolean, copy_video: boolean, width: bigint, height: bigint, aspect: number, fps: number, add_logo: boolean, logo: string, logo_scale: string, logo_opacity: number, logo_position: string, audio_tracks: number, audio_track_index: number, audio_channels: number, volume: number, custom_filter: string, override_filter: boolean, vtt_enable: boolean, vtt_dummy: string | null, };
+/**
+ * Strategy to get back on time, when the playout falls behind schedule.
+ */
+export type RecoveryStrategy = "none" | "trim_clip" | "drop_filler" | "speed_up";
 
-export type Storage = { filler: string, extensions: Array<string>, shuffle: boolean, shared_storage: boolean, };
+export type Storage = { filler: string, extensions: Array<string>, shuffle: boolean, vod_path: string, vod_template: string, vod_hook: string, fallback: Array<string>, fallback_clip: string, 
+/**
+ * Image or video loop for the slate, which runs when no source can be played.
+ */
+slate: string, 
+/**
+ * Audio, which loops under the slate, silence when it is empty.
+ */
+slate_audio: string, 
+/**
+ * Clip, which runs in front of an ad break.
+ */
+bumper_in: string, 
+/**
+ * Clip, which runs after an ad break.
+ */
+bumper_out: string, 
+/**
+ * File name patterns of uploads in progress, which folder mode ignores, like `*.part`.
+ */
+upload_ignore: string, 
+/**
+ * Seconds after the last change, before folder mode plays a new file.
+ */
+upload_min_age: number, 
+/**
+ * FTP or SFTP drop folder, which gets polled for new media.
+ */
+incoming_url: string, 
+/**
+ * Target folder for incoming media, relative paths are inside the storage.
+ */
+incoming_path: string, 
+/**
+ * Poll interval in seconds.
+ */
+incoming_interval: number, 
+/**
+ * Delete files from the drop folder after the transfer.
+ */
+incoming_delete: boolean, 
+/**
+ * Transcode new media (uploads and incoming) to the house format before playout.
+ */
+transcode: boolean, 
+/**
+ * Transcode jobs, which run at the same time.
+ */
+transcode_workers: number, 
+/**
+ * Loudness target in LUFS, 0 disables the normalization.
+ */
+transcode_loudness: number, 
+/**
+ * Hours before a clip can air again, in folder mode and in the playlist generator.
+ * 0 disables the rule.
+ */
+separation: number, shared_storage: boolean, };
 
-export type Task = { enable: boolean, path: string, };
+export type Task = { enable: boolean, path: string, 
+/**
+ * Icecast admin metadata URL, with credentials and mount point.
+ */
+icecast_url: string, 
+/**
+ * Webhook, which gets the now playing metadata as JSON.
+ */
+webhook_url: string, 
+/**
+ * Hook commands for lifecycle events, see [hooks](crate::utils::hooks).
+ */
+hook_clip_start: string, hook_clip_end: string, hook_ingest_start: string, hook_ingest_stop: string, hook_error: string, hook_day_change: string, 
+/**
+ * Lua script, which decides about the next clip, see [script](crate::utils::script).
+ */
+script: string, 
+/**
+ * GPIO folder or serial port for triggers, see [triggers](crate::utils::triggers).
+ */
+trigger_device: string, 
+/**
+ * Debounce time of trigger inputs in milliseconds.
+ */
+trigger_debounce: number, 
+/**
+ * Mapping table, `input=action` per line.
+ */
+trigger_map: string, 
+/**
+ * Listen address for the VDCP adapter, see [vdcp](crate::utils::vdcp).
+ */
+vdcp_address: string, 
+/**
+ * Listen address for Companion commands over TCP and UDP, see [companion](crate::utils::companion).
+ */
+companion_address: string, 
+/**
+ * Listen address for OSC commands, see [osc](crate::utils::osc).
+ */
+osc_address: string, 
+/**
+ * OSC feedback targets, `host:port` separated by comma.
+ */
+osc_feedback: string, 
+/**
+ * Role in a redundant pair, `active` or `passive`, see [redundancy](crate::utils::redundancy).
+ */
+redundancy_role: string, 
+/**
+ * UDP listen address for the heartbeat of the peer.
+ */
+redundancy_address: string, 
+/**
+ * Heartbeat address of the peer, `host:port`.
+ */
+redundancy_peer: string, 
+/**
+ * Seconds without heartbeat, before the passive instance takes over.
+ */
+redundancy_timeout: number, };
 
-export type Text = { add_text: boolean, font: string, text_from_filename: boolean, style: string, regex: string, };
+export type Text = { add_text: boolean, font: string, text_from_filename: boolean, style: string, regex: string, 
+/**
+ * RSS or JSON feed of the news ticker.
+ */
+ticker_url: string, 
+/**
+ * Seconds between the updates of the feed.
+ */
+ticker_interval: number, 
+/**
+ * Speed of the crawl in pixels per second.
+ */
+ticker_speed: number, 
+/**
+ * Text between the items of the feed.
+ */
+ticker_separator: string, 
+/**
+ * Hours, in which the ticker runs, like `06:00-09:00; 17:00-23:00`, empty is always.
+ */
+ticker_hours: string, 
+/**
+ * drawtext values of the ticker, without text and x.
+ */
+ticker_style: string, };
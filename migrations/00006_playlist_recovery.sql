@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD playlist_recovery TEXT NOT NULL DEFAULT 'none';
+ALTER TABLE configurations ADD playlist_recovery_speed REAL NOT NULL DEFAULT 1.05;
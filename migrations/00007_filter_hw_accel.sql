@@ -0,0 +1 @@
+ALTER TABLE advanced_configurations ADD filter_hw_accel TEXT;
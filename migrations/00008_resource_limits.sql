@@ -0,0 +1,3 @@
+ALTER TABLE configurations ADD general_nice INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE configurations ADD general_cgroup TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD general_threads INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE configurations ADD ingest_preview INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD ingest_record INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE configurations ADD ingest_record_path TEXT NOT NULL DEFAULT 'recordings';
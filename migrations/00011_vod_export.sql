@@ -0,0 +1,3 @@
+ALTER TABLE configurations ADD storage_vod_path TEXT NOT NULL DEFAULT 'vod';
+ALTER TABLE configurations ADD storage_vod_template TEXT NOT NULL DEFAULT '{date}_{time}_{title}';
+ALTER TABLE configurations ADD storage_vod_hook TEXT NOT NULL DEFAULT '';
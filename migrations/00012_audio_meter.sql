@@ -0,0 +1 @@
+ALTER TABLE configurations ADD processing_audio_meter INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD storage_fallback TEXT NOT NULL DEFAULT 'filler;clip;placeholder=300;test';
+ALTER TABLE configurations ADD storage_fallback_clip TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD processing_fps_policy TEXT NOT NULL DEFAULT 'drop_dup';
+ALTER TABLE configurations ADD processing_fps_tolerance REAL NOT NULL DEFAULT 0.0;
@@ -0,0 +1,4 @@
+ALTER TABLE configurations ADD processing_tonemap INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE configurations ADD processing_tonemap_algorithm TEXT NOT NULL DEFAULT 'hable';
+ALTER TABLE configurations ADD processing_tonemap_desat REAL NOT NULL DEFAULT 0.0;
+ALTER TABLE configurations ADD processing_tonemap_peak REAL NOT NULL DEFAULT 0.0;
@@ -0,0 +1 @@
+ALTER TABLE outputs ADD field_order TEXT NOT NULL DEFAULT 'progressive';
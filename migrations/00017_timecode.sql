@@ -0,0 +1,3 @@
+ALTER TABLE configurations ADD processing_timecode_burn_in INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE configurations ADD processing_timecode_embed INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE configurations ADD processing_ltc_channel INTEGER NOT NULL DEFAULT 0;
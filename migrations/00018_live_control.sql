@@ -0,0 +1 @@
+ALTER TABLE configurations ADD processing_live_control INTEGER NOT NULL DEFAULT 0;
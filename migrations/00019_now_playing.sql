@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD task_icecast_url TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD task_webhook_url TEXT NOT NULL DEFAULT '';
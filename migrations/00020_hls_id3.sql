@@ -0,0 +1 @@
+ALTER TABLE configurations ADD processing_hls_id3 INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE configurations ADD general_stall_timeout REAL NOT NULL DEFAULT 30;
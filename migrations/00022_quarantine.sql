@@ -0,0 +1 @@
+ALTER TABLE configurations ADD general_quarantine INTEGER NOT NULL DEFAULT 2;
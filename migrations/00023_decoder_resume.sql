@@ -0,0 +1 @@
+ALTER TABLE configurations ADD general_decoder_resume INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+ALTER TABLE configurations ADD storage_incoming_url TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD storage_incoming_path TEXT NOT NULL DEFAULT 'incoming';
+ALTER TABLE configurations ADD storage_incoming_interval INTEGER NOT NULL DEFAULT 60;
+ALTER TABLE configurations ADD storage_incoming_transcode INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE configurations ADD storage_incoming_delete INTEGER NOT NULL DEFAULT 0;
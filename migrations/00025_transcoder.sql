@@ -0,0 +1,3 @@
+ALTER TABLE configurations RENAME COLUMN storage_incoming_transcode TO storage_transcode;
+ALTER TABLE configurations ADD storage_transcode_workers INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE configurations ADD storage_transcode_loudness REAL NOT NULL DEFAULT -23;
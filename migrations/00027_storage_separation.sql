@@ -0,0 +1 @@
+ALTER TABLE configurations ADD storage_separation REAL NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+ALTER TABLE configurations ADD task_hook_clip_start TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD task_hook_clip_end TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD task_hook_ingest_start TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD task_hook_ingest_stop TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD task_hook_error TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD task_hook_day_change TEXT NOT NULL DEFAULT '';
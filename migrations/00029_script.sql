@@ -0,0 +1 @@
+ALTER TABLE configurations ADD task_script TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,3 @@
+ALTER TABLE configurations ADD task_trigger_device TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD task_trigger_debounce INTEGER NOT NULL DEFAULT 50;
+ALTER TABLE configurations ADD task_trigger_map TEXT NOT NULL DEFAULT '';
@@ -0,0 +1 @@
+ALTER TABLE configurations ADD task_vdcp_address TEXT NOT NULL DEFAULT '';
@@ -0,0 +1 @@
+ALTER TABLE configurations ADD task_companion_address TEXT NOT NULL DEFAULT '';
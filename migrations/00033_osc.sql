@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD task_osc_address TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD task_osc_feedback TEXT NOT NULL DEFAULT '';
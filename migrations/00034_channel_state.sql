@@ -0,0 +1,6 @@
+CREATE TABLE
+    channel_state (
+        channel_id INTEGER PRIMARY KEY,
+        state TEXT NOT NULL DEFAULT '{}',
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE
+    );
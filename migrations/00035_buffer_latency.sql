@@ -0,0 +1 @@
+ALTER TABLE configurations ADD general_buffer_latency INTEGER NOT NULL DEFAULT 500;
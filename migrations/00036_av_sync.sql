@@ -0,0 +1 @@
+ALTER TABLE configurations ADD processing_av_sync_tolerance INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+ALTER TABLE configurations ADD task_redundancy_role TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD task_redundancy_address TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD task_redundancy_peer TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD task_redundancy_timeout INTEGER NOT NULL DEFAULT 3;
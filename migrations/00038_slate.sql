@@ -0,0 +1 @@
+ALTER TABLE configurations ADD storage_slate TEXT NOT NULL DEFAULT '';
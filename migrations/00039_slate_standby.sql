@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD storage_slate_audio TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD general_slate_errors INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD storage_bumper_in TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD storage_bumper_out TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD processing_audio_chains TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD processing_audio_dayparts TEXT NOT NULL DEFAULT '';
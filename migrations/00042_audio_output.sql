@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD output_audio_url TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD output_audio_param TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD storage_upload_ignore TEXT NOT NULL DEFAULT '*.part, *.tmp, *.crdownload, *.filepart, .*';
+ALTER TABLE configurations ADD storage_upload_min_age INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD general_probe_workers INTEGER NOT NULL DEFAULT 8;
+ALTER TABLE configurations ADD general_probe_timeout REAL NOT NULL DEFAULT 30.0;
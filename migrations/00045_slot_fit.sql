@@ -0,0 +1 @@
+ALTER TABLE configurations ADD playlist_slot_fit REAL NOT NULL DEFAULT 2.5;
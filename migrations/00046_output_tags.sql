@@ -0,0 +1 @@
+ALTER TABLE configurations ADD output_tags TEXT NOT NULL DEFAULT '';
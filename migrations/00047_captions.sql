@@ -0,0 +1 @@
+ALTER TABLE configurations ADD processing_captions INTEGER NOT NULL DEFAULT 0;
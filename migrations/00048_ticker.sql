@@ -0,0 +1,6 @@
+ALTER TABLE configurations ADD text_ticker_url TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD text_ticker_interval INTEGER NOT NULL DEFAULT 300;
+ALTER TABLE configurations ADD text_ticker_speed INTEGER NOT NULL DEFAULT 100;
+ALTER TABLE configurations ADD text_ticker_separator TEXT NOT NULL DEFAULT ' +++ ';
+ALTER TABLE configurations ADD text_ticker_hours TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD text_ticker_style TEXT NOT NULL DEFAULT 'y=h-th-20:fontsize=30:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=8';
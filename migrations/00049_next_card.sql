@@ -0,0 +1 @@
+ALTER TABLE configurations ADD playlist_next_card INTEGER NOT NULL DEFAULT 0;
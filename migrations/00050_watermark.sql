@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD processing_watermark TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD processing_watermark_latency INTEGER NOT NULL DEFAULT 0;
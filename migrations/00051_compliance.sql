@@ -0,0 +1,3 @@
+ALTER TABLE configurations ADD output_compliance_path TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD output_compliance_days INTEGER NOT NULL DEFAULT 30;
+ALTER TABLE configurations ADD output_compliance_param TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD output_check_url TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD output_check_interval INTEGER NOT NULL DEFAULT 60;
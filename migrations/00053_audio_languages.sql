@@ -0,0 +1,2 @@
+ALTER TABLE configurations ADD processing_audio_languages TEXT NOT NULL DEFAULT '';
+ALTER TABLE configurations ADD output_languages TEXT NOT NULL DEFAULT '';
@@ -0,0 +1 @@
+ALTER TABLE configurations ADD processing_description_track INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE configurations ADD playlist_read_ahead INTEGER NOT NULL DEFAULT 240;
@@ -0,0 +1 @@
+ALTER TABLE configurations ADD playlist_join TEXT NOT NULL DEFAULT 'seek';
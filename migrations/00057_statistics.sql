@@ -0,0 +1,11 @@
+CREATE TABLE
+    statistics (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        date TEXT NOT NULL,
+        category TEXT NOT NULL,
+        seconds REAL NOT NULL DEFAULT 0,
+        errors INTEGER NOT NULL DEFAULT 0,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE,
+        UNIQUE (channel_id, date, category)
+    );
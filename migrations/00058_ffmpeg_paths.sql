@@ -0,0 +1,2 @@
+ALTER TABLE global ADD ffmpeg TEXT NOT NULL DEFAULT '';
+ALTER TABLE global ADD ffprobe TEXT NOT NULL DEFAULT '';
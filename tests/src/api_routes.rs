@@ -1,13 +1,24 @@
+use std::sync::{Arc, atomic::Ordering};
+
 use actix_web::{App, Error, HttpResponse, Responder, get, web};
+use actix_web_httpauth::middleware::HttpAuthentication;
 
-use serde_json::json;
+use serde_json::{Value, json};
 use sqlx::{Pool, Sqlite, sqlite::SqlitePoolOptions};
+use tokio::sync::{Mutex, RwLock};
 
-use ffplayout::api::routes::login;
+use ffplayout::api::routes::{add_channel_config, health, login, readyz};
 use ffplayout::db::{handles, init_globales, models::User};
-use ffplayout::player::controller::ChannelManager;
-use ffplayout::utils::config::PlayoutConfig;
-// use ffplayout::validator;
+use ffplayout::player::{
+    controller::{ChannelController, ChannelManager},
+    utils::Media,
+};
+use ffplayout::utils::{
+    config::{OutputMode, PlayoutConfig},
+    mail::MailQueue,
+    statistics::{self, StatsQuery},
+};
+use ffplayout::validator;
 
 async fn prepare_config() -> (PlayoutConfig, ChannelManager, Pool<Sqlite>) {
     let pool = SqlitePoolOptions::new()
@@ -64,7 +75,7 @@ async fn test_get() {
 async fn test_login() {
     let (_, _, pool) = prepare_config().await;
 
-    init_globales(&pool).await.unwrap();
+    let _ = init_globales(&pool).await;
 
     let srv = actix_test::start(move || {
         let db_pool = web::Data::new(pool.clone());
@@ -91,3 +102,224 @@ async fn test_login() {
 
     assert_eq!(res.status().as_u16(), 403);
 }
+
+#[actix_web::test]
+async fn test_health() {
+    let (_, manager, _) = prepare_config().await;
+    let mut controller = ChannelController::new();
+    manager.channel.lock().await.active = false;
+    controller.add(manager.clone());
+
+    let controllers = web::Data::new(RwLock::new(controller));
+    let srv = actix_test::start(move || App::new().app_data(controllers.clone()).service(health));
+
+    let res = srv.get("/health").send().await.unwrap();
+    assert!(res.status().is_success());
+
+    manager.channel.lock().await.active = true;
+
+    let res = srv.get("/health").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 503);
+}
+
+#[actix_web::test]
+async fn test_probes() {
+    let (_, manager, _) = prepare_config().await;
+    let mut controller = ChannelController::new();
+    manager.channel.lock().await.active = true;
+    manager.is_alive.store(true, Ordering::SeqCst);
+    controller.add(manager.clone());
+
+    let controllers = web::Data::new(RwLock::new(controller));
+    let srv = actix_test::start(move || {
+        App::new()
+            .app_data(controllers.clone())
+            .service(health)
+            .service(readyz)
+    });
+
+    // no progress and no encoder yet
+    let res = srv.get("/healthz").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 503);
+    let res = srv.get("/readyz").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 503);
+
+    manager.tick(true);
+
+    let res = srv.get("/healthz").send().await.unwrap();
+    assert!(res.status().is_success());
+    // still no encoder process
+    let res = srv.get("/readyz").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 503);
+}
+
+#[actix_web::test]
+async fn test_probes_hls() {
+    let (_, manager, _) = prepare_config().await;
+    let mut controller = ChannelController::new();
+    manager.config.write().await.output.mode = OutputMode::HLS;
+    manager.channel.lock().await.active = true;
+    manager.is_alive.store(true, Ordering::SeqCst);
+    manager.tick(true);
+    controller.add(manager.clone());
+
+    let controllers = web::Data::new(RwLock::new(controller));
+    let srv = actix_test::start(move || {
+        App::new()
+            .app_data(controllers.clone())
+            .service(health)
+            .service(readyz)
+    });
+
+    let res = srv.get("/healthz").send().await.unwrap();
+    assert!(res.status().is_success());
+    // no ffmpeg process writes the segments yet
+    let res = srv.get("/readyz").send().await.unwrap();
+    assert_eq!(res.status().as_u16(), 503);
+
+    let proc = tokio::process::Command::new("sleep")
+        .arg("5")
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+    *manager.decoder.lock().await = Some(proc);
+
+    // HLS mode has no separate encoder, the decoder writes the output
+    let res = srv.get("/readyz").send().await.unwrap();
+    assert!(res.status().is_success());
+    assert!(manager.encoder.lock().await.is_none());
+}
+
+#[actix_web::test]
+#[ignore]
+async fn test_add_channel_config() {
+    let (config, manager, pool) = prepare_config().await;
+    let mut controller = ChannelController::new();
+    controller.add(manager);
+
+    // the globals are shared by all tests, another test can have them set already
+    let _ = init_globales(&pool).await;
+
+    let controllers = web::Data::new(RwLock::new(controller));
+    let queues = web::Data::new(Mutex::new(Vec::<Arc<Mutex<MailQueue>>>::new()));
+    let db_pool = pool.clone();
+    let srv = actix_test::start(move || {
+        App::new()
+            .app_data(web::Data::new(db_pool.clone()))
+            .app_data(controllers.clone())
+            .app_data(queues.clone())
+            .service(web::scope("/auth").service(login))
+            .service(
+                web::scope("/api")
+                    .wrap(HttpAuthentication::bearer(validator))
+                    .service(add_channel_config),
+            )
+    });
+
+    let mut res = srv
+        .post("/auth/login/")
+        .send_json(&json!({"username": "admin", "password": "admin"}))
+        .await
+        .unwrap();
+    let auth: Value = res.json().await.unwrap();
+    let token = format!("Bearer {}", auth["access"].as_str().unwrap());
+
+    let channel = json!({
+        "name": "Channel 2",
+        "preview_url": "http://127.0.0.1:8787/live/stream.m3u8",
+        "extra_extensions": "jpg,jpeg,png",
+        "active": false,
+        "public": "assets/hls",
+        "playlists": "assets/playlists",
+        "storage": "assets/storage",
+        "last_date": null,
+        "time_shift": 0.0
+    });
+    let mut bad_config = config.clone();
+    bad_config.processing.width = 1023;
+
+    // an invalid config removes the new channel again
+    let res = srv
+        .post("/api/channel/config/")
+        .insert_header(("Authorization", token.clone()))
+        .send_json(&json!({"channel": channel, "config": bad_config}))
+        .await
+        .unwrap();
+
+    assert_eq!(res.status().as_u16(), 409);
+    assert_eq!(
+        handles::select_related_channels(&pool, None)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let mut new_config = config.clone();
+    new_config.processing.width = 1280;
+    new_config.processing.height = 720;
+    new_config.processing.aspect = 1280.0 / 720.0;
+
+    let mut res = srv
+        .post("/api/channel/config/")
+        .insert_header(("Authorization", token))
+        .send_json(&json!({"channel": channel, "config": new_config}))
+        .await
+        .unwrap();
+
+    assert!(res.status().is_success());
+
+    let created: Value = res.json().await.unwrap();
+    let id = created["id"].as_i64().unwrap() as i32;
+    let saved = PlayoutConfig::new(&pool, id, None).await.unwrap();
+
+    assert_eq!(saved.processing.width, 1280);
+    assert_eq!(saved.processing.height, 720);
+}
+
+#[tokio::test]
+async fn channel_statistics() {
+    let (config, _, pool) = prepare_config().await;
+    let ad = Media {
+        category: "advertisement".to_string(),
+        ..Media::default()
+    };
+    let filler = Media {
+        category: "filler".to_string(),
+        ..Media::default()
+    };
+
+    assert_eq!(statistics::category(&config, &Media::default()), "clip");
+    assert_eq!(statistics::category(&config, &ad), "advertisement");
+    assert_eq!(statistics::category(&config, &filler), "filler");
+
+    statistics::add(&pool, &config, "2026-10-01", "clip", 1800.0, false).await;
+    statistics::add(&pool, &config, "2026-10-01", "clip", 1200.0, true).await;
+    statistics::add(&pool, &config, "2026-10-01", "filler", 90.0, false).await;
+    statistics::add(&pool, &config, "2026-10-02", "ingest", 2700.0, false).await;
+    statistics::add(&pool, &config, "2026-10-02", "advertisement", 30.0, false).await;
+    statistics::add(&pool, &config, "2026-11-01", "clip", 600.0, false).await;
+
+    let query = StatsQuery {
+        from: "2026-10-01".to_string(),
+        to: "2026-10-31".to_string(),
+    };
+    let report = statistics::report(&pool, &config, &query).await.unwrap();
+
+    assert_eq!(report.days.len(), 2);
+    assert_eq!(report.days[0].date.as_deref(), Some("2026-10-01"));
+    assert_eq!(report.days[0].airtime["clip"], 50.0);
+    assert_eq!(report.days[0].errors, 1);
+    assert_eq!(report.days[0].filler, 1.5);
+    assert_eq!(report.days[1].ingest, 45.0);
+    assert_eq!(report.days[1].airtime["advertisement"], 0.5);
+    assert_eq!(report.total.airtime.len(), 2);
+    assert_eq!(report.total.date, None);
+
+    let query = StatsQuery {
+        from: "10/01/2026".to_string(),
+        ..StatsQuery::default()
+    };
+
+    assert!(statistics::report(&pool, &config, &query).await.is_err());
+}
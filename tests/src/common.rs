@@ -0,0 +1,45 @@
+// every test binary uses only some of the helpers
+#![allow(dead_code)]
+
+use sqlx::sqlite::SqlitePoolOptions;
+
+use ffplayout::db::handles;
+use ffplayout::player::{controller::ChannelManager, utils::Media};
+use ffplayout::utils::config::PlayoutConfig;
+
+/// Config and manager of channel 1, in an in-memory database.
+pub async fn prepare_config() -> (PlayoutConfig, ChannelManager) {
+    let pool = SqlitePoolOptions::new()
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    handles::db_migrate(&pool).await.unwrap();
+
+    sqlx::query(
+        r#"
+        UPDATE global SET public = "assets/hls", logs = "assets/log", playlists = "assets/playlists", storage = "assets/storage";
+        UPDATE channels SET public = "assets/hls", playlists = "assets/playlists", storage = "assets/storage";
+        UPDATE configurations SET processing_width = 1024, processing_height = 576;
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let config = PlayoutConfig::new(&pool, 1, None).await.unwrap();
+    let channel = handles::select_channel(&pool, &1).await.unwrap();
+    let manager = ChannelManager::new(pool, channel, config.clone()).await;
+
+    (config, manager)
+}
+
+/// Playlist item with `source` and `duration`, which begins at `begin`.
+pub fn clip(source: &str, begin: Option<f64>, duration: f64) -> Media {
+    Media {
+        begin,
+        source: source.to_string(),
+        out: duration,
+        duration,
+        ..Media::default()
+    }
+}
@@ -0,0 +1,218 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::prepare_config;
+use ffplayout::db::handles;
+use ffplayout::utils::{
+    advanced_config::AdvancedConfig,
+    config::{
+        FallbackSource, FallbackStep, PlayoutConfig, apply_overrides, channel_path, check_format,
+        fallback_chain, fill_path, fixed_path, format_errors, from_toml, read_config_file,
+    },
+    time_machine::time_now,
+};
+use ffplayout::vec_strings;
+
+#[tokio::test]
+async fn channel_format_check() {
+    let (mut config, _) = prepare_config().await;
+
+    assert!(check_format(&config).is_empty());
+
+    config.processing.width = 1023;
+    config.processing.audio_track_index = 2;
+    config.advanced.decoder.output_cmd = Some(vec_strings!["-r", "30", "-c:v", "mpeg2video"]);
+    config.advanced.filter.scale = Some("scale={}:{}:{}".to_string());
+
+    let findings = check_format(&config);
+
+    assert_eq!(findings.len(), 4);
+    assert!(findings.iter().all(|(level, _)| level.as_str() == "ERROR"));
+    assert!(findings[0].1.contains("even width"));
+    assert!(findings[3].1.contains("'scale'"));
+}
+
+#[tokio::test]
+async fn staged_format_errors() {
+    let (mut config, manager) = prepare_config().await;
+
+    assert!(format_errors(&config).is_ok());
+
+    config.processing.width = 1023;
+
+    // submitted settings are checked, before they get saved
+    let staged = PlayoutConfig::staged(&manager.db_pool, 1, config)
+        .await
+        .unwrap();
+    let error = format_errors(&staged).unwrap_err().to_string();
+
+    assert!(error.contains("even width"));
+}
+
+#[tokio::test]
+async fn advanced_config_hw_accel() {
+    let (_, manager) = prepare_config().await;
+    let mut advanced = AdvancedConfig::default();
+    advanced.filter.hw_accel = Some("cuda".to_string());
+
+    handles::insert_advanced_configuration(&manager.db_pool, 1, None, advanced)
+        .await
+        .unwrap();
+
+    let config = PlayoutConfig::new(&manager.db_pool, 1, None).await.unwrap();
+
+    assert_eq!(config.advanced.filter.hw_accel.as_deref(), Some("cuda"));
+}
+
+#[tokio::test]
+async fn strict_config_parsing() {
+    let (config, _) = prepare_config().await;
+    let toml_string = toml_edit::ser::to_string_pretty(&config).unwrap();
+
+    let (_, unknown) = from_toml::<PlayoutConfig>(&toml_string).unwrap();
+    assert!(unknown.is_empty());
+
+    let extra = toml_string.replace("[processing]", "[processing]\nwidht = 1280");
+    let (_, unknown) = from_toml::<PlayoutConfig>(&extra).unwrap();
+    assert_eq!(unknown, vec!["processing.widht".to_string()]);
+
+    let wrong = toml_string.replace("width = 1024", "width = \"1024\"");
+    let err = from_toml::<PlayoutConfig>(&wrong).unwrap_err();
+    assert!(err.to_string().contains("processing.width"));
+
+    // older exports have no recovery speed
+    let older = toml_string
+        .lines()
+        .filter(|l| !l.starts_with("recovery_speed"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let (older, _) = from_toml::<PlayoutConfig>(&older).unwrap();
+    assert_eq!(older.playlist.recovery_speed, 1.05);
+}
+
+#[tokio::test]
+async fn config_overrides() {
+    let (_, manager) = prepare_config().await;
+    let pool = &manager.db_pool;
+    let mut config = handles::select_configuration(pool, 1).await.unwrap();
+    let mut outputs = handles::select_outputs(pool, 1).await.unwrap();
+    let hls_id = outputs.iter().find(|o| o.name == "hls").unwrap().id;
+
+    let overrides = vec![
+        ("processing.width".to_string(), "1280".to_string()),
+        ("text.add_text".to_string(), "true".to_string()),
+        ("out.mode".to_string(), "hls".to_string()),
+        (
+            "output.output_param".to_string(),
+            "-f hls live.m3u8".to_string(),
+        ),
+    ];
+
+    apply_overrides(&mut config, &mut outputs, &overrides).unwrap();
+
+    assert_eq!(config.processing_width, 1280);
+    assert!(config.text_add);
+    assert_eq!(config.output_id, hls_id);
+    assert_eq!(
+        outputs.iter().find(|o| o.id == hls_id).unwrap().parameters,
+        "-f hls live.m3u8"
+    );
+
+    let wrong = vec![("processing.width".to_string(), "wide".to_string())];
+    assert!(apply_overrides(&mut config, &mut outputs, &wrong).is_err());
+
+    let unknown = vec![("processing.widht".to_string(), "1280".to_string())];
+    assert!(apply_overrides(&mut config, &mut outputs, &unknown).is_err());
+}
+
+#[tokio::test]
+async fn config_file_includes() {
+    let dir = std::env::temp_dir().join("ffplayout_config_includes");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    std::fs::write(
+        dir.join("shared.toml"),
+        "[logging]\nffmpeg_level = \"warning\"\ndetect_silence = true\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("channel.json"),
+        r#"{"include": "shared.toml", "logging": {"detect_silence": false}}"#,
+    )
+    .unwrap();
+    std::fs::write(dir.join("loop.toml"), "include = [\"loop.toml\"]\n").unwrap();
+
+    let contents = read_config_file(&dir.join("channel.json")).await.unwrap();
+
+    assert!(!contents.contains("include"));
+    assert!(contents.contains("ffmpeg_level = \"warning\""));
+    assert!(contents.contains("detect_silence = false"));
+    assert!(read_config_file(&dir.join("loop.toml")).await.is_err());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fallback_chain_parsing() {
+    let chain = fallback_chain(&vec_strings!["filler", "clip=600", "placeholder"]).unwrap();
+
+    assert_eq!(
+        chain,
+        vec![
+            FallbackStep {
+                source: FallbackSource::Filler,
+                timeout: 0
+            },
+            FallbackStep {
+                source: FallbackSource::Clip,
+                timeout: 600
+            },
+            FallbackStep {
+                source: FallbackSource::Placeholder,
+                timeout: 0
+            },
+            FallbackStep {
+                source: FallbackSource::Test,
+                timeout: 0
+            },
+        ]
+    );
+
+    let chain = fallback_chain(&[]).unwrap();
+    assert_eq!(chain.len(), 4);
+    assert_eq!(chain[2].timeout, 300);
+
+    assert!(fallback_chain(&vec_strings!["filler", "folder"]).is_err());
+    assert!(fallback_chain(&vec_strings!["clip=soon"]).is_err());
+}
+
+#[tokio::test]
+async fn path_templates() {
+    assert_eq!(
+        fill_path("/var/lib/ffplayout/{channel}/playlists", 3),
+        "/var/lib/ffplayout/3/playlists"
+    );
+    assert_eq!(fill_path("/media/storage", 1), "/media/storage");
+
+    let host = fill_path("/mnt/{hostname}/logs", 1);
+
+    assert!(host.starts_with("/mnt/") && !host.contains("{hostname}"));
+
+    // {date} is only resolved, when the folder is used
+    let (mut config, _) = prepare_config().await;
+    let today = time_now(&config.channel.timezone)
+        .format("%Y-%m-%d")
+        .to_string();
+
+    assert_eq!(
+        channel_path(&config, "archive/{channel}/{date}"),
+        format!("archive/{}/{today}", config.general.channel_id)
+    );
+    assert!(fixed_path("Storage", "/media/{channel}").is_ok());
+    assert!(fixed_path("Storage", "/media/{date}").is_err());
+
+    config.channel.playlists = PathBuf::from("/playlists/{date}");
+
+    assert!(format_errors(&config).is_err());
+}
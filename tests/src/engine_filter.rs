@@ -1,10 +1,33 @@
 use std::fs;
 
+use serial_test::serial;
 use sqlx::sqlite::SqlitePoolOptions;
 
 use ffplayout::db::handles;
-use ffplayout::player::{controller::ChannelManager, utils::Media};
-use ffplayout::utils::config::{OutputMode::*, PlayoutConfig};
+use ffplayout::player::{
+    controller::{ChannelManager, ProcessUnit::Encoder},
+    filter::{
+        audio_chains::{self, chain_at, parse_chains, parse_dayparts},
+        graphics::{self, Graphic},
+    },
+    utils::{
+        Media,
+        meter::AudioMeter,
+        probe::{AudioStream, MediaProbe, VideoStream},
+        timecode::{ltc_expr, timecode},
+    },
+};
+use ffplayout::utils::{
+    TextFilter,
+    config::{
+        FieldOrder, FpsPolicy, OutputMode::*, PlayoutConfig, caption_flags, interlace_flags,
+        subtitle_rendition,
+    },
+    control::{FilterParams, live_commands},
+    ticker::{self, active, parse_feed, ticker_text},
+    widgets::{clock_fields, flatten},
+};
+use ffplayout::vec_strings;
 
 async fn get_config() -> (PlayoutConfig, ChannelManager) {
     let pool = SqlitePoolOptions::new()
@@ -47,3 +70,598 @@ async fn simple_filtering() {
 
     // println!("{f:?}");
 }
+
+#[tokio::test]
+async fn gpu_filtering() {
+    let (mut config, _) = get_config().await;
+
+    config.output.mode = Stream;
+    config.advanced.filter.hw_accel = Some("cuda".to_string());
+
+    let mut media = Media {
+        source: "./assets/storage/media_mix/with_audio.mp4".to_string(),
+        out: 30.0,
+        duration: 30.0,
+        probe: Some(MediaProbe {
+            audio: vec![AudioStream {
+                channels: 2,
+                duration: Some(30.0),
+                ..AudioStream::default()
+            }],
+            video: vec![VideoStream {
+                aspect_ratio: Some("16:9".to_string()),
+                width: Some(1920),
+                height: Some(1080),
+                frame_rate: "25/1".to_string(),
+                duration: Some(30.0),
+                ..VideoStream::default()
+            }],
+            ..MediaProbe::default()
+        }),
+        ..Media::default()
+    };
+    media.add_filter(&config, &None).await;
+
+    let f = media.filter.unwrap().cmd();
+    let video = f[1].split(';').next().unwrap();
+
+    assert!(video.contains("format=nv12,hwupload_cuda,scale_npp=1024:576"));
+    assert!(video.ends_with("hwdownload,format=nv12,format=yuv420p[vout0]"));
+}
+
+#[tokio::test]
+async fn audio_meter_filtering() {
+    let (mut config, _) = get_config().await;
+
+    config.output.mode = Stream;
+    config.processing.audio_meter = true;
+
+    let mut media = Media {
+        source: "./assets/storage/media_mix/with_audio.mp4".to_string(),
+        out: 30.0,
+        duration: 30.0,
+        probe: Some(MediaProbe {
+            audio: vec![AudioStream {
+                channels: 2,
+                duration: Some(30.0),
+                ..AudioStream::default()
+            }],
+            ..MediaProbe::default()
+        }),
+        ..Media::default()
+    };
+    media.add_filter(&config, &None).await;
+
+    let f = media.filter.unwrap().cmd();
+    let audio = f[1].split(';').next_back().unwrap();
+
+    assert!(audio.contains("ebur128=metadata=1:peak=true,ametadata=mode=print"));
+    assert!(audio.ends_with("[aout0]"));
+
+    let mut meter = AudioMeter::new();
+
+    assert!(!meter.update("[Parsed_volume_1 @ 0x55d] [error] some error"));
+    assert!(meter.update("frame:21   pts:20480   pts_time:0.426667"));
+    assert!(meter.update("lavfi.r128.M=-23.474"));
+    assert!(meter.update("lavfi.r128.true_peaks_ch0=-6.021"));
+    assert!(meter.update("lavfi.r128.true_peaks_ch1=-5.880"));
+    assert!(meter.update("frame:22   pts:21504   pts_time:0.448000"));
+
+    assert_eq!(meter.momentary(), Some(-23.474));
+    assert_eq!(meter.true_peak(), Some(-5.880));
+
+    assert!(meter.update("lavfi.r128.M=-inf"));
+    assert_eq!(meter.momentary(), None);
+}
+
+#[tokio::test]
+async fn watermark_filtering() {
+    let (mut config, _) = get_config().await;
+
+    config.output.mode = Stream;
+    config.processing.audio_meter = true;
+    config.processing.watermark = "ladspa=file=watermark:plugin=encode".to_string();
+    config.processing.watermark_latency = 250;
+
+    let mut media = Media {
+        source: "./assets/storage/media_mix/with_audio.mp4".to_string(),
+        out: 30.0,
+        duration: 30.0,
+        probe: Some(MediaProbe {
+            audio: vec![AudioStream {
+                channels: 2,
+                duration: Some(30.0),
+                ..AudioStream::default()
+            }],
+            ..MediaProbe::default()
+        }),
+        ..Media::default()
+    };
+    media.add_filter(&config, &None).await;
+
+    let f = media.filter.take().unwrap().cmd();
+    let audio = f[1].split(';').next_back().unwrap();
+
+    // the watermark comes before the meter, which measures the output
+    assert!(audio.contains(
+        "apad=pad_dur=0.25,ladspa=file=watermark:plugin=encode,atrim=start=0.25,asetpts=PTS-STARTPTS,ebur128"
+    ));
+
+    config.processing.watermark_latency = 0;
+
+    let mut media = Media {
+        unit: Encoder,
+        ..media
+    };
+    media.add_filter(&config, &None).await;
+
+    // the encoder gets the audio already watermarked
+    assert!(!media.filter.unwrap().cmd().concat().contains("ladspa"));
+}
+
+#[tokio::test]
+async fn fps_policy_filtering() {
+    let (mut config, _) = get_config().await;
+
+    config.output.mode = Stream;
+    config.processing.fps = 25.0;
+
+    let media = |frame_rate: &str| Media {
+        source: "./assets/storage/media_mix/with_audio.mp4".to_string(),
+        out: 30.0,
+        duration: 30.0,
+        probe: Some(MediaProbe {
+            video: vec![VideoStream {
+                aspect_ratio: Some("16:9".to_string()),
+                width: Some(1024),
+                height: Some(576),
+                frame_rate: frame_rate.to_string(),
+                duration: Some(30.0),
+                ..VideoStream::default()
+            }],
+            ..MediaProbe::default()
+        }),
+        ..Media::default()
+    };
+
+    let mut node = media("30/1");
+    node.add_filter(&config, &None).await;
+    assert!(node.filter.unwrap().cmd()[1].contains("fps=25"));
+
+    config.processing.fps_policy = FpsPolicy::Interpolate;
+    let mut node = media("30/1");
+    node.add_filter(&config, &None).await;
+    assert!(node.filter.unwrap().cmd()[1].contains("minterpolate=fps=25:mi_mode=mci"));
+
+    config.processing.fps_policy = FpsPolicy::Blend;
+    config.processing.fps_tolerance = 0.1;
+    let mut node = media("24000/1001");
+    node.add_filter(&config, &None).await;
+    assert!(node.filter.unwrap().cmd()[1].contains("minterpolate=fps=25:mi_mode=blend"));
+
+    let mut node = media("2997/120");
+    node.add_filter(&config, &None).await;
+    let f = node.filter.unwrap().cmd();
+    assert!(!f.join(" ").contains("fps="));
+
+    // minterpolate drops the captions
+    config.processing.captions = true;
+    let mut node = media("24000/1001");
+    node.add_filter(&config, &None).await;
+    let f = node.filter.unwrap().cmd()[1].clone();
+    assert!(f.contains("fps=25") && !f.contains("minterpolate"));
+}
+
+#[tokio::test]
+async fn tonemap_filtering() {
+    let (mut config, _) = get_config().await;
+
+    config.output.mode = Stream;
+
+    let media = |transfer: Option<&str>, primaries: Option<&str>| Media {
+        source: "./assets/storage/media_mix/with_audio.mp4".to_string(),
+        out: 30.0,
+        duration: 30.0,
+        probe: Some(MediaProbe {
+            video: vec![VideoStream {
+                aspect_ratio: Some("16:9".to_string()),
+                width: Some(1024),
+                height: Some(576),
+                frame_rate: "25/1".to_string(),
+                duration: Some(30.0),
+                color_transfer: transfer.map(String::from),
+                color_primaries: primaries.map(String::from),
+                ..VideoStream::default()
+            }],
+            ..MediaProbe::default()
+        }),
+        ..Media::default()
+    };
+
+    config.processing.tonemap_peak = 10.0;
+    let mut node = media(Some("smpte2084"), Some("bt2020"));
+    node.add_filter(&config, &None).await;
+    let f = node.filter.unwrap().cmd();
+
+    assert!(f[1].contains(
+        "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,tonemap=tonemap=hable:desat=0:peak=10"
+    ));
+
+    let mut node = media(Some("bt2020-10"), Some("bt2020"));
+    node.add_filter(&config, &None).await;
+    let f = node.filter.unwrap().cmd();
+
+    assert!(f[1].contains("zscale=p=bt709:t=bt709:m=bt709:r=tv"));
+    assert!(!f[1].contains("tonemap"));
+
+    let mut node = media(Some("bt709"), Some("bt709"));
+    node.add_filter(&config, &None).await;
+    assert!(!node.filter.unwrap().cmd().join(" ").contains("zscale"));
+
+    config.processing.tonemap = false;
+    let mut node = media(Some("arib-std-b67"), Some("bt2020"));
+    node.add_filter(&config, &None).await;
+    assert!(!node.filter.unwrap().cmd().join(" ").contains("zscale"));
+}
+
+#[tokio::test]
+async fn interlaced_output() {
+    let (mut config, _) = get_config().await;
+
+    config.output.mode = Stream;
+    config.output.field_order = FieldOrder::Tff;
+    config.processing.fps = 50.0;
+
+    let mut media = Media {
+        unit: Encoder,
+        ..Media::default()
+    };
+    media.add_filter(&config, &None).await;
+
+    let f = media.filter.unwrap().cmd();
+
+    assert!(f[1].ends_with(",tinterlace=mode=interleave_top,fieldorder=tff[vout0]"));
+
+    config.output.field_order = FieldOrder::Bff;
+    config.processing.fps = 25.0;
+
+    let mut media = Media {
+        unit: Encoder,
+        ..Media::default()
+    };
+    media.add_filter(&config, &None).await;
+
+    assert!(media.filter.unwrap().cmd()[1].ends_with(",setfield=bff[vout0]"));
+
+    let mut cmd = vec_strings![
+        "-c:v", "libx264", "-f", "mpegts", "out1.ts", "-f", "mpegts", "out2.ts"
+    ];
+    interlace_flags(FieldOrder::Tff, &mut cmd);
+
+    assert_eq!(
+        cmd,
+        vec_strings![
+            "-flags",
+            "+ildct+ilme",
+            "-top",
+            "1",
+            "-c:v",
+            "libx264",
+            "-f",
+            "mpegts",
+            "out1.ts",
+            "-flags",
+            "+ildct+ilme",
+            "-top",
+            "1",
+            "-f",
+            "mpegts",
+            "out2.ts"
+        ]
+    );
+
+    let mut cmd = vec_strings![
+        "-c:v",
+        "libx264",
+        "-f",
+        "mpegts",
+        "out1.ts",
+        "-c:v",
+        "libvpx",
+        "-f",
+        "webm",
+        "out2.webm"
+    ];
+    caption_flags(&mut cmd);
+
+    assert_eq!(
+        cmd,
+        vec_strings![
+            "-c:v",
+            "libx264",
+            "-a53cc",
+            "1",
+            "-f",
+            "mpegts",
+            "out1.ts",
+            "-c:v",
+            "libvpx",
+            "-f",
+            "webm",
+            "out2.webm"
+        ]
+    );
+}
+
+#[test]
+fn hls_subtitle_rendition() {
+    let mut cmd = vec_strings!["-c:v", "libx264", "-f", "hls", "live/stream.m3u8"];
+    subtitle_rendition(&mut cmd, 1);
+
+    assert_eq!(
+        cmd,
+        vec_strings![
+            "-c:v",
+            "libx264",
+            "-f",
+            "hls",
+            "-var_stream_map",
+            "v:0,a:0,s:0,sgroup:subs,sname:Subtitles,default:YES",
+            "-master_pl_name",
+            "master.m3u8",
+            "live/stream.m3u8"
+        ]
+    );
+
+    let mut cmd = vec_strings![
+        "-f",
+        "hls",
+        "-var_stream_map",
+        "v:0,a:0,name:720p",
+        "live/stream_%v.m3u8"
+    ];
+    subtitle_rendition(&mut cmd, 2);
+    assert_eq!(
+        cmd[3],
+        "v:0,a:0,name:720p,s:0,sgroup:subs,sname:Subtitles,default:YES"
+    );
+
+    // more variants, and a map with subtitles, stay as they are
+    for map in ["v:0,a:0 v:1,a:1", "v:0,a:0,s:0,sgroup:subs"] {
+        let mut cmd = vec_strings!["-f", "hls", "-var_stream_map", map, "live/stream_%v.m3u8"];
+        subtitle_rendition(&mut cmd, 1);
+        assert_eq!(cmd[3], map);
+    }
+
+    let mut cmd = vec_strings!["-f", "hls", "live/stream.m3u8"];
+    subtitle_rendition(&mut cmd, 2);
+    assert_eq!(cmd.len(), 3);
+}
+
+#[tokio::test]
+async fn timecode_filtering() {
+    let (mut config, _) = get_config().await;
+
+    config.output.mode = Stream;
+    config.processing.timecode_burn_in = true;
+    config.processing.audio_channels = 4;
+    config.processing.ltc_channel = 4;
+
+    let mut media = Media {
+        source: "./assets/storage/media_mix/with_audio.mp4".to_string(),
+        out: 30.0,
+        duration: 30.0,
+        probe: Some(MediaProbe {
+            audio: vec![AudioStream {
+                channels: 2,
+                duration: Some(30.0),
+                ..AudioStream::default()
+            }],
+            ..MediaProbe::default()
+        }),
+        ..Media::default()
+    };
+    media.add_filter(&config, &None).await;
+
+    let f = media.filter.unwrap().cmd();
+    let (video, audio) = f[1].split_once(";[0:a:0]").unwrap();
+
+    assert!(video.contains("drawtext=timecode='"));
+    assert!(audio.contains("aformat=sample_rates=48000:channel_layouts=4.0[ltc_prg];aevalsrc='"));
+    assert!(audio.ends_with("amerge=inputs=2,pan=4c|c0=c0|c1=c1|c2=c2|c3=c4[aout0]"));
+
+    let time = chrono::NaiveTime::from_hms_milli_opt(10, 5, 30, 520).unwrap();
+
+    assert_eq!(timecode(&time, 25), "10:05:30:13");
+    assert!(ltc_expr(&time, 25).starts_with("st(0,(t+36330.52)*25);"));
+}
+
+#[tokio::test]
+async fn live_control_filtering() {
+    let (mut config, _) = get_config().await;
+
+    config.output.mode = Stream;
+    config.processing.live_control = true;
+    config.processing.zmq_stream_socket = Some("127.0.0.1:5555".to_string());
+    config.processing.add_logo = true;
+    let logo_path = fs::canonicalize("./assets/storage/logo.png").unwrap();
+    config.processing.logo_path = logo_path.to_string_lossy().to_string();
+
+    let mut media = Media {
+        source: "./assets/storage/media_mix/with_audio.mp4".to_string(),
+        out: 30.0,
+        duration: 30.0,
+        probe: Some(MediaProbe {
+            audio: vec![AudioStream {
+                channels: 2,
+                duration: Some(30.0),
+                ..AudioStream::default()
+            }],
+            ..MediaProbe::default()
+        }),
+        ..Media::default()
+    };
+    media.add_filter(&config, &None).await;
+
+    let f = media.filter.unwrap().cmd();
+
+    assert!(f[1].contains("colorchannelmixer@logo=aa=0.7"));
+    assert!(f[1].contains("zmq=b=tcp\\\\://'127.0.0.1\\:5555'[vout0]"));
+    assert!(f[1].contains("volume@volume0=0.05"));
+
+    let commands = live_commands(
+        &mut config,
+        &FilterParams {
+            logo_opacity: Some(1.5),
+            volume: Some(0.8),
+        },
+    );
+
+    assert_eq!(
+        commands,
+        vec!["colorchannelmixer@logo aa 1", "volume@volume0 volume 0.8"]
+    );
+    assert_eq!(config.processing.volume, 0.8);
+}
+
+#[test]
+fn daypart_audio_chains() {
+    let (chains, errors) = parse_chains(
+        "# loud\ndrive = acompressor=threshold=-18dB:ratio=4,alimiter=limit=0.9; night=dynaudnorm\nbroken",
+    );
+
+    assert_eq!(
+        chains.get("drive").map(String::as_str),
+        Some("acompressor=threshold=-18dB:ratio=4,alimiter=limit=0.9")
+    );
+    assert_eq!(errors.len(), 1);
+
+    let (dayparts, errors) = parse_dayparts(
+        "06:00-10:00 = drive\n22:00-06:00=night\n10:00-12:00=morning\n25:00-26:00=night",
+        &chains,
+    );
+
+    assert_eq!(dayparts.len(), 2);
+    assert_eq!(errors.len(), 2);
+    assert!(dayparts[0].contains(8.0 * 3600.0));
+    assert!(!dayparts[0].contains(10.0 * 3600.0));
+    // over midnight
+    assert!(dayparts[1].contains(23.0 * 3600.0));
+    assert!(dayparts[1].contains(86400.0 + 3600.0));
+    assert!(!dayparts[1].contains(12.0 * 3600.0));
+
+    let mut config = PlayoutConfig::default();
+    config.processing.audio_chains = "drive = alimiter=limit=0.9".to_string();
+    config.processing.audio_dayparts = "16:00-19:00 = drive".to_string();
+
+    assert_eq!(
+        chain_at(&config, 17.0 * 3600.0),
+        Some("alimiter=limit=0.9".to_string())
+    );
+    assert_eq!(chain_at(&config, 20.0 * 3600.0), None);
+    assert!(audio_chains::check(&config).is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn news_ticker() {
+    let rss = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>News</title>
+  <item><title>Storm warning for the coast</title><link>https://example.org/1</link></item>
+  <item><title><![CDATA[Elections: turnout  at 60%]]></title></item>
+  <item><title>Trains &amp; buses on strike</title></item>
+</channel></rss>"#;
+
+    let items = parse_feed(rss);
+    assert_eq!(
+        items,
+        vec![
+            "Storm warning for the coast",
+            "Elections: turnout at 60%",
+            "Trains & buses on strike"
+        ]
+    );
+    assert_eq!(
+        ticker_text(&items[..2], " +++ "),
+        "Storm warning for the coast +++ Elections: turnout at 60% +++ "
+    );
+    assert_eq!(ticker_text(&[], " +++ "), "");
+
+    let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>Feed</title>
+<entry><title type="text">First</title></entry><entry><title>Second</title></entry></feed>"#;
+    assert_eq!(parse_feed(atom), vec!["First", "Second"]);
+
+    assert_eq!(parse_feed(r#"["one", " two "]"#), vec!["one", "two"]);
+    assert_eq!(
+        parse_feed(
+            r#"{"version": "https://jsonfeed.org/version/1.1", "items": [{"title": "A"}, {"headline": "B"}, {"id": 3}]}"#
+        ),
+        vec!["A", "B"]
+    );
+    assert!(parse_feed("not a feed").is_empty());
+
+    assert!(active("", 3.0 * 3600.0));
+    assert!(active("06:00-09:00; 17:00-23:00", 18.0 * 3600.0));
+    assert!(!active("06:00-09:00; 17:00-23:00", 12.0 * 3600.0));
+    assert!(active("22:00-02:00", 3600.0));
+
+    let (mut config, _) = get_config().await;
+    config.output.zmq_socket = Some("127.0.0.1:5557".to_string());
+    config.text.font_path = String::new();
+    config.text.ticker_speed = 80;
+    config.text.ticker_style = "y=h-th-20:fontsize=30".to_string();
+
+    assert_eq!(ticker::filter(&config), None);
+
+    config.text.ticker_url = "https://example.org/rss".to_string();
+
+    assert_eq!(
+        ticker::filter(&config).unwrap(),
+        "drawtext@ticker=text='':x='w-mod(t*80,w+tw)':y=h-th-20:fontsize=30"
+    );
+}
+
+#[tokio::test]
+async fn data_widget() {
+    let data = serde_json::json!({
+        "city": "Berlin",
+        "current": {"temp": 21.5, "wind": null, "rain": false},
+        "hours": [{"temp": 20}, {"temp": 19}]
+    });
+    let fields = flatten(&data);
+
+    assert_eq!(fields["city"], "Berlin");
+    assert_eq!(fields["current.temp"], "21.5");
+    assert_eq!(fields["current.wind"], "");
+    assert_eq!(fields["current.rain"], "false");
+    assert_eq!(fields["hours.1.temp"], "19");
+    assert_eq!(
+        graphics::fill("{city}: {current.temp}°C", &fields),
+        "Berlin: 21.5°C"
+    );
+
+    let (config, _) = get_config().await;
+    let clock = clock_fields(&config);
+
+    assert_eq!(clock["time"].len(), 5);
+    assert_eq!(clock["date"].len(), 10);
+
+    let mut graphic = Graphic {
+        text: Some(TextFilter {
+            text: Some("{name}".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    assert!(!graphic.is_widget());
+
+    graphic.text.as_mut().unwrap().text = Some("{time}".to_string());
+    assert!(graphic.is_widget());
+
+    let weather: Graphic =
+        serde_json::from_str(r#"{"url": "https://example.org/weather.json", "visible": true}"#)
+            .unwrap();
+    assert!(weather.is_widget());
+    assert!(weather.visible);
+    assert_eq!(weather.interval, 300);
+}
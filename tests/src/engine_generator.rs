@@ -1,8 +1,11 @@
+mod common;
+
 use std::env;
 
 use chrono::NaiveTime;
 use sqlx::sqlite::SqlitePoolOptions;
 
+use common::clip;
 use ffplayout::db::handles;
 use ffplayout::player::{controller::ChannelManager, utils::*};
 use ffplayout::utils::config::ProcessMode::Playlist;
@@ -152,6 +155,7 @@ async fn test_generate_playlist_from_template() {
                 paths: vec![current_path.join("assets/storage")],
             },
         ],
+        ident: None,
     });
     config.processing.mode = Playlist;
     config.storage.filler = "assets/storage/media_filler".into();
@@ -172,3 +176,21 @@ async fn test_generate_playlist_from_template() {
         "total_duration is {total_duration}"
     );
 }
+
+#[test]
+fn test_insert_idents() {
+    let ident = clip("ident.mp4", None, 10.0);
+    let list = vec![
+        clip("", None, 1800.0),
+        clip("", None, 1790.0),
+        clip("", None, 1800.0),
+        clip("", None, 1800.0),
+    ];
+
+    // block starts at 05:00 and has two hour boundaries
+    let new_list = insert_idents(list, &ident, 3600.0, 18000.0, 2.0);
+
+    assert_eq!(new_list.len(), 6);
+    assert_eq!(new_list[3].source, "ident.mp4");
+    assert_eq!(new_list[5].source, "ident.mp4");
+}
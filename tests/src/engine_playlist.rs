@@ -1,19 +1,57 @@
-use std::{env, sync::atomic::Ordering};
+mod common;
 
+use std::{
+    env, fs,
+    path::PathBuf,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use chrono::NaiveDate;
 use serial_test::serial;
 use sqlx::sqlite::SqlitePoolOptions;
+use tokio::process::Command;
 
+use common::clip;
 use ffplayout::{
     db::handles,
     player::{
         controller::ChannelManager,
+        input::{
+            CurrentProgram,
+            playlist::{
+                Bumper, HOLD, NEXT_CARD, NEXT_CARD_LENGTH, bumper_at, catch_up_speed, drop_filler,
+                hard_overrun, insert_next_card, merge_program, skip_late,
+            },
+            watch_playlist,
+        },
         output::player,
-        utils::{get_delta, is_close, time_in_seconds},
+        utils::{
+            ItemType, Media, Origin, StartMode, gen_next_card, get_delta,
+            import::*,
+            is_close, is_filler, is_generated,
+            json_serializer::{
+                JsonPlaylist, expand_loops, fit_hard_starts, loop_pieces, next_hard_start,
+                set_defaults, slot_speed,
+            },
+            probe,
+            program::{program_at, programs},
+            quarantine::Quarantine,
+            time_in_seconds,
+            warnings::{PlaylistWarnings, WarningKind, short_item, stream_mismatch},
+        },
     },
     utils::{
-        config::{PlayoutConfig, ProcessMode::Playlist},
+        as_run::{AsRunItem, AsRunStatus, compare},
+        calendar::Calendar,
+        config::{JoinPolicy, PlayoutConfig, ProcessMode::Playlist},
+        control::{
+            InsertPolicy, ItemStatus, PlayerCtl, control_state, file_index, insert_media,
+            replace_media, resume_media,
+        },
         time_machine::set_mock_time,
     },
+    vec_strings,
 };
 
 async fn prepare_config() -> (PlayoutConfig, ChannelManager) {
@@ -69,6 +107,19 @@ async fn timed_stop(sec: u64, manager: ChannelManager) {
     manager.stop_all(false).await;
 }
 
+/// Hard start news item of ten minutes at `time`.
+fn news(time: &str, begin: Option<f64>) -> Media {
+    Media {
+        start: StartMode::Hard,
+        time: Some(time.to_string()),
+        ..clip("news", begin, 600.0)
+    }
+}
+
+fn sources(program: &[Media]) -> Vec<&str> {
+    program.iter().map(|m| m.source.as_str()).collect()
+}
+
 #[tokio::test]
 #[serial]
 #[ignore]
@@ -139,6 +190,186 @@ async fn playlist_next_missing() {
     assert_eq!(playlist_date, "2023-02-10");
 }
 
+#[tokio::test]
+#[serial]
+async fn playlist_missing_appears() {
+    let (mut config, manager) = prepare_config().await;
+    let dir = env::temp_dir().join("ffplayout_missing_playlist");
+    let path = dir.join("2023/02/2023-02-10.json");
+
+    let _ = fs::remove_dir_all(&dir);
+
+    config.channel.playlists = dir.clone();
+    config.playlist.day_start = "00:00:00".into();
+    config.playlist.start_sec = Some(0.0);
+    config.playlist.length = "24:00:00".into();
+    config.playlist.length_sec = Some(86400.0);
+
+    manager.update_config(config.clone()).await;
+    manager.is_alive.store(true, Ordering::SeqCst);
+    manager.list_init.store(true, Ordering::SeqCst);
+
+    set_mock_time(&Some("2023-02-10T12:00:00+01:00".to_string())).unwrap();
+
+    let mut program = CurrentProgram::new(manager.clone()).await;
+    program.next().await;
+
+    assert_eq!(
+        *manager.missing_playlist.lock().await,
+        Some("2023-02-10".to_string())
+    );
+    assert!(manager.list_init.load(Ordering::SeqCst));
+
+    // the playlist comes in the middle of the day
+    let playlist = fs::read_to_string("assets/playlists/2023/02/2023-02-08.json")
+        .unwrap()
+        .replace("2023-02-08", "2023-02-10");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, playlist).unwrap();
+
+    let node = program.next().await.unwrap();
+
+    assert_eq!(*manager.missing_playlist.lock().await, None);
+    assert_eq!(*manager.current_date.lock().await, "2023-02-10");
+    // it starts with the clip at the current time
+    assert!(node.index.unwrap() > 0);
+    assert!(!manager.list_init.load(Ordering::SeqCst));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn playlist_watch_merge() {
+    let (mut config, manager) = prepare_config().await;
+    let dir = env::temp_dir().join("ffplayout_watch_playlist");
+    let path = dir.join("2023/02/2023-02-08.json");
+    let playlist = fs::read_to_string("assets/playlists/2023/02/2023-02-08.json").unwrap();
+
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, &playlist).unwrap();
+
+    config.channel.playlists = dir.clone();
+    config.playlist.day_start = "00:00:00".into();
+    config.playlist.start_sec = Some(0.0);
+    config.playlist.length = "24:00:00".into();
+    config.playlist.length_sec = Some(86400.0);
+
+    manager.update_config(config.clone()).await;
+    manager.is_alive.store(true, Ordering::SeqCst);
+    manager.list_init.store(true, Ordering::SeqCst);
+
+    set_mock_time(&Some("2023-02-08T12:00:00+01:00".to_string())).unwrap();
+
+    let mut program = CurrentProgram::new(manager.clone()).await;
+    program.next().await;
+
+    let (loaded, modified) = manager.loaded_playlist.lock().await.clone().unwrap();
+    assert_eq!(loaded, path.to_string_lossy());
+
+    let watcher = tokio::spawn(watch_playlist(manager.clone()));
+
+    // the last item changes
+    let index = playlist.rfind("media_mix/av_sync.mp4").unwrap();
+    let mut changed = playlist.clone();
+    changed.replace_range(
+        index..index + "media_mix/av_sync.mp4".len(),
+        "media_mix/with_audio.mp4",
+    );
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    fs::write(&path, changed).unwrap();
+    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+
+    let last = manager.current_list.lock().await.last().unwrap().clone();
+    let (_, merged) = manager.loaded_playlist.lock().await.clone().unwrap();
+
+    assert!(last.source.ends_with("media_mix/with_audio.mp4"));
+    assert_ne!(merged, modified);
+
+    // the iterator doesn't load the changed playlist again
+    program.next().await;
+
+    let last = manager.current_list.lock().await.last().unwrap().clone();
+    assert!(last.source.ends_with("media_mix/with_audio.mp4"));
+
+    manager.is_alive.store(false, Ordering::SeqCst);
+    watcher.await.unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn playlist_hold() {
+    let (mut config, manager) = prepare_config().await;
+
+    config.playlist.day_start = "00:00:00".into();
+    config.playlist.start_sec = Some(0.0);
+    config.playlist.length = "24:00:00".into();
+    config.playlist.length_sec = Some(86400.0);
+
+    manager.update_config(config.clone()).await;
+    manager.is_alive.store(true, Ordering::SeqCst);
+    manager.list_init.store(true, Ordering::SeqCst);
+
+    set_mock_time(&Some("2023-02-08T12:00:00+01:00".to_string())).unwrap();
+
+    let mut program = CurrentProgram::new(manager.clone()).await;
+    program.next().await;
+
+    let index = manager.current_index.load(Ordering::SeqCst);
+    manager.hold.store(true, Ordering::SeqCst);
+
+    // the schedule doesn't advance, while it is on hold
+    for _ in 0..2 {
+        let node = program.next().await.unwrap();
+
+        assert_eq!(node.category, HOLD);
+        assert!(node.cmd.is_some());
+        assert_eq!(manager.current_index.load(Ordering::SeqCst), index);
+    }
+
+    manager.hold.store(false, Ordering::SeqCst);
+
+    let node = program.next().await.unwrap();
+
+    assert_eq!(node.index, Some(index));
+    assert_eq!(manager.current_index.load(Ordering::SeqCst), index + 1);
+}
+
+#[tokio::test]
+#[serial]
+async fn release_ends_hold_clip() {
+    let (_, manager) = prepare_config().await;
+
+    manager.hold.store(true, Ordering::SeqCst);
+    manager
+        .set_current(&Media {
+            category: HOLD.to_string(),
+            ..Media::default()
+        })
+        .await;
+    *manager.decoder.lock().await = Some(Command::new("sleep").arg("60").spawn().unwrap());
+
+    let start = Instant::now();
+    let data = control_state(&manager.db_pool, &manager, &PlayerCtl::Release)
+        .await
+        .unwrap();
+
+    // the hold clip doesn't play to its end
+    assert_eq!(data["operation"], "release_hold");
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(
+        manager
+            .decoder
+            .lock()
+            .await
+            .as_mut()
+            .is_none_or(|p| p.try_wait().unwrap().is_some())
+    );
+    assert!(!manager.hold.load(Ordering::SeqCst));
+}
+
 #[tokio::test]
 #[serial]
 #[ignore]
@@ -347,3 +578,847 @@ async fn playlist_change_at_six() {
 
     assert_eq!(playlist_date, "2023-02-09");
 }
+
+#[test]
+fn import_csv_with_header() {
+    let content = "start;source;title;duration\n06:00:00;/media/a.mp4;\"Clip; A\";300\n06:05:00;/media/b.mp4;;00:10:00\n";
+    let items = parse_csv(content);
+
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].start, Some(21600.0));
+    assert_eq!(items[0].title, Some("Clip; A".to_string()));
+    assert_eq!(items[0].duration, Some(300.0));
+    assert_eq!(items[1].source, "/media/b.mp4".to_string());
+    assert_eq!(items[1].title, None);
+    assert_eq!(items[1].duration, Some(600.0));
+}
+
+#[test]
+fn import_csv_without_header() {
+    let items = parse_csv("06:00:00,/media/a.mp4,Clip A,300");
+
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].start, Some(21600.0));
+    assert_eq!(items[0].source, "/media/a.mp4".to_string());
+}
+
+#[test]
+fn import_extended_m3u() {
+    let content = r#"#EXTM3U
+#EXTINF:300 start="06:00:00",Clip A
+/media/a.mp4
+#EXT-X-PROGRAM-DATE-TIME:2024-01-01T06:05:00+01:00
+#EXTINF:120,Clip B
+/media/b.mp4
+/media/c.mp4
+"#;
+    let items = parse_m3u(content);
+
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0].start, Some(21600.0));
+    assert_eq!(items[0].duration, Some(300.0));
+    assert_eq!(items[0].title, Some("Clip A".to_string()));
+    assert_eq!(items[1].start, Some(21900.0));
+    assert_eq!(items[2].start, None);
+    assert_eq!(items[2].title, None);
+}
+
+#[tokio::test]
+async fn import_items_to_media() {
+    let items = parse_csv(
+        "start,source,duration\n06:00:00,/media/a.mp4,300\n06:02:00,/media/b.mp4,60\n06:03:00,/media/c.mp4,0",
+    );
+    let program = import_to_media(items, false).await;
+
+    assert_eq!(program.len(), 2);
+    assert_eq!(program[0].out, 120.0);
+    assert_eq!(program[0].duration, 300.0);
+    assert_eq!(program[1].out, 60.0);
+
+    // a gap to the next start gets filled at load
+    let items =
+        parse_csv("start,source,duration\n06:00:00,/media/a.mp4,60\n06:05:00,/media/b.mp4,60");
+    let mut program = import_to_media(items, false).await;
+
+    assert_eq!(program[1].start, StartMode::Hard);
+    assert_eq!(program[1].time.as_deref(), Some("06:05:00.000"));
+
+    fit_hard_starts(&PlayoutConfig::default(), &mut program, 0, 21600.0);
+
+    assert_eq!(program.len(), 3);
+    assert_eq!(program[1].category, "filler");
+    assert_eq!(program[1].out, 240.0);
+    assert_eq!(program[2].begin, Some(21900.0));
+}
+
+#[test]
+fn import_time_values() {
+    assert_eq!(parse_time("06:30:15"), Some(23415.0));
+    assert_eq!(parse_time("06:30:15.5"), Some(23415.5));
+    assert_eq!(parse_time("06:30:15-05:00"), Some(23415.0));
+    assert_eq!(parse_time("2024-01-01T06:30:15Z"), Some(23415.0));
+    assert_eq!(parse_time("2024-01-01T06:30:15.250+01:00"), Some(23415.25));
+    assert_eq!(parse_time("2024-01-01 06:30:15"), Some(23415.0));
+    assert_eq!(parse_time("30:00:00"), Some(108000.0));
+    assert_eq!(parse_time("90"), Some(90.0));
+    assert_eq!(parse_time("06:30:15 EST"), None);
+    assert_eq!(parse_time(""), None);
+}
+
+#[test]
+fn import_xspf() {
+    let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <trackList>
+    <track>
+      <location>file:///media/my%20clip.mp4</location>
+      <creator>Artist</creator>
+      <title>Rock &amp; Roll</title>
+      <duration>185000</duration>
+    </track>
+    <track>
+      <location>https://example.org/stream.mp4</location>
+    </track>
+  </trackList>
+</playlist>"#;
+    let items = parse_xspf(content);
+
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].source, "/media/my clip.mp4".to_string());
+    assert_eq!(items[0].title, Some("Artist - Rock & Roll".to_string()));
+    assert_eq!(items[0].duration, Some(185.0));
+    assert_eq!(
+        items[1].source,
+        "https://example.org/stream.mp4".to_string()
+    );
+    assert_eq!(items[1].title, None);
+}
+
+#[test]
+fn import_cue_sheet() {
+    let content = r#"PERFORMER "Various"
+TITLE "Mix"
+FILE "/media/mix.wav" WAVE
+  TRACK 01 AUDIO
+    TITLE "Intro"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Second"
+    PERFORMER "Band"
+    INDEX 00 03:58:00
+    INDEX 01 04:00:37
+  TRACK 03 AUDIO
+    TITLE "Outro"
+    INDEX 01 10:00:00
+"#;
+    let items = parse_cue(content, None);
+
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0].source, "/media/mix.wav".to_string());
+    assert_eq!(items[0].title, Some("Various - Intro".to_string()));
+    assert_eq!(items[0].seek, Some(0.0));
+    assert_eq!(items[0].out, Some(240.0 + 37.0 / 75.0));
+    assert_eq!(items[1].title, Some("Band - Second".to_string()));
+    assert_eq!(items[1].out, Some(600.0));
+    assert_eq!(items[2].out, None);
+}
+
+#[test]
+fn calendar_rules() {
+    let calendar: Calendar = serde_json::from_str(
+        r#"{
+            "rules": [
+                { "weekdays": ["Sat"], "every_weeks": 2, "from": "2025-01-04", "template": { "sources": [] } },
+                { "weekdays": ["Mon", "Tue", "Wed", "Thu", "Fri"], "template": { "sources": [
+                    { "start": "06:00:00", "duration": "12:00:00", "shuffle": true, "paths": ["weekday"] }
+                ] } }
+            ],
+            "dates": [
+                { "date": "2025-12-25", "template": { "sources": [
+                    { "start": "06:00:00", "duration": "12:00:00", "shuffle": false, "paths": ["christmas"] }
+                ] } }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    let weekday = calendar.template_for("2025-01-06").unwrap();
+    assert_eq!(weekday.sources[0].paths[0].to_string_lossy(), "weekday");
+
+    let special = calendar.template_for("2025-12-25").unwrap();
+    assert_eq!(special.sources[0].paths[0].to_string_lossy(), "christmas");
+
+    assert!(calendar.template_for("2025-01-04").is_some());
+    assert!(calendar.template_for("2025-01-11").is_none());
+    assert!(calendar.template_for("2025-01-18").is_some());
+    assert!(calendar.template_for("2025-01-05").is_none());
+}
+
+#[test]
+fn merge_changed_program() {
+    let current = vec![
+        clip("a", None, 10.0),
+        clip("b", None, 10.0),
+        clip("c", None, 10.0),
+        clip("d", None, 10.0),
+    ];
+
+    // "b" is playing, future item "c" got replaced
+    let new = vec![
+        clip("a", None, 10.0),
+        clip("b", None, 10.0),
+        clip("x", None, 10.0),
+        clip("d", None, 10.0),
+    ];
+    let (program, conflict) = merge_program(&current, new, 2);
+
+    assert!(!conflict);
+    assert_eq!(program[2].source, "x");
+    assert_eq!(program[3].index, Some(3));
+
+    // item before the playing one got removed, this change is ignored
+    let new = vec![
+        clip("b", None, 10.0),
+        clip("y", None, 10.0),
+        clip("d", None, 10.0),
+    ];
+    let (program, conflict) = merge_program(&current, new, 2);
+
+    assert!(conflict);
+    assert_eq!(sources(&program), vec!["a", "b", "y", "d"]);
+}
+
+#[test]
+fn merge_keeps_runtime_items() {
+    let mut current = vec![
+        clip("a", None, 10.0),
+        clip("b", None, 10.0),
+        clip("c", None, 10.0),
+        clip("d", None, 10.0),
+    ];
+
+    // a bumper was played, "x" is inserted after the playing "b"
+    current.insert(
+        1,
+        Media {
+            category: "bumper".to_string(),
+            origin: Origin::Inserted,
+            ..clip("bumper", None, 10.0)
+        },
+    );
+    insert_media(
+        &mut current,
+        3,
+        clip("x", None, 10.0),
+        InsertPolicy::Shift,
+        |_| false,
+    );
+
+    // "c" got replaced in the file
+    let new = vec![
+        clip("a", None, 10.0),
+        clip("b", None, 10.0),
+        clip("y", None, 10.0),
+        clip("d", None, 10.0),
+    ];
+    let (program, conflict) = merge_program(&current, new, 3);
+
+    assert!(!conflict);
+    assert_eq!(sources(&program), vec!["a", "bumper", "b", "x", "y", "d"]);
+
+    // the schedule resumes with "d", "c" stays dropped
+    let mut current = vec![
+        clip("a", None, 10.0),
+        clip("b", None, 10.0),
+        clip("c", None, 10.0),
+        clip("d", None, 10.0),
+        clip("e", None, 10.0),
+    ];
+    resume_media(&mut current, 2, 3, 25.0, 20.0).unwrap();
+
+    let new = vec![
+        clip("a", None, 10.0),
+        clip("b", None, 10.0),
+        clip("c", None, 10.0),
+        clip("d", None, 10.0),
+        clip("f", None, 10.0),
+    ];
+    let (program, conflict) = merge_program(&current, new, 2);
+
+    assert!(!conflict);
+    assert_eq!(sources(&program), vec!["a", "b", "", "d", "f"]);
+}
+
+#[test]
+fn underrun_recovery() {
+    let mut list = vec![
+        clip("a", Some(0.0), 60.0),
+        clip("filler", Some(60.0), 5.0),
+        clip("b", Some(65.0), 60.0),
+        clip("filler", Some(125.0), 30.0),
+    ];
+    let is_filler = |m: &Media| m.source == "filler";
+
+    let recovered = drop_filler(&mut list, 1, 8.0, is_filler);
+
+    assert_eq!(recovered, 8.0);
+    assert_eq!(list.len(), 3);
+    assert_eq!(list[2].out, 27.0);
+    assert_eq!(list[2].begin, Some(128.0));
+    assert_eq!(list[2].index, Some(2));
+
+    assert_eq!(catch_up_speed(100.0, 5.0, 1.1), 100.0 / 95.0);
+    assert_eq!(catch_up_speed(100.0, 50.0, 1.1), 1.1);
+    assert_eq!(catch_up_speed(10.0, 20.0, 0.9), 1.0);
+}
+
+#[test]
+fn skip_late_item() {
+    let promo = Media {
+        source: "promo.mp4".to_string(),
+        skip_late: Some(30.0),
+        ..Media::default()
+    };
+
+    assert!(!skip_late(&promo, 20.0));
+    assert!(skip_late(&promo, 45.0));
+
+    let hard = Media {
+        start: StartMode::Hard,
+        ..promo.clone()
+    };
+
+    assert!(!skip_late(&hard, 45.0));
+    assert!(!skip_late(&Media::default(), 3600.0));
+
+    let item: Media = serde_json::from_str(
+        r#"{"in": 0, "out": 20, "duration": 20, "source": "promo.mp4", "skip_late": 30}"#,
+    )
+    .unwrap();
+
+    assert_eq!(item.skip_late, Some(30.0));
+}
+
+#[test]
+fn insert_next_clip() {
+    let list = vec![
+        clip("a", Some(0.0), 60.0),
+        clip("b", Some(60.0), 60.0),
+        clip("filler", Some(120.0), 30.0),
+        clip("filler", Some(150.0), 60.0),
+        clip("c", Some(210.0), 60.0),
+    ];
+    let is_filler = |m: &Media| m.source == "filler";
+
+    let mut shifted = list.clone();
+    let recovered = insert_media(
+        &mut shifted,
+        1,
+        clip("news", Some(0.0), 45.0),
+        InsertPolicy::Shift,
+        is_filler,
+    );
+
+    assert_eq!(recovered, 0.0);
+    assert_eq!(shifted[1].begin, Some(60.0));
+    assert_eq!(shifted[5].begin, Some(255.0));
+
+    let mut dropped = list;
+    let recovered = insert_media(
+        &mut dropped,
+        2,
+        clip("news", Some(0.0), 45.0),
+        InsertPolicy::DropFiller,
+        is_filler,
+    );
+
+    assert_eq!(recovered, 45.0);
+    assert_eq!(dropped.len(), 5);
+    assert_eq!(dropped[3].out, 45.0);
+    assert_eq!(dropped[4].begin, Some(210.0));
+    assert_eq!(dropped[4].index, Some(4));
+}
+
+#[test]
+fn resume_from_item() {
+    let list = vec![
+        clip("live", Some(0.0), 600.0),
+        clip("a", Some(600.0), 600.0),
+        clip("b", Some(1200.0), 600.0),
+        clip("c", Some(1800.0), 600.0),
+    ];
+
+    // the live item runs 30 minutes over, resume with c now
+    let mut now = list.clone();
+    assert_eq!(resume_media(&mut now, 1, 3, 2400.0, 2400.0), Ok(1));
+    assert_eq!(now.len(), 2);
+    assert_eq!(now[1].source, "c");
+    assert_eq!(now[1].begin, Some(2400.0));
+    assert_eq!(now[1].index, Some(1));
+
+    // c begins later, a filler gap plays until then
+    let mut later = list.clone();
+    assert_eq!(resume_media(&mut later, 1, 3, 2700.0, 2400.0), Ok(2));
+    assert_eq!(later[1].category, "filler");
+    assert_eq!(later[1].out, 300.0);
+    assert_eq!(later[2].begin, Some(2700.0));
+
+    // b should have begun before, it gets trimmed
+    let mut earlier = list.clone();
+    assert_eq!(resume_media(&mut earlier, 1, 2, 2300.0, 2400.0), Ok(1));
+    assert_eq!(earlier[1].seek, 100.0);
+    assert_eq!(earlier[1].begin, Some(2400.0));
+    assert_eq!(earlier[2].begin, Some(2900.0));
+
+    let mut over = list.clone();
+    assert!(resume_media(&mut over, 1, 2, 1000.0, 2400.0).is_err());
+    assert!(resume_media(&mut over, 1, 4, 2400.0, 2400.0).is_err());
+    assert_eq!(over, list);
+}
+
+#[test]
+fn edit_program_item() {
+    let mut list = vec![
+        clip("/media/a.mp4", Some(0.0), 60.0),
+        clip("/media/b.mp4", Some(60.0), 60.0),
+        clip("/media/c.mp4", Some(120.0), 60.0),
+    ];
+
+    assert_eq!(ItemStatus::new(0, 2), ItemStatus::Played);
+    assert_eq!(ItemStatus::new(1, 2), ItemStatus::Playing);
+    assert_eq!(ItemStatus::new(2, 2), ItemStatus::Pending);
+
+    replace_media(&mut list, 1, clip("/media/d.mp4", Some(0.0), 90.0));
+
+    assert_eq!(list[1].begin, Some(60.0));
+    assert_eq!(list[1].index, Some(1));
+    assert_eq!(list[2].begin, Some(150.0));
+
+    let mut config = PlayoutConfig::default();
+    config.storage.path = PathBuf::from("/media");
+    let program = vec![
+        clip("c.mp4", Some(0.0), 60.0),
+        clip("a.mp4", Some(0.0), 60.0),
+        clip("c.mp4", Some(0.0), 60.0),
+    ];
+
+    assert_eq!(file_index(&config, &program, &list[2], 2), Some(2));
+    assert_eq!(file_index(&config, &program, &list[2], 0), Some(0));
+    assert_eq!(file_index(&config, &program, &list[1], 1), None);
+}
+
+#[test]
+fn fit_hard_start_items() {
+    let config = PlayoutConfig::default();
+
+    // content before the news is 100 seconds to long
+    let mut program = vec![
+        clip("a", None, 1800.0),
+        clip("b", None, 1900.0),
+        news("07:00:00", None),
+        clip("c", None, 600.0),
+    ];
+    fit_hard_starts(&config, &mut program, 0, 21600.0);
+
+    assert_eq!(program[1].out, 1800.0);
+    assert_eq!(program[2].begin, Some(25200.0));
+    assert_eq!(program[3].begin, Some(25800.0));
+
+    // content before the news is 60 seconds to short
+    let mut program = vec![
+        clip("a", None, 3540.0),
+        news("07:00:00", None),
+        clip("c", None, 600.0),
+    ];
+    fit_hard_starts(&config, &mut program, 0, 21600.0);
+
+    assert_eq!(program.len(), 4);
+    assert_eq!(program[1].category, "filler");
+    assert_eq!(program[1].out, 60.0);
+    assert!(is_generated(&program[1].source));
+    assert!(program[1].cmd.is_some());
+    assert_eq!(program[2].begin, Some(25200.0));
+    assert_eq!(program[3].index, Some(3));
+}
+
+#[test]
+fn join_next_hard_start() {
+    let mut program = vec![
+        clip("a", Some(21600.0), 1800.0),
+        clip("b", Some(23400.0), 1800.0),
+        news("07:00:00", Some(25200.0)),
+        clip("c", Some(25800.0), 600.0),
+    ];
+
+    assert_eq!(next_hard_start(&program, 0, 21600.0), Some((2, 25200.0)));
+    assert_eq!(next_hard_start(&program, 3, 21600.0), None);
+
+    // the playout starts 20 minutes into a, a filler gap plays until the news
+    let position = resume_media(&mut program, 0, 2, 25200.0, 22800.0);
+    assert_eq!(position, Ok(1));
+    assert_eq!(program[0].category, "filler");
+    assert_eq!(program[0].out, 2400.0);
+    assert_eq!(program[1].begin, Some(25200.0));
+
+    assert_eq!("next_hard".parse(), Ok(JoinPolicy::NextHard));
+    assert_eq!(JoinPolicy::Top.to_string(), "top");
+    assert!("late".parse::<JoinPolicy>().is_err());
+}
+
+#[test]
+fn hard_start_overrun() {
+    let program = vec![
+        clip("a", Some(21600.0), 1800.0),
+        clip("b", Some(23400.0), 1800.0),
+        news("07:00:00", Some(25200.0)),
+    ];
+
+    // "b" begins 45 seconds late, it runs into the news
+    assert_eq!(
+        hard_overrun(&program[1], &program, 1, 23445.0, 21600.0),
+        45.0
+    );
+    assert_eq!(
+        hard_overrun(&program[1], &program, 1, 23400.0, 21600.0),
+        0.0
+    );
+    assert_eq!(
+        hard_overrun(&program[0], &program, 0, 21700.0, 21600.0),
+        0.0
+    );
+    assert_eq!(
+        hard_overrun(&program[2], &program, 2, 25300.0, 21600.0),
+        0.0
+    );
+}
+
+#[tokio::test]
+async fn loop_until_clock_time() {
+    let config = PlayoutConfig::default();
+    let reel = Media {
+        item_type: ItemType::Loop,
+        until: Some("06:10:00".to_string()),
+        ..clip("/media/reel.mp4", None, 30.0)
+    };
+
+    let mut playlist = JsonPlaylist::new("2024-02-01".to_string(), 21600.0);
+    playlist.program = vec![
+        clip("/media/a.mp4", None, 100.0),
+        reel.clone(),
+        clip("/media/b.mp4", None, 50.0),
+    ];
+    playlist.start_sec = Some(21600.0);
+    expand_loops(&config, &mut playlist).await;
+
+    // 500 seconds: 16 full repetitions and 20 seconds from the last
+    assert_eq!(playlist.program.len(), 19);
+    assert!(
+        playlist.program[1..18]
+            .iter()
+            .all(|m| m.source == "/media/reel.mp4")
+    );
+    assert!(
+        playlist.program[1..18]
+            .iter()
+            .all(|m| m.item_type == ItemType::Clip)
+    );
+    assert_eq!(playlist.program[17].out, 20.0);
+
+    let length: f64 = playlist.program[..18].iter().map(|m| m.out - m.seek).sum();
+    assert_eq!(length, 600.0);
+
+    // a rest under one second is taken from the repetition before
+    let pieces = loop_pieces(&reel, &[clip("/media/reel.mp4", None, 30.0)], 60.5);
+
+    assert_eq!(pieces.len(), 3);
+    assert_eq!(pieces[1].out, 29.5);
+    assert_eq!(pieces[2].out, 1.0);
+
+    assert!(loop_pieces(&reel, &[clip("/media/empty.mp4", None, 0.0)], 60.0).is_empty());
+}
+
+#[test]
+fn fit_clip_into_slot() {
+    let mut config = PlayoutConfig::default();
+    config.playlist.slot_fit = 2.5;
+
+    // 24:30 episode into a 24:00 slot
+    let factor = slot_speed(1470.0, 1440.0, 2.5).unwrap();
+    assert!((factor - 1470.0 / 1440.0).abs() < 1e-9);
+    assert_eq!(slot_speed(1500.0, 1440.0, 2.5), None);
+    assert_eq!(slot_speed(1420.0, 1440.0, 0.0), None);
+
+    let episode = Media {
+        source: "/media/episode.mp4".to_string(),
+        out: 1470.0,
+        duration: 1470.0,
+        slot: Some(1440.0),
+        ..Media::default()
+    };
+    let next = Media {
+        source: "/media/next.mp4".to_string(),
+        out: 60.0,
+        duration: 60.0,
+        ..Media::default()
+    };
+
+    let mut playlist = JsonPlaylist::new("2024-02-01".to_string(), 21600.0);
+    playlist.program = vec![episode, next];
+    set_defaults(&config, &mut playlist);
+
+    assert_eq!(playlist.program[0].speed, Some(factor));
+    assert_eq!(playlist.program[0].out, 1470.0);
+    assert_eq!(playlist.program[1].begin, Some(23040.0));
+    assert_eq!(playlist.length, Some(1500.0));
+
+    // the speed can't change in copy mode
+    config.processing.copy_video = true;
+    set_defaults(&config, &mut playlist);
+
+    assert_eq!(playlist.program[0].speed, None);
+    assert_eq!(playlist.program[1].begin, Some(23070.0));
+}
+
+#[tokio::test]
+#[serial]
+async fn playlist_warnings() {
+    let (mut config, _) = prepare_config().await;
+    let video = |codec: &str, width: i64| probe::MediaProbe {
+        format: probe::MediaFormat {
+            duration: Some(60.0),
+            ..Default::default()
+        },
+        video: vec![probe::VideoStream {
+            codec_name: Some(codec.to_string()),
+            width: Some(width),
+            height: Some(width * 9 / 16),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+
+    assert_eq!(short_item(61.0, &video("h264", 1024)), None);
+    assert!(short_item(90.0, &video("h264", 1024)).is_some());
+
+    let mut reference = None;
+    let no_video = probe::MediaProbe::default();
+
+    assert!(stream_mismatch(&config, &no_video, &mut reference).is_some());
+    assert_eq!(
+        stream_mismatch(&config, &video("h264", 1024), &mut reference),
+        None
+    );
+    assert_eq!(
+        stream_mismatch(&config, &video("hevc", 1920), &mut reference),
+        None
+    );
+
+    config.processing.copy_video = true;
+    assert_eq!(
+        stream_mismatch(&config, &video("h264", 1024), &mut reference),
+        None
+    );
+    assert!(stream_mismatch(&config, &video("hevc", 1024), &mut reference).is_some());
+
+    let mut warnings = PlaylistWarnings::new("2024-02-01");
+    warnings.push(Some(3), 21600.0, "a.mp4", WarningKind::Short, "short");
+    warnings.push(Some(1), 21600.0, "b.mp4", WarningKind::Missing, "gone");
+    warnings.push(Some(2), 21600.0, "c.mp4", WarningKind::Missing, "gone");
+
+    assert_eq!(warnings.summary(), "2 missing, 1 short");
+    assert_eq!(warnings.warnings[0].begin, "06:00:00.000");
+
+    warnings.push(None, 86400.0, "", WarningKind::Length, "too short");
+
+    assert_eq!(warnings.lines()[0], "003 06:00:00.000 a.mp4: short");
+    assert_eq!(warnings.lines()[3], "--- 24:00:00.000: too short");
+}
+
+#[test]
+fn program_grouping() {
+    let item = |source: &str, duration: f64, category: &str, program_id: Option<&str>| Media {
+        category: category.to_string(),
+        program_id: program_id.map(str::to_string),
+        ..clip(source, None, duration)
+    };
+    let list = vec![
+        item("intro", 30.0, "", None),
+        item("show_1", 1500.0, "", Some("show")),
+        item("ad", 120.0, "advertisement", Some("show")),
+        item("show_2", 1380.0, "", Some("show")),
+        item("filler", 60.0, "", None),
+        item("next", 600.0, "", Some("next")),
+    ];
+
+    let groups = programs(&list);
+
+    assert_eq!(groups.len(), 4);
+    assert_eq!(groups[1].program_id, Some("show".to_string()));
+    assert_eq!(groups[1].index, 1);
+    assert_eq!(groups[1].items, 3);
+    assert_eq!(groups[1].duration, 3000.0);
+    assert_eq!(groups[1].breaks, 120.0);
+    assert_eq!(groups[3].index, 5);
+
+    assert_eq!(program_at(&list, 3), Some(groups[1].clone()));
+    assert_eq!(program_at(&list, 5), Some(groups[3].clone()));
+    assert_eq!(program_at(&list, 0), None);
+
+    let item: Media = serde_json::from_str(
+        r#"{"in": 0, "out": 10, "duration": 10, "source": "a.mp4", "block": "b1"}"#,
+    )
+    .unwrap();
+
+    assert_eq!(item.program_id, Some("b1".to_string()));
+}
+
+#[test]
+fn as_run_report() {
+    let aired = |index: usize, source: &str, begin: f64, duration: f64| AsRunItem {
+        index: Some(index),
+        source: source.to_string(),
+        begin,
+        duration,
+        ..AsRunItem::default()
+    };
+    let scheduled = vec![
+        clip("a", None, 60.0),
+        clip("ad", None, 30.0),
+        clip("b", None, 120.0),
+        clip("c", None, 60.0),
+        clip("d", None, 60.0),
+    ];
+    let as_run = vec![
+        aired(0, "a", 21600.0, 60.0),
+        aired(1, "ad", 21660.5, 30.0),
+        aired(2, "b", 21690.0, 40.0),
+        aired(2, "b", 21730.0, 80.0),
+        aired(3, "c", 21820.0, 30.0),
+    ];
+
+    let report = compare(21600.0, &scheduled, &as_run, 2.0);
+    let status = report.iter().map(|r| r.status).collect::<Vec<_>>();
+
+    assert_eq!(
+        status,
+        vec![
+            AsRunStatus::Ok,
+            AsRunStatus::Ok,
+            AsRunStatus::Ok,
+            AsRunStatus::Short,
+            AsRunStatus::Missed
+        ]
+    );
+    assert_eq!(report[1].delay, Some(0.5));
+    assert_eq!(report[2].duration, 120.0);
+    assert_eq!(report[4].aired, None);
+
+    let late = compare(
+        21600.0,
+        &scheduled[..1],
+        &[aired(0, "a", 21610.0, 60.0)],
+        2.0,
+    );
+
+    assert_eq!(late[0].status, AsRunStatus::Late);
+    assert_eq!(late[0].delay, Some(10.0));
+}
+
+#[test]
+fn quarantine_after_failures() {
+    let day = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+    let mut quarantine = Quarantine::new();
+
+    assert!(!quarantine.failed("/media/broken.mp4", day, 2));
+    quarantine.succeeded("/media/broken.mp4");
+    assert!(!quarantine.failed("/media/broken.mp4", day, 2));
+    assert!(!quarantine.contains("/media/broken.mp4", day));
+
+    assert!(quarantine.failed("/media/broken.mp4", day, 2));
+    assert!(quarantine.contains("/media/broken.mp4", day));
+    assert_eq!(quarantine.sources(), vec_strings!["/media/broken.mp4"]);
+
+    assert!(!quarantine.failed("/media/other.mp4", day, 0));
+    assert!(!quarantine.contains("/media/other.mp4", day));
+
+    assert!(!quarantine.contains("/media/broken.mp4", day.succ_opt().unwrap()));
+    assert!(quarantine.sources().is_empty());
+}
+
+#[test]
+fn break_bumpers() {
+    let media = |category: &str| Media {
+        category: category.to_string(),
+        ..Media::default()
+    };
+    let list = vec![
+        media(""),
+        media("advertisement"),
+        media("advertisement"),
+        media("filler"),
+        media("bumper"),
+        media("advertisement"),
+        media("bumper"),
+        media(""),
+    ];
+
+    assert_eq!(bumper_at(&list, 0), None);
+    assert_eq!(bumper_at(&list, 1), Some(Bumper::In));
+    assert_eq!(bumper_at(&list, 2), None);
+    assert_eq!(bumper_at(&list, 3), Some(Bumper::Out));
+    // bumpers are already in place
+    assert_eq!(bumper_at(&list, 5), None);
+    assert_eq!(bumper_at(&list, 6), None);
+    assert_eq!(bumper_at(&list, 7), None);
+    assert_eq!(bumper_at(&list, 8), None);
+    assert_eq!(bumper_at(&[media("advertisement")], 0), None);
+}
+
+#[tokio::test]
+async fn next_card_in_gap() {
+    let (config, _) = prepare_config().await;
+    let filler = |begin: f64, length: f64| Media {
+        category: "filler".to_string(),
+        ..clip("", Some(begin), length)
+    };
+    let mut list = vec![
+        clip("/storage/show.mp4", Some(0.0), 100.0),
+        filler(100.0, 50.0),
+        clip("/storage/evening_news.mp4", Some(150.0), 60.0),
+        filler(210.0, 10.5),
+        Media {
+            title: Some("Movie: Night".to_string()),
+            ..clip("/storage/movie.mp4", Some(220.5), 100.0)
+        },
+    ];
+    let filler = |m: &Media| is_filler(&config, m);
+
+    assert_eq!(insert_next_card(&mut list, 0, filler), None);
+    assert_eq!(
+        insert_next_card(&mut list, 1, filler),
+        Some("evening_news".to_string())
+    );
+    assert_eq!(list.len(), 6);
+    assert_eq!(list[1].out, 40.0);
+    assert_eq!(list[2].category, NEXT_CARD);
+    assert_eq!(list[2].begin, Some(140.0));
+    assert_eq!(list[2].out, NEXT_CARD_LENGTH);
+    assert_eq!(list[2].index, Some(2));
+    // a card is already in place
+    assert_eq!(insert_next_card(&mut list, 1, filler), None);
+
+    // the gap is not much longer than the card
+    assert_eq!(
+        insert_next_card(&mut list, 4, filler),
+        Some("Movie: Night".to_string())
+    );
+    assert_eq!(list.len(), 6);
+    assert_eq!(list[4].category, NEXT_CARD);
+    assert_eq!(list[4].out, 10.5);
+
+    let (source, cmd) = gen_next_card(&config, 10.0, "Movie: Night's", 20.0 * 3600.0 + 900.0);
+
+    assert!(is_generated(&source));
+    assert!(cmd[3].contains("text='Movie\\: Nights'"));
+    assert!(cmd[3].contains("text='at 20\\:15'"));
+}
@@ -0,0 +1,703 @@
+use std::{
+    env,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use serial_test::serial;
+use sqlx::sqlite::SqlitePoolOptions;
+use tokio::process::Child;
+
+use ffplayout::{
+    db::handles,
+    player::{
+        controller::{ChannelManager, ProcessUnit::*},
+        output::{
+            SlateTrigger, player, resume_node,
+            supervisor::{RestartPolicy, SWITCH_OVERLAP},
+        },
+        process::{MockProcess, MockSpawner},
+        utils::{
+            Media,
+            av_sync::{PesKind, SyncMonitor, pes_pts},
+            output_stats::{OutputStats, Progress, STALL_TIME, prometheus},
+            probe,
+            resume::{self, ResumeState},
+            ring_buffer::{ByteRate, MIN_CAPACITY, RingBuffer},
+        },
+    },
+    utils::{
+        compliance,
+        config::{OutputMode::Null, PlayoutConfig, ProcessMode::Folder},
+        control::PlayerCtl,
+        downstream::{self, Hls, HlsState},
+        redundancy::{Failover, Heartbeat, Role},
+        triggers::TriggerAction,
+    },
+    vec_strings,
+};
+
+async fn prepare_manager(spawner: Arc<MockSpawner>) -> (PlayoutConfig, ChannelManager) {
+    let pool = SqlitePoolOptions::new()
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    handles::db_migrate(&pool).await.unwrap();
+
+    let current_path = env::current_dir().unwrap();
+    let hls = current_path.join("assets/hls");
+    // as-run logs of the mock playout are not kept
+    let log = env::temp_dir().join("ffplayout_process_log");
+    let playlists = current_path.join("assets/playlists");
+    let storage = current_path.join("assets/storage/media_sorted");
+
+    sqlx::query(
+        r#"
+        UPDATE global SET public = $1, logs = $2, playlists = $3, storage = $4;
+        UPDATE channels SET public = $1, playlists = $3, storage = $4;
+        UPDATE configurations SET processing_width = 1024, processing_height = 576;
+        "#,
+    )
+    .bind(hls.to_string_lossy())
+    .bind(log.to_string_lossy())
+    .bind(playlists.to_string_lossy())
+    .bind(storage.to_string_lossy())
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let mut config = PlayoutConfig::new(&pool, 1, None).await.unwrap();
+    let channel = handles::select_channel(&pool, &1).await.unwrap();
+
+    config.general.skip_validation = true;
+    config.mail.recipient = "".into();
+    config.processing.mode = Folder;
+    config.storage.shuffle = false;
+    config.output.mode = Null;
+    config.ingest.enable = false;
+    config.text.add_text = false;
+
+    let mut manager = ChannelManager::new(pool, channel, config.clone()).await;
+    manager.spawner = spawner;
+    manager.is_alive.store(true, Ordering::SeqCst);
+    manager.list_init.store(true, Ordering::SeqCst);
+
+    (config, manager)
+}
+
+async fn run_for(manager: &ChannelManager, time: Duration) {
+    let stopper = manager.clone();
+
+    tokio::spawn(async move {
+        tokio::time::sleep(time).await;
+        stopper.channel.lock().await.active = false;
+        stopper.stop_all(false).await;
+    });
+
+    if let Err(e) = tokio::spawn(player(manager.clone())).await.unwrap() {
+        eprintln!("{e:?}");
+    }
+}
+
+fn input(args: &[String]) -> String {
+    args.windows(2)
+        .find(|w| w[0] == "-i")
+        .map(|w| w[1].clone())
+        .unwrap_or_default()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn mock_clip_switching() {
+    let spawner = Arc::new(MockSpawner::new());
+    spawner.push(Decoder, MockProcess::new(vec![0; 188 * 100]));
+
+    let (_, manager) = prepare_manager(spawner.clone()).await;
+
+    run_for(&manager, Duration::from_secs(2)).await;
+
+    let inputs = spawner
+        .calls(Decoder)
+        .iter()
+        .map(|args| input(args))
+        .collect::<Vec<_>>();
+
+    assert!(inputs.len() >= 3);
+    assert!(inputs[0].ends_with("Aqua_00-00-30.mp4"));
+    assert!(inputs[1].ends_with("Cornsilk_00-00-50.mp4"));
+    assert!(inputs[2].ends_with("Cyan_00-00-30.mp4"));
+
+    // one encoder for all clips
+    assert_eq!(spawner.calls(Encoder).len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn mock_decoder_error() {
+    let spawner = Arc::new(MockSpawner::new());
+    spawner.push(
+        Decoder,
+        MockProcess {
+            stderr: vec!["[error] Invalid data found when processing input".to_string()],
+            exit_code: 1,
+            ..Default::default()
+        },
+    );
+
+    let (mut config, manager) = prepare_manager(spawner.clone()).await;
+    config.general.quarantine = 1;
+    manager.update_config(config).await;
+
+    run_for(&manager, Duration::from_secs(2)).await;
+
+    let quarantine = manager.quarantine.lock().await.sources();
+
+    // the playout goes on with the next clips
+    assert!(spawner.calls(Decoder).len() >= 2);
+    assert!(quarantine.iter().any(|s| s.ends_with("Aqua_00-00-30.mp4")));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn mock_ingest_takeover() {
+    let spawner = Arc::new(MockSpawner::new());
+    spawner.push(Decoder, MockProcess::new(vec![0; 188 * 100]));
+    spawner.push(
+        Ingest,
+        MockProcess {
+            stderr: vec!["Input #0, flv, from 'rtmp://127.0.0.1:1936/live/stream':".to_string()],
+            stdout: vec![0; 188 * 100],
+            duration: Duration::from_millis(500),
+            exit_code: 0,
+        },
+    );
+    // waits for the next stream
+    spawner.push(
+        Ingest,
+        MockProcess {
+            duration: Duration::from_secs(10),
+            ..Default::default()
+        },
+    );
+
+    let (mut config, manager) = prepare_manager(spawner.clone()).await;
+    config.ingest.enable = true;
+    manager.update_config(config).await;
+
+    let live = Arc::new(AtomicBool::new(false));
+    let live_seen = live.clone();
+    let watcher = manager.clone();
+
+    tokio::spawn(async move {
+        while !live_seen.load(Ordering::SeqCst) {
+            live_seen.store(
+                watcher.ingest_is_alive.load(Ordering::SeqCst),
+                Ordering::SeqCst,
+            );
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    });
+
+    run_for(&manager, Duration::from_secs(3)).await;
+
+    assert!(live.load(Ordering::SeqCst));
+    assert_eq!(spawner.calls(Ingest).len(), 2);
+    assert!(!manager.ingest_is_alive.load(Ordering::SeqCst));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn mock_output_switch() {
+    let spawner = Arc::new(MockSpawner::new());
+    spawner.push(Decoder, MockProcess::new(vec![0; 188 * 100]));
+    spawner.push(
+        Encoder,
+        MockProcess {
+            duration: Duration::from_secs(10),
+            ..Default::default()
+        },
+    );
+
+    let (mut config, manager) = prepare_manager(spawner.clone()).await;
+    let switcher = manager.clone();
+
+    config.output.output_cmd = Some(vec_strings!["-f", "null", "-metadata", "cdn=new", "-"]);
+
+    let pids = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let old = switcher.encoder.lock().await.as_ref().and_then(Child::id);
+
+        switcher.update_config(config).await;
+        switcher.output_switch.notify_one();
+
+        tokio::time::sleep(SWITCH_OVERLAP + Duration::from_secs(1)).await;
+        let new = switcher.encoder.lock().await.as_ref().and_then(Child::id);
+
+        (old, new)
+    });
+
+    run_for(&manager, SWITCH_OVERLAP + Duration::from_secs(2)).await;
+
+    let (old, new) = pids.await.unwrap();
+    let calls = spawner.calls(Encoder);
+
+    // a second encoder with the new output, which took over without a restart
+    assert_eq!(calls.len(), 2);
+    assert!(calls[1].contains(&"cdn=new".to_string()));
+    assert!(old.is_some() && new.is_some());
+    assert_ne!(old, new);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn mock_decoder_resume() {
+    let (config, manager) = prepare_manager(Arc::new(MockSpawner::new())).await;
+    // with fixed length, without ffprobe the folder clips have no length to resume from
+    let node = Media {
+        source: config
+            .channel
+            .storage
+            .join("Aqua_00-00-30.mp4")
+            .to_string_lossy()
+            .to_string(),
+        duration: 30.0,
+        out: 30.0,
+        ..Media::default()
+    };
+
+    // the crashed clip goes on from the played position
+    let resume = resume_node(&manager, &config, &node, Duration::from_secs(10))
+        .await
+        .unwrap();
+    let cmd = resume.cmd.unwrap();
+
+    assert_eq!(resume.seek, 10.0);
+    assert_eq!(resume.source, node.source);
+    assert_eq!(input(&cmd), node.source);
+    assert!(cmd.windows(2).any(|w| w[0] == "-ss"));
+
+    // not worth to resume
+    assert!(
+        resume_node(&manager, &config, &node, Duration::from_secs(27))
+            .await
+            .is_none()
+    );
+
+    // looped clips start again
+    let looped = Media {
+        out: 60.0,
+        ..node.clone()
+    };
+    assert!(
+        resume_node(&manager, &config, &looped, Duration::from_secs(10))
+            .await
+            .is_none()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn mock_decoder_stall() {
+    let spawner = Arc::new(MockSpawner::new());
+    spawner.push(
+        Decoder,
+        MockProcess {
+            stdout: vec![0; 188 * 100],
+            duration: Duration::from_secs(1),
+            ..Default::default()
+        },
+    );
+    spawner.push(Decoder, MockProcess::new(vec![0; 188 * 100]));
+
+    let (mut config, manager) = prepare_manager(spawner.clone()).await;
+    config.general.decoder_resume = true;
+    config.general.stall_timeout = 0.5;
+    manager.update_config(config).await;
+
+    run_for(&manager, Duration::from_secs(3)).await;
+
+    let inputs = spawner
+        .calls(Decoder)
+        .iter()
+        .map(|args| input(args))
+        .collect::<Vec<_>>();
+
+    // a stalled clip is skipped, even with decoder resume
+    assert!(inputs.len() >= 2);
+    assert!(inputs[0].ends_with("Aqua_00-00-30.mp4"));
+    assert!(inputs[1].ends_with("Cornsilk_00-00-50.mp4"));
+}
+
+#[test]
+fn restart_policy() {
+    let mut policy = RestartPolicy::new(3, Duration::from_secs(60));
+    let start = Instant::now();
+
+    assert!(policy.allow(start));
+    assert!(policy.allow(start + Duration::from_secs(10)));
+    assert!(policy.allow(start + Duration::from_secs(20)));
+    assert!(!policy.allow(start + Duration::from_secs(30)));
+
+    // the first restart is out of the window
+    assert!(policy.allow(start + Duration::from_secs(61)));
+    assert!(!policy.allow(start + Duration::from_secs(62)));
+}
+
+#[test]
+fn byte_rate() {
+    let start = Instant::now();
+    let mut rate = ByteRate::new(start);
+
+    rate.add(500_000, start + Duration::from_millis(500));
+    assert_eq!(rate.rate(), 0.0);
+
+    rate.add(500_000, start + Duration::from_secs(1));
+    assert_eq!(rate.rate(), 1_000_000.0);
+
+    // the next window is averaged with the last rate
+    rate.add(2_000_000, start + Duration::from_secs(2));
+    assert_eq!(rate.rate(), 1_500_000.0);
+}
+
+#[tokio::test]
+async fn ring_buffer() {
+    let buffer = RingBuffer::new(Duration::from_millis(500));
+
+    buffer.write(&[1, 2, 3, 4]).await.unwrap();
+    buffer.write(&[5]).await.unwrap();
+    assert_eq!(buffer.len(), 5);
+    assert_eq!(buffer.latency(), Duration::ZERO);
+
+    assert_eq!(buffer.read(3).await, Some(vec![1, 2, 3]));
+    assert_eq!(buffer.read(10).await, Some(vec![4, 5]));
+    assert!(buffer.is_empty());
+
+    // the buffer is full at the minimum capacity, until there is a read rate
+    let chunk = vec![0u8; MIN_CAPACITY];
+    buffer.write(&chunk).await.unwrap();
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), buffer.write(&[0]))
+            .await
+            .is_err()
+    );
+
+    buffer.close();
+    assert!(buffer.write(&[0]).await.is_err());
+    assert_eq!(
+        buffer.read(MIN_CAPACITY).await.map(|d| d.len()),
+        Some(MIN_CAPACITY)
+    );
+    assert_eq!(buffer.read(1).await, None);
+}
+
+/// TS packet with the start of a PES packet.
+fn ts_packet(stream_id: u8, pts: i64) -> Vec<u8> {
+    let mut packet = vec![
+        0x47, 0x41, 0x00, 0x10, 0, 0, 1, stream_id, 0, 0, 0x80, 0x80, 5,
+    ];
+
+    packet.extend_from_slice(&[
+        0x21 | ((pts >> 29) & 0x0E) as u8,
+        (pts >> 22) as u8,
+        0x01 | ((pts >> 14) & 0xFE) as u8,
+        (pts >> 7) as u8,
+        0x01 | ((pts << 1) & 0xFE) as u8,
+    ]);
+    packet.resize(188, 0xFF);
+
+    packet
+}
+
+#[test]
+fn av_sync_monitor() {
+    assert_eq!(
+        pes_pts(&ts_packet(0xE0, 8_589_934_000)),
+        Some((PesKind::Video, 8_589_934_000))
+    );
+    assert_eq!(
+        pes_pts(&ts_packet(0xC0, 3600)),
+        Some((PesKind::Audio, 3600))
+    );
+    assert_eq!(pes_pts(&ts_packet(0xBE, 3600)), None);
+
+    let mut monitor = SyncMonitor::new();
+    let mut drift = None;
+
+    // audio 10ms behind video, split at odd places
+    for i in 0..60 {
+        let mut data = ts_packet(0xC0, i * 3600);
+        data.extend(ts_packet(0xE0, i * 3600 + 900));
+
+        for part in data.chunks(100) {
+            if let Some(d) = monitor.feed(part) {
+                drift = Some(d);
+            }
+        }
+    }
+
+    assert_eq!(drift, Some(0.0));
+
+    // the audio lags more and more
+    for i in 60..200 {
+        let mut data = ts_packet(0xC0, i * 3600 - (i - 60) * 180);
+        data.extend(ts_packet(0xE0, i * 3600 + 900));
+
+        if let Some(d) = monitor.feed(&data) {
+            drift = Some(d);
+        }
+    }
+
+    assert!(drift.unwrap() > 0.2);
+}
+
+#[test]
+fn slate_trigger() {
+    let mut trigger = SlateTrigger::new(3);
+
+    assert_eq!(trigger.count(true), None);
+    assert_eq!(trigger.count(false), None);
+    assert_eq!(trigger.count(true), None);
+    assert_eq!(trigger.count(true), None);
+    assert_eq!(trigger.count(true), Some(true));
+    assert!(trigger.active());
+    assert_eq!(trigger.count(true), None);
+    // a clip plays again
+    assert_eq!(trigger.count(false), Some(false));
+    assert!(!trigger.active());
+
+    let mut off = SlateTrigger::new(0);
+
+    assert!((0..10).all(|_| off.count(true).is_none()));
+    assert!(!off.active());
+
+    assert_eq!("slate".parse(), Ok(TriggerAction::Slate));
+    assert_eq!("resume".parse(), Ok(TriggerAction::Resume));
+    assert_eq!("Slate".parse(), Ok(PlayerCtl::Slate));
+    assert_eq!(PlayerCtl::Resume.to_string(), "resume");
+    assert_eq!("hold".parse(), Ok(TriggerAction::Hold));
+    assert_eq!("release".parse(), Ok(TriggerAction::Release));
+    assert_eq!("Hold".parse(), Ok(PlayerCtl::Hold));
+    assert_eq!(PlayerCtl::Release.to_string(), "release");
+}
+
+#[test]
+fn redundancy_failover() {
+    let timeout = Duration::from_secs(3);
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+    let beat = |on_air| Heartbeat {
+        on_air,
+        release: false,
+    };
+
+    assert_eq!("Passive".parse::<Role>(), Ok(Role::Passive));
+    assert_eq!("".parse::<Role>(), Ok(Role::Off));
+    assert!("backup".parse::<Role>().is_err());
+
+    let mut active = Failover::new(Role::Active, timeout, start);
+    let mut passive = Failover::new(Role::Passive, timeout, start);
+
+    // both start, the active one goes on air, when it hears the peer
+    passive.heartbeat(&beat(false), at(100));
+    active.heartbeat(&beat(false), at(100));
+    assert_eq!(active.check(at(200)), Some(true));
+    assert_eq!(passive.check(at(200)), None);
+
+    passive.heartbeat(&beat(true), at(600));
+    assert_eq!(passive.check(at(3000)), None);
+
+    // active dies, passive takes over after the timeout
+    assert_eq!(passive.check(at(3500)), None);
+    assert_eq!(passive.check(at(3700)), Some(true));
+
+    // the old active comes back and stays in standby, the peer is on air
+    let mut restarted = Failover::new(Role::Active, timeout, at(10_000));
+    restarted.heartbeat(&beat(true), at(10_200));
+    assert_eq!(restarted.check(at(10_300)), None);
+    restarted.heartbeat(&beat(true), at(13_000));
+    assert_eq!(restarted.check(at(14_000)), None);
+    assert!(!restarted.on_air());
+
+    // a release hands the output over at once
+    restarted.heartbeat(
+        &Heartbeat {
+            on_air: false,
+            release: true,
+        },
+        at(14_500),
+    );
+    assert_eq!(restarted.check(at(14_500)), Some(true));
+
+    // both on air after a network split: the passive one goes to standby
+    passive.heartbeat(&beat(true), at(15_000));
+    assert_eq!(passive.check(at(15_000)), Some(false));
+}
+
+#[test]
+fn output_bitrate_and_stalls() {
+    let start = Instant::now();
+    let mut stats = OutputStats::new(start);
+    let mut progress = Progress::default();
+
+    for sec in 0..60 {
+        let now = start + Duration::from_secs(sec);
+
+        stats.input(125_000, now);
+        assert!(progress.update(
+            &format!("total_size={}", (sec + 1) * 100_000),
+            &mut stats,
+            now
+        ));
+        assert!(progress.update("progress=continue", &mut stats, now));
+    }
+
+    assert!(!progress.update("[error] Connection reset by peer", &mut stats, start));
+
+    let snapshot = stats.snapshot(start + Duration::from_secs(60), true);
+
+    assert_eq!(snapshot.input.bytes, 7_500_000);
+    assert_eq!(snapshot.input.bitrate, 1_000_000);
+    assert_eq!(snapshot.output.as_ref().unwrap().bitrate, 800_000);
+    assert!(snapshot.connected);
+
+    // a pause of both, then only the input goes on
+    let hang = start + Duration::from_secs(60) + STALL_TIME * 2;
+    stats.input(125_000, hang);
+    let snapshot = stats.snapshot(hang, true);
+
+    assert_eq!(snapshot.input.stalls, 1);
+    assert_eq!(snapshot.output.as_ref().unwrap().stalls, 1);
+    assert!(!snapshot.connected);
+
+    // a new encoder counts from zero again
+    let mut progress = Progress::default();
+    progress.update("total_size=50000", &mut stats, hang);
+    let snapshot = stats.snapshot(hang, true);
+
+    assert_eq!(snapshot.output.as_ref().unwrap().bytes, 6_050_000);
+    assert!(snapshot.connected);
+
+    let text = prometheus(&[(1, snapshot, 500)]);
+
+    assert!(text.contains("# TYPE ffplayout_output_stalls_total counter\n"));
+    assert!(text.contains("ffplayout_output_bytes_total{channel=\"1\"} 6050000\n"));
+    assert!(text.contains("ffplayout_buffer_milliseconds{channel=\"1\"} 500\n"));
+
+    // no stream output, no output values
+    let text = prometheus(&[(2, OutputStats::new(start).snapshot(start, true), 0)]);
+
+    assert!(text.contains("ffplayout_input_bytes_total{channel=\"2\"} 0\n"));
+    assert!(!text.contains("ffplayout_output_bytes_total{"));
+}
+
+#[test]
+fn downstream_hls_check() {
+    let master = "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1500000\nstream/720p.m3u8\n";
+
+    assert_eq!(
+        downstream::parse_hls(master),
+        Some(Hls::Master("stream/720p.m3u8".to_string()))
+    );
+    assert_eq!(downstream::parse_hls("<html></html>"), None);
+
+    let media = |seq: u64| {
+        format!(
+            "#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXT-X-MEDIA-SEQUENCE:{seq}\n#EXTINF:6.0,\nstream-{seq}.ts\n#EXTINF:6.0,\nstream-{}.ts\n",
+            seq + 1
+        )
+    };
+    let Some(Hls::Media(first)) = downstream::parse_hls(&media(10)) else {
+        panic!("no media playlist");
+    };
+
+    assert_eq!(first.sequence, Some(10));
+    assert_eq!(first.last_segment.as_deref(), Some("stream-11.ts"));
+    assert_eq!(downstream::hls_problem(None, &first), None);
+
+    let Some(Hls::Media(next)) = downstream::parse_hls(&media(11)) else {
+        panic!("no media playlist");
+    };
+
+    assert_eq!(downstream::hls_problem(Some(&first), &next), None);
+    assert!(downstream::hls_problem(Some(&next), &next).is_some());
+    assert!(downstream::hls_problem(None, &HlsState::default()).is_some());
+
+    let mut config = PlayoutConfig::default();
+    let mut probe = probe::MediaProbe {
+        audio: vec![probe::AudioStream::default()],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        downstream::probe_problem(&config, &probe).as_deref(),
+        Some("stream has no video")
+    );
+
+    config.processing.audio_only = true;
+    assert_eq!(downstream::probe_problem(&config, &probe), None);
+
+    probe.audio.clear();
+    assert!(downstream::probe_problem(&config, &probe).is_some());
+}
+
+#[tokio::test]
+#[serial]
+async fn resume_state() {
+    let (mut config, manager) = prepare_manager(Arc::new(MockSpawner::new())).await;
+
+    assert_eq!(ResumeState::from_json("no json"), ResumeState::default());
+    assert!(resume::load(&manager).await.is_empty());
+
+    resume::save(&manager, |s| {
+        s.text = Some("text='News'".to_string());
+        s.volume = Some(0.5);
+    })
+    .await;
+    resume::save(&manager, |s| s.filler_index = 3).await;
+
+    let state = resume::load(&manager).await;
+    assert_eq!(state.text, Some("text='News'".to_string()));
+    assert_eq!(state.filler_index, 3);
+
+    state.apply(&mut config);
+    assert_eq!(config.processing.volume, 0.5);
+
+    resume::clear(&manager).await;
+    assert!(resume::load(&manager).await.is_empty());
+}
+
+#[tokio::test]
+async fn compliance_pruning() {
+    let dir = std::env::temp_dir().join("ffplayout_compliance");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for file in [
+        "2024-06-01_10-00-00.ts",
+        "2024-06-01_10-15-00.ts",
+        "notes.txt",
+    ] {
+        std::fs::write(dir.join(file), "").unwrap();
+    }
+
+    let now = SystemTime::now();
+
+    assert!(compliance::prune(&dir, 1, now).await.is_empty());
+
+    let mut removed = compliance::prune(&dir, 1, now + Duration::from_secs(2 * 86400)).await;
+    removed.sort();
+
+    assert_eq!(
+        removed,
+        vec![
+            dir.join("2024-06-01_10-00-00.ts"),
+            dir.join("2024-06-01_10-15-00.ts")
+        ]
+    );
+    assert!(dir.join("notes.txt").is_file());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,51 @@
+use std::{
+    env, fs,
+    os::unix::fs::PermissionsExt,
+    sync::{Arc, atomic::AtomicBool},
+    time::{Duration, Instant},
+};
+
+use sqlx::sqlite::SqlitePoolOptions;
+
+use ffplayout::{
+    db::handles,
+    player::utils::{Media, json_validate::probe_items, set_binaries},
+    utils::config::PlayoutConfig,
+};
+
+#[tokio::test]
+async fn probe_concurrent_with_timeout() {
+    // an ffprobe, which hangs on every file
+    let ffprobe = env::temp_dir().join("ffplayout_hanging_ffprobe");
+    fs::write(&ffprobe, "#!/bin/sh\nsleep 5\n").unwrap();
+    fs::set_permissions(&ffprobe, fs::Permissions::from_mode(0o755)).unwrap();
+    set_binaries(None, Some(ffprobe));
+
+    let pool = SqlitePoolOptions::new()
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    handles::db_migrate(&pool).await.unwrap();
+
+    let mut config = PlayoutConfig::new(&pool, 1, None).await.unwrap();
+    config.general.probe_workers = 4;
+    config.general.probe_timeout = 0.5;
+
+    let mut items = vec![];
+
+    for i in 0..8 {
+        items.push(Media::new(i, &format!("clip_{i}.mp4"), false).await);
+    }
+
+    let timer = Instant::now();
+    let probed = probe_items(&config, items, Arc::new(AtomicBool::new(true))).await;
+
+    // two rounds of four probes, serial it would take four seconds
+    assert!(timer.elapsed() < Duration::from_secs(2));
+    assert_eq!(probed.len(), 8);
+
+    for (i, (item, result)) in probed.iter().enumerate() {
+        assert_eq!(item.source, format!("clip_{i}.mp4"));
+        assert!(result.as_ref().unwrap_err().contains("timed out"));
+    }
+}
@@ -1,38 +1,33 @@
-use sqlx::sqlite::SqlitePoolOptions;
+mod common;
+
+use std::time::{Duration, Instant, SystemTime};
 
 use chrono::prelude::*;
+use chrono_tz::{Europe::Berlin, Tz};
 use serial_test::serial;
 
-use ffplayout::db::handles;
-use ffplayout::player::{controller::ChannelManager, utils::*};
+use common::prepare_config;
+use ffplayout::player::utils::{read_ahead::next_date, *};
 use ffplayout::utils::{
+    clock::{ClockWatch, STEP_THRESHOLD, parse_ntp},
     config::{PlayoutConfig, ProcessMode::Playlist},
+    error_report::{self, Endpoint, ErrorEvent, MAX_EVENTS, RateLimit},
+    telemetry,
     time_machine::{set_mock_time, time_now},
+    update::{self, is_newer, parse_version},
 };
 
-async fn prepare_config() -> (PlayoutConfig, ChannelManager) {
-    let pool = SqlitePoolOptions::new()
-        .connect("sqlite::memory:")
-        .await
-        .unwrap();
-    handles::db_migrate(&pool).await.unwrap();
-
-    sqlx::query(
-        r#"
-        UPDATE global SET public = "assets/hls", logs = "assets/log", playlists = "assets/playlists", storage = "assets/storage";
-        UPDATE channels SET public = "assets/hls", playlists = "assets/playlists", storage = "assets/storage";
-        UPDATE configurations SET processing_width = 1024, processing_height = 576;
-        "#,
-    )
-    .execute(&pool)
-    .await
-    .unwrap();
-
-    let config = PlayoutConfig::new(&pool, 1, None).await.unwrap();
-    let channel = handles::select_channel(&pool, &1).await.unwrap();
-    let manager = ChannelManager::new(pool, channel, config.clone()).await;
-
-    (config, manager)
+/// Local time in Berlin, the clock changes there in the tests.
+fn berlin_time(date: &str, hour: u32, min: u32) -> DateTime<Tz> {
+    Berlin
+        .from_local_datetime(
+            &NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .unwrap()
+                .and_hms_opt(hour, min, 0)
+                .unwrap(),
+        )
+        .latest()
+        .unwrap()
 }
 
 #[test]
@@ -89,3 +84,193 @@ async fn test_delta() {
 
     assert!(delta < 2.0);
 }
+
+#[test]
+fn error_report_endpoint() {
+    assert_eq!(
+        Endpoint::parse("https://abc123@o42.ingest.sentry.io/4501"),
+        Ok(Endpoint::Sentry {
+            url: "https://o42.ingest.sentry.io/api/4501/envelope/".to_string(),
+            key: "abc123".to_string(),
+        })
+    );
+    assert_eq!(
+        Endpoint::parse("http://key@sentry.local:9000/errors/7"),
+        Ok(Endpoint::Sentry {
+            url: "http://sentry.local:9000/errors/api/7/envelope/".to_string(),
+            key: "key".to_string(),
+        })
+    );
+    assert_eq!(
+        Endpoint::parse("https://collector.example.org/errors"),
+        Ok(Endpoint::Http(
+            "https://collector.example.org/errors".to_string()
+        ))
+    );
+    assert!(Endpoint::parse("https://key@sentry.io/project").is_err());
+    assert!(Endpoint::parse("ftp://collector.example.org").is_err());
+    assert!(Endpoint::parse("collector").is_err());
+
+    let event = ErrorEvent::new(
+        "error",
+        "Decoder <span class=\"log-addr\">failed</span>",
+        Some(2),
+    );
+    let envelope = event.envelope();
+    let lines = envelope.lines().collect::<Vec<_>>();
+    let item: serde_json::Value = serde_json::from_str(lines[2]).unwrap();
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[1], r#"{"type":"event"}"#);
+    assert_eq!(item["event_id"], event.event_id.as_str());
+    assert_eq!(item["message"]["formatted"], "Decoder failed");
+    assert_eq!(item["tags"]["channel"], "2");
+
+    assert!(error_report::sampled(0.5, 0.2));
+    assert!(!error_report::sampled(0.5, 0.7));
+    assert!(!error_report::sampled(0.0, 0.0));
+
+    let start = Instant::now();
+    let mut limit = RateLimit::new(start);
+
+    assert!((0..MAX_EVENTS).all(|_| limit.allow(start)));
+    assert!(!limit.allow(start + Duration::from_secs(30)));
+    assert!(limit.allow(start + Duration::from_secs(61)));
+}
+
+#[test]
+fn update_version_check() {
+    assert_eq!(parse_version("v0.25.7"), Some((0, 25, 7)));
+    assert_eq!(parse_version("0.26.0-beta1"), Some((0, 26, 0)));
+    assert_eq!(parse_version("1.2"), Some((1, 2, 0)));
+    assert_eq!(parse_version("nightly"), None);
+
+    assert!(is_newer("v0.25.10", "0.25.7"));
+    assert!(is_newer("1.0.0", "0.25.7"));
+    assert!(!is_newer("v0.25.7", "0.25.7"));
+    assert!(!is_newer("0.24.9", "0.25.7"));
+    assert!(!is_newer("latest", "0.25.7"));
+
+    assert_eq!(
+        update::api_url(),
+        "https://api.github.com/repos/ffplayout/ffplayout/releases/latest"
+    );
+}
+
+#[test]
+fn telemetry_without_endpoint() {
+    let _telemetry = telemetry::init(&None).unwrap();
+    let _empty = telemetry::init(&Some(String::new())).unwrap();
+    let node = Media {
+        source: "clip.mp4".to_string(),
+        out: 10.0,
+        duration: 10.0,
+        ..Media::default()
+    };
+
+    // spans are no-ops without exporter
+    let mut clip = telemetry::span("clip", 1);
+    telemetry::media(&mut clip, &node);
+
+    let mut decoder = telemetry::child("decoder", 1, &clip);
+    telemetry::event(&mut decoder, "first data");
+    telemetry::attribute(&mut decoder, "process.exit_code", 1);
+    telemetry::error(&mut decoder, "failed");
+}
+
+#[test]
+fn clock_discipline() {
+    assert_eq!(parse_ntp("NTPSynchronized=yes\n"), Some(true));
+    assert_eq!(parse_ntp("no"), Some(false));
+    assert_eq!(parse_ntp(""), None);
+
+    let wall = SystemTime::now();
+    let mono = Instant::now();
+    let mut watch = ClockWatch::new(wall, mono);
+    let step = watch.step(wall + Duration::from_secs(5), mono + Duration::from_secs(5));
+
+    assert!(step.abs() < 0.001);
+
+    // clock was set back by two seconds
+    let step = watch.step(
+        wall + Duration::from_secs(8),
+        mono + Duration::from_secs(10),
+    );
+
+    assert!((step + 2.0).abs() < 0.001);
+    assert!(step.abs() > STEP_THRESHOLD);
+
+    let step = watch.step(wall, mono + Duration::from_secs(11));
+
+    assert!((step + 9.0).abs() < 0.001);
+}
+
+#[test]
+fn dst_playlist_days() {
+    let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+
+    assert_eq!(day_length(date("2024-03-30"), 0.0, Berlin), 86400.0);
+    assert_eq!(day_length(date("2024-03-31"), 0.0, Berlin), 82800.0);
+    assert_eq!(day_length(date("2024-10-27"), 0.0, Berlin), 90000.0);
+    // With a start in the morning, the change falls into the day before.
+    assert_eq!(day_length(date("2024-10-26"), 21600.0, Berlin), 90000.0);
+    assert_eq!(day_length(date("2024-10-27"), 21600.0, Berlin), 86400.0);
+
+    // After the clock goes back the seconds run on, instead of jumping back one hour.
+    assert_eq!(
+        playlist_seconds_at(berlin_time("2024-10-27", 23, 0), 0.0),
+        86400.0
+    );
+    assert_eq!(
+        playlist_seconds_at(berlin_time("2024-03-31", 12, 0), 0.0),
+        39600.0
+    );
+    // Before the start, the day before is running.
+    assert_eq!(
+        playlist_seconds_at(berlin_time("2024-03-31", 3, 0), 21600.0),
+        93600.0
+    );
+    assert_eq!(
+        playlist_seconds_at(berlin_time("2024-03-30", 8, 0), 21600.0),
+        28800.0
+    );
+
+    let mut config = PlayoutConfig::default();
+    config.channel.timezone = Some(Berlin);
+    config.playlist.start_sec = Some(0.0);
+    config.playlist.length_sec = Some(86400.0);
+
+    assert_eq!(target_length(&config, "2024-03-31"), 82800.0);
+    assert_eq!(target_length(&config, "2024-10-27"), 90000.0);
+
+    // Special events keep their length.
+    config.playlist.length_sec = Some(7200.0);
+    assert_eq!(target_length(&config, "2024-10-27"), 7200.0);
+}
+
+#[test]
+fn read_ahead_date() {
+    let mut config = PlayoutConfig::default();
+    config.channel.timezone = Some(Berlin);
+    config.playlist.start_sec = Some(21600.0);
+    config.playlist.length_sec = Some(86400.0);
+    config.playlist.read_ahead = 240;
+
+    assert_eq!(next_date(&config, berlin_time("2024-02-01", 23, 0)), None);
+    // The day of 2024-02-01 runs until 06:00.
+    assert_eq!(
+        next_date(&config, berlin_time("2024-02-02", 3, 0)),
+        Some("2024-02-02".to_string())
+    );
+    assert_eq!(next_date(&config, berlin_time("2024-02-02", 6, 30)), None);
+
+    config.playlist.start_sec = Some(0.0);
+    assert_eq!(next_date(&config, berlin_time("2024-02-01", 12, 0)), None);
+    assert_eq!(
+        next_date(&config, berlin_time("2024-02-01", 21, 0)),
+        Some("2024-02-02".to_string())
+    );
+
+    config.playlist.read_ahead = 0;
+    assert_eq!(next_date(&config, berlin_time("2024-02-01", 21, 0)), None);
+}